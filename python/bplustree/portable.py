@@ -0,0 +1,220 @@
+"""
+Serialized format shared with the Rust implementation.
+
+The Rust crate and this package read and write the same bytes, so a dataset
+can move between the two without a conversion script. Keys and values are
+limited to what both sides can represent: ints that fit in 64 signed bits,
+str and bytes.
+
+All integers are little-endian::
+
+    magic     4 bytes   b"BPT+"
+    version   1 byte    PORTABLE_VERSION
+    capacity  4 bytes   node capacity of the writer (unsigned)
+    count     8 bytes   number of entries (unsigned)
+    entries             count pairs of an encoded key and value
+
+Each key or value is a tag byte followed by its payload: 0x01 and a signed
+64-bit int, 0x02 and an unsigned 64-bit length and UTF-8 bytes for a str,
+0x03 and an unsigned 64-bit length and the raw bytes for bytes. Entries are
+in strictly ascending key order and every key has the same tag. Readers
+reject anything else, including bytes after the last entry, and a capacity
+above PORTABLE_MAX_CAPACITY.
+
+Golden files in ``testdata/portable`` at the repository root are checked by
+the test suites of both implementations.
+"""
+
+import struct
+from typing import Any, BinaryIO, Tuple
+
+from .bplus_tree import MIN_CAPACITY
+
+__all__ = [
+    "PORTABLE_MAGIC",
+    "PORTABLE_MAX_CAPACITY",
+    "PORTABLE_VERSION",
+    "PortableFormatError",
+    "dump",
+    "dumps",
+    "load",
+    "loads",
+]
+
+PORTABLE_MAGIC = b"BPT+"
+PORTABLE_VERSION = 1
+# Largest capacity accepted from a file, so that a short file cannot ask
+# for huge nodes
+PORTABLE_MAX_CAPACITY = 4096
+
+_TAG_INT = 0x01
+_TAG_STR = 0x02
+_TAG_BYTES = 0x03
+
+_HEADER = struct.Struct("<4sBIQ")
+_INT = struct.Struct("<q")
+_LENGTH = struct.Struct("<Q")
+
+
+class PortableFormatError(ValueError):
+    """Raised when bytes are not a well-formed portable file."""
+
+    pass
+
+
+def _encode_item(item: Any, out: bytearray) -> None:
+    """Append the tag and payload of one key or value to out."""
+    # bool is an int subclass, but would not come back as a bool
+    if type(item) is int:
+        try:
+            payload = _INT.pack(item)
+        except struct.error:
+            raise OverflowError(f"{item} does not fit in 64 signed bits") from None
+        out.append(_TAG_INT)
+        out += payload
+    elif isinstance(item, str):
+        encoded = item.encode("utf-8")
+        out.append(_TAG_STR)
+        out += _LENGTH.pack(len(encoded))
+        out += encoded
+    elif isinstance(item, (bytes, bytearray, memoryview)):
+        raw = bytes(item)
+        out.append(_TAG_BYTES)
+        out += _LENGTH.pack(len(raw))
+        out += raw
+    else:
+        raise TypeError(
+            f"portable format holds int, str and bytes, not {type(item).__name__}"
+        )
+
+
+def _decode_item(data: bytes, offset: int) -> Tuple[Any, int]:
+    """Decode one key or value at offset, returning it and the next offset."""
+    if offset >= len(data):
+        raise PortableFormatError("truncated entry")
+    tag = data[offset]
+    offset += 1
+    if tag == _TAG_INT:
+        if offset + _INT.size > len(data):
+            raise PortableFormatError("truncated int")
+        return _INT.unpack_from(data, offset)[0], offset + _INT.size
+    if tag in (_TAG_STR, _TAG_BYTES):
+        if offset + _LENGTH.size > len(data):
+            raise PortableFormatError("truncated length")
+        (length,) = _LENGTH.unpack_from(data, offset)
+        start = offset + _LENGTH.size
+        end = start + length
+        if end > len(data):
+            raise PortableFormatError("truncated payload")
+        raw = data[start:end]
+        if tag == _TAG_BYTES:
+            return raw, end
+        try:
+            return raw.decode("utf-8"), end
+        except UnicodeDecodeError:
+            raise PortableFormatError("str payload is not UTF-8") from None
+    raise PortableFormatError(f"unknown tag {tag:#04x}")
+
+
+def dumps(tree) -> bytes:
+    """Serialize a tree to bytes in the portable format.
+
+    Args:
+        tree: A BPlusTreeMap (either implementation) with int, str or bytes
+            keys of a single type and int, str or bytes values.
+
+    Returns:
+        The serialized tree.
+
+    Raises:
+        TypeError: If a key or value has another type, or keys are mixed.
+        OverflowError: If an int does not fit in 64 signed bits.
+    """
+    items = list(tree.items())
+    out = bytearray(
+        _HEADER.pack(PORTABLE_MAGIC, PORTABLE_VERSION, tree.capacity, len(items))
+    )
+    key_tag = None
+    for key, value in items:
+        start = len(out)
+        _encode_item(key, out)
+        if key_tag is None:
+            key_tag = out[start]
+        elif out[start] != key_tag:
+            raise TypeError("portable keys must all have the same type")
+        _encode_item(value, out)
+    return bytes(out)
+
+
+def dump(tree, fp: BinaryIO) -> None:
+    """Serialize a tree in the portable format to a binary file object.
+
+    Args:
+        tree: The tree to write; see dumps.
+        fp: A file object opened for binary writing.
+    """
+    fp.write(dumps(tree))
+
+
+def loads(data: bytes):
+    """Deserialize a tree written in the portable format by either implementation.
+
+    Args:
+        data: The serialized tree.
+
+    Returns:
+        A BPlusTreeMap with the node capacity recorded in the data.
+
+    Raises:
+        PortableFormatError: If data is not a well-formed portable file.
+    """
+    from . import BPlusTreeMap
+
+    data = bytes(data)
+    if len(data) < _HEADER.size:
+        raise PortableFormatError("truncated header")
+    magic, version, capacity, count = _HEADER.unpack_from(data, 0)
+    if magic != PORTABLE_MAGIC:
+        raise PortableFormatError("bad magic")
+    if version != PORTABLE_VERSION:
+        raise PortableFormatError(f"unsupported version {version}")
+    if capacity < MIN_CAPACITY:
+        raise PortableFormatError(f"capacity {capacity} is too small")
+    if capacity > PORTABLE_MAX_CAPACITY:
+        raise PortableFormatError(
+            f"capacity {capacity} is above the maximum of {PORTABLE_MAX_CAPACITY}"
+        )
+    tree = BPlusTreeMap(capacity=capacity)
+
+    offset = _HEADER.size
+    key_tag = None
+    previous = None
+    for index in range(count):
+        if offset < len(data):
+            if key_tag is None:
+                key_tag = data[offset]
+            elif data[offset] != key_tag:
+                raise PortableFormatError("keys do not all have the same type")
+        key, offset = _decode_item(data, offset)
+        value, offset = _decode_item(data, offset)
+        if index > 0 and not previous < key:
+            raise PortableFormatError(
+                f"entry {index} is not in ascending key order"
+            )
+        tree[key] = value
+        previous = key
+    if offset != len(data):
+        raise PortableFormatError("bytes after the last entry")
+    return tree
+
+
+def load(fp: BinaryIO):
+    """Deserialize a tree in the portable format from a binary file object.
+
+    Args:
+        fp: A file object opened for binary reading.
+
+    Returns:
+        A BPlusTreeMap; see loads.
+    """
+    return loads(fp.read())
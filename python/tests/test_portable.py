@@ -0,0 +1,120 @@
+"""
+Tests for the portable format shared with the Rust implementation.
+
+The golden files in testdata/portable at the repository root are also read
+and written by the Rust test suite, so both implementations must produce
+exactly these bytes.
+"""
+
+import io
+import os
+import sys
+
+import pytest
+
+try:
+    import bplustree
+except ImportError:
+    sys.path.insert(0, os.path.dirname(os.path.dirname(os.path.abspath(__file__))))
+    import bplustree
+
+from bplustree import portable
+from bplustree.portable import PortableFormatError
+
+GOLDEN_DIR = os.path.join(
+    os.path.dirname(os.path.abspath(__file__)), "..", "..", "testdata", "portable"
+)
+
+
+def _int_str():
+    keys = [-(2**63), -1, 0, 1, 2**63 - 1] + list(range(10, 40))
+    values = {0: "", 1: "naïve ✓"}
+    return [(k, values.get(k, f"value {k}")) for k in sorted(keys)]
+
+
+def _str_bytes():
+    keys = ["", "a", "apple", "b", "ζ"]
+    return [(k, k.encode("utf-8") + b"\x00\xff") for k in keys]
+
+
+def _bytes_mixed():
+    return [(b"", 7), (b"\x00", "seven"), (b"\x00\x01", b"\x07"), (b"\xff", -7)]
+
+
+# (file name, capacity, entries)
+GOLDEN = [
+    ("empty.bpt", 16, []),
+    ("int_str.bpt", 4, _int_str()),
+    ("str_bytes.bpt", 8, _str_bytes()),
+    ("bytes_mixed.bpt", 5, _bytes_mixed()),
+]
+
+
+def _tree(capacity, entries):
+    tree = bplustree.BPlusTreeMap(capacity=capacity)
+    for key, value in entries:
+        tree[key] = value
+    return tree
+
+
+def _golden(name):
+    with open(os.path.join(GOLDEN_DIR, name), "rb") as f:
+        return f.read()
+
+
+@pytest.mark.parametrize("name,capacity,entries", GOLDEN)
+def test_golden_file_loads(name, capacity, entries):
+    tree = portable.loads(_golden(name))
+    assert list(tree.items()) == entries
+
+
+@pytest.mark.parametrize("name,capacity,entries", GOLDEN)
+def test_dumps_matches_golden_file(name, capacity, entries):
+    tree = _tree(capacity, entries)
+    if tree.capacity != capacity:
+        pytest.skip("the C extension does not report its capacity")
+    assert portable.dumps(tree) == _golden(name)
+
+
+def test_dump_and_load_through_a_file_object():
+    tree = _tree(4, _int_str())
+    buffer = io.BytesIO()
+    portable.dump(tree, buffer)
+    buffer.seek(0)
+    assert list(portable.load(buffer).items()) == _int_str()
+
+
+def test_dumps_rejects_unportable_entries():
+    with pytest.raises(TypeError):
+        portable.dumps(_tree(4, [(1, 1.5)]))
+    with pytest.raises(TypeError):
+        portable.dumps(_tree(4, [(1, True)]))
+    with pytest.raises(OverflowError):
+        portable.dumps(_tree(4, [(2**63, "too big")]))
+
+
+def test_loads_rejects_malformed_input():
+    data = _golden("str_bytes.bpt")
+    for length in range(len(data)):
+        with pytest.raises(PortableFormatError):
+            portable.loads(data[:length])
+    with pytest.raises(PortableFormatError):
+        portable.loads(data + b"\x00")
+    with pytest.raises(PortableFormatError):
+        portable.loads(b"XXXX" + data[4:])
+    with pytest.raises(PortableFormatError):
+        portable.loads(data[:4] + bytes([portable.PORTABLE_VERSION + 1]) + data[5:])
+    # Swap the first two entries so the keys are out of order
+    first = portable.dumps(_tree(4, [("a", b"")]))[17:]
+    second = portable.dumps(_tree(4, [("b", b"")]))[17:]
+    header = portable.dumps(_tree(4, [("a", b""), ("b", b"")]))[:17]
+    with pytest.raises(PortableFormatError):
+        portable.loads(header + second + first)
+
+
+def test_loads_rejects_oversized_capacity():
+    empty = portable.dumps(_tree(4, []))
+    for capacity in (portable.PORTABLE_MAX_CAPACITY + 1, 2**32 - 1):
+        data = empty[:5] + capacity.to_bytes(4, "little") + empty[9:]
+        with pytest.raises(PortableFormatError):
+            portable.loads(data)
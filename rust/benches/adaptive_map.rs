@@ -0,0 +1,99 @@
+use bplustree::{AdaptiveMap, BPlusTreeMap};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const MAPS: u64 = 1_000;
+const CAPACITY: usize = 16;
+
+/// Keys in a fixed pseudo-random order.
+fn shuffled_keys(n: u64) -> Vec<u64> {
+    let mut state: u64 = 42;
+    (0..n)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            state >> 11
+        })
+        .collect()
+}
+
+/// Many tiny maps, the case the sorted vector is for, then single maps of
+/// growing size with the vector kept past its default threshold, to show
+/// where the tree takes over.
+fn bench_adaptive_map(c: &mut Criterion) {
+    let mut group = c.benchmark_group("adaptive_map");
+
+    let keys = shuffled_keys(16);
+    group.bench_function("build_1000x16/AdaptiveMap", |b| {
+        b.iter(|| {
+            (0..MAPS)
+                .map(|_| {
+                    let mut map = AdaptiveMap::new(CAPACITY).unwrap();
+                    for &key in &keys {
+                        map.insert(key, key);
+                    }
+                    map
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("build_1000x16/BPlusTreeMap", |b| {
+        b.iter(|| {
+            (0..MAPS)
+                .map(|_| {
+                    let mut map = BPlusTreeMap::new(CAPACITY).unwrap();
+                    for &key in &keys {
+                        map.insert(key, key);
+                    }
+                    map
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+
+    for size in [16u64, 64, 256, 1_024] {
+        let keys = shuffled_keys(size);
+        let mut small = AdaptiveMap::with_threshold(CAPACITY, usize::MAX).unwrap();
+        let mut tree = BPlusTreeMap::new(CAPACITY).unwrap();
+        for &key in &keys {
+            small.insert(key, key);
+            tree.insert(key, key);
+        }
+        group.bench_with_input(BenchmarkId::new("get_all/vec", size), &keys, |b, keys| {
+            b.iter(|| {
+                keys.iter()
+                    .filter_map(|k| small.get(black_box(k)))
+                    .sum::<u64>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("get_all/tree", size), &keys, |b, keys| {
+            b.iter(|| {
+                keys.iter()
+                    .filter_map(|k| tree.get(black_box(k)))
+                    .sum::<u64>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("build/vec", size), &keys, |b, keys| {
+            b.iter(|| {
+                let mut map = AdaptiveMap::with_threshold(CAPACITY, usize::MAX).unwrap();
+                for &key in keys {
+                    map.insert(key, key);
+                }
+                map
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("build/tree", size), &keys, |b, keys| {
+            b.iter(|| {
+                let mut map = BPlusTreeMap::new(CAPACITY).unwrap();
+                for &key in keys {
+                    map.insert(key, key);
+                }
+                map
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_adaptive_map);
+criterion_main!(benches);
@@ -0,0 +1,144 @@
+use bplustree::{BPlusTreeMap, WriteBatch};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+/// Scatter keys across the tree so each batch touches many leaves.
+fn scattered_keys(count: usize, seed: u64) -> Vec<i32> {
+    let mut state = seed;
+    (0..count)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((state >> 33) % 1_000_000) as i32
+        })
+        .collect()
+}
+
+fn populated_tree(capacity: usize, size: i32) -> BPlusTreeMap<i32, i32> {
+    let mut tree = BPlusTreeMap::new(capacity).unwrap();
+    for i in 0..size {
+        tree.insert(i * 10, i);
+    }
+    tree
+}
+
+fn benchmark_batch_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch_insert");
+
+    for &batch_size in &[1_000usize, 10_000] {
+        let keys = scattered_keys(batch_size, batch_size as u64);
+
+        group.bench_function(format!("per_op_{}", batch_size), |b| {
+            b.iter_batched(
+                || populated_tree(64, 100_000),
+                |mut tree| {
+                    for &k in &keys {
+                        tree.insert(black_box(k), k);
+                    }
+                    tree
+                },
+                BatchSize::LargeInput,
+            );
+        });
+
+        group.bench_function(format!("apply_batch_{}", batch_size), |b| {
+            b.iter_batched(
+                || populated_tree(64, 100_000),
+                |mut tree| {
+                    let mut batch = WriteBatch::with_capacity(keys.len());
+                    for &k in &keys {
+                        batch.insert(black_box(k), k);
+                    }
+                    tree.apply_batch(batch);
+                    tree
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+fn benchmark_batch_remove(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch_remove");
+    let keys: Vec<i32> = (0..20_000).map(|i| i * 10).collect();
+
+    group.bench_function("per_op_20000", |b| {
+        b.iter_batched(
+            || populated_tree(64, 100_000),
+            |mut tree| {
+                for k in &keys {
+                    tree.remove(black_box(k));
+                }
+                tree
+            },
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.bench_function("apply_batch_20000", |b| {
+        b.iter_batched(
+            || populated_tree(64, 100_000),
+            |mut tree| {
+                let mut batch = WriteBatch::with_capacity(keys.len());
+                for &k in &keys {
+                    batch.remove(black_box(k));
+                }
+                tree.apply_batch(batch);
+                tree
+            },
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.finish();
+}
+
+fn benchmark_clustered_insert(c: &mut Criterion) {
+    // A dense run of new keys landing in one region splits the same leaves
+    // repeatedly when applied one at a time.
+    let mut group = c.benchmark_group("clustered_insert");
+    let keys: Vec<i32> = scattered_keys(10_000, 7)
+        .into_iter()
+        .map(|k| 5_000_000 + k % 20_000)
+        .collect();
+
+    group.bench_function("per_op_10000", |b| {
+        b.iter_batched(
+            || populated_tree(64, 100_000),
+            |mut tree| {
+                for &k in &keys {
+                    tree.insert(black_box(k), k);
+                }
+                tree
+            },
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.bench_function("apply_batch_10000", |b| {
+        b.iter_batched(
+            || populated_tree(64, 100_000),
+            |mut tree| {
+                let mut batch = WriteBatch::with_capacity(keys.len());
+                for &k in &keys {
+                    batch.insert(black_box(k), k);
+                }
+                tree.apply_batch(batch);
+                tree
+            },
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    benchmark_batch_insert,
+    benchmark_batch_remove,
+    benchmark_clustered_insert
+);
+criterion_main!(benches);
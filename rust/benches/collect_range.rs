@@ -0,0 +1,69 @@
+use bplustree::BPlusTreeMap;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+/// Compares collecting owned range results through the iterator with the
+/// preallocated, leaf-by-leaf `collect_range_owned`.
+fn bench_collect_range(c: &mut Criterion) {
+    let mut group = c.benchmark_group("collect_range");
+    let mut tree = BPlusTreeMap::new(64).unwrap();
+    for i in 0..200_000 {
+        tree.insert(i, format!("value_{}", i));
+    }
+
+    for &range_size in &[100, 10_000, 100_000] {
+        let start = 50_000;
+        let end = start + range_size;
+
+        group.bench_with_input(
+            BenchmarkId::new("range_map_clone_collect", range_size),
+            &range_size,
+            |b, _| {
+                b.iter(|| {
+                    let items: Vec<(i32, String)> = tree
+                        .range(start..end)
+                        .map(|(k, v)| (*k, v.clone()))
+                        .collect();
+                    black_box(items)
+                })
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("collect_range_owned", range_size),
+            &range_size,
+            |b, _| b.iter(|| black_box(tree.collect_range_owned(start..end))),
+        );
+    }
+
+    let mut ints = BPlusTreeMap::new(64).unwrap();
+    for i in 0..200_000 {
+        ints.insert(i, i);
+    }
+    for &range_size in &[100, 10_000, 100_000] {
+        let start = 50_000;
+        let end = start + range_size;
+
+        group.bench_with_input(
+            BenchmarkId::new("range_map_clone_collect_i32", range_size),
+            &range_size,
+            |b, _| {
+                b.iter(|| {
+                    let items: Vec<(i32, i32)> =
+                        ints.range(start..end).map(|(k, v)| (*k, *v)).collect();
+                    black_box(items)
+                })
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("collect_range_owned_i32", range_size),
+            &range_size,
+            |b, _| b.iter(|| black_box(ints.collect_range_owned(start..end))),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_collect_range);
+criterion_main!(benches);
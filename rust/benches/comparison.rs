@@ -341,9 +341,7 @@ fn bench_range_queries(c: &mut Criterion) {
             range_size,
             |b, _| {
                 b.iter(|| {
-                    for (key, value) in
-                        bplus.items_range(Some(&black_box(start)), Some(&black_box(end)))
-                    {
+                    for (key, value) in bplus.range(black_box(start)..black_box(end)) {
                         black_box((key, value));
                     }
                 });
@@ -379,7 +377,7 @@ fn bench_range_edge_cases(c: &mut Criterion) {
 
     group.bench_function("small_range_start_BPlusTreeMap", |b| {
         b.iter(|| {
-            for (key, value) in bplus.items_range(Some(&black_box(0)), Some(&black_box(10))) {
+            for (key, value) in bplus.range(black_box(0)..black_box(10)) {
                 black_box((key, value));
             }
         });
@@ -396,9 +394,7 @@ fn bench_range_edge_cases(c: &mut Criterion) {
 
     group.bench_function("small_range_end_BPlusTreeMap", |b| {
         b.iter(|| {
-            for (key, value) in
-                bplus.items_range(Some(&black_box(size - 10)), Some(&black_box(size)))
-            {
+            for (key, value) in bplus.range(black_box(size - 10)..black_box(size)) {
                 black_box((key, value));
             }
         });
@@ -415,7 +411,7 @@ fn bench_range_edge_cases(c: &mut Criterion) {
 
     group.bench_function("range_to_end_BPlusTreeMap", |b| {
         b.iter(|| {
-            for (key, value) in bplus.items_range(Some(&black_box(size / 2)), None) {
+            for (key, value) in bplus.range(black_box(size / 2)..) {
                 black_box((key, value));
             }
         });
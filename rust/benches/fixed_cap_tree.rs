@@ -0,0 +1,72 @@
+use bplustree::{BPlusTreeMap, FixedCapTree};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const SIZE: u64 = 100_000;
+
+/// Keys in a fixed pseudo-random order, so inserts and lookups miss cache.
+fn shuffled_keys() -> Vec<u64> {
+    let mut state: u64 = 42;
+    (0..SIZE)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            state >> 11
+        })
+        .collect()
+}
+
+/// Compares inline-array nodes with the Vec-backed tree at the same capacity.
+fn bench_fixed_cap_tree(c: &mut Criterion) {
+    let keys = shuffled_keys();
+    let mut group = c.benchmark_group("fixed_cap_tree");
+
+    group.bench_function(BenchmarkId::new("insert", "FixedCapTree<32>"), |b| {
+        b.iter(|| {
+            let mut tree: FixedCapTree<u64, u64, 32> = FixedCapTree::new();
+            for &key in &keys {
+                tree.insert(key, key);
+            }
+            black_box(tree)
+        })
+    });
+    group.bench_function(BenchmarkId::new("insert", "BPlusTreeMap(32)"), |b| {
+        b.iter(|| {
+            let mut tree = BPlusTreeMap::new(32).unwrap();
+            for &key in &keys {
+                tree.insert(key, key);
+            }
+            black_box(tree)
+        })
+    });
+
+    let mut fixed: FixedCapTree<u64, u64, 32> = FixedCapTree::new();
+    let mut tree = BPlusTreeMap::new(32).unwrap();
+    for &key in &keys {
+        fixed.insert(key, key);
+        tree.insert(key, key);
+    }
+    group.bench_function(BenchmarkId::new("get", "FixedCapTree<32>"), |b| {
+        b.iter(|| {
+            let mut sum = 0u64;
+            for key in &keys {
+                sum = sum.wrapping_add(*fixed.get(key).unwrap());
+            }
+            black_box(sum)
+        })
+    });
+    group.bench_function(BenchmarkId::new("get", "BPlusTreeMap(32)"), |b| {
+        b.iter(|| {
+            let mut sum = 0u64;
+            for key in &keys {
+                sum = sum.wrapping_add(*tree.get(key).unwrap());
+            }
+            black_box(sum)
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_fixed_cap_tree);
+criterion_main!(benches);
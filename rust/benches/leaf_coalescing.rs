@@ -0,0 +1,66 @@
+use bplustree::{BPlusTreeMap, DeletionMode};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+/// Builds a 200,000-key tree and removes a pseudo-random `removed_percent`
+/// of the keys in the given deletion mode.
+fn churned_tree(mode: DeletionMode, removed_percent: u64) -> BPlusTreeMap<u64, u64> {
+    let mut tree = BPlusTreeMap::new(64).unwrap();
+    for i in 0..200_000 {
+        tree.insert(i, i);
+    }
+    tree.set_deletion_mode(mode);
+    let mut state: u64 = 9;
+    for i in 0..200_000 {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        if (state >> 33) % 100 < removed_percent {
+            tree.remove(&i);
+        }
+    }
+    tree
+}
+
+/// Compares full scans of churned trees before and after `coalesce_leaves`.
+fn bench_leaf_coalescing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("leaf_coalescing");
+
+    for (name, mode, removed_percent) in [
+        ("eager_50", DeletionMode::Eager, 50),
+        ("deferred_90", DeletionMode::DeferredRebalance, 90),
+    ] {
+        let churned = churned_tree(mode, removed_percent);
+        let mut coalesced = churned_tree(mode, removed_percent);
+        coalesced.coalesce_leaves();
+        println!(
+            "{}: {} entries, {} leaves before, {} after",
+            name,
+            churned.len(),
+            churned.leaf_count(),
+            coalesced.leaf_count()
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("scan_churned", name),
+            &churned,
+            |b, tree| b.iter(|| black_box(tree.values().sum::<u64>())),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("scan_coalesced", name),
+            &coalesced,
+            |b, tree| b.iter(|| black_box(tree.values().sum::<u64>())),
+        );
+        group.bench_function(BenchmarkId::new("coalesce", name), |b| {
+            b.iter_batched(
+                || churned_tree(mode, removed_percent),
+                |mut tree| black_box(tree.coalesce_leaves()),
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_leaf_coalescing);
+criterion_main!(benches);
@@ -0,0 +1,263 @@
+//! BPlusTreeMap against other popular maps, printed as markdown tables.
+//!
+//! Run with `cargo bench --bench map_comparison`. Each cell is the median of
+//! several timed runs, in nanoseconds per operation, so the tables can be
+//! pasted straight into an issue or the performance log.
+//!
+//! `IndexMap` keeps insertion order rather than key order, so it has no range
+//! query, and its removal is the O(1) `swap_remove`, which gives up even the
+//! insertion order. It is here because it is the usual answer to "why not a
+//! hash map", not as a like-for-like ordered map.
+
+use bplustree::BPlusTreeMap;
+use indexmap::IndexMap;
+use std::collections::BTreeMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const SIZES: [usize; 2] = [1_000, 100_000];
+const CAPACITY: usize = 16;
+const RANGE_LEN: usize = 100;
+const RANGE_QUERIES: usize = 1_000;
+const SAMPLES: usize = 5;
+/// Each sample repeats an operation until it has run at least this long.
+const MIN_RUN: Duration = Duration::from_millis(10);
+
+const OPERATIONS: [&str; 5] = [
+    "insert (random order)",
+    "get (random hits)",
+    "iterate (per entry)",
+    "range of 100 entries",
+    "remove (random order)",
+];
+
+/// The operations every map in the comparison is timed on.
+trait Contender {
+    const NAME: &'static str;
+    fn empty() -> Self;
+    fn insert(&mut self, key: u64, value: u64);
+    fn get(&self, key: u64) -> Option<u64>;
+    fn remove(&mut self, key: u64) -> Option<u64>;
+    fn sum_all(&self) -> u64;
+    /// Sum of the values of up to `len` entries from `start` in key order,
+    /// or `None` if the map does not keep its keys ordered.
+    fn sum_range(&self, start: u64, len: usize) -> Option<u64>;
+}
+
+impl Contender for BPlusTreeMap<u64, u64> {
+    const NAME: &'static str = "BPlusTreeMap";
+    fn empty() -> Self {
+        BPlusTreeMap::new(CAPACITY).unwrap()
+    }
+    fn insert(&mut self, key: u64, value: u64) {
+        BPlusTreeMap::insert(self, key, value);
+    }
+    fn get(&self, key: u64) -> Option<u64> {
+        BPlusTreeMap::get(self, &key).copied()
+    }
+    fn remove(&mut self, key: u64) -> Option<u64> {
+        BPlusTreeMap::remove(self, &key)
+    }
+    fn sum_all(&self) -> u64 {
+        self.values().sum()
+    }
+    fn sum_range(&self, start: u64, len: usize) -> Option<u64> {
+        Some(self.range(start..).take(len).map(|(_, v)| *v).sum())
+    }
+}
+
+impl Contender for BTreeMap<u64, u64> {
+    const NAME: &'static str = "BTreeMap";
+    fn empty() -> Self {
+        BTreeMap::new()
+    }
+    fn insert(&mut self, key: u64, value: u64) {
+        BTreeMap::insert(self, key, value);
+    }
+    fn get(&self, key: u64) -> Option<u64> {
+        BTreeMap::get(self, &key).copied()
+    }
+    fn remove(&mut self, key: u64) -> Option<u64> {
+        BTreeMap::remove(self, &key)
+    }
+    fn sum_all(&self) -> u64 {
+        self.values().sum()
+    }
+    fn sum_range(&self, start: u64, len: usize) -> Option<u64> {
+        Some(self.range(start..).take(len).map(|(_, v)| *v).sum())
+    }
+}
+
+impl Contender for im::OrdMap<u64, u64> {
+    const NAME: &'static str = "im::OrdMap";
+    fn empty() -> Self {
+        im::OrdMap::new()
+    }
+    fn insert(&mut self, key: u64, value: u64) {
+        im::OrdMap::insert(self, key, value);
+    }
+    fn get(&self, key: u64) -> Option<u64> {
+        im::OrdMap::get(self, &key).copied()
+    }
+    fn remove(&mut self, key: u64) -> Option<u64> {
+        im::OrdMap::remove(self, &key)
+    }
+    fn sum_all(&self) -> u64 {
+        self.values().sum()
+    }
+    fn sum_range(&self, start: u64, len: usize) -> Option<u64> {
+        Some(self.range(start..).take(len).map(|(_, v)| *v).sum())
+    }
+}
+
+impl Contender for IndexMap<u64, u64> {
+    const NAME: &'static str = "IndexMap";
+    fn empty() -> Self {
+        IndexMap::new()
+    }
+    fn insert(&mut self, key: u64, value: u64) {
+        IndexMap::insert(self, key, value);
+    }
+    fn get(&self, key: u64) -> Option<u64> {
+        IndexMap::get(self, &key).copied()
+    }
+    fn remove(&mut self, key: u64) -> Option<u64> {
+        self.swap_remove(&key)
+    }
+    fn sum_all(&self) -> u64 {
+        self.values().sum()
+    }
+    fn sum_range(&self, _start: u64, _len: usize) -> Option<u64> {
+        None
+    }
+}
+
+/// Keys `0..n` in a fixed shuffled order.
+fn shuffled(n: usize, seed: u64) -> Vec<u64> {
+    let mut keys: Vec<u64> = (0..n as u64).collect();
+    let mut state = seed;
+    for i in (1..n).rev() {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        keys.swap(i, ((state >> 33) % (i as u64 + 1)) as usize);
+    }
+    keys
+}
+
+/// Median nanoseconds per operation. `run` consumes what `setup` made and
+/// performs `ops` operations; setup and dropping the outputs are not timed.
+fn time_per_op<S, O>(ops: usize, mut setup: impl FnMut() -> S, mut run: impl FnMut(S) -> O) -> f64 {
+    let state = setup();
+    let start = Instant::now();
+    let output = run(state);
+    let once = start.elapsed();
+    drop(output);
+    let reps = (MIN_RUN.as_nanos() / once.as_nanos().max(1)).max(1) as usize;
+
+    let mut samples: Vec<f64> = (0..SAMPLES)
+        .map(|_| {
+            let states: Vec<S> = (0..reps).map(|_| setup()).collect();
+            let start = Instant::now();
+            let outputs: Vec<O> = states.into_iter().map(&mut run).collect();
+            let elapsed = start.elapsed();
+            drop(outputs);
+            elapsed.as_nanos() as f64 / (reps * ops) as f64
+        })
+        .collect();
+    samples.sort_by(f64::total_cmp);
+    samples[SAMPLES / 2]
+}
+
+/// One column of a table: a time per operation, in `OPERATIONS` order.
+fn measure<M: Contender>(n: usize) -> (&'static str, Vec<Option<f64>>) {
+    let inserts = shuffled(n, 1);
+    let lookups = shuffled(n, 2);
+    let removals = shuffled(n, 3);
+    let starts: Vec<u64> = shuffled(n - RANGE_LEN, 4)
+        .into_iter()
+        .cycle()
+        .take(RANGE_QUERIES)
+        .collect();
+
+    let build = || {
+        let mut map = M::empty();
+        for &key in &inserts {
+            map.insert(key, key * 2);
+        }
+        map
+    };
+    let full = build();
+
+    let insert = time_per_op(n, || (), |()| build());
+    let get = time_per_op(
+        n,
+        || (),
+        |()| {
+            for &key in &lookups {
+                black_box(full.get(black_box(key)));
+            }
+        },
+    );
+    let iterate = time_per_op(n, || (), |()| black_box(full.sum_all()));
+    let range = full.sum_range(0, RANGE_LEN).map(|_| {
+        time_per_op(
+            RANGE_QUERIES,
+            || (),
+            |()| {
+                for &start in &starts {
+                    black_box(full.sum_range(black_box(start), RANGE_LEN));
+                }
+            },
+        )
+    });
+    let remove = time_per_op(n, build, |mut map| {
+        for &key in &removals {
+            black_box(map.remove(key));
+        }
+        map
+    });
+
+    (
+        M::NAME,
+        vec![Some(insert), Some(get), Some(iterate), range, Some(remove)],
+    )
+}
+
+fn print_table(n: usize, columns: &[(&str, Vec<Option<f64>>)]) {
+    println!("### {} entries (ns per operation, lower is better)\n", n);
+    let names: Vec<&str> = columns.iter().map(|(name, _)| *name).collect();
+    println!("| Operation | {} |", names.join(" | "));
+    println!("|---|{}", "---:|".repeat(columns.len()));
+    for (row, operation) in OPERATIONS.iter().enumerate() {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|(_, times)| match times[row] {
+                Some(ns) if ns < 100.0 => format!("{:.1}", ns),
+                Some(ns) => format!("{:.0}", ns),
+                None => "n/a".to_string(),
+            })
+            .collect();
+        println!("| {} | {} |", operation, cells.join(" | "));
+    }
+    println!();
+}
+
+fn main() {
+    println!(
+        "BPlusTreeMap (capacity {}) against other maps, u64 keys and values.\n",
+        CAPACITY
+    );
+    for n in SIZES {
+        let columns = [
+            measure::<BPlusTreeMap<u64, u64>>(n),
+            measure::<BTreeMap<u64, u64>>(n),
+            measure::<im::OrdMap<u64, u64>>(n),
+            measure::<IndexMap<u64, u64>>(n),
+        ];
+        print_table(n, &columns);
+    }
+    println!(
+        "IndexMap keeps insertion order, so it has no range query, and removes with `swap_remove`."
+    );
+}
@@ -0,0 +1,40 @@
+use bplustree::BPlusTreeMap;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const KEYS: u64 = 100_000;
+
+fn tree_with_every(step: u64) -> BPlusTreeMap<u64, u64> {
+    let mut tree = BPlusTreeMap::new(64).unwrap();
+    for key in (0..KEYS).step_by(step as usize) {
+        tree.insert(key, key);
+    }
+    tree
+}
+
+/// Joining via the leaf chains against looking every key of one tree up in
+/// the other, for trees of equal density and for a sparse right side.
+fn bench_merge_join(c: &mut Criterion) {
+    let mut group = c.benchmark_group("merge_join");
+    let dense = tree_with_every(1);
+    for (name, step) in [("dense_x_dense", 1), ("dense_x_every_100th", 100)] {
+        let other = tree_with_every(step);
+        group.bench_function(format!("{}/join_inner", name), |b| {
+            b.iter(|| dense.join_inner(&other).count())
+        });
+        group.bench_function(format!("{}/lookups", name), |b| {
+            b.iter(|| {
+                dense
+                    .items()
+                    .filter(|(k, _)| other.get(k).is_some())
+                    .count()
+            })
+        });
+        group.bench_function(format!("{}/join_outer", name), |b| {
+            b.iter(|| dense.join_outer(&other).count())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_merge_join);
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+use bplustree::BPlusTreeMap;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const SIZE: u64 = 100_000;
+
+/// Keys in a fixed pseudo-random order, so splits happen all over the tree.
+fn shuffled_keys() -> Vec<u64> {
+    let mut state: u64 = 42;
+    (0..SIZE)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            state >> 11
+        })
+        .collect()
+}
+
+/// Builds trees from scratch, which is dominated by filling and splitting
+/// nodes, at a small and a large capacity.
+fn bench_node_fill(c: &mut Criterion) {
+    let shuffled = shuffled_keys();
+    let sequential: Vec<u64> = (0..SIZE).collect();
+    let mut group = c.benchmark_group("node_fill");
+
+    for capacity in [16, 128] {
+        for (order, keys) in [("sequential", &sequential), ("shuffled", &shuffled)] {
+            group.bench_with_input(BenchmarkId::new(order, capacity), keys, |b, keys| {
+                b.iter(|| {
+                    let mut tree = BPlusTreeMap::new(capacity).unwrap();
+                    for &key in keys {
+                        tree.insert(key, key);
+                    }
+                    black_box(tree)
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_node_fill);
+criterion_main!(benches);
@@ -0,0 +1,38 @@
+use bplustree::BPlusTreeMap;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const CAPACITY: usize = 64;
+
+/// Maps that fit in one leaf, where the root is the only node: building
+/// them, looking up every key, and a larger map for comparison.
+fn bench_small_map(c: &mut Criterion) {
+    let mut group = c.benchmark_group("small_map");
+
+    for size in [8u64, 64, 10_000] {
+        group.bench_with_input(BenchmarkId::new("build", size), &size, |b, &size| {
+            b.iter(|| {
+                let mut tree = BPlusTreeMap::new(CAPACITY).unwrap();
+                for i in 0..size {
+                    tree.insert(i, i);
+                }
+                black_box(tree)
+            })
+        });
+
+        let mut tree = BPlusTreeMap::new(CAPACITY).unwrap();
+        for i in 0..size {
+            tree.insert(i, i);
+        }
+        group.bench_with_input(BenchmarkId::new("get_all", size), &tree, |b, tree| {
+            b.iter(|| {
+                (0..size)
+                    .filter_map(|i| tree.get(black_box(&i)))
+                    .sum::<u64>()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_small_map);
+criterion_main!(benches);
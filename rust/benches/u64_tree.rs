@@ -0,0 +1,79 @@
+use bplustree::{BPlusTreeMap, U64Tree};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const SIZE: u64 = 100_000;
+
+/// Keys in a fixed pseudo-random order, so inserts and lookups miss cache.
+fn shuffled_keys() -> Vec<u64> {
+    let mut state: u64 = 42;
+    (0..SIZE)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            state >> 11
+        })
+        .collect()
+}
+
+/// Compares the `u64`-specialized tree with the generic tree at a few node
+/// capacities.
+fn bench_u64_tree(c: &mut Criterion) {
+    let keys = shuffled_keys();
+    let mut group = c.benchmark_group("u64_tree");
+
+    group.bench_function(BenchmarkId::new("insert", "U64Tree"), |b| {
+        b.iter(|| {
+            let mut tree = U64Tree::new();
+            for &key in &keys {
+                tree.insert(key, key);
+            }
+            black_box(tree)
+        })
+    });
+    for &capacity in &[16, 64] {
+        group.bench_function(BenchmarkId::new("insert", capacity), |b| {
+            b.iter(|| {
+                let mut tree = BPlusTreeMap::new(capacity).unwrap();
+                for &key in &keys {
+                    tree.insert(key, key);
+                }
+                black_box(tree)
+            })
+        });
+    }
+
+    let mut fast = U64Tree::new();
+    for &key in &keys {
+        fast.insert(key, key);
+    }
+    group.bench_function(BenchmarkId::new("get", "U64Tree"), |b| {
+        b.iter(|| {
+            let mut sum = 0u64;
+            for &key in &keys {
+                sum = sum.wrapping_add(*fast.get(key).unwrap());
+            }
+            black_box(sum)
+        })
+    });
+    for &capacity in &[16, 64] {
+        let mut tree = BPlusTreeMap::new(capacity).unwrap();
+        for &key in &keys {
+            tree.insert(key, key);
+        }
+        group.bench_function(BenchmarkId::new("get", capacity), |b| {
+            b.iter(|| {
+                let mut sum = 0u64;
+                for key in &keys {
+                    sum = sum.wrapping_add(*tree.get(key).unwrap());
+                }
+                black_box(sum)
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_u64_tree);
+criterion_main!(benches);
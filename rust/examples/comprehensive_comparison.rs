@@ -165,6 +165,7 @@ fn main() {
             }
         });
 
+        #[allow(deprecated)]
         let bplus_fast_iter_time = run_benchmark("BPlusTreeMap Fast Iteration", iterations, || {
             for (k, v) in bplus.items_fast() {
                 black_box((k, v));
@@ -189,9 +190,7 @@ fn main() {
         });
 
         let bplus_range_time = run_benchmark("BPlusTreeMap Range", 1000, || {
-            for (k, v) in
-                bplus.items_range(Some(&black_box(range_start)), Some(&black_box(range_end)))
-            {
+            for (k, v) in bplus.range(black_box(range_start)..black_box(range_end)) {
                 black_box((k, v));
             }
         });
@@ -259,6 +258,7 @@ fn main() {
         }
     });
 
+    #[allow(deprecated)]
     let small_bplus_fast_time = run_benchmark("Small BPlusTreeMap Fast", 10000, || {
         for (k, v) in small_bplus.items_fast() {
             black_box((k, v));
@@ -420,7 +420,7 @@ fn main() {
     println!("• Range-heavy workloads: BTreeMap");
     println!("• Deletion-heavy workloads: BTreeMap");
     println!("• Memory-constrained environments: BTreeMap");
-    println!("• Iteration-heavy workloads: BPlusTreeMap with items_fast()");
+    println!("• Iteration-heavy workloads: BPlusTreeMap with items()");
     println!("• Large datasets with mixed operations: BPlusTreeMap");
     println!("• Database-like access patterns: BPlusTreeMap");
 
@@ -29,12 +29,12 @@ fn quick_start_example() {
     tree.insert(2, "two");
 
     // Range query
-    let range: Vec<_> = tree.items_range(Some(&1), Some(&2)).collect();
+    let range: Vec<_> = tree.range(1..=2).collect();
     println!("Range [1,2]: {:?}", range); // [(&1, &"one"), (&2, &"two")]
 
     // Sequential access
     println!("All entries in order:");
-    for (key, value) in tree.slice() {
+    for (key, value) in tree.items() {
         println!("  {}: {}", key, value);
     }
 }
@@ -78,19 +78,19 @@ fn range_query_examples() {
     tree.insert(25, "twenty-five");
 
     // Get all entries in a range
-    let entries: Vec<_> = tree.items_range(Some(&5), Some(&15)).collect();
+    let entries: Vec<_> = tree.range(5..15).collect();
     println!("Range [5,15]: {:?}", entries);
 
     // Get all entries from a minimum key
-    let entries: Vec<_> = tree.items_range(Some(&15), None).collect();
+    let entries: Vec<_> = tree.range(15..).collect();
     println!("Range [15,∞): {:?}", entries);
 
     // Get all entries up to a maximum key
-    let entries: Vec<_> = tree.items_range(None, Some(&15)).collect();
+    let entries: Vec<_> = tree.range(..15).collect();
     println!("Range (-∞,15]: {:?}", entries);
 
     // Get all entries in sorted order
-    let all_entries = tree.slice();
+    let all_entries: Vec<_> = tree.items().collect();
     println!("All entries: {:?}", all_entries);
 }
 
@@ -108,9 +108,7 @@ fn time_series_example() {
     // Efficient range query for a time period
     let start_time = 1640995200;
     let end_time = 1641168000;
-    let period_data: Vec<_> = time_series
-        .items_range(Some(&start_time), Some(&end_time))
-        .collect();
+    let period_data: Vec<_> = time_series.range(start_time..end_time).collect();
 
     println!("Time series data from {} to {}:", start_time, end_time);
     for (timestamp, data) in period_data {
@@ -119,7 +117,7 @@ fn time_series_example() {
 
     // Sequential scan
     println!("All time series data:");
-    for (timestamp, data) in time_series.slice() {
+    for (timestamp, data) in time_series.items() {
         println!("  {}: {}", timestamp, data);
     }
 }
@@ -0,0 +1,103 @@
+//! Applies an arbitrary operation sequence to a tree of arbitrary capacity and
+//! checks every result, the final contents and the tree invariants against
+//! `BTreeMap`.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use bplustree::{BPlusTreeMap, DeletionMode, WriteBatch};
+use libfuzzer_sys::fuzz_target;
+use std::collections::BTreeMap;
+
+/// Smallest capacity the tree accepts.
+const MIN_CAPACITY: usize = 4;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Insert(u16, u16),
+    Remove(u16),
+    Get(u16),
+    Range(u16, u16),
+    RemoveMinK(u8),
+    RemoveMaxK(u8),
+    Batch(Vec<(bool, u16, u16)>),
+    ReplaceKey(u16, u16),
+    SetDeferred(bool),
+    Vacuum,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    capacity: u8,
+    ops: Vec<Op>,
+}
+
+fuzz_target!(|input: Input| {
+    // Spend most inputs near the minimum, where splits and merges are densest
+    let capacity = usize::from(input.capacity % 40);
+    let mut tree = match BPlusTreeMap::new(capacity) {
+        Ok(tree) => tree,
+        Err(_) => {
+            assert!(capacity < MIN_CAPACITY, "capacity {} rejected", capacity);
+            return;
+        }
+    };
+    assert!(capacity >= MIN_CAPACITY, "capacity {} accepted", capacity);
+    let mut model = BTreeMap::new();
+
+    for op in input.ops {
+        match op {
+            Op::Insert(k, v) => assert_eq!(tree.insert(k, v), model.insert(k, v)),
+            Op::Remove(k) => assert_eq!(tree.remove(&k), model.remove(&k)),
+            Op::Get(k) => assert_eq!(tree.get(&k), model.get(&k)),
+            Op::Range(a, b) => {
+                let (lo, hi) = (a.min(b), a.max(b));
+                assert!(tree.range(lo..hi).eq(model.range(lo..hi)));
+            }
+            Op::RemoveMinK(n) => {
+                let expected: Vec<_> = std::iter::from_fn(|| model.pop_first())
+                    .take(usize::from(n))
+                    .collect();
+                assert_eq!(tree.remove_min_k(usize::from(n)), expected);
+            }
+            Op::RemoveMaxK(n) => {
+                let expected: Vec<_> = std::iter::from_fn(|| model.pop_last())
+                    .take(usize::from(n))
+                    .collect();
+                assert_eq!(tree.remove_max_k(usize::from(n)), expected);
+            }
+            Op::Batch(writes) => {
+                let mut batch = WriteBatch::new();
+                let mut expected = Vec::with_capacity(writes.len());
+                for (is_insert, k, v) in writes {
+                    if is_insert {
+                        batch.insert(k, v);
+                        expected.push(model.insert(k, v));
+                    } else {
+                        batch.remove(k);
+                        expected.push(model.remove(&k));
+                    }
+                }
+                assert_eq!(tree.apply_batch(batch), expected);
+            }
+            Op::ReplaceKey(old, new) => {
+                let expected = model.remove(&old).map(|v| model.insert(new, v));
+                assert_eq!(tree.replace_key(&old, new).ok(), expected);
+            }
+            Op::SetDeferred(deferred) => tree.set_deletion_mode(if deferred {
+                DeletionMode::DeferredRebalance
+            } else {
+                DeletionMode::Eager
+            }),
+            Op::Vacuum => tree.vacuum(),
+        }
+        if let Err(e) = tree.check_invariants_detailed() {
+            panic!("invariants violated: {}", e);
+        }
+    }
+
+    assert_eq!(tree.len(), model.len());
+    assert!(tree.items().eq(model.iter()));
+    tree.vacuum();
+    assert!(tree.validate().is_ok());
+});
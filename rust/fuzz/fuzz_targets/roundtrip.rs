@@ -0,0 +1,49 @@
+//! Round-trips arbitrary contents out of one tree and into another of a
+//! different capacity, through both one-at-a-time inserts and a write batch,
+//! and checks that nothing is lost, duplicated or reordered.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use bplustree::{BPlusTreeMap, BatchOp, WriteBatch};
+use libfuzzer_sys::fuzz_target;
+
+/// Smallest capacity the tree accepts.
+const MIN_CAPACITY: usize = 4;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    source_capacity: u8,
+    target_capacity: u8,
+    entries: Vec<(u32, Vec<u8>)>,
+}
+
+fuzz_target!(|input: Input| {
+    let source_capacity = MIN_CAPACITY + usize::from(input.source_capacity % 64);
+    let target_capacity = MIN_CAPACITY + usize::from(input.target_capacity % 64);
+
+    let mut source = BPlusTreeMap::new(source_capacity).unwrap();
+    for (k, v) in input.entries {
+        source.insert(k, v);
+    }
+
+    // Entry by entry
+    let mut copied = BPlusTreeMap::new(target_capacity).unwrap();
+    for (k, v) in source.items() {
+        assert_eq!(copied.insert(*k, v.clone()), None);
+    }
+
+    // As a single batch
+    let batch: WriteBatch<_, _> = source
+        .items()
+        .map(|(k, v)| BatchOp::Insert(*k, v.clone()))
+        .collect();
+    let mut batched = BPlusTreeMap::new(target_capacity).unwrap();
+    assert!(batched.apply_batch(batch).iter().all(Option::is_none));
+
+    for tree in [&copied, &batched] {
+        assert!(tree.validate().is_ok());
+        assert_eq!(tree.len(), source.len());
+        assert!(tree.items().eq(source.items()));
+    }
+});
@@ -0,0 +1,296 @@
+//! Sorted-vector storage for maps that are usually small.
+//!
+//! Applications that keep thousands of tiny maps pay for a tree's nodes and
+//! arenas in every one of them. [`AdaptiveMap`] holds its entries in a single
+//! sorted `Vec`, searched by bisection, until it grows past a threshold. It
+//! then moves them into a [`BPlusTreeMap`] and behaves like one from then on.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::error::{BPlusTreeError, InitResult};
+use crate::iteration::RangeIterator;
+use crate::types::{BPlusTreeMap, MIN_CAPACITY};
+use std::ops::{Bound, RangeBounds};
+
+/// Entries an [`AdaptiveMap`] keeps in a sorted vector before it becomes a
+/// tree, unless another threshold is given.
+pub const DEFAULT_SMALL_THRESHOLD: usize = 64;
+
+// The tree is boxed so a small map costs no more than its vector.
+#[derive(Debug)]
+enum Repr<K, V> {
+    Small(Vec<(K, V)>),
+    Tree(Box<BPlusTreeMap<K, V>>),
+}
+
+/// A map that is a sorted vector while small and a B+ tree once large.
+///
+/// The switch happens when an insert takes the map past its threshold, and
+/// is one-way: removing entries from a tree leaves it a tree. [`clear`]
+/// starts over with an empty vector.
+///
+/// [`clear`]: Self::clear
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::AdaptiveMap;
+///
+/// let mut tags = AdaptiveMap::with_threshold(16, 4).unwrap();
+/// for (i, tag) in ["d", "a", "c", "b"].into_iter().enumerate() {
+///     tags.insert(tag, i);
+/// }
+/// assert!(!tags.is_tree());
+/// assert_eq!(tags.get(&"c"), Some(&2));
+///
+/// tags.insert("e", 4);
+/// assert!(tags.is_tree());
+/// assert_eq!(tags.iter().map(|(k, _)| *k).collect::<String>(), "abcde");
+/// ```
+#[derive(Debug)]
+pub struct AdaptiveMap<K, V> {
+    repr: Repr<K, V>,
+    capacity: usize,
+    threshold: usize,
+}
+
+impl<K: TreeKey, V: TreeValue> AdaptiveMap<K, V> {
+    /// Create an empty map that becomes a tree with node capacity `capacity`
+    /// once it holds more than [`DEFAULT_SMALL_THRESHOLD`] entries.
+    pub fn new(capacity: usize) -> InitResult<Self> {
+        Self::with_threshold(capacity, DEFAULT_SMALL_THRESHOLD)
+    }
+
+    /// Create an empty map that becomes a tree with node capacity `capacity`
+    /// once it holds more than `threshold` entries.
+    pub fn with_threshold(capacity: usize, threshold: usize) -> InitResult<Self> {
+        if capacity < MIN_CAPACITY {
+            return Err(BPlusTreeError::invalid_capacity(capacity, MIN_CAPACITY));
+        }
+        Ok(Self {
+            repr: Repr::Small(Vec::new()),
+            capacity,
+            threshold,
+        })
+    }
+
+    /// Most entries the map holds before it becomes a tree.
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Returns true once the entries have moved into a tree.
+    pub fn is_tree(&self) -> bool {
+        matches!(self.repr, Repr::Tree(_))
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        match &self.repr {
+            Repr::Small(entries) => entries.len(),
+            Repr::Tree(tree) => tree.len(),
+        }
+    }
+
+    /// Returns true if the map holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the value stored under `key`.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        match &self.repr {
+            Repr::Small(entries) => search(entries, key).ok().map(|i| &entries[i].1),
+            Repr::Tree(tree) => tree.get(key),
+        }
+    }
+
+    /// Get a mutable reference to the value stored under `key`.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        match &mut self.repr {
+            Repr::Small(entries) => match search(entries, key) {
+                Ok(i) => Some(&mut entries[i].1),
+                Err(_) => None,
+            },
+            Repr::Tree(tree) => tree.get_mut(key),
+        }
+    }
+
+    /// Returns true if the map holds `key`.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Insert `value` under `key`, returning the previous value if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let entries = match &mut self.repr {
+            Repr::Small(entries) => entries,
+            Repr::Tree(tree) => return tree.insert(key, value),
+        };
+        match search(entries, &key) {
+            Ok(i) => return Some(std::mem::replace(&mut entries[i].1, value)),
+            Err(i) => entries.insert(i, (key, value)),
+        }
+        if entries.len() > self.threshold {
+            self.grow_into_tree();
+        }
+        None
+    }
+
+    /// Remove `key`, returning its value if it was present.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        match &mut self.repr {
+            Repr::Small(entries) => search(entries, key).ok().map(|i| entries.remove(i).1),
+            Repr::Tree(tree) => tree.remove(key),
+        }
+    }
+
+    /// Remove every entry, going back to a sorted vector.
+    pub fn clear(&mut self) {
+        self.repr = Repr::Small(Vec::new());
+    }
+
+    /// Iterate over all entries in key order.
+    pub fn iter(&self) -> AdaptiveIter<'_, K, V> {
+        self.range(..)
+    }
+
+    /// Iterate over the entries in `range` in key order.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> AdaptiveIter<'_, K, V> {
+        let inner = match &self.repr {
+            Repr::Small(entries) => {
+                let start = match range.start_bound() {
+                    Bound::Included(key) => entries.partition_point(|(k, _)| k < key),
+                    Bound::Excluded(key) => entries.partition_point(|(k, _)| k <= key),
+                    Bound::Unbounded => 0,
+                };
+                let end = match range.end_bound() {
+                    Bound::Included(key) => entries.partition_point(|(k, _)| k <= key),
+                    Bound::Excluded(key) => entries.partition_point(|(k, _)| k < key),
+                    Bound::Unbounded => entries.len(),
+                };
+                IterRepr::Small(entries[start..end.max(start)].iter())
+            }
+            Repr::Tree(tree) => IterRepr::Tree(tree.range(range)),
+        };
+        AdaptiveIter { inner }
+    }
+
+    /// Convert into a tree, moving the entries over if the map is still small.
+    pub fn into_tree(self) -> BPlusTreeMap<K, V> {
+        match self.repr {
+            Repr::Tree(tree) => *tree,
+            Repr::Small(entries) => build_tree(self.capacity, entries),
+        }
+    }
+
+    fn grow_into_tree(&mut self) {
+        if let Repr::Small(entries) = &mut self.repr {
+            let tree = build_tree(self.capacity, entries.drain(..));
+            self.repr = Repr::Tree(Box::new(tree));
+        }
+    }
+}
+
+/// Build a tree from sorted entries. `capacity` was checked when the map was
+/// constructed.
+fn build_tree<K: TreeKey, V: TreeValue>(
+    capacity: usize,
+    entries: impl IntoIterator<Item = (K, V)>,
+) -> BPlusTreeMap<K, V> {
+    let mut tree = BPlusTreeMap::with_valid_capacity(capacity);
+    for (key, value) in entries {
+        tree.insert(key, value);
+    }
+    tree
+}
+
+fn search<K: Ord, V>(entries: &[(K, V)], key: &K) -> Result<usize, usize> {
+    entries.binary_search_by(|(k, _)| k.cmp(key))
+}
+
+enum IterRepr<'a, K, V> {
+    Small(std::slice::Iter<'a, (K, V)>),
+    Tree(RangeIterator<'a, K, V>),
+}
+
+/// Iterator over the entries of an [`AdaptiveMap`], in key order.
+pub struct AdaptiveIter<'a, K, V> {
+    inner: IterRepr<'a, K, V>,
+}
+
+impl<'a, K: Ord + Clone, V: Clone> Iterator for AdaptiveIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            IterRepr::Small(entries) => entries.next().map(|(k, v)| (k, v)),
+            IterRepr::Tree(range) => range.next(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_matches_btreemap_across_the_threshold() {
+        let mut map = AdaptiveMap::with_threshold(4, 40).unwrap();
+        let mut model = BTreeMap::new();
+        let mut rng = SeededRng::new(21);
+        for i in 0..2_000 {
+            // Grow slowly, so the map spends a while on both sides
+            let key = rng.below(10 + i / 20) as i32;
+            if rng.below(4) == 0 {
+                assert_eq!(map.remove(&key), model.remove(&key));
+            } else {
+                assert_eq!(map.insert(key, i), model.insert(key, i));
+            }
+            assert_eq!(map.len(), model.len());
+            if !map.is_tree() {
+                assert!(map.len() <= 40);
+            }
+            if i % 50 == 0 {
+                assert!(map.iter().eq(model.iter()));
+                assert!(map.range(3..=7).eq(model.range(3..=7)));
+                assert!(map
+                    .range((Bound::Excluded(2), Bound::Unbounded))
+                    .eq(model.range((Bound::Excluded(2), Bound::Unbounded))));
+            }
+        }
+        assert!(map.is_tree());
+        assert_eq!(map.len(), model.len());
+        assert!(map.into_tree().check_invariants());
+    }
+
+    #[test]
+    fn test_small_map_edits_and_conversion() {
+        let mut map = AdaptiveMap::with_threshold(4, 3).unwrap();
+        map.insert(2, "b");
+        map.insert(1, "a");
+        map.insert(3, "c");
+        *map.get_mut(&2).unwrap() = "B";
+        assert_eq!(map.remove(&1), Some("a"));
+        assert_eq!(map.remove(&1), None);
+        assert!(map.contains_key(&2) && !map.contains_key(&1));
+        assert_eq!(
+            map.range((Bound::Included(5), Bound::Excluded(1))).count(),
+            0
+        );
+        assert!(!map.is_tree());
+
+        map.insert(4, "d");
+        map.insert(5, "e");
+        assert!(map.is_tree());
+        assert_eq!(map.get(&2), Some(&"B"));
+        map.clear();
+        assert!(!map.is_tree() && map.is_empty());
+
+        map.insert(7, "g");
+        let tree = map.into_tree();
+        assert_eq!(tree.get(&7), Some(&"g"));
+        assert!(AdaptiveMap::<i32, i32>::new(2).is_err());
+    }
+}
@@ -0,0 +1,335 @@
+//! A tree shared between async tasks.
+//!
+//! [`AsyncBPlusTreeMap`] puts a [`BPlusTreeMap`] behind a Tokio `RwLock`.
+//! Point operations hold the lock for one call. Bulk operations work in
+//! batches, releasing the lock and yielding to the executor between them, so
+//! loading or scanning a large map does not starve other tasks or writers.
+//!
+//! Values are returned by clone, since no reference into the tree can
+//! outlive the lock guard it was read under.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::error::InitResult;
+use crate::types::BPlusTreeMap;
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::ops::{Bound, RangeBounds};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::{OwnedRwLockReadGuard, RwLock};
+
+/// Entries handled per lock acquisition by bulk operations, unless changed
+/// with [`AsyncBPlusTreeMap::with_batch_size`].
+pub const DEFAULT_ASYNC_BATCH: usize = 256;
+
+/// A handle to a tree shared between tasks. Clones share the same tree.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::AsyncBPlusTreeMap;
+/// use futures::StreamExt;
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let map = AsyncBPlusTreeMap::new(16).unwrap().with_batch_size(100);
+/// map.insert_all((0..1_000).map(|i| (i, i * 2))).await;
+/// assert_eq!(map.get(&10).await, Some(20));
+///
+/// let evens: Vec<_> = map.range_stream(..5).collect().await;
+/// assert_eq!(evens, [(0, 0), (1, 2), (2, 4), (3, 6), (4, 8)]);
+/// # });
+/// ```
+pub struct AsyncBPlusTreeMap<K, V> {
+    tree: Arc<RwLock<BPlusTreeMap<K, V>>>,
+    batch_size: usize,
+}
+
+impl<K, V> Clone for AsyncBPlusTreeMap<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            tree: Arc::clone(&self.tree),
+            batch_size: self.batch_size,
+        }
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> AsyncBPlusTreeMap<K, V> {
+    /// Create an empty map backed by a tree with node capacity `capacity`.
+    pub fn new(capacity: usize) -> InitResult<Self> {
+        Ok(Self::from_tree(BPlusTreeMap::new(capacity)?))
+    }
+
+    /// Share an existing tree.
+    pub fn from_tree(tree: BPlusTreeMap<K, V>) -> Self {
+        Self {
+            tree: Arc::new(RwLock::new(tree)),
+            batch_size: DEFAULT_ASYNC_BATCH,
+        }
+    }
+
+    /// Set how many entries bulk operations and streams handle per lock
+    /// acquisition. Smaller batches yield more often.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Number of entries.
+    pub async fn len(&self) -> usize {
+        self.tree.read().await.len()
+    }
+
+    /// Returns true if the map holds no entries.
+    pub async fn is_empty(&self) -> bool {
+        self.tree.read().await.is_empty()
+    }
+
+    /// Get a clone of the value stored under `key`.
+    pub async fn get(&self, key: &K) -> Option<V> {
+        self.tree.read().await.get(key).cloned()
+    }
+
+    /// Returns true if the map holds `key`.
+    pub async fn contains_key(&self, key: &K) -> bool {
+        self.tree.read().await.contains_key(key)
+    }
+
+    /// Insert `value` under `key`, returning the previous value if any.
+    pub async fn insert(&self, key: K, value: V) -> Option<V> {
+        self.tree.write().await.insert(key, value)
+    }
+
+    /// Remove `key`, returning its value if it was present.
+    pub async fn remove(&self, key: &K) -> Option<V> {
+        self.tree.write().await.remove(key)
+    }
+
+    /// Run `f` with shared access to the tree.
+    pub async fn read<R>(&self, f: impl FnOnce(&BPlusTreeMap<K, V>) -> R) -> R {
+        f(&*self.tree.read().await)
+    }
+
+    /// Run `f` with exclusive access to the tree.
+    pub async fn write<R>(&self, f: impl FnOnce(&mut BPlusTreeMap<K, V>) -> R) -> R {
+        f(&mut *self.tree.write().await)
+    }
+
+    /// Insert every entry of `entries`, a batch per write lock, yielding
+    /// between batches. Returns the number of keys that were not already
+    /// present.
+    pub async fn insert_all<I>(&self, entries: I) -> usize
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut entries = entries.into_iter().peekable();
+        let mut added = 0;
+        while entries.peek().is_some() {
+            {
+                let mut tree = self.tree.write().await;
+                for (key, value) in entries.by_ref().take(self.batch_size) {
+                    added += usize::from(tree.insert(key, value).is_none());
+                }
+            }
+            yield_now().await;
+        }
+        added
+    }
+
+    /// Remove every key in `keys`, a batch per write lock, yielding between
+    /// batches. Returns the number of keys that were present.
+    pub async fn remove_all<'k, I>(&self, keys: I) -> usize
+    where
+        I: IntoIterator<Item = &'k K>,
+        K: 'k,
+    {
+        let mut keys = keys.into_iter().peekable();
+        let mut removed = 0;
+        while keys.peek().is_some() {
+            {
+                let mut tree = self.tree.write().await;
+                for key in keys.by_ref().take(self.batch_size) {
+                    removed += usize::from(tree.remove(key).is_some());
+                }
+            }
+            yield_now().await;
+        }
+        removed
+    }
+}
+
+impl<K, V> AsyncBPlusTreeMap<K, V>
+where
+    K: TreeKey + Send + Sync + 'static,
+    V: TreeValue + Send + Sync + 'static,
+{
+    /// Stream clones of the entries in `range` in key order.
+    ///
+    /// Entries are copied out a batch at a time under a read lock, which is
+    /// released while the batch is consumed. The stream is therefore not a
+    /// snapshot: writes between batches are seen if they land after the last
+    /// key streamed so far.
+    pub fn range_stream<R: RangeBounds<K>>(&self, range: R) -> RangeStream<K, V> {
+        RangeStream {
+            tree: Arc::clone(&self.tree),
+            batch_size: self.batch_size,
+            next_start: range.start_bound().cloned(),
+            end: range.end_bound().cloned(),
+            buffer: VecDeque::new(),
+            pending_read: None,
+            exhausted: false,
+        }
+    }
+}
+
+type ReadFuture<K, V> =
+    Pin<Box<dyn Future<Output = OwnedRwLockReadGuard<BPlusTreeMap<K, V>>> + Send>>;
+
+/// Stream of entries from [`AsyncBPlusTreeMap::range_stream`].
+pub struct RangeStream<K, V> {
+    tree: Arc<RwLock<BPlusTreeMap<K, V>>>,
+    batch_size: usize,
+    next_start: Bound<K>,
+    end: Bound<K>,
+    buffer: VecDeque<(K, V)>,
+    pending_read: Option<ReadFuture<K, V>>,
+    exhausted: bool,
+}
+
+// Nothing is pinned in place: the pending read is boxed and the buffered
+// entries are only ever moved out
+impl<K, V> Unpin for RangeStream<K, V> {}
+
+impl<K, V> RangeStream<K, V>
+where
+    K: TreeKey,
+    V: TreeValue,
+{
+    /// Copy the next batch out of the tree and move the start past it.
+    fn refill(&mut self, tree: &BPlusTreeMap<K, V>) {
+        let batch = tree
+            .range((self.next_start.as_ref(), self.end.as_ref()))
+            .take(self.batch_size)
+            .map(|(key, value)| (key.clone(), value.clone()));
+        self.buffer.extend(batch);
+        match self.buffer.back() {
+            Some((last, _)) if self.buffer.len() == self.batch_size => {
+                self.next_start = Bound::Excluded(last.clone());
+            }
+            _ => self.exhausted = true,
+        }
+    }
+}
+
+impl<K, V> Stream for RangeStream<K, V>
+where
+    K: TreeKey + Send + Sync + 'static,
+    V: TreeValue + Send + Sync + 'static,
+{
+    type Item = (K, V);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(entry) = this.buffer.pop_front() {
+                return Poll::Ready(Some(entry));
+            }
+            if this.exhausted {
+                return Poll::Ready(None);
+            }
+            let read = this
+                .pending_read
+                .get_or_insert_with(|| Box::pin(Arc::clone(&this.tree).read_owned()));
+            let guard = match read.as_mut().poll(cx) {
+                Poll::Ready(guard) => guard,
+                Poll::Pending => return Poll::Pending,
+            };
+            this.pending_read = None;
+            this.refill(&guard);
+        }
+    }
+}
+
+/// Yield to the executor once, so other tasks can run between batches.
+fn yield_now() -> impl Future<Output = ()> {
+    let mut yielded = false;
+    std::future::poll_fn(move |cx| {
+        if yielded {
+            Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    #[tokio::test]
+    async fn test_bulk_operations_and_point_access() {
+        let map = AsyncBPlusTreeMap::new(8).unwrap().with_batch_size(64);
+        assert_eq!(map.insert_all((0..1_000).map(|i| (i, i))).await, 1_000);
+        assert_eq!(map.insert_all([(5, 50), (2_000, 0)]).await, 1);
+        assert_eq!(map.len().await, 1_001);
+        assert_eq!(map.get(&5).await, Some(50));
+
+        let evens: Vec<i32> = (0..1_000).step_by(2).collect();
+        assert_eq!(map.remove_all(&evens).await, 500);
+        assert!(!map.contains_key(&4).await);
+        assert_eq!(map.remove(&5).await, Some(50));
+        assert_eq!(map.insert(5, 5).await, None);
+        assert!(map.read(|tree| tree.check_invariants()).await);
+        assert_eq!(map.write(|tree| tree.remove(&2_000)).await, Some(0));
+        assert_eq!(map.len().await, 500);
+    }
+
+    #[tokio::test]
+    async fn test_range_stream_matches_range_across_batches() {
+        let map = AsyncBPlusTreeMap::new(4).unwrap().with_batch_size(7);
+        map.insert_all((0..200).map(|i| (i, i * 3))).await;
+        for (start, end) in [(0, 200), (10, 11), (13, 63), (199, 500), (300, 400)] {
+            let streamed: Vec<_> = map.range_stream(start..end).collect().await;
+            let expected: Vec<_> = map
+                .read(|tree| tree.range(start..end).map(|(k, v)| (*k, *v)).collect())
+                .await;
+            assert_eq!(streamed, expected);
+        }
+        let all: Vec<_> = map.range_stream(..).collect().await;
+        assert_eq!(all.len(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_writers_run_between_stream_batches() {
+        let map = AsyncBPlusTreeMap::new(4).unwrap().with_batch_size(10);
+        map.insert_all((0..100).map(|i| (i, 0))).await;
+
+        let mut stream = map.range_stream(..);
+        assert_send(&stream);
+        let mut seen = Vec::new();
+        while let Some((key, value)) = stream.next().await {
+            seen.push((key, value));
+            if key == 15 {
+                // Not blocked by the stream, and visible to its later batches
+                map.insert(50, 1).await;
+            }
+        }
+        assert_eq!(seen.len(), 100);
+        assert_eq!(seen[50], (50, 1));
+    }
+
+    #[tokio::test]
+    async fn test_handles_share_one_tree() {
+        let map = AsyncBPlusTreeMap::new(4).unwrap();
+        let other = map.clone();
+        let task = tokio::spawn(async move { other.insert_all((0..100).map(|i| (i, i))).await });
+        assert_eq!(task.await.unwrap(), 100);
+        assert_eq!(map.len().await, 100);
+    }
+}
@@ -0,0 +1,1510 @@
+//! Batched write operations for BPlusTreeMap.
+//!
+//! Applying a large batch one operation at a time makes the tree split and
+//! merge the same nodes over and over. This module applies a whole batch of
+//! inserts and removals directly to the leaves, letting nodes overflow or
+//! underflow while the batch is in flight, and then restores the B+ tree
+//! invariants with a single bottom-up pass over the paths the batch touched.
+//! Bulk pops from either end of the tree and leaf coalescing reuse the same
+//! pass.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::error::{BPlusTreeError, CorruptionError, ModifyResult};
+use crate::invariant::invariant;
+use crate::node::split_off_slots;
+#[cfg(feature = "smallvec")]
+use crate::node::SplitOff;
+use crate::types::{
+    BPlusTreeMap, BranchNode, DeletionMode, LeafNode, NodeId, NodeRef, NodeVec, INLINE_ROOT,
+};
+use std::ops::{Bound, RangeBounds};
+
+/// A single operation recorded in a [`WriteBatch`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchOp<K, V> {
+    /// Insert or replace the value for a key.
+    Insert(K, V),
+    /// Remove a key if present.
+    Remove(K),
+}
+
+/// An ordered list of writes applied to a tree in one pass.
+///
+/// Operations are applied in the order they were added, so a later write to
+/// the same key wins exactly as it would with individual calls.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::{BPlusTreeMap, WriteBatch};
+///
+/// let mut tree = BPlusTreeMap::new(4).unwrap();
+/// tree.insert(1, "one");
+///
+/// let mut batch = WriteBatch::new();
+/// batch.insert(2, "two").insert(3, "three").remove(1);
+///
+/// let results = tree.apply_batch(batch);
+/// assert_eq!(results, vec![None, None, Some("one")]);
+/// assert_eq!(tree.keys().copied().collect::<Vec<_>>(), vec![2, 3]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteBatch<K, V> {
+    ops: Vec<BatchOp<K, V>>,
+}
+
+impl<K, V> BatchOp<K, V> {
+    /// Returns the key this operation applies to.
+    pub fn key(&self) -> &K {
+        match self {
+            BatchOp::Insert(key, _) | BatchOp::Remove(key) => key,
+        }
+    }
+}
+
+impl<K, V> WriteBatch<K, V> {
+    /// Create an empty batch.
+    pub fn new() -> Self {
+        Self { ops: Vec::new() }
+    }
+
+    /// Create an empty batch with room for `capacity` operations.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            ops: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Queue an insert of `key` with `value`.
+    pub fn insert(&mut self, key: K, value: V) -> &mut Self {
+        self.ops.push(BatchOp::Insert(key, value));
+        self
+    }
+
+    /// Queue a removal of `key`.
+    pub fn remove(&mut self, key: K) -> &mut Self {
+        self.ops.push(BatchOp::Remove(key));
+        self
+    }
+
+    /// Returns the number of queued operations.
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Returns true if no operations are queued.
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Returns the queued operations in application order.
+    pub fn ops(&self) -> &[BatchOp<K, V>] {
+        &self.ops
+    }
+}
+
+impl<K, V> Default for WriteBatch<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> FromIterator<BatchOp<K, V>> for WriteBatch<K, V> {
+    fn from_iter<I: IntoIterator<Item = BatchOp<K, V>>>(iter: I) -> Self {
+        Self {
+            ops: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// What [`merge_with_sorted_stream`](BPlusTreeMap::merge_with_sorted_stream)
+/// does with one key of the stream.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeAction<V> {
+    /// Leave the key as it is: present with its value, or absent.
+    Keep,
+    /// Insert the key, or replace its value.
+    Put(V),
+    /// Remove the key if present.
+    Remove,
+}
+
+/// Counts of the changes made by
+/// [`merge_with_sorted_stream`](BPlusTreeMap::merge_with_sorted_stream).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeStats {
+    /// Keys that were absent and are now present.
+    pub inserted: usize,
+    /// Keys whose value was replaced.
+    pub updated: usize,
+    /// Keys that were present and are now absent.
+    pub removed: usize,
+}
+
+/// Nodes touched by a batch, indexed by node ID; only these are visited by
+/// the fix pass.
+#[derive(Default)]
+struct DirtyNodes {
+    leaves: Vec<bool>,
+    branches: Vec<bool>,
+}
+
+impl DirtyNodes {
+    /// Flag index for `id`. The inline root shares index 0 with the first
+    /// arena leaf; a spurious mark only means one more node is checked.
+    #[inline]
+    fn index(id: NodeId) -> usize {
+        if id == INLINE_ROOT {
+            0
+        } else {
+            id as usize
+        }
+    }
+
+    #[inline]
+    fn mark(flags: &mut Vec<bool>, id: NodeId) {
+        let index = Self::index(id);
+        if index >= flags.len() {
+            flags.resize(index + 1, false);
+        }
+        flags[index] = true;
+    }
+
+    #[inline]
+    fn is_marked(flags: &[bool], id: NodeId) -> bool {
+        flags.get(Self::index(id)).copied().unwrap_or(false)
+    }
+}
+
+/// A leaf found by descending from the root, with the separators that bound
+/// its keys. Any key within the bounds belongs in the same leaf for as long
+/// as the branches are left alone, as they are until the fix pass.
+struct FencedLeaf<K> {
+    id: NodeId,
+    /// The branch holding the leaf and the leaf's index in it, or `None` if
+    /// the leaf is the root.
+    parent: Option<(NodeId, usize)>,
+    /// Inclusive lower bound, or `None` on the left edge of the tree.
+    lower: Option<K>,
+    /// Exclusive upper bound, or `None` on the right edge of the tree.
+    upper: Option<K>,
+}
+
+impl<K: Ord> FencedLeaf<K> {
+    fn contains(&self, key: &K) -> bool {
+        self.lower.as_ref().is_none_or(|lower| lower <= key)
+            && self.upper.as_ref().is_none_or(|upper| key < upper)
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Apply every operation in `batch` and rebalance once at the end.
+    ///
+    /// Returns one entry per operation: the previous value for an insert and
+    /// the removed value for a removal, matching what [`insert`] and
+    /// [`remove`] would have returned if called in order.
+    ///
+    /// While the batch is applied, leaves may temporarily hold more than
+    /// `capacity` keys or fewer than the minimum. A single bottom-up pass over
+    /// the touched paths then splits overfull nodes into evenly sized pieces
+    /// and borrows or merges underfull ones, instead of rebalancing after
+    /// every operation.
+    ///
+    /// [`insert`]: BPlusTreeMap::insert
+    /// [`remove`]: BPlusTreeMap::remove
+    pub fn apply_batch(&mut self, batch: WriteBatch<K, V>) -> Vec<Option<V>> {
+        let mut dirty = DirtyNodes::default();
+        let mut results: Vec<Option<V>> = (0..batch.len()).map(|_| None).collect();
+
+        // Operations on different keys commute, so apply them in key order for
+        // locality. The stable sort keeps same-key operations in batch order.
+        let mut ops: Vec<(usize, BatchOp<K, V>)> = batch.ops.into_iter().enumerate().collect();
+        ops.sort_by(|(_, a), (_, b)| a.key().cmp(b.key()));
+
+        for (position, op) in ops {
+            results[position] = match op {
+                BatchOp::Insert(key, value) => self.batch_leaf_insert(key, value, &mut dirty),
+                BatchOp::Remove(key) => self.batch_leaf_remove(&key, &mut dirty),
+            };
+        }
+
+        self.fix_batch_structure(&dirty);
+        results
+    }
+
+    /// Insert a batch of key-value pairs, validating the tree before and after.
+    ///
+    /// The items are applied through [`apply_batch`](BPlusTreeMap::apply_batch),
+    /// so the tree is rebalanced once for the whole batch.
+    ///
+    /// A batch is never partly applied. If the tree fails validation before
+    /// the batch, nothing is inserted and the tree is left as it was. If it
+    /// fails afterwards, every item has been inserted and none are rolled
+    /// back: the error reports a [`Corruption`](crate::CorruptionError), and
+    /// undoing writes on a tree whose invariants no longer hold could only
+    /// make it worse. Without the `validation` feature the checks are
+    /// compiled out and this never fails.
+    pub fn batch_insert(&mut self, items: Vec<(K, V)>) -> ModifyResult<Vec<Option<V>>> {
+        // Validate tree state before the batch
+        if let Err(e) = self.integrity_check() {
+            return Err(CorruptionError::DataIntegrity(e).into());
+        }
+
+        let batch: WriteBatch<K, V> = items
+            .into_iter()
+            .map(|(key, value)| BatchOp::Insert(key, value))
+            .collect();
+        let results = self.apply_batch(batch);
+
+        // Validate tree state after the batch
+        if let Err(e) = self.integrity_check() {
+            return Err(CorruptionError::DataIntegrity(e).into());
+        }
+
+        Ok(results)
+    }
+
+    /// Remove and return the `n` smallest entries in ascending key order.
+    ///
+    /// The entries are drained from the head leaves in one pass and the tree
+    /// is rebalanced once afterwards, which is much cheaper than `n`
+    /// individual removals. Returns fewer than `n` entries if the tree holds
+    /// fewer. In [`DeletionMode::DeferredRebalance`] the rebalance is skipped, as it is
+    /// for `remove`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..10 {
+    ///     tree.insert(i, i * 10);
+    /// }
+    ///
+    /// assert_eq!(tree.remove_min_k(3), vec![(0, 0), (1, 10), (2, 20)]);
+    /// assert_eq!(tree.first(), Some((&3, &30)));
+    /// ```
+    pub fn remove_min_k(&mut self, n: usize) -> Vec<(K, V)> {
+        let mut removed = Vec::with_capacity(n.min(self.len()));
+        if n == 0 {
+            return removed;
+        }
+        let mut dirty = DirtyNodes::default();
+        self.drain_front(self.root, n, &mut dirty, &mut removed);
+        self.finish_bulk_pop(&dirty);
+        removed
+    }
+
+    /// Remove and return the `n` largest entries in descending key order.
+    ///
+    /// This is the mirror image of [`remove_min_k`](BPlusTreeMap::remove_min_k):
+    /// entries come back largest first, as repeated removals of the last key
+    /// would return them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..10 {
+    ///     tree.insert(i, i * 10);
+    /// }
+    ///
+    /// assert_eq!(tree.remove_max_k(2), vec![(9, 90), (8, 80)]);
+    /// assert_eq!(tree.last(), Some((&7, &70)));
+    /// ```
+    pub fn remove_max_k(&mut self, n: usize) -> Vec<(K, V)> {
+        let mut removed = Vec::with_capacity(n.min(self.len()));
+        if n == 0 {
+            return removed;
+        }
+        let mut dirty = DirtyNodes::default();
+        self.drain_back(self.root, n, &mut dirty, &mut removed);
+        self.finish_bulk_pop(&dirty);
+        removed
+    }
+
+    /// Move every entry in `range` from this tree into `dest`, returning the
+    /// number of entries moved.
+    ///
+    /// The move is checked: if `dest` already holds a key in `range`, neither
+    /// tree is changed and an [`InvalidState`](crate::QueryError::InvalidState)
+    /// error is returned, so no value is ever overwritten. This is meant for
+    /// handing a key range from one shard's tree to another's.
+    ///
+    /// Entries move a leaf at a time. The leaves inside the range give up
+    /// their keys and values whole, and only the two leaves at the ends of
+    /// the range are split. In `dest` the moved leaves are linked in next to
+    /// the leaf whose keys they fall between. Each tree is then rebalanced
+    /// once, so the cost grows with the number of leaves moved rather than
+    /// with one search and insert per entry. Leaves are reallocated in
+    /// `dest`'s arena, since every tree owns its nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut left = BPlusTreeMap::new(4).unwrap();
+    /// let mut right = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..100 {
+    ///     left.insert(i, i);
+    /// }
+    /// right.insert(500, 500);
+    ///
+    /// assert_eq!(left.move_range(50..100, &mut right).unwrap(), 50);
+    /// assert_eq!(left.last(), Some((&49, &49)));
+    /// assert_eq!(right.first(), Some((&50, &50)));
+    ///
+    /// // Moving back over a key both trees hold is refused
+    /// left.insert(500, 0);
+    /// assert!(right.move_range(.., &mut left).is_err());
+    /// assert_eq!(right.len(), 51);
+    /// ```
+    pub fn move_range<R: RangeBounds<K>>(
+        &mut self,
+        range: R,
+        dest: &mut BPlusTreeMap<K, V>,
+    ) -> ModifyResult<usize> {
+        let bounds = (range.start_bound(), range.end_bound());
+        if dest.range(bounds).next().is_some() {
+            // Leave both trees as they were rather than overwrite any value
+            return Err(BPlusTreeError::invalid_state(
+                "move range",
+                "destination already holds keys in the range",
+            ));
+        }
+
+        let mut dirty = DirtyNodes::default();
+        let mut runs = Vec::new();
+        self.take_range_runs(self.root, &bounds, &mut dirty, &mut runs);
+        if runs.is_empty() {
+            return Ok(0);
+        }
+        self.fix_batch_structure(&dirty);
+
+        let moved = runs.iter().map(|(keys, _)| keys.len()).sum();
+        dest.graft_runs(runs);
+        Ok(moved)
+    }
+
+    /// Remove the entries in `range` for which `keep` returns false, and
+    /// return how many were removed.
+    ///
+    /// Only the range is scanned, so pruning one slice of a large tree costs
+    /// in proportion to the slice. The removals are then applied as a single
+    /// batch, rebalancing once rather than after each entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// // Orders keyed by day, with whether each was cancelled
+    /// let mut orders = BPlusTreeMap::new(4).unwrap();
+    /// for day in 0..30 {
+    ///     orders.insert(day, day % 3 == 0);
+    /// }
+    ///
+    /// // Drop last week's cancelled orders, leaving older ones alone
+    /// let removed = orders.retain_range(23..30, |_, &cancelled| !cancelled);
+    /// assert_eq!(removed, 2);
+    /// assert!(!orders.contains_key(&24) && !orders.contains_key(&27));
+    /// assert!(orders.contains_key(&21));
+    /// ```
+    pub fn retain_range<R, F>(&mut self, range: R, mut keep: F) -> usize
+    where
+        R: RangeBounds<K>,
+        F: FnMut(&K, &V) -> bool,
+    {
+        let removals: WriteBatch<K, V> = self
+            .range(range)
+            .filter(|(key, value)| !keep(key, value))
+            .map(|(key, _)| BatchOp::Remove(key.clone()))
+            .collect();
+        let removed = removals.len();
+        if removed > 0 {
+            self.apply_batch(removals);
+        }
+        removed
+    }
+
+    /// Apply a stream of changes, in ascending key order, against the tree
+    /// in one pass, letting `merge` decide what happens to each key.
+    ///
+    /// `merge` is called with each key, its current value if present and the
+    /// incoming item from the stream, and returns a [`MergeAction`]. This is
+    /// the apply step of a sorted changelog, such as one read from a file or
+    /// received from a replication source.
+    ///
+    /// The tree is searched from the root only when the stream moves past
+    /// the leaf it is on, and, as with [`apply_batch`], it is rebalanced once
+    /// at the end. Out-of-order keys are still applied correctly, with a
+    /// search from the root each time the order breaks. A key that appears
+    /// more than once sees the result of its earlier occurrences.
+    ///
+    /// [`apply_batch`]: BPlusTreeMap::apply_batch
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::{BPlusTreeMap, MergeAction, MergeStats};
+    ///
+    /// let mut stock = BPlusTreeMap::new(4).unwrap();
+    /// for item in 0..10 {
+    ///     stock.insert(item, 5);
+    /// }
+    ///
+    /// // Changes in stock level; anything that runs out is dropped
+    /// let changes = [(2, -5), (3, 1), (12, 4), (13, -1)];
+    /// let stats = stock.merge_with_sorted_stream(changes, |_, current, delta| {
+    ///     match current.copied().unwrap_or(0) + delta {
+    ///         level if level > 0 => MergeAction::Put(level),
+    ///         _ => MergeAction::Remove,
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(stats, MergeStats { inserted: 1, updated: 1, removed: 1 });
+    /// assert_eq!(stock.get(&2), None);
+    /// assert_eq!(stock.get(&3), Some(&6));
+    /// assert_eq!(stock.get(&12), Some(&4));
+    /// assert!(!stock.contains_key(&13));
+    /// ```
+    pub fn merge_with_sorted_stream<W, I, F>(&mut self, stream: I, mut merge: F) -> MergeStats
+    where
+        I: IntoIterator<Item = (K, W)>,
+        F: FnMut(&K, Option<&V>, W) -> MergeAction<V>,
+    {
+        let mut dirty = DirtyNodes::default();
+        let mut stats = MergeStats::default();
+        let mut current: Option<FencedLeaf<K>> = None;
+
+        for (key, incoming) in stream {
+            if !current.as_ref().is_some_and(|leaf| leaf.contains(&key)) {
+                current = self.batch_descend_fenced(&key, &mut dirty);
+            }
+            let Some(leaf_id) = current.as_ref().map(|leaf| leaf.id) else {
+                continue;
+            };
+            let Some(leaf) = self.get_leaf(leaf_id) else {
+                continue;
+            };
+            let found = leaf.binary_search_keys(&key);
+            let existing = found.ok().and_then(|index| leaf.get_value(index));
+            let action = merge(&key, existing, incoming);
+
+            let Some(leaf) = self.get_leaf_mut(leaf_id) else {
+                continue;
+            };
+            match (action, found) {
+                (MergeAction::Keep, _) | (MergeAction::Remove, Err(_)) => {}
+                (MergeAction::Put(value), Ok(index)) => {
+                    if let Some(slot) = leaf.get_value_mut(index) {
+                        *slot = value;
+                        stats.updated += 1;
+                    }
+                }
+                (MergeAction::Put(value), Err(index)) => {
+                    leaf.insert_at_index(index, key, value);
+                    stats.inserted += 1;
+                }
+                (MergeAction::Remove, Ok(index)) => {
+                    if leaf.remove_at(index).is_some() {
+                        stats.removed += 1;
+                    }
+                }
+            }
+        }
+
+        self.fix_batch_structure(&dirty);
+        stats
+    }
+
+    /// Merge neighbouring leaves whose entries fit together in one leaf.
+    ///
+    /// Churn, and deferred rebalancing in particular, can leave many leaves holding
+    /// only a few keys each, and a scan pays for a leaf hop per handful of
+    /// items. This pass joins runs of sibling leaves while their combined size
+    /// stays within capacity, then rebalances branches that lost children.
+    /// Returns the number of leaves released.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::{BPlusTreeMap, DeletionMode};
+    ///
+    /// let mut tree = BPlusTreeMap::new(16).unwrap();
+    /// for i in 0..1_000 {
+    ///     tree.insert(i, i);
+    /// }
+    /// tree.set_deletion_mode(DeletionMode::DeferredRebalance);
+    /// for i in (0..1_000).filter(|i| i % 10 != 0) {
+    ///     tree.remove(&i);
+    /// }
+    ///
+    /// let before = tree.leaf_count();
+    /// let released = tree.coalesce_leaves();
+    /// assert_eq!(tree.leaf_count(), before - released);
+    /// assert!(tree.leaf_count() < before / 4);
+    /// # #[cfg(feature = "validation")]
+    /// assert!(tree.check_invariants());
+    /// ```
+    pub fn coalesce_leaves(&mut self) -> usize {
+        self.coalesce_leaves_in(..)
+    }
+
+    /// Merge neighbouring leaves, like [`coalesce_leaves`](Self::coalesce_leaves),
+    /// visiting only the part of the tree that overlaps `range`.
+    ///
+    /// The work is proportional to the size of the range rather than the
+    /// tree, so it can follow a scan over a hot range to keep that range
+    /// dense without a full maintenance pass.
+    pub fn coalesce_leaves_in<R: RangeBounds<K>>(&mut self, range: R) -> usize {
+        let before = self.leaf_arena.len();
+
+        // Rebalancing branches can bring newly mergeable leaves under one
+        // parent, so repeat until a pass releases nothing
+        loop {
+            let pass_start = self.leaf_arena.len();
+            let root_id = match self.root {
+                NodeRef::Branch(id, _) => id,
+                NodeRef::Leaf(_, _) => break,
+            };
+            if !self.coalesce_subtree(root_id, &range) {
+                break;
+            }
+            self.collapse_root_if_needed();
+            if self.leaf_arena.len() == pass_start {
+                break;
+            }
+        }
+        before - self.leaf_arena.len()
+    }
+
+    // ============================================================================
+    // BULK POP HELPERS
+    // ============================================================================
+
+    /// Move entries from the front of the subtree into `out` until it holds
+    /// `n` entries, marking every node touched.
+    fn drain_front(
+        &mut self,
+        node: NodeRef<K, V>,
+        n: usize,
+        dirty: &mut DirtyNodes,
+        out: &mut Vec<(K, V)>,
+    ) {
+        match node {
+            NodeRef::Leaf(id, _) => {
+                DirtyNodes::mark(&mut dirty.leaves, id);
+                if let Some(leaf) = self.get_leaf_mut(id) {
+                    let take = (n - out.len()).min(leaf.keys.len());
+                    out.extend(leaf.keys.drain(..take).zip(leaf.values.drain(..take)));
+                }
+            }
+            NodeRef::Branch(id, _) => {
+                DirtyNodes::mark(&mut dirty.branches, id);
+                let mut index = 0;
+                while out.len() < n {
+                    let child = match self.get_branch(id).and_then(|branch| branch.child(index)) {
+                        Some(child) => child,
+                        None => return,
+                    };
+                    self.drain_front(child, n, dirty, out);
+                    index += 1;
+                }
+            }
+        }
+    }
+
+    /// Move entries from the back of the subtree into `out`, largest first,
+    /// until it holds `n` entries, marking every node touched.
+    fn drain_back(
+        &mut self,
+        node: NodeRef<K, V>,
+        n: usize,
+        dirty: &mut DirtyNodes,
+        out: &mut Vec<(K, V)>,
+    ) {
+        match node {
+            NodeRef::Leaf(id, _) => {
+                DirtyNodes::mark(&mut dirty.leaves, id);
+                if let Some(leaf) = self.get_leaf_mut(id) {
+                    let at = leaf.keys.len() - (n - out.len()).min(leaf.keys.len());
+                    let keys = leaf.keys.split_off(at);
+                    let values = leaf.values.split_off(at);
+                    out.extend(keys.into_iter().zip(values).rev());
+                }
+            }
+            NodeRef::Branch(id, _) => {
+                DirtyNodes::mark(&mut dirty.branches, id);
+                let mut index = match self.get_branch(id) {
+                    Some(branch) => branch.child_count(),
+                    None => return,
+                };
+                while out.len() < n && index > 0 {
+                    index -= 1;
+                    let child = match self.get_branch(id).and_then(|branch| branch.child(index)) {
+                        Some(child) => child,
+                        None => return,
+                    };
+                    self.drain_back(child, n, dirty, out);
+                }
+            }
+        }
+    }
+
+    /// Rebalance after a bulk pop unless deletions are deferred.
+    fn finish_bulk_pop(&mut self, dirty: &DirtyNodes) {
+        if self.deletion_mode == DeletionMode::Eager {
+            self.fix_batch_structure(dirty);
+        }
+    }
+
+    // ============================================================================
+    // BATCH APPLICATION HELPERS
+    // ============================================================================
+
+    /// Descend to the leaf for `key`, recording every branch on the way.
+    fn batch_descend(&self, key: &K, dirty: &mut DirtyNodes) -> Option<NodeId> {
+        let mut current = self.root;
+        loop {
+            match current {
+                NodeRef::Leaf(id, _) => {
+                    DirtyNodes::mark(&mut dirty.leaves, id);
+                    return Some(id);
+                }
+                NodeRef::Branch(id, _) => {
+                    DirtyNodes::mark(&mut dirty.branches, id);
+                    let branch = self.get_branch(id)?;
+                    current = branch.get_child(key)?;
+                }
+            }
+        }
+    }
+
+    /// Descend to the leaf for `key` like `batch_descend`, also collecting
+    /// the separators that bound the leaf.
+    fn batch_descend_fenced(&self, key: &K, dirty: &mut DirtyNodes) -> Option<FencedLeaf<K>> {
+        let mut current = self.root;
+        let mut lower = None;
+        let mut upper = None;
+        let mut parent = None;
+        loop {
+            match current {
+                NodeRef::Leaf(id, _) => {
+                    DirtyNodes::mark(&mut dirty.leaves, id);
+                    return Some(FencedLeaf {
+                        id,
+                        parent,
+                        lower,
+                        upper,
+                    });
+                }
+                NodeRef::Branch(id, _) => {
+                    DirtyNodes::mark(&mut dirty.branches, id);
+                    let branch = self.get_branch(id)?;
+                    let index = branch.find_child_index(key);
+                    // Deeper separators are always at least as tight
+                    if let Some(separator) = index.checked_sub(1).and_then(|i| branch.keys.get(i)) {
+                        lower = Some(separator.clone());
+                    }
+                    if let Some(separator) = branch.keys.get(index) {
+                        upper = Some(separator.clone());
+                    }
+                    current = branch.child(index)?;
+                    parent = Some((id, index));
+                }
+            }
+        }
+    }
+
+    // ============================================================================
+    // RANGE MOVE HELPERS
+    // ============================================================================
+
+    /// Take the entries in `range` out of the subtree and append them to
+    /// `runs` in key order, each leaf's share as one run. A leaf that lies
+    /// entirely inside the range gives up its vectors whole. Leaves stay in
+    /// place, possibly empty, for the fix pass to merge away.
+    fn take_range_runs<R: RangeBounds<K>>(
+        &mut self,
+        node: NodeRef<K, V>,
+        range: &R,
+        dirty: &mut DirtyNodes,
+        runs: &mut Vec<(NodeVec<K>, NodeVec<V>)>,
+    ) {
+        match node {
+            NodeRef::Leaf(id, _) => {
+                let Some(leaf) = self.get_leaf_mut(id) else {
+                    return;
+                };
+                let start = match range.start_bound() {
+                    Bound::Included(start) => leaf.keys.partition_point(|key| key < start),
+                    Bound::Excluded(start) => leaf.keys.partition_point(|key| key <= start),
+                    Bound::Unbounded => 0,
+                };
+                let end = match range.end_bound() {
+                    Bound::Included(end) => leaf.keys.partition_point(|key| key <= end),
+                    Bound::Excluded(end) => leaf.keys.partition_point(|key| key < end),
+                    Bound::Unbounded => leaf.keys.len(),
+                };
+                if start >= end {
+                    return;
+                }
+                if start == 0 && end == leaf.keys.len() {
+                    runs.push((leaf.take_keys(), leaf.take_values()));
+                } else {
+                    runs.push((
+                        leaf.keys.drain(start..end).collect(),
+                        leaf.values.drain(start..end).collect(),
+                    ));
+                }
+                DirtyNodes::mark(&mut dirty.leaves, id);
+            }
+            NodeRef::Branch(id, _) => {
+                DirtyNodes::mark(&mut dirty.branches, id);
+                let mut index = 0;
+                while let Some(branch) = self.get_branch(id) {
+                    let Some(child) = branch.child(index) else {
+                        break;
+                    };
+                    if child_overlaps(&branch.keys, index, range) {
+                        self.take_range_runs(child, range, dirty, runs);
+                    }
+                    index += 1;
+                }
+            }
+        }
+    }
+
+    /// Add runs of entries, in ascending key order and sharing no key with
+    /// the tree, as new leaves next to the leaves they fall between, then
+    /// rebalance once.
+    fn graft_runs(&mut self, runs: Vec<(NodeVec<K>, NodeVec<V>)>) {
+        // Give a root leaf a parent, so new leaves always join a branch
+        if let NodeRef::Leaf(..) = self.root {
+            self.spill_inline_root();
+            let root_id = self.allocate_branch(BranchNode::from_parts(
+                self.capacity,
+                NodeVec::new(),
+                std::iter::once(self.root.id()).collect(),
+                true,
+            ));
+            self.root = NodeRef::branch(root_id);
+        }
+
+        let mut dirty = DirtyNodes::default();
+        // Reversed, so the next run is popped off the end
+        let mut pending: Vec<_> = runs
+            .into_iter()
+            .rev()
+            .filter(|(keys, _)| !keys.is_empty())
+            .collect();
+        while let Some(first) = pending.last().and_then(|(keys, _)| keys.first()).cloned() {
+            let Some(FencedLeaf {
+                id,
+                parent: Some(parent),
+                upper,
+                ..
+            }) = self.batch_descend_fenced(&first, &mut dirty)
+            else {
+                break;
+            };
+
+            // Everything below the leaf's upper separator goes beside it
+            let mut group = Vec::new();
+            while let Some((mut keys, mut values)) = pending.pop() {
+                let fits = upper
+                    .as_ref()
+                    .map_or(keys.len(), |upper| keys.partition_point(|key| key < upper));
+                if fits == keys.len() {
+                    group.push((keys, values));
+                    continue;
+                }
+                if fits > 0 {
+                    let rest_keys = split_off_slots(&mut keys, fits, self.capacity);
+                    let rest_values = split_off_slots(&mut values, fits, self.capacity);
+                    group.push((keys, values));
+                    pending.push((rest_keys, rest_values));
+                } else {
+                    pending.push((keys, values));
+                }
+                break;
+            }
+            if group.is_empty() {
+                break;
+            }
+            self.graft_beside(id, parent, &first, group, &mut dirty);
+        }
+
+        self.fix_batch_structure(&dirty);
+    }
+
+    /// Split leaf `leaf_id` before `first` and link the leaves of `group`
+    /// in between the two halves, as children of the leaf's parent.
+    fn graft_beside(
+        &mut self,
+        leaf_id: NodeId,
+        (parent_id, child_index): (NodeId, usize),
+        first: &K,
+        mut group: Vec<(NodeVec<K>, NodeVec<V>)>,
+        dirty: &mut DirtyNodes,
+    ) {
+        let capacity = self.capacity;
+        let Some(leaf) = self.get_leaf_mut(leaf_id) else {
+            return;
+        };
+        let at = leaf.keys.partition_point(|key| key < first);
+        let tail_keys = split_off_slots(&mut leaf.keys, at, capacity);
+        let tail_values = split_off_slots(&mut leaf.values, at, capacity);
+        if !tail_keys.is_empty() {
+            group.push((tail_keys, tail_values));
+        }
+
+        // Allocate right to left so each leaf knows its successor
+        let mut next = leaf.next;
+        let mut separators = NodeVec::with_capacity(group.len());
+        let mut child_ids = NodeVec::with_capacity(group.len());
+        for (keys, values) in group.into_iter().rev() {
+            let Some(separator) = keys.first().cloned() else {
+                continue;
+            };
+            next = self.allocate_leaf_with_data(capacity, keys, values, next);
+            DirtyNodes::mark(&mut dirty.leaves, next);
+            separators.push(separator);
+            child_ids.push(next);
+        }
+        separators.reverse();
+        child_ids.reverse();
+        if let Some(leaf) = self.get_leaf_mut(leaf_id) {
+            leaf.next = next;
+        }
+        let mut left_id = leaf_id;
+        for &right_id in child_ids.iter() {
+            self.report_leaf_split(left_id, right_id);
+            left_id = right_id;
+        }
+
+        if let Some(branch) = self.get_branch_mut(parent_id) {
+            let keys_after = split_off_slots(&mut branch.keys, child_index, capacity);
+            branch.keys.extend(separators);
+            branch.keys.extend(keys_after);
+            let children_after = split_off_slots(&mut branch.child_ids, child_index + 1, capacity);
+            branch.child_ids.extend(child_ids);
+            branch.child_ids.extend(children_after);
+        }
+    }
+
+    /// Insert into the target leaf without splitting it.
+    fn batch_leaf_insert(&mut self, key: K, value: V, dirty: &mut DirtyNodes) -> Option<V> {
+        let leaf_id = self.batch_descend(&key, dirty)?;
+        let leaf = self.get_leaf_mut(leaf_id)?;
+        match leaf.binary_search_keys(&key) {
+            Ok(index) => leaf
+                .get_value_mut(index)
+                .map(|slot| std::mem::replace(slot, value)),
+            Err(index) => {
+                leaf.insert_at_index(index, key, value);
+                None
+            }
+        }
+    }
+
+    /// Remove from the target leaf without rebalancing it.
+    fn batch_leaf_remove(&mut self, key: &K, dirty: &mut DirtyNodes) -> Option<V> {
+        let leaf_id = self.batch_descend(key, dirty)?;
+        let (removed, _is_underfull) = self.get_leaf_mut(leaf_id)?.remove(key);
+        removed
+    }
+
+    // ============================================================================
+    // COALESCING HELPERS
+    // ============================================================================
+
+    /// Merge sibling leaves within the part of a subtree that overlaps
+    /// `range`, then fix any children left underfull. Returns true if the
+    /// subtree changed.
+    fn coalesce_subtree<R: RangeBounds<K>>(&mut self, branch_id: NodeId, range: &R) -> bool {
+        let mut changed = false;
+        let mut index = 0;
+        while let Some(branch) = self.get_branch(branch_id) {
+            let Some(child) = branch.child(index) else {
+                break;
+            };
+            let right = branch.child(index + 1);
+            let overlaps = child_overlaps(&branch.keys, index, range)
+                || child_overlaps(&branch.keys, index + 1, range);
+
+            match (child, right) {
+                (NodeRef::Branch(child_id, _), _) => {
+                    if overlaps {
+                        changed |= self.coalesce_subtree(child_id, range);
+                    }
+                    index += 1;
+                }
+                (NodeRef::Leaf(child_id, _), Some(NodeRef::Leaf(right_id, _))) if overlaps => {
+                    let fits = match (self.get_leaf(child_id), self.get_leaf(right_id)) {
+                        (Some(child), Some(right)) => {
+                            child.keys.len() + right.keys.len() <= self.capacity
+                        }
+                        _ => false,
+                    };
+                    if fits {
+                        // Stay on this leaf so it can absorb the next neighbour too
+                        self.merge_with_right::<LeafNode<K, V>>(
+                            branch_id, index, child_id, right_id,
+                        );
+                        changed = true;
+                    } else {
+                        index += 1;
+                    }
+                }
+                _ => index += 1,
+            }
+        }
+
+        if changed {
+            self.fix_underfull_children(branch_id);
+        }
+        changed
+    }
+
+    // ============================================================================
+    // FIX PASS
+    // ============================================================================
+
+    /// Run the fix pass over every node in the tree.
+    pub(crate) fn rebalance_whole_tree(&mut self) {
+        let mut dirty = DirtyNodes::default();
+        let mut stack = vec![self.root];
+        while let Some(node) = stack.pop() {
+            match node {
+                NodeRef::Leaf(id, _) => DirtyNodes::mark(&mut dirty.leaves, id),
+                NodeRef::Branch(id, _) => {
+                    DirtyNodes::mark(&mut dirty.branches, id);
+                    if let Some(branch) = self.get_branch(id) {
+                        stack.extend(branch.children());
+                    }
+                }
+            }
+        }
+        self.fix_batch_structure(&dirty);
+    }
+
+    /// Restore size invariants on every node touched by the batch.
+    fn fix_batch_structure(&mut self, dirty: &DirtyNodes) {
+        let mut siblings = self.fix_batch_subtree(self.root, dirty);
+
+        // The root overflowed: grow the tree until a single root remains
+        if !siblings.is_empty() {
+            self.spill_inline_root();
+        }
+        while !siblings.is_empty() {
+            let mut new_root = BranchNode::new(self.capacity);
+            new_root.push_child(self.root);
+            for (separator, node) in siblings {
+                new_root.keys.push(separator);
+                new_root.push_child(node);
+            }
+            let root_id = self.allocate_branch(new_root);
+            self.root = NodeRef::branch(root_id);
+            siblings = self.split_overfull_branch(root_id);
+        }
+
+        self.collapse_root_if_needed();
+    }
+
+    /// Fix a subtree in post-order. Returns the new right siblings (with their
+    /// separator keys) that the caller must insert after `node`.
+    fn fix_batch_subtree(
+        &mut self,
+        node: NodeRef<K, V>,
+        dirty: &DirtyNodes,
+    ) -> Vec<(K, NodeRef<K, V>)> {
+        match node {
+            NodeRef::Leaf(id, _) => {
+                if DirtyNodes::is_marked(&dirty.leaves, id) {
+                    self.split_overfull_leaf(id)
+                } else {
+                    Vec::new()
+                }
+            }
+            NodeRef::Branch(id, _) => {
+                if !DirtyNodes::is_marked(&dirty.branches, id) {
+                    return Vec::new();
+                }
+                let children: Vec<_> = match self.get_branch(id) {
+                    Some(branch) => branch.children().collect(),
+                    None => return Vec::new(),
+                };
+
+                // Walk right to left so inserted siblings don't shift pending indices
+                for (index, child) in children.into_iter().enumerate().rev() {
+                    let new_siblings = self.fix_batch_subtree(child, dirty);
+                    if new_siblings.is_empty() {
+                        continue;
+                    }
+                    if let Some(branch) = self.get_branch_mut(id) {
+                        for (offset, (separator, sibling)) in new_siblings.into_iter().enumerate() {
+                            branch.keys.insert(index + offset, separator);
+                            branch.insert_child(index + offset + 1, sibling);
+                        }
+                    }
+                }
+
+                self.fix_underfull_children(id);
+                self.split_overfull_branch(id)
+            }
+        }
+    }
+
+    /// Borrow or merge until no child of `branch_id` is underfull.
+    fn fix_underfull_children(&mut self, branch_id: NodeId) {
+        let mut index = 0;
+        loop {
+            let (child, child_count) = match self.get_branch(branch_id) {
+                Some(branch) if branch.child_count() > 1 => match branch.child(index) {
+                    Some(child) => (child, branch.child_count()),
+                    None => return,
+                },
+                _ => return,
+            };
+            if !self.is_node_underfull(&child) {
+                index += 1;
+                continue;
+            }
+
+            let before = self.node_key_count(&child);
+            let child_still_exists = self.rebalance_child(branch_id, index);
+            let after_count = self
+                .get_branch(branch_id)
+                .map_or(child_count, |branch| branch.child_count());
+
+            let merged_left = !child_still_exists && after_count < child_count;
+            if merged_left {
+                // Merged into the left sibling; re-check the merged node
+                index = index.saturating_sub(1);
+            } else if after_count == child_count && self.node_key_count(&child) == before {
+                // No progress possible for this child; leave it for the parent
+                index += 1;
+                continue;
+            }
+
+            // A merged or refilled branch may have inherited an underfull child
+            // that had no sibling to rebalance with before
+            let resulting = self
+                .get_branch(branch_id)
+                .and_then(|branch| branch.child(index));
+            if let Some(NodeRef::Branch(resulting_id, _)) = resulting {
+                self.fix_underfull_children(resulting_id);
+            }
+        }
+    }
+
+    /// Number of keys held by a node.
+    fn node_key_count(&self, node: &NodeRef<K, V>) -> usize {
+        match node {
+            NodeRef::Leaf(id, _) => self.get_leaf(*id).map_or(0, |leaf| leaf.keys.len()),
+            NodeRef::Branch(id, _) => self.get_branch(*id).map_or(0, |branch| branch.keys.len()),
+        }
+    }
+
+    /// Split an overfull leaf into evenly sized leaves, returning the new
+    /// right siblings and their separators in key order.
+    fn split_overfull_leaf(&mut self, leaf_id: NodeId) -> Vec<(K, NodeRef<K, V>)> {
+        let (mut keys, mut values, next, capacity) = match self.get_leaf_mut(leaf_id) {
+            Some(leaf) if leaf.keys.len() > leaf.capacity => (
+                leaf.take_keys(),
+                leaf.take_values(),
+                leaf.next,
+                leaf.capacity,
+            ),
+            _ => return Vec::new(),
+        };
+
+        let sizes = even_chunk_sizes(keys.len(), keys.len().div_ceil(capacity));
+        let mut siblings = Vec::with_capacity(sizes.len() - 1);
+        let mut next_id = next;
+
+        // Carve pieces off the tail so each new leaf can point at the previous one
+        for &size in sizes[1..].iter().rev() {
+            let at = keys.len() - size;
+            let right_keys = split_off_slots(&mut keys, at, capacity);
+            let right_values = split_off_slots(&mut values, at, capacity);
+            let separator = right_keys[0].clone();
+            next_id = self.allocate_leaf_with_data(capacity, right_keys, right_values, next_id);
+            siblings.push((separator, NodeRef::leaf(next_id)));
+        }
+        siblings.reverse();
+
+        if let Some(leaf) = self.get_leaf_mut(leaf_id) {
+            leaf.keys = keys;
+            leaf.values = values;
+            leaf.next = next_id;
+            leaf.shrink_excess();
+        }
+        let mut left_id = leaf_id;
+        for (_, right) in &siblings {
+            self.report_leaf_split(left_id, right.id());
+            left_id = right.id();
+        }
+        siblings
+    }
+
+    /// Split an overfull branch into evenly sized branches, returning the new
+    /// right siblings and their promoted separators in key order.
+    fn split_overfull_branch(&mut self, branch_id: NodeId) -> Vec<(K, NodeRef<K, V>)> {
+        let (mut keys, mut children, capacity, leaves) = match self.get_branch_mut(branch_id) {
+            Some(branch) if branch.keys.len() > branch.capacity => (
+                std::mem::take(&mut branch.keys),
+                std::mem::take(&mut branch.child_ids),
+                branch.capacity,
+                branch.children_are_leaves,
+            ),
+            _ => return Vec::new(),
+        };
+
+        let sizes = even_chunk_sizes(children.len(), children.len().div_ceil(capacity + 1));
+        let mut siblings = Vec::with_capacity(sizes.len() - 1);
+
+        for &size in sizes[1..].iter().rev() {
+            let at = children.len() - size;
+            let right_children = split_off_slots(&mut children, at, capacity + 2);
+            let right_keys = split_off_slots(&mut keys, at, capacity + 1);
+            // The key between the two halves moves up to the parent
+            let promoted = invariant(keys.pop(), "branch split needs a separator");
+            let new_id = self.allocate_branch(BranchNode::from_parts(
+                capacity,
+                right_keys,
+                right_children,
+                leaves,
+            ));
+            siblings.push((promoted, NodeRef::branch(new_id)));
+        }
+        siblings.reverse();
+
+        if let Some(branch) = self.get_branch_mut(branch_id) {
+            branch.keys = keys;
+            branch.child_ids = children;
+            branch.shrink_excess();
+        }
+        siblings
+    }
+}
+
+/// Whether child `index` of a branch with separator `keys` may hold keys in
+/// `range`. The child holds keys from `keys[index - 1]` up to, but not
+/// including, `keys[index]`.
+fn child_overlaps<K: Ord, R: RangeBounds<K>>(keys: &[K], index: usize, range: &R) -> bool {
+    let below_start = match (keys.get(index), range.start_bound()) {
+        (Some(upper), Bound::Included(start) | Bound::Excluded(start)) => upper <= start,
+        _ => false,
+    };
+    let above_end = match (
+        index.checked_sub(1).and_then(|i| keys.get(i)),
+        range.end_bound(),
+    ) {
+        (Some(lower), Bound::Included(end)) => lower > end,
+        (Some(lower), Bound::Excluded(end)) => lower >= end,
+        _ => false,
+    };
+    index <= keys.len() && !below_start && !above_end
+}
+
+/// Split `total` items into `parts` sizes that differ by at most one.
+pub(crate) fn even_chunk_sizes(total: usize, parts: usize) -> Vec<usize> {
+    let parts = parts.max(1);
+    let base = total / parts;
+    let extra = total % parts;
+    (0..parts).map(|i| base + usize::from(i < extra)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_even_chunk_sizes() {
+        assert_eq!(even_chunk_sizes(9, 2), vec![5, 4]);
+        assert_eq!(even_chunk_sizes(12, 3), vec![4, 4, 4]);
+        assert_eq!(even_chunk_sizes(3, 1), vec![3]);
+    }
+
+    #[test]
+    fn test_failed_batch_insert_leaves_tree_unchanged() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..20 {
+            tree.insert(i * 2, i);
+        }
+        let before: Vec<(i32, i32)> = tree.items().map(|(k, v)| (*k, *v)).collect();
+
+        // Swap two keys in the first leaf so that validation fails
+        let first = tree.get_first_leaf_id().unwrap();
+        tree.get_leaf_mut(first).unwrap().keys.swap(0, 1);
+        let reported = tree.check_invariants_detailed().unwrap_err();
+
+        let result = tree.batch_insert((0..10).map(|i| (i * 2 + 1, -i)).collect());
+        assert!(result.unwrap_err().is_corruption());
+        assert_eq!(tree.check_invariants_detailed(), Err(reported));
+        assert_eq!(tree.len(), before.len());
+
+        // Undoing the damage gives back exactly the tree from before the batch
+        tree.get_leaf_mut(first).unwrap().keys.swap(0, 1);
+        assert_eq!(tree.check_invariants_detailed(), Ok(()));
+        assert!(tree.items().map(|(k, v)| (*k, *v)).eq(before));
+    }
+
+    #[test]
+    fn test_apply_batch_into_empty_tree() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        let mut batch = WriteBatch::new();
+        for i in 0..100 {
+            batch.insert(i, i * 10);
+        }
+
+        let results = tree.apply_batch(batch);
+        assert!(results.iter().all(Option::is_none));
+        assert_eq!(tree.len(), 100);
+        assert!(tree.check_invariants_detailed().is_ok());
+        assert_eq!(tree.get(&42), Some(&420));
+    }
+
+    #[test]
+    fn test_apply_batch_drains_tree() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..100 {
+            tree.insert(i, i);
+        }
+
+        let batch: WriteBatch<i32, i32> = (0..100).map(BatchOp::Remove).collect();
+        let results = tree.apply_batch(batch);
+        assert_eq!(results.iter().filter(|r| r.is_some()).count(), 100);
+        assert!(tree.is_empty());
+        assert!(tree.is_leaf_root());
+        assert!(tree.check_invariants_detailed().is_ok());
+    }
+
+    #[test]
+    fn test_move_range_between_shards() {
+        let mut source = BPlusTreeMap::new(4).unwrap();
+        let mut dest = BPlusTreeMap::new(5).unwrap();
+        for i in 0..1_000 {
+            source.insert(i, i * 2);
+        }
+        for i in 2_000..2_300 {
+            dest.insert(i, i);
+        }
+
+        assert_eq!(source.move_range(200..700, &mut dest).unwrap(), 500);
+        assert_eq!(source.len(), 500);
+        assert_eq!(dest.len(), 800);
+        assert!(source.range(200..700).next().is_none());
+        assert!(dest
+            .range(200..700)
+            .map(|(k, v)| (*k, *v))
+            .eq((200..700).map(|i| (i, i * 2))));
+        assert!(source.check_invariants_detailed().is_ok());
+        assert!(dest.check_invariants_detailed().is_ok());
+
+        // Ranges move back and forth as long as they don't meet a held key
+        assert_eq!(source.move_range(..200, &mut dest).unwrap(), 200);
+        assert_eq!(dest.move_range(..700, &mut source).unwrap(), 700);
+        assert_eq!(dest.move_range(2_000.., &mut source).unwrap(), 300);
+        assert!(dest.is_empty());
+        assert_eq!(source.len(), 1_300);
+        assert!(source.check_invariants_detailed().is_ok());
+        assert_eq!(source.move_range(5_000.., &mut dest).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_move_range_refuses_overlap() {
+        let mut source = BPlusTreeMap::new(4).unwrap();
+        let mut dest = BPlusTreeMap::new(4).unwrap();
+        for i in 0..100 {
+            source.insert(i, i);
+        }
+        dest.insert(60, 0);
+
+        let error = source.move_range(50..70, &mut dest).unwrap_err();
+        assert!(matches!(
+            error,
+            BPlusTreeError::Query(crate::QueryError::InvalidState(_))
+        ));
+        assert_eq!(source.len(), 100);
+        assert_eq!(dest.len(), 1);
+        assert_eq!(dest.get(&60), Some(&0));
+
+        // Either side of the clash still moves
+        assert_eq!(source.move_range(61..70, &mut dest).unwrap(), 9);
+        assert_eq!(dest.len(), 10);
+    }
+
+    #[test]
+    fn test_move_range_matches_model() {
+        let mut rng = SeededRng::new(31);
+        for (source_capacity, dest_capacity) in [(4, 4), (4, 16), (16, 5), (7, 4)] {
+            let mut trees = [
+                BPlusTreeMap::new(source_capacity).unwrap(),
+                BPlusTreeMap::new(dest_capacity).unwrap(),
+            ];
+            let mut models = [BTreeMap::new(), BTreeMap::new()];
+            // Each tree starts with alternate bands of 2,000 keys
+            for i in 0..8_000u32 {
+                let key = rng.below(20_000) as u32;
+                let side = (key / 2_000 % 2) as usize;
+                trees[side].insert(key, i);
+                models[side].insert(key, i);
+            }
+            // Removals without rebalancing leave sparse leaves and stale separators behind
+            for tree in &mut trees {
+                tree.set_deletion_mode(DeletionMode::DeferredRebalance);
+            }
+            for _ in 0..1_000 {
+                let side = rng.below(2) as usize;
+                let key = rng.below(20_000) as u32;
+                trees[side].remove(&key);
+                models[side].remove(&key);
+            }
+
+            for round in 0..60 {
+                let from = (round % 2) as usize;
+                // Start at a key the source holds and mostly stop short of
+                // the next key the destination holds
+                let Some(&start) = models[from]
+                    .keys()
+                    .nth(rng.below(models[from].len().max(1) as u64) as usize)
+                else {
+                    continue;
+                };
+                let clash = models[1 - from].range(start..).next().map(|(k, _)| *k);
+                let end = match clash {
+                    Some(clash) if round % 5 != 0 => clash.min(start + rng.below(3_000) as u32),
+                    _ => start + rng.below(3_000) as u32,
+                };
+                let [first, second] = &mut trees;
+                let (source, dest) = if from == 0 {
+                    (first, second)
+                } else {
+                    (second, first)
+                };
+                let result = source.move_range(start..end, dest);
+
+                if models[1 - from].range(start..end).next().is_some() {
+                    assert!(result.is_err());
+                } else {
+                    let moving: Vec<(u32, u32)> = models[from]
+                        .range(start..end)
+                        .map(|(k, v)| (*k, *v))
+                        .collect();
+                    assert_eq!(result.unwrap(), moving.len());
+                    for (key, value) in moving {
+                        models[from].remove(&key);
+                        models[1 - from].insert(key, value);
+                    }
+                }
+                for (tree, model) in trees.iter().zip(&models) {
+                    tree.check_invariants_detailed().unwrap_or_else(|e| {
+                        panic!(
+                            "capacities {}/{} round {}: {}",
+                            source_capacity, dest_capacity, round, e
+                        )
+                    });
+                    assert!(tree.items().eq(model.iter()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_retain_range_matches_model() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        let mut model = std::collections::BTreeMap::new();
+        for i in 0..2_000u32 {
+            tree.insert(i, i * 7);
+            model.insert(i, i * 7);
+        }
+
+        let mut rng = SeededRng::new(13);
+        for _ in 0..40 {
+            let start = rng.below(2_000) as u32;
+            let end = start + rng.below(300) as u32;
+            let modulus = 2 + rng.below(4) as u32;
+
+            let mut seen = Vec::new();
+            let removed = tree.retain_range(start..end, |key, value| {
+                seen.push(*key);
+                value % modulus != 0
+            });
+            let doomed: Vec<u32> = model
+                .range(start..end)
+                .filter(|(_, value)| *value % modulus == 0)
+                .map(|(key, _)| *key)
+                .collect();
+            for key in &doomed {
+                model.remove(key);
+            }
+            assert_eq!(removed, doomed.len());
+            // Only the range was offered to the predicate
+            assert!(seen.iter().all(|key| (start..end).contains(key)));
+            tree.check_invariants_detailed().unwrap();
+        }
+        assert!(tree.items().eq(model.iter()));
+    }
+    #[test]
+    fn test_merge_with_sorted_stream_matches_model() {
+        let mut rng = SeededRng::new(21);
+        for capacity in [4, 5, 16] {
+            let mut tree = BPlusTreeMap::new(capacity).unwrap();
+            let mut model = std::collections::BTreeMap::new();
+            for i in (0..3_000u32).step_by(2) {
+                tree.insert(i, i);
+                model.insert(i, i);
+            }
+
+            for round in 0..20u32 {
+                let mut stream = Vec::new();
+                for _ in 0..400 {
+                    stream.push((rng.below(3_200) as u32, rng.below(3)));
+                }
+                // Sorted, except every fifth round, with repeated keys kept
+                if round % 5 != 0 {
+                    stream.sort_by_key(|(key, _)| *key);
+                }
+
+                let mut expected = MergeStats::default();
+                for &(key, verdict) in &stream {
+                    match (verdict, model.contains_key(&key)) {
+                        (0, _) => {}
+                        (1, present) => {
+                            model.insert(key, key + round);
+                            if present {
+                                expected.updated += 1;
+                            } else {
+                                expected.inserted += 1;
+                            }
+                        }
+                        (_, present) => {
+                            model.remove(&key);
+                            expected.removed += usize::from(present);
+                        }
+                    }
+                }
+                let stats =
+                    tree.merge_with_sorted_stream(stream, |key, _, verdict| match verdict {
+                        0 => MergeAction::Keep,
+                        1 => MergeAction::Put(key + round),
+                        _ => MergeAction::Remove,
+                    });
+                assert_eq!(stats, expected);
+                tree.check_invariants_detailed()
+                    .unwrap_or_else(|e| panic!("capacity {} round {}: {}", capacity, round, e));
+                assert!(tree.items().eq(model.iter()));
+            }
+        }
+    }
+}
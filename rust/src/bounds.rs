@@ -0,0 +1,107 @@
+//! The traits keys and values must implement, and checks on key ordering.
+//!
+//! [`TreeKey`] and [`TreeValue`] are shorthands for the bounds every map
+//! operation needs. They exist mostly for the compiler: a key type without
+//! `Ord` gets an error naming the map and the fix instead of a list of
+//! unsatisfied bounds on each method.
+//!
+//! ```compile_fail
+//! use bplustree::BPlusTreeMap;
+//!
+//! // error: `f64` cannot be used as a B+ tree key
+//! let mut tree = BPlusTreeMap::new(16).unwrap();
+//! tree.insert(1.5_f64, "floats have no total order");
+//! ```
+
+use std::cmp::Ordering;
+
+/// A type that can be used as a key: `Ord + Clone`.
+///
+/// Implemented for every such type; there is nothing to implement by hand.
+///
+/// # Inconsistent `Ord`
+///
+/// The tree trusts `Ord` to be a total order that does not change while a
+/// key is stored. If it is not, lookups, ranges and removals may miss or
+/// return the wrong entries, but the tree never reads out of bounds or panics
+/// because of it in release builds. Debug builds check every search against
+/// its neighbouring keys and panic as soon as an answer contradicts itself,
+/// so such bugs show up in tests rather than as missing entries.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as a B+ tree key",
+    label = "keys must implement `Ord + Clone`",
+    note = "derive `Clone, PartialEq, Eq, PartialOrd, Ord`, or wrap types such as `f64` in a newtype with a total order",
+    note = "keys without a meaningful order belong in a `HashMap`"
+)]
+pub trait TreeKey: Ord + Clone {}
+
+impl<T: Ord + Clone> TreeKey for T {}
+
+/// A type that can be stored as a value: `Clone`.
+///
+/// Implemented for every such type; there is nothing to implement by hand.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be stored as a B+ tree value",
+    label = "values must implement `Clone`",
+    note = "wrap values that cannot be cloned in an `Rc` or `Arc`"
+)]
+pub trait TreeValue: Clone {}
+
+impl<T: Clone> TreeValue for T {}
+
+/// Check the result of searching sorted `keys` for `key` against the keys
+/// next to it, in debug builds. A consistent `Ord` always passes.
+#[inline]
+pub(crate) fn debug_check_search<K: Ord>(keys: &[K], key: &K, result: Result<usize, usize>) {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    match result {
+        Ok(index) => check_pair(&keys[index], key, Ordering::Equal),
+        Err(index) => {
+            if let Some(before) = index.checked_sub(1).map(|i| &keys[i]) {
+                check_pair(before, key, Ordering::Less);
+            }
+            if let Some(after) = keys.get(index) {
+                check_pair(after, key, Ordering::Greater);
+            }
+        }
+    }
+}
+
+/// Panic unless `a` compares as `expected` to `b`, `b` compares the opposite
+/// way to `a`, and `a` equals itself.
+#[cold]
+#[inline(never)]
+// Reports a bug in the caller's key type, and only in debug builds
+#[allow(clippy::panic)]
+fn check_pair<K: Ord>(a: &K, b: &K, expected: Ordering) {
+    if a.cmp(b) != expected || b.cmp(a) != expected.reverse() || a.cmp(a) != Ordering::Equal {
+        panic!(
+            "inconsistent `Ord` implementation: a key search contradicts the stored key order \
+             (checked in debug builds only)"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consistent_searches_pass() {
+        let keys = [1, 3, 5];
+        for key in 0..7 {
+            debug_check_search(&keys, &key, keys.binary_search(&key));
+        }
+        debug_check_search(&[] as &[i32], &1, Err(0));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "inconsistent `Ord`")]
+    fn test_contradicting_search_panics() {
+        // Claims 4 belongs between 5 and 3
+        debug_check_search(&[1, 5, 3], &4, Err(2));
+    }
+}
@@ -0,0 +1,357 @@
+//! Operations that stop after a fixed amount of work.
+//!
+//! An event loop that owns a large tree cannot afford a call that removes a
+//! million entries in one go. The `*_with_budget` methods take a budget of
+//! node touches, do as much as fits, and otherwise hand back a continuation
+//! that the caller passes to the matching `resume_*` method on a later turn.
+//!
+//! Costs are counted before any change is made, from the path the operation
+//! will take: every node from the root to the leaf, plus each node a split or
+//! a rebalance may add to it. The count is conservative, so a call never does
+//! more than its budget allows. A step that costs more than the whole budget
+//! is not started; [`touches_needed`](PendingInsert::touches_needed) says how
+//! large a budget it takes.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::types::{BPlusTreeMap, NodeRef};
+use std::ops::{Bound, RangeBounds};
+
+/// Outcome of an operation run under a budget of node touches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Budgeted<T, C> {
+    /// The operation finished with this result.
+    Done(T),
+    /// The budget ran out. Resume with the continuation to carry on.
+    Yielded(C),
+}
+
+/// An insert that did not fit in its budget. Nothing has been changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingInsert<K, V> {
+    key: K,
+    value: V,
+    needed: usize,
+}
+
+impl<K, V> PendingInsert<K, V> {
+    /// Budget the insert needed when it was last tried.
+    pub fn touches_needed(&self) -> usize {
+        self.needed
+    }
+}
+
+/// A range removal that ran out of budget before reaching the end of its
+/// range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingRangeRemoval<K> {
+    start: Bound<K>,
+    end: Bound<K>,
+    removed: usize,
+    needed: usize,
+}
+
+impl<K> PendingRangeRemoval<K> {
+    /// Entries removed so far, over all calls.
+    pub fn removed(&self) -> usize {
+        self.removed
+    }
+
+    /// Budget the next removal needed when it was last tried.
+    pub fn touches_needed(&self) -> usize {
+        self.needed
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Insert `value` under `key` if it can be done within `budget` node
+    /// touches, returning the previous value if any.
+    ///
+    /// An insert cannot stop halfway without leaving the tree unbalanced, so
+    /// it happens whole or not at all. Its cost is the height of the tree,
+    /// plus one for each node it splits and one more if the root splits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::{BPlusTreeMap, Budgeted};
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..100 {
+    ///     tree.insert(i, i);
+    /// }
+    ///
+    /// let pending = match tree.insert_with_budget(1_000, 0, 1) {
+    ///     Budgeted::Yielded(pending) => pending,
+    ///     Budgeted::Done(_) => unreachable!("a tree of 100 is more than one node deep"),
+    /// };
+    /// assert!(!tree.contains_key(&1_000));
+    ///
+    /// let budget = pending.touches_needed();
+    /// assert_eq!(tree.resume_insert(pending, budget), Budgeted::Done(None));
+    /// assert!(tree.contains_key(&1_000));
+    /// ```
+    pub fn insert_with_budget(
+        &mut self,
+        key: K,
+        value: V,
+        budget: usize,
+    ) -> Budgeted<Option<V>, PendingInsert<K, V>> {
+        self.resume_insert(
+            PendingInsert {
+                key,
+                value,
+                needed: 0,
+            },
+            budget,
+        )
+    }
+
+    /// Try a yielded insert again with a new budget.
+    pub fn resume_insert(
+        &mut self,
+        mut pending: PendingInsert<K, V>,
+        budget: usize,
+    ) -> Budgeted<Option<V>, PendingInsert<K, V>> {
+        pending.needed = self.insert_touches(&pending.key);
+        if pending.needed > budget {
+            return Budgeted::Yielded(pending);
+        }
+        Budgeted::Done(self.insert(pending.key, pending.value))
+    }
+
+    /// Remove the entries in `range`, in key order, until the range is empty
+    /// or the next removal would exceed `budget` node touches. Returns the
+    /// number of entries removed once the range is empty.
+    ///
+    /// Each removal costs the height of the tree, doubled when its leaf is at
+    /// minimum occupancy and rebalancing may reach a sibling at every level.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::{BPlusTreeMap, Budgeted};
+    ///
+    /// let mut tree = BPlusTreeMap::new(8).unwrap();
+    /// for i in 0..1_000 {
+    ///     tree.insert(i, i);
+    /// }
+    ///
+    /// let mut turns = 1;
+    /// let mut step = tree.remove_range_with_budget(100..900, 64);
+    /// let removed = loop {
+    ///     match step {
+    ///         Budgeted::Done(removed) => break removed,
+    ///         Budgeted::Yielded(pending) => {
+    ///             // ... serve other work, then carry on
+    ///             turns += 1;
+    ///             step = tree.resume_remove_range(pending, 64);
+    ///         }
+    ///     }
+    /// };
+    /// assert_eq!(removed, 800);
+    /// assert_eq!(tree.len(), 200);
+    /// assert!(turns > 1);
+    /// ```
+    pub fn remove_range_with_budget<R: RangeBounds<K>>(
+        &mut self,
+        range: R,
+        budget: usize,
+    ) -> Budgeted<usize, PendingRangeRemoval<K>> {
+        self.resume_remove_range(
+            PendingRangeRemoval {
+                start: range.start_bound().cloned(),
+                end: range.end_bound().cloned(),
+                removed: 0,
+                needed: 0,
+            },
+            budget,
+        )
+    }
+
+    /// Continue a yielded range removal with a new budget.
+    pub fn resume_remove_range(
+        &mut self,
+        mut pending: PendingRangeRemoval<K>,
+        budget: usize,
+    ) -> Budgeted<usize, PendingRangeRemoval<K>> {
+        let mut spent = 0;
+        loop {
+            let key = match self
+                .range((pending.start.as_ref(), pending.end.as_ref()))
+                .next()
+            {
+                Some((key, _)) => key.clone(),
+                None => return Budgeted::Done(pending.removed),
+            };
+            pending.needed = self.remove_touches(&key);
+            if spent + pending.needed > budget {
+                return Budgeted::Yielded(pending);
+            }
+            spent += pending.needed;
+            self.remove(&key);
+            pending.removed += 1;
+            pending.start = Bound::Excluded(key);
+        }
+    }
+
+    /// Nodes inserting `key` reads or creates: the path to its leaf, each
+    /// node that splits, and a new root if every node on the path splits.
+    fn insert_touches(&self, key: &K) -> usize {
+        let mut path = 0;
+        // Full branches directly above the current node
+        let mut full_above = 0;
+        let mut node = self.root;
+        loop {
+            path += 1;
+            match node {
+                NodeRef::Branch(id, _) => {
+                    let Some(branch) = self.get_branch(id) else {
+                        return path;
+                    };
+                    full_above = if branch.is_full() { full_above + 1 } else { 0 };
+                    match branch.get_child(key) {
+                        Some(child) => node = child,
+                        None => return path,
+                    }
+                }
+                NodeRef::Leaf(id, _) => {
+                    let splits = match self.get_leaf(id) {
+                        // Under a byte budget any new key may split its leaf
+                        Some(leaf)
+                            if (leaf.is_full()
+                                || (self.byte_budget.is_some() && !leaf.keys.is_empty()))
+                                && leaf.binary_search_keys(key).is_err() =>
+                        {
+                            1 + full_above
+                        }
+                        _ => 0,
+                    };
+                    let new_root = usize::from(splits == path);
+                    return path + splits + new_root;
+                }
+            }
+        }
+    }
+
+    /// Nodes removing `key` may touch: the path to its leaf, and a sibling
+    /// per level if the leaf is at minimum occupancy.
+    fn remove_touches(&self, key: &K) -> usize {
+        let mut path = 1;
+        let mut node = self.root;
+        while let NodeRef::Branch(id, _) = node {
+            match self.get_branch(id).and_then(|branch| branch.get_child(key)) {
+                Some(child) => node = child,
+                None => return path,
+            }
+            path += 1;
+        }
+        let at_minimum = path > 1
+            && self
+                .get_leaf(node.id())
+                .is_some_and(|leaf| !leaf.can_donate());
+        if at_minimum {
+            2 * path
+        } else {
+            path
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_insert_costs_follow_splits() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        // One leaf with room
+        assert_eq!(tree.insert_touches(&1), 1);
+        for i in 0..4 {
+            tree.insert(i, i);
+        }
+        // A full root leaf splits and gains a parent
+        assert_eq!(tree.insert_touches(&9), 3);
+        // An update never splits
+        assert_eq!(tree.insert_touches(&2), 1);
+    }
+
+    #[test]
+    fn test_yielded_insert_changes_nothing() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..1_000u32 {
+            tree.insert(i, i);
+        }
+        let before: Vec<_> = tree.items().map(|(k, v)| (*k, *v)).collect();
+        let pending = match tree.insert_with_budget(5_000, 1, 2) {
+            Budgeted::Yielded(pending) => pending,
+            Budgeted::Done(_) => panic!("a tree of 1000 is more than two nodes deep"),
+        };
+        assert!(pending.touches_needed() > 2);
+        assert!(tree.items().map(|(k, v)| (*k, *v)).eq(before));
+
+        let budget = pending.touches_needed();
+        assert!(matches!(
+            tree.resume_insert(pending, budget - 1),
+            Budgeted::Yielded(_)
+        ));
+        assert_eq!(
+            tree.insert_with_budget(5_000, 1, budget),
+            Budgeted::Done(None)
+        );
+        assert_eq!(
+            tree.insert_with_budget(5_000, 2, budget),
+            Budgeted::Done(Some(1))
+        );
+        assert!(tree.check_invariants());
+    }
+
+    #[test]
+    fn test_budgeted_range_removal_matches_btreemap() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..5_000u32 {
+            tree.insert(i, i);
+        }
+        let mut model: BTreeMap<u32, u32> = (0..5_000).map(|i| (i, i)).collect();
+        let budget = 40;
+
+        let mut turns = 0;
+        let mut step = tree.remove_range_with_budget(1_000..=4_000, budget);
+        let removed = loop {
+            turns += 1;
+            match step {
+                Budgeted::Done(removed) => break removed,
+                Budgeted::Yielded(pending) => {
+                    // Every turn makes progress while the budget covers a removal
+                    assert!(pending.removed() > 0);
+                    assert!(pending.touches_needed() <= budget);
+                    assert!(tree.check_invariants());
+                    step = tree.resume_remove_range(pending, budget);
+                }
+            }
+        };
+
+        model.retain(|k, _| !(1_000..=4_000).contains(k));
+        assert_eq!(removed, 3_001);
+        assert!(turns > 100);
+        assert!(tree.items().map(|(k, v)| (*k, *v)).eq(model.into_iter()));
+        assert!(tree.check_invariants());
+    }
+
+    #[test]
+    fn test_range_removal_with_no_budget_yields_untouched() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..100u32 {
+            tree.insert(i, i);
+        }
+        match tree.remove_range_with_budget(10..20, 0) {
+            Budgeted::Yielded(pending) => {
+                assert_eq!(pending.removed(), 0);
+                assert!(pending.touches_needed() > 0);
+            }
+            Budgeted::Done(_) => panic!("nothing fits in a zero budget"),
+        }
+        assert_eq!(tree.len(), 100);
+        assert_eq!(tree.remove_range_with_budget(200.., 0), Budgeted::Done(0));
+    }
+}
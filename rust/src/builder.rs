@@ -0,0 +1,364 @@
+//! Configuring a tree in one expression.
+//!
+//! A tree has grown several settings besides its capacity: what a full leaf
+//! does, how removals rebalance, how much arena space to reserve. Passing
+//! them positionally would make every constructor call a list of unlabelled
+//! arguments, and setting them one by one after construction leaves the
+//! combinations unchecked. [`BPlusTreeBuilder`] names each setting, offers
+//! presets for common workloads, and checks the combination before building.
+
+use crate::bounds::{TreeKey, TreeValue};
+#[cfg(feature = "compressed")]
+use crate::compressed_values::{CompressedValueMap, ValueCodec};
+use crate::construction::validation::recommended_capacity;
+use crate::construction::DEFAULT_CAPACITY;
+use crate::error::BuildError;
+use crate::types::{BPlusTreeMap, DeletionMode, OverflowMode, RebalanceStrategy};
+
+/// Lowest fill factor accepted. Eager removals keep every leaf but the root
+/// at least half full.
+const MIN_FILL_FACTOR: f64 = 0.5;
+
+/// The storage a builder produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// An ordinary [`BPlusTreeMap`], built with
+    /// [`build`](BPlusTreeBuilder::build).
+    #[default]
+    Standard,
+    /// A [`CompressedValueMap`], built with
+    /// [`build_compressed`](BPlusTreeBuilder::build_compressed).
+    #[cfg(feature = "compressed")]
+    Compressed,
+}
+
+/// Settings for a new tree, checked together when it is built.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::{BPlusTreeBuilder, BuildError, DeletionMode, OverflowMode};
+///
+/// let mut tree = BPlusTreeBuilder::new()
+///     .capacity(32)
+///     .split_policy(OverflowMode::GrowLeaves { factor: 3 })
+///     .deletion_mode(DeletionMode::DeferredRebalance)
+///     .expected_items(100_000)
+///     .fill_factor(0.7)
+///     .build()
+///     .unwrap();
+/// tree.insert(1, "one");
+/// assert_eq!(tree.capacity(), 32);
+///
+/// // A fill factor only sizes the reservation for the expected items
+/// let err = BPlusTreeBuilder::new()
+///     .fill_factor(0.7)
+///     .build::<u32, u32>()
+///     .unwrap_err();
+/// assert!(matches!(err, BuildError::Conflict { setting: "fill_factor", .. }));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BPlusTreeBuilder {
+    capacity: Option<usize>,
+    overflow_mode: OverflowMode,
+    rebalance_strategy: RebalanceStrategy,
+    deletion_mode: DeletionMode,
+    fill_factor: Option<f64>,
+    expected_items: Option<usize>,
+    backend: Backend,
+}
+
+impl Default for BPlusTreeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BPlusTreeBuilder {
+    /// A builder with every setting at the tree's default.
+    pub fn new() -> Self {
+        Self {
+            capacity: None,
+            overflow_mode: OverflowMode::default(),
+            rebalance_strategy: RebalanceStrategy::default(),
+            deletion_mode: DeletionMode::default(),
+            fill_factor: None,
+            expected_items: None,
+            backend: Backend::default(),
+        }
+    }
+
+    /// Preset for lookup-dominated workloads: wide nodes for a shallow tree.
+    pub fn read_heavy() -> Self {
+        Self::new().capacity(64)
+    }
+
+    /// Preset for bursts of inserts and removals: full leaves grow to three
+    /// times the capacity before splitting, removals skip rebalancing until
+    /// [`vacuum`](BPlusTreeMap::vacuum), and rebalancing borrows from the
+    /// fuller sibling.
+    pub fn write_heavy() -> Self {
+        Self::new()
+            .split_policy(OverflowMode::GrowLeaves { factor: 3 })
+            .deletion_mode(DeletionMode::DeferredRebalance)
+            .rebalance_strategy(RebalanceStrategy::PreferFuller)
+    }
+
+    #[cfg(feature = "compressed")]
+    /// Preset for data that is written once and kept: compressed values in
+    /// large blocks.
+    pub fn archival() -> Self {
+        Self::new().capacity(128).backend(Backend::Compressed)
+    }
+
+    /// Maximum number of keys per node. Without it the capacity is chosen
+    /// from [`expected_items`](Self::expected_items), or is
+    /// [`DEFAULT_CAPACITY`].
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// What an insert does when its leaf is full.
+    pub fn split_policy(mut self, mode: OverflowMode) -> Self {
+        self.overflow_mode = mode;
+        self
+    }
+
+    /// Which sibling removals borrow from and merge with.
+    pub fn rebalance_strategy(mut self, strategy: RebalanceStrategy) -> Self {
+        self.rebalance_strategy = strategy;
+        self
+    }
+
+    /// When removals restore the minimum occupancy.
+    pub fn deletion_mode(mut self, mode: DeletionMode) -> Self {
+        self.deletion_mode = mode;
+        self
+    }
+
+    /// Expected share of each node in use, from 0.5 to 1.0, for sizing the
+    /// reservation made for [`expected_items`](Self::expected_items).
+    /// Defaults to 0.5, the worst case [`reserve_keys_hint`] plans for;
+    /// trees loaded in key order run close to full.
+    ///
+    /// [`reserve_keys_hint`]: BPlusTreeMap::reserve_keys_hint
+    pub fn fill_factor(mut self, fill_factor: f64) -> Self {
+        self.fill_factor = Some(fill_factor);
+        self
+    }
+
+    /// Number of entries the tree is expected to hold. Arena space for them
+    /// is reserved up front, and the capacity is chosen to suit them unless
+    /// set explicitly.
+    pub fn expected_items(mut self, n: usize) -> Self {
+        self.expected_items = Some(n);
+        self
+    }
+
+    /// The storage to build.
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Build a [`BPlusTreeMap`] with these settings.
+    pub fn build<K: TreeKey, V: TreeValue>(&self) -> Result<BPlusTreeMap<K, V>, BuildError> {
+        self.check(Backend::Standard)?;
+        let capacity = self.resolved_capacity();
+        let mut tree = BPlusTreeMap::new(capacity).map_err(BuildError::InvalidCapacity)?;
+        tree.set_overflow_mode(self.overflow_mode);
+        tree.set_rebalance_strategy(self.rebalance_strategy);
+        tree.set_deletion_mode(self.deletion_mode);
+        if let Some(n) = self.expected_items {
+            let fill_factor = self.fill_factor.unwrap_or(MIN_FILL_FACTOR);
+            tree.reserve_nodes_for(n, (capacity as f64 * fill_factor) as usize);
+        }
+        Ok(tree)
+    }
+
+    #[cfg(feature = "compressed")]
+    /// Build a [`CompressedValueMap`] encoding its values with `codec`.
+    ///
+    /// The compressed map manages its own blocks, so only the capacity and
+    /// expected item count apply to it; any other setting is a conflict.
+    pub fn build_compressed<K, V, C>(
+        &self,
+        codec: C,
+    ) -> Result<CompressedValueMap<K, V, C>, BuildError>
+    where
+        K: Ord + Clone,
+        V: Clone,
+        C: ValueCodec<V>,
+    {
+        self.check(Backend::Compressed)?;
+        CompressedValueMap::new(self.resolved_capacity(), codec)
+            .map_err(BuildError::InvalidCapacity)
+    }
+
+    fn resolved_capacity(&self) -> usize {
+        match (self.capacity, self.expected_items) {
+            (Some(capacity), _) => capacity,
+            (None, Some(n)) => recommended_capacity(n),
+            (None, None) => DEFAULT_CAPACITY,
+        }
+    }
+
+    /// Reject settings that do not fit together or with `requested`.
+    fn check(&self, requested: Backend) -> Result<(), BuildError> {
+        if self.backend != requested {
+            return Err(BuildError::BackendMismatch {
+                configured: self.backend,
+                requested,
+            });
+        }
+        if let Some(fill_factor) = self.fill_factor {
+            if !(MIN_FILL_FACTOR..=1.0).contains(&fill_factor) {
+                return Err(BuildError::FillFactorOutOfRange {
+                    fill_factor,
+                    minimum: MIN_FILL_FACTOR,
+                    maximum: 1.0,
+                });
+            }
+            if self.expected_items.is_none() {
+                return Err(BuildError::Conflict {
+                    setting: "fill_factor",
+                    with: "expected_items",
+                    reason: "the fill factor only sizes the reservation for expected items, \
+                             so it needs an expected item count",
+                });
+            }
+        }
+        #[cfg(feature = "compressed")]
+        if requested == Backend::Compressed {
+            let compressed = |setting| BuildError::Conflict {
+                setting,
+                with: "backend",
+                reason: "compressed maps manage their own blocks",
+            };
+            if self.overflow_mode != OverflowMode::default() {
+                return Err(compressed("split_policy"));
+            }
+            if self.rebalance_strategy != RebalanceStrategy::default() {
+                return Err(compressed("rebalance_strategy"));
+            }
+            if self.deletion_mode != DeletionMode::default() {
+                return Err(compressed("deletion_mode"));
+            }
+            if self.fill_factor.is_some() {
+                return Err(compressed("fill_factor"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BPlusTreeError, MIN_CAPACITY};
+
+    #[test]
+    fn test_builder_applies_settings() {
+        let mut tree = BPlusTreeBuilder::write_heavy()
+            .capacity(8)
+            .build::<u32, u32>()
+            .unwrap();
+        assert_eq!(tree.capacity(), 8);
+        assert_eq!(tree.overflow_mode(), OverflowMode::GrowLeaves { factor: 3 });
+        assert_eq!(tree.rebalance_strategy, RebalanceStrategy::PreferFuller);
+        assert_eq!(tree.deletion_mode, DeletionMode::DeferredRebalance);
+        for i in 0..1_000 {
+            tree.insert(i, i);
+        }
+        assert!(tree.check_invariants());
+
+        // The capacity follows the expected size unless given
+        let tree = BPlusTreeBuilder::new()
+            .expected_items(500_000)
+            .build::<u32, u32>()
+            .unwrap();
+        assert_eq!(tree.capacity(), recommended_capacity(500_000));
+        assert_eq!(
+            BPlusTreeBuilder::read_heavy()
+                .build::<u32, u32>()
+                .unwrap()
+                .capacity(),
+            64
+        );
+    }
+
+    #[test]
+    fn test_fill_factor_sizes_reservation() {
+        let reserved = |fill_factor| {
+            let tree = BPlusTreeBuilder::new()
+                .capacity(16)
+                .expected_items(10_000)
+                .fill_factor(fill_factor)
+                .build::<u32, u32>()
+                .unwrap();
+            tree.leaf_arena.capacity()
+        };
+        assert!(reserved(0.5) >= 10_000 / 8);
+        assert!(reserved(1.0) >= 10_000 / 16);
+        assert!(reserved(1.0) < reserved(0.5));
+
+        let mut hinted = BPlusTreeMap::<u32, u32>::new(16).unwrap();
+        hinted.reserve_keys_hint(10_000);
+        assert_eq!(hinted.leaf_arena.capacity(), reserved(0.5));
+    }
+
+    #[test]
+    fn test_invalid_settings_are_reported() {
+        assert_eq!(
+            BPlusTreeBuilder::new()
+                .capacity(2)
+                .build::<u32, u32>()
+                .unwrap_err(),
+            BuildError::InvalidCapacity(BPlusTreeError::invalid_capacity(2, MIN_CAPACITY))
+        );
+        assert!(matches!(
+            BPlusTreeBuilder::new()
+                .expected_items(10)
+                .fill_factor(0.2)
+                .build::<u32, u32>(),
+            Err(BuildError::FillFactorOutOfRange { .. })
+        ));
+    }
+
+    #[cfg(feature = "compressed")]
+    #[test]
+    fn test_archival_builds_compressed_map() {
+        let mut map = BPlusTreeBuilder::archival()
+            .build_compressed(crate::DeltaVarintCodec)
+            .unwrap();
+        for i in 0..1_000u32 {
+            map.insert(i, u64::from(i) * 3);
+        }
+        assert_eq!(map.get(&500), Some(1_500));
+        assert_eq!(map.len(), 1_000);
+
+        assert_eq!(
+            BPlusTreeBuilder::archival()
+                .build::<u32, u32>()
+                .unwrap_err(),
+            BuildError::BackendMismatch {
+                configured: Backend::Compressed,
+                requested: Backend::Standard,
+            }
+        );
+        let err = BPlusTreeBuilder::write_heavy()
+            .backend(Backend::Compressed)
+            .build_compressed::<u32, u64, _>(crate::DeltaVarintCodec)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BuildError::Conflict {
+                setting: "split_policy",
+                with: "backend",
+                ..
+            }
+        ));
+        assert!(err.to_string().contains("split_policy"));
+    }
+}
@@ -0,0 +1,200 @@
+//! Building a tree bottom-up from entries in key order.
+//!
+//! [`FromIterator`] and [`Extend`] into an empty tree sort their input if
+//! needed and then lay the entries out in leaves left to right, evenly sized
+//! so that none is underfull, and build each level of branches over the one
+//! below. Every node is allocated once and nothing is split or rebalanced,
+//! which is far cheaper than inserting the entries one at a time.
+
+use crate::batch_operations::{even_chunk_sizes, BatchOp, WriteBatch};
+use crate::bounds::{TreeKey, TreeValue};
+use crate::construction::DEFAULT_CAPACITY;
+use crate::types::{BPlusTreeMap, BranchNode, NodeRef, NodeVec, NULL_NODE};
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Replace the contents of this tree with `entries`, which must be in
+    /// strictly ascending key order.
+    fn load_sorted(&mut self, entries: Vec<(K, V)>) {
+        self.clear();
+        let capacity = self.capacity;
+        let sizes = even_chunk_sizes(entries.len(), entries.len().div_ceil(capacity));
+        let mut entries = entries.into_iter();
+        let mut leaves: Vec<(NodeVec<K>, NodeVec<V>)> = sizes
+            .into_iter()
+            .map(|size| entries.by_ref().take(size).unzip())
+            .collect();
+
+        if leaves.len() <= 1 {
+            if let (Some(root), Some((keys, values))) = (self.inline_root.as_mut(), leaves.pop()) {
+                root.keys = keys;
+                root.values = values;
+            }
+            return;
+        }
+
+        // Allocate right to left so each leaf knows its successor
+        let mut level = Vec::with_capacity(leaves.len());
+        let mut next = NULL_NODE;
+        for (keys, values) in leaves.into_iter().rev() {
+            let first = keys[0].clone();
+            next = self.allocate_leaf_with_data(capacity, keys, values, next);
+            level.push((first, next));
+        }
+        level.reverse();
+        self.inline_root = None;
+
+        // Each pass groups a level into evenly sized branches until one is left
+        let mut children_are_leaves = true;
+        while level.len() > 1 {
+            let sizes = even_chunk_sizes(level.len(), level.len().div_ceil(capacity + 1));
+            let mut nodes = level.into_iter();
+            level = Vec::with_capacity(sizes.len());
+            for size in sizes {
+                let Some((first, first_id)) = nodes.next() else {
+                    break;
+                };
+                let (keys, mut child_ids): (NodeVec<K>, NodeVec<_>) =
+                    nodes.by_ref().take(size - 1).unzip();
+                child_ids.insert(0, first_id);
+                let id = self.allocate_branch(BranchNode::from_parts(
+                    capacity,
+                    keys,
+                    child_ids,
+                    children_are_leaves,
+                ));
+                level.push((first, id));
+            }
+            children_are_leaves = false;
+        }
+        if let Some(&(_, root_id)) = level.first() {
+            self.root = NodeRef::branch(root_id);
+        }
+    }
+}
+
+/// Sort `entries` by key if they are not already in order, keeping only the
+/// last entry for each key, as inserting them in turn would.
+fn sorted_unique<K: Ord, V>(mut entries: Vec<(K, V)>) -> Vec<(K, V)> {
+    if entries.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+        return entries;
+    }
+    // Stable, so equal keys stay in input order
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut unique: Vec<(K, V)> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        match unique.last_mut() {
+            Some(last) if last.0 == entry.0 => *last = entry,
+            _ => unique.push(entry),
+        }
+    }
+    unique
+}
+
+impl<K: TreeKey, V: TreeValue> FromIterator<(K, V)> for BPlusTreeMap<K, V> {
+    /// Build a tree with the default capacity from `(key, value)` pairs,
+    /// bulk-loaded bottom-up. Later pairs win over earlier ones with the same
+    /// key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let tree: BPlusTreeMap<i32, i32> = (0..1_000).map(|i| (i, i * 2)).collect();
+    /// assert_eq!(tree.len(), 1_000);
+    /// assert_eq!(tree.get(&500), Some(&1_000));
+    ///
+    /// let tree: BPlusTreeMap<&str, i32> = [("b", 1), ("a", 2), ("b", 3)].into_iter().collect();
+    /// assert_eq!(tree.items().collect::<Vec<_>>(), vec![(&"a", &2), (&"b", &3)]);
+    /// ```
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut tree = Self::with_valid_capacity(DEFAULT_CAPACITY);
+        tree.load_sorted(sorted_unique(iter.into_iter().collect()));
+        tree
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> Extend<(K, V)> for BPlusTreeMap<K, V> {
+    /// Insert every `(key, value)` pair, overwriting existing values.
+    ///
+    /// An empty tree is bulk-loaded like [`FromIterator`], keeping its
+    /// capacity and settings, unless it has a byte budget or a leaf boundary
+    /// hook, which need to see each leaf as it fills. Otherwise the pairs are
+    /// applied as one [`WriteBatch`], which rebalances once at the end.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// tree.extend((0..10).map(|i| (i, i)));
+    /// tree.extend([(5, 50), (20, 200)]);
+    /// assert_eq!(tree.len(), 11);
+    /// assert_eq!(tree.get(&5), Some(&50));
+    /// ```
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        let entries: Vec<(K, V)> = iter.into_iter().collect();
+        if self.is_empty() && self.byte_budget.is_none() && self.leaf_boundary_hook.is_none() {
+            self.load_sorted(sorted_unique(entries));
+        } else {
+            let batch: WriteBatch<K, V> = entries
+                .into_iter()
+                .map(|(key, value)| BatchOp::Insert(key, value))
+                .collect();
+            self.apply_batch(batch);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_bulk_load_builds_valid_trees() {
+        for capacity in [4, 5, 7, 16] {
+            for n in [0, 1, capacity, capacity + 1, 2 * capacity + 3, 1_000, 5_000] {
+                let mut tree = BPlusTreeMap::new(capacity).unwrap();
+                tree.extend((0..n as i32).map(|i| (i, i * 3)));
+                tree.check_invariants_detailed()
+                    .unwrap_or_else(|e| panic!("capacity {} n {}: {}", capacity, n, e));
+                assert_eq!(tree.len(), n);
+                assert!(tree
+                    .items()
+                    .map(|(k, v)| (*k, *v))
+                    .eq((0..n as i32).map(|i| (i, i * 3))));
+                // Still usable for ordinary updates afterwards
+                for i in (0..n as i32).step_by(3) {
+                    tree.remove(&i);
+                }
+                tree.insert(-1, 0);
+                tree.check_invariants_detailed()
+                    .unwrap_or_else(|e| panic!("capacity {} n {} after edits: {}", capacity, n, e));
+            }
+        }
+    }
+
+    #[test]
+    fn test_collect_and_extend_match_btreemap() {
+        let mut rng = SeededRng::new(99);
+        let mut pairs = Vec::new();
+        for i in 0..3_000 {
+            pairs.push((rng.below(1_000) as u32, i));
+        }
+        let expected: BTreeMap<u32, i32> = pairs.iter().copied().collect();
+
+        let tree: BPlusTreeMap<u32, i32> = pairs.iter().copied().collect();
+        assert!(tree.check_invariants());
+        assert!(tree.items().eq(expected.iter()));
+
+        // Extending a non-empty tree goes through a write batch
+        let (head, tail) = pairs.split_at(1_000);
+        let mut tree: BPlusTreeMap<u32, i32> = head.iter().copied().collect();
+        tree.extend(tail.iter().copied());
+        assert!(tree.check_invariants());
+        assert!(tree.items().eq(expected.iter()));
+    }
+}
@@ -0,0 +1,373 @@
+//! Leaf sizes measured in bytes rather than entries.
+//!
+//! Capacity counts entries, which suits fixed-size keys. With `String` keys
+//! or values, a full leaf can weigh a few hundred bytes or many kilobytes
+//! depending on what it holds. A [`ByteBudget`] also makes a leaf split once
+//! its entries would weigh more than the budget, and lets removals merge
+//! leaves only while the result still fits. Leaf sizes then stay within a
+//! chosen envelope, with capacity still capping the number of entries.
+//!
+//! Under a budget a leaf may hold fewer than half its capacity, so the
+//! count-based minimum occupancy is not enforced for leaves. Branches are
+//! still sized by count, and values changed in place through `get_mut` or
+//! `merge_value` are only weighed again when their leaf next changes shape. Bulk paths that split by count (`apply_batch`,
+//! `coalesce_leaves`) do not consult the budget; the next insert into a leaf
+//! they left oversized splits it.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::types::{BPlusTreeMap, LeafNode, NodeId, NodeRef};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A limit on how many bytes one leaf's entries may weigh, and the function
+/// that weighs an entry.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::{BPlusTreeMap, ByteBudget};
+///
+/// let mut tree = BPlusTreeMap::new(64).unwrap();
+/// tree.set_byte_budget(Some(ByteBudget::new(256, |key: &String, value: &String| {
+///     key.len() + value.len()
+/// })));
+///
+/// for i in 0..200 {
+///     tree.insert(format!("key{:04}", i), "v".repeat(i % 50));
+/// }
+/// assert!(tree.leaf_byte_sizes().unwrap().iter().all(|&bytes| bytes <= 256));
+/// # #[cfg(feature = "validation")]
+/// assert!(tree.check_invariants());
+/// ```
+pub struct ByteBudget<K, V> {
+    max_bytes: usize,
+    size_of: fn(&K, &V) -> usize,
+}
+
+impl<K, V> ByteBudget<K, V> {
+    /// A budget of `max_bytes` per leaf, weighing each entry with `size_of`.
+    pub fn new(max_bytes: usize, size_of: fn(&K, &V) -> usize) -> Self {
+        Self { max_bytes, size_of }
+    }
+
+    /// The most bytes a leaf of more than one entry may weigh.
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Weight of one entry.
+    pub fn entry_bytes(&self, key: &K, value: &V) -> usize {
+        (self.size_of)(key, value)
+    }
+
+    fn slice_bytes(&self, keys: &[K], values: &[V]) -> usize {
+        keys.iter()
+            .zip(values)
+            .map(|(key, value)| self.entry_bytes(key, value))
+            .sum()
+    }
+
+    pub(crate) fn leaf_bytes(&self, leaf: &LeafNode<K, V>) -> usize {
+        self.slice_bytes(&leaf.keys, &leaf.values)
+    }
+
+    /// Whether adding the entry to the non-empty `leaf` would take it over
+    /// budget. A lone entry heavier than the budget still gets a leaf.
+    pub(crate) fn overflows(&self, leaf: &LeafNode<K, V>, key: &K, value: &V) -> bool {
+        !leaf.keys.is_empty()
+            && self.leaf_bytes(leaf) + self.entry_bytes(key, value) > self.max_bytes
+    }
+
+    /// Whether replacing the value at `index` with `value` would take the
+    /// leaf over budget.
+    pub(crate) fn replacement_overflows(
+        &self,
+        leaf: &LeafNode<K, V>,
+        index: usize,
+        value: &V,
+    ) -> bool {
+        let old = self.entry_bytes(&leaf.keys[index], &leaf.values[index]);
+        let new = self.entry_bytes(&leaf.keys[index], value);
+        self.leaf_bytes(leaf) - old + new > self.max_bytes
+    }
+
+    /// Where to split `leaf` so the two halves weigh about the same once the
+    /// new entry is placed at `index`. Returns the number of existing
+    /// entries the left half keeps and whether the new entry joins it.
+    pub(crate) fn split_point(
+        &self,
+        leaf: &LeafNode<K, V>,
+        index: usize,
+        key: &K,
+        value: &V,
+    ) -> (usize, bool) {
+        let len = leaf.keys.len();
+        let new_bytes = self.entry_bytes(key, value);
+        let total = self.leaf_bytes(leaf) + new_bytes;
+
+        // Entries in the left half, counting the new one, chosen to make the
+        // heavier half as light as possible; both halves keep an entry
+        let weight_at = |position: usize| match position.cmp(&index) {
+            Ordering::Less => self.entry_bytes(&leaf.keys[position], &leaf.values[position]),
+            Ordering::Equal => new_bytes,
+            Ordering::Greater => {
+                self.entry_bytes(&leaf.keys[position - 1], &leaf.values[position - 1])
+            }
+        };
+        let mut prefix = 0;
+        let mut left = 1;
+        let mut heaviest = usize::MAX;
+        for count in 1..=len {
+            prefix += weight_at(count - 1);
+            let heavier = prefix.max(total - prefix);
+            if heavier < heaviest {
+                heaviest = heavier;
+                left = count;
+            }
+        }
+
+        if index < left {
+            (left - 1, true)
+        } else {
+            (left, false)
+        }
+    }
+}
+
+impl<K, V> Clone for ByteBudget<K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for ByteBudget<K, V> {}
+
+impl<K, V> fmt::Debug for ByteBudget<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ByteBudget")
+            .field("max_bytes", &self.max_bytes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Returns the byte budget for leaves, if one is set.
+    pub fn byte_budget(&self) -> Option<ByteBudget<K, V>> {
+        self.byte_budget
+    }
+
+    /// Size leaves by the bytes their entries weigh as well as by count, or
+    /// by count alone with `None`.
+    ///
+    /// The budget applies to later inserts and removals; leaves that are
+    /// already over it split the next time an entry is added to them.
+    pub fn set_byte_budget(&mut self, budget: Option<ByteBudget<K, V>>) {
+        self.byte_budget = budget;
+    }
+
+    /// Weight of each non-empty leaf in key order, or `None` without a byte
+    /// budget to weigh entries with.
+    pub fn leaf_byte_sizes(&self) -> Option<Vec<usize>> {
+        let budget = self.byte_budget?;
+        Some(
+            self.group_by_leaf()
+                .map(|(keys, values)| budget.slice_bytes(keys, values))
+                .collect(),
+        )
+    }
+
+    /// Rebalance an underfull leaf under a byte budget: merge it with a
+    /// neighbour when the result fits, otherwise borrow an entry that fits,
+    /// otherwise leave it. A leaf weighing at least half the budget is not
+    /// underfull, however few entries it holds. Returns whether the child
+    /// still exists.
+    pub(crate) fn rebalance_leaf_by_bytes(
+        &mut self,
+        budget: ByteBudget<K, V>,
+        parent_id: NodeId,
+        child_index: usize,
+    ) -> bool {
+        let Some(parent) = self.get_branch(parent_id) else {
+            return false;
+        };
+        let leaf_at = |index: usize| match parent.child(index) {
+            Some(NodeRef::Leaf(id, _)) => Some(id),
+            _ => None,
+        };
+        let Some(child_id) = leaf_at(child_index) else {
+            return false;
+        };
+        let left_id = child_index.checked_sub(1).and_then(leaf_at);
+        let right_id = leaf_at(child_index + 1);
+
+        let Some(child) = self.get_leaf(child_id) else {
+            return false;
+        };
+        let child_len = child.keys.len();
+        let child_bytes = budget.leaf_bytes(child);
+        if !child.is_underfull() || 2 * child_bytes >= budget.max_bytes {
+            return true;
+        }
+
+        let fits_merged = |id: NodeId| {
+            self.get_leaf(id).is_some_and(|sibling| {
+                child_len + sibling.keys.len() <= self.capacity
+                    && child_bytes + budget.leaf_bytes(sibling) <= budget.max_bytes
+            })
+        };
+        if let Some(left_id) = left_id.filter(|&id| fits_merged(id)) {
+            return self.merge_with_left::<LeafNode<K, V>>(
+                parent_id,
+                child_index,
+                left_id,
+                child_id,
+            );
+        }
+        if let Some(right_id) = right_id.filter(|&id| fits_merged(id)) {
+            return self.merge_with_right::<LeafNode<K, V>>(
+                parent_id,
+                child_index,
+                child_id,
+                right_id,
+            );
+        }
+
+        // The entry a sibling would hand over: its last from the left, its
+        // first from the right
+        let fits_borrowed = |id: NodeId, last: bool| {
+            self.get_leaf(id).is_some_and(|sibling| {
+                let index = if last { sibling.keys.len() - 1 } else { 0 };
+                sibling.can_donate()
+                    && child_bytes
+                        + budget.entry_bytes(&sibling.keys[index], &sibling.values[index])
+                        <= budget.max_bytes
+            })
+        };
+        if let Some(left_id) = left_id.filter(|&id| fits_borrowed(id, true)) {
+            return self.borrow_from_left::<LeafNode<K, V>>(
+                parent_id,
+                child_index,
+                left_id,
+                child_id,
+            );
+        }
+        if let Some(right_id) = right_id.filter(|&id| fits_borrowed(id, false)) {
+            return self.borrow_from_right::<LeafNode<K, V>>(
+                parent_id,
+                child_index,
+                child_id,
+                right_id,
+            );
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use std::collections::BTreeMap;
+
+    fn budgeted_tree(max_bytes: usize) -> BPlusTreeMap<String, String> {
+        let mut tree = BPlusTreeMap::new(32).unwrap();
+        tree.set_byte_budget(Some(ByteBudget::new(
+            max_bytes,
+            |key: &String, value: &String| key.len() + value.len(),
+        )));
+        tree
+    }
+
+    /// Values from 0 to 199 bytes long, in a fixed shuffled order.
+    fn entries(count: u64) -> Vec<(String, String)> {
+        let mut rng = SeededRng::new(7);
+        (0..count)
+            .map(|_| {
+                let key = format!("{:08}", rng.below(100_000));
+                let value = "x".repeat(rng.below(200) as usize);
+                (key, value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_split_point_balances_weight() {
+        let budget = ByteBudget::new(100, |_: &u32, value: &usize| *value);
+        let mut leaf = LeafNode::new(8);
+        for (i, weight) in [10, 10, 10, 60].into_iter().enumerate() {
+            leaf.insert_at_index(i, i as u32 * 2, weight);
+        }
+        // The heavy last entry sits alone on the right
+        assert_eq!(budget.split_point(&leaf, 0, &0, &5), (3, true));
+        assert_eq!(budget.split_point(&leaf, 4, &9, &1), (3, false));
+        // A heavy new entry at the front is kept apart
+        assert_eq!(budget.split_point(&leaf, 0, &0, &500), (0, true));
+    }
+
+    #[test]
+    fn test_inserts_keep_leaves_within_budget() {
+        let mut tree = budgeted_tree(512);
+        let mut model = BTreeMap::new();
+        for (key, value) in entries(3_000) {
+            assert_eq!(
+                tree.insert(key.clone(), value.clone()),
+                model.insert(key, value)
+            );
+        }
+        assert!(tree.items().eq(model.iter()));
+        assert!(tree
+            .leaf_byte_sizes()
+            .unwrap()
+            .iter()
+            .all(|&bytes| bytes <= 512));
+        assert!(tree.check_invariants());
+
+        // Without a budget the same entries make much heavier leaves
+        let mut counted = BPlusTreeMap::new(32).unwrap();
+        for (key, value) in model {
+            counted.insert(key, value);
+        }
+        assert!(counted.leaf_count() < tree.leaf_count() / 2);
+    }
+
+    #[test]
+    fn test_removals_merge_only_within_budget() {
+        let mut tree = budgeted_tree(512);
+        let mut model = BTreeMap::new();
+        for (key, value) in entries(3_000) {
+            tree.insert(key.clone(), value.clone());
+            model.insert(key, value);
+        }
+        let keys: Vec<String> = model.keys().cloned().collect();
+        for key in keys.iter().step_by(3) {
+            assert_eq!(tree.remove(key), model.remove(key));
+        }
+        let before = tree.leaf_count();
+        for key in keys.iter().skip(1).step_by(3) {
+            assert_eq!(tree.remove(key), model.remove(key));
+        }
+
+        assert!(tree.items().eq(model.iter()));
+        assert!(tree.leaf_count() < before);
+        assert!(tree
+            .leaf_byte_sizes()
+            .unwrap()
+            .iter()
+            .all(|&bytes| bytes <= 512));
+        assert!(tree.check_invariants());
+    }
+
+    #[test]
+    fn test_entry_heavier_than_budget_gets_own_leaf() {
+        let mut tree = budgeted_tree(64);
+        tree.insert("a".to_string(), "x".repeat(10));
+        tree.insert("b".to_string(), "x".repeat(500));
+        tree.insert("c".to_string(), "x".repeat(10));
+        let sizes = tree.leaf_byte_sizes().unwrap();
+        assert_eq!(sizes, vec![11, 501, 11]);
+        assert!(tree.check_invariants());
+
+        tree.set_byte_budget(None);
+        assert!(tree.leaf_byte_sizes().is_none());
+    }
+}
@@ -0,0 +1,355 @@
+//! Memcomparable byte keys.
+//!
+//! Composite keys such as `(tenant, timestamp, name)` normally need a derived
+//! or hand-written `Ord`. Encoding them with [`MemComparable`] instead turns
+//! each key into bytes whose lexicographic order is the order of the original
+//! values, as in the key encodings of RocksDB-backed stores. One
+//! [`BytesTree`] can then hold keys of any shape, compared byte by byte.
+//!
+//! Every encoding is self-delimiting: integers have a fixed width and byte
+//! strings end in a terminator that cannot occur inside them. A tuple is its
+//! fields' encodings back to back, so the encoding of its leading fields is a
+//! byte prefix of the whole, and [`prefix_range`](BPlusTreeMap::prefix_range)
+//! finds every key that shares them.
+
+use crate::bounds::TreeValue;
+use crate::iteration::RangeIterator;
+use crate::types::BPlusTreeMap;
+use std::ops::Bound;
+
+/// A tree keyed by memcomparable byte strings.
+pub type BytesTree<V> = BPlusTreeMap<Vec<u8>, V>;
+
+/// Escapes a zero byte inside a byte string.
+const ESCAPE: u8 = 0xFF;
+/// Follows the zero byte that ends a byte string. Lower than [`ESCAPE`], so
+/// a string sorts before every longer string it is a prefix of.
+const TERMINATOR: u8 = 0x01;
+
+/// A type with an order-preserving byte encoding.
+///
+/// For any two values, `a.cmp(&b) == a.to_key_bytes().cmp(&b.to_key_bytes())`.
+/// Implemented for integers, `bool`, `String`, `Vec<u8>`, `Option` and tuples
+/// of up to four encodable fields.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::{BytesTree, MemComparable};
+///
+/// let mut events = BytesTree::new(16).unwrap();
+/// events.insert(("tenant-b".to_string(), -5i64).to_key_bytes(), "b early");
+/// events.insert(("tenant-a".to_string(), 10i64).to_key_bytes(), "a late");
+/// events.insert(("tenant-a".to_string(), -3i64).to_key_bytes(), "a early");
+///
+/// let tenant_a = ("tenant-a".to_string(),).to_key_bytes();
+/// let found: Vec<_> = events.prefix_range(&tenant_a).map(|(_, v)| *v).collect();
+/// assert_eq!(found, ["a early", "a late"]);
+///
+/// let (key, _) = events.first().unwrap();
+/// assert_eq!(
+///     <(String, i64)>::from_key_bytes(key),
+///     Some(("tenant-a".to_string(), -3))
+/// );
+/// ```
+pub trait MemComparable: Sized {
+    /// Append the encoding of `self` to `out`.
+    fn encode_into(&self, out: &mut Vec<u8>);
+
+    /// Decode a value from the front of `input` and advance past it.
+    /// Returns `None` if `input` does not start with a valid encoding.
+    fn decode_from(input: &mut &[u8]) -> Option<Self>;
+
+    /// The encoding of `self` as a new byte key.
+    fn to_key_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_into(&mut out);
+        out
+    }
+
+    /// Decode a key holding exactly one value, with no bytes left over.
+    fn from_key_bytes(mut bytes: &[u8]) -> Option<Self> {
+        let value = Self::decode_from(&mut bytes)?;
+        bytes.is_empty().then_some(value)
+    }
+}
+
+/// Split `N` bytes off the front of `input`.
+fn take<const N: usize>(input: &mut &[u8]) -> Option<[u8; N]> {
+    let (head, rest) = input.split_first_chunk::<N>()?;
+    *input = rest;
+    Some(*head)
+}
+
+macro_rules! impl_mem_comparable_unsigned {
+    ($($t:ty),*) => {
+        $(
+            impl MemComparable for $t {
+                fn encode_into(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_be_bytes());
+                }
+
+                fn decode_from(input: &mut &[u8]) -> Option<Self> {
+                    take(input).map(<$t>::from_be_bytes)
+                }
+            }
+        )*
+    };
+}
+
+// Signed integers flip the sign bit, so negative values sort below positive
+macro_rules! impl_mem_comparable_signed {
+    ($($t:ty => $u:ty),*) => {
+        $(
+            impl MemComparable for $t {
+                fn encode_into(&self, out: &mut Vec<u8>) {
+                    let flipped = (*self as $u) ^ (1 << (<$u>::BITS - 1));
+                    out.extend_from_slice(&flipped.to_be_bytes());
+                }
+
+                fn decode_from(input: &mut &[u8]) -> Option<Self> {
+                    let flipped = <$u>::from_be_bytes(take(input)?);
+                    Some((flipped ^ (1 << (<$u>::BITS - 1))) as $t)
+                }
+            }
+        )*
+    };
+}
+
+impl_mem_comparable_unsigned!(u8, u16, u32, u64, u128);
+impl_mem_comparable_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128);
+
+// Pointer-sized integers are encoded at 64 bits, so keys do not depend on
+// the platform that wrote them
+impl MemComparable for usize {
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode_into(out);
+    }
+
+    fn decode_from(input: &mut &[u8]) -> Option<Self> {
+        usize::try_from(u64::decode_from(input)?).ok()
+    }
+}
+
+impl MemComparable for isize {
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        (*self as i64).encode_into(out);
+    }
+
+    fn decode_from(input: &mut &[u8]) -> Option<Self> {
+        isize::try_from(i64::decode_from(input)?).ok()
+    }
+}
+
+impl MemComparable for bool {
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        out.push(u8::from(*self));
+    }
+
+    fn decode_from(input: &mut &[u8]) -> Option<Self> {
+        match take::<1>(input)? {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        }
+    }
+}
+
+/// Append `bytes` with each zero byte escaped, then the terminator.
+fn encode_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    for &byte in bytes {
+        out.push(byte);
+        if byte == 0 {
+            out.push(ESCAPE);
+        }
+    }
+    out.extend_from_slice(&[0, TERMINATOR]);
+}
+
+/// Decode a byte string written by [`encode_bytes`].
+fn decode_bytes(input: &mut &[u8]) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    loop {
+        match take::<1>(input)? {
+            [0] => match take::<1>(input)? {
+                [ESCAPE] => bytes.push(0),
+                [TERMINATOR] => return Some(bytes),
+                _ => return None,
+            },
+            [byte] => bytes.push(byte),
+        }
+    }
+}
+
+impl MemComparable for Vec<u8> {
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        encode_bytes(self, out);
+    }
+
+    fn decode_from(input: &mut &[u8]) -> Option<Self> {
+        decode_bytes(input)
+    }
+}
+
+impl MemComparable for String {
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        encode_bytes(self.as_bytes(), out);
+    }
+
+    fn decode_from(input: &mut &[u8]) -> Option<Self> {
+        String::from_utf8(decode_bytes(input)?).ok()
+    }
+}
+
+impl<T: MemComparable> MemComparable for Option<T> {
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(value) => {
+                out.push(1);
+                value.encode_into(out);
+            }
+        }
+    }
+
+    fn decode_from(input: &mut &[u8]) -> Option<Self> {
+        match take::<1>(input)? {
+            [0] => Some(None),
+            [1] => T::decode_from(input).map(Some),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_mem_comparable_tuple {
+    ($(($($name:ident),+)),*) => {
+        $(
+            impl<$($name: MemComparable),+> MemComparable for ($($name,)+) {
+                #[allow(non_snake_case)]
+                fn encode_into(&self, out: &mut Vec<u8>) {
+                    let ($($name,)+) = self;
+                    $($name.encode_into(out);)+
+                }
+
+                fn decode_from(input: &mut &[u8]) -> Option<Self> {
+                    Some(($($name::decode_from(input)?,)+))
+                }
+            }
+        )*
+    };
+}
+
+impl_mem_comparable_tuple!((A), (A, B), (A, B, C), (A, B, C, D));
+
+/// The smallest byte string above every string that starts with `prefix`,
+/// or `None` if there is none.
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition(|&byte| byte != u8::MAX)?;
+    let mut successor = prefix[..=last].to_vec();
+    successor[last] += 1;
+    Some(successor)
+}
+
+impl<V: TreeValue> BPlusTreeMap<Vec<u8>, V> {
+    /// Returns an iterator over the entries whose keys start with `prefix`,
+    /// in key order.
+    ///
+    /// With [`MemComparable`] keys, passing the encoding of a tuple's leading
+    /// fields visits every key that has those fields.
+    pub fn prefix_range(&self, prefix: &[u8]) -> RangeIterator<'_, Vec<u8>, V> {
+        let end = match prefix_successor(prefix) {
+            Some(successor) => Bound::Excluded(successor),
+            None => Bound::Unbounded,
+        };
+        self.range((Bound::Included(prefix.to_vec()), end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use std::fmt::Debug;
+
+    fn assert_order_preserved<T: MemComparable + Ord + Debug>(values: &[T]) {
+        for a in values {
+            let encoded = a.to_key_bytes();
+            assert_eq!(T::from_key_bytes(&encoded).as_ref(), Some(a));
+            for b in values {
+                assert_eq!(
+                    a.cmp(b),
+                    encoded.cmp(&b.to_key_bytes()),
+                    "{:?} vs {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_encodings_preserve_order() {
+        let mut rng = SeededRng::new(5);
+
+        let mut signed: Vec<i64> = vec![i64::MIN, -1, 0, 1, i64::MAX];
+        let mut small: Vec<i8> = vec![i8::MIN, -1, 0, 1, i8::MAX];
+        let mut unsigned: Vec<u32> = vec![0, 1, u32::MAX];
+        let mut strings: Vec<String> = ["", "\0", "\0\0", "a", "a\0", "a\0b", "a\u{1}", "ab"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mut tuples = Vec::new();
+        for _ in 0..60 {
+            let r = rng.next_u64();
+            signed.push((r as i64) >> (r % 60));
+            small.push(r as i8);
+            unsigned.push((r >> 32) as u32);
+            let text: String = (0..r % 4)
+                .map(|i| ["\0", "a", "b"][((r >> (8 + i * 2)) % 3) as usize])
+                .collect();
+            strings.push(text.clone());
+            tuples.push(((r % 3) as i32 - 1, text, r.is_multiple_of(5), (r >> 20) % 2));
+        }
+
+        assert_order_preserved(&signed);
+        assert_order_preserved(&small);
+        assert_order_preserved(&unsigned);
+        assert_order_preserved(&strings);
+        assert_order_preserved(&tuples);
+        assert_order_preserved(&[None, Some(-1isize), Some(0), Some(7)]);
+        assert_order_preserved(&[vec![], vec![0u8], vec![0, 0], vec![0, 255], vec![1]]);
+    }
+
+    #[test]
+    fn test_decode_rejects_invalid_input() {
+        assert_eq!(u32::from_key_bytes(&[0, 0, 1]), None);
+        assert_eq!(u8::from_key_bytes(&[1, 2]), None);
+        assert_eq!(bool::from_key_bytes(&[2]), None);
+        assert_eq!(String::from_key_bytes(b"abc"), None);
+        assert_eq!(String::from_key_bytes(&[b'a', 0, 7]), None);
+        assert_eq!(String::from_key_bytes(&[0xC0, 0, TERMINATOR]), None);
+        assert_eq!(<Option<u8>>::from_key_bytes(&[3, 1]), None);
+    }
+
+    #[test]
+    fn test_prefix_range_matches_filter() {
+        let mut tree = BytesTree::new(4).unwrap();
+        for tenant in 0u8..6 {
+            for seq in 0u16..40 {
+                let tenant = if tenant == 5 { u8::MAX } else { tenant };
+                tree.insert((tenant, seq).to_key_bytes(), (tenant, seq));
+            }
+        }
+
+        for tenant in [0u8, 3, u8::MAX] {
+            let prefix = (tenant,).to_key_bytes();
+            let found: Vec<_> = tree.prefix_range(&prefix).map(|(_, v)| *v).collect();
+            let expected: Vec<_> = (0..40).map(|seq| (tenant, seq)).collect();
+            assert_eq!(found, expected);
+        }
+        assert_eq!(tree.prefix_range(&[]).count(), tree.len());
+        assert_eq!(tree.prefix_range(&[7]).count(), 0);
+        assert_eq!(prefix_successor(&[1, 255, 255]), Some(vec![2]));
+        assert_eq!(prefix_successor(&[255]), None);
+    }
+}
@@ -0,0 +1,335 @@
+//! Read-through and write-through caching over a slower store.
+//!
+//! [`CachedTree`] answers lookups from a [`BPlusTreeMap`] and falls back to a
+//! [`Loader`] on a miss, keeping what it loads. Because the cache is ordered,
+//! a whole key range can be pulled in ahead of a scan with
+//! [`warm_range`](CachedTree::warm_range) rather than faulting keys in one at
+//! a time.
+
+use crate::error::InitResult;
+use crate::types::BPlusTreeMap;
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+/// The backing store behind a [`CachedTree`].
+///
+/// Only [`load`](Loader::load) is required. Closures `FnMut(&K) -> Option<V>`
+/// implement the trait as read-only loaders.
+pub trait Loader<K, V> {
+    /// Fetch the value for `key`, or `None` if the store has none.
+    fn load(&mut self, key: &K) -> Option<V>;
+
+    /// Fetch every entry between `start` and `end`, in any order. The default
+    /// loads nothing, which makes warming a no-op.
+    fn load_range(&mut self, _start: Bound<&K>, _end: Bound<&K>) -> Vec<(K, V)> {
+        Vec::new()
+    }
+
+    /// Persist a value written through the cache. The default discards it.
+    fn store(&mut self, _key: &K, _value: &V) {}
+
+    /// Delete a key removed through the cache. The default does nothing.
+    fn delete(&mut self, _key: &K) {}
+}
+
+impl<K, V, F: FnMut(&K) -> Option<V>> Loader<K, V> for F {
+    fn load(&mut self, key: &K) -> Option<V> {
+        self(key)
+    }
+}
+
+/// A cached value and when it stops being fresh.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry<V> {
+    /// The cached value.
+    pub value: V,
+    /// Clock reading from which the value must be reloaded, if it expires.
+    pub expires_at: Option<u64>,
+}
+
+struct Expiry {
+    ttl: u64,
+    clock: Box<dyn FnMut() -> u64>,
+}
+
+/// A tree that loads missing keys from a [`Loader`] and writes changes
+/// through to it.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::CachedTree;
+///
+/// let mut loads = 0;
+/// let mut squares = CachedTree::new(16, move |n: &u64| {
+///     loads += 1;
+///     assert!(loads <= 2, "cached keys are not reloaded");
+///     Some(n * n)
+/// })
+/// .unwrap();
+///
+/// assert_eq!(squares.get(&12), Some(&144));
+/// assert_eq!(squares.get(&12), Some(&144));
+/// assert_eq!(squares.get(&7), Some(&49));
+/// assert_eq!((squares.hits(), squares.misses()), (1, 2));
+/// ```
+pub struct CachedTree<K, V, L> {
+    cache: BPlusTreeMap<K, CacheEntry<V>>,
+    loader: L,
+    expiry: Option<Expiry>,
+    hits: usize,
+    misses: usize,
+}
+
+impl<K: Ord + Clone, V: Clone, L: Loader<K, V>> CachedTree<K, V, L> {
+    /// Create an empty cache whose entries never expire.
+    pub fn new(capacity: usize, loader: L) -> InitResult<Self> {
+        Ok(Self {
+            cache: BPlusTreeMap::new(capacity)?,
+            loader,
+            expiry: None,
+            hits: 0,
+            misses: 0,
+        })
+    }
+
+    /// Create an empty cache whose entries are reloaded once `ttl` has
+    /// passed on `clock` since they were cached.
+    pub fn with_ttl(
+        capacity: usize,
+        loader: L,
+        ttl: u64,
+        clock: impl FnMut() -> u64 + 'static,
+    ) -> InitResult<Self> {
+        let mut cached = Self::new(capacity, loader)?;
+        cached.expiry = Some(Expiry {
+            ttl,
+            clock: Box::new(clock),
+        });
+        Ok(cached)
+    }
+
+    /// The cached entries, including any that have expired.
+    pub fn tree(&self) -> &BPlusTreeMap<K, CacheEntry<V>> {
+        &self.cache
+    }
+
+    /// The backing store.
+    pub fn loader(&self) -> &L {
+        &self.loader
+    }
+
+    /// Number of cached entries, including any that have expired.
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Returns true if nothing is cached.
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Number of lookups answered from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Number of lookups that went to the loader.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Get the value for `key`, loading and caching it on a miss or after
+    /// it has expired.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let now = self.now();
+        let fresh = self
+            .cache
+            .get(key)
+            .is_some_and(|entry| entry.expires_at.is_none_or(|expires| now < expires));
+        if fresh {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+            match self.loader.load(key) {
+                Some(value) => {
+                    self.cache_value(key.clone(), value, now);
+                }
+                None => {
+                    self.cache.remove(key);
+                }
+            }
+        }
+        self.cache.get(key).map(|entry| &entry.value)
+    }
+
+    /// Load every entry in `range` from the store and cache it, returning
+    /// how many were loaded.
+    pub fn warm_range<R: RangeBounds<K>>(&mut self, range: R) -> usize {
+        let now = self.now();
+        let loaded = self
+            .loader
+            .load_range(range.start_bound(), range.end_bound());
+        let count = loaded.len();
+        for (key, value) in loaded {
+            self.cache_value(key, value, now);
+        }
+        count
+    }
+
+    /// Write `value` to the store and the cache, returning the previously
+    /// cached value if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.loader.store(&key, &value);
+        let now = self.now();
+        self.cache_value(key, value, now)
+    }
+
+    /// Delete `key` from the store and the cache, returning the previously
+    /// cached value if any.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.loader.delete(key);
+        self.invalidate(key)
+    }
+
+    /// Drop `key` from the cache only, so the next lookup reloads it.
+    pub fn invalidate(&mut self, key: &K) -> Option<V> {
+        self.cache.remove(key).map(|entry| entry.value)
+    }
+
+    /// Drop every cached entry.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    fn now(&mut self) -> u64 {
+        self.expiry.as_mut().map_or(0, |expiry| (expiry.clock)())
+    }
+
+    fn cache_value(&mut self, key: K, value: V, now: u64) -> Option<V> {
+        let expires_at = self
+            .expiry
+            .as_ref()
+            .map(|expiry| now.saturating_add(expiry.ttl));
+        self.cache
+            .insert(key, CacheEntry { value, expires_at })
+            .map(|entry| entry.value)
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, L> fmt::Debug for CachedTree<K, V, L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachedTree")
+            .field("cache", &self.cache)
+            .field("ttl", &self.expiry.as_ref().map(|expiry| expiry.ttl))
+            .field("hits", &self.hits)
+            .field("misses", &self.misses)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::collections::BTreeMap;
+    use std::rc::Rc;
+
+    /// A store backed by a `BTreeMap` that counts how often it is read.
+    #[derive(Default)]
+    struct Store {
+        rows: BTreeMap<i32, String>,
+        reads: usize,
+    }
+
+    impl Loader<i32, String> for Store {
+        fn load(&mut self, key: &i32) -> Option<String> {
+            self.reads += 1;
+            self.rows.get(key).cloned()
+        }
+
+        fn load_range(&mut self, start: Bound<&i32>, end: Bound<&i32>) -> Vec<(i32, String)> {
+            self.reads += 1;
+            self.rows
+                .range((start, end))
+                .map(|(k, v)| (*k, v.clone()))
+                .collect()
+        }
+
+        fn store(&mut self, key: &i32, value: &String) {
+            self.rows.insert(*key, value.clone());
+        }
+
+        fn delete(&mut self, key: &i32) {
+            self.rows.remove(key);
+        }
+    }
+
+    fn store_with(rows: std::ops::Range<i32>) -> Store {
+        Store {
+            rows: rows.map(|i| (i, i.to_string())).collect(),
+            reads: 0,
+        }
+    }
+
+    #[test]
+    fn test_read_through_and_write_through() {
+        let mut cached = CachedTree::new(4, store_with(0..100)).unwrap();
+        assert_eq!(cached.get(&42).map(String::as_str), Some("42"));
+        assert_eq!(cached.get(&42).map(String::as_str), Some("42"));
+        assert_eq!(cached.get(&500), None);
+        assert_eq!(cached.loader().reads, 2);
+        assert_eq!(cached.len(), 1);
+
+        cached.insert(500, "new".to_string());
+        assert_eq!(
+            cached.loader().rows.get(&500).map(String::as_str),
+            Some("new")
+        );
+        assert_eq!(cached.get(&500).map(String::as_str), Some("new"));
+        assert_eq!(cached.remove(&42).as_deref(), Some("42"));
+        assert!(!cached.loader().rows.contains_key(&42));
+        assert_eq!(cached.get(&42), None);
+        assert_eq!(cached.loader().reads, 3);
+    }
+
+    #[test]
+    fn test_warm_range_avoids_per_key_loads() {
+        let mut cached = CachedTree::new(4, store_with(0..1_000)).unwrap();
+        assert_eq!(cached.warm_range(200..300), 100);
+        for key in 200..300 {
+            assert_eq!(cached.get(&key), Some(&key.to_string()));
+        }
+        assert_eq!(cached.loader().reads, 1);
+        assert_eq!(cached.hits(), 100);
+        assert!(cached.tree().check_invariants());
+
+        // Closures have no range loader, so warming is a no-op
+        let mut lazy = CachedTree::new(4, |key: &i32| Some(*key)).unwrap();
+        assert_eq!(lazy.warm_range(..), 0);
+        assert_eq!(lazy.get(&3), Some(&3));
+    }
+
+    #[test]
+    fn test_expired_entries_are_reloaded() {
+        let now = Rc::new(Cell::new(0));
+        let clock = Rc::clone(&now);
+        let mut cached =
+            CachedTree::with_ttl(4, store_with(0..10), 100, move || clock.get()).unwrap();
+
+        cached.get(&1);
+        now.set(99);
+        cached.get(&1);
+        assert_eq!(cached.loader().reads, 1);
+
+        now.set(100);
+        cached.get(&1);
+        assert_eq!(cached.loader().reads, 2);
+
+        // A key that vanished from the store is dropped once it expires
+        cached.loader.rows.remove(&1);
+        now.set(250);
+        assert_eq!(cached.get(&1), None);
+        assert!(cached.is_empty());
+    }
+}
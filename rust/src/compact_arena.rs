@@ -1,6 +1,7 @@
 //! Compact arena implementation using Vec<T> instead of Vec<Option<T>>
 //! This eliminates the Option wrapper overhead for better performance
 
+use crate::invariant::{exhausted, invariant};
 use std::convert::TryFrom;
 use std::fmt::Debug;
 
@@ -15,6 +16,42 @@ pub struct CompactArenaStats {
     pub free_count: usize,
     pub utilization: f64,
     pub fragmentation: f64,
+    /// Allocations since creation or the last `reset_stats`
+    pub allocations: u64,
+    /// Deallocations since creation or the last `reset_stats`
+    pub deallocations: u64,
+    /// Most items allocated at once
+    pub peak_allocated: usize,
+    /// Longest the free list has been
+    pub peak_free: usize,
+    /// Times the backing storage had to grow
+    pub growth_events: u64,
+}
+
+/// Unused space inside the key, value and child vectors of live nodes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NodeStorageStats {
+    /// Bytes reserved by leaf vectors but not holding a key or value
+    pub leaf_wasted_bytes: usize,
+    /// Bytes reserved by branch vectors but not holding a key or child
+    pub branch_wasted_bytes: usize,
+}
+
+impl NodeStorageStats {
+    /// Wasted bytes across leaves and branches
+    pub fn wasted_bytes(&self) -> usize {
+        self.leaf_wasted_bytes + self.branch_wasted_bytes
+    }
+}
+
+/// Running counters behind the history fields of `CompactArenaStats`
+#[derive(Debug, Clone, Copy, Default)]
+struct ArenaCounters {
+    allocations: u64,
+    deallocations: u64,
+    peak_allocated: usize,
+    peak_free: usize,
+    growth_events: u64,
 }
 
 /// Compact arena allocator that eliminates Option wrapper overhead
@@ -29,6 +66,8 @@ pub struct CompactArena<T> {
     generation: u32,
     /// Track which slots are actually allocated
     allocated_mask: Vec<bool>,
+    /// Allocation history for capacity planning
+    counters: ArenaCounters,
 }
 
 impl<T> CompactArena<T> {
@@ -39,6 +78,7 @@ impl<T> CompactArena<T> {
             free_list: Vec::new(),
             generation: 0,
             allocated_mask: Vec::new(),
+            counters: ArenaCounters::default(),
         }
     }
 
@@ -49,6 +89,7 @@ impl<T> CompactArena<T> {
             free_list: Vec::new(),
             generation: 0,
             allocated_mask: Vec::with_capacity(capacity),
+            counters: ArenaCounters::default(),
         }
     }
 
@@ -65,12 +106,21 @@ impl<T> CompactArena<T> {
         } else {
             // Allocate new slot
             let index = self.storage.len();
+            if index == self.storage.capacity() {
+                self.counters.growth_events += 1;
+            }
             self.storage.push(item);
             self.allocated_mask.push(true);
             index
         };
 
-        NodeId::try_from(index).expect("Index should fit in NodeId")
+        self.counters.allocations += 1;
+        self.counters.peak_allocated = self.counters.peak_allocated.max(self.len());
+
+        match NodeId::try_from(index) {
+            Ok(id) => id,
+            Err(_) => exhausted("arena holds more nodes than a NodeId can address"),
+        }
     }
 
     /// Deallocate an item from the arena and return it (requires Default)
@@ -93,6 +143,7 @@ impl<T> CompactArena<T> {
         // Mark as free
         self.allocated_mask[index] = false;
         self.free_list.push(index);
+        self.record_deallocation();
 
         // Replace with default and return the old value
         let old_value = std::mem::take(&mut self.storage[index]);
@@ -115,6 +166,7 @@ impl<T> CompactArena<T> {
         // Mark as free
         self.allocated_mask[index] = false;
         self.free_list.push(index);
+        self.record_deallocation();
         true
     }
 
@@ -152,6 +204,23 @@ impl<T> CompactArena<T> {
         }
     }
 
+    /// Get mutable references to two distinct items at once
+    /// Returns None if the IDs are equal or either one is not allocated
+    pub fn get_pair_mut(&mut self, a: NodeId, b: NodeId) -> Option<(&mut T, &mut T)> {
+        if a == b || !self.contains(a) || !self.contains(b) {
+            return None;
+        }
+
+        let (a, b) = (a as usize, b as usize);
+        if a < b {
+            let (low, high) = self.storage.split_at_mut(b);
+            Some((&mut low[a], &mut high[0]))
+        } else {
+            let (low, high) = self.storage.split_at_mut(a);
+            Some((&mut high[0], &mut low[b]))
+        }
+    }
+
     /// Unsafe fast access without bounds checking or allocation verification
     ///
     /// # Safety
@@ -206,9 +275,30 @@ impl<T> CompactArena<T> {
             free_count,
             utilization,
             fragmentation,
+            allocations: self.counters.allocations,
+            deallocations: self.counters.deallocations,
+            peak_allocated: self.counters.peak_allocated,
+            peak_free: self.counters.peak_free,
+            growth_events: self.counters.growth_events,
         }
     }
 
+    /// Restart the allocation history: counts go to zero and peaks to the
+    /// current values
+    pub fn reset_stats(&mut self) {
+        self.counters = ArenaCounters {
+            peak_allocated: self.len(),
+            peak_free: self.free_list.len(),
+            ..ArenaCounters::default()
+        };
+    }
+
+    #[inline]
+    fn record_deallocation(&mut self) {
+        self.counters.deallocations += 1;
+        self.counters.peak_free = self.counters.peak_free.max(self.free_list.len());
+    }
+
     /// Compact the arena by removing gaps (expensive operation)
     pub fn compact(&mut self)
     where
@@ -241,12 +331,41 @@ impl<T> CompactArena<T> {
         // In a real implementation, you'd need to update all references
     }
 
+    /// Mutable references to every slot, indexed by id, with `None` for
+    /// free slots. Callers take the items they need out of the `Vec`, which
+    /// lets them hold several at once without aliasing.
+    pub(crate) fn slots_mut(&mut self) -> Vec<Option<&mut T>> {
+        self.storage
+            .iter_mut()
+            .zip(&self.allocated_mask)
+            .map(|(item, &allocated)| allocated.then_some(item))
+            .collect()
+    }
+
+    /// Iterate over the allocated items
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &T> {
+        self.storage
+            .iter()
+            .zip(&self.allocated_mask)
+            .filter_map(|(item, &allocated)| allocated.then_some(item))
+    }
+
     /// Get the number of allocated items
     pub fn len(&self) -> usize {
-        self.allocated_mask
-            .iter()
-            .filter(|&&allocated| allocated)
-            .count()
+        // Every unallocated slot is on the free list
+        self.storage.len() - self.free_list.len()
+    }
+
+    /// Reserve room for `additional` more allocations without reallocating.
+    /// Free slots are reused first, so only the remainder needs new storage.
+    pub fn reserve(&mut self, additional: usize) {
+        let new_slots = additional.saturating_sub(self.free_list.len());
+        let before = self.storage.capacity();
+        self.storage.reserve(new_slots);
+        if self.storage.capacity() != before {
+            self.counters.growth_events += 1;
+        }
+        self.allocated_mask.reserve(new_slots);
     }
 
     /// Check if the arena is empty
@@ -261,6 +380,7 @@ impl<T> CompactArena<T> {
 
     /// Clear all items from the arena
     pub fn clear(&mut self) {
+        self.counters.deallocations += self.len() as u64;
         self.storage.clear();
         self.allocated_mask.clear();
         self.free_list.clear();
@@ -308,6 +428,7 @@ impl<T: Default> CompactArena<T> {
         // Mark as free and replace with default
         self.allocated_mask[index] = false;
         self.free_list.push(index);
+        self.record_deallocation();
 
         let old_value = std::mem::take(&mut self.storage[index]);
         Some(old_value)
@@ -320,9 +441,10 @@ impl<T: Default> CompactArena<T> {
 // BPLUSTREE ARENA ALLOCATION HELPERS
 // ============================================================================
 
-use crate::types::{BPlusTreeMap, BranchNode, LeafNode};
+use crate::bounds::{TreeKey, TreeValue};
+use crate::types::{BPlusTreeMap, BranchNode, LeafNode, NodeRef, NodeVec, INLINE_ROOT};
 
-impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
     // ============================================================================
     // ARENA ALLOCATION METHODS
     // ============================================================================
@@ -330,6 +452,7 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
     /// Allocate a new leaf node in the arena and return its ID.
     #[inline]
     pub fn allocate_leaf(&mut self, leaf: LeafNode<K, V>) -> NodeId {
+        self.bump_generation();
         self.leaf_arena.allocate(leaf)
     }
 
@@ -339,19 +462,48 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
     pub fn allocate_leaf_with_data(
         &mut self,
         capacity: usize,
-        keys: Vec<K>,
-        values: Vec<V>,
+        keys: NodeVec<K>,
+        values: NodeVec<V>,
         next: NodeId,
     ) -> NodeId {
-        let leaf = LeafNode {
+        let mut leaf = LeafNode {
             capacity,
             keys,
             values,
             next,
         };
+        leaf.reserve_slots();
+        self.bump_generation();
         self.leaf_arena.allocate(leaf)
     }
 
+    /// Move the inline root leaf into the arena, so that the root can become
+    /// a branch. Does nothing once the root lives in the arena.
+    pub(crate) fn spill_inline_root(&mut self) {
+        if let Some(leaf) = self.inline_root.take() {
+            let id = self.allocate_leaf(leaf);
+            self.root = NodeRef::leaf(id);
+        }
+    }
+
+    /// Move a root leaf that lives in the arena back inline, once the tree
+    /// has shrunk to a single leaf.
+    pub(crate) fn unspill_root(&mut self) {
+        if let NodeRef::Leaf(id, _) = self.root {
+            if id != INLINE_ROOT {
+                self.inline_root = self.deallocate_leaf(id);
+                self.root = NodeRef::leaf(INLINE_ROOT);
+            }
+        }
+    }
+
+    /// Make the root an empty inline leaf again.
+    pub(crate) fn reset_inline_root(&mut self) {
+        self.bump_generation();
+        self.inline_root = Some(LeafNode::new(self.capacity));
+        self.root = NodeRef::leaf(INLINE_ROOT);
+    }
+
     /// Allocate a new branch node in the arena and return its ID.
     #[inline]
     pub fn allocate_branch(&mut self, branch: BranchNode<K, V>) -> NodeId {
@@ -361,6 +513,7 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
     /// Deallocate a leaf node from the arena.
     #[inline]
     pub fn deallocate_leaf(&mut self, id: NodeId) -> Option<LeafNode<K, V>> {
+        self.bump_generation();
         self.leaf_arena.deallocate(id)
     }
 
@@ -414,6 +567,22 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
         self.branch_arena.stats()
     }
 
+    /// Measure the space node vectors hold in reserve. Nodes keep room for a
+    /// full load, so some waste is expected; merges and borrows release
+    /// anything beyond that.
+    pub fn node_storage_stats(&self) -> NodeStorageStats {
+        NodeStorageStats {
+            leaf_wasted_bytes: self.leaf_arena.iter().map(LeafNode::spare_bytes).sum(),
+            branch_wasted_bytes: self.branch_arena.iter().map(BranchNode::spare_bytes).sum(),
+        }
+    }
+
+    /// Restart the allocation history of both node arenas.
+    pub fn reset_arena_stats(&mut self) {
+        self.leaf_arena.reset_stats();
+        self.branch_arena.reset_stats();
+    }
+
     /// Set the next pointer of a leaf node in the arena.
     pub fn set_leaf_next(&mut self, id: NodeId, next_id: NodeId) -> bool {
         self.get_leaf_mut(id)
@@ -430,9 +599,18 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
 
     /// Unsafe fast access to leaf node (no bounds checking)
     ///
+    /// The inline root is still checked: an `INLINE_ROOT` id with no inline
+    /// root panics rather than reading past it.
+    ///
     /// # Safety
     /// Caller must ensure id is valid and allocated
     pub unsafe fn get_leaf_unchecked(&self, id: NodeId) -> &LeafNode<K, V> {
+        if id == INLINE_ROOT {
+            return invariant(
+                self.inline_root.as_ref(),
+                "INLINE_ROOT id without an inline root",
+            );
+        }
         self.leaf_arena.get_unchecked(id)
     }
 
@@ -448,6 +626,7 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model_test::SeededRng;
 
     #[test]
     fn test_compact_arena_basic_operations() {
@@ -491,6 +670,123 @@ mod tests {
         assert_eq!(stats.free_count, 0);
     }
 
+    #[test]
+    fn test_compact_arena_history() {
+        let mut arena = CompactArena::new();
+        let ids: Vec<NodeId> = (0..10).map(|i| arena.allocate(i)).collect();
+        for &id in &ids[..6] {
+            arena.deallocate_with_default(id);
+        }
+        arena.allocate(100);
+
+        let stats = arena.stats();
+        assert_eq!((stats.allocations, stats.deallocations), (11, 6));
+        assert_eq!((stats.peak_allocated, stats.peak_free), (10, 6));
+        assert!(stats.growth_events >= 1);
+
+        arena.reset_stats();
+        let stats = arena.stats();
+        assert_eq!((stats.allocations, stats.deallocations), (0, 0));
+        assert_eq!((stats.peak_allocated, stats.peak_free), (5, 5));
+        assert_eq!(stats.growth_events, 0);
+
+        arena.reserve(1_000);
+        arena.clear();
+        let stats = arena.stats();
+        assert_eq!(stats.deallocations, 5);
+        assert_eq!(stats.peak_allocated, 5);
+        assert_eq!(stats.growth_events, 1);
+    }
+
+    /// Most items a node vector with room for `limit` may reserve
+    fn reserved_limit(limit: usize) -> usize {
+        #[cfg(feature = "smallvec")]
+        let limit = limit.max(crate::node::NODE_INLINE);
+        limit
+    }
+
+    #[test]
+    fn test_node_storage_stays_within_full_node_size() {
+        let capacity = 16;
+        let mut tree = BPlusTreeMap::new(capacity).unwrap();
+        for i in 0..5_000u64 {
+            tree.insert(i, i);
+        }
+        // A batch past the end lands in one leaf, which grows far beyond
+        // capacity before it is split
+        tree.batch_insert((5_000..20_000).map(|i| (i, i)).collect())
+            .unwrap();
+        let mut rng = SeededRng::new(7);
+        for _ in 0..30_000 {
+            tree.remove(&rng.below(20_000));
+        }
+        assert!(tree.check_invariants());
+
+        let mut expected = NodeStorageStats::default();
+        for leaf in tree.leaf_arena.iter() {
+            assert!(leaf.keys.capacity() <= reserved_limit(capacity));
+            assert!(leaf.values.capacity() <= reserved_limit(capacity));
+            expected.leaf_wasted_bytes += (leaf.keys.capacity() - leaf.len()) * 16;
+        }
+        for branch in tree.branch_arena.iter() {
+            assert!(branch.keys.capacity() <= reserved_limit(capacity + 1));
+            assert!(branch.child_ids.capacity() <= reserved_limit(capacity + 2));
+            expected.branch_wasted_bytes += (branch.keys.capacity() - branch.keys.len()) * 8
+                + (branch.child_ids.capacity() - branch.child_ids.len()) * 4;
+        }
+        let stats = tree.node_storage_stats();
+        assert_eq!(stats, expected);
+        assert!(stats.wasted_bytes() > 0);
+    }
+
+    #[test]
+    fn test_root_leaf_stays_inline_until_it_splits() {
+        let mut tree = BPlusTreeMap::new(8).unwrap();
+        for i in 0..8 {
+            tree.insert(i, i);
+        }
+        assert_eq!(tree.root.id(), INLINE_ROOT);
+        assert_eq!(tree.leaf_arena.len() + tree.branch_arena.len(), 0);
+        assert_eq!(tree.get(&3), Some(&3));
+        assert!(tree.range(2..5).map(|(k, _)| *k).eq(2..5));
+
+        tree.insert(8, 8);
+        assert!(tree.inline_root.is_none());
+        assert_eq!(tree.leaf_arena.len(), 2);
+        assert!(tree.check_invariants());
+
+        for i in 0..9 {
+            tree.remove(&i);
+        }
+        assert_eq!(tree.root.id(), INLINE_ROOT);
+        assert!(tree.check_invariants());
+
+        tree.batch_insert((0..100).map(|i| (i, i)).collect())
+            .unwrap();
+        assert!(tree.inline_root.is_none());
+        assert!(tree.items().map(|(k, _)| *k).eq(0..100));
+        tree.clear();
+        assert_eq!(tree.root.id(), INLINE_ROOT);
+        assert_eq!(tree.leaf_arena.len(), 0);
+    }
+
+    #[test]
+    fn test_get_pair_mut() {
+        let mut arena = CompactArena::new();
+        let id1 = arena.allocate(1);
+        let id2 = arena.allocate(2);
+
+        if let Some((a, b)) = arena.get_pair_mut(id2, id1) {
+            std::mem::swap(a, b);
+        }
+        assert_eq!(arena.get(id1), Some(&2));
+        assert_eq!(arena.get(id2), Some(&1));
+
+        assert!(arena.get_pair_mut(id1, id1).is_none());
+        arena.deallocate_no_return(id2);
+        assert!(arena.get_pair_mut(id1, id2).is_none());
+    }
+
     #[test]
     fn test_unsafe_access() {
         let mut arena = CompactArena::new();
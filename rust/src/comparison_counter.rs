@@ -0,0 +1,253 @@
+//! Counting key comparisons, for checking claims about search cost.
+//!
+//! Whether a layout or search change pays off often comes down to how many
+//! times it calls `Ord::cmp`, which wall-clock benchmarks measure only
+//! indirectly. [`BPlusTreeMap::with_comparison_counter`] builds a
+//! [`CountingMap`], which stores its keys wrapped in [`CountingOrd`] and
+//! records how many comparisons each operation made.
+//!
+//! Counting happens in the key's `Ord` implementation, so it sees every
+//! comparison the tree makes, including the consistency checks that debug
+//! builds run on each search (see [`TreeKey`]). Measure release builds for
+//! figures that describe the search itself.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::error::InitResult;
+use crate::types::BPlusTreeMap;
+use std::cell::Cell;
+use std::cmp::Ordering;
+
+thread_local! {
+    // Comparisons made by `CountingOrd` on this thread. An operation runs on
+    // the calling thread, so the difference across it is what it made.
+    static COMPARISONS: Cell<u64> = const { Cell::new(0) };
+}
+
+fn comparisons_so_far() -> u64 {
+    COMPARISONS.with(Cell::get)
+}
+
+fn count_comparison() {
+    COMPARISONS.with(|count| count.set(count.get() + 1));
+}
+
+/// A key that counts every comparison made with it.
+///
+/// Orders exactly like the wrapped key. Equality tests count as comparisons
+/// too.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CountingOrd<K>(pub K);
+
+impl<K: PartialEq> PartialEq for CountingOrd<K> {
+    fn eq(&self, other: &Self) -> bool {
+        count_comparison();
+        self.0 == other.0
+    }
+}
+
+impl<K: Eq> Eq for CountingOrd<K> {}
+
+impl<K: Ord> PartialOrd for CountingOrd<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord> Ord for CountingOrd<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        count_comparison();
+        self.0.cmp(&other.0)
+    }
+}
+
+/// Comparisons made by the operations of a [`CountingMap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ComparisonStats {
+    /// Operations measured.
+    pub operations: u64,
+    /// Comparisons made by all of them.
+    pub comparisons: u64,
+    /// Comparisons made by the most recent one.
+    pub last: u64,
+}
+
+impl ComparisonStats {
+    /// Mean comparisons per operation, or 0 if nothing was measured.
+    pub fn mean(&self) -> f64 {
+        if self.operations == 0 {
+            0.0
+        } else {
+            self.comparisons as f64 / self.operations as f64
+        }
+    }
+
+    fn record(&mut self, comparisons: u64) {
+        self.operations += 1;
+        self.comparisons += comparisons;
+        self.last = comparisons;
+    }
+}
+
+/// A tree that counts the key comparisons each operation makes.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::BPlusTreeMap;
+///
+/// let mut map = BPlusTreeMap::with_comparison_counter(16).unwrap();
+/// for i in 0..10_000 {
+///     map.insert(i, i);
+/// }
+///
+/// map.reset_stats();
+/// for i in (0..10_000).step_by(7) {
+///     assert_eq!(map.get(&i), Some(&i));
+/// }
+/// let stats = map.stats();
+/// assert_eq!(stats.operations, 1_429);
+/// // Binary search over 10 000 keys needs about 14 comparisons
+/// assert!(stats.mean() >= 10.0);
+///
+/// // Anything else can be measured through the inner tree
+/// let (count, comparisons) = map.measure(|tree| tree.range(..).count());
+/// assert_eq!(count, 10_000);
+/// assert_eq!(comparisons, map.stats().last);
+/// ```
+#[derive(Debug)]
+pub struct CountingMap<K, V> {
+    tree: BPlusTreeMap<CountingOrd<K>, V>,
+    stats: ComparisonStats,
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Create an empty tree with node capacity `capacity` that counts the
+    /// key comparisons made by each operation.
+    pub fn with_comparison_counter(capacity: usize) -> InitResult<CountingMap<K, V>> {
+        Ok(CountingMap {
+            tree: BPlusTreeMap::new(capacity)?,
+            stats: ComparisonStats::default(),
+        })
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> CountingMap<K, V> {
+    /// Run `operation` on the inner tree and record the comparisons it made.
+    /// Returns its result and that count.
+    pub fn measure<R>(
+        &mut self,
+        operation: impl FnOnce(&mut BPlusTreeMap<CountingOrd<K>, V>) -> R,
+    ) -> (R, u64) {
+        let before = comparisons_so_far();
+        let result = operation(&mut self.tree);
+        let comparisons = comparisons_so_far() - before;
+        self.stats.record(comparisons);
+        (result, comparisons)
+    }
+
+    /// Get the value stored under `key`.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let key = CountingOrd(key.clone());
+        let before = comparisons_so_far();
+        let value = self.tree.get(&key);
+        self.stats.record(comparisons_so_far() - before);
+        value
+    }
+
+    /// Returns true if `key` is in the map.
+    pub fn contains_key(&mut self, key: &K) -> bool {
+        let key = CountingOrd(key.clone());
+        self.measure(|tree| tree.contains_key(&key)).0
+    }
+
+    /// Insert `value` under `key`, returning the value it replaced.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.measure(|tree| tree.insert(CountingOrd(key), value)).0
+    }
+
+    /// Remove `key`, returning its value if it was present.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let key = CountingOrd(key.clone());
+        self.measure(|tree| tree.remove(&key)).0
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Returns true if the map holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// The comparisons recorded since creation or the last reset.
+    pub fn stats(&self) -> ComparisonStats {
+        self.stats
+    }
+
+    /// Forget the comparisons recorded so far.
+    pub fn reset_stats(&mut self) {
+        self.stats = ComparisonStats::default();
+    }
+
+    /// The inner tree, for reads that should not be counted.
+    pub fn tree(&self) -> &BPlusTreeMap<CountingOrd<K>, V> {
+        &self.tree
+    }
+
+    /// Unwrap the inner tree.
+    pub fn into_inner(self) -> BPlusTreeMap<CountingOrd<K>, V> {
+        self.tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_counting_map_matches_model_and_sums_stats() {
+        let mut map = BPlusTreeMap::with_comparison_counter(4).unwrap();
+        let mut model = BTreeMap::new();
+        let mut rng = SeededRng::new(3);
+        let mut total = 0;
+        for i in 0..2_000u32 {
+            let key = rng.below(300) as u32;
+            match i % 4 {
+                0 => assert_eq!(map.remove(&key), model.remove(&key)),
+                1 => assert_eq!(map.get(&key), model.get(&key)),
+                2 => assert_eq!(map.contains_key(&key), model.contains_key(&key)),
+                _ => assert_eq!(map.insert(key, i), model.insert(key, i)),
+            }
+            total += map.stats().last;
+        }
+        let stats = map.stats();
+        assert_eq!(stats.operations, 2_000);
+        assert_eq!(stats.comparisons, total);
+        assert_eq!(map.len(), model.len());
+        assert!(map.tree().keys().map(|key| key.0).eq(model.keys().copied()));
+    }
+
+    #[test]
+    fn test_comparisons_grow_with_tree_size() {
+        let mut empty = BPlusTreeMap::<u32, u32>::with_comparison_counter(16).unwrap();
+        assert_eq!(empty.get(&1), None);
+        assert_eq!(empty.stats().last, 0);
+
+        let mean_get = |size: u32| {
+            let mut map = BPlusTreeMap::with_comparison_counter(16).unwrap();
+            for i in 0..size {
+                map.insert(i, i);
+            }
+            map.reset_stats();
+            for i in 0..size {
+                map.get(&i);
+            }
+            map.stats().mean()
+        };
+        assert!(mean_get(10_000) > mean_get(10));
+    }
+}
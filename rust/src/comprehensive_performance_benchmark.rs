@@ -230,7 +230,7 @@ fn benchmark_iterate(
 
     let start = Instant::now();
     for _ in 0..iterations {
-        for (_k, _v) in bplus.items_range(Some(&start_key), Some(&end_key)) {
+        for (_k, _v) in bplus.range(start_key..end_key) {
             // Consume iterator
         }
     }
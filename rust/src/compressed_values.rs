@@ -0,0 +1,350 @@
+//! Per-leaf value compression for cold trees.
+//!
+//! [`CompressedValueMap`] keeps its keys in leaf-sized blocks, like the leaves
+//! of a [`BPlusTreeMap`], but stores each block's values as one encoded byte
+//! array produced by a [`ValueCodec`]. Values are decoded a block at a time
+//! when they are read, so the map trades access latency for memory and suits
+//! archival data that is rarely touched.
+
+use crate::error::{BPlusTreeError, InitResult};
+use crate::invariant::invariant;
+use crate::types::{BPlusTreeMap, MIN_CAPACITY};
+use std::ops::Bound;
+
+/// Encodes a leaf's value array into bytes and back.
+///
+/// Implementations see every value of a block at once, so they can exploit
+/// similarity between neighbouring values (delta encoding, dictionary or
+/// general-purpose compression).
+pub trait ValueCodec<V> {
+    /// Encode `values` into a byte array.
+    fn encode(&self, values: &[V]) -> Vec<u8>;
+
+    /// Decode a byte array produced by [`encode`](ValueCodec::encode).
+    fn decode(&self, bytes: &[u8]) -> Vec<V>;
+}
+
+/// Delta encoding with zigzag varints, for integer values that change slowly
+/// from key to key, such as timestamps or counters.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeltaVarintCodec;
+
+impl ValueCodec<i64> for DeltaVarintCodec {
+    fn encode(&self, values: &[i64]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(values.len());
+        let mut previous = 0i64;
+        for &value in values {
+            let delta = value.wrapping_sub(previous);
+            write_varint(&mut bytes, ((delta << 1) ^ (delta >> 63)) as u64);
+            previous = value;
+        }
+        bytes
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Vec<i64> {
+        let mut values = Vec::new();
+        let mut previous = 0i64;
+        let mut position = 0;
+        while position < bytes.len() {
+            let zigzag = read_varint(bytes, &mut position);
+            let delta = ((zigzag >> 1) as i64) ^ -((zigzag & 1) as i64);
+            previous = previous.wrapping_add(delta);
+            values.push(previous);
+        }
+        values
+    }
+}
+
+impl ValueCodec<u64> for DeltaVarintCodec {
+    fn encode(&self, values: &[u64]) -> Vec<u8> {
+        let signed: Vec<i64> = values.iter().map(|&v| v as i64).collect();
+        ValueCodec::<i64>::encode(self, &signed)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Vec<u64> {
+        ValueCodec::<i64>::decode(self, bytes)
+            .into_iter()
+            .map(|v| v as u64)
+            .collect()
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], position: &mut usize) -> u64 {
+    let mut value = 0u64;
+    let mut shift = 0;
+    while let Some(&byte) = bytes.get(*position) {
+        *position += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    value
+}
+
+/// A block of keys with their values encoded together.
+#[derive(Debug, Clone)]
+struct Block<K> {
+    keys: Vec<K>,
+    encoded: Vec<u8>,
+}
+
+/// An ordered map whose values are stored compressed, one block per leaf.
+///
+/// Blocks are indexed by their first key in an ordinary [`BPlusTreeMap`].
+/// Reads decode the block holding the key and return owned values; writes
+/// decode, modify and re-encode one block, splitting it once it holds twice
+/// the leaf capacity.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::{BPlusTreeMap, CompressedValueMap, DeltaVarintCodec};
+///
+/// let mut readings = BPlusTreeMap::new(64).unwrap();
+/// for minute in 0..10_000u64 {
+///     readings.insert(minute, 1_700_000_000 + (minute * 60) as i64);
+/// }
+///
+/// let archive = CompressedValueMap::from_tree(&readings, DeltaVarintCodec);
+/// assert_eq!(archive.get(&42), Some(1_700_002_520));
+/// assert!(archive.encoded_bytes() < 10_000 * 2);
+/// ```
+#[derive(Debug)]
+pub struct CompressedValueMap<K, V, C> {
+    blocks: BPlusTreeMap<K, Block<K>>,
+    codec: C,
+    capacity: usize,
+    len: usize,
+    _values: std::marker::PhantomData<V>,
+}
+
+impl<K: Ord + Clone, V: Clone, C: ValueCodec<V>> CompressedValueMap<K, V, C> {
+    /// Create an empty map whose blocks hold up to `capacity` entries after a
+    /// split, matching a tree with that node capacity.
+    pub fn new(capacity: usize, codec: C) -> InitResult<Self> {
+        if capacity < MIN_CAPACITY {
+            return Err(BPlusTreeError::invalid_capacity(capacity, MIN_CAPACITY));
+        }
+        Ok(Self::with_valid_capacity(capacity, codec))
+    }
+
+    fn with_valid_capacity(capacity: usize, codec: C) -> Self {
+        Self {
+            blocks: BPlusTreeMap::with_valid_capacity(capacity),
+            codec,
+            capacity,
+            len: 0,
+            _values: std::marker::PhantomData,
+        }
+    }
+
+    /// Compress a tree, encoding each of its leaves as one block.
+    pub fn from_tree(tree: &BPlusTreeMap<K, V>, codec: C) -> Self {
+        let mut map = Self::with_valid_capacity(tree.capacity, codec);
+        tree.for_each_leaf_slice_in_range(&(..), |keys, values| {
+            let block = Block {
+                keys: keys.to_vec(),
+                encoded: map.codec.encode(values),
+            };
+            map.blocks.insert(keys[0].clone(), block);
+        });
+        map.len = tree.len();
+        map
+    }
+
+    /// Decompress every block into an ordinary tree.
+    pub fn to_tree(&self) -> BPlusTreeMap<K, V> {
+        let mut tree = BPlusTreeMap::with_valid_capacity(self.capacity);
+        for (key, value) in self.iter() {
+            tree.insert(key.clone(), value);
+        }
+        tree
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the map holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of compressed blocks.
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Total size of the encoded values in bytes.
+    pub fn encoded_bytes(&self) -> usize {
+        self.blocks.values().map(|block| block.encoded.len()).sum()
+    }
+
+    /// Get a copy of the value stored under `key`, decoding its block.
+    pub fn get(&self, key: &K) -> Option<V> {
+        let (_, block) = self.blocks.last_entry_before(Bound::Included(key))?;
+        let index = block.keys.binary_search(key).ok()?;
+        self.codec.decode(&block.encoded).into_iter().nth(index)
+    }
+
+    /// Returns true if `key` is present. Does not decode any values.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.blocks
+            .last_entry_before(Bound::Included(key))
+            .is_some_and(|(_, block)| block.keys.binary_search(key).is_ok())
+    }
+
+    /// Insert `value` under `key`, returning the previous value if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        // Keys below every block go into the first block
+        let start = match self.blocks.last_entry_before(Bound::Included(&key)) {
+            Some((start, _)) => start.clone(),
+            None => match self.blocks.first() {
+                Some((start, _)) => start.clone(),
+                None => {
+                    let block = Block {
+                        keys: vec![key.clone()],
+                        encoded: self.codec.encode(&[value]),
+                    };
+                    self.blocks.insert(key, block);
+                    self.len = 1;
+                    return None;
+                }
+            },
+        };
+
+        let mut block = invariant(self.blocks.remove(&start), "block start is present");
+        let mut values = self.codec.decode(&block.encoded);
+        let previous = match block.keys.binary_search(&key) {
+            Ok(index) => Some(std::mem::replace(&mut values[index], value)),
+            Err(index) => {
+                block.keys.insert(index, key);
+                values.insert(index, value);
+                self.len += 1;
+                None
+            }
+        };
+
+        if block.keys.len() >= 2 * self.capacity {
+            let right_keys = block.keys.split_off(self.capacity);
+            let right_values = values.split_off(self.capacity);
+            self.store_block(right_keys, &right_values);
+        }
+        self.store_block(block.keys, &values);
+        previous
+    }
+
+    /// Remove `key`, returning its value if it was present.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let start = self
+            .blocks
+            .last_entry_before(Bound::Included(key))?
+            .0
+            .clone();
+        let index = self.blocks.get(&start)?.keys.binary_search(key).ok()?;
+
+        let mut block = self.blocks.remove(&start)?;
+        let mut values = self.codec.decode(&block.encoded);
+        block.keys.remove(index);
+        let removed = values.remove(index);
+        self.len -= 1;
+
+        if !block.keys.is_empty() {
+            self.store_block(block.keys, &values);
+        }
+        Some(removed)
+    }
+
+    /// Iterate over all entries in key order, decoding one block at a time.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, V)> + '_ {
+        self.blocks
+            .values()
+            .flat_map(move |block| block.keys.iter().zip(self.codec.decode(&block.encoded)))
+    }
+
+    /// Encode and index a non-empty block under its first key.
+    fn store_block(&mut self, keys: Vec<K>, values: &[V]) {
+        let block = Block {
+            encoded: self.codec.encode(values),
+            keys,
+        };
+        self.blocks.insert(block.keys[0].clone(), block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_delta_varint_round_trip() {
+        let values = vec![0i64, 5, 3, i64::MAX, i64::MIN, -1, 1_000_000];
+        let encoded = ValueCodec::<i64>::encode(&DeltaVarintCodec, &values);
+        assert_eq!(
+            ValueCodec::<i64>::decode(&DeltaVarintCodec, &encoded),
+            values
+        );
+
+        // Slowly increasing values take one byte each
+        let steady: Vec<u64> = (1_000_000..1_000_100).collect();
+        let encoded = ValueCodec::<u64>::encode(&DeltaVarintCodec, &steady);
+        assert_eq!(encoded.len(), 3 + 99);
+        assert_eq!(
+            ValueCodec::<u64>::decode(&DeltaVarintCodec, &encoded),
+            steady
+        );
+    }
+
+    #[test]
+    fn test_from_tree_keeps_leaf_boundaries() {
+        let mut tree = BPlusTreeMap::new(8).unwrap();
+        for i in 0..500i64 {
+            tree.insert(i, i * 3);
+        }
+        let map = CompressedValueMap::from_tree(&tree, DeltaVarintCodec);
+
+        assert_eq!(map.len(), 500);
+        assert_eq!(map.block_count(), tree.leaf_count());
+        assert!(map
+            .iter()
+            .map(|(k, v)| (*k, v))
+            .eq(tree.items().map(|(k, v)| (*k, *v))));
+        assert!(map.to_tree().items().eq(tree.items()));
+    }
+
+    #[test]
+    fn test_random_operations_match_btreemap() {
+        let mut map = CompressedValueMap::new(4, DeltaVarintCodec).unwrap();
+        let mut reference = BTreeMap::new();
+        let mut rng = SeededRng::new(3);
+
+        for i in 0..4_000i64 {
+            let key = rng.below(500);
+            if rng.below(3) == 0 {
+                assert_eq!(map.remove(&key), reference.remove(&key));
+            } else {
+                assert_eq!(map.insert(key, i), reference.insert(key, i));
+            }
+        }
+
+        assert_eq!(map.len(), reference.len());
+        assert!(map.iter().map(|(k, v)| (*k, v)).eq(reference.clone()));
+        for key in 0..500 {
+            assert_eq!(map.get(&key), reference.get(&key).copied());
+            assert_eq!(map.contains_key(&key), reference.contains_key(&key));
+        }
+    }
+}
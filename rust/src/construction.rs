@@ -4,10 +4,13 @@
 //! for the B+ tree and its nodes. This includes capacity validation,
 //! arena initialization, and default implementations.
 
+use crate::bounds::{TreeKey, TreeValue};
 use crate::compact_arena::CompactArena;
 use crate::error::{BPlusTreeError, BTreeResult};
-use crate::types::{BPlusTreeMap, BranchNode, LeafNode, NodeRef, MIN_CAPACITY, NULL_NODE};
-use std::marker::PhantomData;
+use crate::types::{
+    BPlusTreeMap, BranchNode, DeletionMode, LeafNode, NodeRef, NodeVec, OverflowMode,
+    RebalanceStrategy, INLINE_ROOT, MIN_CAPACITY, NULL_NODE,
+};
 
 /// Result type for initialization operations
 pub type InitResult<T> = BTreeResult<T>;
@@ -20,11 +23,13 @@ impl<K, V> BPlusTreeMap<K, V> {
     ///
     /// # Arguments
     ///
-    /// * `capacity` - Maximum number of keys per node (minimum 8)
+    /// * `capacity` - Maximum number of keys per node (minimum [`MIN_CAPACITY`])
     ///
     /// # Returns
     ///
-    /// Returns `Ok(BPlusTreeMap)` if capacity is valid, `Err(BPlusTreeError)` otherwise.
+    /// Returns `Ok(BPlusTreeMap)` if capacity is valid, otherwise
+    /// [`CapacityError::TooSmall`](crate::CapacityError::TooSmall) with the minimum and a suggested
+    /// capacity.
     ///
     /// # Examples
     ///
@@ -38,20 +43,47 @@ impl<K, V> BPlusTreeMap<K, V> {
         if capacity < MIN_CAPACITY {
             return Err(BPlusTreeError::invalid_capacity(capacity, MIN_CAPACITY));
         }
+        Ok(Self::with_valid_capacity(capacity))
+    }
 
-        // Initialize compact arena with the first leaf at id=0
-        let mut leaf_arena = CompactArena::new();
-        let root_id = leaf_arena.allocate(LeafNode::new(capacity));
-
-        // Initialize compact branch arena (starts empty)
-        let branch_arena = CompactArena::new();
-
-        Ok(Self {
+    /// Create an empty tree with a capacity the caller has already checked
+    /// is at least [`MIN_CAPACITY`].
+    pub(crate) fn with_valid_capacity(capacity: usize) -> Self {
+        debug_assert!(capacity >= MIN_CAPACITY);
+        // The root leaf starts inline; the arenas stay empty until it splits
+        Self {
             capacity,
-            root: NodeRef::Leaf(root_id, PhantomData),
-            leaf_arena,
-            branch_arena,
-        })
+            root: NodeRef::leaf(INLINE_ROOT),
+            inline_root: Some(LeafNode::new(capacity)),
+            leaf_arena: CompactArena::new(),
+            branch_arena: CompactArena::new(),
+            rebalance_strategy: RebalanceStrategy::default(),
+            deletion_mode: DeletionMode::default(),
+            overflow_mode: OverflowMode::default(),
+            byte_budget: None,
+            leaf_boundary_hook: None,
+            generation: 0,
+        }
+    }
+
+    /// Create a B+ tree with capacity `capacity`, raised to [`MIN_CAPACITY`]
+    /// if it is smaller.
+    ///
+    /// Useful when the capacity comes from configuration and a too-small
+    /// value should not be fatal. Use [`new`](Self::new) to be told instead;
+    /// its error carries the minimum and a suggested capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::{BPlusTreeMap, MIN_CAPACITY};
+    ///
+    /// let mut tree = BPlusTreeMap::clamp_capacity(2);
+    /// tree.insert(1, "one");
+    /// assert_eq!(tree.capacity(), MIN_CAPACITY);
+    /// ```
+    pub fn clamp_capacity(capacity: usize) -> Self {
+        Self::with_valid_capacity(capacity.max(MIN_CAPACITY))
     }
 
     /// Create a B+ tree with default capacity.
@@ -78,7 +110,7 @@ impl<K, V> BPlusTreeMap<K, V> {
     ///
     /// # Arguments
     ///
-    /// * `capacity` - Maximum number of keys per node (minimum 8)
+    /// * `capacity` - Maximum number of keys per node (minimum [`MIN_CAPACITY`])
     ///
     /// # Examples
     ///
@@ -93,15 +125,19 @@ impl<K, V> BPlusTreeMap<K, V> {
             return Err(BPlusTreeError::invalid_capacity(capacity, MIN_CAPACITY));
         }
 
-        // For empty tree, we still need a root - create an empty leaf
-        let mut leaf_arena = CompactArena::new();
-        let root_id = leaf_arena.allocate(LeafNode::new(capacity));
-
+        // For empty tree, we still need a root - create an empty inline leaf
         Ok(Self {
             capacity,
-            root: NodeRef::Leaf(root_id, PhantomData),
-            leaf_arena,
+            root: NodeRef::leaf(INLINE_ROOT),
+            inline_root: Some(LeafNode::new(capacity)),
+            leaf_arena: CompactArena::new(),
             branch_arena: CompactArena::new(),
+            rebalance_strategy: RebalanceStrategy::default(),
+            deletion_mode: DeletionMode::default(),
+            overflow_mode: OverflowMode::default(),
+            byte_budget: None,
+            leaf_boundary_hook: None,
+            generation: 0,
         })
     }
 }
@@ -122,11 +158,12 @@ impl<K, V> LeafNode<K, V> {
     /// // Leaf node created successfully
     /// ```
     pub fn new(capacity: usize) -> Self {
-        // Pre-allocate to capacity to avoid reallocations during steady-state ops
+        // Pre-allocate to capacity to avoid reallocations during steady-state ops.
+        // Leaves split before inserting, so they never need room for more.
         Self {
             capacity,
-            keys: Vec::with_capacity(capacity),
-            values: Vec::with_capacity(capacity),
+            keys: NodeVec::with_capacity(capacity),
+            values: NodeVec::with_capacity(capacity),
             next: NULL_NODE,
         }
     }
@@ -165,8 +202,8 @@ impl<K, V> LeafNode<K, V> {
     pub fn with_reserved_capacity(capacity: usize) -> Self {
         Self {
             capacity,
-            keys: Vec::with_capacity(capacity),
-            values: Vec::with_capacity(capacity),
+            keys: NodeVec::with_capacity(capacity),
+            values: NodeVec::with_capacity(capacity),
             next: NULL_NODE,
         }
     }
@@ -188,12 +225,13 @@ impl<K, V> BranchNode<K, V> {
     /// // Branch node created successfully
     /// ```
     pub fn new(capacity: usize) -> Self {
-        // Pre-allocate: keys up to capacity, children up to capacity+1
-        Self {
+        // Pre-allocate for the extra key and child held just before a split
+        Self::from_parts(
             capacity,
-            keys: Vec::with_capacity(capacity),
-            children: Vec::with_capacity(capacity + 1),
-        }
+            NodeVec::with_capacity(capacity + 1),
+            NodeVec::with_capacity(capacity + 2),
+            true,
+        )
     }
 
     /// Creates a new branch node with default capacity.
@@ -228,19 +266,21 @@ impl<K, V> BranchNode<K, V> {
     /// // Branch node created with reserved capacity
     /// ```
     pub fn with_reserved_capacity(capacity: usize) -> Self {
-        Self {
+        // Branch nodes have one more child than keys
+        Self::from_parts(
             capacity,
-            keys: Vec::with_capacity(capacity),
-            children: Vec::with_capacity(capacity + 1), // Branch nodes have one more child than keys
-        }
+            NodeVec::with_capacity(capacity + 1),
+            NodeVec::with_capacity(capacity + 2),
+            true,
+        )
     }
 }
 
 // Default implementations
-impl<K: Ord + Clone, V: Clone> Default for BPlusTreeMap<K, V> {
+impl<K: TreeKey, V: TreeValue> Default for BPlusTreeMap<K, V> {
     /// Create a B+ tree with default capacity.
     fn default() -> Self {
-        Self::with_default_capacity().unwrap()
+        Self::with_valid_capacity(DEFAULT_CAPACITY)
     }
 }
 
@@ -292,7 +332,6 @@ pub mod validation {
     /// # Returns
     ///
     /// Recommended capacity (always >= MIN_CAPACITY)
-    #[allow(dead_code)]
     pub fn recommended_capacity(expected_elements: usize) -> usize {
         if expected_elements < 100 {
             MIN_CAPACITY
@@ -320,8 +359,18 @@ mod tests {
     #[test]
     fn test_btree_invalid_capacity() {
         let result = BPlusTreeMap::<i32, String>::new(2); // Below MIN_CAPACITY (4)
-        assert!(result.is_err());
-        // Note: is_capacity_error() method needs to be implemented in error module
+        assert_eq!(
+            result.err(),
+            Some(BPlusTreeError::Capacity(crate::CapacityError::TooSmall {
+                capacity: 2,
+                minimum: MIN_CAPACITY,
+                suggested: DEFAULT_CAPACITY
+            }))
+        );
+        assert_eq!(
+            BPlusTreeMap::<i32, String>::clamp_capacity(0).capacity,
+            MIN_CAPACITY
+        );
     }
 
     #[test]
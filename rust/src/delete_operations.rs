@@ -4,14 +4,21 @@
 //! key-value removal, node merging, tree shrinking, and helper methods for
 //! managing the tree structure during deletions.
 
-use crate::error::{BPlusTreeError, ModifyResult};
-use crate::types::{BPlusTreeMap, LeafNode, NodeId, NodeRef, RemoveResult};
-use std::marker::PhantomData;
+use crate::bounds::{TreeKey, TreeValue};
+use crate::error::{BPlusTreeError, ModifyResult, QueryError};
+use crate::node::RebalanceNode;
+use crate::types::{
+    BPlusTreeMap, BranchNode, DeletionMode, LeafNode, NodeId, NodeRef, RebalanceStrategy,
+    MAX_HEIGHT, NULL_NODE,
+};
 
 // The RebalanceContext and SiblingInfo structs have been removed in favor of a simpler approach
 // that avoids borrowing conflicts while still optimizing arena access patterns.
 
-impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
+/// Sibling reference, its key count, and whether it can donate a key.
+type SiblingInfo<K, V> = (NodeRef<K, V>, usize, bool);
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
     /// Remove a key from the tree and return its associated value.
     ///
     /// # Arguments
@@ -42,72 +49,192 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
     /// # Panics
     /// Never panics - all operations are memory safe
     pub fn remove(&mut self, key: &K) -> Option<V> {
-        // Use remove_recursive to handle the removal
-        let result = self.remove_recursive(&self.root.clone(), key);
-
-        match result {
-            RemoveResult::Updated(removed_value, _root_became_underfull) => {
-                // Check if root needs collapsing after removal
-                if removed_value.is_some() {
-                    self.collapse_root_if_needed();
-                }
-                removed_value
+        if self.deletion_mode == DeletionMode::DeferredRebalance {
+            return self.remove_without_rebalance(key);
+        }
+
+        // Record the path so underfull nodes can be rebalanced bottom-up
+        let mut path = [(NULL_NODE, 0usize); MAX_HEIGHT];
+        let (leaf_id, depth) = self.path_to_leaf(key, &mut path)?;
+        self.remove_along_path(&path[..depth], leaf_id, key)
+    }
+
+    /// Remove `key` from `leaf_id`, then rebalance up through the branches on
+    /// `path`, as recorded by `path_to_leaf`, and collapse the root if needed.
+    pub(crate) fn remove_along_path(
+        &mut self,
+        path: &[(NodeId, usize)],
+        leaf_id: NodeId,
+        key: &K,
+    ) -> Option<V> {
+        let (removed_value, mut child_became_underfull) = self.get_leaf_mut(leaf_id)?.remove(key);
+        removed_value.as_ref()?;
+
+        for &(branch_id, child_index) in path.iter().rev() {
+            // If child became underfull, try to rebalance
+            if child_became_underfull {
+                let _child_still_exists = self.rebalance_child(branch_id, child_index);
             }
+            child_became_underfull = self.is_node_underfull(&NodeRef::branch(branch_id));
         }
+
+        // Check if root needs collapsing after removal
+        self.collapse_root_if_needed();
+        removed_value
     }
 
     /// Remove a key from the tree, returning an error if the key doesn't exist.
     /// This is equivalent to Python's `del tree[key]`.
     pub fn remove_item(&mut self, key: &K) -> ModifyResult<V> {
-        self.remove(key).ok_or(BPlusTreeError::KeyNotFound)
+        self.remove(key)
+            .ok_or(BPlusTreeError::Query(QueryError::KeyNotFound))
     }
 
-    /// Recursively remove a key with proper arena access.
-    #[inline]
-    fn remove_recursive(&mut self, node: &NodeRef<K, V>, key: &K) -> RemoveResult<V> {
-        match node {
-            NodeRef::Leaf(id, _) => {
-                self.get_leaf_mut(*id)
-                    .map_or(RemoveResult::Updated(None, false), |leaf| {
-                        let (removed_value, is_underfull) = leaf.remove(key);
-                        RemoveResult::Updated(removed_value, is_underfull)
-                    })
+    /// Move the entry stored under `old` to the key `new`, keeping its value.
+    ///
+    /// The value is moved, never cloned. If `new` sorts into the same slot of
+    /// the same leaf, only the key is overwritten and the tree is not
+    /// restructured; otherwise the entry is removed and reinserted.
+    ///
+    /// Returns the value previously stored under `new`, if any, which is
+    /// overwritten. Returns `KeyNotFound` if `old` is not in the tree.
+    ///
+    /// # Examples
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// tree.insert(10, "ten");
+    /// tree.insert(20, "twenty");
+    ///
+    /// assert_eq!(tree.replace_key(&10, 15), Ok(None));
+    /// assert_eq!(tree.get(&15), Some(&"ten"));
+    /// assert_eq!(tree.replace_key(&15, 20), Ok(Some("twenty")));
+    /// assert!(tree.replace_key(&15, 30).is_err());
+    /// ```
+    pub fn replace_key(&mut self, old: &K, new: K) -> ModifyResult<Option<V>> {
+        let (leaf_id, index, matched) = self
+            .find_leaf_for_key_with_match(old)
+            .ok_or(BPlusTreeError::Query(QueryError::KeyNotFound))?;
+        if !matched {
+            return Err(BPlusTreeError::Query(QueryError::KeyNotFound));
+        }
+        if &new == old {
+            return Ok(None);
+        }
+
+        if let Some(leaf) = self.get_leaf_mut(leaf_id) {
+            // Stay inside the leaf's neighbours. At either end of the leaf the
+            // key may only move inwards, since the separator bounds are unknown.
+            let above_previous = match index.checked_sub(1).and_then(|i| leaf.keys.get(i)) {
+                Some(previous) => previous < &new,
+                None => &new > old,
+            };
+            let below_next = match leaf.keys.get(index + 1) {
+                Some(next) => &new < next,
+                None => &new < old,
+            };
+            if above_previous && below_next {
+                leaf.keys[index] = new;
+                return Ok(None);
             }
-            NodeRef::Branch(id, _) => {
-                let id = *id;
+        }
 
-                // First get child info without mutable borrow
-                let (child_index, child_ref) = match self.get_child_for_key(id, key) {
-                    Some(info) => info,
-                    None => return RemoveResult::Updated(None, false),
-                };
+        let value = self
+            .remove(old)
+            .ok_or(BPlusTreeError::Query(QueryError::KeyNotFound))?;
+        Ok(self.insert(new, value))
+    }
 
-                // Recursively remove
-                let child_result = self.remove_recursive(&child_ref, key);
-
-                // Handle the result
-                match child_result {
-                    RemoveResult::Updated(removed_value, child_became_underfull) => {
-                        // If child became underfull, try to rebalance
-                        if removed_value.is_some() && child_became_underfull {
-                            let _child_still_exists = self.rebalance_child(id, child_index);
-                        }
-
-                        // Only compute underfull if a removal actually happened
-                        let is_underfull = if removed_value.is_some() {
-                            self.is_node_underfull(&NodeRef::Branch(id, PhantomData))
-                        } else {
-                            false
-                        };
-                        RemoveResult::Updated(removed_value, is_underfull)
-                    }
-                }
-            }
+    /// Returns the strategy used to pick a sibling when rebalancing after removal.
+    pub fn rebalance_strategy(&self) -> RebalanceStrategy {
+        self.rebalance_strategy
+    }
+
+    /// Set the strategy used to pick a sibling when rebalancing after removal.
+    ///
+    /// # Examples
+    /// ```
+    /// use bplustree::{BPlusTreeMap, RebalanceStrategy};
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// tree.set_rebalance_strategy(RebalanceStrategy::PreferFuller);
+    /// for i in 0..32 {
+    ///     tree.insert(i, i);
+    /// }
+    /// for i in (0..32).step_by(2) {
+    ///     tree.remove(&i);
+    /// }
+    /// # #[cfg(feature = "validation")]
+    /// assert!(tree.check_invariants());
+    /// ```
+    pub fn set_rebalance_strategy(&mut self, strategy: RebalanceStrategy) {
+        self.rebalance_strategy = strategy;
+    }
+
+    /// Returns the current deletion mode.
+    pub fn deletion_mode(&self) -> DeletionMode {
+        self.deletion_mode
+    }
+
+    /// Set the deletion mode.
+    ///
+    /// Switching back to [`DeletionMode::Eager`] vacuums the tree first so that
+    /// eager rebalancing starts from a tree that satisfies every invariant.
+    ///
+    /// # Examples
+    /// ```
+    /// use bplustree::{BPlusTreeMap, DeletionMode};
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..100 {
+    ///     tree.insert(i, i);
+    /// }
+    ///
+    /// tree.set_deletion_mode(DeletionMode::DeferredRebalance);
+    /// for i in 0..90 {
+    ///     tree.remove(&i);
+    /// }
+    /// assert_eq!(tree.len(), 10);
+    ///
+    /// tree.vacuum();
+    /// # #[cfg(feature = "validation")]
+    /// assert!(tree.check_invariants());
+    /// ```
+    pub fn set_deletion_mode(&mut self, mode: DeletionMode) {
+        if mode == DeletionMode::Eager && self.deletion_mode == DeletionMode::DeferredRebalance {
+            self.vacuum();
         }
+        self.deletion_mode = mode;
+    }
+
+    /// Fold away space left behind by removals in
+    /// [`DeletionMode::DeferredRebalance`].
+    ///
+    /// Borrows into or merges every underfull node and releases emptied nodes
+    /// back to the arena, restoring the minimum-occupancy invariant. Leaves
+    /// grown past capacity under [`OverflowMode::GrowLeaves`] are split back
+    /// into leaves of normal size. This is a single pass over the whole tree
+    /// and is a no-op for a well-formed tree.
+    ///
+    /// [`OverflowMode::GrowLeaves`]: crate::OverflowMode::GrowLeaves
+    pub fn vacuum(&mut self) {
+        self.rebalance_whole_tree();
+    }
+
+    /// Remove a key from its leaf without borrowing or merging.
+    fn remove_without_rebalance(&mut self, key: &K) -> Option<V> {
+        let (leaf_id, index, matched) = self.find_leaf_for_key_with_match(key)?;
+        if !matched {
+            return None;
+        }
+        self.get_leaf_mut(leaf_id)?
+            .remove_at(index)
+            .map(|(_, value)| value)
     }
 
     /// Collapse the root if it's a branch with only one child or no children.
-    fn collapse_root_if_needed(&mut self) {
+    pub(crate) fn collapse_root_if_needed(&mut self) {
         loop {
             // Capture root ID first to avoid borrowing conflicts
             let root_branch_id = match &self.root {
@@ -117,19 +244,14 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
 
             // Use Option combinators for cleaner nested logic handling
             let branch_info = root_branch_id.and_then(|branch_id| {
-                self.get_branch(branch_id).map(|branch| {
-                    (
-                        branch_id,
-                        branch.children.len(),
-                        branch.children.first().cloned(),
-                    )
-                })
+                self.get_branch(branch_id)
+                    .map(|branch| (branch_id, branch.child_count(), branch.child(0)))
             });
 
             match branch_info {
                 Some((branch_id, 0, _)) => {
                     // Empty branch - replace with empty leaf
-                    self.create_empty_root_leaf();
+                    self.reset_inline_root();
                     self.deallocate_branch(branch_id);
                     break;
                 }
@@ -147,39 +269,28 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
                     // Handle missing branch or already leaf root
                     if root_branch_id.filter(|_| true).is_some() {
                         // Branch ID exists but branch is missing
-                        self.create_empty_root_leaf();
+                        self.reset_inline_root();
                     }
                     break;
                 }
             }
         }
+        self.unspill_root();
     }
 
-    /// Helper method to create empty root leaf
-    #[inline]
-    fn create_empty_root_leaf(&mut self) {
-        let empty_id = self.allocate_leaf(LeafNode::new(self.capacity));
-        self.root = NodeRef::Leaf(empty_id, PhantomData);
-    }
-
-    /// Helper to check if a node is underfull.
+    /// Rebalance an underfull child in an arena branch
     #[inline]
-    fn is_node_underfull(&self, node_ref: &NodeRef<K, V>) -> bool {
-        match node_ref {
-            NodeRef::Leaf(id, _) => self
-                .get_leaf(*id)
-                .map(|leaf| leaf.is_underfull())
-                .unwrap_or(false),
-            NodeRef::Branch(id, _) => self
-                .get_branch(*id)
-                .map(|branch| branch.is_underfull())
-                .unwrap_or(false),
+    pub(crate) fn rebalance_child(&mut self, parent_id: NodeId, child_index: usize) -> bool {
+        if let Some(budget) = self.byte_budget {
+            let child_is_leaf = self
+                .get_branch(parent_id)
+                .and_then(|branch| branch.child(child_index))
+                .is_some_and(|child| child.is_leaf());
+            if child_is_leaf {
+                return self.rebalance_leaf_by_bytes(budget, parent_id, child_index);
+            }
         }
-    }
 
-    /// Rebalance an underfull child in an arena branch
-    #[inline]
-    fn rebalance_child(&mut self, parent_id: NodeId, child_index: usize) -> bool {
         // Gather rebalancing information in minimal arena accesses
         let rebalance_info = {
             let parent_branch = match self.get_branch(parent_id) {
@@ -187,38 +298,24 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
                 None => return false,
             };
 
-            let child_is_leaf = matches!(parent_branch.children[child_index], NodeRef::Leaf(_, _));
+            let child_is_leaf = parent_branch.children_are_leaves;
 
             let left_sibling_info = if child_index > 0 {
-                let sibling_ref = parent_branch.children[child_index - 1];
-                let can_donate = match &sibling_ref {
-                    NodeRef::Leaf(id, _) => self
-                        .get_leaf(*id)
-                        .map(|leaf| leaf.keys.len() > leaf.min_keys())
-                        .unwrap_or(false),
-                    NodeRef::Branch(id, _) => self
-                        .get_branch(*id)
-                        .map(|branch| branch.keys.len() > branch.min_keys())
-                        .unwrap_or(false),
+                let Some(sibling_ref) = parent_branch.child(child_index - 1) else {
+                    return false;
                 };
-                Some((sibling_ref, can_donate))
+                let (len, can_donate) = self.sibling_fill(&sibling_ref);
+                Some((sibling_ref, len, can_donate))
             } else {
                 None
             };
 
-            let right_sibling_info = if child_index < parent_branch.children.len() - 1 {
-                let sibling_ref = parent_branch.children[child_index + 1];
-                let can_donate = match &sibling_ref {
-                    NodeRef::Leaf(id, _) => self
-                        .get_leaf(*id)
-                        .map(|leaf| leaf.keys.len() > leaf.min_keys())
-                        .unwrap_or(false),
-                    NodeRef::Branch(id, _) => self
-                        .get_branch(*id)
-                        .map(|branch| branch.keys.len() > branch.min_keys())
-                        .unwrap_or(false),
+            let right_sibling_info = if child_index < parent_branch.child_count() - 1 {
+                let Some(sibling_ref) = parent_branch.child(child_index + 1) else {
+                    return false;
                 };
-                Some((sibling_ref, can_donate))
+                let (len, can_donate) = self.sibling_fill(&sibling_ref);
+                Some((sibling_ref, len, can_donate))
             } else {
                 None
             };
@@ -229,14 +326,14 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
         let (child_is_leaf, left_sibling_info, right_sibling_info) = rebalance_info;
 
         if child_is_leaf {
-            self.rebalance_leaf(
+            self.rebalance_node::<LeafNode<K, V>>(
                 parent_id,
                 child_index,
                 left_sibling_info,
                 right_sibling_info,
             )
         } else {
-            self.rebalance_branch(
+            self.rebalance_node::<BranchNode<K, V>>(
                 parent_id,
                 child_index,
                 left_sibling_info,
@@ -245,6 +342,41 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
         }
     }
 
+    /// Number of keys in a sibling node and whether it can donate one.
+    #[inline]
+    fn sibling_fill(&self, sibling_ref: &NodeRef<K, V>) -> (usize, bool) {
+        match sibling_ref {
+            NodeRef::Leaf(id, _) => self
+                .get_leaf(*id)
+                .map(|leaf| (leaf.keys.len(), leaf.can_donate()))
+                .unwrap_or((0, false)),
+            NodeRef::Branch(id, _) => self
+                .get_branch(*id)
+                .map(|branch| (branch.keys.len(), branch.can_donate()))
+                .unwrap_or((0, false)),
+        }
+    }
+
+    /// Decide sibling order for rebalancing according to the configured strategy.
+    /// Returns `(borrow_right_first, merge_right_first)`.
+    #[inline]
+    fn sibling_preference(
+        &self,
+        left_sibling_info: &Option<SiblingInfo<K, V>>,
+        right_sibling_info: &Option<SiblingInfo<K, V>>,
+    ) -> (bool, bool) {
+        match (
+            self.rebalance_strategy,
+            left_sibling_info,
+            right_sibling_info,
+        ) {
+            (RebalanceStrategy::PreferFuller, Some((_, left_len, _)), Some((_, right_len, _))) => {
+                (right_len > left_len, right_len < left_len)
+            }
+            _ => (false, false),
+        }
+    }
+
     // (Experimental ID-based helpers removed)
 }
 
@@ -363,328 +495,96 @@ mod tests {
     }
 }
 
-impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
-    /// Rebalance an underfull leaf child using pre-gathered sibling information.
-    /// Optimized to minimize repeated arena lookups by resolving sibling IDs once.
-    fn rebalance_leaf(
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Rebalance an underfull child of kind `N` using pre-gathered sibling
+    /// information: borrow from a sibling that can donate, otherwise merge.
+    fn rebalance_node<N: RebalanceNode<K, V>>(
         &mut self,
         parent_id: NodeId,
         child_index: usize,
-        left_sibling_info: Option<(NodeRef<K, V>, bool)>,
-        right_sibling_info: Option<(NodeRef<K, V>, bool)>,
+        left_sibling_info: Option<SiblingInfo<K, V>>,
+        right_sibling_info: Option<SiblingInfo<K, V>>,
     ) -> bool {
-        // Resolve sibling IDs once from parent
-        let (left_id_opt, right_id_opt) = match self.get_branch(parent_id) {
-            Some(parent) => {
-                let left_id_opt = if child_index > 0 {
-                    match parent.children[child_index - 1] {
-                        NodeRef::Leaf(id, _) => Some(id),
-                        _ => None,
-                    }
-                } else {
-                    None
-                };
-                let right_id_opt = if child_index + 1 < parent.children.len() {
-                    match parent.children[child_index + 1] {
-                        NodeRef::Leaf(id, _) => Some(id),
-                        _ => None,
-                    }
-                } else {
-                    None
-                };
-                (left_id_opt, right_id_opt)
-            }
+        let child_id = match self
+            .get_branch(parent_id)
+            .and_then(|parent| N::id_of(&parent.child(child_index)?))
+        {
+            Some(id) => id,
             None => return false,
         };
-
-        // Strategy 1: Try to borrow from a sibling that can donate (prefer left)
-        if let Some((_left_ref, can_donate)) = left_sibling_info {
-            if can_donate {
-                if let Some(left_id) = left_id_opt {
-                    // Child ID from parent
-                    let child_id = match self.get_branch(parent_id) {
-                        Some(parent) => match parent.children[child_index] {
-                            NodeRef::Leaf(id, _) => id,
-                            _ => return false,
-                        },
-                        None => return false,
-                    };
-                    return self.borrow_from_left_leaf_with_ids(
-                        parent_id,
-                        child_index,
-                        left_id,
-                        child_id,
-                    );
-                }
+        let left_id_opt = left_sibling_info
+            .as_ref()
+            .and_then(|(sibling, _, _)| N::id_of(sibling));
+        let right_id_opt = right_sibling_info
+            .as_ref()
+            .and_then(|(sibling, _, _)| N::id_of(sibling));
+
+        let (borrow_right_first, merge_right_first) =
+            self.sibling_preference(&left_sibling_info, &right_sibling_info);
+        let left_donor = left_id_opt.filter(|_| matches!(left_sibling_info, Some((_, _, true))));
+        let right_donor = right_id_opt.filter(|_| matches!(right_sibling_info, Some((_, _, true))));
+
+        // Strategy 1: Try to borrow from a sibling that can donate
+        match (left_donor, right_donor) {
+            (Some(_), Some(right_id)) if borrow_right_first => {
+                return self.borrow_from_right::<N>(parent_id, child_index, child_id, right_id);
             }
-        }
-        if let Some((_right_ref, can_donate)) = right_sibling_info {
-            if can_donate {
-                if let Some(right_id) = right_id_opt {
-                    let child_id = match self.get_branch(parent_id) {
-                        Some(parent) => match parent.children[child_index] {
-                            NodeRef::Leaf(id, _) => id,
-                            _ => return false,
-                        },
-                        None => return false,
-                    };
-                    return self.borrow_from_right_leaf_with_ids(
-                        parent_id,
-                        child_index,
-                        child_id,
-                        right_id,
-                    );
-                }
+            (Some(left_id), _) => {
+                return self.borrow_from_left::<N>(parent_id, child_index, left_id, child_id);
             }
+            (None, Some(right_id)) => {
+                return self.borrow_from_right::<N>(parent_id, child_index, child_id, right_id);
+            }
+            (None, None) => {}
         }
 
-        // Strategy 2: No siblings can donate, must merge (prefer left)
-        if let Some(left_id) = left_id_opt {
-            let child_id = match self.get_branch(parent_id) {
-                Some(parent) => match parent.children[child_index] {
-                    NodeRef::Leaf(id, _) => id,
-                    _ => return false,
-                },
-                None => return false,
-            };
-            self.merge_with_left_leaf_with_ids(parent_id, child_index, left_id, child_id)
-        } else if let Some(right_id) = right_id_opt {
-            let child_id = match self.get_branch(parent_id) {
-                Some(parent) => match parent.children[child_index] {
-                    NodeRef::Leaf(id, _) => id,
-                    _ => return false,
-                },
-                None => return false,
-            };
-            self.merge_with_right_leaf_with_ids(parent_id, child_index, child_id, right_id)
-        } else {
-            // No siblings available - this shouldn't happen in a valid B+ tree
-            false
-        }
-    }
-
-    /// Rebalance an underfull branch child using pre-gathered sibling information.
-    /// Optimized to reduce repeated arena lookups by resolving sibling IDs and separator keys once.
-    fn rebalance_branch(
-        &mut self,
-        parent_id: NodeId,
-        child_index: usize,
-        left_sibling_info: Option<(NodeRef<K, V>, bool)>,
-        right_sibling_info: Option<(NodeRef<K, V>, bool)>,
-    ) -> bool {
-        // Resolve sibling IDs and separator keys once from parent
-        let (left_id_opt, right_id_opt, left_sep_opt, right_sep_opt, child_id) =
-            match self.get_branch(parent_id) {
-                Some(parent) => {
-                    let left = if child_index > 0 {
-                        match parent.children[child_index - 1] {
-                            NodeRef::Branch(id, _) => Some(id),
-                            _ => None,
-                        }
-                    } else {
-                        None
-                    };
-                    let right = if child_index + 1 < parent.children.len() {
-                        match parent.children[child_index + 1] {
-                            NodeRef::Branch(id, _) => Some(id),
-                            _ => None,
-                        }
-                    } else {
-                        None
-                    };
-                    let left_sep = if left.is_some() {
-                        Some(parent.keys[child_index - 1].clone())
-                    } else {
-                        None
-                    };
-                    let right_sep = if right.is_some() {
-                        Some(parent.keys[child_index].clone())
-                    } else {
-                        None
-                    };
-                    let child_id = match parent.children[child_index] {
-                        NodeRef::Branch(id, _) => id,
-                        _ => return false,
-                    };
-                    (left, right, left_sep, right_sep, child_id)
-                }
-                None => return false,
-            };
-
-        // Strategy 1: Try to borrow (prefer left)
-        if let Some((_left_ref, can_donate)) = left_sibling_info {
-            if can_donate {
-                if let (Some(left_id), Some(sep)) = (left_id_opt, left_sep_opt) {
-                    return self.borrow_from_left_branch_with(
-                        parent_id,
-                        child_index,
-                        left_id,
-                        child_id,
-                        sep,
-                    );
-                }
+        // Strategy 2: No siblings can donate, must merge
+        match (left_id_opt, right_id_opt) {
+            (Some(_), Some(right_id)) if merge_right_first => {
+                self.merge_with_right::<N>(parent_id, child_index, child_id, right_id)
             }
-        }
-        if let Some((_right_ref, can_donate)) = right_sibling_info {
-            if can_donate {
-                if let (Some(right_id), Some(sep)) = (right_id_opt, right_sep_opt) {
-                    return self.borrow_from_right_branch_with(
-                        parent_id,
-                        child_index,
-                        child_id,
-                        right_id,
-                        sep,
-                    );
-                }
+            (Some(left_id), _) => {
+                self.merge_with_left::<N>(parent_id, child_index, left_id, child_id)
             }
-        }
-
-        // Strategy 2: Merge (prefer left)
-        if left_id_opt.is_some() {
-            self.merge_with_left_branch(parent_id, child_index)
-        } else if right_id_opt.is_some() {
-            self.merge_with_right_branch(parent_id, child_index)
-        } else {
-            false
-        }
-    }
-
-    /// Merge branch with left sibling
-    fn merge_with_left_branch(&mut self, parent_id: NodeId, child_index: usize) -> bool {
-        // Get the branch IDs and collect all needed info from parent in one access
-        let (left_id, child_id, separator_key) = match self.get_branch(parent_id) {
-            Some(parent) => {
-                match (
-                    &parent.children[child_index - 1],
-                    &parent.children[child_index],
-                ) {
-                    (NodeRef::Branch(left, _), NodeRef::Branch(child, _)) => {
-                        (*left, *child, parent.keys[child_index - 1].clone())
-                    }
-                    _ => return false,
-                }
+            (None, Some(right_id)) => {
+                self.merge_with_right::<N>(parent_id, child_index, child_id, right_id)
             }
-            None => return false,
-        };
-
-        // Extract all content from child and merge into left in one pass
-        // Use a safer approach that avoids multiple mutable borrows
-        {
-            // First, extract content from child
-            let (mut child_keys, mut child_children) = match self.get_branch_mut(child_id) {
-                Some(child_branch) => {
-                    let keys = std::mem::take(&mut child_branch.keys);
-                    let children = std::mem::take(&mut child_branch.children);
-                    (keys, children)
-                }
-                None => return false,
-            };
-
-            // Then merge into left (no extra reserving; capacity invariants hold)
-            let Some(left_branch) = self.get_branch_mut(left_id) else {
-                return false;
-            };
-            debug_assert!(left_branch.keys.len() + 1 + child_keys.len() <= left_branch.capacity);
-            debug_assert!(
-                left_branch.children.len() + child_children.len() <= left_branch.capacity + 1
-            );
-            left_branch.keys.push(separator_key);
-            left_branch.keys.append(&mut child_keys);
-            left_branch.children.append(&mut child_children);
+            // No siblings available - this shouldn't happen in a valid B+ tree
+            (None, None) => false,
         }
-
-        // Remove child from parent (single parent access)
-        let Some(parent) = self.get_branch_mut(parent_id) else {
-            return false;
-        };
-        parent.children.remove(child_index);
-        parent.keys.remove(child_index - 1);
-
-        // Deallocate the merged child
-        self.deallocate_branch(child_id);
-
-        false // Child was merged away
     }
 
-    /// Merge branch with right sibling
-    fn merge_with_right_branch(&mut self, parent_id: NodeId, child_index: usize) -> bool {
-        // Get the branch IDs and collect all needed info from parent in one access
-        let (child_id, right_id, separator_key) = match self.get_branch(parent_id) {
-            Some(parent) => {
-                match (
-                    &parent.children[child_index],
-                    &parent.children[child_index + 1],
-                ) {
-                    (NodeRef::Branch(child, _), NodeRef::Branch(right, _)) => {
-                        (*child, *right, parent.keys[child_index].clone())
-                    }
-                    _ => return false,
-                }
-            }
-            None => return false,
-        };
-
-        // Extract all content from right and merge into child in one pass
-        // Use a safer approach that avoids multiple mutable borrows
-        {
-            // First, extract content from right
-            let (mut right_keys, mut right_children) = match self.get_branch_mut(right_id) {
-                Some(right_branch) => {
-                    let keys = std::mem::take(&mut right_branch.keys);
-                    let children = std::mem::take(&mut right_branch.children);
-                    (keys, children)
-                }
-                None => return false,
-            };
-
-            // Then merge into child (no extra reserving; capacity invariants hold)
-            let Some(child_branch) = self.get_branch_mut(child_id) else {
-                return false;
-            };
-            debug_assert!(child_branch.keys.len() + 1 + right_keys.len() <= child_branch.capacity);
-            debug_assert!(
-                child_branch.children.len() + right_children.len() <= child_branch.capacity + 1
-            );
-            child_branch.keys.push(separator_key);
-            child_branch.keys.append(&mut right_keys);
-            child_branch.children.append(&mut right_children);
+    /// The parent key at `index`, if borrowing moves it into a node of kind `N`.
+    fn separator_for_borrow<N: RebalanceNode<K, V>>(
+        &self,
+        parent_id: NodeId,
+        index: usize,
+    ) -> Option<K> {
+        if !N::SEPARATOR_MOVES_DOWN {
+            return None;
         }
-
-        // Remove right from parent (second and final parent access)
-        let Some(parent) = self.get_branch_mut(parent_id) else {
-            return false;
-        };
-        parent.children.remove(child_index + 1);
-        parent.keys.remove(child_index);
-
-        // Deallocate the merged right sibling
-        self.deallocate_branch(right_id);
-
-        true // Child still exists
+        self.get_branch(parent_id)?.keys.get(index).cloned()
     }
 
-    // Optimized helpers that avoid re-reading parent for IDs/keys
-    fn borrow_from_left_branch_with(
+    /// Move the last entry of the left sibling into the child. Returns
+    /// whether the borrow happened.
+    pub(crate) fn borrow_from_left<N: RebalanceNode<K, V>>(
         &mut self,
         parent_id: NodeId,
         child_index: usize,
         left_id: NodeId,
         child_id: NodeId,
-        separator_key: K,
     ) -> bool {
-        let (moved_key, moved_child) = match self.get_branch_mut(left_id) {
-            Some(left_branch) => match left_branch.borrow_last() {
-                Some(result) => result,
-                None => return false,
-            },
-            None => return false,
+        let separator = self.separator_for_borrow::<N>(parent_id, child_index - 1);
+        let Some((moved, new_separator)) =
+            N::node_mut(self, left_id).and_then(|left| left.donate_last(separator))
+        else {
+            return false;
         };
-
-        let Some(child_branch) = self.get_branch_mut(child_id) else {
+        let Some(child) = N::node_mut(self, child_id) else {
             return false;
         };
-        let new_separator = child_branch.accept_from_left(separator_key, moved_key, moved_child);
-
+        child.accept_first(moved);
         let Some(parent) = self.get_branch_mut(parent_id) else {
             return false;
         };
@@ -692,27 +592,25 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
         true
     }
 
-    fn borrow_from_right_branch_with(
+    /// Move the first entry of the right sibling into the child. Returns
+    /// whether the borrow happened.
+    pub(crate) fn borrow_from_right<N: RebalanceNode<K, V>>(
         &mut self,
         parent_id: NodeId,
         child_index: usize,
         child_id: NodeId,
         right_id: NodeId,
-        separator_key: K,
     ) -> bool {
-        let (moved_key, moved_child) = match self.get_branch_mut(right_id) {
-            Some(right_branch) => match right_branch.borrow_first() {
-                Some(result) => result,
-                None => return false,
-            },
-            None => return false,
+        let separator = self.separator_for_borrow::<N>(parent_id, child_index);
+        let Some((moved, new_separator)) =
+            N::node_mut(self, right_id).and_then(|right| right.donate_first(separator))
+        else {
+            return false;
         };
-
-        let Some(child_branch) = self.get_branch_mut(child_id) else {
+        let Some(child) = N::node_mut(self, child_id) else {
             return false;
         };
-        let new_separator = child_branch.accept_from_right(separator_key, moved_key, moved_child);
-
+        child.accept_last(moved);
         let Some(parent) = self.get_branch_mut(parent_id) else {
             return false;
         };
@@ -720,121 +618,58 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
         true
     }
 
-    fn borrow_from_left_leaf_with_ids(
+    /// Merge the child into its left sibling. Always returns false, as the
+    /// child no longer exists.
+    pub(crate) fn merge_with_left<N: RebalanceNode<K, V>>(
         &mut self,
-        branch_id: NodeId,
+        parent_id: NodeId,
         child_index: usize,
         left_id: NodeId,
         child_id: NodeId,
     ) -> bool {
-        let (key, value) = match self.get_leaf_mut(left_id) {
-            Some(left_leaf) => match left_leaf.borrow_last() {
-                Some(kv) => kv,
-                None => return false,
-            },
-            None => return false,
-        };
-        let sep = key.clone();
-        let Some(child_leaf) = self.get_leaf_mut(child_id) else {
-            return false;
-        };
-        child_leaf.accept_from_left(key, value);
-        if let Some(parent) = self.get_branch_mut(branch_id) {
-            parent.keys[child_index - 1] = sep;
-            true
-        } else {
-            false
-        }
+        self.merge_siblings::<N>(parent_id, child_index - 1, left_id, child_id);
+        false // Child was merged away
     }
 
-    fn borrow_from_right_leaf_with_ids(
+    /// Merge the right sibling into the child. Returns whether the merge
+    /// happened, in which case the child still exists.
+    pub(crate) fn merge_with_right<N: RebalanceNode<K, V>>(
         &mut self,
-        branch_id: NodeId,
+        parent_id: NodeId,
         child_index: usize,
         child_id: NodeId,
         right_id: NodeId,
     ) -> bool {
-        let (key, value, new_first_opt) = if let Some(right_leaf) = self.get_leaf_mut(right_id) {
-            if let Some((k, v)) = right_leaf.borrow_first() {
-                (k, v, right_leaf.first_key().cloned())
-            } else {
-                return false;
-            }
-        } else {
-            return false;
-        };
-        let Some(child_leaf) = self.get_leaf_mut(child_id) else {
-            return false;
-        };
-        child_leaf.accept_from_right(key, value);
-        if let (Some(sep), Some(parent)) = (new_first_opt, self.get_branch_mut(branch_id)) {
-            parent.keys[child_index] = sep;
-            true
-        } else {
-            false
-        }
+        self.merge_siblings::<N>(parent_id, child_index, child_id, right_id)
     }
 
-    fn merge_with_left_leaf_with_ids(
+    /// Move everything in the parent's child `left_index + 1` into child
+    /// `left_index`, drop it from the parent and free it.
+    fn merge_siblings<N: RebalanceNode<K, V>>(
         &mut self,
-        branch_id: NodeId,
-        child_index: usize,
+        parent_id: NodeId,
+        left_index: usize,
         left_id: NodeId,
-        child_id: NodeId,
+        right_id: NodeId,
     ) -> bool {
-        let (mut child_keys, mut child_values, child_next) = match self.get_leaf_mut(child_id) {
-            Some(child_leaf) => child_leaf.extract_all(),
-            None => return false,
-        };
-        let Some(left_leaf) = self.get_leaf_mut(left_id) else {
+        let Some(contents) = N::node_mut(self, right_id).map(N::take_all) else {
             return false;
         };
-        debug_assert!(left_leaf.keys.len() + child_keys.len() <= left_leaf.capacity);
-        debug_assert!(left_leaf.values.len() + child_values.len() <= left_leaf.capacity);
-        left_leaf.append_keys(&mut child_keys);
-        left_leaf.append_values(&mut child_values);
-        left_leaf.next = child_next;
-        let Some(branch) = self.get_branch_mut(branch_id) else {
+        let Some(parent) = self.get_branch_mut(parent_id) else {
             return false;
         };
-        branch.children.remove(child_index);
-        branch.keys.remove(child_index - 1);
-        self.deallocate_leaf(child_id);
-        false
-    }
-
-    fn merge_with_right_leaf_with_ids(
-        &mut self,
-        branch_id: NodeId,
-        child_index: usize,
-        child_id: NodeId,
-        right_id: NodeId,
-    ) -> bool {
-        {
-            let (mut right_keys, mut right_values, right_next) = match self.get_leaf_mut(right_id) {
-                Some(right_leaf) => {
-                    let keys = right_leaf.take_keys();
-                    let values = right_leaf.take_values();
-                    let next = right_leaf.next;
-                    (keys, values, next)
-                }
-                None => return false,
-            };
-            let Some(child_leaf) = self.get_leaf_mut(child_id) else {
-                return false;
-            };
-            debug_assert!(child_leaf.keys.len() + right_keys.len() <= child_leaf.capacity);
-            debug_assert!(child_leaf.values.len() + right_values.len() <= child_leaf.capacity);
-            child_leaf.append_keys(&mut right_keys);
-            child_leaf.append_values(&mut right_values);
-            child_leaf.next = right_next;
-        }
-        let Some(branch) = self.get_branch_mut(branch_id) else {
+        parent.remove_child(left_index + 1);
+        let separator = parent.keys.remove(left_index);
+        let Some(left) = N::node_mut(self, left_id) else {
             return false;
         };
-        branch.children.remove(child_index + 1);
-        branch.keys.remove(child_index);
-        self.deallocate_leaf(right_id);
+        // No extra reserving; capacity invariants hold
+        let dropped_separator = left.absorb(separator, contents);
+        N::free(self, right_id);
+        if let Some(separator) = dropped_separator {
+            // A leaf boundary is gone
+            self.report_leaf_merge(left_id, &separator);
+        }
         true
     }
 }
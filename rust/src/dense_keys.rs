@@ -0,0 +1,160 @@
+//! Queries specific to integer keys.
+//!
+//! Trees used as id allocators hold long runs of consecutive integers. The
+//! [`DenseKey`] trait lets queries recognise such runs: a slice of sorted,
+//! distinct keys is a run exactly when its last key is as far from its first
+//! as its length implies, so a whole leaf can be skipped after one comparison.
+
+use crate::types::BPlusTreeMap;
+use std::ops::Bound;
+
+/// An integer key type whose values can be counted between.
+pub trait DenseKey: Ord + Clone {
+    /// The next value, or `None` at the type's maximum.
+    fn successor(&self) -> Option<Self>;
+
+    /// Number of steps from `low` up to `high`, or `None` if it does not fit
+    /// in a `usize`. Callers only pass `low <= high`.
+    fn steps_between(low: &Self, high: &Self) -> Option<usize>;
+}
+
+macro_rules! impl_dense_key {
+    ($($t:ty),*) => {
+        $(
+            impl DenseKey for $t {
+                #[inline]
+                fn successor(&self) -> Option<Self> {
+                    self.checked_add(1)
+                }
+
+                #[inline]
+                fn steps_between(low: &Self, high: &Self) -> Option<usize> {
+                    usize::try_from(high.abs_diff(*low)).ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_dense_key!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+/// Length of the run of consecutive integers at the start of `keys`.
+pub(crate) fn dense_prefix_len<K: DenseKey>(keys: &[K]) -> usize {
+    let (first, last) = match (keys.first(), keys.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return 0,
+    };
+    if K::steps_between(first, last) == Some(keys.len() - 1) {
+        return keys.len();
+    }
+
+    // Keys are distinct and sorted, so `keys[j]` is at least `j` steps from
+    // the first key, and exactly `j` only inside the leading run
+    let (mut low, mut high) = (1, keys.len() - 1);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if K::steps_between(first, &keys[mid]) == Some(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+impl<K: DenseKey, V: Clone> BPlusTreeMap<K, V> {
+    /// Returns the smallest key at or after `start` that is not in the tree.
+    ///
+    /// Returns `None` only when every value from `start` to the key type's
+    /// maximum is present. Leaves that hold a single run of consecutive keys
+    /// are skipped after checking their first and last key, so allocating ids
+    /// from a densely used range does not visit every key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut ids = BPlusTreeMap::new(16).unwrap();
+    /// for id in (0u32..100).chain(101..200) {
+    ///     ids.insert(id, ());
+    /// }
+    /// assert_eq!(ids.first_missing_key_after(&0), Some(100));
+    /// assert_eq!(ids.first_missing_key_after(&101), Some(200));
+    /// assert_eq!(ids.first_missing_key_after(&500), Some(500));
+    /// ```
+    pub fn first_missing_key_after(&self, start: &K) -> Option<K> {
+        let mut candidate = start.clone();
+        let (mut leaf_id, mut index) = match self.range_start_position(Bound::Included(start)) {
+            Some(position) => position,
+            None => return Some(candidate),
+        };
+
+        while let Some(leaf) = self.get_leaf(leaf_id) {
+            let keys = &leaf.keys[index.min(leaf.keys.len())..];
+            if let Some(first) = keys.first() {
+                if *first != candidate {
+                    return Some(candidate);
+                }
+                let run = dense_prefix_len(keys);
+                candidate = keys[run - 1].successor()?;
+                if run < keys.len() {
+                    return Some(candidate);
+                }
+            }
+            leaf_id = leaf.next;
+            index = 0;
+        }
+        Some(candidate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+
+    #[test]
+    fn test_dense_prefix_len() {
+        assert_eq!(dense_prefix_len::<u32>(&[]), 0);
+        assert_eq!(dense_prefix_len(&[5u32]), 1);
+        assert_eq!(dense_prefix_len(&[5u32, 6, 7, 9, 10]), 3);
+        assert_eq!(dense_prefix_len(&[-2i64, -1, 0, 1]), 4);
+        assert_eq!(dense_prefix_len(&[0u8, 2]), 1);
+    }
+
+    #[test]
+    fn test_first_missing_key_after_matches_scan() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        let mut present = vec![false; 2_000];
+        let mut rng = SeededRng::new(7);
+        for _ in 0..1_500 {
+            let key = rng.below(1_000) as u32 + 200;
+            tree.insert(key, ());
+            present[key as usize] = true;
+        }
+
+        for start in 0..1_300u32 {
+            let expected = (start..).find(|&k| !present[k as usize]);
+            assert_eq!(
+                tree.first_missing_key_after(&start),
+                expected,
+                "start {}",
+                start
+            );
+        }
+    }
+
+    #[test]
+    fn test_first_missing_key_after_at_type_maximum() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for key in 250u8..=255 {
+            tree.insert(key, ());
+        }
+        assert_eq!(tree.first_missing_key_after(&250), None);
+        assert_eq!(tree.first_missing_key_after(&249), Some(249));
+
+        let empty: BPlusTreeMap<u8, ()> = BPlusTreeMap::new(4).unwrap();
+        assert_eq!(empty.first_missing_key_after(&3), Some(3));
+    }
+}
@@ -0,0 +1,277 @@
+//! Run-length storage for dense `u64` keys.
+//!
+//! A `BPlusTreeMap<u64, V>` stores every key next to its value. When the keys
+//! are mostly consecutive, as with row ids or sequence numbers, that doubles
+//! the memory for small values. [`DenseU64Map`] instead keys the tree by the
+//! first key of each run of consecutive keys and stores the run's values in
+//! one array, so a fully dense map holds a single key per run no matter how
+//! many entries it has.
+
+use crate::error::InitResult;
+use crate::invariant::invariant;
+use crate::types::BPlusTreeMap;
+use std::ops::{Bound, RangeBounds};
+
+/// A map from `u64` keys that stores runs of consecutive keys compactly.
+///
+/// Lookups find the run whose start is the greatest at or below the key, then
+/// index into it. Inserting a key next to a run extends it, and inserting the
+/// key that closes a gap joins the two runs; removing a key from the middle
+/// of a run splits it.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::DenseU64Map;
+///
+/// let mut rows = DenseU64Map::new(16).unwrap();
+/// for id in 0..1_000 {
+///     rows.insert(id, id * 2);
+/// }
+/// assert_eq!(rows.run_count(), 1);
+/// assert_eq!(rows.get(500), Some(&1_000));
+///
+/// rows.remove(500);
+/// assert_eq!(rows.run_count(), 2);
+/// assert_eq!(rows.range(498..503).map(|(k, _)| k).collect::<Vec<_>>(), vec![498, 499, 501, 502]);
+/// ```
+#[derive(Debug)]
+pub struct DenseU64Map<V> {
+    runs: BPlusTreeMap<u64, Vec<V>>,
+    len: usize,
+}
+
+impl<V: Clone> DenseU64Map<V> {
+    /// Create an empty map whose underlying tree has the given node capacity.
+    pub fn new(capacity: usize) -> InitResult<Self> {
+        Ok(Self {
+            runs: BPlusTreeMap::new(capacity)?,
+            len: 0,
+        })
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the map holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of runs of consecutive keys, which is the number of keys the
+    /// underlying tree stores.
+    pub fn run_count(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Get the value stored under `key`.
+    pub fn get(&self, key: u64) -> Option<&V> {
+        let (start, run) = self.runs.last_entry_before(Bound::Included(&key))?;
+        run.get(offset(*start, key)?)
+    }
+
+    /// Get a mutable reference to the value stored under `key`.
+    pub fn get_mut(&mut self, key: u64) -> Option<&mut V> {
+        let start = self.run_start_containing(key)?;
+        self.runs.get_mut(&start)?.get_mut(offset(start, key)?)
+    }
+
+    /// Returns true if `key` is present.
+    pub fn contains_key(&self, key: u64) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Insert `value` under `key`, returning the previous value if any.
+    pub fn insert(&mut self, key: u64, value: V) -> Option<V> {
+        if let Some(existing) = self.get_mut(key) {
+            return Some(std::mem::replace(existing, value));
+        }
+
+        // Steal the run that starts right after `key`, if there is one
+        let following = key.checked_add(1).and_then(|next| self.runs.remove(&next));
+        let preceding = key
+            .checked_sub(1)
+            .and_then(|previous| self.run_start_containing(previous));
+
+        match preceding {
+            Some(start) => {
+                let run = invariant(self.runs.get_mut(&start), "run start is present");
+                run.push(value);
+                if let Some(mut following) = following {
+                    run.append(&mut following);
+                }
+            }
+            None => {
+                let mut run = Vec::with_capacity(1 + following.as_ref().map_or(0, Vec::len));
+                run.push(value);
+                run.extend(following.unwrap_or_default());
+                self.runs.insert(key, run);
+            }
+        }
+        self.len += 1;
+        None
+    }
+
+    /// Remove `key`, returning its value if it was present.
+    pub fn remove(&mut self, key: u64) -> Option<V> {
+        let start = self.run_start_containing(key)?;
+        let index = offset(start, key)?;
+        let run = self.runs.get_mut(&start)?;
+
+        let value = if index + 1 == run.len() {
+            // Last key of the run: shrink it in place
+            let value = run.pop();
+            if run.is_empty() {
+                self.runs.remove(&start);
+            }
+            value
+        } else {
+            // Split off the keys after `key` into their own run
+            let tail = run.split_off(index + 1);
+            let value = run.pop();
+            if run.is_empty() {
+                self.runs.remove(&start);
+            }
+            self.runs.insert(key + 1, tail);
+            value
+        };
+        self.len -= 1;
+        value
+    }
+
+    /// Iterate over all entries in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &V)> + '_ {
+        self.runs.items().flat_map(expand_run)
+    }
+
+    /// Iterate over the entries whose keys fall in `range`, in key order.
+    pub fn range<R>(&self, range: R) -> impl Iterator<Item = (u64, &V)> + '_
+    where
+        R: RangeBounds<u64>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(&key) => Some(key),
+            Bound::Excluded(&key) => key.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let end = range.end_bound().cloned();
+
+        // Begin at the run that may contain the start key
+        let first_run = start.map(|start| {
+            self.runs
+                .last_entry_before(Bound::Included(&start))
+                .map_or(start, |(run_start, _)| *run_start)
+        });
+        first_run
+            .into_iter()
+            .flat_map(move |first_run| self.runs.range(first_run..).flat_map(expand_run))
+            .skip_while(move |(key, _)| start.is_some_and(|start| *key < start))
+            .take_while(move |(key, _)| match end {
+                Bound::Included(end) => *key <= end,
+                Bound::Excluded(end) => *key < end,
+                Bound::Unbounded => true,
+            })
+    }
+
+    /// Start of the run holding `key`, if `key` is present.
+    fn run_start_containing(&self, key: u64) -> Option<u64> {
+        let (start, run) = self.runs.last_entry_before(Bound::Included(&key))?;
+        (offset(*start, key)? < run.len()).then_some(*start)
+    }
+}
+
+/// Position of `key` within a run starting at `start`.
+fn offset(start: u64, key: u64) -> Option<usize> {
+    usize::try_from(key - start).ok()
+}
+
+/// The entries of one run, with their keys.
+fn expand_run<'a, V>((start, run): (&'a u64, &'a Vec<V>)) -> impl Iterator<Item = (u64, &'a V)> {
+    let start = *start;
+    run.iter()
+        .enumerate()
+        .map(move |(index, value)| (start + index as u64, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_runs_join_and_split() {
+        let mut map = DenseU64Map::new(4).unwrap();
+        for key in (0..10).chain(11..20) {
+            map.insert(key, key);
+        }
+        assert_eq!(map.run_count(), 2);
+
+        // Closing the gap joins the two runs
+        map.insert(10, 10);
+        assert_eq!(map.run_count(), 1);
+        assert_eq!(map.len(), 20);
+
+        assert_eq!(map.remove(0), Some(0));
+        assert_eq!(map.remove(19), Some(19));
+        assert_eq!(map.remove(7), Some(7));
+        assert_eq!(map.remove(7), None);
+        assert_eq!(map.run_count(), 2);
+        assert_eq!(map.get(8), Some(&8));
+        assert_eq!(map.iter().map(|(k, _)| k).collect::<Vec<_>>(), {
+            let mut keys: Vec<u64> = (1..19).collect();
+            keys.retain(|&k| k != 7);
+            keys
+        });
+    }
+
+    #[test]
+    fn test_extreme_keys() {
+        let mut map = DenseU64Map::new(4).unwrap();
+        map.insert(u64::MAX, 1);
+        map.insert(u64::MAX - 1, 2);
+        map.insert(0, 3);
+        assert_eq!(map.run_count(), 2);
+        assert_eq!(map.get(u64::MAX), Some(&1));
+        assert_eq!(map.range(u64::MAX..).count(), 1);
+        assert_eq!(
+            map.range((Bound::Excluded(u64::MAX), Bound::Unbounded))
+                .count(),
+            0
+        );
+        assert_eq!(map.remove(u64::MAX), Some(1));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn test_random_operations_match_btreemap() {
+        let mut map = DenseU64Map::new(5).unwrap();
+        let mut reference = BTreeMap::new();
+        let mut rng = SeededRng::new(99);
+
+        for i in 0..5_000 {
+            let key = rng.below(600);
+            if rng.below(3) == 0 {
+                assert_eq!(map.remove(key), reference.remove(&key));
+            } else {
+                assert_eq!(map.insert(key, i), reference.insert(key, i));
+            }
+            if i % 500 == 0 {
+                let (lo, hi) = (rng.below(600), rng.below(600));
+                let (lo, hi) = (lo.min(hi), lo.max(hi));
+                assert!(map
+                    .range(lo..=hi)
+                    .eq(reference.range(lo..=hi).map(|(k, v)| (*k, v))));
+            }
+        }
+
+        assert_eq!(map.len(), reference.len());
+        assert!(map.iter().eq(reference.iter().map(|(k, v)| (*k, v))));
+        for key in 0..600 {
+            assert_eq!(map.get(key), reference.get(&key));
+        }
+        assert!(map.runs.check_invariants());
+    }
+}
@@ -45,7 +45,7 @@ fn analyze_arena_access_pattern(bplus: &BPlusTreeMap<usize, usize>, size: usize)
     let start_time = Instant::now();
     for _ in 0..iterations {
         let mut count = 0;
-        for (_k, _v) in bplus.items_range(Some(&start), Some(&_end)) {
+        for (_k, _v) in bplus.range(start.._end) {
             count += 1;
         }
         assert_eq!(count, 1000);
@@ -89,9 +89,10 @@ fn compare_iterator_implementations(bplus: &BPlusTreeMap<usize, usize>, size: us
     }
     let regular_time = start_time.elapsed();
 
-    // Test FastItemIterator
+    // Test the deprecated items_fast alias, which now shares ItemIterator
     let start_time = Instant::now();
     for _ in 0..iterations {
+        #[allow(deprecated)]
         for (count, (_k, _v)) in bplus.items_fast().enumerate() {
             if count >= 1000 {
                 break;
@@ -175,7 +176,7 @@ fn compare_with_btreemap(bplus: &BPlusTreeMap<usize, usize>, size: usize) {
     // Benchmark BPlusTreeMap iterator
     let start_time = Instant::now();
     for _ in 0..iterations {
-        for (_k, _v) in bplus.items_range(Some(&start), Some(&end)) {
+        for (_k, _v) in bplus.range(start..end) {
             // Consume iterator
         }
     }
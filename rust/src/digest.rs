@@ -0,0 +1,237 @@
+//! Range digests for anti-entropy sync between trees.
+//!
+//! Two replicas of a map can find where they disagree without exchanging
+//! their contents: one side summarises its key space as a list of
+//! [`RangeDigest`]s, sends them over, and the other side calls
+//! [`BPlusTreeMap::diff_ranges`] to learn which ranges hash differently. Only
+//! those ranges need to be transferred, or digested again at a finer chunk
+//! size to narrow them down further, Merkle-tree style.
+//!
+//! Digests hash each entry with FNV-1a through the keys' and values' `Hash`
+//! implementations, so they do not depend on a per-process random seed and
+//! can be compared between processes on platforms of the same endianness.
+
+use crate::types::BPlusTreeMap;
+use std::hash::{Hash, Hasher};
+use std::ops::{Bound, RangeBounds};
+
+/// A summary of the entries of a tree within one key range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeDigest<K> {
+    /// Start of the summarised range.
+    pub start: Bound<K>,
+    /// End of the summarised range.
+    pub end: Bound<K>,
+    /// Number of entries in the range.
+    pub count: usize,
+    /// Order-sensitive hash of the entries in the range.
+    pub hash: u64,
+}
+
+impl<K: Ord> RangeBounds<K> for RangeDigest<K> {
+    fn start_bound(&self) -> Bound<&K> {
+        self.start.as_ref()
+    }
+
+    fn end_bound(&self) -> Bound<&K> {
+        self.end.as_ref()
+    }
+}
+
+/// 64-bit FNV-1a, chosen because it is deterministic and needs no seed.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+impl<K: Ord + Clone + Hash, V: Clone + Hash> BPlusTreeMap<K, V> {
+    /// Summarise the entries in `range` as a single digest.
+    pub fn digest_range<R: RangeBounds<K>>(&self, range: R) -> RangeDigest<K> {
+        let mut hasher = Fnv1a::new();
+        let mut count = 0;
+        self.for_each_leaf_slice_in_range(&range, |keys, values| {
+            for (key, value) in keys.iter().zip(values) {
+                key.hash(&mut hasher);
+                value.hash(&mut hasher);
+            }
+            count += keys.len();
+        });
+        RangeDigest {
+            start: range.start_bound().cloned(),
+            end: range.end_bound().cloned(),
+            count,
+            hash: hasher.finish(),
+        }
+    }
+
+    /// Split `range` into consecutive subranges of about `chunk_size` entries
+    /// each and digest every one.
+    ///
+    /// The subranges exactly cover `range`, including the key space between
+    /// and around this tree's keys, so a peer holding keys this tree lacks
+    /// still sees them fall into some digest. An empty range yields a single
+    /// digest with a count of zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn range_digests<R: RangeBounds<K>>(
+        &self,
+        range: R,
+        chunk_size: usize,
+    ) -> Vec<RangeDigest<K>> {
+        assert!(chunk_size > 0, "chunk_size must be positive");
+        let mut digests = Vec::new();
+        let mut start = range.start_bound().cloned();
+        let mut hasher = Fnv1a::new();
+        let mut count = 0;
+
+        for (key, value) in self.range((range.start_bound(), range.end_bound())) {
+            if count == chunk_size {
+                // Close the chunk just before the key that starts the next one
+                digests.push(RangeDigest {
+                    start: std::mem::replace(&mut start, Bound::Included(key.clone())),
+                    end: Bound::Excluded(key.clone()),
+                    count,
+                    hash: hasher.finish(),
+                });
+                hasher = Fnv1a::new();
+                count = 0;
+            }
+            key.hash(&mut hasher);
+            value.hash(&mut hasher);
+            count += 1;
+        }
+
+        digests.push(RangeDigest {
+            start,
+            end: range.end_bound().cloned(),
+            count,
+            hash: hasher.finish(),
+        });
+        digests
+    }
+
+    /// Compare a peer's digests with this tree's contents over the same
+    /// ranges, returning the ranges where the two differ.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut primary = BPlusTreeMap::new(16).unwrap();
+    /// let mut replica = BPlusTreeMap::new(16).unwrap();
+    /// for i in 0..1_000 {
+    ///     primary.insert(i, i);
+    ///     replica.insert(i, i);
+    /// }
+    /// replica.insert(420, 0);
+    ///
+    /// // The primary sends 10 digests instead of 1,000 entries
+    /// let digests = primary.range_digests(.., 100);
+    /// let stale = replica.diff_ranges(&digests);
+    /// assert_eq!(stale.len(), 1);
+    /// assert_eq!(stale[0].start, std::ops::Bound::Included(400));
+    ///
+    /// // Copy just the stale range across
+    /// for digest in &stale {
+    ///     for (k, v) in primary.range(digest.clone()) {
+    ///         replica.insert(*k, *v);
+    ///     }
+    /// }
+    /// assert!(replica.diff_ranges(&digests).is_empty());
+    /// ```
+    pub fn diff_ranges(&self, other_digests: &[RangeDigest<K>]) -> Vec<RangeDigest<K>> {
+        other_digests
+            .iter()
+            .filter(|remote| {
+                let local = self.digest_range((remote.start.as_ref(), remote.end.as_ref()));
+                local.count != remote.count || local.hash != remote.hash
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_digests_cover_the_requested_range() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..95u32 {
+            tree.insert(i * 2, i);
+        }
+        let digests = tree.range_digests(10..150, 20);
+
+        assert_eq!(digests.first().unwrap().start, Bound::Included(10));
+        assert_eq!(digests.last().unwrap().end, Bound::Excluded(150));
+        for pair in digests.windows(2) {
+            match (&pair[0].end, &pair[1].start) {
+                (Bound::Excluded(end), Bound::Included(start)) => assert_eq!(end, start),
+                other => panic!("subranges do not meet: {:?}", other),
+            }
+        }
+        assert_eq!(digests.iter().map(|d| d.count).sum::<usize>(), 70);
+        assert!(digests.iter().all(|d| d.count <= 20));
+        for digest in &digests {
+            assert_eq!(tree.digest_range(digest.clone()), *digest);
+        }
+
+        let empty: BPlusTreeMap<u32, u32> = BPlusTreeMap::new(4).unwrap();
+        let digests = empty.range_digests(.., 10);
+        assert_eq!(digests.len(), 1);
+        assert_eq!(digests[0].count, 0);
+    }
+
+    #[test]
+    fn test_diff_ranges_finds_every_difference() {
+        let mut primary = BPlusTreeMap::new(4).unwrap();
+        let mut replica = BPlusTreeMap::new(4).unwrap();
+        for i in 0..2_000u32 {
+            primary.insert(i, i);
+            replica.insert(i, i);
+        }
+        replica.remove(&150);
+        replica.insert(777, 0);
+        replica.insert(5_000, 1);
+
+        let stale = replica.diff_ranges(&primary.range_digests(.., 100));
+        let stale_keys: Vec<Bound<u32>> = stale.iter().map(|d| d.start).collect();
+        assert_eq!(
+            stale_keys,
+            vec![
+                Bound::Included(100),
+                Bound::Included(700),
+                Bound::Included(1_900)
+            ]
+        );
+
+        // Refining a stale range narrows it down
+        let finer = replica.diff_ranges(&primary.range_digests(stale[1].clone(), 10));
+        assert_eq!(finer.len(), 1);
+        assert_eq!(finer[0].start, Bound::Included(770));
+
+        assert!(primary
+            .diff_ranges(&primary.range_digests(.., 100))
+            .is_empty());
+    }
+}
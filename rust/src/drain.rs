@@ -0,0 +1,170 @@
+//! Removing a key range while handing back what was removed.
+//!
+//! [`BPlusTreeMap::drain_range`] removes the entries of a range one at a
+//! time as its iterator is advanced, so a caller can archive each entry or
+//! delete it from a secondary index as it goes, without first scanning the
+//! range to find out what is about to disappear.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::types::BPlusTreeMap;
+use std::iter::FusedIterator;
+use std::ops::{Bound, RangeBounds};
+
+/// Iterator that removes and yields the entries of a key range in ascending
+/// order. Returned by [`BPlusTreeMap::drain_range`].
+///
+/// Dropping it removes whatever is left of the range, as `Vec::drain` does.
+/// Leaking it with [`std::mem::forget`] leaves the rest in the tree.
+pub struct DrainRange<'a, K: TreeKey, V: TreeValue> {
+    tree: &'a mut BPlusTreeMap<K, V>,
+    start: Bound<K>,
+    end: Bound<K>,
+    done: bool,
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Remove the entries in `range`, yielding each `(key, value)` pair as it
+    /// is removed, in ascending key order.
+    ///
+    /// Removal is lazy: each call to `next` takes out one entry and
+    /// rebalances, so the tree is valid between steps. Whatever the iterator
+    /// has not reached when it is dropped is removed then.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..10 {
+    ///     tree.insert(i, i * 10);
+    /// }
+    ///
+    /// let mut archive = Vec::new();
+    /// for (key, value) in tree.drain_range(3..6) {
+    ///     archive.push((key, value));
+    /// }
+    /// assert_eq!(archive, vec![(3, 30), (4, 40), (5, 50)]);
+    /// assert_eq!(tree.len(), 7);
+    ///
+    /// // Stopping early still removes the whole range
+    /// assert_eq!(tree.drain_range(7..).next(), Some((7, 70)));
+    /// assert_eq!(tree.last(), Some((&6, &60)));
+    /// ```
+    pub fn drain_range<R: RangeBounds<K>>(&mut self, range: R) -> DrainRange<'_, K, V> {
+        DrainRange {
+            start: range.start_bound().cloned(),
+            end: range.end_bound().cloned(),
+            tree: self,
+            done: false,
+        }
+    }
+
+    /// Remove the entries in `range` and return how many there were.
+    ///
+    /// Use [`drain_range`](Self::drain_range) to see the removed entries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..10 {
+    ///     tree.insert(i, i);
+    /// }
+    /// assert_eq!(tree.remove_range(2..=4), 3);
+    /// assert_eq!(tree.remove_range(2..=4), 0);
+    /// ```
+    pub fn remove_range<R: RangeBounds<K>>(&mut self, range: R) -> usize {
+        self.drain_range(range).count()
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> Iterator for DrainRange<'_, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        if self.done {
+            return None;
+        }
+        let next_key = self
+            .tree
+            .range((self.start.as_ref(), self.end.as_ref()))
+            .next()
+            .map(|(key, _)| key.clone());
+        let Some(key) = next_key else {
+            self.done = true;
+            return None;
+        };
+        let value = self.tree.remove(&key)?;
+        self.start = Bound::Excluded(key.clone());
+        Some((key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            (0, Some(0))
+        } else {
+            (0, Some(self.tree.len()))
+        }
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> FusedIterator for DrainRange<'_, K, V> {}
+
+impl<K: TreeKey, V: TreeValue> Drop for DrainRange<'_, K, V> {
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_drain_range_matches_reference() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        let mut model = BTreeMap::new();
+        for i in 0..1_000u32 {
+            tree.insert(i, i * 3);
+            model.insert(i, i * 3);
+        }
+
+        let mut rng = SeededRng::new(7);
+        for _ in 0..50 {
+            let start = rng.below(1_000) as u32;
+            let end = start + rng.below(60) as u32;
+
+            let drained: Vec<_> = tree.drain_range(start..end).collect();
+            let expected: Vec<_> = model.range(start..end).map(|(k, v)| (*k, *v)).collect();
+            for (key, _) in &expected {
+                model.remove(key);
+            }
+            assert_eq!(drained, expected);
+            assert!(tree.check_invariants());
+        }
+        assert!(tree.items().map(|(k, v)| (*k, *v)).eq(model));
+    }
+
+    #[test]
+    fn test_dropping_drain_removes_rest_of_range() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..100 {
+            tree.insert(i, i);
+        }
+
+        let mut drain = tree.drain_range(10..=40);
+        assert_eq!(drain.next(), Some((10, 10)));
+        assert_eq!(drain.next(), Some((11, 11)));
+        drop(drain);
+
+        assert_eq!(tree.len(), 69);
+        assert_eq!(tree.next_key_after(&9), Some(&41));
+        assert!(tree.check_invariants());
+        assert_eq!(tree.drain_range(200..).next(), None);
+    }
+}
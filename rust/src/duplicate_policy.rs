@@ -0,0 +1,175 @@
+//! What an insert does when its key is already present.
+//!
+//! Keys in the tree are unique, so a second insert under a key has to be
+//! folded into the entry that is already there. [`insert`] overwrites; a
+//! [`DuplicatePolicy`] chosen per call can instead refuse the insert or keep
+//! every value. Unique-insert and multimap helpers are all this one
+//! mechanism with a different policy.
+//!
+//! [`insert`]: BPlusTreeMap::insert
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::types::BPlusTreeMap;
+
+/// How [`BPlusTreeMap::insert_with_policy`] resolves an insert under a key
+/// that already holds a value.
+pub trait DuplicatePolicy<V> {
+    /// Fold `new` into `existing`. Returns `Ok` with any value displaced from
+    /// the map, or `Err(new)` to refuse the insert and leave `existing` as it
+    /// was.
+    fn resolve(existing: &mut V, new: V) -> Result<Option<V>, V>;
+}
+
+/// Replace the stored value and hand back the old one. This is what
+/// [`BPlusTreeMap::insert`] does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Overwrite;
+
+/// Refuse the insert and keep the stored value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Reject;
+
+/// Keep every value by appending the new items to the stored `Vec`, making
+/// the map a multimap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KeepAll;
+
+impl<V> DuplicatePolicy<V> for Overwrite {
+    fn resolve(existing: &mut V, new: V) -> Result<Option<V>, V> {
+        Ok(Some(std::mem::replace(existing, new)))
+    }
+}
+
+impl<V> DuplicatePolicy<V> for Reject {
+    fn resolve(_existing: &mut V, new: V) -> Result<Option<V>, V> {
+        Err(new)
+    }
+}
+
+impl<T> DuplicatePolicy<Vec<T>> for KeepAll {
+    fn resolve(existing: &mut Vec<T>, mut new: Vec<T>) -> Result<Option<Vec<T>>, Vec<T>> {
+        existing.append(&mut new);
+        Ok(None)
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Insert `value` under `key`, letting `P` decide what happens if the key
+    /// is already present.
+    ///
+    /// A new key is always inserted and gives `Ok(None)`. For an existing
+    /// key the result is whatever [`P::resolve`](DuplicatePolicy::resolve)
+    /// returns. The stored key is kept either way, and like
+    /// [`merge_value`](Self::merge_value) the existing value is changed in
+    /// place without re-checking a [`ByteBudget`](crate::ByteBudget).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::{BPlusTreeMap, KeepAll, Overwrite, Reject};
+    ///
+    /// let mut tree = BPlusTreeMap::new(16).unwrap();
+    /// assert_eq!(tree.insert_with_policy::<Reject>(1, "one"), Ok(None));
+    /// assert_eq!(tree.insert_with_policy::<Reject>(1, "uno"), Err("uno"));
+    /// assert_eq!(tree.insert_with_policy::<Overwrite>(1, "uno"), Ok(Some("one")));
+    ///
+    /// let mut tags = BPlusTreeMap::new(16).unwrap();
+    /// tags.insert_with_policy::<KeepAll>("rust", vec![1]).unwrap();
+    /// tags.insert_with_policy::<KeepAll>("rust", vec![2, 3]).unwrap();
+    /// assert_eq!(tags.get(&"rust"), Some(&vec![1, 2, 3]));
+    /// ```
+    pub fn insert_with_policy<P: DuplicatePolicy<V>>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Result<Option<V>, V> {
+        let existing = self
+            .find_leaf_for_key_with_match(&key)
+            .filter(|&(_, _, matched)| matched)
+            .and_then(|(leaf_id, index, _)| self.get_leaf_mut(leaf_id)?.get_value_mut(index));
+        match existing {
+            Some(existing) => P::resolve(existing, value),
+            None => {
+                self.insert(key, value);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Insert `value` under `key` only if the key is absent, handing `value`
+    /// back otherwise. Shorthand for `insert_with_policy::<Reject>` that
+    /// discards the always-`None` displaced value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(16).unwrap();
+    /// assert_eq!(tree.insert_unique(7, "first"), Ok(()));
+    /// assert_eq!(tree.insert_unique(7, "second"), Err("second"));
+    /// assert_eq!(tree.get(&7), Some(&"first"));
+    /// ```
+    pub fn insert_unique(&mut self, key: K, value: V) -> Result<(), V> {
+        self.insert_with_policy::<Reject>(key, value).map(drop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use std::collections::btree_map::Entry;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_policies_match_reference_behaviour() {
+        let mut overwrite = BPlusTreeMap::new(4).unwrap();
+        let mut reject = BPlusTreeMap::new(4).unwrap();
+        let mut keep_all = BPlusTreeMap::new(4).unwrap();
+        let mut last: BTreeMap<u32, u32> = BTreeMap::new();
+        let mut first: BTreeMap<u32, u32> = BTreeMap::new();
+        let mut all: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+
+        let mut rng = SeededRng::new(1);
+        for i in 0..2_000 {
+            let key = rng.below(300) as u32;
+
+            assert_eq!(
+                overwrite.insert_with_policy::<Overwrite>(key, i),
+                Ok(last.insert(key, i))
+            );
+            let expected = match first.entry(key) {
+                Entry::Occupied(_) => Err(i),
+                Entry::Vacant(slot) => {
+                    slot.insert(i);
+                    Ok(None)
+                }
+            };
+            assert_eq!(reject.insert_with_policy::<Reject>(key, i), expected);
+            assert_eq!(
+                keep_all.insert_with_policy::<KeepAll>(key, vec![i]),
+                Ok(None)
+            );
+            all.entry(key).or_default().push(i);
+        }
+
+        assert!(overwrite.items().map(|(k, v)| (*k, *v)).eq(last));
+        assert!(reject.items().map(|(k, v)| (*k, *v)).eq(first));
+        assert!(keep_all.items().map(|(k, v)| (*k, v.clone())).eq(all));
+        assert!(keep_all.check_invariants());
+    }
+
+    #[test]
+    fn test_insert_unique_leaves_existing_value() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..50 {
+            assert_eq!(tree.insert_unique(i, i), Ok(()));
+        }
+        for i in 0..50 {
+            assert_eq!(tree.insert_unique(i, 0), Err(0));
+        }
+        assert!(tree.items().all(|(k, v)| k == v));
+        assert_eq!(tree.len(), 50);
+    }
+}
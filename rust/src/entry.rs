@@ -0,0 +1,237 @@
+//! The standard library's `Entry` API over a single descent.
+//!
+//! [`BPlusTreeMap::entry`] locates the key once, as
+//! [`locate`](BPlusTreeMap::locate) does, and sorts the result into an
+//! [`OccupiedEntry`] or a [`VacantEntry`]. Inserting through a vacant entry
+//! starts from the leaf the lookup found and splits up the recorded path, so
+//! "insert if absent, otherwise update" never walks from the root twice.
+//! Code written against `BTreeMap::entry` works unchanged.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::invariant::invariant;
+use crate::locate::Located;
+use crate::types::BPlusTreeMap;
+
+/// A view into one key of a tree, which is either present or absent.
+/// Returned by [`BPlusTreeMap::entry`].
+pub enum Entry<'a, K, V> {
+    /// The key is in the tree.
+    Occupied(OccupiedEntry<'a, K, V>),
+    /// The key is not in the tree.
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+/// An entry whose key is in the tree.
+pub struct OccupiedEntry<'a, K, V> {
+    located: Located<'a, K, V>,
+}
+
+/// An entry whose key is not in the tree, holding the position where it
+/// would be inserted.
+pub struct VacantEntry<'a, K, V> {
+    located: Located<'a, K, V>,
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// The entry for `key`, for reading or changing it in place.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut stock = BPlusTreeMap::new(4).unwrap();
+    /// for item in ["pear", "fig", "pear", "plum", "pear"] {
+    ///     stock.entry(item).and_modify(|n| *n += 1).or_insert(1);
+    /// }
+    /// assert_eq!(stock.get(&"pear"), Some(&3));
+    /// assert_eq!(stock.get(&"fig"), Some(&1));
+    /// ```
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        let located = self.locate(key);
+        if located.is_found() {
+            Entry::Occupied(OccupiedEntry { located })
+        } else {
+            Entry::Vacant(VacantEntry { located })
+        }
+    }
+}
+
+impl<'a, K: TreeKey, V: TreeValue> Entry<'a, K, V> {
+    /// The entry's key.
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// The value, inserting `value` first if the key is absent.
+    pub fn or_insert(self, value: V) -> &'a mut V {
+        self.or_insert_with(|| value)
+    }
+
+    /// The value, inserting the result of `default` first if the key is
+    /// absent. `default` is only called for a missing key.
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// The value, inserting the result of `default` on the key first if the
+    /// key is absent.
+    pub fn or_insert_with_key(self, default: impl FnOnce(&K) -> V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+
+    /// The value, inserting `V::default()` first if the key is absent.
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Apply `f` to the value if the key is present, and return the entry
+    /// for chaining.
+    pub fn and_modify(mut self, f: impl FnOnce(&mut V)) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, K: TreeKey, V: TreeValue> OccupiedEntry<'a, K, V> {
+    /// The entry's key.
+    pub fn key(&self) -> &K {
+        self.located.key()
+    }
+
+    /// The value stored under the key.
+    pub fn get(&self) -> &V {
+        invariant(self.located.get(), "occupied entry has a value")
+    }
+
+    /// Mutable access to the value, for as long as the entry lives.
+    pub fn get_mut(&mut self) -> &mut V {
+        invariant(self.located.get_mut(), "occupied entry has a value")
+    }
+
+    /// Mutable access to the value, for as long as the tree stays borrowed.
+    pub fn into_mut(self) -> &'a mut V {
+        invariant(self.located.into_mut(), "occupied entry has a value")
+    }
+
+    /// Replace the value, returning the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    /// Remove the entry from the tree, returning its value. Rebalances up
+    /// the path found by the lookup.
+    pub fn remove(self) -> V {
+        invariant(self.located.remove(), "occupied entry has a value")
+    }
+}
+
+impl<'a, K: TreeKey, V: TreeValue> VacantEntry<'a, K, V> {
+    /// The key that would be inserted.
+    pub fn key(&self) -> &K {
+        self.located.key()
+    }
+
+    /// Give back the key without inserting it.
+    pub fn into_key(self) -> K {
+        self.located.into_key()
+    }
+
+    /// Insert `value` under the key, starting from the leaf the lookup
+    /// found, and return a reference to it.
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.located.or_insert(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use std::collections::btree_map;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_entries_match_btreemap() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        let mut model = BTreeMap::new();
+        let mut rng = SeededRng::new(23);
+        for i in 0..5_000u32 {
+            let key = rng.below(800) as u32;
+            match i % 4 {
+                0 => {
+                    tree.entry(key).and_modify(|v| *v += 2).or_insert(i);
+                    model.entry(key).and_modify(|v| *v += 2).or_insert(i);
+                }
+                1 => {
+                    *tree.entry(key).or_insert_with_key(|k| k * 3) += 1;
+                    *model.entry(key).or_insert_with_key(|k| k * 3) += 1;
+                }
+                2 => match (tree.entry(key), model.entry(key)) {
+                    (Entry::Occupied(entry), btree_map::Entry::Occupied(expected)) => {
+                        assert_eq!(entry.key(), expected.key());
+                        assert_eq!(entry.remove(), expected.remove());
+                    }
+                    (Entry::Vacant(entry), btree_map::Entry::Vacant(expected)) => {
+                        assert_eq!(entry.into_key(), expected.into_key());
+                    }
+                    _ => panic!("entry for {} disagrees with BTreeMap", key),
+                },
+                _ => {
+                    if let Entry::Occupied(mut entry) = tree.entry(key) {
+                        assert_eq!(entry.insert(i), model.insert(key, i).unwrap());
+                        assert_eq!(*entry.get(), i);
+                    } else {
+                        assert_eq!(*tree.entry(key).or_default(), 0);
+                        model.insert(key, 0);
+                    }
+                }
+            }
+        }
+        tree.check_invariants_detailed().unwrap();
+        assert!(tree.items().eq(model.iter()));
+    }
+
+    #[test]
+    fn test_vacant_insert_returns_value_under_key_after_root_split() {
+        // The root leaf is full, so each insert splits it; the new key lands
+        // first, in the middle or last
+        for (new_key, position) in [(5, 0), (25, 2), (50, 4)] {
+            let mut tree = BPlusTreeMap::new(4).unwrap();
+            for key in [10, 20, 30, 40] {
+                tree.insert(key, key);
+            }
+            let Entry::Vacant(entry) = tree.entry(new_key) else {
+                panic!("{} is not in the tree", new_key);
+            };
+            let value = entry.insert(0);
+            assert_eq!(*value, 0);
+            *value = -1;
+
+            assert_eq!(tree.leaf_count(), 2);
+            assert_eq!(tree.get(&new_key), Some(&-1));
+            assert_eq!(tree.keys().position(|key| *key == new_key), Some(position));
+            for key in [10, 20, 30, 40] {
+                assert_eq!(tree.get(&key), Some(&key));
+            }
+            tree.check_invariants_detailed().unwrap();
+        }
+    }
+}
@@ -3,95 +3,350 @@
 //! This module provides comprehensive error handling for all B+ tree operations,
 //! including specialized error types and result type aliases for better ergonomics.
 
+use crate::builder::Backend;
+use crate::construction::DEFAULT_CAPACITY;
+
 /// Error type for B+ tree operations.
+///
+/// Errors fall into three categories, so that callers can match on the
+/// category without listing every case: [`Query`](Self::Query) errors are
+/// expected outcomes a caller can recover from, [`Capacity`](Self::Capacity)
+/// errors mean the tree could not be sized or given memory, and
+/// [`Corruption`](Self::Corruption) errors mean an invariant of the tree no
+/// longer holds.
+///
+/// # Breaking change in 0.10
+///
+/// The flat variants of earlier releases (`KeyNotFound`, `InvalidCapacity`,
+/// `CapacityTooSmall`, `DataIntegrityError`, `ArenaError`, `NodeError`,
+/// `CorruptedTree`, `InvalidState` and `AllocationError`) were removed. Match
+/// on the category instead, for example
+/// `BPlusTreeError::Query(QueryError::KeyNotFound)` for `KeyNotFound` and
+/// `BPlusTreeError::Capacity(CapacityError::TooSmall { .. })` for
+/// `CapacityTooSmall`.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::{BPlusTreeError, BPlusTreeMap, QueryError};
+///
+/// let tree = BPlusTreeMap::<i32, i32>::new(16).unwrap();
+/// match tree.get_item(&1) {
+///     Ok(value) => println!("found {}", value),
+///     Err(BPlusTreeError::Query(QueryError::KeyNotFound)) => println!("not there"),
+///     Err(BPlusTreeError::Corruption(e)) => panic!("tree is broken: {}", e),
+///     Err(other) => panic!("unexpected error: {}", other),
+/// }
+/// ```
 #[derive(Debug, Clone, PartialEq)]
 pub enum BPlusTreeError {
+    /// An expected outcome the caller can handle.
+    Query(QueryError),
+    /// The tree could not be sized or given memory.
+    Capacity(CapacityError),
+    /// An invariant of the tree does not hold.
+    Corruption(CorruptionError),
+}
+
+/// Expected outcomes of an operation, which a caller can recover from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
     /// Key not found in the tree.
     KeyNotFound,
+    /// The operation is not allowed in the tree's current state.
+    InvalidState(String),
+}
+
+/// Failures to size a tree or give it memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CapacityError {
+    /// Capacity below the smallest a node can work with.
+    TooSmall {
+        /// The capacity asked for.
+        capacity: usize,
+        /// The smallest capacity accepted.
+        minimum: usize,
+        /// A good general-purpose capacity to use instead.
+        suggested: usize,
+    },
     /// Invalid capacity specified.
-    InvalidCapacity(String),
+    Invalid(String),
+    /// Memory allocation failed.
+    Allocation(String),
+}
+
+/// Violations of the tree's invariants. Retrying will not help; the tree
+/// should be rebuilt or the bug reported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorruptionError {
     /// Internal data structure integrity violation.
-    DataIntegrityError(String),
+    DataIntegrity(String),
     /// Arena operation failed.
-    ArenaError(String),
+    Arena(String),
     /// Node operation failed.
-    NodeError(String),
+    Node(String),
     /// Tree corruption detected.
-    CorruptedTree(String),
-    /// Invalid tree state.
-    InvalidState(String),
-    /// Memory allocation failed.
-    AllocationError(String),
+    Tree(String),
+}
+
+impl From<QueryError> for BPlusTreeError {
+    fn from(error: QueryError) -> Self {
+        Self::Query(error)
+    }
+}
+
+impl From<CapacityError> for BPlusTreeError {
+    fn from(error: CapacityError) -> Self {
+        Self::Capacity(error)
+    }
+}
+
+impl From<CorruptionError> for BPlusTreeError {
+    fn from(error: CorruptionError) -> Self {
+        Self::Corruption(error)
+    }
 }
 
 impl BPlusTreeError {
-    /// Create an InvalidCapacity error with context
+    /// Create a capacity error for a capacity below the minimum, suggesting
+    /// the default capacity
     pub fn invalid_capacity(capacity: usize, min_required: usize) -> Self {
-        Self::InvalidCapacity(format!(
-            "Capacity {} is invalid (minimum required: {})",
-            capacity, min_required
-        ))
+        CapacityError::TooSmall {
+            capacity,
+            minimum: min_required,
+            suggested: DEFAULT_CAPACITY.max(min_required),
+        }
+        .into()
     }
 
-    /// Create a DataIntegrityError with context
+    /// Create a data integrity error with context
     pub fn data_integrity(context: &str, details: &str) -> Self {
-        Self::DataIntegrityError(format!("{}: {}", context, details))
+        CorruptionError::DataIntegrity(format!("{}: {}", context, details)).into()
     }
 
-    /// Create an ArenaError with context
+    /// Create an arena error with context
     pub fn arena_error(operation: &str, details: &str) -> Self {
-        Self::ArenaError(format!("{} failed: {}", operation, details))
+        CorruptionError::Arena(format!("{} failed: {}", operation, details)).into()
     }
 
-    /// Create a NodeError with context
+    /// Create a node error with context
     pub fn node_error(node_type: &str, node_id: u32, details: &str) -> Self {
-        Self::NodeError(format!("{} node {}: {}", node_type, node_id, details))
+        CorruptionError::Node(format!("{} node {}: {}", node_type, node_id, details)).into()
     }
 
-    /// Create a CorruptedTree error with context
+    /// Create a corrupted tree error with context
     pub fn corrupted_tree(component: &str, details: &str) -> Self {
-        Self::CorruptedTree(format!("{} corruption: {}", component, details))
+        CorruptionError::Tree(format!("{} corruption: {}", component, details)).into()
     }
 
-    /// Create an InvalidState error with context
+    /// Create an invalid state error with context
     pub fn invalid_state(operation: &str, state: &str) -> Self {
-        Self::InvalidState(format!("Cannot {} in state: {}", operation, state))
+        QueryError::InvalidState(format!("Cannot {} in state: {}", operation, state)).into()
     }
 
-    /// Create an AllocationError with context
+    /// Create an allocation error with context
     pub fn allocation_error(resource: &str, reason: &str) -> Self {
-        Self::AllocationError(format!("Failed to allocate {}: {}", resource, reason))
+        CapacityError::Allocation(format!("Failed to allocate {}: {}", resource, reason)).into()
     }
 
     /// Check if this error is a capacity error
     pub fn is_capacity_error(&self) -> bool {
-        matches!(self, Self::InvalidCapacity(_))
+        matches!(
+            self,
+            Self::Capacity(CapacityError::TooSmall { .. } | CapacityError::Invalid(_))
+        )
     }
 
     /// Check if this error is an arena error
     pub fn is_arena_error(&self) -> bool {
-        matches!(self, Self::ArenaError(_))
+        matches!(self, Self::Corruption(CorruptionError::Arena(_)))
+    }
+
+    /// Check if this error means the tree's invariants no longer hold
+    pub fn is_corruption(&self) -> bool {
+        matches!(self, Self::Corruption(_))
     }
 }
 
 impl std::fmt::Display for BPlusTreeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            BPlusTreeError::KeyNotFound => write!(f, "Key not found in tree"),
-            BPlusTreeError::InvalidCapacity(msg) => write!(f, "Invalid capacity: {}", msg),
-            BPlusTreeError::DataIntegrityError(msg) => write!(f, "Data integrity error: {}", msg),
-            BPlusTreeError::ArenaError(msg) => write!(f, "Arena error: {}", msg),
-            BPlusTreeError::NodeError(msg) => write!(f, "Node error: {}", msg),
-            BPlusTreeError::CorruptedTree(msg) => write!(f, "Corrupted tree: {}", msg),
-            BPlusTreeError::InvalidState(msg) => write!(f, "Invalid state: {}", msg),
-            BPlusTreeError::AllocationError(msg) => write!(f, "Allocation error: {}", msg),
+            BPlusTreeError::Query(e) => write!(f, "{}", e),
+            BPlusTreeError::Capacity(e) => write!(f, "{}", e),
+            BPlusTreeError::Corruption(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::fmt::Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryError::KeyNotFound => write!(f, "Key not found in tree"),
+            QueryError::InvalidState(msg) => write!(f, "Invalid state: {}", msg),
+        }
+    }
+}
+
+impl std::fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CapacityError::TooSmall {
+                capacity,
+                minimum,
+                suggested,
+            } => write!(
+                f,
+                "Invalid capacity: Capacity {} is invalid (minimum required: {}, suggested: {})",
+                capacity, minimum, suggested
+            ),
+            CapacityError::Invalid(msg) => write!(f, "Invalid capacity: {}", msg),
+            CapacityError::Allocation(msg) => write!(f, "Allocation error: {}", msg),
         }
     }
 }
 
-impl std::error::Error for BPlusTreeError {}
+impl std::fmt::Display for CorruptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CorruptionError::DataIntegrity(msg) => write!(f, "Data integrity error: {}", msg),
+            CorruptionError::Arena(msg) => write!(f, "Arena error: {}", msg),
+            CorruptionError::Node(msg) => write!(f, "Node error: {}", msg),
+            CorruptionError::Tree(msg) => write!(f, "Corrupted tree: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for BPlusTreeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BPlusTreeError::Query(e) => Some(e),
+            BPlusTreeError::Capacity(e) => Some(e),
+            BPlusTreeError::Corruption(e) => Some(e),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+impl std::error::Error for CapacityError {}
+
+impl std::error::Error for CorruptionError {}
+
+/// Why [`compare_and_update`](crate::BPlusTreeMap::compare_and_update) did
+/// not write. Both variants hand the rejected new value back.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CasError<V> {
+    /// The key is not in the tree.
+    KeyNotFound {
+        /// The value that was not written.
+        new: V,
+    },
+    /// The stored value is not the expected one.
+    Mismatch {
+        /// The value currently stored, for a retry.
+        current: V,
+        /// The value that was not written.
+        new: V,
+    },
+}
+
+impl<V> CasError<V> {
+    /// Take back the value that was not written.
+    pub fn into_new(self) -> V {
+        match self {
+            CasError::KeyNotFound { new } | CasError::Mismatch { new, .. } => new,
+        }
+    }
+}
+
+impl<V> std::fmt::Display for CasError<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CasError::KeyNotFound { .. } => write!(f, "Key not found in tree"),
+            CasError::Mismatch { .. } => write!(f, "Stored value does not match the expected one"),
+        }
+    }
+}
+
+impl<V: std::fmt::Debug> std::error::Error for CasError<V> {}
+
+/// Why a [`BPlusTreeBuilder`](crate::BPlusTreeBuilder) could not build.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError {
+    /// The node capacity was rejected; the inner error says why.
+    InvalidCapacity(BPlusTreeError),
+    /// The fill factor is outside the occupancy a leaf can have.
+    FillFactorOutOfRange {
+        /// The fill factor asked for.
+        fill_factor: f64,
+        /// The smallest fill factor accepted.
+        minimum: f64,
+        /// The largest fill factor accepted.
+        maximum: f64,
+    },
+    /// Two settings that cannot be used together.
+    Conflict {
+        /// The setting that was rejected.
+        setting: &'static str,
+        /// The setting it conflicts with.
+        with: &'static str,
+        /// Why the two do not combine.
+        reason: &'static str,
+    },
+    /// The builder was configured for one backend and asked to build another.
+    BackendMismatch {
+        /// The backend set with `backend`.
+        configured: Backend,
+        /// The backend the build method produces.
+        requested: Backend,
+    },
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::InvalidCapacity(e) => write!(f, "{}", e),
+            BuildError::FillFactorOutOfRange {
+                fill_factor,
+                minimum,
+                maximum,
+            } => write!(
+                f,
+                "Fill factor {} is outside {}..={}",
+                fill_factor, minimum, maximum
+            ),
+            BuildError::Conflict {
+                setting,
+                with,
+                reason,
+            } => write!(
+                f,
+                "`{}` cannot be combined with `{}`: {}",
+                setting, with, reason
+            ),
+            BuildError::BackendMismatch {
+                configured,
+                requested,
+            } => write!(
+                f,
+                "Builder is configured for the {:?} backend but was asked to build {:?}",
+                configured, requested
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BuildError::InvalidCapacity(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 /// Internal result type for tree operations
+#[cfg(any(test, feature = "validation"))]
 pub(crate) type TreeResult<T> = Result<T, BPlusTreeError>;
 
 /// Public result type for tree operations that may fail
@@ -123,20 +378,28 @@ pub trait BTreeResultExt<T> {
 impl<T> BTreeResultExt<T> for Result<T, BPlusTreeError> {
     fn with_context(self, context: &str) -> BTreeResult<T> {
         self.map_err(|e| match e {
-            BPlusTreeError::KeyNotFound => BPlusTreeError::KeyNotFound,
-            BPlusTreeError::InvalidCapacity(msg) => {
-                BPlusTreeError::InvalidCapacity(format!("{}: {}", context, msg))
+            BPlusTreeError::Capacity(CapacityError::Invalid(msg)) => {
+                CapacityError::Invalid(format!("{}: {}", context, msg)).into()
+            }
+            BPlusTreeError::Capacity(CapacityError::Allocation(msg)) => {
+                BPlusTreeError::allocation_error(context, &msg)
+            }
+            BPlusTreeError::Query(QueryError::InvalidState(msg)) => {
+                BPlusTreeError::invalid_state(context, &msg)
             }
-            BPlusTreeError::DataIntegrityError(msg) => {
+            BPlusTreeError::Corruption(CorruptionError::DataIntegrity(msg)) => {
                 BPlusTreeError::data_integrity(context, &msg)
             }
-            BPlusTreeError::ArenaError(msg) => BPlusTreeError::arena_error(context, &msg),
-            BPlusTreeError::NodeError(msg) => {
-                BPlusTreeError::NodeError(format!("{}: {}", context, msg))
+            BPlusTreeError::Corruption(CorruptionError::Arena(msg)) => {
+                BPlusTreeError::arena_error(context, &msg)
+            }
+            BPlusTreeError::Corruption(CorruptionError::Node(msg)) => {
+                CorruptionError::Node(format!("{}: {}", context, msg)).into()
+            }
+            BPlusTreeError::Corruption(CorruptionError::Tree(msg)) => {
+                BPlusTreeError::corrupted_tree(context, &msg)
             }
-            BPlusTreeError::CorruptedTree(msg) => BPlusTreeError::corrupted_tree(context, &msg),
-            BPlusTreeError::InvalidState(msg) => BPlusTreeError::invalid_state(context, &msg),
-            BPlusTreeError::AllocationError(msg) => BPlusTreeError::allocation_error(context, &msg),
+            unchanged => unchanged,
         })
     }
 
@@ -0,0 +1,180 @@
+//! Trimming a tree down to a quota.
+//!
+//! A bounded cache keyed by something ordered, such as an insertion
+//! timestamp, sheds entries from one end when it grows past its limit.
+//! Popping them one at a time rebalances after every removal; these methods
+//! work out how many entries have to go first and then remove them with one
+//! bulk pop, which rebalances once.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::types::BPlusTreeMap;
+
+/// Which end of the key order eviction removes entries from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictFrom {
+    /// Remove the smallest keys first, such as the oldest timestamps.
+    #[default]
+    Head,
+    /// Remove the largest keys first.
+    Tail,
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Remove entries from the `from` end until at most `max_len` remain.
+    ///
+    /// The evicted entries are returned in the order they were removed:
+    /// ascending from the head, descending from the tail.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::{BPlusTreeMap, EvictFrom};
+    ///
+    /// let mut cache = BPlusTreeMap::new(16).unwrap();
+    /// for timestamp in 0..100 {
+    ///     cache.insert(timestamp, "event");
+    /// }
+    ///
+    /// let evicted = cache.evict_to_len(90, EvictFrom::Head);
+    /// assert_eq!(evicted.len(), 10);
+    /// assert_eq!(cache.first(), Some((&10, &"event")));
+    /// assert!(cache.evict_to_len(90, EvictFrom::Head).is_empty());
+    /// ```
+    pub fn evict_to_len(&mut self, max_len: usize, from: EvictFrom) -> Vec<(K, V)> {
+        let excess = self.len().saturating_sub(max_len);
+        self.evict(excess, from)
+    }
+
+    /// Remove entries from the `from` end until the entries left weigh at
+    /// most `max_bytes` by `size_of`, which gives each entry's size.
+    ///
+    /// Sizes are summed over the whole tree once, so `size_of` should be
+    /// cheap; the removal itself is a single bulk pop. Entries come back in
+    /// the same order as from [`evict_to_len`](Self::evict_to_len).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::{BPlusTreeMap, EvictFrom};
+    ///
+    /// let mut cache = BPlusTreeMap::new(16).unwrap();
+    /// for timestamp in 0..10 {
+    ///     cache.insert(timestamp, "x".repeat(100));
+    /// }
+    ///
+    /// let evicted = cache.evict_to_bytes(550, EvictFrom::Tail, |_, body| body.len());
+    /// assert_eq!(evicted.len(), 5);
+    /// assert_eq!(evicted[0].0, 9);
+    /// assert_eq!(cache.last().map(|(k, _)| *k), Some(4));
+    /// ```
+    pub fn evict_to_bytes<F>(
+        &mut self,
+        max_bytes: usize,
+        from: EvictFrom,
+        size_of: F,
+    ) -> Vec<(K, V)>
+    where
+        F: Fn(&K, &V) -> usize,
+    {
+        let sizes: Vec<usize> = self
+            .items()
+            .map(|(key, value)| size_of(key, value))
+            .collect();
+        let total: usize = sizes.iter().sum();
+        if total <= max_bytes {
+            return Vec::new();
+        }
+
+        let excess = match from {
+            // The shortest prefix whose removal brings the rest under quota
+            EvictFrom::Head => {
+                let mut remaining = total;
+                sizes
+                    .iter()
+                    .take_while(|&&size| {
+                        let over = remaining > max_bytes;
+                        remaining -= size;
+                        over
+                    })
+                    .count()
+            }
+            // Everything after the longest prefix that fits
+            EvictFrom::Tail => {
+                let mut kept = 0;
+                let fitting = sizes
+                    .iter()
+                    .take_while(|&&size| {
+                        kept += size;
+                        kept <= max_bytes
+                    })
+                    .count();
+                sizes.len() - fitting
+            }
+        };
+        self.evict(excess, from)
+    }
+
+    fn evict(&mut self, count: usize, from: EvictFrom) -> Vec<(K, V)> {
+        match from {
+            EvictFrom::Head => self.remove_min_k(count),
+            EvictFrom::Tail => self.remove_max_k(count),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn tree_and_model(count: u32) -> (BPlusTreeMap<u32, u32>, BTreeMap<u32, u32>) {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        let mut model = BTreeMap::new();
+        for i in 0..count {
+            tree.insert(i, i % 7);
+            model.insert(i, i % 7);
+        }
+        (tree, model)
+    }
+
+    #[test]
+    fn test_evict_to_len_from_either_end() {
+        let (mut tree, mut model) = tree_and_model(1_000);
+
+        let evicted = tree.evict_to_len(700, EvictFrom::Head);
+        let expected: Vec<_> = (0..300)
+            .map(|i| model.remove(&i).map(|v| (i, v)).unwrap())
+            .collect();
+        assert_eq!(evicted, expected);
+
+        let evicted = tree.evict_to_len(250, EvictFrom::Tail);
+        let expected: Vec<_> = (0..450).map(|_| model.pop_last().unwrap()).collect();
+        assert_eq!(evicted, expected);
+
+        assert!(tree.items().map(|(k, v)| (*k, *v)).eq(model.into_iter()));
+        assert!(tree.check_invariants());
+        assert!(tree.evict_to_len(500, EvictFrom::Tail).is_empty());
+        assert_eq!(tree.evict_to_len(0, EvictFrom::Head).len(), 250);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn test_evict_to_bytes_leaves_tightest_fit() {
+        for max_bytes in [0, 1, 500, 1_234, 2_999, 3_000, 10_000] {
+            for from in [EvictFrom::Head, EvictFrom::Tail] {
+                let (mut tree, _) = tree_and_model(1_000);
+                let size_of = |_: &u32, value: &u32| *value as usize;
+                let evicted = tree.evict_to_bytes(max_bytes, from, size_of);
+
+                let kept: usize = tree.items().map(|(k, v)| size_of(k, v)).sum();
+                assert!(kept <= max_bytes);
+                // Evicting one entry fewer would have been over quota
+                if let Some((key, value)) = evicted.last() {
+                    assert!(kept + size_of(key, value) > max_bytes);
+                }
+                assert_eq!(tree.len() + evicted.len(), 1_000);
+                assert!(tree.check_invariants());
+            }
+        }
+    }
+}
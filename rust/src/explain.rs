@@ -0,0 +1,339 @@
+//! Query explanations for performance debugging.
+//!
+//! [`BPlusTreeMap::explain_get`] and [`BPlusTreeMap::explain_range`] answer a
+//! query the same way `get` and `range` do, but record what the answer cost:
+//! the nodes visited on the way down, how many keys each search compared, and
+//! how many leaves a range scan walked. Like a database `EXPLAIN`, the result
+//! prints as a readable plan.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::types::{BPlusTreeMap, NodeId, NodeRef, NULL_NODE};
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+/// One node visited while descending from the root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplainStep {
+    /// Arena id of the node.
+    pub node: NodeId,
+    /// Whether the node is a leaf.
+    pub is_leaf: bool,
+    /// Children of a branch, or entries of a leaf.
+    pub fanout: usize,
+    /// Key comparisons made by the binary search in this node.
+    pub comparisons: usize,
+    /// Child taken from a branch, or position reached in a leaf.
+    pub index: usize,
+}
+
+/// How a query was answered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryExplain {
+    /// Nodes visited from the root down to the first leaf.
+    pub descent: Vec<ExplainStep>,
+    /// Leaves read, in order, starting with the one the descent reached.
+    pub leaves_touched: Vec<NodeId>,
+    /// Key comparisons made while scanning leaves for the end of a range.
+    pub scan_comparisons: usize,
+    /// Entries the query returns: 0 or 1 for a lookup.
+    pub entries: usize,
+}
+
+impl QueryExplain {
+    /// Number of levels descended, including the leaf.
+    pub fn depth(&self) -> usize {
+        self.descent.len()
+    }
+
+    /// Key comparisons made in total.
+    pub fn comparisons(&self) -> usize {
+        self.descent
+            .iter()
+            .map(|step| step.comparisons)
+            .sum::<usize>()
+            + self.scan_comparisons
+    }
+}
+
+impl fmt::Display for QueryExplain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (level, step) in self.descent.iter().enumerate() {
+            let (kind, fanout, taken) = if step.is_leaf {
+                ("Leaf", "entries", "position")
+            } else {
+                ("Branch", "children", "child")
+            };
+            writeln!(
+                f,
+                "{:indent$}{}[id={}] {} {}, {} comparisons -> {} {}",
+                "",
+                kind,
+                step.node,
+                step.fanout,
+                fanout,
+                step.comparisons,
+                taken,
+                step.index,
+                indent = level * 2
+            )?;
+        }
+        write!(
+            f,
+            "leaves touched: {}, entries: {}, comparisons: {}",
+            self.leaves_touched.len(),
+            self.entries,
+            self.comparisons()
+        )
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Look up `key` as [`get`](Self::get) does and report the path taken.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(16).unwrap();
+    /// for i in 0..1_000 {
+    ///     tree.insert(i, i);
+    /// }
+    ///
+    /// let plan = tree.explain_get(&500);
+    /// assert_eq!(plan.entries, 1);
+    /// assert_eq!(plan.depth(), 3);
+    /// assert_eq!(plan.leaves_touched.len(), 1);
+    /// println!("{}", plan);
+    /// ```
+    pub fn explain_get(&self, key: &K) -> QueryExplain {
+        let descent = self.explain_descent(Some(key));
+        let leaf = descent.last().filter(|step| step.is_leaf);
+        let found = leaf
+            .and_then(|step| Some((self.get_leaf(step.node)?, step.index)))
+            .and_then(|(leaf, index)| leaf.keys.get(index))
+            .is_some_and(|found| found == key);
+
+        QueryExplain {
+            leaves_touched: leaf.map(|step| step.node).into_iter().collect(),
+            descent,
+            scan_comparisons: 0,
+            entries: usize::from(found),
+        }
+    }
+
+    /// Run `range` as [`range`](Self::range) does and report the descent to
+    /// its start and the leaves scanned to reach its end.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(16).unwrap();
+    /// for i in 0..1_000 {
+    ///     tree.insert(i, i);
+    /// }
+    ///
+    /// let plan = tree.explain_range(100..400);
+    /// assert_eq!(plan.entries, 300);
+    /// assert!(plan.leaves_touched.len() >= 300 / 16);
+    /// ```
+    pub fn explain_range<R: RangeBounds<K>>(&self, range: R) -> QueryExplain {
+        let (start_key, excluded) = match range.start_bound() {
+            Bound::Included(key) => (Some(key), false),
+            Bound::Excluded(key) => (Some(key), true),
+            Bound::Unbounded => (None, false),
+        };
+        let descent = self.explain_descent(start_key);
+        let mut explain = QueryExplain {
+            descent,
+            leaves_touched: Vec::new(),
+            scan_comparisons: 0,
+            entries: 0,
+        };
+
+        let (mut leaf_id, mut index) = match explain.descent.last() {
+            Some(step) if step.is_leaf => (step.node, step.index),
+            _ => return explain,
+        };
+        if let (Some(key), true) = (start_key, excluded) {
+            (leaf_id, index) = self.position_after_key((leaf_id, index), key);
+        }
+
+        // Walk the leaf chain as the range iterator does: one comparison
+        // against the leaf's last key, then per key only in the leaf where
+        // the range ends
+        let end = range.end_bound();
+        let within = |key: &K| match end {
+            Bound::Included(end) => key <= end,
+            Bound::Excluded(end) => key < end,
+            Bound::Unbounded => true,
+        };
+        let bounded = !matches!(end, Bound::Unbounded);
+        while let Some(leaf) = self.get_leaf(leaf_id) {
+            explain.leaves_touched.push(leaf_id);
+            let keys = &leaf.keys[index.min(leaf.keys.len())..];
+            if let Some(last) = leaf.keys.last() {
+                explain.scan_comparisons += usize::from(bounded);
+                if !within(last) {
+                    let inside = keys.iter().take_while(|key| within(key)).count();
+                    explain.scan_comparisons += inside + usize::from(inside < keys.len());
+                    explain.entries += inside;
+                    return explain;
+                }
+            }
+            explain.entries += keys.len();
+            if leaf.next == NULL_NODE {
+                break;
+            }
+            leaf_id = leaf.next;
+            index = 0;
+        }
+        explain
+    }
+
+    /// Descend towards `key`, or to the leftmost leaf when `key` is `None`,
+    /// following the same child choices as `find_leaf_for_key`.
+    fn explain_descent(&self, key: Option<&K>) -> Vec<ExplainStep> {
+        let mut steps = Vec::new();
+        let mut current = self.root;
+        loop {
+            match current {
+                NodeRef::Branch(id, _) => {
+                    let Some(branch) = self.get_branch(id) else {
+                        return steps;
+                    };
+                    let (comparisons, index) = match key {
+                        Some(key) => match counted_search(&branch.keys, key) {
+                            (comparisons, Ok(index)) => (comparisons, index + 1),
+                            (comparisons, Err(index)) => (comparisons, index),
+                        },
+                        None => (0, 0),
+                    };
+                    steps.push(ExplainStep {
+                        node: id,
+                        is_leaf: false,
+                        fanout: branch.child_count(),
+                        comparisons,
+                        index,
+                    });
+                    match branch.child(index) {
+                        Some(child) => current = child,
+                        None => return steps,
+                    }
+                }
+                NodeRef::Leaf(id, _) => {
+                    let Some(leaf) = self.get_leaf(id) else {
+                        return steps;
+                    };
+                    let (comparisons, index) = match key {
+                        Some(key) => {
+                            let (comparisons, result) = counted_search(&leaf.keys, key);
+                            (comparisons, result.unwrap_or_else(|index| index))
+                        }
+                        None => (0, 0),
+                    };
+                    steps.push(ExplainStep {
+                        node: id,
+                        is_leaf: true,
+                        fanout: leaf.keys.len(),
+                        comparisons,
+                        index,
+                    });
+                    return steps;
+                }
+            }
+        }
+    }
+}
+
+/// Binary search `keys` for `key` exactly as `slice::binary_search` does,
+/// counting the comparisons made.
+fn counted_search<K: Ord>(keys: &[K], key: &K) -> (usize, Result<usize, usize>) {
+    let mut comparisons = 0;
+    let result = keys.binary_search_by(|probe| -> Ordering {
+        comparisons += 1;
+        probe.cmp(key)
+    });
+    (comparisons, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_get_follows_the_lookup_path() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..500 {
+            tree.insert(i * 2, i);
+        }
+
+        for key in [0, 1, 250, 499, 998, 1_000] {
+            let plan = tree.explain_get(&key);
+            assert_eq!(plan.entries, usize::from(tree.contains_key(&key)));
+            assert_eq!(plan.depth(), tree.explain_get(&0).depth());
+            assert!(plan
+                .descent
+                .iter()
+                .take(plan.depth() - 1)
+                .all(|step| !step.is_leaf));
+
+            // The descent ends at the leaf a plain lookup reaches
+            let (leaf_id, index) = tree.find_leaf_for_key(&key).unwrap();
+            let last = plan.descent.last().unwrap();
+            assert!(last.is_leaf);
+            assert_eq!((last.node, last.index), (leaf_id, index));
+            assert_eq!(plan.leaves_touched, vec![leaf_id]);
+
+            // Each binary search takes about log2(fanout) comparisons
+            for step in &plan.descent {
+                let keys = if step.is_leaf {
+                    step.fanout
+                } else {
+                    step.fanout - 1
+                };
+                assert!(
+                    step.comparisons <= (keys + 1).next_power_of_two().trailing_zeros() as usize
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_explain_range_counts_entries_and_leaves() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..300 {
+            tree.insert(i, i);
+        }
+
+        let bounds = [
+            (Bound::Included(10), Bound::Excluded(200)),
+            (Bound::Excluded(10), Bound::Included(200)),
+            (Bound::Unbounded, Bound::Included(5)),
+            (Bound::Included(290), Bound::Unbounded),
+            (Bound::Included(400), Bound::Unbounded),
+        ];
+        for range in bounds {
+            let plan = tree.explain_range(range);
+            assert_eq!(plan.entries, tree.range(range).count(), "{:?}", range);
+
+            // Every leaf holding a returned entry was touched
+            let first = tree.range(range).next().map(|(k, _)| *k);
+            if let Some(first) = first {
+                let (first_leaf, _) = tree.find_leaf_for_key(&first).unwrap();
+                assert!(plan.leaves_touched.contains(&first_leaf));
+            }
+        }
+
+        let plan = tree.explain_range(..);
+        assert_eq!(plan.leaves_touched.len(), tree.leaf_count());
+        assert_eq!(plan.comparisons(), 0);
+
+        let text = plan.to_string();
+        assert!(text.starts_with("Branch[id="));
+        assert!(text.ends_with("entries: 300, comparisons: 0"));
+    }
+}
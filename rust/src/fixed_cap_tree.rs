@@ -0,0 +1,809 @@
+//! A B+ tree whose node capacity is a compile-time constant.
+//!
+//! [`BPlusTreeMap`](crate::BPlusTreeMap) chooses its capacity at runtime, so
+//! every node keeps its keys, values and children in separately allocated
+//! `Vec`s. [`FixedCapTree`] takes the capacity as a const generic instead:
+//!
+//! - nodes store keys, values and children inline in `[MaybeUninit<T>; CAP]`
+//!   arrays, so creating a node is one push onto the node arena and reading
+//!   one touches a single allocation;
+//! - searches run over slices whose maximum length is known at compile time,
+//!   which lets the compiler specialise and unroll them for small `CAP`.
+//!
+//! As in [`U64Tree`](crate::U64Tree), leaves are never freed and removal does
+//! not rebalance; call [`FixedCapTree::compact`] after heavy deletion.
+//!
+//! [`BPlusTreeMap::into_fixed_capacity`] and [`FixedCapTree::into_tree`] move
+//! existing data between the two by handing over whole leaves, so switching
+//! representation does not insert the entries one by one.
+
+use crate::batch_operations::even_chunk_sizes;
+use crate::bounds::{TreeKey, TreeValue};
+use crate::error::{BPlusTreeError, BTreeResult};
+use crate::invariant::invariant;
+use crate::types::{BPlusTreeMap, BranchNode, LeafNode, NodeRef, NodeVec, INLINE_ROOT, NULL_NODE};
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ops::{Bound, RangeBounds};
+use std::ptr;
+
+/// Marks the end of a leaf chain.
+const NO_LEAF: u32 = u32::MAX;
+
+/// Up to `CAP` items stored inline.
+///
+/// The first `len` slots are initialised; the rest are not. This is the only
+/// place in the module that touches uninitialised memory.
+struct InlineVec<T, const CAP: usize> {
+    len: usize,
+    items: [MaybeUninit<T>; CAP],
+}
+
+impl<T, const CAP: usize> InlineVec<T, CAP> {
+    fn new() -> Self {
+        Self {
+            len: 0,
+            items: [const { MaybeUninit::uninit() }; CAP],
+        }
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    fn is_full(&self) -> bool {
+        self.len == CAP
+    }
+
+    #[inline]
+    fn as_slice(&self) -> &[T] {
+        // SAFETY: the first `len` slots are initialised
+        unsafe { std::slice::from_raw_parts(self.items.as_ptr().cast::<T>(), self.len) }
+    }
+
+    #[inline]
+    fn as_mut_slice(&mut self) -> &mut [T] {
+        // SAFETY: the first `len` slots are initialised
+        unsafe { std::slice::from_raw_parts_mut(self.items.as_mut_ptr().cast::<T>(), self.len) }
+    }
+
+    /// Insert `item` at `index`, shifting later items right.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector is full or `index > len`.
+    #[inline]
+    fn insert(&mut self, index: usize, item: T) {
+        assert!(self.len < CAP && index <= self.len);
+        // SAFETY: slots `index..len` are initialised and slot `len` exists, so
+        // shifting them right by one stays in bounds; slot `index` is then
+        // logically uninitialised and is overwritten without dropping
+        unsafe {
+            let slot = self.items.as_mut_ptr().add(index).cast::<T>();
+            ptr::copy(slot, slot.add(1), self.len - index);
+            slot.write(item);
+        }
+        self.len += 1;
+    }
+
+    #[inline]
+    fn push(&mut self, item: T) {
+        self.insert(self.len, item);
+    }
+
+    /// Remove and return the item at `index`, shifting later items left.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= len`.
+    #[inline]
+    fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.len);
+        // SAFETY: slot `index` is initialised and is read out exactly once;
+        // the initialised slots after it are shifted over it
+        let item = unsafe {
+            let slot = self.items.as_mut_ptr().add(index).cast::<T>();
+            let item = slot.read();
+            ptr::copy(slot.add(1), slot, self.len - index - 1);
+            item
+        };
+        self.len -= 1;
+        item
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            Some(self.remove(self.len - 1))
+        }
+    }
+
+    /// Move the items from `at` onwards into a new vector.
+    fn split_off(&mut self, at: usize) -> Self {
+        assert!(at <= self.len);
+        let mut right = Self::new();
+        // SAFETY: slots `at..len` are initialised and move to the start of
+        // `right`; shrinking `self.len` stops them being dropped twice
+        unsafe {
+            ptr::copy_nonoverlapping(
+                self.items.as_ptr().add(at),
+                right.items.as_mut_ptr(),
+                self.len - at,
+            );
+        }
+        right.len = self.len - at;
+        self.len = at;
+        right
+    }
+
+    fn into_vec(mut self) -> Vec<T> {
+        let mut items = Vec::with_capacity(self.len);
+        while let Some(item) = self.pop() {
+            items.push(item);
+        }
+        items.reverse();
+        items
+    }
+}
+
+impl<T, const CAP: usize> Drop for InlineVec<T, CAP> {
+    fn drop(&mut self) {
+        // SAFETY: drops exactly the initialised slots
+        unsafe { ptr::drop_in_place(self.as_mut_slice()) }
+    }
+}
+
+impl<T: Clone, const CAP: usize> Clone for InlineVec<T, CAP> {
+    fn clone(&self) -> Self {
+        let mut copy = Self::new();
+        for item in self.as_slice() {
+            copy.push(item.clone());
+        }
+        copy
+    }
+}
+
+impl<T: fmt::Debug, const CAP: usize> fmt::Debug for InlineVec<T, CAP> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Leaf<K, V, const CAP: usize> {
+    keys: InlineVec<K, CAP>,
+    values: InlineVec<V, CAP>,
+    next: u32,
+}
+
+impl<K, V, const CAP: usize> Leaf<K, V, CAP> {
+    fn new() -> Self {
+        Self {
+            keys: InlineVec::new(),
+            values: InlineVec::new(),
+            next: NO_LEAF,
+        }
+    }
+}
+
+/// A branch holds up to `CAP` children and so up to `CAP - 1` keys.
+#[derive(Debug, Clone)]
+struct Branch<K, const CAP: usize> {
+    keys: InlineVec<K, CAP>,
+    children: InlineVec<u32, CAP>,
+}
+
+/// An ordered map whose nodes hold up to `CAP` entries inline.
+///
+/// `CAP` must be at least 4; smaller values fail to compile.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::FixedCapTree;
+///
+/// let mut tree: FixedCapTree<String, usize, 32> = FixedCapTree::new();
+/// for word in ["pear", "apple", "fig", "plum"] {
+///     tree.insert(word.to_string(), word.len());
+/// }
+/// assert_eq!(tree.get(&"fig".to_string()), Some(&3));
+/// assert_eq!(
+///     tree.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>(),
+///     ["apple", "fig", "pear", "plum"]
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct FixedCapTree<K, V, const CAP: usize> {
+    leaves: Vec<Leaf<K, V, CAP>>,
+    branches: Vec<Branch<K, CAP>>,
+    root: u32,
+    /// Number of branch levels above the leaves.
+    height: usize,
+    len: usize,
+}
+
+impl<K: Ord + Clone, V, const CAP: usize> Default for FixedCapTree<K, V, CAP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Clone, V, const CAP: usize> FixedCapTree<K, V, CAP> {
+    const VALID_CAPACITY: () = assert!(CAP >= 4, "FixedCapTree needs CAP >= 4");
+
+    /// Create an empty tree.
+    pub fn new() -> Self {
+        let () = Self::VALID_CAPACITY;
+        Self {
+            leaves: vec![Leaf::new()],
+            branches: Vec::new(),
+            root: 0,
+            height: 0,
+            len: 0,
+        }
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the tree holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Remove every entry and release all nodes but one.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Get the value stored under `key`.
+    #[inline]
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let leaf = &self.leaves[self.find_leaf(key) as usize];
+        let index = leaf.keys.as_slice().binary_search(key).ok()?;
+        Some(&leaf.values.as_slice()[index])
+    }
+
+    /// Get a mutable reference to the value stored under `key`.
+    #[inline]
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let leaf_id = self.find_leaf(key);
+        let leaf = &mut self.leaves[leaf_id as usize];
+        let index = leaf.keys.as_slice().binary_search(key).ok()?;
+        Some(&mut leaf.values.as_mut_slice()[index])
+    }
+
+    /// Returns true if `key` is present.
+    #[inline]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Insert `value` under `key`, returning the previous value if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let mut path = Vec::with_capacity(self.height);
+        let mut node = self.root;
+        for _ in 0..self.height {
+            let branch = &self.branches[node as usize];
+            let child_index = branch.keys.as_slice().partition_point(|k| *k <= key);
+            path.push((node, child_index));
+            node = branch.children.as_slice()[child_index];
+        }
+
+        let new_id = self.leaves.len() as u32;
+        let leaf = &mut self.leaves[node as usize];
+        let index = match leaf.keys.as_slice().binary_search(&key) {
+            Ok(index) => {
+                return Some(std::mem::replace(
+                    &mut leaf.values.as_mut_slice()[index],
+                    value,
+                ))
+            }
+            Err(index) => index,
+        };
+        self.len += 1;
+
+        if !leaf.keys.is_full() {
+            leaf.keys.insert(index, key);
+            leaf.values.insert(index, value);
+            return None;
+        }
+
+        // Split the leaf in half, then insert into the correct side
+        let mid = CAP / 2;
+        let mut right = Leaf {
+            keys: leaf.keys.split_off(mid),
+            values: leaf.values.split_off(mid),
+            next: leaf.next,
+        };
+        leaf.next = new_id;
+        let (target, target_index) = if index <= mid {
+            (leaf, index)
+        } else {
+            (&mut right, index - mid)
+        };
+        target.keys.insert(target_index, key);
+        target.values.insert(target_index, value);
+        let separator = right.keys.as_slice()[0].clone();
+        self.leaves.push(right);
+
+        self.insert_into_parents(&path, separator, new_id);
+        None
+    }
+
+    /// Remove `key`, returning its value if it was present.
+    ///
+    /// Leaves are not rebalanced; see [`compact`](FixedCapTree::compact).
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let leaf_id = self.find_leaf(key);
+        let leaf = &mut self.leaves[leaf_id as usize];
+        let index = leaf.keys.as_slice().binary_search(key).ok()?;
+        leaf.keys.remove(index);
+        self.len -= 1;
+        Some(leaf.values.remove(index))
+    }
+
+    /// Rebuild the tree with full leaves, dropping space left by removals.
+    pub fn compact(&mut self) {
+        let mut rebuilt = Self::new();
+        // Follow the leaf chain from the leftmost leaf, which is always the
+        // first allocated, so entries arrive in order
+        let mut leaves: Vec<Option<Leaf<K, V, CAP>>> = std::mem::take(&mut self.leaves)
+            .into_iter()
+            .map(Some)
+            .collect();
+        let mut next = 0;
+        while let Some(leaf) = leaves.get_mut(next as usize).and_then(Option::take) {
+            next = leaf.next;
+            for (key, value) in leaf.keys.into_vec().into_iter().zip(leaf.values.into_vec()) {
+                rebuilt.insert(key, value);
+            }
+        }
+        *self = rebuilt;
+    }
+
+    /// Iterate over all entries in key order.
+    pub fn iter(&self) -> FixedCapIter<'_, K, V, CAP> {
+        FixedCapIter {
+            tree: self,
+            leaf: 0,
+            index: 0,
+            end: Bound::Unbounded,
+        }
+    }
+
+    /// Iterate over the entries whose keys fall in `range`, in key order.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> FixedCapIter<'_, K, V, CAP> {
+        let (leaf, index) = match range.start_bound() {
+            Bound::Included(start) => {
+                let leaf = self.find_leaf(start);
+                let keys = self.leaves[leaf as usize].keys.as_slice();
+                (leaf, keys.partition_point(|k| k < start))
+            }
+            Bound::Excluded(start) => {
+                let leaf = self.find_leaf(start);
+                let keys = self.leaves[leaf as usize].keys.as_slice();
+                (leaf, keys.partition_point(|k| k <= start))
+            }
+            Bound::Unbounded => (0, 0),
+        };
+        FixedCapIter {
+            tree: self,
+            leaf,
+            index,
+            end: range.end_bound().cloned(),
+        }
+    }
+
+    /// Descend to the leaf that would hold `key`.
+    #[inline]
+    fn find_leaf(&self, key: &K) -> u32 {
+        let mut node = self.root;
+        for _ in 0..self.height {
+            let branch = &self.branches[node as usize];
+            node = branch.children.as_slice()[branch.keys.as_slice().partition_point(|k| k <= key)];
+        }
+        node
+    }
+
+    /// Insert a new right child and its separator into each ancestor on
+    /// `path`, splitting full branches and growing a new root if needed.
+    fn insert_into_parents(&mut self, path: &[(u32, usize)], mut separator: K, mut child: u32) {
+        for &(branch_id, child_index) in path.iter().rev() {
+            let branch = &mut self.branches[branch_id as usize];
+            if !branch.children.is_full() {
+                branch.keys.insert(child_index, separator);
+                branch.children.insert(child_index + 1, child);
+                return;
+            }
+
+            // Split around the middle key, which moves up, then add the new
+            // child to whichever half its left neighbour landed in
+            let mid = branch.keys.len() / 2;
+            let mut right = Branch {
+                keys: branch.keys.split_off(mid + 1),
+                children: branch.children.split_off(mid + 1),
+            };
+            let promoted = invariant(branch.keys.pop(), "full branch has a middle key");
+            if child_index <= mid {
+                branch.keys.insert(child_index, separator);
+                branch.children.insert(child_index + 1, child);
+            } else {
+                right.keys.insert(child_index - mid - 1, separator);
+                right.children.insert(child_index - mid, child);
+            }
+
+            separator = promoted;
+            child = self.branches.len() as u32;
+            self.branches.push(right);
+        }
+
+        // The root split: grow the tree by one level
+        let mut root = Branch {
+            keys: InlineVec::new(),
+            children: InlineVec::new(),
+        };
+        root.keys.push(separator);
+        root.children.push(self.root);
+        root.children.push(child);
+        self.root = self.branches.len() as u32;
+        self.branches.push(root);
+        self.height += 1;
+    }
+}
+
+// ============================================================================
+// CONVERSION TO AND FROM BPLUSTREEMAP
+// ============================================================================
+
+impl<K: Ord + Clone, V, const CAP: usize> FixedCapTree<K, V, CAP> {
+    /// Build a tree over `leaves`, which are non-empty and in key order, by
+    /// adding branch levels above them. No entry is searched for or moved.
+    fn from_leaves(mut leaves: Vec<Leaf<K, V, CAP>>) -> Self {
+        if leaves.is_empty() {
+            return Self::new();
+        }
+        let count = leaves.len();
+        let mut len = 0;
+        for (index, leaf) in leaves.iter_mut().enumerate() {
+            leaf.next = if index + 1 < count {
+                (index + 1) as u32
+            } else {
+                NO_LEAF
+            };
+            len += leaf.keys.len();
+        }
+
+        // Each level as node ids with the smallest key below each node
+        let mut level: Vec<(u32, K)> = leaves
+            .iter()
+            .enumerate()
+            .map(|(index, leaf)| (index as u32, leaf.keys.as_slice()[0].clone()))
+            .collect();
+        let mut branches = Vec::new();
+        let mut height = 0;
+        while level.len() > 1 {
+            let sizes = even_chunk_sizes(level.len(), level.len().div_ceil(CAP));
+            let mut nodes = level.into_iter();
+            level = Vec::with_capacity(sizes.len());
+            for size in sizes {
+                let Some((first, smallest)) = nodes.next() else {
+                    break;
+                };
+                let mut branch = Branch {
+                    keys: InlineVec::new(),
+                    children: InlineVec::new(),
+                };
+                branch.children.push(first);
+                for (child, separator) in nodes.by_ref().take(size - 1) {
+                    branch.keys.push(separator);
+                    branch.children.push(child);
+                }
+                level.push((branches.len() as u32, smallest));
+                branches.push(branch);
+            }
+            height += 1;
+        }
+
+        Self {
+            leaves,
+            branches,
+            root: level[0].0,
+            height,
+            len,
+        }
+    }
+}
+
+impl<K: TreeKey, V: TreeValue, const CAP: usize> FixedCapTree<K, V, CAP> {
+    /// Move the entries into a [`BPlusTreeMap`] with capacity `CAP`.
+    ///
+    /// Leaves move across whole, in key order, and only the branches are
+    /// built anew; leaves left sparse by [`remove`](Self::remove) are merged
+    /// on the way, so the result is balanced. The tree has default settings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::{BPlusTreeMap, FixedCapTree};
+    ///
+    /// let mut fixed: FixedCapTree<u32, u32, 16> = FixedCapTree::new();
+    /// for i in 0..1_000 {
+    ///     fixed.insert(i, i * 2);
+    /// }
+    ///
+    /// let tree: BPlusTreeMap<u32, u32> = fixed.into_tree();
+    /// assert_eq!(tree.capacity(), 16);
+    /// assert_eq!(tree.get(&500), Some(&1_000));
+    ///
+    /// let fixed = tree.into_fixed_capacity::<16>().unwrap();
+    /// assert_eq!(fixed.len(), 1_000);
+    /// ```
+    pub fn into_tree(self) -> BPlusTreeMap<K, V> {
+        let mut tree = BPlusTreeMap::with_valid_capacity(CAP);
+        let mut leaves: Vec<Option<Leaf<K, V, CAP>>> = self.leaves.into_iter().map(Some).collect();
+        let mut moved = Vec::new();
+        let mut next = 0;
+        while let Some(leaf) = leaves.get_mut(next as usize).and_then(Option::take) {
+            next = leaf.next;
+            if leaf.keys.len() > 0 {
+                moved.push((
+                    leaf.keys.into_vec().into_iter().collect(),
+                    leaf.values.into_vec().into_iter().collect(),
+                ));
+            }
+        }
+        tree.adopt_leaves(moved);
+        tree
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Move the entries into a [`FixedCapTree`], whose node capacity `CAP` is
+    /// fixed for the type rather than chosen per tree.
+    ///
+    /// Leaves move across whole, in key order, cut into pieces of at most
+    /// `CAP` entries where they are larger; only the branches are built anew.
+    /// Settings such as the deletion mode or a byte budget have no
+    /// counterpart there and are dropped.
+    ///
+    /// Fails with a corruption error, rather than returning a tree that is
+    /// missing entries, if the leaf chain does not reach every entry.
+    pub fn into_fixed_capacity<const CAP: usize>(mut self) -> BTreeResult<FixedCapTree<K, V, CAP>> {
+        let expected = self.len();
+        let mut leaves = Vec::new();
+        let mut moved = 0;
+        let mut next = self.get_first_leaf_id().unwrap_or(NULL_NODE);
+        while next != NULL_NODE {
+            let leaf = if next == INLINE_ROOT {
+                self.inline_root.take()
+            } else {
+                self.leaf_arena.deallocate(next)
+            };
+            let Some(leaf) = leaf else {
+                break;
+            };
+            next = leaf.next;
+            moved += leaf.keys.len();
+            let sizes = even_chunk_sizes(leaf.keys.len(), leaf.keys.len().div_ceil(CAP));
+            let mut entries = leaf.keys.into_iter().zip(leaf.values);
+            for size in sizes.into_iter().filter(|&size| size > 0) {
+                let mut piece = Leaf::new();
+                for (key, value) in entries.by_ref().take(size) {
+                    piece.keys.push(key);
+                    piece.values.push(value);
+                }
+                leaves.push(piece);
+            }
+        }
+
+        if moved != expected {
+            return Err(BPlusTreeError::corrupted_tree(
+                "Leaf chain",
+                &format!("only {} of {} entries are reachable", moved, expected),
+            ));
+        }
+        Ok(FixedCapTree::from_leaves(leaves))
+    }
+
+    /// Make `leaves`, which are non-empty and in key order, the content of
+    /// this empty tree, and let the fix pass build the branches above them.
+    fn adopt_leaves(&mut self, leaves: Vec<(NodeVec<K>, NodeVec<V>)>) {
+        if leaves.len() <= 1 {
+            if let Some((keys, values)) = leaves.into_iter().next() {
+                self.inline_root = Some(LeafNode {
+                    capacity: self.capacity,
+                    keys,
+                    values,
+                    next: NULL_NODE,
+                });
+            }
+            return;
+        }
+
+        // Allocate right to left so each leaf knows its successor
+        let mut separators = Vec::with_capacity(leaves.len());
+        let mut child_ids = Vec::with_capacity(leaves.len());
+        let mut next = NULL_NODE;
+        for (keys, values) in leaves.into_iter().rev() {
+            separators.push(keys[0].clone());
+            next = self.allocate_leaf_with_data(self.capacity, keys, values, next);
+            child_ids.push(next);
+        }
+        separators.pop();
+        separators.reverse();
+        child_ids.reverse();
+
+        // One root over every leaf; the fix pass splits it into levels
+        self.inline_root = None;
+        let root = self.allocate_branch(BranchNode::from_parts(
+            self.capacity,
+            separators.into_iter().collect(),
+            child_ids.into_iter().collect(),
+            true,
+        ));
+        self.root = NodeRef::branch(root);
+        self.rebalance_whole_tree();
+    }
+}
+
+/// Iterator over the entries of a [`FixedCapTree`] in key order.
+pub struct FixedCapIter<'a, K, V, const CAP: usize> {
+    tree: &'a FixedCapTree<K, V, CAP>,
+    leaf: u32,
+    index: usize,
+    end: Bound<K>,
+}
+
+impl<'a, K: Ord, V, const CAP: usize> Iterator for FixedCapIter<'a, K, V, CAP> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let leaf = self.tree.leaves.get(self.leaf as usize)?;
+            if let Some(key) = leaf.keys.as_slice().get(self.index) {
+                let in_range = match &self.end {
+                    Bound::Included(end) => key <= end,
+                    Bound::Excluded(end) => key < end,
+                    Bound::Unbounded => true,
+                };
+                if !in_range {
+                    self.leaf = NO_LEAF;
+                    return None;
+                }
+                self.index += 1;
+                return Some((key, &leaf.values.as_slice()[self.index - 1]));
+            }
+            self.leaf = leaf.next;
+            self.index = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use std::collections::BTreeMap;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_inline_vec_drops_each_item_once() {
+        let item = Rc::new(());
+        let mut items: InlineVec<Rc<()>, 8> = InlineVec::new();
+        for _ in 0..8 {
+            items.push(Rc::clone(&item));
+        }
+        let right = items.split_off(3);
+        drop(items.remove(1));
+        let copy = right.clone();
+        assert_eq!(Rc::strong_count(&item), 1 + 2 + 5 + 5);
+        drop((items, right, copy));
+        assert_eq!(Rc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn test_random_operations_match_btreemap() {
+        let mut tree: FixedCapTree<u64, u64, 8> = FixedCapTree::new();
+        let mut reference = BTreeMap::new();
+        let mut rng = SeededRng::new(17);
+
+        for i in 0..30_000u64 {
+            let key = rng.below(5_000);
+            if rng.below(4) == 0 {
+                assert_eq!(tree.remove(&key), reference.remove(&key));
+            } else {
+                assert_eq!(tree.insert(key, i), reference.insert(key, i));
+            }
+        }
+        assert_eq!(tree.len(), reference.len());
+        assert!(tree.iter().eq(reference.iter()));
+        assert!(tree.range(1_000..=2_500).eq(reference.range(1_000..=2_500)));
+        assert!(tree
+            .range((Bound::Excluded(700), Bound::Excluded(900)))
+            .eq(reference.range((Bound::Excluded(700), Bound::Excluded(900)))));
+
+        tree.compact();
+        assert_eq!(tree.len(), reference.len());
+        assert!(tree.iter().eq(reference.iter()));
+        for key in 0..5_000 {
+            assert_eq!(tree.get(&key), reference.get(&key));
+        }
+    }
+
+    #[test]
+    fn test_heap_keys_and_values_are_released() {
+        let value = Rc::new(());
+        let mut tree: FixedCapTree<String, Rc<()>, 4> = FixedCapTree::new();
+        for i in 0..500 {
+            tree.insert(format!("{:04}", i), Rc::clone(&value));
+        }
+        for i in (0..500).step_by(3) {
+            tree.remove(&format!("{:04}", i));
+        }
+        tree.compact();
+        assert_eq!(Rc::strong_count(&value), 1 + tree.len());
+        drop(tree);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn test_conversions_move_every_entry_and_keep_both_trees_valid() {
+        let mut tree = BPlusTreeMap::new(16).unwrap();
+        tree.set_deletion_mode(crate::DeletionMode::DeferredRebalance);
+        let mut reference = BTreeMap::new();
+        let mut rng = SeededRng::new(3);
+        for i in 0..3_000u64 {
+            let key = rng.below(10_000);
+            tree.insert(key, i);
+            reference.insert(key, i);
+        }
+        // Removals without rebalancing leave empty and sparse leaves behind
+        for key in (0..10_000).filter(|key| key % 5 != 0) {
+            assert_eq!(tree.remove(&key), reference.remove(&key));
+        }
+
+        // Leaves of up to 16 entries are cut to fit 4
+        let fixed: FixedCapTree<u64, u64, 4> = tree.into_fixed_capacity().unwrap();
+        assert_eq!(fixed.len(), reference.len());
+        assert!(fixed.iter().eq(reference.iter()));
+        for key in (0..10_000).step_by(7) {
+            assert_eq!(fixed.get(&key), reference.get(&key));
+        }
+
+        let mut fixed = fixed;
+        for key in (0..10_000).step_by(10) {
+            assert_eq!(fixed.remove(&key), reference.remove(&key));
+        }
+        let back = fixed.into_tree();
+        assert_eq!(back.capacity(), 4);
+        assert!(back.check_invariants());
+        assert_eq!(back.unreachable_node_count(), (0, 0));
+        assert!(back.items().eq(reference.iter()));
+
+        let empty: FixedCapTree<u64, u64, 8> = FixedCapTree::new();
+        assert!(empty.into_tree().is_empty());
+        let single: FixedCapTree<u64, u64, 8> =
+            BPlusTreeMap::new(8).unwrap().into_fixed_capacity().unwrap();
+        assert!(single.is_empty());
+    }
+
+    #[test]
+    fn test_conversion_refuses_a_broken_leaf_chain() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..100 {
+            tree.insert(i, i);
+        }
+        let first = tree.get_first_leaf_id().unwrap();
+        let second = tree.get_leaf(first).unwrap().next;
+        tree.deallocate_leaf(second);
+        assert!(tree
+            .into_fixed_capacity::<8>()
+            .is_err_and(|error| error.is_corruption()));
+    }
+}
@@ -3,10 +3,11 @@
 //! This module contains all the read operations for the B+ tree, including
 //! key lookup, value retrieval, and helper methods for accessing nodes.
 
-use crate::error::{BPlusTreeError, BTreeResult, KeyResult};
-use crate::types::{BPlusTreeMap, BranchNode, LeafNode, NodeId, NodeRef, NULL_NODE};
+use crate::bounds::{TreeKey, TreeValue};
+use crate::error::{BPlusTreeError, BTreeResult, KeyResult, QueryError};
+use crate::types::{BPlusTreeMap, BranchNode, LeafNode, NodeId, NodeRef, INLINE_ROOT, NULL_NODE};
 
-impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
     // ============================================================================
     // PUBLIC GET OPERATIONS
     // ============================================================================
@@ -32,11 +33,7 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
     /// assert_eq!(tree.get(&2), None);
     /// ```
     pub fn get(&self, key: &K) -> Option<&V> {
-        let (leaf_id, index, matched) = self.find_leaf_for_key_with_match(key)?;
-        if !matched {
-            return None;
-        }
-        self.get_leaf(leaf_id)?.get_value(index)
+        self.leaf_for_key(key)?.get(key)
     }
 
     /// Check if key exists in the tree.
@@ -110,7 +107,8 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
     /// assert!(tree.get_item(&2).is_err());
     /// ```
     pub fn get_item(&self, key: &K) -> KeyResult<&V> {
-        self.get(key).ok_or(BPlusTreeError::KeyNotFound)
+        self.get(key)
+            .ok_or(BPlusTreeError::Query(QueryError::KeyNotFound))
     }
 
     /// Get a mutable reference to the value for a key.
@@ -143,6 +141,49 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
         self.get_leaf_mut(leaf_id)?.get_value_mut(index)
     }
 
+    /// Swap the values stored under two keys without cloning either one.
+    ///
+    /// Returns `KeyNotFound` and leaves the tree unchanged if either key is
+    /// missing. Swapping a key with itself is a no-op.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(16).unwrap();
+    /// tree.insert(1, "one");
+    /// tree.insert(2, "two");
+    /// tree.swap_values(&1, &2).unwrap();
+    /// assert_eq!(tree.get(&1), Some(&"two"));
+    /// assert_eq!(tree.get(&2), Some(&"one"));
+    /// assert!(tree.swap_values(&1, &3).is_err());
+    /// ```
+    pub fn swap_values(&mut self, first: &K, second: &K) -> KeyResult<()> {
+        let locate = |tree: &Self, key: &K| match tree.find_leaf_for_key_with_match(key) {
+            Some((leaf_id, index, true)) => Ok((leaf_id, index)),
+            _ => Err(BPlusTreeError::Query(QueryError::KeyNotFound)),
+        };
+        let (first_leaf, first_index) = locate(self, first)?;
+        let (second_leaf, second_index) = locate(self, second)?;
+
+        if first_leaf == second_leaf {
+            let leaf = self
+                .get_leaf_mut(first_leaf)
+                .ok_or(BPlusTreeError::Query(QueryError::KeyNotFound))?;
+            leaf.values.swap(first_index, second_index);
+            return Ok(());
+        }
+
+        self.bump_generation();
+        let (a, b) = self
+            .leaf_arena
+            .get_pair_mut(first_leaf, second_leaf)
+            .ok_or(BPlusTreeError::Query(QueryError::KeyNotFound))?;
+        std::mem::swap(&mut a.values[first_index], &mut b.values[second_index]);
+        Ok(())
+    }
+
     /// Try to get a value, returning detailed error context on failure.
     ///
     /// # Arguments
@@ -164,7 +205,8 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
     /// assert!(tree.try_get(&2).is_err());
     /// ```
     pub fn try_get(&self, key: &K) -> KeyResult<&V> {
-        self.get(key).ok_or(BPlusTreeError::KeyNotFound)
+        self.get(key)
+            .ok_or(BPlusTreeError::Query(QueryError::KeyNotFound))
     }
 
     /// Get multiple keys with detailed error reporting.
@@ -198,7 +240,7 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
             match self.get(key) {
                 Some(value) => values.push(value),
                 None => {
-                    return Err(BPlusTreeError::KeyNotFound);
+                    return Err(BPlusTreeError::Query(QueryError::KeyNotFound));
                 }
             }
         }
@@ -217,26 +259,30 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
     pub fn get_child_for_key(&self, branch_id: NodeId, key: &K) -> Option<(usize, NodeRef<K, V>)> {
         let branch = self.get_branch(branch_id)?;
         let child_index = branch.find_child_index(key);
-        branch
-            .children
-            .get(child_index)
-            .cloned()
-            .map(|child| (child_index, child))
+        branch.child(child_index).map(|child| (child_index, child))
     }
 
     // ============================================================================
     // ARENA ACCESS METHODS
     // ============================================================================
 
-    /// Get a reference to a leaf node in the arena.
+    /// Get a reference to a leaf node in the arena, or to the inline root.
     #[inline]
     pub fn get_leaf(&self, id: NodeId) -> Option<&LeafNode<K, V>> {
+        if id == INLINE_ROOT {
+            return self.inline_root.as_ref();
+        }
         self.leaf_arena.get(id)
     }
 
-    /// Get a mutable reference to a leaf node in the arena.
+    /// Get a mutable reference to a leaf node in the arena, or to the inline
+    /// root.
     #[inline]
     pub fn get_leaf_mut(&mut self, id: NodeId) -> Option<&mut LeafNode<K, V>> {
+        self.bump_generation();
+        if id == INLINE_ROOT {
+            return self.inline_root.as_mut();
+        }
         self.leaf_arena.get_mut(id)
     }
 
@@ -317,7 +363,7 @@ mod tests {
         assert!(tree.get_item(&2).is_err());
         assert!(matches!(
             tree.get_item(&2),
-            Err(BPlusTreeError::KeyNotFound)
+            Err(BPlusTreeError::Query(QueryError::KeyNotFound))
         ));
     }
 
@@ -336,6 +382,31 @@ mod tests {
         assert_eq!(tree.get_mut(&2), None);
     }
 
+    #[test]
+    fn test_swap_values() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..50 {
+            tree.insert(i, i * 10);
+        }
+
+        // Same leaf and different leaves
+        tree.swap_values(&0, &1).unwrap();
+        tree.swap_values(&2, &49).unwrap();
+        tree.swap_values(&7, &7).unwrap();
+        assert_eq!(tree.get(&0), Some(&10));
+        assert_eq!(tree.get(&1), Some(&0));
+        assert_eq!(tree.get(&2), Some(&490));
+        assert_eq!(tree.get(&49), Some(&20));
+        assert_eq!(tree.get(&7), Some(&70));
+
+        assert!(matches!(
+            tree.swap_values(&3, &50),
+            Err(BPlusTreeError::Query(QueryError::KeyNotFound))
+        ));
+        assert_eq!(tree.get(&3), Some(&30));
+        assert!(tree.check_invariants());
+    }
+
     #[test]
     fn test_get_many() {
         let mut tree = BPlusTreeMap::new(4).unwrap();
@@ -394,16 +465,15 @@ mod tests {
     #[test]
     fn test_branch_node_operations() {
         use crate::types::NodeRef;
-        use std::marker::PhantomData;
 
         let mut branch = BranchNode::<i32, String>::new(4);
 
         // Add some keys and children for testing
         branch.keys.push(5);
         branch.keys.push(10);
-        branch.children.push(NodeRef::Leaf(0, PhantomData));
-        branch.children.push(NodeRef::Leaf(1, PhantomData));
-        branch.children.push(NodeRef::Leaf(2, PhantomData));
+        branch.push_child(NodeRef::leaf(0));
+        branch.push_child(NodeRef::leaf(1));
+        branch.push_child(NodeRef::leaf(2));
 
         // Test find_child_index
         assert_eq!(branch.find_child_index(&3), 0); // Less than first key
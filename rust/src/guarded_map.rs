@@ -0,0 +1,255 @@
+//! Detection of keys that change after insertion.
+//!
+//! A key whose ordering depends on interior mutability (a `Cell`, `RefCell`
+//! or shared handle) can be changed while the tree holds it. The tree is not
+//! told, so the key stays where it was sorted and later searches may miss it
+//! or its neighbours. [`GuardedMap`] records a hash of each key when it is
+//! inserted and compares it with a fresh hash in [`validate`], catching the
+//! change even before it breaks the key order.
+//!
+//! Hashing every key costs time on each insert and a `u64` per entry, so this
+//! is a debugging aid behind the `guarded` feature.
+//!
+//! [`validate`]: GuardedMap::validate
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::error::{BPlusTreeError, BTreeResult, InitResult};
+use crate::types::{BPlusTreeMap, NULL_NODE};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::RangeBounds;
+
+/// A map that can detect keys whose hash changed since they were inserted.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::GuardedMap;
+/// use std::cell::Cell;
+/// use std::cmp::Ordering;
+/// use std::hash::{Hash, Hasher};
+/// use std::rc::Rc;
+///
+/// #[derive(Clone)]
+/// struct Handle(Rc<Cell<u32>>);
+/// # impl PartialEq for Handle { fn eq(&self, o: &Self) -> bool { self.0.get() == o.0.get() } }
+/// # impl Eq for Handle {}
+/// # impl PartialOrd for Handle { fn partial_cmp(&self, o: &Self) -> Option<Ordering> { Some(self.cmp(o)) } }
+/// # impl Ord for Handle { fn cmp(&self, o: &Self) -> Ordering { self.0.get().cmp(&o.0.get()) } }
+/// # impl Hash for Handle { fn hash<H: Hasher>(&self, h: &mut H) { self.0.get().hash(h) } }
+///
+/// let shared = Rc::new(Cell::new(10));
+/// let mut map = GuardedMap::new(16).unwrap();
+/// map.insert(Handle(Rc::clone(&shared)), "ten");
+/// map.insert(Handle(Rc::new(Cell::new(20))), "twenty");
+/// assert!(map.validate().is_ok());
+///
+/// shared.set(15);
+/// assert!(map.validate().is_err());
+/// ```
+#[derive(Debug)]
+pub struct GuardedMap<K, V> {
+    tree: BPlusTreeMap<K, (u64, V)>,
+}
+
+impl<K: TreeKey + Hash, V: TreeValue> GuardedMap<K, V> {
+    /// Create an empty map backed by a tree with node capacity `capacity`.
+    pub fn new(capacity: usize) -> InitResult<Self> {
+        Ok(Self {
+            tree: BPlusTreeMap::new(capacity)?,
+        })
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Returns true if the map holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Get the value stored under `key`.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.tree.get(key).map(|(_, value)| value)
+    }
+
+    /// Get a mutable reference to the value stored under `key`.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.tree.get_mut(key).map(|(_, value)| value)
+    }
+
+    /// Returns true if the map holds `key`.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.tree.contains_key(key)
+    }
+
+    /// Insert `value` under `key`, returning the previous value if any. The
+    /// key's hash is recorded for [`validate`](Self::validate).
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let hash = hash_key(&key);
+        self.tree.insert(key, (hash, value)).map(|(_, old)| old)
+    }
+
+    /// Remove `key`, returning its value if it was present.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.tree.remove(key).map(|(_, value)| value)
+    }
+
+    /// Remove every entry.
+    pub fn clear(&mut self) {
+        self.tree.clear();
+    }
+
+    /// Iterate over all entries in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.tree.items().map(|(key, (_, value))| (key, value))
+    }
+
+    /// Iterate over the entries in `range` in key order.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> impl Iterator<Item = (&K, &V)> {
+        self.tree.range(range).map(|(key, (_, value))| (key, value))
+    }
+
+    /// Check that every key still hashes as it did when inserted and that
+    /// the keys are still in strictly increasing order. The error names the
+    /// position of the first key that fails, counting from the smallest.
+    pub fn validate(&self) -> BTreeResult<()> {
+        let mut previous: Option<&K> = None;
+        let mut position = 0;
+        // Walk the leaf chain directly: iterators built with `order-checks`
+        // would panic on the very disorder this reports
+        let mut leaf_id = self.tree.get_first_leaf_id();
+        while let Some(leaf) = leaf_id.and_then(|id| self.tree.get_leaf(id)) {
+            for (key, (hash, _)) in leaf.keys.iter().zip(leaf.values.iter()) {
+                if hash_key(key) != *hash {
+                    return Err(BPlusTreeError::data_integrity(
+                        "GuardedMap",
+                        &format!("key at position {} changed after it was inserted", position),
+                    ));
+                }
+                if previous.is_some_and(|previous| previous >= key) {
+                    return Err(BPlusTreeError::data_integrity(
+                        "GuardedMap",
+                        &format!(
+                            "keys at positions {} and {} are out of order",
+                            position - 1,
+                            position
+                        ),
+                    ));
+                }
+                previous = Some(key);
+                position += 1;
+            }
+            leaf_id = (leaf.next != NULL_NODE).then_some(leaf.next);
+        }
+        Ok(())
+    }
+
+    /// Stop guarding and return the entries as a plain tree.
+    pub fn into_tree(self) -> BPlusTreeMap<K, V> {
+        let mut tree = BPlusTreeMap::with_valid_capacity(self.tree.capacity);
+        for (key, (_, value)) in self.tree.items() {
+            tree.insert(key.clone(), value.clone());
+        }
+        tree
+    }
+}
+
+fn hash_key<K: Hash>(key: &K) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::cmp::Ordering;
+    use std::rc::Rc;
+
+    /// A key that shares its value with the test, which can change it.
+    #[derive(Clone)]
+    struct Handle(Rc<Cell<u32>>);
+
+    impl PartialEq for Handle {
+        fn eq(&self, other: &Self) -> bool {
+            self.0.get() == other.0.get()
+        }
+    }
+
+    impl Eq for Handle {}
+
+    impl PartialOrd for Handle {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Handle {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.0.get().cmp(&other.0.get())
+        }
+    }
+
+    impl Hash for Handle {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.0.get().hash(state);
+        }
+    }
+
+    fn guarded_handles() -> (GuardedMap<Handle, u32>, Vec<Rc<Cell<u32>>>) {
+        let mut map = GuardedMap::new(4).unwrap();
+        let cells: Vec<_> = (0..100).map(|i| Rc::new(Cell::new(i * 10))).collect();
+        for (i, cell) in cells.iter().enumerate() {
+            map.insert(Handle(Rc::clone(cell)), i as u32);
+        }
+        assert!(map.validate().is_ok());
+        (map, cells)
+    }
+
+    #[test]
+    fn test_change_that_keeps_order_is_caught() {
+        let (map, cells) = guarded_handles();
+        // 500 becomes 501: still between 490 and 510, so only the hash tells
+        cells[50].set(501);
+        let error = map.validate().unwrap_err().to_string();
+        assert!(error.contains("position 50 changed"), "{}", error);
+    }
+
+    #[test]
+    fn test_change_that_breaks_order_is_caught() {
+        let (map, cells) = guarded_handles();
+        cells[20].set(5_000);
+        assert!(map.validate().is_err());
+        // Put back, the map is whole again
+        cells[20].set(200);
+        assert!(map.validate().is_ok());
+        assert_eq!(map.len(), 100);
+    }
+
+    #[test]
+    fn test_map_operations() {
+        let mut map = GuardedMap::new(4).unwrap();
+        for i in (0..50).rev() {
+            assert_eq!(map.insert(i, i * 2), None);
+        }
+        assert_eq!(map.insert(7, 0), Some(14));
+        *map.get_mut(&8).unwrap() += 1;
+        assert_eq!(map.remove(&9), Some(18));
+        assert!(!map.contains_key(&9));
+        assert_eq!(map.get(&8), Some(&17));
+        assert_eq!(
+            map.range(6..=8).map(|(k, _)| *k).collect::<Vec<_>>(),
+            [6, 7, 8]
+        );
+        assert_eq!(map.iter().count(), 49);
+        assert!(map.validate().is_ok());
+
+        let tree = map.into_tree();
+        assert_eq!(tree.len(), 49);
+        assert_eq!(tree.get(&7), Some(&0));
+    }
+}
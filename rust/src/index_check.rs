@@ -0,0 +1,230 @@
+//! Checking a primary tree against a reverse index kept beside it.
+//!
+//! An application that looks entries up both ways often keeps two trees: a
+//! primary `K -> V` and a reverse `R -> K`, where `R` is derived from each
+//! entry. Both are updated by hand, so a bug or a crash between the two
+//! writes leaves them disagreeing. [`verify_bijection`] finds every such
+//! disagreement with one sorted merge of the two, rather than a lookup in
+//! the reverse tree per primary entry.
+//!
+//! [`verify_bijection`]: BPlusTreeMap::verify_bijection
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::types::BPlusTreeMap;
+use std::cmp::Ordering;
+
+/// A way in which a primary tree and its reverse index disagree. Reported by
+/// [`BPlusTreeMap::verify_bijection`], at most one per reverse key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexDiscrepancy<K, R> {
+    /// A primary entry whose reverse key is not in the reverse index.
+    Missing {
+        /// The primary key.
+        key: K,
+        /// The reverse key the entry projects to.
+        reverse_key: R,
+    },
+    /// The reverse index maps the reverse key to a different primary key.
+    Mismatched {
+        /// The reverse key.
+        reverse_key: R,
+        /// The primary key that projects to it.
+        expected: K,
+        /// The primary key the reverse index holds for it.
+        found: K,
+    },
+    /// A reverse entry that no primary entry projects to.
+    Orphaned {
+        /// The reverse key.
+        reverse_key: R,
+        /// The primary key the reverse index holds for it.
+        key: K,
+    },
+    /// Several primary entries project to the same reverse key, so no
+    /// reverse index can mirror them all.
+    Collision {
+        /// The shared reverse key.
+        reverse_key: R,
+        /// The primary keys that project to it, ascending.
+        keys: Vec<K>,
+    },
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Check that `reverse` mirrors this tree exactly: every entry `(k, v)`
+    /// has `reverse[project(k, v)] == k`, and `reverse` holds nothing else.
+    ///
+    /// Returns every discrepancy in ascending reverse-key order, or an empty
+    /// `Vec` if the trees agree. The projected entries are sorted by reverse
+    /// key and then merged with `reverse` in one pass. When `project` is
+    /// increasing in the primary key, as with a timestamp index over
+    /// sequential ids, that sort takes linear time and the whole check is
+    /// O(n); otherwise it is O(n log n).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::{BPlusTreeMap, IndexDiscrepancy};
+    ///
+    /// let mut users = BPlusTreeMap::new(16).unwrap();
+    /// let mut by_email = BPlusTreeMap::new(16).unwrap();
+    /// for (id, email) in [(1, "ann@example.com"), (2, "bob@example.com")] {
+    ///     users.insert(id, email.to_string());
+    ///     by_email.insert(email.to_string(), id);
+    /// }
+    /// assert!(users.verify_bijection(&by_email, |_, email| email.clone()).is_empty());
+    ///
+    /// // Bob changed address but the index was not updated
+    /// users.insert(2, "robert@example.com".to_string());
+    /// let problems = users.verify_bijection(&by_email, |_, email| email.clone());
+    /// assert_eq!(
+    ///     problems,
+    ///     vec![
+    ///         IndexDiscrepancy::Orphaned {
+    ///             reverse_key: "bob@example.com".to_string(),
+    ///             key: 2,
+    ///         },
+    ///         IndexDiscrepancy::Missing {
+    ///             key: 2,
+    ///             reverse_key: "robert@example.com".to_string(),
+    ///         },
+    ///     ]
+    /// );
+    /// ```
+    pub fn verify_bijection<R, F>(
+        &self,
+        reverse: &BPlusTreeMap<R, K>,
+        mut project: F,
+    ) -> Vec<IndexDiscrepancy<K, R>>
+    where
+        R: TreeKey,
+        F: FnMut(&K, &V) -> R,
+    {
+        let mut projected: Vec<(R, &K)> = self
+            .items()
+            .map(|(key, value)| (project(key, value), key))
+            .collect();
+        // Stable, so primary keys sharing a reverse key stay ascending
+        projected.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut discrepancies = Vec::new();
+        let mut projected = projected.into_iter().peekable();
+        let mut reverse = reverse.items().peekable();
+        loop {
+            let order = match (projected.peek(), reverse.peek()) {
+                (None, None) => break,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((expected, _)), Some((found, _))) => expected.cmp(found),
+            };
+
+            let mirrored = match order {
+                Ordering::Greater => None,
+                _ => projected.next().map(|(reverse_key, key)| {
+                    let mut keys = vec![key.clone()];
+                    while let Some((_, key)) = projected.next_if(|(next, _)| *next == reverse_key) {
+                        keys.push(key.clone());
+                    }
+                    (reverse_key, keys)
+                }),
+            };
+            let found = match order {
+                Ordering::Less => None,
+                _ => reverse.next(),
+            };
+
+            discrepancies.extend(match (mirrored, found) {
+                (Some((reverse_key, keys)), _) if keys.len() > 1 => {
+                    Some(IndexDiscrepancy::Collision { reverse_key, keys })
+                }
+                (Some((reverse_key, mut keys)), None) => Some(IndexDiscrepancy::Missing {
+                    key: keys.remove(0),
+                    reverse_key,
+                }),
+                (Some((reverse_key, mut keys)), Some((_, found))) => {
+                    let expected = keys.remove(0);
+                    (expected != *found).then(|| IndexDiscrepancy::Mismatched {
+                        reverse_key,
+                        expected,
+                        found: found.clone(),
+                    })
+                }
+                (None, Some((reverse_key, key))) => Some(IndexDiscrepancy::Orphaned {
+                    reverse_key: reverse_key.clone(),
+                    key: key.clone(),
+                }),
+                (None, None) => None,
+            });
+        }
+        discrepancies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_bijection_reports_each_kind() {
+        let mut primary = BPlusTreeMap::new(4).unwrap();
+        let mut reverse = BPlusTreeMap::new(4).unwrap();
+        for i in 0..500u32 {
+            primary.insert(i, i * 10);
+            reverse.insert(i * 10, i);
+        }
+        let project = |_: &u32, v: &u32| *v;
+        assert!(primary.verify_bijection(&reverse, project).is_empty());
+
+        primary.remove(&100); // reverse 1000 -> 100 is orphaned
+        reverse.remove(&2_000); // primary 200 is missing
+        reverse.insert(3_000, 7); // mismatched: should be 300
+        primary.insert(400, 4_010); // collides with primary 401
+        reverse.remove(&4_000); // stale reverse entry for 400 would be orphaned
+
+        assert_eq!(
+            primary.verify_bijection(&reverse, project),
+            vec![
+                IndexDiscrepancy::Orphaned {
+                    reverse_key: 1_000,
+                    key: 100
+                },
+                IndexDiscrepancy::Missing {
+                    key: 200,
+                    reverse_key: 2_000
+                },
+                IndexDiscrepancy::Mismatched {
+                    reverse_key: 3_000,
+                    expected: 300,
+                    found: 7
+                },
+                IndexDiscrepancy::Collision {
+                    reverse_key: 4_010,
+                    keys: vec![400, 401]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_bijection_non_monotonic_projection() {
+        // Reverse keys descend as primary keys ascend
+        let mut primary = BPlusTreeMap::new(4).unwrap();
+        let mut reverse = BPlusTreeMap::new(4).unwrap();
+        for i in 0..300u32 {
+            primary.insert(i, ());
+            reverse.insert(u32::MAX - i, i);
+        }
+        let project = |k: &u32, _: &()| u32::MAX - k;
+        assert!(primary.verify_bijection(&reverse, project).is_empty());
+
+        reverse.insert(u32::MAX - 5, 6);
+        assert_eq!(
+            primary.verify_bijection(&reverse, project),
+            vec![IndexDiscrepancy::Mismatched {
+                reverse_key: u32::MAX - 5,
+                expected: 5,
+                found: 6
+            }]
+        );
+    }
+}
@@ -4,10 +4,15 @@
 //! key-value insertion, node splitting, tree growth, and helper methods for
 //! managing the tree structure during insertions.
 
-use crate::types::{BPlusTreeMap, BranchNode, InsertResult, NodeId, NodeRef, SplitNodeData};
-use std::marker::PhantomData;
-
-impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
+use crate::bounds::{TreeKey, TreeValue};
+use crate::error::{BPlusTreeError, CasError};
+use crate::node::split_off_slots;
+use crate::types::{
+    BPlusTreeMap, BranchNode, InsertResult, NodeId, NodeRef, OverflowMode, SplitNodeData,
+    INLINE_ROOT, MAX_HEIGHT, MAX_LEAF_GROWTH, NULL_NODE,
+};
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
     // allocate_leaf and allocate_branch methods moved to arena.rs module
 
     /// Create a new root node when the current root splits.
@@ -18,46 +23,79 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
 
         // Move the current root to be the left child
         // Use a dummy NodeRef with NULL_NODE to avoid arena allocation
-        let dummy = NodeRef::Leaf(crate::types::NULL_NODE, PhantomData);
+        let dummy = NodeRef::leaf(crate::types::NULL_NODE);
         let old_root = std::mem::replace(&mut self.root, dummy);
 
-        new_root.children.push(old_root);
-        new_root.children.push(new_node);
+        new_root.push_child(old_root);
+        new_root.push_child(new_node);
 
         new_root
     }
 
-    /// Insert into a leaf node by ID.
-    fn insert_into_leaf(&mut self, leaf_id: NodeId, key: K, value: V) -> InsertResult<K, V> {
+    /// Insert into a leaf node by ID. Also returns the leaf and index where
+    /// the entry ended up, which is in the new right leaf if the key falls
+    /// past the split point.
+    fn insert_into_leaf(
+        &mut self,
+        leaf_id: NodeId,
+        key: K,
+        value: V,
+    ) -> (InsertResult<K, V>, Option<(NodeId, usize)>) {
+        let budget = self.byte_budget;
+        let key_limit = self.leaf_key_limit();
         let leaf = match self.get_leaf_mut(leaf_id) {
             Some(leaf) => leaf,
-            None => return InsertResult::Updated(None),
+            None => return (InsertResult::Updated(None), None),
         };
 
         // Do binary search once and use the result throughout
-        match leaf.binary_search_keys(&key) {
+        let (index, replaced, stored_key) = match leaf.binary_search_keys(&key) {
             Ok(index) => {
-                // Key already exists, update the value
-                if let Some(old_val) = leaf.get_value_mut(index) {
-                    let old_value = std::mem::replace(old_val, value);
-                    InsertResult::Updated(Some(old_value))
-                } else {
-                    InsertResult::Updated(None)
+                // A heavier value can take the leaf over its byte budget. Take
+                // the old entry out and add the new one as if the key were new,
+                // so the leaf can split.
+                let regrows = budget.is_some_and(|budget| {
+                    leaf.keys.len() > 1 && budget.replacement_overflows(leaf, index, &value)
+                });
+                if !regrows {
+                    // Key already exists, update the value
+                    return if let Some(old_val) = leaf.get_value_mut(index) {
+                        let old_value = std::mem::replace(old_val, value);
+                        (
+                            InsertResult::Updated(Some(old_value)),
+                            Some((leaf_id, index)),
+                        )
+                    } else {
+                        (InsertResult::Updated(None), None)
+                    };
                 }
-            }
-            Err(index) => {
-                // Key doesn't exist, need to insert
-                // Check if split is needed BEFORE inserting
-                if !leaf.is_full() {
-                    // Room to insert without splitting
-                    leaf.insert_at_index(index, key, value);
-                    // Simple insertion - no split needed
-                    return InsertResult::Updated(None);
+                // The stored key stays, as it does for a plain update
+                match leaf.remove_at(index) {
+                    Some((stored_key, old_value)) => (index, Some(old_value), Some(stored_key)),
+                    None => return (InsertResult::Updated(None), None),
                 }
+            }
+            Err(index) => (index, None, None),
+        };
+        let key = stored_key.unwrap_or(key);
+
+        // Insert as a new entry
+        // Check if split is needed BEFORE inserting
+        let over_budget = budget.is_some_and(|budget| budget.overflows(leaf, &key, &value));
+        if leaf.keys.len() < key_limit && !over_budget {
+            // Room to insert without splitting
+            leaf.insert_at_index(index, key, value);
+            // Simple insertion - no split needed
+            return (InsertResult::Updated(replaced), Some((leaf_id, index)));
+        }
 
-                // Node is full, need to split
-                // Don't insert first. That causes the Vecs to overflow.
+        // Node is full, need to split
+        // Don't insert first. That causes the Vecs to overflow.
 
+        let (mid, insert_left) = match budget {
+            // Split by weight so both halves land well inside the budget
+            Some(budget) => budget.split_point(leaf, index, &key, &value),
+            None => {
                 // Calculate split point for better balance while ensuring both sides have at least min_keys
                 let min_keys = leaf.capacity / 2; // min_keys() inlined
                 let total_keys = leaf.keys.len();
@@ -67,139 +105,105 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
 
                 // Ensure the split point respects minimum requirements
                 let mid = mid.max(min_keys).min(total_keys - min_keys);
+                (mid, index <= mid)
+            }
+        };
 
-                // Split the keys and values
-                let right_keys = leaf.keys.split_off(mid);
-                let right_values = leaf.values.split_off(mid);
-
-                // Store values we need before releasing the leaf borrow
-                let leaf_capacity = leaf.capacity;
-                let leaf_next = leaf.next;
-                let leaf_keys_len = leaf.keys.len();
-
-                // End the leaf borrow scope here
-
-                // Create the new right node - allocate directly in arena to reuse deallocated nodes
-                let new_right_id = self.allocate_leaf_with_data(
-                    leaf_capacity,
-                    right_keys,
-                    right_values,
-                    leaf_next, // Right node takes over the next pointer
-                );
-
-                // Update the linked list first
-                if let Some(leaf) = self.get_leaf_mut(leaf_id) {
-                    leaf.next = new_right_id;
-                    // Then insert into the correct node
-                    if index <= leaf_keys_len {
-                        // Insert into the original (left) leaf
-                        leaf.insert_at_index(index, key, value);
-                    } else {
-                        // Insert into the new (right) leaf
-                        if let Some(new_right) = self.get_leaf_mut(new_right_id) {
-                            new_right.insert_at_index(index - leaf_keys_len, key, value);
-                        }
-                    }
-                }
-
-                // Get the separator key from the newly allocated node
-                let separator_key = self
-                    .get_leaf(new_right_id)
-                    .and_then(|node| node.first_key())
-                    .unwrap()
-                    .clone();
-
-                // Return the already-allocated node ID
-                InsertResult::Split {
-                    old_value: None,
-                    new_node_data: SplitNodeData::AllocatedLeaf(new_right_id),
-                    separator_key,
+        // Split the keys and values
+        let right_keys = split_off_slots(&mut leaf.keys, mid, leaf.capacity);
+        let right_values = split_off_slots(&mut leaf.values, mid, leaf.capacity);
+
+        // Store values we need before releasing the leaf borrow
+        let leaf_capacity = leaf.capacity;
+        let leaf_next = leaf.next;
+        let leaf_keys_len = leaf.keys.len();
+
+        // End the leaf borrow scope here
+
+        // Create the new right node - allocate directly in arena to reuse deallocated nodes
+        let new_right_id = self.allocate_leaf_with_data(
+            leaf_capacity,
+            right_keys,
+            right_values,
+            leaf_next, // Right node takes over the next pointer
+        );
+
+        // Update the linked list first
+        let mut placed = None;
+        if let Some(leaf) = self.get_leaf_mut(leaf_id) {
+            leaf.next = new_right_id;
+            // Then insert into the correct node
+            if insert_left {
+                // Insert into the original (left) leaf
+                leaf.insert_at_index(index, key, value);
+                placed = Some((leaf_id, index));
+            } else {
+                // Insert into the new (right) leaf
+                if let Some(new_right) = self.get_leaf_mut(new_right_id) {
+                    new_right.insert_at_index(index - leaf_keys_len, key, value);
+                    placed = Some((new_right_id, index - leaf_keys_len));
                 }
             }
         }
+
+        self.report_leaf_split(leaf_id, new_right_id);
+
+        // Get the separator key from the newly allocated node
+        let Some(separator_key) = self
+            .get_leaf(new_right_id)
+            .and_then(|node| node.first_key())
+            .cloned()
+        else {
+            return (
+                InsertResult::Error(BPlusTreeError::data_integrity(
+                    "Leaf split",
+                    "new right leaf is empty",
+                )),
+                None,
+            );
+        };
+
+        // Return the already-allocated node ID
+        (
+            InsertResult::Split {
+                old_value: replaced,
+                new_node_data: SplitNodeData::AllocatedLeaf(new_right_id),
+                separator_key,
+            },
+            placed,
+        )
     }
 
-    /// Recursively insert a key with proper arena access.
-    pub fn insert_recursive(
+    /// Give the new right half of a split node an id, allocating it if the
+    /// split returned raw node data. `original` is the node that split; a new
+    /// leaf is linked in after it.
+    fn place_split_node(
         &mut self,
-        node: &NodeRef<K, V>,
-        key: K,
-        value: V,
-    ) -> InsertResult<K, V> {
-        match node {
-            NodeRef::Leaf(id, _) => self.insert_into_leaf(*id, key, value),
-            NodeRef::Branch(id, _) => {
-                let id = *id;
-
-                // First get child info without mutable borrow
-                let (child_index, child_ref) = match self.get_child_for_key(id, &key) {
-                    Some(info) => info,
-                    None => return InsertResult::Updated(None),
-                };
-
-                // Recursively insert
-                let child_result = self.insert_recursive(&child_ref, key, value);
-
-                // Handle the result
-                match child_result {
-                    InsertResult::Updated(old_value) => InsertResult::Updated(old_value),
-                    InsertResult::Error(error) => InsertResult::Error(error),
-                    InsertResult::Split {
-                        old_value,
-                        new_node_data,
-                        separator_key,
-                    } => {
-                        // Allocate the new node based on its type
-                        let new_node = match new_node_data {
-                            SplitNodeData::Leaf(new_leaf_data) => {
-                                let new_id = self.allocate_leaf(new_leaf_data);
-
-                                // Update linked list pointers for leaf splits
-                                if let NodeRef::Leaf(original_id, _) = child_ref {
-                                    if let Some(original_leaf) = self.get_leaf_mut(original_id) {
-                                        original_leaf.next = new_id;
-                                    }
-                                }
-
-                                NodeRef::Leaf(new_id, PhantomData)
-                            }
-                            SplitNodeData::Branch(new_branch_data) => {
-                                let new_id = self.allocate_branch(new_branch_data);
-                                NodeRef::Branch(new_id, PhantomData)
-                            }
-                            SplitNodeData::AllocatedLeaf(new_id) => {
-                                // Node already allocated, just create NodeRef
-                                NodeRef::Leaf(new_id, PhantomData)
-                            }
-                            SplitNodeData::AllocatedBranch(new_id) => {
-                                // Node already allocated, just create NodeRef
-                                NodeRef::Branch(new_id, PhantomData)
-                            }
-                        };
-
-                        // Insert into this branch
-                        match self.get_branch_mut(id).and_then(|branch| {
-                            branch.insert_child_and_split_if_needed(
-                                child_index,
-                                separator_key,
-                                new_node,
-                            )
-                        }) {
-                            Some((new_branch_data, promoted_key)) => {
-                                // This branch split too - return raw branch data
-                                InsertResult::Split {
-                                    old_value,
-                                    new_node_data: SplitNodeData::Branch(new_branch_data),
-                                    separator_key: promoted_key,
-                                }
-                            }
-                            None => {
-                                // No split needed or branch not found
-                                InsertResult::Updated(old_value)
-                            }
-                        }
-                    }
+        new_node_data: SplitNodeData<K, V>,
+        original: NodeId,
+    ) -> NodeRef<K, V> {
+        match new_node_data {
+            SplitNodeData::Leaf(new_leaf_data) => {
+                let new_id = self.allocate_leaf(new_leaf_data);
+
+                // Update linked list pointers for leaf splits
+                if let Some(original_leaf) = self.get_leaf_mut(original) {
+                    original_leaf.next = new_id;
                 }
+
+                NodeRef::leaf(new_id)
+            }
+            SplitNodeData::Branch(new_branch_data) => {
+                let new_id = self.allocate_branch(new_branch_data);
+                NodeRef::branch(new_id)
+            }
+            SplitNodeData::AllocatedLeaf(new_id) => {
+                // Node already allocated, just create NodeRef
+                NodeRef::leaf(new_id)
+            }
+            SplitNodeData::AllocatedBranch(new_id) => {
+                // Node already allocated, just create NodeRef
+                NodeRef::branch(new_id)
             }
         }
     }
@@ -228,10 +232,56 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
     /// assert_eq!(tree.insert(1, "second"), Some("first"));
     /// ```
     pub fn insert(&mut self, key: K, value: V) -> Option<V> {
-        // Use insert_recursive to handle the insertion
-        let result = self.insert_recursive(&self.root.clone(), key, value);
+        // Record the path so splits can be pushed upwards without recursion
+        let mut path = [(NULL_NODE, 0usize); MAX_HEIGHT];
+        let (leaf_id, depth) = self.path_to_leaf(&key, &mut path)?;
+        self.insert_along_path(&path[..depth], leaf_id, key, value)
+            .0
+    }
 
-        match result {
+    /// Insert into `leaf_id`, then push any split up through the branches on
+    /// `path`, as recorded by `path_to_leaf`, growing a new root if needed.
+    ///
+    /// Returns the previous value and the leaf and index where the entry now
+    /// is, or `None` for the position if the leaf was missing.
+    pub(crate) fn insert_along_path(
+        &mut self,
+        path: &[(NodeId, usize)],
+        leaf_id: NodeId,
+        key: K,
+        value: V,
+    ) -> (Option<V>, Option<(NodeId, usize)>) {
+        let (mut result, mut placed) = self.insert_into_leaf(leaf_id, key, value);
+        let mut child_id = leaf_id;
+
+        for &(branch_id, child_index) in path.iter().rev() {
+            let InsertResult::Split {
+                old_value,
+                new_node_data,
+                separator_key,
+            } = result
+            else {
+                break;
+            };
+            let new_node = self.place_split_node(new_node_data, child_id);
+
+            // Insert into this branch
+            result = match self.get_branch_mut(branch_id).and_then(|branch| {
+                branch.insert_child_and_split_if_needed(child_index, separator_key, new_node)
+            }) {
+                // This branch split too - pass raw branch data up
+                Some((new_branch_data, promoted_key)) => InsertResult::Split {
+                    old_value,
+                    new_node_data: SplitNodeData::Branch(new_branch_data),
+                    separator_key: promoted_key,
+                },
+                // No split needed or branch not found
+                None => InsertResult::Updated(old_value),
+            };
+            child_id = branch_id;
+        }
+
+        let old_value = match result {
             InsertResult::Updated(old_value) => old_value,
             InsertResult::Error(_error) => {
                 // Log the error but maintain API compatibility
@@ -245,48 +295,268 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
                 separator_key,
             } => {
                 // Root split - need to create a new root
-                let new_node_ref = match new_node_data {
-                    SplitNodeData::Leaf(new_leaf_data) => {
-                        let new_id = self.allocate_leaf(new_leaf_data);
-
-                        // Update linked list pointers for root leaf split
-                        if let Some(leaf) = matches!(&self.root, NodeRef::Leaf(_, _))
-                            .then(|| self.root.id())
-                            .and_then(|original_id| self.get_leaf_mut(original_id))
-                        {
-                            leaf.next = new_id;
-                        }
-
-                        NodeRef::Leaf(new_id, PhantomData)
-                    }
-                    SplitNodeData::Branch(new_branch_data) => {
-                        let new_id = self.allocate_branch(new_branch_data);
-                        NodeRef::Branch(new_id, PhantomData)
-                    }
-                    SplitNodeData::AllocatedLeaf(new_id) => {
-                        // Node already allocated, just create NodeRef
-                        NodeRef::Leaf(new_id, PhantomData)
-                    }
-                    SplitNodeData::AllocatedBranch(new_id) => {
-                        // Node already allocated, just create NodeRef
-                        NodeRef::Branch(new_id, PhantomData)
-                    }
-                };
+                let new_node_ref = self.place_split_node(new_node_data, child_id);
 
                 // Create new root with the split nodes
+                self.spill_inline_root();
+                // Spilling moved the inline root leaf to an arena id
+                if let (Some((placed_id, _)), NodeRef::Leaf(spilled_id, _)) =
+                    (placed.as_mut(), self.root)
+                {
+                    if *placed_id == INLINE_ROOT {
+                        *placed_id = spilled_id;
+                    }
+                }
                 let new_root = self.new_root(new_node_ref, separator_key);
                 let root_id = self.allocate_branch(new_root);
-                self.root = NodeRef::Branch(root_id, PhantomData);
+                self.root = NodeRef::branch(root_id);
 
                 old_value
             }
+        };
+        (old_value, placed)
+    }
+
+    /// Returns the current overflow mode.
+    pub fn overflow_mode(&self) -> OverflowMode {
+        self.overflow_mode
+    }
+
+    /// Set what an insert does when its leaf is full.
+    ///
+    /// Leaves already grown past capacity stay that size when switching back
+    /// to [`OverflowMode::Split`] until the next [`vacuum`](Self::vacuum).
+    /// Batch operations lay out the leaves they touch at normal size.
+    ///
+    /// # Examples
+    /// ```
+    /// use bplustree::{BPlusTreeMap, OverflowMode};
+    ///
+    /// let mut tree = BPlusTreeMap::new(16).unwrap();
+    /// for i in 0..1_000 {
+    ///     tree.insert(i * 1_000, i);
+    /// }
+    /// let leaves = tree.leaf_count();
+    ///
+    /// // A burst into one small range grows the leaf it lands in, to up
+    /// // to 5 * 16 keys, instead of splitting it
+    /// tree.set_overflow_mode(OverflowMode::GrowLeaves { factor: 5 });
+    /// for i in 1..50 {
+    ///     tree.insert(500_000 + i, i);
+    /// }
+    /// assert_eq!(tree.leaf_count(), leaves);
+    /// # #[cfg(feature = "validation")]
+    /// assert!(tree.check_invariants());
+    ///
+    /// // Vacuum splits the grown leaf back into leaves of normal size
+    /// tree.vacuum();
+    /// assert!(tree.leaf_count() > leaves + 1);
+    /// ```
+    pub fn set_overflow_mode(&mut self, mode: OverflowMode) {
+        self.overflow_mode = mode;
+    }
+
+    /// Most keys a leaf may hold before an insert splits it.
+    pub(crate) fn leaf_key_limit(&self) -> usize {
+        match self.overflow_mode {
+            OverflowMode::Split => self.capacity,
+            OverflowMode::GrowLeaves { factor } => self.capacity * factor.clamp(1, MAX_LEAF_GROWTH),
         }
     }
+
+    /// Prepare for `n` new keys that are about to be inserted.
+    ///
+    /// Inserts that split leaves and branches allocate new nodes, and when the
+    /// node arenas run out of room they grow by reallocating and moving every
+    /// node, which shows up as a latency spike in the middle of a burst. This
+    /// reserves arena space for the nodes that `n` inserts can create in the
+    /// worst case, where every split leaves nodes half full, so a burst such as
+    /// loading a partition proceeds without arena reallocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(64).unwrap();
+    /// tree.reserve_keys_hint(10_000);
+    /// for i in 0..10_000 {
+    ///     tree.insert(i, i);
+    /// }
+    /// assert_eq!(tree.len(), 10_000);
+    /// ```
+    pub fn reserve_keys_hint(&mut self, n: usize) {
+        // Every split creates one node holding at least half a node's worth
+        self.reserve_nodes_for(n, self.capacity / 2);
+    }
+
+    /// Reserve arena space for the nodes that hold `n` keys when each node
+    /// holds `per_node` of them.
+    pub(crate) fn reserve_nodes_for(&mut self, n: usize, per_node: usize) {
+        let per_node = per_node.max(1);
+        let leaves = n.div_ceil(per_node);
+        let mut branches = 0;
+        let mut level = leaves;
+        while level > 1 {
+            level = level.div_ceil(per_node);
+            branches += level;
+        }
+        self.leaf_arena.reserve(leaves);
+        self.branch_arena.reserve(branches + 1);
+    }
+
+    /// Combine `value` into the entry for `key`, inserting it if the key is new.
+    ///
+    /// When the key exists, `merge` is called with the stored value and
+    /// `value` and may update the stored value in place. Existing keys and
+    /// inserts into a leaf with spare room are handled after a single descent;
+    /// only an insert that splits a leaf falls back to [`insert`].
+    ///
+    /// [`insert`]: BPlusTreeMap::insert
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut counts = BPlusTreeMap::new(16).unwrap();
+    /// for word in ["a", "b", "a", "a"] {
+    ///     counts.merge_value(word, 1, |count, one| *count += one);
+    /// }
+    /// assert_eq!(counts.get(&"a"), Some(&3));
+    /// assert_eq!(counts.get(&"b"), Some(&1));
+    /// ```
+    pub fn merge_value<F>(&mut self, key: K, value: V, merge: F)
+    where
+        F: FnOnce(&mut V, V),
+    {
+        let (leaf_id, index, matched) = match self.find_leaf_for_key_with_match(&key) {
+            Some(found) => found,
+            None => return,
+        };
+        let budget = self.byte_budget;
+        let leaf = match self.get_leaf_mut(leaf_id) {
+            Some(leaf) => leaf,
+            None => return,
+        };
+
+        if matched {
+            if let Some(existing) = leaf.get_value_mut(index) {
+                merge(existing, value);
+            }
+        } else if !leaf.is_full()
+            && !budget.is_some_and(|budget| budget.overflows(leaf, &key, &value))
+        {
+            // Fits without a split, so no ancestor needs to change
+            leaf.insert_at_index(index, key, value);
+        } else {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: TreeKey, V: TreeValue + PartialEq> BPlusTreeMap<K, V> {
+    /// Replace the value for `key` with `new` only if it currently equals
+    /// `expected`.
+    ///
+    /// The comparison and the write happen on the same leaf after a single
+    /// descent, so there is no window between reading the value and writing
+    /// it back. A concurrent map can build an atomic compare-and-swap on the
+    /// same shape. On failure the tree is unchanged and the error carries
+    /// `new` back, along with the current value if the key exists.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::{BPlusTreeMap, CasError};
+    ///
+    /// let mut versions = BPlusTreeMap::new(16).unwrap();
+    /// versions.insert("doc", 1);
+    ///
+    /// assert_eq!(versions.compare_and_update(&"doc", &1, 2), Ok(()));
+    /// assert_eq!(
+    ///     versions.compare_and_update(&"doc", &1, 3),
+    ///     Err(CasError::Mismatch { current: 2, new: 3 })
+    /// );
+    /// assert_eq!(
+    ///     versions.compare_and_update(&"other", &1, 3),
+    ///     Err(CasError::KeyNotFound { new: 3 })
+    /// );
+    /// ```
+    pub fn compare_and_update(&mut self, key: &K, expected: &V, new: V) -> Result<(), CasError<V>> {
+        let budget = self.byte_budget;
+        let found = self
+            .find_leaf_for_key_with_match(key)
+            .filter(|&(_, _, matched)| matched)
+            .and_then(|(leaf_id, index, _)| Some((self.get_leaf_mut(leaf_id)?, index)));
+        let Some((leaf, index)) = found else {
+            return Err(CasError::KeyNotFound { new });
+        };
+        let Some(current) = leaf.values.get(index) else {
+            return Err(CasError::KeyNotFound { new });
+        };
+        if current != expected {
+            return Err(CasError::Mismatch {
+                current: current.clone(),
+                new,
+            });
+        }
+
+        if budget.is_some_and(|budget| budget.replacement_overflows(leaf, index, &new)) {
+            // The heavier value may need its leaf split; insert keeps the stored key
+            self.insert(key.clone(), new);
+        } else {
+            leaf.values[index] = new;
+        }
+        Ok(())
+    }
+}
+
+impl<K: Ord + Clone, T: Clone> BPlusTreeMap<K, Vec<T>> {
+    /// Push `item` onto the `Vec` stored under `key`, creating it if needed.
+    ///
+    /// This supports using the tree as a multimap without a separate lookup
+    /// and insert for every item.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut index = BPlusTreeMap::new(16).unwrap();
+    /// index.insert_or_append("fruit", "apple");
+    /// index.insert_or_append("fruit", "pear");
+    /// index.insert_or_append("veg", "leek");
+    /// assert_eq!(index.get(&"fruit"), Some(&vec!["apple", "pear"]));
+    /// ```
+    pub fn insert_or_append(&mut self, key: K, item: T) {
+        // KeepAll never refuses, so there is nothing to hand back
+        let _ = self.insert_with_policy::<crate::KeepAll>(key, vec![item]);
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::BPlusTreeMap;
+    use crate::{BPlusTreeMap, ByteBudget, CasError};
+
+    #[test]
+    fn test_reserve_keys_hint_avoids_arena_growth() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..100 {
+            tree.insert(i * 1_000, i);
+        }
+        tree.reserve_keys_hint(5_000);
+        let leaf_capacity = tree.leaf_arena.capacity();
+        let branch_capacity = tree.branch_arena.capacity();
+
+        // A burst landing inside one region of the existing key space
+        for i in 0..5_000 {
+            tree.insert(40_000 + i, i);
+        }
+        assert_eq!(tree.leaf_arena.capacity(), leaf_capacity);
+        assert_eq!(tree.branch_arena.capacity(), branch_capacity);
+        assert!(tree.check_invariants());
+    }
 
     #[test]
     fn test_insert_operations_module_exists() {
@@ -295,4 +565,85 @@ mod tests {
         assert_eq!(tree.insert(1, 10), None);
         assert_eq!(tree.insert(1, 20), Some(10));
     }
+
+    #[test]
+    fn test_insert_or_append_matches_reference_multimap() {
+        use std::collections::BTreeMap;
+
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        let mut reference: BTreeMap<i32, Vec<i32>> = BTreeMap::new();
+        for i in 0..500 {
+            let key = (i * 37) % 101;
+            tree.insert_or_append(key, i);
+            reference.entry(key).or_default().push(i);
+        }
+
+        assert!(tree.check_invariants());
+        assert_eq!(tree.len(), reference.len());
+        for (key, items) in &reference {
+            assert_eq!(tree.get(key), Some(items));
+        }
+    }
+
+    #[test]
+    fn test_merge_value_splits_full_leaves() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..200 {
+            tree.merge_value(i % 50, 1, |count, one| *count += one);
+        }
+
+        assert!(tree.check_invariants());
+        assert_eq!(tree.len(), 50);
+        assert!(tree.values().all(|&count| count == 4));
+    }
+
+    #[test]
+    fn test_compare_and_update_checks_before_writing() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..100 {
+            tree.insert(i, i);
+        }
+
+        for i in 0..100 {
+            assert_eq!(tree.compare_and_update(&i, &i, i + 1), Ok(()));
+        }
+        for i in 0..100 {
+            assert_eq!(
+                tree.compare_and_update(&i, &i, 0),
+                Err(CasError::Mismatch {
+                    current: i + 1,
+                    new: 0
+                })
+            );
+        }
+        assert_eq!(
+            tree.compare_and_update(&100, &0, 7),
+            Err(CasError::KeyNotFound { new: 7 })
+        );
+        assert!(tree.items().all(|(k, v)| *v == k + 1));
+    }
+
+    #[test]
+    fn test_compare_and_update_respects_byte_budget() {
+        let mut tree = BPlusTreeMap::new(16).unwrap();
+        tree.set_byte_budget(Some(ByteBudget::new(100, |_: &u32, v: &String| v.len())));
+        for i in 0..20 {
+            tree.insert(i, "x".repeat(10));
+        }
+
+        let heavy = "y".repeat(60);
+        for i in [3, 11] {
+            assert_eq!(
+                tree.compare_and_update(&i, &"x".repeat(10), heavy.clone()),
+                Ok(())
+            );
+        }
+        assert_eq!(tree.get(&11), Some(&heavy));
+        assert!(tree
+            .leaf_byte_sizes()
+            .unwrap()
+            .iter()
+            .all(|&bytes| bytes <= 100));
+        assert!(tree.check_invariants());
+    }
 }
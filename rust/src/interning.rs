@@ -0,0 +1,186 @@
+//! Key interning for trees with repeated string keys.
+//!
+//! Trees keyed by `String` store a separate allocation for every leaf key and
+//! every separator cloned into a branch. When many trees share the same key
+//! universe, interning the keys through a [`KeyInterner`] stores each distinct
+//! string once; leaves and branches hold [`InternedKey`] handles whose clones
+//! are reference-count bumps.
+
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// A shared handle to an interned string key.
+///
+/// Compares, orders and hashes by string content, so a tree keyed by
+/// `InternedKey` iterates in the same order as one keyed by `String`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InternedKey(Arc<str>);
+
+impl InternedKey {
+    /// Returns the interned string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns true if both handles point at the same interned allocation.
+    pub fn ptr_eq(&self, other: &InternedKey) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Deref for InternedKey {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for InternedKey {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for InternedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for InternedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+/// Deduplicates string keys so that trees sharing a key universe share storage.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::{BPlusTreeMap, KeyInterner};
+///
+/// let mut interner = KeyInterner::new();
+/// let mut prices = BPlusTreeMap::new(16).unwrap();
+/// let mut stock = BPlusTreeMap::new(16).unwrap();
+///
+/// for name in ["apple", "pear", "plum"] {
+///     prices.insert(interner.intern(name), 1.5);
+///     stock.insert(interner.intern(name), 10);
+/// }
+///
+/// // Both trees hold handles to the same three strings
+/// assert_eq!(interner.len(), 3);
+/// let pear = interner.get("pear").unwrap();
+/// assert_eq!(prices.get(&pear), Some(&1.5));
+/// assert_eq!(stock.get(&pear), Some(&10));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct KeyInterner {
+    keys: HashSet<Arc<str>>,
+}
+
+impl KeyInterner {
+    /// Create an empty interner.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the handle for `key`, interning it on first use.
+    pub fn intern(&mut self, key: &str) -> InternedKey {
+        if let Some(existing) = self.keys.get(key) {
+            return InternedKey(Arc::clone(existing));
+        }
+        let interned: Arc<str> = Arc::from(key);
+        self.keys.insert(Arc::clone(&interned));
+        InternedKey(interned)
+    }
+
+    /// Return the handle for `key` if it has already been interned.
+    ///
+    /// Use this for lookups so that querying an unknown key does not grow the
+    /// interner.
+    pub fn get(&self, key: &str) -> Option<InternedKey> {
+        self.keys.get(key).map(|k| InternedKey(Arc::clone(k)))
+    }
+
+    /// Number of distinct interned keys.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns true if nothing has been interned.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Drop interned keys that are no longer referenced by any handle.
+    ///
+    /// Returns the number of keys released.
+    pub fn purge_unused(&mut self) -> usize {
+        let before = self.keys.len();
+        self.keys.retain(|key| Arc::strong_count(key) > 1);
+        before - self.keys.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BPlusTreeMap;
+
+    #[test]
+    fn test_intern_returns_shared_handles() {
+        let mut interner = KeyInterner::new();
+        let a = interner.intern("alpha");
+        let b = interner.intern("alpha");
+        let c = interner.intern("beta");
+
+        assert!(a.ptr_eq(&b));
+        assert!(!a.ptr_eq(&c));
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.get("gamma"), None);
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn test_tree_keys_share_interned_storage() {
+        let mut interner = KeyInterner::new();
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..200 {
+            let name = format!("key{:03}", i % 50);
+            tree.insert(interner.intern(&name), i);
+        }
+
+        assert_eq!(interner.len(), 50);
+        assert_eq!(tree.len(), 50);
+        assert!(tree.check_invariants());
+
+        // Leaf keys and separators are handles to the interned strings
+        let key = interner.get("key007").unwrap();
+        assert_eq!(tree.get(&key), Some(&157));
+        assert!(tree
+            .keys()
+            .all(|k| k.ptr_eq(&interner.get(k.as_str()).unwrap())));
+
+        let names: Vec<_> = tree.keys().map(|k| k.to_string()).collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names, sorted);
+    }
+
+    #[test]
+    fn test_purge_unused() {
+        let mut interner = KeyInterner::new();
+        let kept = interner.intern("kept");
+        interner.intern("dropped");
+
+        assert_eq!(interner.purge_unused(), 1);
+        assert_eq!(interner.len(), 1);
+        assert!(interner.get("kept").unwrap().ptr_eq(&kept));
+    }
+}
@@ -0,0 +1,54 @@
+//! What the tree does when it finds one of its own invariants broken.
+//!
+//! Almost every internal inconsistency is reported as a
+//! [`CorruptionError`](crate::CorruptionError) or skipped over, but a few
+//! operations have no error to return. [`OccupiedEntry::get`] relies on the
+//! entry it found still being there under the same mutable borrow, and
+//! allocating a node relies on arena ids not running out. By default a broken
+//! invariant there panics with a message.
+//!
+//! The `panic-free` feature is for environments that must show the library
+//! cannot panic. With it, clippy denies `unwrap`, `expect`, `panic!` and
+//! `unreachable!` across the crate, and a broken invariant below or running
+//! out of node ids aborts the process the way a failed allocation does.
+//!
+//! [`OccupiedEntry::get`]: crate::OccupiedEntry::get
+
+/// Unwrap a value that the tree's invariants guarantee is present.
+///
+/// Callers pass values that are `Some` whenever the tree's own code is
+/// correct and the key type's `Ord` keeps its contract, such as an entry
+/// looked up earlier under the same borrow of the tree. A `None` panics, or
+/// aborts with `panic-free`; it is never undefined behaviour.
+#[inline(always)]
+#[track_caller]
+pub(crate) fn invariant<T>(value: Option<T>, what: &'static str) -> T {
+    #[cfg(not(feature = "panic-free"))]
+    {
+        value.expect(what)
+    }
+    #[cfg(feature = "panic-free")]
+    {
+        let _ = what;
+        match value {
+            Some(value) => value,
+            None => std::process::abort(),
+        }
+    }
+}
+
+/// Stop on running out of a resource the tree has no way to report, such as
+/// node ids.
+#[cold]
+#[inline(never)]
+pub(crate) fn exhausted(what: &'static str) -> ! {
+    #[cfg(not(feature = "panic-free"))]
+    {
+        panic!("{}", what)
+    }
+    #[cfg(feature = "panic-free")]
+    {
+        let _ = what;
+        std::process::abort()
+    }
+}
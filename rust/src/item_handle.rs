@@ -0,0 +1,171 @@
+//! Handles for re-reading an entry without a search.
+//!
+//! A caller that keeps coming back to the same entries, such as an index
+//! row pointing into a table or a scheduler holding its current jobs, pays
+//! for a descent from the root on every read. An [`ItemHandle`] records
+//! where an entry was found: its leaf, its slot in that leaf, and the
+//! tree's generation at the time. Reading through the handle goes straight
+//! to the slot while the generation is unchanged. Any change that may move
+//! entries bumps the generation, after which the handle is reported stale
+//! and the caller looks the key up again.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::error::{BPlusTreeError, KeyResult, QueryError};
+use crate::types::{BPlusTreeMap, NodeId};
+
+/// The position of an entry as of one generation of the tree that issued
+/// it. Returned by [`BPlusTreeMap::get_with_handle`].
+///
+/// A handle is a plain `#[repr(C)]` value of fixed size with no lifetime,
+/// so it can be stored in other structures, copied freely, or passed across
+/// an FFI boundary. It is only meaningful to the tree that issued it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct ItemHandle {
+    leaf: NodeId,
+    slot: u32,
+    generation: u64,
+}
+
+impl ItemHandle {
+    /// The tree generation the handle was issued in.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Get the value for `key` together with a handle for reading the entry
+    /// again without a search.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..100 {
+    ///     tree.insert(i, i * 10);
+    /// }
+    ///
+    /// let (value, handle) = tree.get_with_handle(&42).unwrap();
+    /// assert_eq!(*value, 420);
+    /// assert_eq!(tree.get_by_handle(handle), Some((&42, &420)));
+    ///
+    /// // Any change to the leaves makes the handle stale
+    /// tree.insert(1_000, 0);
+    /// assert_eq!(tree.get_by_handle(handle), None);
+    /// assert!(tree.try_get_by_handle(handle).is_err());
+    /// ```
+    pub fn get_with_handle(&self, key: &K) -> Option<(&V, ItemHandle)> {
+        let (leaf_id, index, true) = self.find_leaf_for_key_with_match(key)? else {
+            return None;
+        };
+        let value = self.get_leaf(leaf_id)?.values.get(index)?;
+        let handle = ItemHandle {
+            leaf: leaf_id,
+            slot: u32::try_from(index).ok()?,
+            generation: self.generation,
+        };
+        Some((value, handle))
+    }
+
+    /// The entry `handle` points at, or `None` if the tree has changed since
+    /// the handle was issued.
+    pub fn get_by_handle(&self, handle: ItemHandle) -> Option<(&K, &V)> {
+        self.try_get_by_handle(handle).ok()
+    }
+
+    /// The entry `handle` points at.
+    ///
+    /// Fails with [`QueryError::InvalidState`] if the tree has changed since
+    /// the handle was issued, and with [`QueryError::KeyNotFound`] if the
+    /// handle does not point at an entry of this tree.
+    ///
+    /// [`QueryError::InvalidState`]: crate::QueryError::InvalidState
+    /// [`QueryError::KeyNotFound`]: crate::QueryError::KeyNotFound
+    pub fn try_get_by_handle(&self, handle: ItemHandle) -> KeyResult<(&K, &V)> {
+        if handle.generation != self.generation {
+            return Err(BPlusTreeError::invalid_state(
+                "read by handle",
+                "tree changed since the handle was issued",
+            ));
+        }
+        let leaf = self
+            .get_leaf(handle.leaf)
+            .ok_or(BPlusTreeError::Query(QueryError::KeyNotFound))?;
+        let slot = handle.slot as usize;
+        match (leaf.keys.get(slot), leaf.values.get(slot)) {
+            (Some(key), Some(value)) => Ok((key, value)),
+            _ => Err(BPlusTreeError::Query(QueryError::KeyNotFound)),
+        }
+    }
+
+    /// Mark every handle issued so far as stale.
+    #[inline]
+    pub(crate) fn bump_generation(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handles_read_entries_until_the_tree_changes() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..500u32 {
+            tree.insert(i, i * 2);
+        }
+
+        let handles: Vec<_> = (0..500)
+            .step_by(7)
+            .map(|i| tree.get_with_handle(&i).unwrap().1)
+            .collect();
+        for (handle, i) in handles.iter().zip((0..500).step_by(7)) {
+            assert_eq!(tree.get_by_handle(*handle), Some((&i, &(i * 2))));
+        }
+        assert_eq!(tree.get_with_handle(&1_000), None);
+
+        // Reads leave handles valid
+        assert_eq!(tree.range(10..20).count(), 10);
+        assert!(tree.get_by_handle(handles[3]).is_some());
+
+        let changes: [fn(&mut BPlusTreeMap<u32, u32>); 4] = [
+            |tree| {
+                tree.insert(10_000, 0);
+            },
+            |tree| {
+                tree.remove(&3);
+            },
+            |tree| {
+                *tree.get_mut(&14).unwrap() += 1;
+            },
+            |tree| tree.clear(),
+        ];
+        for change in changes {
+            let (_, handle) = tree.get_with_handle(&14).unwrap();
+            change(&mut tree);
+            assert_eq!(tree.get_by_handle(handle), None);
+            assert!(matches!(
+                tree.try_get_by_handle(handle),
+                Err(BPlusTreeError::Query(crate::QueryError::InvalidState(_)))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_handle_to_inline_root_goes_stale_when_it_spills() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        tree.insert(1, "one");
+        let (_, handle) = tree.get_with_handle(&1).unwrap();
+        assert_eq!(tree.get_by_handle(handle), Some((&1, &"one")));
+        for i in 2..10 {
+            tree.insert(i, "more");
+        }
+        assert_eq!(tree.get_by_handle(handle), None);
+        let (_, handle) = tree.get_with_handle(&1).unwrap();
+        assert_eq!(tree.get_by_handle(handle), Some((&1, &"one")));
+    }
+}
@@ -1,16 +1,30 @@
 //! Iterator implementations for BPlusTreeMap.
 //!
 //! This module contains all iterator types and their implementations for the B+ tree,
-//! including basic iteration, range iteration, and optimized fast iteration.
+//! including basic iteration and range iteration. Every iterator caches a reference
+//! to its current leaf, so the arena is only consulted when moving to the next leaf.
+//! The item, key, value and range iterators are double-ended: their back end walks
+//! the tree from the right without a backward link between leaves.
 
-use crate::types::{BPlusTreeMap, LeafNode, NodeId, NULL_NODE};
+use crate::bounds::{TreeKey, TreeValue};
+use crate::compact_arena::CompactArena;
+use crate::error::BPlusTreeError;
+use crate::types::{
+    BPlusTreeMap, BranchNode, LeafNode, NodeId, NodeRef, NodeVec, INLINE_ROOT, NULL_NODE,
+};
+use std::collections::VecDeque;
+use std::iter::FusedIterator;
 use std::ops::Bound;
+use std::ptr;
 
 // ============================================================================
 // ITERATOR STRUCTS
 // ============================================================================
 
 /// Iterator over key-value pairs in the B+ tree using the leaf linked list.
+///
+/// The end bound is compared against each leaf's last key once; when the whole
+/// leaf is in range its items are returned without per-item comparisons.
 pub struct ItemIterator<'a, K, V> {
     tree: &'a BPlusTreeMap<K, V>,
     current_leaf_id: Option<NodeId>,
@@ -19,25 +33,101 @@ pub struct ItemIterator<'a, K, V> {
     end_key: Option<&'a K>,
     end_bound_key: Option<K>,
     end_inclusive: bool,
+    leaf_bound_checked: bool,
+    unchecked_until: usize, // items below this index are known to be in range
+    back: Option<BackCursor<'a, K, V>>,
 }
 
-/// Fast iterator over key-value pairs using unsafe arena access for better performance.
-pub struct FastItemIterator<'a, K, V> {
+/// Former name of [`ItemIterator`], from when the fast path was a separate type.
+#[deprecated(
+    since = "0.9.0",
+    note = "use `ItemIterator`, which takes the fast path automatically"
+)]
+pub type FastItemIterator<'a, K, V> = ItemIterator<'a, K, V>;
+
+/// Iterator over keys in the B+ tree, walking each leaf's key slice directly.
+pub struct KeyIterator<'a, K, V> {
     tree: &'a BPlusTreeMap<K, V>,
-    current_leaf_id: Option<NodeId>,
     pub current_leaf_ref: Option<&'a LeafNode<K, V>>, // CACHED leaf reference
-    current_leaf_index: usize,
-    finished: bool,
+    keys: std::slice::Iter<'a, K>,
+    back: Option<(BackCursor<'a, K, V>, std::slice::Iter<'a, K>)>,
 }
 
-/// Iterator over keys in the B+ tree.
-pub struct KeyIterator<'a, K, V> {
-    items: ItemIterator<'a, K, V>,
+/// Iterator over values in the B+ tree, walking each leaf's value slice directly.
+pub struct ValueIterator<'a, K, V> {
+    tree: &'a BPlusTreeMap<K, V>,
+    pub current_leaf_ref: Option<&'a LeafNode<K, V>>, // CACHED leaf reference
+    values: std::slice::Iter<'a, V>,
+    back: Option<(BackCursor<'a, K, V>, std::slice::Iter<'a, V>)>,
 }
 
-/// Iterator over values in the B+ tree.
-pub struct ValueIterator<'a, K, V> {
-    items: ItemIterator<'a, K, V>,
+/// Iterator over key-value pairs in the B+ tree with mutable access to the
+/// values, following the leaf chain.
+///
+/// The leaves are borrowed from the arena all at once when the iterator is
+/// created and handed out one at a time along the chain, so no leaf is
+/// reachable twice and no unsafe code is needed.
+pub struct ItemIteratorMut<'a, K, V> {
+    leaves: Vec<Option<&'a mut LeafNode<K, V>>>,
+    next_id: NodeId,
+    entries: std::iter::Zip<std::slice::Iter<'a, K>, std::slice::IterMut<'a, V>>,
+}
+
+/// Iterator over mutable references to the values in the B+ tree, in key
+/// order.
+pub struct ValueIteratorMut<'a, K, V> {
+    items: ItemIteratorMut<'a, K, V>,
+}
+
+/// Owning iterator over the key-value pairs of a B+ tree in sorted order,
+/// returned by `into_iter` on the tree.
+///
+/// Branches are dropped when iteration starts. Each leaf is taken out of the
+/// arena and its slot freed when the iterator reaches it; entries not yet
+/// reached are dropped with the iterator.
+pub struct IntoIter<K, V> {
+    leaves: CompactArena<LeafNode<K, V>>,
+    next_id: NodeId,
+    keys: <NodeVec<K> as IntoIterator>::IntoIter,
+    values: <NodeVec<V> as IntoIterator>::IntoIter,
+    remaining: usize,
+}
+
+/// Iterator over the entries of each leaf in turn, as parallel key and value
+/// slices. Leaves left empty by deferred rebalancing are skipped.
+pub struct LeafGroupIterator<'a, K, V> {
+    tree: &'a BPlusTreeMap<K, V>,
+    pub current_leaf_ref: Option<&'a LeafNode<K, V>>, // CACHED leaf reference
+}
+
+/// Iterator over every leaf with its arena id, following the leaf chain from
+/// the first leaf. Unlike [`LeafGroupIterator`], leaves left empty by
+/// deferred rebalancing are included.
+pub struct LeafIterator<'a, K, V> {
+    tree: &'a BPlusTreeMap<K, V>,
+    next_id: NodeId,
+}
+
+/// Iterator over every branch with its arena id, level by level from the
+/// root, left to right within a level.
+pub struct BranchIterator<'a, K, V> {
+    tree: &'a BPlusTreeMap<K, V>,
+    queue: VecDeque<NodeId>,
+}
+
+/// The back end of a double-ended iterator, created by the first call to
+/// `next_back`.
+///
+/// Leaves are only linked forwards, so the cursor keeps the branches above
+/// its leaf with the child taken in each. The previous leaf is found by
+/// climbing to the nearest branch with a child further left and descending
+/// that child's rightmost edge, which over a whole scan is constant work per
+/// leaf.
+struct BackCursor<'a, K, V> {
+    path: Vec<(NodeId, usize)>,
+    leaf: &'a LeafNode<K, V>,
+    /// Entries of `leaf` from this index on are yielded or out of range.
+    end: usize,
 }
 
 /// Optimized iterator over a range of key-value pairs in the B+ tree.
@@ -48,24 +138,47 @@ pub struct RangeIterator<'a, K, V> {
     first_key: Option<K>,
 }
 
+/// Iterator over key-value pairs that reports a broken leaf chain instead of
+/// stopping quietly. Returned by [`BPlusTreeMap::try_items`] and
+/// [`BPlusTreeMap::try_range`].
+///
+/// After yielding an `Err` the iterator is finished.
+pub struct TryItemIterator<'a, K, V> {
+    items: Option<RangeIterator<'a, K, V>>,
+    pending: Option<BPlusTreeError>,
+}
+
 // ============================================================================
 // BPLUSTREE ITERATOR METHODS
 // ============================================================================
 
-impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
     /// Returns an iterator over all key-value pairs in sorted order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..20 {
+    ///     tree.insert(i, i * 10);
+    /// }
+    /// let newest: Vec<_> = tree.items().rev().take(2).collect();
+    /// assert_eq!(newest, [(&19, &190), (&18, &180)]);
+    /// assert_eq!(tree.range(5..10).next_back(), Some((&9, &90)));
+    /// ```
     pub fn items(&self) -> ItemIterator<'_, K, V> {
         ItemIterator::new(self)
     }
 
-    /// Returns a fast iterator over all key-value pairs using unsafe arena access.
-    /// This provides better performance by skipping bounds checks.
-    ///
-    /// # Safety
-    /// This is safe to use as long as the tree structure is valid and no concurrent
-    /// modifications occur during iteration.
-    pub fn items_fast(&self) -> FastItemIterator<'_, K, V> {
-        FastItemIterator::new(self)
+    /// Returns an iterator over all key-value pairs in sorted order.
+    #[deprecated(
+        since = "0.9.0",
+        note = "use `items`, which takes the fast path automatically"
+    )]
+    pub fn items_fast(&self) -> ItemIterator<'_, K, V> {
+        ItemIterator::new(self)
     }
 
     /// Returns an iterator over all keys in sorted order.
@@ -78,9 +191,180 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
         ValueIterator::new(self)
     }
 
+    /// Returns an iterator over all key-value pairs in sorted order, with
+    /// mutable access to the values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..20 {
+    ///     tree.insert(i, i);
+    /// }
+    /// for (key, value) in tree.iter_mut() {
+    ///     *value += key * 10;
+    /// }
+    /// assert_eq!(tree.get(&7), Some(&77));
+    /// ```
+    pub fn iter_mut(&mut self) -> ItemIteratorMut<'_, K, V> {
+        ItemIteratorMut::new(self)
+    }
+
+    /// Returns an iterator over mutable references to all values in key
+    /// order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..20 {
+    ///     tree.insert(i, i);
+    /// }
+    /// tree.values_mut().for_each(|value| *value *= 2);
+    /// assert_eq!(tree.values().sum::<i32>(), 380);
+    /// ```
+    pub fn values_mut(&mut self) -> ValueIteratorMut<'_, K, V> {
+        ValueIteratorMut {
+            items: ItemIteratorMut::new(self),
+        }
+    }
+
+    /// Returns an iterator over the tree's leaves in key order, yielding each
+    /// leaf's keys and values as a pair of slices.
+    ///
+    /// Groups follow the physical layout, so each one is a contiguous,
+    /// cache-friendly chunk of at most `capacity` entries. This suits batch
+    /// consumers that want natural work units, such as writing to another
+    /// store a chunk at a time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(8).unwrap();
+    /// for i in 0..100 {
+    ///     tree.insert(i, i * 10);
+    /// }
+    ///
+    /// let mut total = 0;
+    /// for (keys, values) in tree.group_by_leaf() {
+    ///     assert!(keys.len() <= 8);
+    ///     assert_eq!(keys.len(), values.len());
+    ///     total += keys.len();
+    /// }
+    /// assert_eq!(total, 100);
+    /// ```
+    pub fn group_by_leaf(&self) -> LeafGroupIterator<'_, K, V> {
+        LeafGroupIterator::new(self)
+    }
+
+    /// Returns an iterator over every leaf and its id, in key order.
+    ///
+    /// This is a read-only view of the tree's layout for tooling such as
+    /// backups, external validators or graph exporters; the ids match the
+    /// ones taken by [`get_leaf`](Self::get_leaf). The layout itself is not
+    /// stable across mutations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..50 {
+    ///     tree.insert(i, i);
+    /// }
+    ///
+    /// let mut previous_last = None;
+    /// for (id, leaf) in tree.leaves() {
+    ///     assert!(std::ptr::eq(tree.get_leaf(id).unwrap(), leaf));
+    ///     assert!(previous_last < leaf.first_key());
+    ///     previous_last = leaf.last_key();
+    /// }
+    /// assert_eq!(tree.leaves().count(), tree.leaf_count());
+    /// ```
+    pub fn leaves(&self) -> LeafIterator<'_, K, V> {
+        LeafIterator::new(self)
+    }
+
+    /// Returns an iterator over every branch and its id, breadth first from
+    /// the root. Empty when the root is a leaf.
+    ///
+    /// Each level is yielded left to right before the next one starts, so a
+    /// caller can rebuild the shape of the tree from the child counts alone.
+    pub fn branches(&self) -> BranchIterator<'_, K, V> {
+        BranchIterator::new(self)
+    }
+
+    /// Returns the keys and values of every non-empty leaf as slices into
+    /// the tree's own storage, in key order.
+    ///
+    /// This is the collected form of [`group_by_leaf`](Self::group_by_leaf),
+    /// for handing the whole map to a compute kernel without copying it or
+    /// going through a per-item iterator. Concatenating the key slices gives
+    /// every key in ascending order, and `values[i]` belongs to `keys[i]`
+    /// within each pair.
+    ///
+    /// The slices borrow the tree, so it cannot be changed while any of them
+    /// is alive. Apart from that guarantee, the layout is an implementation
+    /// detail. How many slices there are, and where one ends and the next
+    /// begins, depends on the capacity and on the history of inserts and
+    /// removals, and may change with any mutation. Fetch a fresh set after
+    /// changing the tree rather than keeping positions from an earlier one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(16).unwrap();
+    /// for i in 0..1_000u64 {
+    ///     tree.insert(i, i as f64);
+    /// }
+    ///
+    /// // Sum each leaf's values as one contiguous slice
+    /// let slices = tree.leaf_slices();
+    /// let total: f64 = slices.iter().map(|(_, values)| values.iter().sum::<f64>()).sum();
+    /// assert_eq!(total, (0..1_000).sum::<u64>() as f64);
+    /// assert_eq!(slices.iter().map(|(keys, _)| keys.len()).sum::<usize>(), tree.len());
+    /// ```
+    pub fn leaf_slices(&self) -> Vec<(&[K], &[V])> {
+        self.group_by_leaf().collect()
+    }
+
+    /// Returns an iterator over all key-value pairs in sorted order that
+    /// yields an error, rather than ending early, if a leaf is missing.
+    ///
+    /// [`items`](Self::items) cannot tell the end of the data from a broken
+    /// leaf chain; use this where corruption must not go unnoticed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..20 {
+    ///     tree.insert(i, i * 10);
+    /// }
+    /// let items: Result<Vec<_>, _> = tree.try_items().collect();
+    /// assert_eq!(items.unwrap().len(), 20);
+    /// ```
+    pub fn try_items(&self) -> TryItemIterator<'_, K, V> {
+        self.try_range(..)
+    }
+
     /// Returns an iterator over key-value pairs in a range.
     /// If start_key is None, starts from the beginning.
     /// If end_key is None, goes to the end.
+    ///
+    /// Equivalent to `range` over `start_key..end_key`.
+    #[deprecated(since = "0.9.0", note = "use `range`, which takes any `RangeBounds`")]
     pub fn items_range<'a>(
         &'a self,
         start_key: Option<&K>,
@@ -115,6 +399,9 @@ impl<'a, K: Ord + Clone, V: Clone> ItemIterator<'a, K, V> {
             end_key: None,
             end_bound_key: None,
             end_inclusive: false,
+            leaf_bound_checked: false,
+            unchecked_until: 0,
+            back: None,
         }
     }
 
@@ -140,6 +427,75 @@ impl<'a, K: Ord + Clone, V: Clone> ItemIterator<'a, K, V> {
             end_key,
             end_bound_key,
             end_inclusive,
+            leaf_bound_checked: false,
+            unchecked_until: 0,
+            back: None,
+        }
+    }
+
+    /// Stop the iterator before an owned end key.
+    pub(crate) fn with_end_key(mut self, end: K, inclusive: bool) -> Self {
+        self.end_bound_key = Some(end);
+        self.end_inclusive = inclusive;
+        self
+    }
+
+    /// Returns true if every key of `leaf` is before the end bound.
+    #[inline]
+    fn leaf_within_end(&self, leaf: &LeafNode<K, V>) -> bool {
+        let last = match leaf.keys.last() {
+            Some(last) => last,
+            None => return true,
+        };
+        if let Some(end_key) = self.end_key {
+            last < end_key
+        } else if let Some(ref end_bound) = self.end_bound_key {
+            if self.end_inclusive {
+                last <= end_bound
+            } else {
+                last < end_bound
+            }
+        } else {
+            true
+        }
+    }
+
+    /// Returns true if `key` is past the end bound.
+    #[inline]
+    fn is_beyond_end(&self, key: &K) -> bool {
+        if let Some(end_key) = self.end_key {
+            key >= end_key
+        } else if let Some(ref end_bound) = self.end_bound_key {
+            if self.end_inclusive {
+                key > end_bound
+            } else {
+                key >= end_bound
+            }
+        } else {
+            false
+        }
+    }
+
+    /// The end bound as a `Bound`, for seeding the back end.
+    fn end_bound(&self) -> Bound<&K> {
+        match (self.end_key, &self.end_bound_key) {
+            (Some(end_key), _) => Bound::Excluded(end_key),
+            (None, Some(end_bound)) if self.end_inclusive => Bound::Included(end_bound),
+            (None, Some(end_bound)) => Bound::Excluded(end_bound),
+            (None, None) => Bound::Unbounded,
+        }
+    }
+
+    /// The key the front would yield next, ignoring the end bound.
+    fn peek_front_key(&self) -> Option<&'a K> {
+        let mut leaf = self.current_leaf_ref?;
+        let mut index = self.current_leaf_index;
+        loop {
+            if let Some(key) = leaf.keys.get(index) {
+                return Some(key);
+            }
+            leaf = next_leaf(self.tree, leaf)?;
+            index = 0;
         }
     }
 
@@ -169,20 +525,7 @@ impl<'a, K: Ord + Clone, V: Clone> ItemIterator<'a, K, V> {
         // - Critical for competitive iteration performance vs BTreeMap
         let (key, value) = unsafe { leaf.get_key_value_unchecked(self.current_leaf_index) };
 
-        // Optimized: Direct conditional logic instead of Option combinators
-        let beyond_end = if let Some(end_key) = self.end_key {
-            key >= end_key
-        } else if let Some(ref end_bound) = self.end_bound_key {
-            if self.end_inclusive {
-                key > end_bound
-            } else {
-                key >= end_bound
-            }
-        } else {
-            false
-        };
-
-        if beyond_end {
+        if self.is_beyond_end(key) {
             // Set terminal state instead of finished flag
             self.current_leaf_ref = None;
             self.current_leaf_id = None;
@@ -214,11 +557,25 @@ impl<'a, K: Ord + Clone, V: Clone> ItemIterator<'a, K, V> {
         // Advance to next leaf - this is the ONLY arena access during iteration
         self.current_leaf_id = Some(leaf.next);
         self.current_leaf_ref = self.tree.get_leaf(leaf.next);
+        if let Some(next) = self.current_leaf_ref {
+            debug_assert_sorted(leaf, next);
+        }
         self.current_leaf_index = 0;
+        self.leaf_bound_checked = false;
+        self.unchecked_until = 0;
 
         // Return whether we successfully got the next leaf
         self.current_leaf_ref.is_some()
     }
+
+    /// The id of the leaf the iterator stopped at because the arena does
+    /// not hold it. `None` while iterating or after a normal end.
+    pub(crate) fn missing_leaf(&self) -> Option<NodeId> {
+        match (self.current_leaf_id, self.current_leaf_ref) {
+            (Some(id), None) => Some(id),
+            _ => None,
+        }
+    }
 }
 
 impl<'a, K: Ord + Clone, V: Clone> Iterator for ItemIterator<'a, K, V> {
@@ -237,6 +594,39 @@ impl<'a, K: Ord + Clone, V: Clone> Iterator for ItemIterator<'a, K, V> {
             // Direct access - if no leaf, we're done (terminal state)
             let leaf = self.current_leaf_ref?;
 
+            // Fast path: the rest of this leaf is known to be in range
+            if self.current_leaf_index < self.unchecked_until {
+                // SAFETY: unchecked_until is at most this leaf's length, and
+                // keys.len() == values.len() for every leaf
+                let item = unsafe { leaf.get_key_value_unchecked(self.current_leaf_index) };
+                self.current_leaf_index += 1;
+                return Some(item);
+            }
+
+            // Once the back end is in this leaf it bounds the front, and
+            // everything before it is known to be in range
+            if let Some(back) = &self.back {
+                if ptr::eq(leaf, back.leaf) {
+                    let index = self.current_leaf_index;
+                    if index < back.end {
+                        self.current_leaf_index += 1;
+                        return Some((&leaf.keys[index], &leaf.values[index]));
+                    }
+                    self.current_leaf_ref = None;
+                    self.current_leaf_id = None;
+                    return None;
+                }
+            }
+
+            // Compare the end bound against the leaf's last key once per leaf
+            if !self.leaf_bound_checked {
+                self.leaf_bound_checked = true;
+                if self.leaf_within_end(leaf) {
+                    self.unchecked_until = leaf.keys_len();
+                    continue;
+                }
+            }
+
             // Try current leaf first
             if let Some(item) = self.try_get_next_item(leaf) {
                 return Some(item);
@@ -251,14 +641,60 @@ impl<'a, K: Ord + Clone, V: Clone> Iterator for ItemIterator<'a, K, V> {
     }
 }
 
+impl<'a, K: Ord + Clone, V: Clone> DoubleEndedIterator for ItemIterator<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let front = self.current_leaf_ref?;
+        if self.back.is_none() {
+            // A start past the end bound leaves nothing for either end
+            if self
+                .peek_front_key()
+                .is_none_or(|key| self.is_beyond_end(key))
+            {
+                self.current_leaf_ref = None;
+                self.current_leaf_id = None;
+                return None;
+            }
+            self.back = Some(BackCursor::seek(self.tree, self.end_bound())?);
+        }
+
+        let back = self.back.as_mut()?;
+        loop {
+            let same_leaf = ptr::eq(back.leaf, front);
+            let floor = if same_leaf {
+                self.current_leaf_index
+            } else {
+                0
+            };
+            if back.end > floor {
+                back.end -= 1;
+                if same_leaf {
+                    self.unchecked_until = self.unchecked_until.min(back.end);
+                }
+                let leaf = back.leaf;
+                return Some((&leaf.keys[back.end], &leaf.values[back.end]));
+            }
+            // The ends have met, or the back ran out of leaves
+            if same_leaf || !back.retreat(self.tree) {
+                self.current_leaf_ref = None;
+                self.current_leaf_id = None;
+                return None;
+            }
+        }
+    }
+}
+
 // ============================================================================
 // KEYITERATOR IMPLEMENTATION
 // ============================================================================
 
 impl<'a, K: Ord + Clone, V: Clone> KeyIterator<'a, K, V> {
     pub fn new(tree: &'a BPlusTreeMap<K, V>) -> Self {
+        let current_leaf_ref = tree.get_first_leaf_id().and_then(|id| tree.get_leaf(id));
         Self {
-            items: ItemIterator::new(tree),
+            tree,
+            current_leaf_ref,
+            keys: current_leaf_ref.map_or([].iter(), |leaf| leaf.keys.iter()),
+            back: None,
         }
     }
 }
@@ -266,8 +702,52 @@ impl<'a, K: Ord + Clone, V: Clone> KeyIterator<'a, K, V> {
 impl<'a, K: Ord + Clone, V: Clone> Iterator for KeyIterator<'a, K, V> {
     type Item = &'a K;
 
+    #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        self.items.next().map(|(k, _)| k)
+        loop {
+            if let Some(key) = self.keys.next() {
+                return Some(key);
+            }
+            let leaf = self.current_leaf_ref?;
+            self.current_leaf_ref = match &self.back {
+                // Both ends were in this leaf, and it is drained
+                Some((cursor, _)) if ptr::eq(cursor.leaf, leaf) => None,
+                _ => next_leaf(self.tree, leaf),
+            };
+            self.keys = match (&mut self.back, self.current_leaf_ref) {
+                // Caught up with the back end: take over the rest of its leaf
+                (Some((cursor, keys)), Some(next)) if ptr::eq(cursor.leaf, next) => {
+                    std::mem::replace(keys, [].iter())
+                }
+                (_, next) => next.map_or([].iter(), |leaf| leaf.keys.iter()),
+            };
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone> DoubleEndedIterator for KeyIterator<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let front = self.current_leaf_ref?;
+        if self.back.is_none() {
+            let cursor = BackCursor::seek(self.tree, Bound::Unbounded)?;
+            let leaf = cursor.leaf;
+            self.back = Some((cursor, leaf.keys.iter()));
+        }
+
+        let (cursor, keys) = self.back.as_mut()?;
+        loop {
+            // Once both ends are in one leaf, the front's slice holds the rest
+            if ptr::eq(cursor.leaf, front) {
+                return self.keys.next_back();
+            }
+            if let Some(key) = keys.next_back() {
+                return Some(key);
+            }
+            if !cursor.retreat(self.tree) {
+                return None;
+            }
+            *keys = cursor.leaf.keys.iter();
+        }
     }
 }
 
@@ -277,8 +757,12 @@ impl<'a, K: Ord + Clone, V: Clone> Iterator for KeyIterator<'a, K, V> {
 
 impl<'a, K: Ord + Clone, V: Clone> ValueIterator<'a, K, V> {
     pub fn new(tree: &'a BPlusTreeMap<K, V>) -> Self {
+        let current_leaf_ref = tree.get_first_leaf_id().and_then(|id| tree.get_leaf(id));
         Self {
-            items: ItemIterator::new(tree),
+            tree,
+            current_leaf_ref,
+            values: current_leaf_ref.map_or([].iter(), |leaf| leaf.values.iter()),
+            back: None,
         }
     }
 }
@@ -286,8 +770,413 @@ impl<'a, K: Ord + Clone, V: Clone> ValueIterator<'a, K, V> {
 impl<'a, K: Ord + Clone, V: Clone> Iterator for ValueIterator<'a, K, V> {
     type Item = &'a V;
 
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(value) = self.values.next() {
+                return Some(value);
+            }
+            let leaf = self.current_leaf_ref?;
+            self.current_leaf_ref = match &self.back {
+                // Both ends were in this leaf, and it is drained
+                Some((cursor, _)) if ptr::eq(cursor.leaf, leaf) => None,
+                _ => next_leaf(self.tree, leaf),
+            };
+            self.values = match (&mut self.back, self.current_leaf_ref) {
+                // Caught up with the back end: take over the rest of its leaf
+                (Some((cursor, values)), Some(next)) if ptr::eq(cursor.leaf, next) => {
+                    std::mem::replace(values, [].iter())
+                }
+                (_, next) => next.map_or([].iter(), |leaf| leaf.values.iter()),
+            };
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone> DoubleEndedIterator for ValueIterator<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let front = self.current_leaf_ref?;
+        if self.back.is_none() {
+            let cursor = BackCursor::seek(self.tree, Bound::Unbounded)?;
+            let leaf = cursor.leaf;
+            self.back = Some((cursor, leaf.values.iter()));
+        }
+
+        let (cursor, values) = self.back.as_mut()?;
+        loop {
+            // Once both ends are in one leaf, the front's slice holds the rest
+            if ptr::eq(cursor.leaf, front) {
+                return self.values.next_back();
+            }
+            if let Some(value) = values.next_back() {
+                return Some(value);
+            }
+            if !cursor.retreat(self.tree) {
+                return None;
+            }
+            *values = cursor.leaf.values.iter();
+        }
+    }
+}
+
+// ============================================================================
+// MUTABLE ITERATOR IMPLEMENTATIONS
+// ============================================================================
+
+impl<'a, K: Ord + Clone, V: Clone> ItemIteratorMut<'a, K, V> {
+    pub fn new(tree: &'a mut BPlusTreeMap<K, V>) -> Self {
+        let first_id = tree.get_first_leaf_id();
+        // Handles into the tree may point at values about to change
+        tree.bump_generation();
+
+        let mut iter = Self {
+            leaves: Vec::new(),
+            next_id: NULL_NODE,
+            entries: [].iter().zip([].iter_mut()),
+        };
+        match first_id {
+            Some(INLINE_ROOT) => {
+                if let Some(leaf) = tree.inline_root.as_mut() {
+                    iter.enter(leaf);
+                }
+            }
+            Some(first_id) => {
+                iter.leaves = tree.leaf_arena.slots_mut();
+                iter.next_id = first_id;
+            }
+            None => {}
+        }
+        iter
+    }
+
+    /// Make `leaf` the current leaf.
+    fn enter(&mut self, leaf: &'a mut LeafNode<K, V>) {
+        self.next_id = leaf.next;
+        self.entries = leaf.keys.iter().zip(leaf.values.iter_mut());
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone> Iterator for ItemIteratorMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.entries.next() {
+                return Some(entry);
+            }
+            // A slot already taken means the chain loops; stop rather than
+            // hand out a leaf twice
+            let leaf = self
+                .leaves
+                .get_mut(usize::try_from(self.next_id).ok()?)?
+                .take()?;
+            self.enter(leaf);
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone> Iterator for ValueIteratorMut<'a, K, V> {
+    type Item = &'a mut V;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.items.next().map(|(_, value)| value)
+    }
+}
+
+// ============================================================================
+// INTOITER IMPLEMENTATION
+// ============================================================================
+
+impl<K: TreeKey, V: TreeValue> IntoIterator for BPlusTreeMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    /// Consume the tree, yielding its key-value pairs in sorted order without
+    /// cloning them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..20 {
+    ///     tree.insert(i, i.to_string());
+    /// }
+    /// let owned: Vec<(i32, String)> = tree.into_iter().skip(18).collect();
+    /// assert_eq!(owned, vec![(18, "18".to_string()), (19, "19".to_string())]);
+    /// ```
+    fn into_iter(self) -> IntoIter<K, V> {
+        let remaining = self.len();
+        let first_id = self.get_first_leaf_id();
+        let BPlusTreeMap {
+            inline_root,
+            leaf_arena,
+            ..
+        } = self;
+
+        let mut iter = IntoIter {
+            leaves: leaf_arena,
+            next_id: NULL_NODE,
+            keys: NodeVec::new().into_iter(),
+            values: NodeVec::new().into_iter(),
+            remaining,
+        };
+        match (first_id, inline_root) {
+            (Some(INLINE_ROOT), Some(leaf)) => iter.enter(leaf.keys, leaf.values, leaf.next),
+            (Some(first_id), _) if first_id != INLINE_ROOT => iter.next_id = first_id,
+            _ => {}
+        }
+        iter
+    }
+}
+
+impl<K, V> IntoIter<K, V> {
+    /// Make the given leaf contents the current leaf.
+    fn enter(&mut self, keys: NodeVec<K>, values: NodeVec<V>, next: NodeId) {
+        self.next_id = next;
+        self.keys = keys.into_iter();
+        self.values = values.into_iter();
+    }
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
     fn next(&mut self) -> Option<Self::Item> {
-        self.items.next().map(|(_, v)| v)
+        loop {
+            if let (Some(key), Some(value)) = (self.keys.next(), self.values.next()) {
+                self.remaining = self.remaining.saturating_sub(1);
+                return Some((key, value));
+            }
+            // A freed slot means the chain ends or loops back on itself
+            let id = self.next_id;
+            let leaf = self.leaves.get_mut(id)?;
+            let keys = std::mem::take(&mut leaf.keys);
+            let values = std::mem::take(&mut leaf.values);
+            let next = leaf.next;
+            self.leaves.deallocate_no_return(id);
+            self.enter(keys, values, next);
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K, V> FusedIterator for IntoIter<K, V> {}
+
+// ============================================================================
+// LEAFGROUPITERATOR IMPLEMENTATION
+// ============================================================================
+
+impl<'a, K: Ord + Clone, V: Clone> LeafGroupIterator<'a, K, V> {
+    pub fn new(tree: &'a BPlusTreeMap<K, V>) -> Self {
+        Self {
+            tree,
+            current_leaf_ref: tree.get_first_leaf_id().and_then(|id| tree.get_leaf(id)),
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone> Iterator for LeafGroupIterator<'a, K, V> {
+    type Item = (&'a [K], &'a [V]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let leaf = self.current_leaf_ref?;
+            self.current_leaf_ref = next_leaf(self.tree, leaf);
+            if !leaf.keys.is_empty() {
+                return Some((&leaf.keys, &leaf.values));
+            }
+        }
+    }
+}
+
+/// The leaf after `leaf` in the linked list, if any.
+#[inline]
+fn next_leaf<'a, K: Ord + Clone, V: Clone>(
+    tree: &'a BPlusTreeMap<K, V>,
+    leaf: &'a LeafNode<K, V>,
+) -> Option<&'a LeafNode<K, V>> {
+    if leaf.next == NULL_NODE {
+        None
+    } else {
+        let next = tree.get_leaf(leaf.next)?;
+        debug_assert_sorted(leaf, next);
+        Some(next)
+    }
+}
+
+/// With the `order-checks` feature, check in debug builds that keys still
+/// ascend where an iterator steps from `from` to `to`: across the boundary
+/// and between the first, middle and last keys of `to`. A few comparisons
+/// per leaf catch an order broken by a key mutated in place or an
+/// inconsistent `Ord`, without rescanning whole leaves.
+#[cfg(feature = "order-checks")]
+#[inline]
+fn debug_assert_sorted<K: Ord, V>(from: &LeafNode<K, V>, to: &LeafNode<K, V>) {
+    let keys = &to.keys;
+    let (Some(first), Some(last)) = (keys.first(), keys.last()) else {
+        return;
+    };
+    let middle = &keys[keys.len() / 2];
+    debug_assert!(
+        from.keys.last().is_none_or(|previous| previous < first),
+        "keys out of order across a leaf boundary; was a key mutated in place?"
+    );
+    debug_assert!(
+        keys.len() < 2 || (first <= middle && middle <= last && first < last),
+        "keys out of order within a leaf; was a key mutated in place?"
+    );
+}
+
+#[cfg(not(feature = "order-checks"))]
+#[inline(always)]
+fn debug_assert_sorted<K, V>(_from: &LeafNode<K, V>, _to: &LeafNode<K, V>) {}
+
+// ============================================================================
+// LEAFITERATOR IMPLEMENTATION
+// ============================================================================
+
+impl<'a, K: Ord + Clone, V: Clone> LeafIterator<'a, K, V> {
+    pub fn new(tree: &'a BPlusTreeMap<K, V>) -> Self {
+        Self {
+            tree,
+            next_id: tree.get_first_leaf_id().unwrap_or(NULL_NODE),
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone> Iterator for LeafIterator<'a, K, V> {
+    type Item = (NodeId, &'a LeafNode<K, V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next_id == NULL_NODE {
+            return None;
+        }
+        let id = self.next_id;
+        let leaf = self.tree.get_leaf(id)?;
+        self.next_id = leaf.next;
+        Some((id, leaf))
+    }
+}
+
+// ============================================================================
+// BRANCHITERATOR IMPLEMENTATION
+// ============================================================================
+
+impl<'a, K: Ord + Clone, V: Clone> BranchIterator<'a, K, V> {
+    pub fn new(tree: &'a BPlusTreeMap<K, V>) -> Self {
+        let mut queue = VecDeque::new();
+        if let NodeRef::Branch(id, _) = tree.root {
+            queue.push_back(id);
+        }
+        Self { tree, queue }
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone> Iterator for BranchIterator<'a, K, V> {
+    type Item = (NodeId, &'a BranchNode<K, V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.queue.pop_front()?;
+        let branch = self.tree.get_branch(id)?;
+        // Children of one branch are all leaves or all branches
+        self.queue
+            .extend(branch.children().filter_map(|child| match child {
+                NodeRef::Branch(child_id, _) => Some(child_id),
+                NodeRef::Leaf(_, _) => None,
+            }));
+        Some((id, branch))
+    }
+}
+
+// ============================================================================
+// BACKCURSOR IMPLEMENTATION
+// ============================================================================
+
+impl<'a, K: Ord + Clone, V: Clone> BackCursor<'a, K, V> {
+    /// Descend to the position just past the last entry before `end`.
+    fn seek(tree: &'a BPlusTreeMap<K, V>, end: Bound<&K>) -> Option<Self> {
+        let mut path = Vec::new();
+        let mut current = tree.root;
+        loop {
+            match current {
+                NodeRef::Leaf(leaf_id, _) => {
+                    let leaf = tree.get_leaf(leaf_id)?;
+                    let end = match end {
+                        Bound::Included(probe) => leaf.keys.partition_point(|key| key <= probe),
+                        Bound::Excluded(probe) => leaf.keys.partition_point(|key| key < probe),
+                        Bound::Unbounded => leaf.keys.len(),
+                    };
+                    return Some(Self { path, leaf, end });
+                }
+                NodeRef::Branch(branch_id, _) => {
+                    let branch = tree.get_branch(branch_id)?;
+                    let child_index = match end {
+                        Bound::Included(probe) | Bound::Excluded(probe) => {
+                            branch.find_child_index(probe)
+                        }
+                        Bound::Unbounded => branch.child_count().checked_sub(1)?,
+                    };
+                    path.push((branch_id, child_index));
+                    current = branch.child(child_index)?;
+                }
+            }
+        }
+    }
+
+    /// Move to the end of the previous leaf. Returns false at the first leaf
+    /// or if a node on the way is missing.
+    fn retreat(&mut self, tree: &'a BPlusTreeMap<K, V>) -> bool {
+        // Climb to the nearest branch with a child left of the one taken
+        let mut current = loop {
+            let Some(&mut (branch_id, ref mut child_index)) = self.path.last_mut() else {
+                return false;
+            };
+            if *child_index > 0 {
+                *child_index -= 1;
+                match tree
+                    .get_branch(branch_id)
+                    .and_then(|b| b.child(*child_index))
+                {
+                    Some(child) => break child,
+                    None => return false,
+                }
+            }
+            self.path.pop();
+        };
+
+        // Descend that child's rightmost edge
+        loop {
+            match current {
+                NodeRef::Leaf(leaf_id, _) => {
+                    let Some(leaf) = tree.get_leaf(leaf_id) else {
+                        return false;
+                    };
+                    self.leaf = leaf;
+                    self.end = leaf.keys.len();
+                    return true;
+                }
+                NodeRef::Branch(branch_id, _) => {
+                    let Some(branch) = tree.get_branch(branch_id) else {
+                        return false;
+                    };
+                    let last = branch.child_count().saturating_sub(1);
+                    let Some(child) = branch.child(last) else {
+                        return false;
+                    };
+                    self.path.push((branch_id, last));
+                    current = child;
+                }
+            }
+        }
     }
 }
 
@@ -337,6 +1226,11 @@ impl<'a, K: Ord + Clone, V: Clone> RangeIterator<'a, K, V> {
             first_key,
         }
     }
+
+    /// See [`ItemIterator::missing_leaf`].
+    pub(crate) fn missing_leaf(&self) -> Option<NodeId> {
+        self.iterator.as_ref()?.missing_leaf()
+    }
 }
 
 impl<'a, K: Ord + Clone, V: Clone> Iterator for RangeIterator<'a, K, V> {
@@ -362,63 +1256,58 @@ impl<'a, K: Ord + Clone, V: Clone> Iterator for RangeIterator<'a, K, V> {
     }
 }
 
+impl<'a, K: Ord + Clone, V: Clone> DoubleEndedIterator for RangeIterator<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let item = self.iterator.as_mut()?.next_back()?;
+
+        // The back end reaching an excluded start means the range is used up
+        if self.skip_first && self.first_key.as_ref() == Some(item.0) {
+            self.iterator = None;
+            return None;
+        }
+        Some(item)
+    }
+}
+
 // ============================================================================
-// FASTITEMITERATOR IMPLEMENTATION
+// TRYITEMITERATOR IMPLEMENTATION
 // ============================================================================
 
-impl<'a, K: Ord + Clone, V: Clone> FastItemIterator<'a, K, V> {
-    pub fn new(tree: &'a BPlusTreeMap<K, V>) -> Self {
-        // Start with the first (leftmost) leaf in the tree
-        let leftmost_id = tree.get_first_leaf_id();
-
-        // Get the initial leaf reference if we have a starting leaf
-        let current_leaf_ref = leftmost_id.map(|id| unsafe { tree.get_leaf_unchecked(id) });
+impl<'a, K: Ord + Clone, V: Clone> TryItemIterator<'a, K, V> {
+    pub(crate) fn new(items: RangeIterator<'a, K, V>) -> Self {
+        Self {
+            items: Some(items),
+            pending: None,
+        }
+    }
 
+    /// An iterator that yields `error` and ends.
+    pub(crate) fn failed(error: BPlusTreeError) -> Self {
         Self {
-            tree,
-            current_leaf_id: leftmost_id,
-            current_leaf_ref,
-            current_leaf_index: 0,
-            finished: false,
+            items: None,
+            pending: Some(error),
         }
     }
 }
 
-impl<'a, K: Ord + Clone, V: Clone> Iterator for FastItemIterator<'a, K, V> {
-    type Item = (&'a K, &'a V);
+impl<'a, K: Ord + Clone, V: Clone> Iterator for TryItemIterator<'a, K, V> {
+    type Item = Result<(&'a K, &'a V), BPlusTreeError>;
 
-    #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        if self.finished {
-            return None;
+        if let Some(error) = self.pending.take() {
+            return Some(Err(error));
         }
-
-        loop {
-            // Optimized: Direct access with early return
-            let leaf = match self.current_leaf_ref {
-                Some(leaf) => leaf,
-                None => {
-                    self.finished = true;
-                    return None;
-                }
-            };
-
-            if self.current_leaf_index < leaf.keys_len() {
-                let key = leaf.get_key(self.current_leaf_index)?;
-                let value = leaf.get_value(self.current_leaf_index)?;
-                self.current_leaf_index += 1;
-                return Some((key, value));
-            }
-
-            // Move to next leaf - this is the ONLY arena access during iteration
-            if leaf.next != NULL_NODE {
-                self.current_leaf_id = Some(leaf.next);
-                self.current_leaf_ref = unsafe { Some(self.tree.get_leaf_unchecked(leaf.next)) };
-                self.current_leaf_index = 0;
-            } else {
-                self.finished = true;
-                return None;
-            }
+        let items = self.items.as_mut()?;
+        if let Some(item) = items.next() {
+            return Some(Ok(item));
         }
+        let missing = items.missing_leaf();
+        self.items = None;
+        missing.map(|id| {
+            Err(BPlusTreeError::corrupted_tree(
+                "Leaf chain",
+                &format!("leaf {} is linked but not in the arena", id),
+            ))
+        })
     }
 }
@@ -0,0 +1,468 @@
+//! Sorted merge-joins between two trees.
+//!
+//! Both trees keep their entries in key order along the leaf chain, so the
+//! entries two trees share can be found by walking both chains side by side
+//! instead of looking every key of one tree up in the other. The side that is
+//! behind catches up a leaf at a time: a leaf whose last key is still too
+//! small is skipped after one comparison, and within a leaf the catch-up
+//! point is found by binary search.
+//!
+//! The same walk answers containment questions in one pass:
+//! [`contains_all`](BPlusTreeMap::contains_all) for a sorted list of keys and
+//! [`is_submap`](BPlusTreeMap::is_submap) for a whole tree.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::iteration::LeafGroupIterator;
+use crate::types::BPlusTreeMap;
+use std::cmp::Ordering;
+
+/// Where a key of an outer join was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinSide<A, B> {
+    /// Only in the left tree.
+    Left(A),
+    /// Only in the right tree.
+    Right(B),
+    /// In both trees.
+    Both(A, B),
+}
+
+impl<A, B> JoinSide<A, B> {
+    /// The left and right values, `None` on the side that lacks the key.
+    pub fn into_options(self) -> (Option<A>, Option<B>) {
+        match self {
+            JoinSide::Left(left) => (Some(left), None),
+            JoinSide::Right(right) => (None, Some(right)),
+            JoinSide::Both(left, right) => (Some(left), Some(right)),
+        }
+    }
+}
+
+/// Position in one tree's leaf chain: the rest of the current leaf.
+struct LeafCursor<'a, K, V> {
+    leaves: LeafGroupIterator<'a, K, V>,
+    keys: &'a [K],
+    values: &'a [V],
+}
+
+impl<'a, K: Ord + Clone, V: Clone> LeafCursor<'a, K, V> {
+    fn new(tree: &'a BPlusTreeMap<K, V>) -> Self {
+        let mut cursor = Self {
+            leaves: tree.group_by_leaf(),
+            keys: &[],
+            values: &[],
+        };
+        cursor.next_leaf();
+        cursor
+    }
+
+    /// Move to the next non-empty leaf, leaving the slices empty at the end.
+    fn next_leaf(&mut self) {
+        (self.keys, self.values) = self.leaves.next().unwrap_or((&[], &[]));
+    }
+
+    fn peek(&self) -> Option<&'a K> {
+        self.keys.first()
+    }
+
+    fn advance(&mut self) -> Option<(&'a K, &'a V)> {
+        let (key, keys) = self.keys.split_first()?;
+        let (value, values) = self.values.split_first()?;
+        (self.keys, self.values) = (keys, values);
+        if self.keys.is_empty() {
+            self.next_leaf();
+        }
+        Some((key, value))
+    }
+
+    /// Drop every entry before `target`.
+    fn skip_before(&mut self, target: &K) {
+        while self.keys.last().is_some_and(|last| last < target) {
+            self.next_leaf();
+        }
+        let skipped = self.keys.partition_point(|key| key < target);
+        self.keys = &self.keys[skipped..];
+        self.values = &self.values[skipped..];
+    }
+}
+
+/// Entries whose key is in both trees, from [`BPlusTreeMap::join_inner`].
+pub struct InnerJoin<'a, K, V, W> {
+    left: LeafCursor<'a, K, V>,
+    right: LeafCursor<'a, K, W>,
+}
+
+/// Every key in either tree, from [`BPlusTreeMap::join_outer`].
+pub struct OuterJoin<'a, K, V, W> {
+    left: LeafCursor<'a, K, V>,
+    right: LeafCursor<'a, K, W>,
+}
+
+/// Every key in either tree with an optional value from each, from
+/// [`BPlusTreeMap::aligned_iter`].
+pub struct AlignedIter<'a, K, V, W> {
+    join: OuterJoin<'a, K, V, W>,
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Iterate over the keys present in both this tree and `other`, in key
+    /// order, with the value from each.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut names = BPlusTreeMap::new(4).unwrap();
+    /// let mut ages = BPlusTreeMap::new(4).unwrap();
+    /// for (id, name) in [(1, "ann"), (2, "bob"), (4, "dan")] {
+    ///     names.insert(id, name);
+    /// }
+    /// for (id, age) in [(2, 31), (3, 45), (4, 27)] {
+    ///     ages.insert(id, age);
+    /// }
+    ///
+    /// let joined: Vec<_> = names.join_inner(&ages).collect();
+    /// assert_eq!(joined, [(&2, &"bob", &31), (&4, &"dan", &27)]);
+    /// ```
+    pub fn join_inner<'a, W: TreeValue>(
+        &'a self,
+        other: &'a BPlusTreeMap<K, W>,
+    ) -> InnerJoin<'a, K, V, W> {
+        InnerJoin {
+            left: LeafCursor::new(self),
+            right: LeafCursor::new(other),
+        }
+    }
+
+    /// Iterate over the keys present in either this tree or `other`, in key
+    /// order, with the value from each side that has the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::{BPlusTreeMap, JoinSide};
+    ///
+    /// let mut before = BPlusTreeMap::new(4).unwrap();
+    /// let mut after = BPlusTreeMap::new(4).unwrap();
+    /// before.insert("a", 1);
+    /// before.insert("b", 2);
+    /// after.insert("b", 3);
+    /// after.insert("c", 4);
+    ///
+    /// let changes: Vec<_> = before.join_outer(&after).collect();
+    /// assert_eq!(
+    ///     changes,
+    ///     [
+    ///         (&"a", JoinSide::Left(&1)),
+    ///         (&"b", JoinSide::Both(&2, &3)),
+    ///         (&"c", JoinSide::Right(&4)),
+    ///     ]
+    /// );
+    /// ```
+    pub fn join_outer<'a, W: TreeValue>(
+        &'a self,
+        other: &'a BPlusTreeMap<K, W>,
+    ) -> OuterJoin<'a, K, V, W> {
+        OuterJoin {
+            left: LeafCursor::new(self),
+            right: LeafCursor::new(other),
+        }
+    }
+
+    /// Iterate over the union of the keys of this tree and `other`, in key
+    /// order, with each side's value or `None` where it lacks the key.
+    ///
+    /// This is [`join_outer`](Self::join_outer) in a shape suited to
+    /// reconciliation: one pass tells what is missing on either side and
+    /// what differs. [`AlignedIter::with_defaults`] fills the gaps instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut stock = BPlusTreeMap::new(4).unwrap();
+    /// let mut counted = BPlusTreeMap::new(4).unwrap();
+    /// stock.insert("bolts", 40);
+    /// stock.insert("nuts", 25);
+    /// counted.insert("nuts", 22);
+    /// counted.insert("washers", 9);
+    ///
+    /// let report: Vec<_> = stock.aligned_iter(&counted).collect();
+    /// assert_eq!(
+    ///     report,
+    ///     [
+    ///         (&"bolts", Some(&40), None),
+    ///         (&"nuts", Some(&25), Some(&22)),
+    ///         (&"washers", None, Some(&9)),
+    ///     ]
+    /// );
+    ///
+    /// let shortfall: i32 = stock
+    ///     .aligned_iter(&counted)
+    ///     .with_defaults(&0, &0)
+    ///     .map(|(_, expected, found)| expected - found)
+    ///     .sum();
+    /// assert_eq!(shortfall, 40 + 3 - 9);
+    /// ```
+    pub fn aligned_iter<'a, W: TreeValue>(
+        &'a self,
+        other: &'a BPlusTreeMap<K, W>,
+    ) -> AlignedIter<'a, K, V, W> {
+        AlignedIter {
+            join: self.join_outer(other),
+        }
+    }
+
+    /// Returns true if every key in `keys` is in the tree.
+    ///
+    /// With `keys` in ascending order, duplicates allowed, the probes are
+    /// merged with the leaf chain in one pass instead of descending from the
+    /// root for each. Keys in any other order fall back to one lookup each.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..100 {
+    ///     tree.insert(i * 2, ());
+    /// }
+    /// assert!(tree.contains_all(&[0, 10, 10, 98]));
+    /// assert!(!tree.contains_all(&[0, 11, 98]));
+    /// assert!(tree.contains_all(&[]));
+    /// ```
+    pub fn contains_all(&self, keys: &[K]) -> bool {
+        if !keys.windows(2).all(|pair| pair[0] <= pair[1]) {
+            return keys.iter().all(|key| self.contains_key(key));
+        }
+        let mut cursor = LeafCursor::new(self);
+        keys.iter().all(|key| {
+            cursor.skip_before(key);
+            cursor.peek() == Some(key)
+        })
+    }
+}
+
+impl<K: TreeKey, V: TreeValue + PartialEq> BPlusTreeMap<K, V> {
+    /// Returns true if every entry of this tree is also in `other` with an
+    /// equal value.
+    ///
+    /// Walks both leaf chains side by side, skipping whole leaves of `other`
+    /// that fall between this tree's keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut all = BPlusTreeMap::new(4).unwrap();
+    /// let mut some = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..50 {
+    ///     all.insert(i, i * i);
+    /// }
+    /// some.insert(7, 49);
+    /// some.insert(30, 900);
+    /// assert!(some.is_submap(&all));
+    /// assert!(!all.is_submap(&some));
+    ///
+    /// some.insert(8, 0);
+    /// assert!(!some.is_submap(&all));
+    /// ```
+    pub fn is_submap(&self, other: &BPlusTreeMap<K, V>) -> bool {
+        let mut other = LeafCursor::new(other);
+        self.items().all(|(key, value)| {
+            other.skip_before(key);
+            other.peek() == Some(key) && other.values.first() == Some(value)
+        })
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone, W: Clone> Iterator for InnerJoin<'a, K, V, W> {
+    type Item = (&'a K, &'a V, &'a W);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (left, right) = (self.left.peek()?, self.right.peek()?);
+            match left.cmp(right) {
+                Ordering::Less => self.left.skip_before(right),
+                Ordering::Greater => self.right.skip_before(left),
+                Ordering::Equal => {
+                    let (key, value) = self.left.advance()?;
+                    let (_, other) = self.right.advance()?;
+                    return Some((key, value, other));
+                }
+            }
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone, W: Clone> Iterator for OuterJoin<'a, K, V, W> {
+    type Item = (&'a K, JoinSide<&'a V, &'a W>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let order = match (self.left.peek(), self.right.peek()) {
+            (None, None) => return None,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(left), Some(right)) => left.cmp(right),
+        };
+        match order {
+            Ordering::Less => {
+                let (key, value) = self.left.advance()?;
+                Some((key, JoinSide::Left(value)))
+            }
+            Ordering::Greater => {
+                let (key, other) = self.right.advance()?;
+                Some((key, JoinSide::Right(other)))
+            }
+            Ordering::Equal => {
+                let (key, value) = self.left.advance()?;
+                let (_, other) = self.right.advance()?;
+                Some((key, JoinSide::Both(value, other)))
+            }
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone, W: Clone> AlignedIter<'a, K, V, W> {
+    /// Yield `left` or `right` in place of a missing value.
+    pub fn with_defaults(
+        self,
+        left: &'a V,
+        right: &'a W,
+    ) -> impl Iterator<Item = (&'a K, &'a V, &'a W)> {
+        self.map(move |(key, value, other)| (key, value.unwrap_or(left), other.unwrap_or(right)))
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone, W: Clone> Iterator for AlignedIter<'a, K, V, W> {
+    type Item = (&'a K, Option<&'a V>, Option<&'a W>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, side) = self.join.next()?;
+        let (value, other) = side.into_options();
+        Some((key, value, other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use std::collections::BTreeMap;
+
+    /// A tree and its model holding about one key in `one_in` from 0..5000.
+    fn sparse_tree(seed: u64, one_in: u64) -> (BPlusTreeMap<u64, u64>, BTreeMap<u64, u64>) {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        let mut model = BTreeMap::new();
+        let mut rng = SeededRng::new(seed);
+        for key in 0..5_000 {
+            if rng.below(one_in) == 0 {
+                tree.insert(key, key + seed);
+                model.insert(key, key + seed);
+            }
+        }
+        (tree, model)
+    }
+
+    #[test]
+    fn test_joins_match_lookups() {
+        for (one_in_left, one_in_right) in [(1, 1), (2, 3), (1, 50), (40, 1), (7, 7)] {
+            let (left, left_model) = sparse_tree(1, one_in_left);
+            let (right, right_model) = sparse_tree(2, one_in_right);
+
+            let expected: Vec<_> = left_model
+                .iter()
+                .filter_map(|(k, v)| right_model.get(k).map(|w| (k, v, w)))
+                .collect();
+            assert_eq!(left.join_inner(&right).collect::<Vec<_>>(), expected);
+
+            let mut keys: Vec<_> = left_model.keys().chain(right_model.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            let expected: Vec<_> = keys
+                .into_iter()
+                .map(|k| {
+                    let side = match (left_model.get(k), right_model.get(k)) {
+                        (Some(v), Some(w)) => JoinSide::Both(v, w),
+                        (Some(v), None) => JoinSide::Left(v),
+                        (None, Some(w)) => JoinSide::Right(w),
+                        (None, None) => unreachable!(),
+                    };
+                    (k, side)
+                })
+                .collect();
+            assert_eq!(left.join_outer(&right).collect::<Vec<_>>(), expected);
+        }
+    }
+
+    #[test]
+    fn test_aligned_iter_follows_outer_join() {
+        let (left, _) = sparse_tree(4, 3);
+        let (right, _) = sparse_tree(5, 2);
+        let aligned: Vec<_> = left.aligned_iter(&right).collect();
+        let outer: Vec<_> = left
+            .join_outer(&right)
+            .map(|(k, side)| {
+                let (v, w) = side.into_options();
+                (k, v, w)
+            })
+            .collect();
+        assert_eq!(aligned, outer);
+        assert!(aligned.iter().all(|(_, v, w)| v.is_some() || w.is_some()));
+
+        let filled: Vec<_> = left.aligned_iter(&right).with_defaults(&0, &1).collect();
+        assert_eq!(filled.len(), aligned.len());
+        for ((key, v, w), (filled_key, fv, fw)) in aligned.into_iter().zip(filled) {
+            assert_eq!(key, filled_key);
+            assert_eq!(v.unwrap_or(&0), fv);
+            assert_eq!(w.unwrap_or(&1), fw);
+        }
+    }
+
+    #[test]
+    fn test_containment_matches_model() {
+        let (big, big_model) = sparse_tree(6, 2);
+        let probes: Vec<u64> = (0..5_000).step_by(37).collect();
+        let present: Vec<u64> = probes
+            .iter()
+            .copied()
+            .filter(|k| big_model.contains_key(k))
+            .collect();
+        assert!(big.contains_all(&present));
+        assert_eq!(
+            big.contains_all(&probes),
+            probes.iter().all(|k| big_model.contains_key(k))
+        );
+        let mut reversed = present.clone();
+        reversed.reverse();
+        assert!(big.contains_all(&reversed));
+
+        let mut small = BPlusTreeMap::new(4).unwrap();
+        for &key in &present {
+            small.insert(key, big_model[&key]);
+        }
+        assert!(small.is_submap(&big));
+        assert!(big.is_submap(&big));
+        assert!(!big.is_submap(&small));
+        small.insert(present[3], 0);
+        assert!(!small.is_submap(&big));
+        assert!(BPlusTreeMap::new(4).unwrap().is_submap(&small));
+    }
+
+    #[test]
+    fn test_joins_with_empty_trees() {
+        let (full, _) = sparse_tree(3, 1);
+        let empty = BPlusTreeMap::<u64, u64>::new(4).unwrap();
+        assert_eq!(full.join_inner(&empty).count(), 0);
+        assert_eq!(empty.join_inner(&full).count(), 0);
+        assert_eq!(empty.join_outer(&empty).count(), 0);
+        assert!(empty
+            .join_outer(&full)
+            .all(|(_, side)| matches!(side, JoinSide::Right(_))));
+        assert_eq!(full.join_outer(&empty).count(), full.len());
+    }
+}
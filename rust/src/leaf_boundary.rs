@@ -0,0 +1,228 @@
+//! Notifications when leaf boundaries move.
+//!
+//! Every separator in the tree's bottom branch level marks where one leaf
+//! ends and the next begins. Layers built on top of the tree, such as a
+//! learned index fitting a model to those boundaries or a range partitioner
+//! that follows them, would otherwise have to rescan the leaves to notice a
+//! change. With [`BPlusTreeMap::set_leaf_boundary_hook`] the tree reports each
+//! boundary as it is created by a split or removed by a merge, together with
+//! the key range and size of the leaves involved.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::types::{BPlusTreeMap, LeafNode, NodeId};
+use std::fmt;
+
+/// Key range and size of one leaf, as reported to a boundary hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeafSummary<'a, K> {
+    /// Smallest key in the leaf, `None` if it is empty.
+    pub min: Option<&'a K>,
+    /// Largest key in the leaf, `None` if it is empty.
+    pub max: Option<&'a K>,
+    /// Number of entries in the leaf.
+    pub len: usize,
+}
+
+impl<'a, K> LeafSummary<'a, K> {
+    fn of<V>(leaf: &'a LeafNode<K, V>) -> Self {
+        Self {
+            min: leaf.keys.first(),
+            max: leaf.keys.last(),
+            len: leaf.keys.len(),
+        }
+    }
+}
+
+/// A leaf boundary that appeared or disappeared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeafBoundaryEvent<'a, K> {
+    /// A leaf split in two at `separator`, the first key of the new right
+    /// leaf.
+    Split {
+        /// The new boundary.
+        separator: &'a K,
+        /// The leaf below the boundary.
+        left: LeafSummary<'a, K>,
+        /// The leaf from the boundary up.
+        right: LeafSummary<'a, K>,
+    },
+    /// Two neighbouring leaves merged, removing the boundary `separator`
+    /// between them.
+    Merge {
+        /// The boundary that is gone.
+        separator: &'a K,
+        /// The leaf holding both halves.
+        merged: LeafSummary<'a, K>,
+    },
+}
+
+type BoundaryCallback<K> = dyn FnMut(&LeafBoundaryEvent<'_, K>) + Send + Sync;
+
+/// A boundary hook as stored in the tree.
+pub(crate) struct LeafBoundaryHook<K>(Box<BoundaryCallback<K>>);
+
+impl<K> fmt::Debug for LeafBoundaryHook<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LeafBoundaryHook")
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Call `hook` each time a leaf splits or two leaves merge, replacing any
+    /// hook already set.
+    ///
+    /// The hook runs inside the insert or removal that moved the boundary,
+    /// after the leaves have been updated, so it sees their final contents.
+    /// Borrowing between leaves moves a boundary by one key and is not
+    /// reported. Clearing the tree resets its leaves without merging them,
+    /// so it is not reported either.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::{BPlusTreeMap, LeafBoundaryEvent};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let boundaries = Arc::new(Mutex::new(Vec::new()));
+    /// let seen = Arc::clone(&boundaries);
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// tree.set_leaf_boundary_hook(move |event: &LeafBoundaryEvent<'_, i32>| {
+    ///     if let LeafBoundaryEvent::Split { separator, .. } = event {
+    ///         seen.lock().unwrap().push(**separator);
+    ///     }
+    /// });
+    /// for i in 0..10 {
+    ///     tree.insert(i, i);
+    /// }
+    ///
+    /// // Every separator was reported as it was created
+    /// let reported = boundaries.lock().unwrap();
+    /// assert_eq!(reported.len(), tree.leaf_count() - 1);
+    /// ```
+    pub fn set_leaf_boundary_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(&LeafBoundaryEvent<'_, K>) + Send + Sync + 'static,
+    {
+        self.leaf_boundary_hook = Some(LeafBoundaryHook(Box::new(hook)));
+    }
+
+    /// Stop reporting leaf boundary changes.
+    pub fn clear_leaf_boundary_hook(&mut self) {
+        self.leaf_boundary_hook = None;
+    }
+
+    /// Report that `left_id` split, creating `right_id` after it.
+    pub(crate) fn report_leaf_split(&mut self, left_id: NodeId, right_id: NodeId) {
+        let Some(mut hook) = self.leaf_boundary_hook.take() else {
+            return;
+        };
+        if let (Some(left), Some(right)) = (self.get_leaf(left_id), self.get_leaf(right_id)) {
+            if let Some(separator) = right.keys.first() {
+                (hook.0)(&LeafBoundaryEvent::Split {
+                    separator,
+                    left: LeafSummary::of(left),
+                    right: LeafSummary::of(right),
+                });
+            }
+        }
+        self.leaf_boundary_hook = Some(hook);
+    }
+
+    /// Report that the leaf after `separator` was merged into `leaf_id`.
+    pub(crate) fn report_leaf_merge(&mut self, leaf_id: NodeId, separator: &K) {
+        let Some(mut hook) = self.leaf_boundary_hook.take() else {
+            return;
+        };
+        if let Some(merged) = self.get_leaf(leaf_id) {
+            (hook.0)(&LeafBoundaryEvent::Merge {
+                separator,
+                merged: LeafSummary::of(merged),
+            });
+        }
+        self.leaf_boundary_hook = Some(hook);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+    use std::sync::{Arc, Mutex};
+
+    /// The separators between leaves, read off the leaf chain.
+    fn boundaries(tree: &BPlusTreeMap<u32, u32>) -> BTreeSet<u32> {
+        tree.group_by_leaf()
+            .skip(1)
+            .filter_map(|(keys, _)| keys.first().copied())
+            .collect()
+    }
+
+    #[test]
+    fn test_hook_tracks_boundaries_through_splits_and_merges() {
+        // Boundaries created by splits, and the net number of boundaries
+        let tracked = Arc::new(Mutex::new((BTreeSet::new(), 0usize)));
+        let seen = Arc::clone(&tracked);
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        tree.set_leaf_boundary_hook(move |event: &LeafBoundaryEvent<'_, u32>| {
+            let (separators, count) = &mut *seen.lock().unwrap();
+            match *event {
+                LeafBoundaryEvent::Split {
+                    separator,
+                    left,
+                    right,
+                } => {
+                    assert!(left.max < Some(separator));
+                    assert_eq!(right.min, Some(separator));
+                    assert!(left.len > 0 && right.len > 0);
+                    separators.insert(*separator);
+                    *count += 1;
+                }
+                LeafBoundaryEvent::Merge { separator, merged } => {
+                    assert!(merged.max.is_none_or(|max| max >= separator));
+                    *count -= 1;
+                }
+            }
+        });
+
+        for i in 0..300 {
+            tree.insert(i * 3, i);
+        }
+        assert_eq!(tracked.lock().unwrap().0, boundaries(&tree));
+
+        // Removals also borrow between leaves, which shifts boundaries
+        // without a report, so from here only the count is exact
+        for i in 0..250 {
+            tree.remove(&(i * 3));
+        }
+        assert_eq!(tracked.lock().unwrap().1, tree.leaf_count() - 1);
+
+        let mut batch = crate::WriteBatch::new();
+        for i in 0..500 {
+            batch.insert(i * 2 + 1, i);
+        }
+        tree.apply_batch(batch);
+        assert_eq!(tracked.lock().unwrap().1, tree.leaf_count() - 1);
+    }
+
+    #[test]
+    fn test_cleared_hook_is_not_called() {
+        let calls = Arc::new(Mutex::new(0));
+        let counted = Arc::clone(&calls);
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        tree.set_leaf_boundary_hook(move |_: &LeafBoundaryEvent<'_, u32>| {
+            *counted.lock().unwrap() += 1;
+        });
+        for i in 0..20 {
+            tree.insert(i, i);
+        }
+        let before = *calls.lock().unwrap();
+        assert!(before > 0);
+
+        tree.clear_leaf_boundary_hook();
+        for i in 20..100 {
+            tree.insert(i, i);
+        }
+        assert_eq!(*calls.lock().unwrap(), before);
+    }
+}
@@ -5,32 +5,164 @@
 //!
 //! Updated: Compressed node implementations removed due to memory safety concerns.
 
+// Unit tests may still unwrap and panic
+#![cfg_attr(
+    all(feature = "panic-free", not(test)),
+    deny(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::unreachable
+    )
+)]
+
 // Range imports moved to range_queries.rs module
 
 // Import our new modules
 // arena.rs removed - only compact_arena.rs is used
+mod adaptive_map;
+#[cfg(feature = "tokio")]
+mod async_map;
+mod batch_operations;
+mod bounds;
+mod budgeted;
+mod builder;
+mod bulk_load;
+mod byte_budget;
+mod byte_keys;
+mod cached_tree;
 mod compact_arena;
+mod comparison_counter;
+#[cfg(feature = "benchmark")]
+#[cfg_attr(
+    feature = "panic-free",
+    allow(clippy::unwrap_used, clippy::expect_used)
+)]
 mod comprehensive_performance_benchmark;
+#[cfg(feature = "compressed")]
+mod compressed_values;
 mod construction;
 mod delete_operations;
+mod dense_keys;
+mod dense_map;
+#[cfg(feature = "benchmark")]
+#[cfg_attr(
+    feature = "panic-free",
+    allow(clippy::unwrap_used, clippy::expect_used)
+)]
 mod detailed_iterator_analysis;
+mod digest;
+mod drain;
+mod duplicate_policy;
+mod entry;
 mod error;
+mod eviction;
+mod explain;
+mod fixed_cap_tree;
 mod get_operations;
+#[cfg(feature = "guarded")]
+mod guarded_map;
+mod index_check;
 mod insert_operations;
+mod interning;
+mod invariant;
+mod item_handle;
 mod iteration;
+mod join;
+mod leaf_boundary;
+mod locate;
 mod macros;
+#[cfg(any(test, feature = "testing"))]
+pub mod model_test;
+mod multi_range;
 mod node;
+mod op_log;
+mod portable;
+mod query_context;
 mod range_queries;
+mod recycle_bin;
+mod rekey;
+mod revalue;
+mod scan;
+#[cfg(feature = "shadow")]
+mod shadow_map;
+#[cfg(feature = "testing")]
+pub mod soak;
+mod stable_cursor;
+mod time_series;
+mod tree;
 mod tree_structure;
+mod tree_view;
+mod tuning;
 mod types;
+mod u64_tree;
 mod validation;
+mod watch;
 
 // Generic Arena removed - only CompactArena is used in the implementation
-pub use compact_arena::{CompactArena, CompactArenaStats};
+pub use adaptive_map::{AdaptiveIter, AdaptiveMap, DEFAULT_SMALL_THRESHOLD};
+#[cfg(feature = "tokio")]
+pub use async_map::{AsyncBPlusTreeMap, RangeStream, DEFAULT_ASYNC_BATCH};
+pub use batch_operations::{BatchOp, MergeAction, MergeStats, WriteBatch};
+pub use bounds::{TreeKey, TreeValue};
+pub use budgeted::{Budgeted, PendingInsert, PendingRangeRemoval};
+pub use builder::{BPlusTreeBuilder, Backend};
+pub use byte_budget::ByteBudget;
+pub use byte_keys::{BytesTree, MemComparable};
+pub use cached_tree::{CacheEntry, CachedTree, Loader};
+pub use compact_arena::{CompactArena, CompactArenaStats, NodeStorageStats};
+pub use comparison_counter::{ComparisonStats, CountingMap, CountingOrd};
+#[cfg(feature = "compressed")]
+pub use compressed_values::{CompressedValueMap, DeltaVarintCodec, ValueCodec};
 pub use construction::InitResult as ConstructionResult;
-pub use error::{BPlusTreeError, BTreeResult, BTreeResultExt, InitResult, KeyResult, ModifyResult};
-pub use iteration::{FastItemIterator, ItemIterator, KeyIterator, RangeIterator, ValueIterator};
-pub use types::{BPlusTreeMap, BranchNode, LeafNode, NodeId, NodeRef, NULL_NODE, ROOT_NODE};
+pub use construction::DEFAULT_CAPACITY;
+pub use dense_keys::DenseKey;
+pub use dense_map::DenseU64Map;
+pub use digest::RangeDigest;
+pub use drain::DrainRange;
+pub use duplicate_policy::{DuplicatePolicy, KeepAll, Overwrite, Reject};
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use error::{
+    BPlusTreeError, BTreeResult, BTreeResultExt, BuildError, CapacityError, CasError,
+    CorruptionError, InitResult, KeyResult, ModifyResult, QueryError,
+};
+pub use eviction::EvictFrom;
+pub use explain::{ExplainStep, QueryExplain};
+pub use fixed_cap_tree::{FixedCapIter, FixedCapTree};
+#[cfg(feature = "guarded")]
+pub use guarded_map::GuardedMap;
+pub use index_check::IndexDiscrepancy;
+pub use interning::{InternedKey, KeyInterner};
+pub use item_handle::ItemHandle;
+#[allow(deprecated)]
+pub use iteration::FastItemIterator;
+pub use iteration::{
+    BranchIterator, IntoIter, ItemIterator, ItemIteratorMut, KeyIterator, LeafGroupIterator,
+    LeafIterator, RangeIterator, TryItemIterator, ValueIterator, ValueIteratorMut,
+};
+pub use join::{AlignedIter, InnerJoin, JoinSide, OuterJoin};
+pub use leaf_boundary::{LeafBoundaryEvent, LeafSummary};
+pub use locate::Located;
+pub use multi_range::MultiRange;
+pub use op_log::{OpLog, RecordedOp, RecordingMap};
+pub use portable::{
+    Portable, PortableValue, PORTABLE_MAGIC, PORTABLE_MAX_CAPACITY, PORTABLE_VERSION,
+};
+pub use query_context::QueryContext;
+pub use recycle_bin::{Deleted, RecycleBinMap};
+pub use scan::{ScanOutcome, WeakScan};
+#[cfg(feature = "shadow")]
+pub use shadow_map::ShadowMap;
+pub use stable_cursor::StableCursor;
+pub use time_series::{Downsample, TimeSeriesTree};
+pub use tree_view::TreeView;
+pub use tuning::{TuningReport, TuningRun};
+pub use types::{
+    BPlusTreeMap, BranchNode, DeletionMode, LeafNode, NodeId, NodeRef, OverflowMode,
+    RebalanceStrategy, INLINE_ROOT, MAX_LEAF_GROWTH, MIN_CAPACITY, NULL_NODE, ROOT_NODE,
+};
+pub use u64_tree::{U64Tree, U64TreeIter};
+pub use watch::{WatchEvent, WatchId, WatchedMap};
 
 // PhantomData import moved to tree_structure.rs module
 
@@ -38,7 +170,7 @@ pub use types::{BPlusTreeMap, BranchNode, LeafNode, NodeId, NodeRef, NULL_NODE,
 
 // test module moved to end of file to satisfy clippy (items_after_test_module)
 
-impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
     // ============================================================================
     // CONSTRUCTION
     // ============================================================================
@@ -62,15 +194,15 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
         V: Clone,
     {
         // Validate tree state before insertion
-        if let Err(e) = self.check_invariants_detailed() {
-            return Err(BPlusTreeError::DataIntegrityError(e));
+        if let Err(e) = self.integrity_check() {
+            return Err(CorruptionError::DataIntegrity(e).into());
         }
 
         let old_value = self.insert(key, value);
 
         // Validate tree state after insertion
-        if let Err(e) = self.check_invariants_detailed() {
-            return Err(BPlusTreeError::DataIntegrityError(e));
+        if let Err(e) = self.integrity_check() {
+            return Err(CorruptionError::DataIntegrity(e).into());
         }
 
         Ok(old_value)
@@ -79,47 +211,23 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
     /// Remove with comprehensive error handling
     pub fn try_remove(&mut self, key: &K) -> ModifyResult<V> {
         // Validate tree state before removal
-        if let Err(e) = self.check_invariants_detailed() {
-            return Err(BPlusTreeError::DataIntegrityError(e));
+        if let Err(e) = self.integrity_check() {
+            return Err(CorruptionError::DataIntegrity(e).into());
         }
 
-        let value = self.remove(key).ok_or(BPlusTreeError::KeyNotFound)?;
+        let value = self
+            .remove(key)
+            .ok_or(BPlusTreeError::Query(QueryError::KeyNotFound))?;
 
         // Validate tree state after removal
-        if let Err(e) = self.check_invariants_detailed() {
-            return Err(BPlusTreeError::DataIntegrityError(e));
+        if let Err(e) = self.integrity_check() {
+            return Err(CorruptionError::DataIntegrity(e).into());
         }
 
         Ok(value)
     }
 
-    /// Batch insert operations with rollback on any failure
-    pub fn batch_insert(&mut self, items: Vec<(K, V)>) -> ModifyResult<Vec<Option<V>>>
-    where
-        K: Clone,
-        V: Clone,
-    {
-        let mut results = Vec::new();
-        let mut inserted_keys = Vec::new();
-
-        for (key, value) in items {
-            match self.try_insert(key.clone(), value) {
-                Ok(old_value) => {
-                    results.push(old_value);
-                    inserted_keys.push(key);
-                }
-                Err(e) => {
-                    // Rollback all successful insertions
-                    for rollback_key in inserted_keys {
-                        self.remove(&rollback_key);
-                    }
-                    return Err(e);
-                }
-            }
-        }
-
-        Ok(results)
-    }
+    // batch_insert method moved to batch_operations.rs module
 
     // get_many method moved to get_operations.rs module
 
@@ -219,6 +327,7 @@ mod leaf_caching_tests {
             tree.insert(i, i * 100);
         }
 
+        #[allow(deprecated)]
         let mut fast_iter = tree.items_fast();
         let first_item = fast_iter.next();
         assert_eq!(first_item, Some((&0, &0)));
@@ -235,4 +344,59 @@ mod leaf_caching_tests {
         }
         assert_eq!(count, 20);
     }
+
+    #[test]
+    fn test_group_by_leaf_follows_leaf_layout() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..50 {
+            tree.insert(i, i * 100);
+        }
+        tree.set_deletion_mode(DeletionMode::DeferredRebalance);
+        for i in 10..30 {
+            tree.remove(&i);
+        }
+
+        // One group per non-empty leaf, concatenating to the full contents
+        let groups: Vec<_> = tree.group_by_leaf().collect();
+        let sizes: Vec<usize> = tree.leaf_sizes().into_iter().filter(|&n| n > 0).collect();
+        assert_eq!(
+            groups.iter().map(|(k, _)| k.len()).collect::<Vec<_>>(),
+            sizes
+        );
+        assert!(groups
+            .iter()
+            .flat_map(|(keys, values)| keys.iter().zip(values.iter()))
+            .eq(tree.items()));
+
+        let empty: BPlusTreeMap<i32, i32> = BPlusTreeMap::new(4).unwrap();
+        assert_eq!(empty.group_by_leaf().count(), 0);
+    }
+
+    #[test]
+    fn test_key_and_value_iterators_cache_leaf_references() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..50 {
+            tree.insert(i, i * 100);
+        }
+        // Leave some empty leaves behind for the iterators to skip
+        tree.set_deletion_mode(DeletionMode::DeferredRebalance);
+        for i in 10..30 {
+            tree.remove(&i);
+        }
+
+        let mut keys = tree.keys();
+        assert_eq!(keys.next(), Some(&0));
+        assert!(keys.current_leaf_ref.is_some());
+        let mut values = tree.values();
+        assert_eq!(values.next(), Some(&0));
+        assert!(values.current_leaf_ref.is_some());
+
+        let expected: Vec<i32> = (0..10).chain(30..50).collect();
+        assert_eq!(tree.keys().cloned().collect::<Vec<_>>(), expected);
+        assert!(tree.values().eq(tree.items().map(|(_, v)| v)));
+        assert!(tree
+            .range(5..=35)
+            .map(|(k, _)| *k)
+            .eq(expected.iter().cloned().filter(|k| (5..=35).contains(k))));
+    }
 }
@@ -0,0 +1,276 @@
+//! Check-then-act on one key with a single descent.
+//!
+//! `if !tree.contains_key(&k) { tree.insert(k, v); }` walks from the root to
+//! the leaf twice. [`BPlusTreeMap::locate`] walks once and keeps the path it
+//! took in a [`Located`] handle, which can then answer whether the key is
+//! present and insert or remove it starting from that leaf. The handle
+//! borrows the tree mutably, so the path cannot go stale between the check
+//! and the write.
+//!
+//! [`Located::or_insert`] and [`Located::or_default`] play the part of the
+//! standard library's `Entry` API: `*tree.locate(key).or_default() += 1`
+//! counts with one descent. [`BPlusTreeMap::entry`] wraps the same handle in
+//! the standard `Entry` shape for code ported from `BTreeMap`.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::invariant::invariant;
+use crate::types::{BPlusTreeMap, DeletionMode, NodeId, MAX_HEIGHT, NULL_NODE};
+
+/// A key's position in a tree, found by [`BPlusTreeMap::locate`].
+///
+/// Holds the branches passed on the way down and the slot in the leaf where
+/// the key is or would go. Consuming it with [`insert`](Self::insert) or
+/// [`remove`](Self::remove) splits or rebalances up that path without
+/// descending again.
+pub struct Located<'a, K, V> {
+    tree: &'a mut BPlusTreeMap<K, V>,
+    key: K,
+    path: [(NodeId, usize); MAX_HEIGHT],
+    depth: usize,
+    // None only if the descent hit a missing node
+    leaf: Option<(NodeId, usize, bool)>,
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Find where `key` is or would go, for a following insert or remove.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..100 {
+    ///     tree.insert(i * 2, i);
+    /// }
+    ///
+    /// // Insert only if absent, with one descent
+    /// let slot = tree.locate(51);
+    /// assert!(!slot.is_found());
+    /// assert_eq!(slot.insert(-1), None);
+    ///
+    /// let slot = tree.locate(40);
+    /// assert_eq!(slot.get(), Some(&20));
+    /// assert_eq!(slot.remove(), Some(20));
+    /// assert_eq!(tree.get(&51), Some(&-1));
+    /// assert!(!tree.contains_key(&40));
+    /// ```
+    pub fn locate(&mut self, key: K) -> Located<'_, K, V> {
+        let mut path = [(NULL_NODE, 0usize); MAX_HEIGHT];
+        let found = self.path_to_leaf(&key, &mut path);
+        let depth = found.map_or(0, |(_, depth)| depth);
+        let leaf = found.and_then(|(leaf_id, _)| {
+            let position = self.get_leaf(leaf_id)?.binary_search_keys(&key);
+            Some(match position {
+                Ok(index) => (leaf_id, index, true),
+                Err(index) => (leaf_id, index, false),
+            })
+        });
+        Located {
+            tree: self,
+            key,
+            path,
+            depth,
+            leaf,
+        }
+    }
+}
+
+impl<'a, K: TreeKey, V: TreeValue> Located<'a, K, V> {
+    /// The key that was located.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns true if the key is in the tree.
+    pub fn is_found(&self) -> bool {
+        matches!(self.leaf, Some((_, _, true)))
+    }
+
+    /// The value stored under the key, if present.
+    pub fn get(&self) -> Option<&V> {
+        match self.leaf {
+            Some((leaf_id, index, true)) => self.tree.get_leaf(leaf_id)?.get_value(index),
+            _ => None,
+        }
+    }
+
+    /// Mutable access to the value stored under the key, if present.
+    pub fn get_mut(&mut self) -> Option<&mut V> {
+        match self.leaf {
+            Some((leaf_id, index, true)) => self.tree.get_leaf_mut(leaf_id)?.get_value_mut(index),
+            _ => None,
+        }
+    }
+
+    /// Mutable access to the value stored under the key, if present, for as
+    /// long as the tree stays borrowed.
+    pub fn into_mut(self) -> Option<&'a mut V> {
+        match self.leaf {
+            Some((leaf_id, index, true)) => self.tree.get_leaf_mut(leaf_id)?.get_value_mut(index),
+            _ => None,
+        }
+    }
+
+    /// Give back the key without touching the tree.
+    pub(crate) fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Insert `value` under the key, returning the previous value if any.
+    /// Behaves exactly like [`BPlusTreeMap::insert`], starting from the
+    /// located leaf.
+    pub fn insert(self, value: V) -> Option<V> {
+        let (leaf_id, _, _) = self.leaf?;
+        self.tree
+            .insert_along_path(&self.path[..self.depth], leaf_id, self.key, value)
+            .0
+    }
+
+    /// Remove the key, returning its value if it was present. Behaves
+    /// exactly like [`BPlusTreeMap::remove`], starting from the located leaf.
+    pub fn remove(self) -> Option<V> {
+        let (leaf_id, index, true) = self.leaf? else {
+            return None;
+        };
+        if self.tree.deletion_mode == DeletionMode::DeferredRebalance {
+            return self
+                .tree
+                .get_leaf_mut(leaf_id)?
+                .remove_at(index)
+                .map(|(_, value)| value);
+        }
+        self.tree
+            .remove_along_path(&self.path[..self.depth], leaf_id, &self.key)
+    }
+
+    /// The value under the key, inserting `value` first if the key is absent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// *tree.locate("a").or_insert(10) += 1;
+    /// *tree.locate("a").or_insert(10) += 1;
+    /// assert_eq!(tree.get(&"a"), Some(&12));
+    /// ```
+    pub fn or_insert(self, value: V) -> &'a mut V {
+        self.or_insert_with(|| value)
+    }
+
+    /// The value under the key, inserting the result of `default` first if
+    /// the key is absent. `default` is only called for a missing key.
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'a mut V {
+        let Located {
+            tree,
+            key,
+            path,
+            depth,
+            leaf,
+        } = self;
+        let position = match leaf {
+            Some((leaf_id, index, true)) => Some((leaf_id, index)),
+            // The insert reports where the entry landed, even after a split
+            Some((leaf_id, _, false)) => {
+                tree.insert_along_path(&path[..depth], leaf_id, key, default())
+                    .1
+            }
+            None => None,
+        };
+        let (leaf_id, index) = invariant(position, "located key is in the tree");
+        invariant(
+            tree.get_leaf_mut(leaf_id)
+                .and_then(|leaf| leaf.get_value_mut(index)),
+            "located key is in the tree",
+        )
+    }
+
+    /// The value under the key, inserting `V::default()` first if the key is
+    /// absent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut counts = BPlusTreeMap::new(4).unwrap();
+    /// for word in "the cat saw the dog".split(' ') {
+    ///     *counts.locate(word).or_default() += 1;
+    /// }
+    /// assert_eq!(counts.get(&"the"), Some(&2));
+    /// assert_eq!(counts.get(&"dog"), Some(&1));
+    /// ```
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+
+    #[test]
+    fn test_locate_matches_insert_and_remove() {
+        let mut located = BPlusTreeMap::new(4).unwrap();
+        let mut plain = BPlusTreeMap::new(4).unwrap();
+        let mut rng = SeededRng::new(5);
+        for i in 0..4_000u32 {
+            let key = rng.below(600) as u32;
+            let slot = located.locate(key);
+            assert_eq!(slot.is_found(), plain.contains_key(&key));
+            assert_eq!(slot.get(), plain.get(&key));
+            if i % 3 == 0 {
+                assert_eq!(slot.remove(), plain.remove(&key));
+            } else {
+                assert_eq!(slot.insert(i), plain.insert(key, i));
+            }
+        }
+        located.check_invariants_detailed().unwrap();
+        assert!(located.items().eq(plain.items()));
+    }
+
+    #[test]
+    fn test_or_default_counts_through_splits() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        let mut model = std::collections::BTreeMap::new();
+        let mut rng = SeededRng::new(9);
+        for _ in 0..3_000 {
+            let key = rng.below(700) as u32;
+            *tree.locate(key).or_default() += 1u32;
+            *model.entry(key).or_default() += 1;
+        }
+        tree.check_invariants_detailed().unwrap();
+        assert!(tree.items().eq(model.iter()));
+
+        let calls = std::cell::Cell::new(0);
+        let present = *model.keys().next().unwrap();
+        tree.locate(present).or_insert_with(|| {
+            calls.set(calls.get() + 1);
+            0
+        });
+        assert_eq!(calls.get(), 0);
+        assert_eq!(tree.get(&present), model.get(&present));
+    }
+
+    #[test]
+    fn test_locate_deferred_remove_and_get_mut() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..50 {
+            tree.insert(i, i);
+        }
+        tree.set_deletion_mode(DeletionMode::DeferredRebalance);
+        if let Some(value) = tree.locate(7).get_mut() {
+            *value = 70;
+        }
+        assert_eq!(tree.get(&7), Some(&70));
+        assert_eq!(tree.locate(7).remove(), Some(70));
+        assert_eq!(tree.locate(7).remove(), None);
+        assert_eq!(tree.len(), 49);
+    }
+}
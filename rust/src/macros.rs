@@ -227,6 +227,65 @@ macro_rules! process_range_bounds {
     }};
 }
 
+/// Build a [`BPlusTreeMap`](crate::BPlusTreeMap) from `key => value` pairs.
+///
+/// The pairs go into one [`WriteBatch`](crate::WriteBatch), so the tree is
+/// loaded and rebalanced in a single pass rather than split insert by
+/// insert. A repeated key keeps its last value. Start with `capacity: n;` to
+/// choose the node capacity, which otherwise is the default.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::bptree;
+///
+/// let tree = bptree! { 1 => "a", 2 => "b", 3 => "c" };
+/// assert_eq!(tree.get(&2), Some(&"b"));
+///
+/// let small = bptree! { capacity: 4; 3 => 30, 1 => 10, 2 => 20, 1 => 11 };
+/// assert_eq!(small.capacity(), 4);
+/// assert_eq!(small.items().collect::<Vec<_>>(), [(&1, &11), (&2, &20), (&3, &30)]);
+/// ```
+#[macro_export]
+macro_rules! bptree {
+    (capacity: $capacity:expr; $($key:expr => $value:expr),* $(,)?) => {{
+        let mut tree = $crate::BPlusTreeMap::new($capacity).expect("invalid capacity");
+        #[allow(unused_mut)]
+        let mut batch = $crate::WriteBatch::new();
+        $(batch.insert($key, $value);)*
+        tree.apply_batch(batch);
+        tree
+    }};
+    ($($key:expr => $value:expr),* $(,)?) => {
+        $crate::bptree! { capacity: $crate::DEFAULT_CAPACITY; $($key => $value),* }
+    };
+}
+
+/// Build a set of keys, a [`BPlusTreeMap`](crate::BPlusTreeMap) with unit
+/// values, the same way as [`bptree!`].
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::bptreeset;
+///
+/// let primes = bptreeset![2, 3, 5, 7, 11];
+/// assert!(primes.contains_key(&7));
+/// assert_eq!(primes.keys().copied().collect::<Vec<_>>(), [2, 3, 5, 7, 11]);
+///
+/// let small = bptreeset![capacity: 4; 9, 8, 7, 9];
+/// assert_eq!(small.len(), 3);
+/// ```
+#[macro_export]
+macro_rules! bptreeset {
+    (capacity: $capacity:expr; $($key:expr),* $(,)?) => {
+        $crate::bptree! { capacity: $capacity; $($key => ()),* }
+    };
+    ($($key:expr),* $(,)?) => {
+        $crate::bptree! { $($key => ()),* }
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use crate::BPlusTreeMap;
@@ -311,4 +370,24 @@ mod tests {
 
         assert_eq!(tree.len(), 10);
     }
+
+    #[test]
+    fn test_bptree_macros_match_inserts() {
+        let empty: BPlusTreeMap<i32, i32> = bptree! {};
+        assert!(empty.is_empty());
+
+        let tree =
+            bptree! { capacity: 4; 5 => 'e', 1 => 'a', 3 => 'c', 2 => 'b', 4 => 'd', 1 => 'z' };
+        let mut expected = BPlusTreeMap::new(4).unwrap();
+        for (key, value) in [(5, 'e'), (1, 'a'), (3, 'c'), (2, 'b'), (4, 'd'), (1, 'z')] {
+            expected.insert(key, value);
+        }
+        assert!(tree.items().eq(expected.items()));
+        assert_tree_valid!(tree);
+
+        let set = bptreeset![capacity: 4; 10, 20, 30, 40, 50, 60];
+        assert_eq!(set.len(), 6);
+        assert!(set.leaf_count() > 1);
+        assert_tree_valid!(set);
+    }
 }
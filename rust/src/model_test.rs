@@ -0,0 +1,216 @@
+//! Differential testing against `std::collections::BTreeMap`.
+//!
+//! Enabled with the `testing` feature. [`run`] applies one operation stream to
+//! a [`BPlusTreeMap`] and to a `BTreeMap` model, and reports the first step at
+//! which their results, contents or iteration order diverge, or at which the
+//! tree breaks one of its invariants. [`random_ops`] generates such streams,
+//! and [`SeededRng`] is the generator behind it, for tests that need their
+//! own reproducible workload.
+//!
+//! ```
+//! use bplustree::model_test::{self, Op};
+//!
+//! let ops = model_test::random_ops(42, 1_000, 200);
+//! model_test::run(4, &ops).unwrap();
+//!
+//! model_test::run(16, &[Op::Insert(1, 10), Op::Get(1), Op::Remove(1)]).unwrap();
+//! ```
+
+use crate::types::BPlusTreeMap;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// One step of a differential test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op<K, V> {
+    Insert(K, V),
+    Remove(K),
+    Get(K),
+    ContainsKey(K),
+    /// Compare the entries in the half-open range `start..end`.
+    Range(K, K),
+    First,
+    Last,
+    Clear,
+}
+
+/// The first divergence between the tree and the model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Index of the operation after which the divergence was found.
+    pub step: usize,
+    /// Debug rendering of that operation.
+    pub op: String,
+    /// What differed.
+    pub detail: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "step {} ({}): {}", self.step, self.op, self.detail)
+    }
+}
+
+impl std::error::Error for Mismatch {}
+
+/// Apply `ops` to a new tree of the given capacity and to a `BTreeMap`,
+/// checking every result, the length, the invariants and the full iteration
+/// order after each step.
+pub fn run<K, V>(capacity: usize, ops: &[Op<K, V>]) -> Result<(), Mismatch>
+where
+    K: Ord + Clone + fmt::Debug,
+    V: Clone + PartialEq + fmt::Debug,
+{
+    let mut tree = BPlusTreeMap::new(capacity).map_err(|e| Mismatch {
+        step: 0,
+        op: "new".to_string(),
+        detail: e.to_string(),
+    })?;
+    let mut model = BTreeMap::new();
+
+    for (step, op) in ops.iter().enumerate() {
+        let mismatch = |detail: String| Mismatch {
+            step,
+            op: format!("{:?}", op),
+            detail,
+        };
+        let (got, expected) = match op {
+            Op::Insert(k, v) => (
+                format!("{:?}", tree.insert(k.clone(), v.clone())),
+                format!("{:?}", model.insert(k.clone(), v.clone())),
+            ),
+            Op::Remove(k) => (
+                format!("{:?}", tree.remove(k)),
+                format!("{:?}", model.remove(k)),
+            ),
+            Op::Get(k) => (format!("{:?}", tree.get(k)), format!("{:?}", model.get(k))),
+            Op::ContainsKey(k) => (
+                format!("{:?}", tree.contains_key(k)),
+                format!("{:?}", model.contains_key(k)),
+            ),
+            Op::Range(start, end) if start <= end => (
+                format!("{:?}", tree.range(start..end).collect::<Vec<_>>()),
+                format!("{:?}", model.range(start..end).collect::<Vec<_>>()),
+            ),
+            // BTreeMap panics on inverted ranges; the tree yields nothing
+            Op::Range(start, end) => (
+                format!("{:?}", tree.range(start..end).collect::<Vec<_>>()),
+                "[]".to_string(),
+            ),
+            Op::First => (
+                format!("{:?}", tree.first()),
+                format!("{:?}", model.first_key_value()),
+            ),
+            Op::Last => (
+                format!("{:?}", tree.last()),
+                format!("{:?}", model.last_key_value()),
+            ),
+            Op::Clear => {
+                tree.clear();
+                model.clear();
+                (String::new(), String::new())
+            }
+        };
+        if got != expected {
+            return Err(mismatch(format!(
+                "tree returned {}, model returned {}",
+                got, expected
+            )));
+        }
+
+        if let Err(e) = tree.check_invariants_detailed() {
+            return Err(mismatch(format!("invariant violated: {}", e)));
+        }
+        if tree.len() != model.len() {
+            return Err(mismatch(format!(
+                "tree has {} entries, model has {}",
+                tree.len(),
+                model.len()
+            )));
+        }
+        if !tree.items().eq(model.iter()) {
+            return Err(mismatch("iteration order differs".to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+/// A small seeded pseudo-random generator, so that the crate's tests are
+/// reproducible without a `rand` dependency in the library.
+#[derive(Debug, Clone)]
+pub struct SeededRng(u64);
+
+impl SeededRng {
+    /// A generator whose sequence is fixed by `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// The next raw 64-bit state. Its low bits are weak; prefer [`below`](Self::below).
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.0
+    }
+
+    /// The next number in `0..bound`, or 0 if `bound` is 0.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        (self.next_u64() >> 33).checked_rem(bound).unwrap_or(0)
+    }
+}
+
+/// Generate a reproducible stream of `count` operations over keys in
+/// `0..key_space`, weighted towards inserts and removals.
+pub fn random_ops(seed: u64, count: usize, key_space: i32) -> Vec<Op<i32, i32>> {
+    let key_space = key_space.max(1);
+    let mut rng = SeededRng::new(seed);
+    let mut next = move |bound: i32| rng.below(bound as u64) as i32;
+
+    (0..count)
+        .map(|i| match next(100) {
+            0..=44 => Op::Insert(next(key_space), i as i32),
+            45..=79 => Op::Remove(next(key_space)),
+            80..=87 => Op::Get(next(key_space)),
+            88..=91 => Op::ContainsKey(next(key_space)),
+            92..=96 => {
+                let start = next(key_space);
+                Op::Range(start, start + next(key_space / 4 + 1))
+            }
+            97 => Op::First,
+            98 => Op::Last,
+            _ => Op::Clear,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_streams_match_model() {
+        for capacity in [4, 5, 8, 32] {
+            for seed in 0..10 {
+                let ops = random_ops(seed, 2_000, 300);
+                if let Err(mismatch) = run(capacity, &ops) {
+                    panic!("capacity {}: {}", capacity, mismatch);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_capacity_is_reported() {
+        let mismatch = run::<i32, i32>(1, &[]).unwrap_err();
+        assert_eq!(mismatch.step, 0);
+        assert_eq!(mismatch.op, "new");
+    }
+
+    #[test]
+    fn test_inverted_range_yields_nothing() {
+        run(4, &[Op::Insert(1, 1), Op::Insert(5, 5), Op::Range(5, 1)]).unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+//! Reading several key ranges in one pass.
+//!
+//! A query engine that pushes an `IN` list or a disjunction of range
+//! predicates down to the tree would otherwise run one [`range`] per term,
+//! descending from the root each time. [`BPlusTreeMap::multi_range`] sorts
+//! the ranges and walks them with a single forward cursor. When the next
+//! range starts in the leaf the cursor is on, or the one after it, the
+//! cursor carries on along the leaf chain instead of descending again, so
+//! clustered ranges cost little more than one scan over them.
+//!
+//! [`range`]: BPlusTreeMap::range
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::types::{BPlusTreeMap, LeafNode, NULL_NODE};
+use std::cmp::Ordering;
+use std::iter::FusedIterator;
+use std::ops::{Bound, RangeBounds};
+
+/// Iterator over the entries in any of several ranges, in key order.
+/// Returned by [`BPlusTreeMap::multi_range`].
+pub struct MultiRange<'a, K, V> {
+    tree: &'a BPlusTreeMap<K, V>,
+    // Remaining ranges, sorted by start
+    ranges: std::vec::IntoIter<(Bound<K>, Bound<K>)>,
+    // End of the range being read, or `None` between ranges
+    end: Option<Bound<K>>,
+    // The cursor: the entry at `index` in `leaf`, or the start of the next
+    // leaf if `index` is past the end
+    leaf: Option<&'a LeafNode<K, V>>,
+    index: usize,
+    // Descents from the root so far
+    descents: usize,
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Iterate over the entries whose key lies in any of `ranges`, in key
+    /// order, each entry once.
+    ///
+    /// The ranges may be given in any order and may overlap; the result is
+    /// their union. Ranges that start close to where the previous one ended
+    /// are reached along the leaf chain without a new descent from the root.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..100 {
+    ///     tree.insert(i, i * 10);
+    /// }
+    ///
+    /// // WHERE id IN (42, 7, 8) OR id BETWEEN 90 AND 92
+    /// let hits: Vec<_> = tree
+    ///     .multi_range(&[42..=42, 90..=92, 7..=8])
+    ///     .map(|(key, _)| *key)
+    ///     .collect();
+    /// assert_eq!(hits, [7, 8, 42, 90, 91, 92]);
+    /// ```
+    pub fn multi_range<R: RangeBounds<K>>(&self, ranges: &[R]) -> MultiRange<'_, K, V> {
+        let mut bounds: Vec<_> = ranges
+            .iter()
+            .map(|range| (range.start_bound().cloned(), range.end_bound().cloned()))
+            .collect();
+        bounds.sort_by(|a, b| compare_starts(&a.0, &b.0));
+        MultiRange {
+            tree: self,
+            ranges: bounds.into_iter(),
+            end: None,
+            leaf: None,
+            index: 0,
+            descents: 0,
+        }
+    }
+}
+
+impl<'a, K: TreeKey, V: TreeValue> MultiRange<'a, K, V> {
+    /// The leaf after `leaf` in the chain.
+    fn next_leaf(&self, leaf: &LeafNode<K, V>) -> Option<&'a LeafNode<K, V>> {
+        if leaf.next == NULL_NODE {
+            None
+        } else {
+            self.tree.get_leaf(leaf.next)
+        }
+    }
+
+    /// Move the cursor forward to the first entry at or after `start`. The
+    /// cursor never moves back, so entries already yielded are not repeated.
+    fn seek(&mut self, start: Bound<&K>) {
+        let reached = |key: &K| match start {
+            Bound::Included(start) => key >= start,
+            Bound::Excluded(start) => key > start,
+            Bound::Unbounded => true,
+        };
+        if let Some(leaf) = self.leaf {
+            // Stay on the chain if the start is in this leaf or the next one
+            if leaf.keys.last().is_some_and(reached) {
+                let skip = leaf.keys.partition_point(|key| !reached(key));
+                self.index = self.index.max(skip);
+                return;
+            }
+            if let Some(next) = self.next_leaf(leaf) {
+                if next.keys.last().is_some_and(reached) {
+                    self.leaf = Some(next);
+                    self.index = next.keys.partition_point(|key| !reached(key));
+                    return;
+                }
+            }
+        }
+        self.descents += 1;
+        let position = self.tree.range_start_position(start);
+        self.leaf = position.and_then(|(leaf_id, _)| self.tree.get_leaf(leaf_id));
+        self.index = position.map_or(0, |(_, index)| index);
+    }
+}
+
+impl<'a, K: TreeKey, V: TreeValue> Iterator for MultiRange<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(end) = &self.end {
+                // Step over the rest of exhausted or emptied leaves
+                let leaf = loop {
+                    let leaf = self.leaf?;
+                    if self.index < leaf.keys.len() {
+                        break leaf;
+                    }
+                    self.leaf = self.next_leaf(leaf);
+                    self.index = 0;
+                };
+                let key = &leaf.keys[self.index];
+                let in_range = match end {
+                    Bound::Included(end) => key <= end,
+                    Bound::Excluded(end) => key < end,
+                    Bound::Unbounded => true,
+                };
+                if in_range {
+                    let value = &leaf.values[self.index];
+                    self.index += 1;
+                    return Some((key, value));
+                }
+                self.end = None;
+            }
+            let (start, end) = self.ranges.next()?;
+            self.seek(start.as_ref());
+            self.end = Some(end);
+        }
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> FusedIterator for MultiRange<'_, K, V> {}
+
+/// Order range starts by the first key each admits.
+fn compare_starts<K: Ord>(a: &Bound<K>, b: &Bound<K>) -> Ordering {
+    match (a, b) {
+        (Bound::Unbounded, Bound::Unbounded) => Ordering::Equal,
+        (Bound::Unbounded, _) => Ordering::Less,
+        (_, Bound::Unbounded) => Ordering::Greater,
+        (Bound::Included(x) | Bound::Excluded(x), Bound::Included(y) | Bound::Excluded(y)) => {
+            let excluded = |bound: &Bound<K>| matches!(bound, Bound::Excluded(_));
+            x.cmp(y).then(excluded(a).cmp(&excluded(b)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use crate::DeletionMode;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_multi_range_is_union_of_ranges() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        let mut model = BTreeMap::new();
+        let mut rng = SeededRng::new(17);
+        for _ in 0..1_500 {
+            let key = rng.below(3_000) as u32;
+            tree.insert(key, key);
+            model.insert(key, key);
+        }
+        tree.set_deletion_mode(DeletionMode::DeferredRebalance);
+        for _ in 0..500 {
+            let key = rng.below(3_000) as u32;
+            tree.remove(&key);
+            model.remove(&key);
+        }
+
+        for round in 0..200 {
+            let ranges: Vec<(Bound<u32>, Bound<u32>)> = (0..1 + round % 8)
+                .map(|_| {
+                    let start = rng.below(3_100) as u32;
+                    let end = start + rng.below(200) as u32;
+                    let bound = |key, kind| match kind {
+                        0 => Bound::Included(key),
+                        1 => Bound::Excluded(key),
+                        _ => Bound::Unbounded,
+                    };
+                    (bound(start, rng.below(5) / 2), bound(end, rng.below(5) / 2))
+                })
+                .collect();
+            let got: Vec<u32> = tree.multi_range(&ranges).map(|(key, _)| *key).collect();
+            let expected: Vec<u32> = model
+                .keys()
+                .copied()
+                .filter(|key| ranges.iter().any(|range| range.contains(key)))
+                .collect();
+            assert_eq!(got, expected, "ranges {:?}", ranges);
+        }
+    }
+
+    #[test]
+    fn test_clustered_ranges_share_one_descent() {
+        let mut tree = BPlusTreeMap::new(16).unwrap();
+        for i in 0..10_000u32 {
+            tree.insert(i, i);
+        }
+
+        let points: Vec<_> = (5_000..5_040).step_by(3).map(|i| i..=i).collect();
+        let mut iter = tree.multi_range(&points);
+        assert_eq!(iter.by_ref().count(), points.len());
+        assert_eq!(iter.descents, 1);
+
+        let spread: Vec<_> = (0..10).map(|i| i * 1_000..i * 1_000 + 2).collect();
+        let mut iter = tree.multi_range(&spread);
+        assert_eq!(iter.by_ref().count(), 20);
+        assert_eq!(iter.descents, 10);
+    }
+}
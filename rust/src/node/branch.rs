@@ -0,0 +1,321 @@
+//! Branch nodes: separator keys and child references.
+//!
+//! Every leaf sits at the same depth, so the children of one branch are
+//! either all leaves or all branches. A branch stores its children as a
+//! packed array of arena ids with that kind recorded once, rather than one
+//! [`NodeRef`] per child, which keeps the array half the size and lets a
+//! descent scan plain `u32`s. [`BranchNode::child`] and
+//! [`BranchNode::children`] rebuild `NodeRef`s on the way out.
+
+use super::{min_keys_for, spare_bytes, split_off_slots, trim_slots, NodeRef, NodeVec};
+use crate::types::NodeId;
+use std::marker::PhantomData;
+
+/// Internal (branch) node containing keys and child pointers.
+#[derive(Debug, Clone)]
+pub struct BranchNode<K, V> {
+    /// Maximum number of keys this node can hold.
+    pub(crate) capacity: usize,
+    /// Sorted list of separator keys.
+    pub(crate) keys: NodeVec<K>,
+    /// Arena ids of the child nodes, one more than there are keys.
+    pub(crate) child_ids: NodeVec<NodeId>,
+    /// Whether the children are leaves rather than branches.
+    pub(crate) children_are_leaves: bool,
+    _marker: PhantomData<(K, V)>,
+}
+
+impl<K, V> BranchNode<K, V> {
+    /// Assemble a branch from its parts.
+    pub(crate) fn from_parts(
+        capacity: usize,
+        keys: NodeVec<K>,
+        child_ids: NodeVec<NodeId>,
+        children_are_leaves: bool,
+    ) -> Self {
+        Self {
+            capacity,
+            keys,
+            child_ids,
+            children_are_leaves,
+            _marker: PhantomData,
+        }
+    }
+
+    // ============================================================================
+    // CHILD ACCESS
+    // ============================================================================
+
+    /// A reference to the child node with arena id `id`.
+    #[inline]
+    fn child_ref(&self, id: NodeId) -> NodeRef<K, V> {
+        if self.children_are_leaves {
+            NodeRef::leaf(id)
+        } else {
+            NodeRef::branch(id)
+        }
+    }
+
+    /// The child at `index`, if there is one.
+    #[inline]
+    pub fn child(&self, index: usize) -> Option<NodeRef<K, V>> {
+        self.child_ids.get(index).map(|&id| self.child_ref(id))
+    }
+
+    /// The children in key order.
+    pub fn children(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = NodeRef<K, V>> + ExactSizeIterator + '_ {
+        self.child_ids.iter().map(|&id| self.child_ref(id))
+    }
+
+    /// Returns the number of children.
+    #[inline]
+    pub fn child_count(&self) -> usize {
+        self.child_ids.len()
+    }
+
+    /// Record the kind of `child` if this branch has no children yet, and
+    /// return its id.
+    fn adopt(&mut self, child: NodeRef<K, V>) -> NodeId {
+        if self.child_ids.is_empty() {
+            self.children_are_leaves = child.is_leaf();
+        }
+        debug_assert_eq!(child.is_leaf(), self.children_are_leaves);
+        child.id()
+    }
+
+    /// Append `child` after the existing children.
+    pub(crate) fn push_child(&mut self, child: NodeRef<K, V>) {
+        let id = self.adopt(child);
+        self.child_ids.push(id);
+    }
+
+    /// Insert `child` at `index`, shifting later children right.
+    pub(crate) fn insert_child(&mut self, index: usize, child: NodeRef<K, V>) {
+        let id = self.adopt(child);
+        self.child_ids.insert(index, id);
+    }
+
+    /// Remove and return the child at `index`.
+    pub(crate) fn remove_child(&mut self, index: usize) -> NodeRef<K, V> {
+        let id = self.child_ids.remove(index);
+        self.child_ref(id)
+    }
+
+    /// Remove and return the last child.
+    pub(crate) fn pop_child(&mut self) -> Option<NodeRef<K, V>> {
+        let id = self.child_ids.pop()?;
+        Some(self.child_ref(id))
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> BranchNode<K, V> {
+    // ============================================================================
+    // INSERT OPERATIONS
+    // ============================================================================
+
+    /// Insert a separator key and new child into this branch node.
+    /// Returns None if no split needed, or Some((new_branch_data, promoted_key)) if split occurred.
+    /// The caller should handle arena allocation for the split data.
+    pub fn insert_child_and_split_if_needed(
+        &mut self,
+        child_index: usize,
+        separator_key: K,
+        new_child: NodeRef<K, V>,
+    ) -> Option<(BranchNode<K, V>, K)> {
+        // Check if split is needed BEFORE inserting
+        if self.is_full() {
+            // Branch is at capacity, need to handle split
+            // For branches, we MUST insert first because split promotes a key
+            // With capacity=4: 4 keys → split needs 5 keys (2 left + 1 promoted + 2 right)
+            self.keys.insert(child_index, separator_key);
+            self.insert_child(child_index + 1, new_child);
+
+            // Now split the overfull branch
+            let (new_right, promoted_key) = self.split_data();
+            Some((new_right, promoted_key))
+        } else {
+            // Room to insert without splitting
+            self.keys.insert(child_index, separator_key);
+            self.insert_child(child_index + 1, new_child);
+            None
+        }
+    }
+
+    /// Split this branch node, returning the new right node and promoted key.
+    pub fn split_data(&mut self) -> (BranchNode<K, V>, K) {
+        // For branch nodes, we need to ensure both resulting nodes have at least min_keys
+        // The middle key gets promoted, so we need at least min_keys on each side
+        let min_keys = self.min_keys();
+        let _total_keys = self.keys.len();
+
+        // For branch splits, we promote the middle key, so we need:
+        // - Left side: min_keys keys
+        // - Middle: 1 key (promoted)
+        // - Right side: min_keys keys
+        // Total needed: min_keys + 1 + min_keys
+        let mid = min_keys;
+
+        // Extract the promoted key
+        let promoted_key = self.keys[mid].clone();
+
+        // Split keys and children
+        let right_keys = split_off_slots(&mut self.keys, mid + 1, self.capacity + 1); // Skip the promoted key
+        let right_children = split_off_slots(&mut self.child_ids, mid + 1, self.capacity + 2);
+
+        // Remove the promoted key from left side
+        self.keys.pop(); // Remove the key that was promoted
+
+        // Create the new right branch
+        let new_right = BranchNode::from_parts(
+            self.capacity,
+            right_keys,
+            right_children,
+            self.children_are_leaves,
+        );
+
+        (new_right, promoted_key)
+    }
+
+    // ============================================================================
+    // STATUS CHECKS
+    // ============================================================================
+
+    /// Returns true if this branch node is empty.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns true if this branch node is at capacity.
+    pub fn is_full(&self) -> bool {
+        self.keys.len() >= self.capacity
+    }
+
+    /// Returns true if this branch node is underfull (below minimum occupancy).
+    #[inline]
+    pub fn is_underfull(&self) -> bool {
+        self.keys.len() < self.min_keys()
+    }
+
+    /// Returns true if this branch can donate a key to a sibling.
+    #[inline]
+    pub fn can_donate(&self) -> bool {
+        self.keys.len() > self.min_keys()
+    }
+
+    // ============================================================================
+    // OTHER HELPERS
+    // ============================================================================
+
+    /// Returns the minimum number of keys this branch should have.
+    #[inline]
+    pub fn min_keys(&self) -> usize {
+        // Exception: root can have fewer keys
+        min_keys_for(self.capacity)
+    }
+
+    /// Release vector storage beyond what a full branch needs. Branches
+    /// insert before splitting, so full is one key and one child more than
+    /// `capacity` allows at rest.
+    pub(crate) fn shrink_excess(&mut self) {
+        trim_slots(&mut self.keys, self.capacity + 1);
+        trim_slots(&mut self.child_ids, self.capacity + 2);
+    }
+
+    /// Bytes reserved by the key and child vectors but not in use.
+    pub(crate) fn spare_bytes(&self) -> usize {
+        spare_bytes(&self.keys) + spare_bytes(&self.child_ids)
+    }
+
+    /// Find the index of the child that should contain the given key.
+    #[inline]
+    pub fn find_child_index(&self, key: &K) -> usize {
+        // Binary search to find the appropriate child
+        match self.keys.binary_search(key) {
+            Ok(index) => index + 1, // Key found, go to right child
+            Err(index) => index,    // Key not found, index is the insertion point
+        }
+    }
+
+    /// Returns the number of keys in this branch node.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns true if this branch node needs to be split.
+    /// We allow one extra key beyond capacity to ensure proper splitting.
+    pub fn needs_split(&self) -> bool {
+        self.keys.len() > self.capacity
+    }
+
+    /// Get the child node for a given key.
+    #[inline]
+    pub fn get_child(&self, key: &K) -> Option<NodeRef<K, V>> {
+        self.child(self.find_child_index(key))
+    }
+
+    // ============================================================================
+    // BORROWING AND MERGING HELPERS
+    // ============================================================================
+
+    /// Borrow the last key and child from this branch (used when this is the left sibling)
+    pub fn borrow_last(&mut self) -> Option<(K, NodeRef<K, V>)> {
+        if self.keys.is_empty() || !self.can_donate() {
+            return None;
+        }
+        let key = self.keys.pop()?;
+        let child = self.pop_child()?;
+        Some((key, child))
+    }
+
+    /// Borrow the first key and child from this branch (used when this is the right sibling)
+    pub fn borrow_first(&mut self) -> Option<(K, NodeRef<K, V>)> {
+        if self.keys.is_empty() || !self.can_donate() {
+            return None;
+        }
+        let key = self.keys.remove(0);
+        let child = self.remove_child(0);
+        Some((key, child))
+    }
+
+    /// Accept a borrowed key and child at the beginning (from left sibling)
+    /// The separator becomes the first key, and the moved child becomes the first child
+    pub fn accept_from_left(
+        &mut self,
+        separator: K,
+        moved_key: K,
+        moved_child: NodeRef<K, V>,
+    ) -> K {
+        self.keys.insert(0, separator);
+        self.insert_child(0, moved_child);
+        self.shrink_excess();
+        moved_key // Return the new separator for parent
+    }
+
+    /// Accept a borrowed key and child at the end (from right sibling)
+    /// The separator becomes the last key, and the moved child becomes the last child
+    pub fn accept_from_right(
+        &mut self,
+        separator: K,
+        moved_key: K,
+        moved_child: NodeRef<K, V>,
+    ) -> K {
+        self.keys.push(separator);
+        self.push_child(moved_child);
+        self.shrink_excess();
+        moved_key // Return the new separator for parent
+    }
+
+    /// Merge all content from another branch into this one, with separator from parent
+    pub fn merge_from(&mut self, separator: K, other: &mut BranchNode<K, V>) {
+        // Add separator key from parent
+        debug_assert!(self.keys.len() + 1 + other.keys.len() <= self.capacity);
+        debug_assert!(self.child_ids.len() + other.child_ids.len() <= self.capacity + 1);
+        self.keys.push(separator);
+        // Add all keys and children from other
+        self.keys.append(&mut other.keys);
+        self.child_ids.append(&mut other.child_ids);
+        self.shrink_excess();
+    }
+}
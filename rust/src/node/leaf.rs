@@ -1,14 +1,24 @@
-//! Node implementations for BPlusTreeMap.
-//!
-//! This module contains the complete implementations for LeafNode and BranchNode,
-//! including all their methods for insertion, deletion, splitting, merging, and
-//! other node-level operations.
-
-use crate::types::{BranchNode, InsertResult, LeafNode, NodeId, NodeRef, SplitNodeData, NULL_NODE};
-
-// ============================================================================
-// LEAF NODE IMPLEMENTATION
-// ============================================================================
+//! Leaf nodes: sorted key-value storage and the linked list used for scans.
+
+use super::{
+    min_keys_for, reserve_slots, spare_bytes, split_off_slots, trim_slots, InsertResult, NodeVec,
+    SplitNodeData,
+};
+use crate::error::BPlusTreeError;
+use crate::types::{NodeId, NULL_NODE};
+
+/// Leaf node containing key-value pairs.
+#[derive(Debug, Clone)]
+pub struct LeafNode<K, V> {
+    /// Maximum number of keys this node can hold.
+    pub(crate) capacity: usize,
+    /// Sorted list of keys.
+    pub(crate) keys: NodeVec<K>,
+    /// List of values corresponding to keys.
+    pub(crate) values: NodeVec<V>,
+    /// Next leaf node in the linked list (for range queries).
+    pub(crate) next: NodeId,
+}
 
 impl<K: Ord + Clone, V: Clone> LeafNode<K, V> {
     // ============================================================================
@@ -37,17 +47,17 @@ impl<K: Ord + Clone, V: Clone> LeafNode<K, V> {
     }
 
     /// Get a reference to the keys in this leaf node.
-    pub fn keys(&self) -> &Vec<K> {
+    pub fn keys(&self) -> &NodeVec<K> {
         &self.keys
     }
 
     /// Get a reference to the values in this leaf node.
-    pub fn values(&self) -> &Vec<V> {
+    pub fn values(&self) -> &NodeVec<V> {
         &self.values
     }
 
     /// Get a mutable reference to the values in this leaf node.
-    pub fn values_mut(&mut self) -> &mut Vec<V> {
+    pub fn values_mut(&mut self) -> &mut NodeVec<V> {
         &mut self.values
     }
 
@@ -216,25 +226,25 @@ impl<K: Ord + Clone, V: Clone> LeafNode<K, V> {
 
     /// Append keys from another vector.
     #[inline]
-    pub fn append_keys(&mut self, other: &mut Vec<K>) {
+    pub fn append_keys(&mut self, other: &mut NodeVec<K>) {
         self.keys.append(other);
     }
 
     /// Append values from another vector.
     #[inline]
-    pub fn append_values(&mut self, other: &mut Vec<V>) {
+    pub fn append_values(&mut self, other: &mut NodeVec<V>) {
         self.values.append(other);
     }
 
     /// Take all keys, leaving an empty vector.
     #[inline]
-    pub fn take_keys(&mut self) -> Vec<K> {
+    pub fn take_keys(&mut self) -> NodeVec<K> {
         std::mem::take(&mut self.keys)
     }
 
     /// Take all values, leaving an empty vector.
     #[inline]
-    pub fn take_values(&mut self) -> Vec<V> {
+    pub fn take_values(&mut self) -> NodeVec<V> {
         std::mem::take(&mut self.values)
     }
 
@@ -244,7 +254,9 @@ impl<K: Ord + Clone, V: Clone> LeafNode<K, V> {
     where
         K: Ord,
     {
-        self.keys.binary_search(key)
+        let result = self.keys.binary_search(key);
+        crate::bounds::debug_check_search(&self.keys, key, result);
+        result
     }
 
     /// Consume the node and return the keys and values as iterators.
@@ -338,7 +350,12 @@ impl<K: Ord + Clone, V: Clone> LeafNode<K, V> {
                 }
 
                 // Determine the separator key (first key of right node)
-                let separator_key = new_right.first_key().unwrap().clone();
+                let Some(separator_key) = new_right.first_key().cloned() else {
+                    return InsertResult::Error(BPlusTreeError::data_integrity(
+                        "Leaf split",
+                        "new right leaf is empty",
+                    ));
+                };
 
                 InsertResult::Split {
                     old_value: None,
@@ -371,8 +388,8 @@ impl<K: Ord + Clone, V: Clone> LeafNode<K, V> {
         let mid = mid.max(min_keys).min(total_keys - min_keys);
 
         // Split the keys and values
-        let right_keys = self.keys.split_off(mid);
-        let right_values = self.values.split_off(mid);
+        let right_keys = split_off_slots(&mut self.keys, mid, self.capacity);
+        let right_values = split_off_slots(&mut self.values, mid, self.capacity);
 
         // Create the new right node
         // This really should be allocated directly via the arena, but this seems like a big change.
@@ -449,9 +466,26 @@ impl<K: Ord + Clone, V: Clone> LeafNode<K, V> {
     /// Returns the minimum number of keys this leaf should have.
     #[inline]
     pub fn min_keys(&self) -> usize {
-        // For leaf nodes, minimum is floor(capacity / 2)
         // Exception: root can have fewer keys
-        self.capacity / 2
+        min_keys_for(self.capacity)
+    }
+
+    /// Size the key and value vectors for a full leaf, so inserts never
+    /// reallocate them. Leaves split before inserting, so full is `capacity`.
+    pub(crate) fn reserve_slots(&mut self) {
+        reserve_slots(&mut self.keys, self.capacity);
+        reserve_slots(&mut self.values, self.capacity);
+    }
+
+    /// Release vector storage beyond what a full leaf needs.
+    pub(crate) fn shrink_excess(&mut self) {
+        trim_slots(&mut self.keys, self.capacity);
+        trim_slots(&mut self.values, self.capacity);
+    }
+
+    /// Bytes reserved by the key and value vectors but not in use.
+    pub(crate) fn spare_bytes(&self) -> usize {
+        spare_bytes(&self.keys) + spare_bytes(&self.values)
     }
 
     // ============================================================================
@@ -463,7 +497,7 @@ impl<K: Ord + Clone, V: Clone> LeafNode<K, V> {
         if self.keys.is_empty() || !self.can_donate() {
             return None;
         }
-        Some((self.keys.pop().unwrap(), self.values.pop().unwrap()))
+        Some((self.keys.pop()?, self.values.pop()?))
     }
 
     /// Borrow the first key-value pair from this leaf (used when this is the right sibling)
@@ -478,12 +512,14 @@ impl<K: Ord + Clone, V: Clone> LeafNode<K, V> {
     pub fn accept_from_left(&mut self, key: K, value: V) {
         self.keys.insert(0, key);
         self.values.insert(0, value);
+        self.shrink_excess();
     }
 
     /// Accept a borrowed key-value pair at the end (from right sibling)
     pub fn accept_from_right(&mut self, key: K, value: V) {
         self.keys.push(key);
         self.values.push(value);
+        self.shrink_excess();
     }
 
     /// Merge all content from another leaf into this one, returning the other's next pointer
@@ -492,13 +528,14 @@ impl<K: Ord + Clone, V: Clone> LeafNode<K, V> {
         debug_assert!(self.values.len() + other.values.len() <= self.capacity);
         self.keys.append(&mut other.keys);
         self.values.append(&mut other.values);
+        self.shrink_excess();
         let other_next = other.next;
         other.next = NULL_NODE; // Clear the other's next pointer
         other_next
     }
 
     /// Extract all content from this leaf (used for merging)
-    pub fn extract_all(&mut self) -> (Vec<K>, Vec<V>, NodeId) {
+    pub fn extract_all(&mut self) -> (NodeVec<K>, NodeVec<V>, NodeId) {
         let keys = std::mem::take(&mut self.keys);
         let values = std::mem::take(&mut self.values);
         let next = self.next;
@@ -506,215 +543,3 @@ impl<K: Ord + Clone, V: Clone> LeafNode<K, V> {
         (keys, values, next)
     }
 }
-
-// ============================================================================
-// BRANCH NODE IMPLEMENTATION
-// ============================================================================
-
-impl<K: Ord + Clone, V: Clone> BranchNode<K, V> {
-    // ============================================================================
-    // INSERT OPERATIONS
-    // ============================================================================
-
-    /// Insert a separator key and new child into this branch node.
-    /// Returns None if no split needed, or Some((new_branch_data, promoted_key)) if split occurred.
-    /// The caller should handle arena allocation for the split data.
-    pub fn insert_child_and_split_if_needed(
-        &mut self,
-        child_index: usize,
-        separator_key: K,
-        new_child: NodeRef<K, V>,
-    ) -> Option<(BranchNode<K, V>, K)> {
-        // Check if split is needed BEFORE inserting
-        if self.is_full() {
-            // Branch is at capacity, need to handle split
-            // For branches, we MUST insert first because split promotes a key
-            // With capacity=4: 4 keys → split needs 5 keys (2 left + 1 promoted + 2 right)
-            self.keys.insert(child_index, separator_key);
-            self.children.insert(child_index + 1, new_child);
-
-            // Now split the overfull branch
-            let (new_right, promoted_key) = self.split_data();
-            Some((new_right, promoted_key))
-        } else {
-            // Room to insert without splitting
-            self.keys.insert(child_index, separator_key);
-            self.children.insert(child_index + 1, new_child);
-            None
-        }
-    }
-
-    /// Split this branch node, returning the new right node and promoted key.
-    pub fn split_data(&mut self) -> (BranchNode<K, V>, K) {
-        // For branch nodes, we need to ensure both resulting nodes have at least min_keys
-        // The middle key gets promoted, so we need at least min_keys on each side
-        let min_keys = self.min_keys();
-        let _total_keys = self.keys.len();
-
-        // For branch splits, we promote the middle key, so we need:
-        // - Left side: min_keys keys
-        // - Middle: 1 key (promoted)
-        // - Right side: min_keys keys
-        // Total needed: min_keys + 1 + min_keys
-        let mid = min_keys;
-
-        // Extract the promoted key
-        let promoted_key = self.keys[mid].clone();
-
-        // Split keys and children
-        let right_keys = self.keys.split_off(mid + 1); // Skip the promoted key
-        let right_children = self.children.split_off(mid + 1);
-
-        // Remove the promoted key from left side
-        self.keys.pop(); // Remove the key that was promoted
-
-        // Create the new right branch
-        let new_right = BranchNode {
-            capacity: self.capacity,
-            keys: right_keys,
-            children: right_children,
-        };
-
-        (new_right, promoted_key)
-    }
-
-    // ============================================================================
-    // STATUS CHECKS
-    // ============================================================================
-
-    /// Returns true if this branch node is empty.
-    pub fn is_empty(&self) -> bool {
-        self.keys.is_empty()
-    }
-
-    /// Returns true if this branch node is at capacity.
-    pub fn is_full(&self) -> bool {
-        self.keys.len() >= self.capacity
-    }
-
-    /// Returns true if this branch node is underfull (below minimum occupancy).
-    #[inline]
-    pub fn is_underfull(&self) -> bool {
-        self.keys.len() < self.min_keys()
-    }
-
-    /// Returns true if this branch can donate a key to a sibling.
-    #[inline]
-    pub fn can_donate(&self) -> bool {
-        self.keys.len() > self.min_keys()
-    }
-
-    // ============================================================================
-    // OTHER HELPERS
-    // ============================================================================
-
-    /// Returns the minimum number of keys this branch should have.
-    #[inline]
-    pub fn min_keys(&self) -> usize {
-        // For branch nodes, minimum is floor(capacity / 2)
-        // Exception: root can have fewer keys
-        self.capacity / 2
-    }
-
-    /// Find the index of the child that should contain the given key.
-    #[inline]
-    pub fn find_child_index(&self, key: &K) -> usize {
-        // Binary search to find the appropriate child
-        match self.keys.binary_search(key) {
-            Ok(index) => index + 1, // Key found, go to right child
-            Err(index) => index,    // Key not found, index is the insertion point
-        }
-    }
-
-    /// Returns the number of keys in this branch node.
-    pub fn len(&self) -> usize {
-        self.keys.len()
-    }
-
-    /// Returns true if this branch node needs to be split.
-    /// We allow one extra key beyond capacity to ensure proper splitting.
-    pub fn needs_split(&self) -> bool {
-        self.keys.len() > self.capacity
-    }
-
-    /// Get the child node for a given key.
-    #[inline]
-    pub fn get_child(&self, key: &K) -> Option<&NodeRef<K, V>> {
-        let child_index = self.find_child_index(key);
-        if child_index < self.children.len() {
-            Some(&self.children[child_index])
-        } else {
-            None
-        }
-    }
-
-    /// Get a mutable reference to the child node for a given key.
-    pub fn get_child_mut(&mut self, key: &K) -> Option<&mut NodeRef<K, V>> {
-        let child_index = self.find_child_index(key);
-        if child_index >= self.children.len() {
-            return None;
-        }
-        Some(&mut self.children[child_index])
-    }
-
-    // ============================================================================
-    // BORROWING AND MERGING HELPERS
-    // ============================================================================
-
-    /// Borrow the last key and child from this branch (used when this is the left sibling)
-    pub fn borrow_last(&mut self) -> Option<(K, NodeRef<K, V>)> {
-        if self.keys.is_empty() || !self.can_donate() {
-            return None;
-        }
-        let key = self.keys.pop().unwrap();
-        let child = self.children.pop().unwrap();
-        Some((key, child))
-    }
-
-    /// Borrow the first key and child from this branch (used when this is the right sibling)
-    pub fn borrow_first(&mut self) -> Option<(K, NodeRef<K, V>)> {
-        if self.keys.is_empty() || !self.can_donate() {
-            return None;
-        }
-        let key = self.keys.remove(0);
-        let child = self.children.remove(0);
-        Some((key, child))
-    }
-
-    /// Accept a borrowed key and child at the beginning (from left sibling)
-    /// The separator becomes the first key, and the moved child becomes the first child
-    pub fn accept_from_left(
-        &mut self,
-        separator: K,
-        moved_key: K,
-        moved_child: NodeRef<K, V>,
-    ) -> K {
-        self.keys.insert(0, separator);
-        self.children.insert(0, moved_child);
-        moved_key // Return the new separator for parent
-    }
-
-    /// Accept a borrowed key and child at the end (from right sibling)
-    /// The separator becomes the last key, and the moved child becomes the last child
-    pub fn accept_from_right(
-        &mut self,
-        separator: K,
-        moved_key: K,
-        moved_child: NodeRef<K, V>,
-    ) -> K {
-        self.keys.push(separator);
-        self.children.push(moved_child);
-        moved_key // Return the new separator for parent
-    }
-
-    /// Merge all content from another branch into this one, with separator from parent
-    pub fn merge_from(&mut self, separator: K, other: &mut BranchNode<K, V>) {
-        // Add separator key from parent
-        debug_assert!(self.keys.len() + 1 + other.keys.len() <= self.capacity);
-        debug_assert!(self.children.len() + other.children.len() <= self.capacity + 1);
-        self.keys.push(separator);
-        // Add all keys and children from other
-        self.keys.append(&mut other.keys);
-        self.children.append(&mut other.children);
-    }
-}
@@ -0,0 +1,191 @@
+//! Node types for BPlusTreeMap.
+//!
+//! Leaf and branch nodes live in their own submodules, each with the full
+//! set of node-level operations: insertion, deletion, splitting, merging and
+//! borrowing. This module holds the types shared between them.
+
+mod branch;
+mod leaf;
+mod rebalance;
+
+pub use branch::BranchNode;
+pub use leaf::LeafNode;
+pub(crate) use rebalance::RebalanceNode;
+
+use crate::types::NodeId;
+use std::marker::PhantomData;
+
+/// Minimum number of keys a node of `capacity` holds once rebalanced,
+/// `floor(capacity / 2)` for leaves and branches alike. The root is exempt.
+#[inline]
+pub(crate) const fn min_keys_for(capacity: usize) -> usize {
+    capacity / 2
+}
+
+/// Number of keys, values or children a node keeps inline with the
+/// `smallvec` feature before spilling to the heap.
+#[cfg(feature = "smallvec")]
+pub const NODE_INLINE: usize = 64;
+
+/// Storage for the keys, values and children of a node.
+///
+/// A plain `Vec` by default. With the `smallvec` feature nodes of capacity up
+/// to [`NODE_INLINE`] hold their contents inline, saving two heap allocations
+/// per node, at the cost of larger nodes for small capacities.
+#[cfg(not(feature = "smallvec"))]
+pub type NodeVec<T> = Vec<T>;
+
+/// Storage for the keys, values and children of a node.
+///
+/// With the `smallvec` feature nodes of capacity up to [`NODE_INLINE`] hold
+/// their contents inline, saving two heap allocations per node, at the cost of
+/// larger nodes for small capacities.
+#[cfg(feature = "smallvec")]
+pub type NodeVec<T> = smallvec::SmallVec<[T; NODE_INLINE]>;
+
+/// `Vec::split_off` for inline node storage, which lacks it.
+#[cfg(feature = "smallvec")]
+pub(crate) trait SplitOff {
+    fn split_off(&mut self, at: usize) -> Self;
+}
+
+#[cfg(feature = "smallvec")]
+impl<A: smallvec::Array> SplitOff for smallvec::SmallVec<A> {
+    fn split_off(&mut self, at: usize) -> Self {
+        self.drain(at..).collect()
+    }
+}
+
+/// Make room for `limit` items without reallocating.
+pub(crate) fn reserve_slots<T>(vec: &mut NodeVec<T>, limit: usize) {
+    vec.reserve_exact(limit.saturating_sub(vec.len()));
+}
+
+/// Move the items from `at` onwards into a new vector with room for `limit`
+/// items, so the new node does not reallocate as it fills.
+pub(crate) fn split_off_slots<T>(vec: &mut NodeVec<T>, at: usize, limit: usize) -> NodeVec<T> {
+    let mut right = NodeVec::with_capacity(limit.max(vec.len() - at));
+    right.extend(vec.drain(at..));
+    right
+}
+
+/// Release storage beyond `limit` items. A node never holds more than its
+/// limit, so a trimmed vector does not grow back and each reallocation here
+/// is paid for by the growth that made it necessary.
+#[cfg(not(feature = "smallvec"))]
+pub(crate) fn trim_slots<T>(vec: &mut NodeVec<T>, limit: usize) {
+    if vec.capacity() > limit {
+        vec.shrink_to(limit);
+    }
+}
+
+/// Release storage beyond `limit` items, moving back inline if it fits.
+#[cfg(feature = "smallvec")]
+pub(crate) fn trim_slots<T>(vec: &mut NodeVec<T>, limit: usize) {
+    if vec.spilled() && vec.capacity() > limit {
+        vec.grow(limit.max(vec.len()));
+    }
+}
+
+/// Bytes reserved by `vec` but not holding an item.
+pub(crate) fn spare_bytes<T>(vec: &NodeVec<T>) -> usize {
+    (vec.capacity() - vec.len()) * std::mem::size_of::<T>()
+}
+
+/// Node reference that can be either a leaf or branch node.
+///
+/// Build one with [`NodeRef::leaf`] or [`NodeRef::branch`] and inspect it
+/// with [`id`](NodeRef::id) and [`is_leaf`](NodeRef::is_leaf). The variants
+/// are non-exhaustive so that how a reference is stored can change without
+/// breaking code outside this crate.
+///
+/// ```
+/// use bplustree::NodeRef;
+///
+/// let node: NodeRef<i32, i32> = NodeRef::leaf(3);
+/// assert_eq!(node.id(), 3);
+/// assert!(node.is_leaf());
+/// ```
+///
+/// ```compile_fail
+/// use bplustree::NodeRef;
+/// use std::marker::PhantomData;
+///
+/// // Variants cannot be built directly outside the crate
+/// let node: NodeRef<i32, i32> = NodeRef::Leaf(3, PhantomData);
+/// ```
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NodeRef<K, V> {
+    #[non_exhaustive]
+    Leaf(NodeId, PhantomData<(K, V)>),
+    #[non_exhaustive]
+    Branch(NodeId, PhantomData<(K, V)>),
+}
+
+impl<K, V> Clone for NodeRef<K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for NodeRef<K, V> {}
+
+impl<K, V> NodeRef<K, V> {
+    /// A reference to the leaf node `id`.
+    #[inline]
+    pub const fn leaf(id: NodeId) -> Self {
+        NodeRef::Leaf(id, PhantomData)
+    }
+
+    /// A reference to the branch node `id`.
+    #[inline]
+    pub const fn branch(id: NodeId) -> Self {
+        NodeRef::Branch(id, PhantomData)
+    }
+
+    /// Return the raw node ID.
+    pub fn id(&self) -> NodeId {
+        match *self {
+            NodeRef::Leaf(id, _) => id,
+            NodeRef::Branch(id, _) => id,
+        }
+    }
+
+    /// Returns true if this reference points to a leaf node.
+    pub fn is_leaf(&self) -> bool {
+        matches!(self, NodeRef::Leaf(_, _))
+    }
+
+    /// Returns true if this reference points to a branch node.
+    pub fn is_branch(&self) -> bool {
+        matches!(self, NodeRef::Branch(_, _))
+    }
+}
+
+/// Node data that can be allocated in the arena after a split.
+// Inline node storage makes the node-carrying variants large; they are
+// short-lived return values, so boxing them would only add allocations
+#[cfg_attr(feature = "smallvec", allow(clippy::large_enum_variant))]
+pub enum SplitNodeData<K, V> {
+    Leaf(LeafNode<K, V>),
+    Branch(BranchNode<K, V>),
+    /// Node already allocated in arena - contains the NodeId
+    AllocatedLeaf(NodeId),
+    AllocatedBranch(NodeId),
+}
+
+/// Result of an insertion operation on a node.
+#[cfg_attr(feature = "smallvec", allow(clippy::large_enum_variant))]
+pub enum InsertResult<K, V> {
+    /// Insertion completed without splitting. Contains the old value if key existed.
+    Updated(Option<V>),
+    /// Insertion caused a split with arena allocation needed.
+    Split {
+        old_value: Option<V>,
+        new_node_data: SplitNodeData<K, V>,
+        separator_key: K,
+    },
+    /// Internal error occurred during insertion.
+    Error(crate::error::BPlusTreeError),
+}
@@ -0,0 +1,174 @@
+//! The leaf and branch halves of rebalancing.
+//!
+//! Borrowing from a sibling and merging with one follow the same steps for
+//! both node kinds; only what moves differs. A leaf hands over an entry and
+//! its parent separator is a copy of a key, while a branch hands over a child
+//! and rotates the separator down through the parent. [`RebalanceNode`]
+//! captures those differences so that the tree's borrow and merge routines
+//! are written once.
+
+use super::{BranchNode, LeafNode, NodeVec};
+use crate::bounds::{TreeKey, TreeValue};
+use crate::types::{BPlusTreeMap, NodeId, NodeRef};
+
+/// A node kind that can borrow from and merge with its siblings.
+pub(crate) trait RebalanceNode<K, V>: Sized {
+    /// What one borrow moves between siblings.
+    type Moved;
+    /// Everything a merge moves out of the right-hand node.
+    type Contents;
+    /// Whether a borrow moves the parent's separator down into the receiving
+    /// node, so the donor needs it.
+    const SEPARATOR_MOVES_DOWN: bool;
+
+    /// The node's id if `node` refers to this kind.
+    fn id_of(node: &NodeRef<K, V>) -> Option<NodeId>;
+
+    /// The node `id` in the tree's arena.
+    fn node_mut(tree: &mut BPlusTreeMap<K, V>, id: NodeId) -> Option<&mut Self>;
+
+    /// Return the node `id` to the tree's arena.
+    fn free(tree: &mut BPlusTreeMap<K, V>, id: NodeId);
+
+    /// Give up the last entry to the right-hand sibling. `separator` is the
+    /// parent key between the two if [`Self::SEPARATOR_MOVES_DOWN`]. Returns
+    /// what moves and the parent's new separator, or `None` if this node
+    /// cannot spare an entry.
+    fn donate_last(&mut self, separator: Option<K>) -> Option<(Self::Moved, K)>;
+
+    /// Give up the first entry to the left-hand sibling, as for
+    /// [`donate_last`](Self::donate_last).
+    fn donate_first(&mut self, separator: Option<K>) -> Option<(Self::Moved, K)>;
+
+    /// Take an entry donated by the left-hand sibling.
+    fn accept_first(&mut self, moved: Self::Moved);
+
+    /// Take an entry donated by the right-hand sibling.
+    fn accept_last(&mut self, moved: Self::Moved);
+
+    /// Empty this node for a merge into its left-hand sibling.
+    fn take_all(&mut self) -> Self::Contents;
+
+    /// Append the contents of the right-hand sibling, which sat after
+    /// `separator` in the parent. Returns the separator if this node kind
+    /// does not keep it.
+    fn absorb(&mut self, separator: K, contents: Self::Contents) -> Option<K>;
+}
+
+impl<K: TreeKey, V: TreeValue> RebalanceNode<K, V> for LeafNode<K, V> {
+    type Moved = (K, V);
+    type Contents = (NodeVec<K>, NodeVec<V>, NodeId);
+    const SEPARATOR_MOVES_DOWN: bool = false;
+
+    fn id_of(node: &NodeRef<K, V>) -> Option<NodeId> {
+        match node {
+            NodeRef::Leaf(id, _) => Some(*id),
+            NodeRef::Branch(_, _) => None,
+        }
+    }
+
+    fn node_mut(tree: &mut BPlusTreeMap<K, V>, id: NodeId) -> Option<&mut Self> {
+        tree.get_leaf_mut(id)
+    }
+
+    fn free(tree: &mut BPlusTreeMap<K, V>, id: NodeId) {
+        tree.deallocate_leaf(id);
+    }
+
+    fn donate_last(&mut self, _separator: Option<K>) -> Option<((K, V), K)> {
+        let (key, value) = self.borrow_last()?;
+        let separator = key.clone();
+        Some(((key, value), separator))
+    }
+
+    fn donate_first(&mut self, _separator: Option<K>) -> Option<((K, V), K)> {
+        // The key after the donated one becomes the separator
+        let separator = self.keys.get(1)?.clone();
+        Some((self.borrow_first()?, separator))
+    }
+
+    fn accept_first(&mut self, (key, value): (K, V)) {
+        self.accept_from_left(key, value);
+    }
+
+    fn accept_last(&mut self, (key, value): (K, V)) {
+        self.accept_from_right(key, value);
+    }
+
+    fn take_all(&mut self) -> Self::Contents {
+        self.extract_all()
+    }
+
+    fn absorb(&mut self, separator: K, (mut keys, mut values, next): Self::Contents) -> Option<K> {
+        debug_assert!(self.keys.len() + keys.len() <= self.capacity);
+        debug_assert!(self.values.len() + values.len() <= self.capacity);
+        self.append_keys(&mut keys);
+        self.append_values(&mut values);
+        self.next = next;
+        self.shrink_excess();
+        Some(separator)
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> RebalanceNode<K, V> for BranchNode<K, V> {
+    /// The key to place in the receiver and the child that moves with it.
+    type Moved = (K, NodeRef<K, V>);
+    type Contents = (NodeVec<K>, NodeVec<NodeId>);
+    const SEPARATOR_MOVES_DOWN: bool = true;
+
+    fn id_of(node: &NodeRef<K, V>) -> Option<NodeId> {
+        match node {
+            NodeRef::Branch(id, _) => Some(*id),
+            NodeRef::Leaf(_, _) => None,
+        }
+    }
+
+    fn node_mut(tree: &mut BPlusTreeMap<K, V>, id: NodeId) -> Option<&mut Self> {
+        tree.get_branch_mut(id)
+    }
+
+    fn free(tree: &mut BPlusTreeMap<K, V>, id: NodeId) {
+        tree.deallocate_branch(id);
+    }
+
+    fn donate_last(&mut self, separator: Option<K>) -> Option<(Self::Moved, K)> {
+        let separator = separator?;
+        let (key, child) = self.borrow_last()?;
+        Some(((separator, child), key))
+    }
+
+    fn donate_first(&mut self, separator: Option<K>) -> Option<(Self::Moved, K)> {
+        let separator = separator?;
+        let (key, child) = self.borrow_first()?;
+        Some(((separator, child), key))
+    }
+
+    fn accept_first(&mut self, (key, child): Self::Moved) {
+        self.keys.insert(0, key);
+        self.insert_child(0, child);
+        self.shrink_excess();
+    }
+
+    fn accept_last(&mut self, (key, child): Self::Moved) {
+        self.keys.push(key);
+        self.push_child(child);
+        self.shrink_excess();
+    }
+
+    fn take_all(&mut self) -> Self::Contents {
+        (
+            std::mem::take(&mut self.keys),
+            std::mem::take(&mut self.child_ids),
+        )
+    }
+
+    fn absorb(&mut self, separator: K, (mut keys, mut child_ids): Self::Contents) -> Option<K> {
+        debug_assert!(self.keys.len() + 1 + keys.len() <= self.capacity);
+        debug_assert!(self.child_ids.len() + child_ids.len() <= self.capacity + 1);
+        self.keys.push(separator);
+        self.keys.append(&mut keys);
+        self.child_ids.append(&mut child_ids);
+        self.shrink_excess();
+        None
+    }
+}
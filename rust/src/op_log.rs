@@ -0,0 +1,357 @@
+//! Recording mutations so a failure can be reproduced.
+//!
+//! Rebalancing bugs tend to need one exact sequence of inserts and removals
+//! to show up, which a user reporting corrupted results rarely knows.
+//! [`RecordingMap`] logs every mutating call as it happens: the operation,
+//! the key, and a hash of the value, so the log stays small and does not
+//! copy the user's data. [`BPlusTreeMap::replay`] feeds a complete log to a
+//! fresh tree of the same capacity, which goes through the same splits and
+//! merges and ends with the same shape.
+//!
+//! With a length limit the log becomes a ring buffer of the most recent
+//! operations. That still shows what led up to a failure, but replay needs
+//! the whole history and refuses a log that has dropped operations.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::error::{BPlusTreeError, InitResult, ModifyResult};
+use crate::iteration::RangeIterator;
+use crate::types::BPlusTreeMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::RangeBounds;
+
+/// One mutating call, as recorded by [`RecordingMap`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordedOp<K> {
+    /// `insert(key, value)`, whether it added the key or replaced its value.
+    Insert {
+        /// The key inserted.
+        key: K,
+        /// Hash of the value inserted.
+        value_hash: u64,
+    },
+    /// `remove(key)`, whether or not the key was present.
+    Remove {
+        /// The key removed.
+        key: K,
+    },
+    /// `clear()`.
+    Clear,
+}
+
+/// The operations a [`RecordingMap`] has seen, oldest first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpLog<K> {
+    capacity: usize,
+    limit: Option<usize>,
+    ops: VecDeque<RecordedOp<K>>,
+    dropped: u64,
+}
+
+impl<K> OpLog<K> {
+    fn new(capacity: usize, limit: Option<usize>) -> Self {
+        Self {
+            capacity,
+            limit,
+            ops: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    fn push(&mut self, op: RecordedOp<K>) {
+        if self.limit == Some(0) {
+            self.dropped += 1;
+            return;
+        }
+        if self.limit.is_some_and(|limit| self.ops.len() == limit) {
+            self.ops.pop_front();
+            self.dropped += 1;
+        }
+        self.ops.push_back(op);
+    }
+
+    /// Node capacity of the recorded tree.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The retained operations, oldest first.
+    pub fn ops(&self) -> impl Iterator<Item = &RecordedOp<K>> {
+        self.ops.iter()
+    }
+
+    /// Number of retained operations.
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Returns true if no operations are retained.
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Number of operations pushed out of a full ring buffer.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Returns true if the log holds every operation since the map was
+    /// created, so it can be replayed.
+    pub fn is_complete(&self) -> bool {
+        self.dropped == 0
+    }
+}
+
+impl<K: fmt::Debug> fmt::Display for OpLog<K> {
+    /// One operation per line, in a form that is easy to paste into a bug
+    /// report or turn back into test code.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# capacity {}", self.capacity)?;
+        if self.dropped > 0 {
+            writeln!(f, "# {} earlier operations dropped", self.dropped)?;
+        }
+        for op in &self.ops {
+            match op {
+                RecordedOp::Insert { key, value_hash } => {
+                    writeln!(f, "insert {:?} {:016x}", key, value_hash)?
+                }
+                RecordedOp::Remove { key } => writeln!(f, "remove {:?}", key)?,
+                RecordedOp::Clear => writeln!(f, "clear")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A tree that records every mutating call in an [`OpLog`].
+///
+/// Reads go through [`tree`](Self::tree); all writes must go through the
+/// wrapper so that the log misses none.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::{BPlusTreeMap, RecordingMap};
+///
+/// let mut map = RecordingMap::new(4, None).unwrap();
+/// for i in 0..100 {
+///     map.insert(i, i.to_string());
+/// }
+/// for i in (0..100).step_by(3) {
+///     map.remove(&i);
+/// }
+///
+/// // The replayed tree has the same keys in the same leaves
+/// let replayed = BPlusTreeMap::replay(map.log()).unwrap();
+/// let replayed_leaves: Vec<_> = replayed.group_by_leaf().map(|(keys, _)| keys).collect();
+/// let original_leaves: Vec<_> = map.tree().group_by_leaf().map(|(keys, _)| keys).collect();
+/// assert_eq!(replayed_leaves, original_leaves);
+/// ```
+pub struct RecordingMap<K, V> {
+    tree: BPlusTreeMap<K, V>,
+    log: OpLog<K>,
+    value_hash: fn(&V) -> u64,
+}
+
+impl<K: TreeKey, V: TreeValue + Hash> RecordingMap<K, V> {
+    /// Create an empty map with node capacity `capacity` that keeps the last
+    /// `limit` operations, or all of them for `None`. Values are hashed with
+    /// the standard library's default hasher.
+    pub fn new(capacity: usize, limit: Option<usize>) -> InitResult<Self> {
+        Self::with_value_hash(capacity, limit, |value| {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        })
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> RecordingMap<K, V> {
+    /// Like [`new`](Self::new), with values hashed by `value_hash`, for value
+    /// types without a `Hash` impl.
+    pub fn with_value_hash(
+        capacity: usize,
+        limit: Option<usize>,
+        value_hash: fn(&V) -> u64,
+    ) -> InitResult<Self> {
+        Ok(Self {
+            tree: BPlusTreeMap::new(capacity)?,
+            log: OpLog::new(capacity, limit),
+            value_hash,
+        })
+    }
+
+    /// The underlying tree, for reads.
+    pub fn tree(&self) -> &BPlusTreeMap<K, V> {
+        &self.tree
+    }
+
+    /// The operations recorded so far.
+    pub fn log(&self) -> &OpLog<K> {
+        &self.log
+    }
+
+    /// Stop recording and return the tree and its log.
+    pub fn into_parts(self) -> (BPlusTreeMap<K, V>, OpLog<K>) {
+        (self.tree, self.log)
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Returns true if the map holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Get the value stored under `key`.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.tree.get(key)
+    }
+
+    /// Iterate over the entries in `range`.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> RangeIterator<'_, K, V> {
+        self.tree.range(range)
+    }
+
+    /// Insert `value` under `key`, recording the call, and return the
+    /// previous value if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.log.push(RecordedOp::Insert {
+            key: key.clone(),
+            value_hash: (self.value_hash)(&value),
+        });
+        self.tree.insert(key, value)
+    }
+
+    /// Remove `key`, recording the call, and return its value if it was
+    /// present.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.log.push(RecordedOp::Remove { key: key.clone() });
+        self.tree.remove(key)
+    }
+
+    /// Remove every entry, recording the call.
+    pub fn clear(&mut self) {
+        self.log.push(RecordedOp::Clear);
+        self.tree.clear();
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for RecordingMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordingMap")
+            .field("tree", &self.tree)
+            .field("recorded", &self.log.len())
+            .field("dropped", &self.log.dropped)
+            .finish()
+    }
+}
+
+impl<K: TreeKey> BPlusTreeMap<K, u64> {
+    /// Rebuild the tree a [`RecordingMap`] recorded by applying its log to a
+    /// fresh tree of the same capacity. Values are the recorded value hashes.
+    ///
+    /// The tree goes through the same sequence of splits and merges as the
+    /// original, so a structural bug that corrupted the original shows up
+    /// again here. Fails if the log dropped operations from its ring buffer.
+    pub fn replay(log: &OpLog<K>) -> ModifyResult<Self> {
+        if !log.is_complete() {
+            return Err(BPlusTreeError::invalid_state(
+                "replay operation log",
+                &format!("{} earlier operations were dropped", log.dropped),
+            ));
+        }
+        let mut tree = BPlusTreeMap::new(log.capacity)?;
+        for op in log.ops() {
+            match op {
+                RecordedOp::Insert { key, value_hash } => {
+                    tree.insert(key.clone(), *value_hash);
+                }
+                RecordedOp::Remove { key } => {
+                    tree.remove(key);
+                }
+                RecordedOp::Clear => tree.clear(),
+            }
+        }
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+
+    fn leaf_keys<V: TreeValue>(tree: &BPlusTreeMap<u32, V>) -> Vec<Vec<u32>> {
+        tree.group_by_leaf()
+            .map(|(keys, _)| keys.to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn test_replay_reproduces_shape_and_values() {
+        let mut map = RecordingMap::new(4, None).unwrap();
+        let mut rng = SeededRng::new(3);
+        for i in 0..5_000u32 {
+            let key = rng.below(400) as u32;
+            match i % 11 {
+                0..=5 => {
+                    map.insert(key, format!("v{}", i));
+                }
+                6..=9 => {
+                    map.remove(&key);
+                }
+                _ if i % 1_000 == 10 => map.clear(),
+                _ => {}
+            }
+        }
+
+        let replayed = BPlusTreeMap::replay(map.log()).unwrap();
+        assert_eq!(leaf_keys(&replayed), leaf_keys(map.tree()));
+        assert_eq!(
+            replayed.count_nodes_in_tree(),
+            map.tree().count_nodes_in_tree()
+        );
+        for (key, value) in map.tree().items() {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            assert_eq!(replayed.get(key), Some(&hasher.finish()));
+        }
+    }
+
+    #[test]
+    fn test_ring_buffer_keeps_recent_ops_and_refuses_replay() {
+        let mut map = RecordingMap::with_value_hash(4, Some(3), |v: &u32| u64::from(*v)).unwrap();
+        for i in 0..10 {
+            map.insert(i, i * 2);
+        }
+        map.remove(&4);
+
+        let log = map.log();
+        assert_eq!(log.dropped(), 8);
+        assert_eq!(
+            log.ops().cloned().collect::<Vec<_>>(),
+            vec![
+                RecordedOp::Insert {
+                    key: 8,
+                    value_hash: 16
+                },
+                RecordedOp::Insert {
+                    key: 9,
+                    value_hash: 18
+                },
+                RecordedOp::Remove { key: 4 },
+            ]
+        );
+        assert!(log.to_string().starts_with("# capacity 4\n# 8 earlier"));
+        assert!(matches!(
+            BPlusTreeMap::replay(log),
+            Err(BPlusTreeError::Query(crate::QueryError::InvalidState(_)))
+        ));
+    }
+}
@@ -0,0 +1,365 @@
+//! A serialized format shared with the Python implementation.
+//!
+//! The Rust crate and the `bplustree` Python package read and write the same
+//! bytes, so a dataset can move between the two without a conversion script.
+//! Keys and values are limited to what both sides can represent: 64-bit
+//! signed integers, UTF-8 strings and byte strings.
+//!
+//! All integers are little-endian:
+//!
+//! | Field    | Size | Contents                                  |
+//! |----------|------|-------------------------------------------|
+//! | magic    | 4    | `BPT+`                                    |
+//! | version  | 1    | [`PORTABLE_VERSION`]                      |
+//! | capacity | 4    | node capacity of the writer (`u32`)       |
+//! | count    | 8    | number of entries (`u64`)                 |
+//! | entries  | ...  | `count` pairs of an encoded key and value |
+//!
+//! Each key or value is a tag byte followed by its payload: `0x01` and an
+//! `i64` for an integer, `0x02` and a `u64` length and UTF-8 bytes for a
+//! string, `0x03` and a `u64` length and raw bytes for a byte string.
+//! Entries are in strictly ascending key order and every key has the same
+//! tag. Readers reject anything else, including bytes after the last entry
+//! and a capacity above [`PORTABLE_MAX_CAPACITY`].
+//!
+//! Golden files in `testdata/portable` at the repository root are checked by
+//! the test suites of both implementations.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::types::BPlusTreeMap;
+use std::io::{self, Read, Write};
+
+/// First bytes of every portable file.
+pub const PORTABLE_MAGIC: [u8; 4] = *b"BPT+";
+
+/// Format version written by this crate and the only one it reads.
+pub const PORTABLE_VERSION: u8 = 1;
+
+/// Largest node capacity accepted from a portable file. The capacity comes
+/// from the file, and a larger one would let a short file ask for an
+/// allocation big enough to abort the reader.
+pub const PORTABLE_MAX_CAPACITY: u32 = 4096;
+
+const TAG_INT: u8 = 0x01;
+const TAG_STR: u8 = 0x02;
+const TAG_BYTES: u8 = 0x03;
+
+/// A key or value type with an encoding in the portable format.
+///
+/// Implemented for `i64`, `String`, `Vec<u8>` and [`PortableValue`], which
+/// holds whichever of the three a file contains.
+pub trait Portable: Sized {
+    /// Append the tag and payload of `self` to `out`.
+    fn encode_into(&self, out: &mut Vec<u8>);
+
+    /// Decode a value from the front of `input` and advance past it.
+    /// Returns `None` if `input` does not start with a valid encoding of this
+    /// type.
+    fn decode_from(input: &mut &[u8]) -> Option<Self>;
+}
+
+/// A key or value of any type the portable format holds, for files whose
+/// value types are mixed, as Python allows.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PortableValue {
+    /// A 64-bit signed integer.
+    Int(i64),
+    /// A UTF-8 string.
+    Str(String),
+    /// A byte string.
+    Bytes(Vec<u8>),
+}
+
+/// Split `N` bytes off the front of `input`.
+fn take<const N: usize>(input: &mut &[u8]) -> Option<[u8; N]> {
+    let (head, rest) = input.split_first_chunk::<N>()?;
+    *input = rest;
+    Some(*head)
+}
+
+/// Split a length-prefixed byte string off the front of `input`.
+fn take_bytes<'a>(input: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = usize::try_from(u64::from_le_bytes(take(input)?)).ok()?;
+    let (bytes, rest) = input.split_at_checked(len)?;
+    *input = rest;
+    Some(bytes)
+}
+
+fn encode_bytes(tag: u8, bytes: &[u8], out: &mut Vec<u8>) {
+    out.push(tag);
+    out.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+/// Split `tag` off the front of `input`, or fail without consuming it.
+fn expect_tag(input: &mut &[u8], tag: u8) -> Option<()> {
+    let (&found, rest) = input.split_first()?;
+    if found != tag {
+        return None;
+    }
+    *input = rest;
+    Some(())
+}
+
+impl Portable for i64 {
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        out.push(TAG_INT);
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn decode_from(input: &mut &[u8]) -> Option<Self> {
+        expect_tag(input, TAG_INT)?;
+        Some(i64::from_le_bytes(take(input)?))
+    }
+}
+
+impl Portable for String {
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        encode_bytes(TAG_STR, self.as_bytes(), out);
+    }
+
+    fn decode_from(input: &mut &[u8]) -> Option<Self> {
+        expect_tag(input, TAG_STR)?;
+        String::from_utf8(take_bytes(input)?.to_vec()).ok()
+    }
+}
+
+impl Portable for Vec<u8> {
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        encode_bytes(TAG_BYTES, self, out);
+    }
+
+    fn decode_from(input: &mut &[u8]) -> Option<Self> {
+        expect_tag(input, TAG_BYTES)?;
+        Some(take_bytes(input)?.to_vec())
+    }
+}
+
+impl Portable for PortableValue {
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        match self {
+            PortableValue::Int(value) => value.encode_into(out),
+            PortableValue::Str(value) => value.encode_into(out),
+            PortableValue::Bytes(value) => value.encode_into(out),
+        }
+    }
+
+    fn decode_from(input: &mut &[u8]) -> Option<Self> {
+        match *input.first()? {
+            TAG_INT => i64::decode_from(input).map(PortableValue::Int),
+            TAG_STR => String::decode_from(input).map(PortableValue::Str),
+            TAG_BYTES => Vec::decode_from(input).map(PortableValue::Bytes),
+            _ => None,
+        }
+    }
+}
+
+fn invalid_data(details: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid portable tree: {}", details),
+    )
+}
+
+impl<K: TreeKey + Portable, V: TreeValue + Portable> BPlusTreeMap<K, V> {
+    /// Write the tree in the portable format shared with the Python
+    /// implementation.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the keys do not all have
+    /// the same type, which only [`PortableValue`] keys allow.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(16).unwrap();
+    /// tree.insert(1i64, "one".to_string());
+    /// tree.insert(2i64, "two".to_string());
+    ///
+    /// let mut bytes = Vec::new();
+    /// tree.write_portable(&mut bytes).unwrap();
+    /// let copy = BPlusTreeMap::<i64, String>::read_portable(&bytes[..]).unwrap();
+    /// assert_eq!(copy.capacity(), 16);
+    /// assert!(copy.items().eq(tree.items()));
+    /// ```
+    pub fn write_portable<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let capacity = u32::try_from(self.capacity).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "node capacity does not fit the portable format",
+            )
+        })?;
+        let mut out = Vec::with_capacity(17);
+        out.extend_from_slice(&PORTABLE_MAGIC);
+        out.push(PORTABLE_VERSION);
+        out.extend_from_slice(&capacity.to_le_bytes());
+        out.extend_from_slice(&(self.len() as u64).to_le_bytes());
+        writer.write_all(&out)?;
+
+        let mut key_tag = None;
+        for (key, value) in self.items() {
+            out.clear();
+            key.encode_into(&mut out);
+            if *key_tag.get_or_insert(out[0]) != out[0] {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "portable keys must all have the same type",
+                ));
+            }
+            value.encode_into(&mut out);
+            writer.write_all(&out)?;
+        }
+        Ok(())
+    }
+
+    /// Read a tree written in the portable format by either implementation,
+    /// with the node capacity recorded in the file.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if the input is not a
+    /// well-formed portable file whose keys and values decode as `K` and `V`,
+    /// or if its capacity is above [`PORTABLE_MAX_CAPACITY`].
+    pub fn read_portable<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let mut input = bytes.as_slice();
+
+        let header = take::<17>(&mut input).ok_or_else(|| invalid_data("truncated header"))?;
+        let (magic, header) = header.split_at(4);
+        if magic != PORTABLE_MAGIC {
+            return Err(invalid_data("bad magic"));
+        }
+        if header[0] != PORTABLE_VERSION {
+            return Err(invalid_data(&format!("unsupported version {}", header[0])));
+        }
+        let capacity = u32::from_le_bytes([header[1], header[2], header[3], header[4]]);
+        let mut count = [0; 8];
+        count.copy_from_slice(&header[5..]);
+        let count = u64::from_le_bytes(count);
+        if capacity > PORTABLE_MAX_CAPACITY {
+            return Err(invalid_data(&format!(
+                "capacity {} is above the maximum of {}",
+                capacity, PORTABLE_MAX_CAPACITY
+            )));
+        }
+
+        let mut tree = usize::try_from(capacity)
+            .ok()
+            .and_then(|capacity| Self::new(capacity).ok())
+            .ok_or_else(|| invalid_data(&format!("capacity {} is too small", capacity)))?;
+        let mut key_tag = None;
+        for index in 0..count {
+            let tag = input.first().copied();
+            if *key_tag.get_or_insert(tag) != tag {
+                return Err(invalid_data("keys do not all have the same type"));
+            }
+            let key = K::decode_from(&mut input)
+                .ok_or_else(|| invalid_data(&format!("bad key in entry {}", index)))?;
+            let value = V::decode_from(&mut input)
+                .ok_or_else(|| invalid_data(&format!("bad value in entry {}", index)))?;
+            if tree.last().is_some_and(|(last, _)| *last >= key) {
+                return Err(invalid_data(&format!(
+                    "entry {} is not in ascending key order",
+                    index
+                )));
+            }
+            tree.insert(key, value);
+        }
+        if !input.is_empty() {
+            return Err(invalid_data("bytes after the last entry"));
+        }
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded<K, V>(tree: &BPlusTreeMap<K, V>) -> Vec<u8>
+    where
+        K: TreeKey + Portable,
+        V: TreeValue + Portable,
+    {
+        let mut bytes = Vec::new();
+        tree.write_portable(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_round_trip_through_portable_values() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in -20i64..20 {
+            let value = match i.rem_euclid(3) {
+                0 => PortableValue::Int(i * 1_000_000_007),
+                1 => PortableValue::Str(format!("é{}", i)),
+                _ => PortableValue::Bytes(vec![0, i as u8, 0xFF]),
+            };
+            tree.insert(i, value);
+        }
+        let bytes = encoded(&tree);
+        let copy = BPlusTreeMap::<i64, PortableValue>::read_portable(&bytes[..]).unwrap();
+        assert!(copy.items().eq(tree.items()));
+        assert_eq!(encoded(&copy), bytes);
+    }
+
+    #[test]
+    fn test_read_rejects_malformed_input() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        tree.insert("a".to_string(), 1i64);
+        tree.insert("b".to_string(), 2i64);
+        let bytes = encoded(&tree);
+        let read = |bytes: &[u8]| BPlusTreeMap::<String, i64>::read_portable(bytes);
+
+        assert!(read(&bytes).is_ok());
+        for len in 0..bytes.len() {
+            assert!(read(&bytes[..len]).is_err(), "truncated to {}", len);
+        }
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(read(&trailing).is_err());
+
+        let mut bad_version = bytes.clone();
+        bad_version[4] = PORTABLE_VERSION + 1;
+        assert!(read(&bad_version).is_err());
+
+        // Swap the two keys so they are out of order
+        let mut swapped = bytes.clone();
+        let first = swapped.iter().position(|&b| b == b'a').unwrap();
+        let second = swapped.iter().position(|&b| b == b'b').unwrap();
+        swapped.swap(first, second);
+        assert!(read(&swapped).is_err());
+
+        // The right layout with the wrong types
+        assert!(BPlusTreeMap::<i64, i64>::read_portable(&bytes[..]).is_err());
+        let err = read(&bytes[..3]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_read_rejects_oversized_capacity() {
+        let header = |capacity: u32| {
+            let mut bytes = PORTABLE_MAGIC.to_vec();
+            bytes.push(PORTABLE_VERSION);
+            bytes.extend_from_slice(&capacity.to_le_bytes());
+            bytes.extend_from_slice(&0u64.to_le_bytes());
+            bytes
+        };
+        let read = |bytes: &[u8]| BPlusTreeMap::<String, String>::read_portable(bytes);
+
+        let err = read(&header(u32::MAX)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(read(&header(PORTABLE_MAX_CAPACITY + 1)).is_err());
+        let tree = read(&header(PORTABLE_MAX_CAPACITY)).unwrap();
+        assert_eq!(tree.capacity(), PORTABLE_MAX_CAPACITY as usize);
+    }
+
+    #[test]
+    fn test_write_rejects_mixed_key_types() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        tree.insert(PortableValue::Int(1), 1i64);
+        tree.insert(PortableValue::Str("one".to_string()), 1i64);
+        let err = tree.write_portable(Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
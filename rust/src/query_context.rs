@@ -0,0 +1,208 @@
+//! Leaf hints for runs of nearby queries.
+//!
+//! Every `get` or `range` normally descends from the root. Workloads such as
+//! tailing a time series ask about keys close to the previous query, and so
+//! usually land in the same leaf. A [`QueryContext`] remembers the leaf of the
+//! last query; the `_with` query methods check whether the hinted leaf still
+//! covers the new key and only descend when it does not.
+//!
+//! The hint is held by the caller rather than the tree so that shared
+//! references to the tree stay free of interior mutability, and so that
+//! independent readers each keep their own locality.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::iteration::RangeIterator;
+use crate::types::{BPlusTreeMap, NodeId, NULL_NODE};
+use std::ops::{Bound, RangeBounds};
+
+/// Remembers the leaf touched by the previous query on a tree.
+///
+/// A context may be used with any tree and across modifications: a hint is
+/// only used after checking that the leaf still exists and that its keys span
+/// the probed key, and any other case falls back to a normal descent.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QueryContext {
+    leaf: Option<NodeId>,
+    hits: usize,
+    misses: usize,
+}
+
+impl QueryContext {
+    /// Create a context with no hint.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of queries answered from the hinted leaf.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Number of queries that had to descend from the root.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Forget the hinted leaf.
+    pub fn clear(&mut self) {
+        self.leaf = None;
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Get the value for `key`, starting from the leaf of the previous query
+    /// made with `context`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::{BPlusTreeMap, QueryContext};
+    ///
+    /// let mut tree = BPlusTreeMap::new(64).unwrap();
+    /// for i in 0..1_000 {
+    ///     tree.insert(i, i * 2);
+    /// }
+    ///
+    /// let mut context = QueryContext::new();
+    /// for i in 500..510 {
+    ///     assert_eq!(tree.get_with(&mut context, &i), Some(&(i * 2)));
+    /// }
+    /// assert_eq!(context.misses(), 1);
+    /// ```
+    pub fn get_with(&self, context: &mut QueryContext, key: &K) -> Option<&V> {
+        let (leaf_id, index) = self.position_with_hint(context, key)?;
+        let leaf = self.get_leaf(leaf_id)?;
+        match leaf.keys.get(index) {
+            Some(found) if found == key => leaf.values.get(index),
+            _ => None,
+        }
+    }
+
+    /// Iterate over `range`, locating its start from the leaf of the previous
+    /// query made with `context`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::{BPlusTreeMap, QueryContext};
+    ///
+    /// let mut readings = BPlusTreeMap::new(64).unwrap();
+    /// let mut context = QueryContext::new();
+    /// for t in 0..100 {
+    ///     readings.insert(t, t % 7);
+    ///     // Tail the last few readings after each append
+    ///     let recent: i32 = readings.range_with(&mut context, t - 2..=t).map(|(_, v)| v).sum();
+    ///     assert!(recent <= 18);
+    /// }
+    /// assert!(context.hits() > context.misses());
+    /// ```
+    pub fn range_with<R>(&self, context: &mut QueryContext, range: R) -> RangeIterator<'_, K, V>
+    where
+        R: RangeBounds<K>,
+    {
+        let (start_info, skip_first) = match range.start_bound() {
+            Bound::Included(key) => (self.position_with_hint(context, key), false),
+            Bound::Excluded(key) => (
+                self.position_with_hint(context, key)
+                    .map(|position| self.position_after_key(position, key)),
+                false,
+            ),
+            Bound::Unbounded => (self.get_first_leaf_id().map(|id| (id, 0)), false),
+        };
+        let end_info = match range.end_bound() {
+            Bound::Included(key) => Some((key.clone(), true)),
+            Bound::Excluded(key) => Some((key.clone(), false)),
+            Bound::Unbounded => None,
+        };
+        RangeIterator::new_with_skip_owned(self, start_info, skip_first, end_info)
+    }
+
+    /// Position where `key` is or would be inserted, like `find_leaf_for_key`,
+    /// using and then updating the hint in `context`.
+    fn position_with_hint(&self, context: &mut QueryContext, key: &K) -> Option<(NodeId, usize)> {
+        if let Some(leaf_id) = context.leaf {
+            if let Some(leaf) = self.get_leaf(leaf_id) {
+                // Leaves hold disjoint key ranges, so any leaf spanning the key
+                // is the one a descent would reach. Keys past the last leaf
+                // belong to it as well.
+                if let (Some(first), Some(last)) = (leaf.keys.first(), leaf.keys.last()) {
+                    if first <= key && (key <= last || leaf.next == NULL_NODE) {
+                        context.hits += 1;
+                        let index = leaf.binary_search_keys(key).unwrap_or_else(|index| index);
+                        return Some((leaf_id, index));
+                    }
+                }
+            }
+        }
+
+        context.misses += 1;
+        let position = self.find_leaf_for_key(key);
+        context.leaf = position.map(|(leaf_id, _)| leaf_id);
+        position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_hinted_queries_match_plain_queries_under_modification() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        let mut reference = BTreeMap::new();
+        let mut context = QueryContext::new();
+        let mut rng = SeededRng::new(5);
+        let mut next = |bound: u64| rng.below(bound) as i32;
+
+        let mut probe = 0;
+        for i in 0..3_000 {
+            let key = next(400);
+            if next(3) == 0 {
+                tree.remove(&key);
+                reference.remove(&key);
+            } else {
+                tree.insert(key, i);
+                reference.insert(key, i);
+            }
+
+            // Walk a probe slowly across the key space
+            probe = (probe + next(5) - 2).clamp(0, 400);
+            assert_eq!(tree.get_with(&mut context, &probe), reference.get(&probe));
+            assert!(tree
+                .range_with(&mut context, probe..probe + 6)
+                .eq(reference.range(probe..probe + 6)));
+            assert!(tree
+                .range_with(
+                    &mut context,
+                    (Bound::Excluded(probe), Bound::Included(probe + 6))
+                )
+                .eq(reference.range((Bound::Excluded(probe), Bound::Included(probe + 6)))));
+        }
+        assert!(context.hits() > context.misses());
+    }
+
+    #[test]
+    fn test_context_survives_leaf_reuse_and_other_trees() {
+        let mut context = QueryContext::new();
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..100 {
+            tree.insert(i, i);
+        }
+        assert_eq!(tree.get_with(&mut context, &50), Some(&50));
+
+        // Free every leaf, then reuse the slots for different keys
+        tree.clear();
+        for i in 1_000..1_100 {
+            tree.insert(i, i);
+        }
+        assert_eq!(tree.get_with(&mut context, &50), None);
+        assert_eq!(tree.get_with(&mut context, &1_050), Some(&1_050));
+
+        let mut other = BPlusTreeMap::new(4).unwrap();
+        other.insert(1_050, 7);
+        assert_eq!(other.get_with(&mut context, &1_050), Some(&7));
+        assert_eq!(other.get_with(&mut context, &1_051), None);
+    }
+}
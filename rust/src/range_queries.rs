@@ -3,9 +3,11 @@
 //! This module contains all range-related operations including range iteration,
 //! bounds resolution, and range optimization algorithms.
 
-use crate::iteration::RangeIterator;
-use crate::types::{BPlusTreeMap, NodeId};
-use std::ops::{Bound, RangeBounds};
+use crate::bounds::{TreeKey, TreeValue};
+use crate::error::BPlusTreeError;
+use crate::iteration::{ItemIterator, RangeIterator, TryItemIterator};
+use crate::types::{BPlusTreeMap, NodeId, NodeRef, NULL_NODE};
+use std::ops::{Bound, RangeBounds, RangeFrom, RangeTo};
 
 /// Type alias for complex range analysis result
 type RangeAnalysisResult<K> = (Option<(NodeId, usize)>, bool, Option<(K, bool)>);
@@ -14,7 +16,7 @@ type RangeAnalysisResult<K> = (Option<(NodeId, usize)>, bool, Option<(K, bool)>)
 // RANGE QUERY OPERATIONS
 // ============================================================================
 
-impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
     /// Returns an iterator over key-value pairs in a range using Rust's range syntax.
     ///
     /// # Examples
@@ -54,6 +56,314 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
         RangeIterator::new_with_skip_owned(self, start_info, skip_first, end_info)
     }
 
+    /// Returns an iterator over the entries in `range` that yields an error,
+    /// rather than ending early, if the tree structure is broken: a node on
+    /// the way down to the start of the range or a leaf in the chain is not
+    /// in the arena.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..20 {
+    ///     tree.insert(i, i * 10);
+    /// }
+    /// for entry in tree.try_range(5..8) {
+    ///     let (key, value) = entry.unwrap();
+    ///     assert_eq!(*value, key * 10);
+    /// }
+    /// ```
+    pub fn try_range<R>(&self, range: R) -> TryItemIterator<'_, K, V>
+    where
+        R: RangeBounds<K>,
+    {
+        let (start_info, skip_first, end_info) = self.resolve_range_bounds(range);
+        if start_info.is_none() {
+            // Even an empty tree has a root leaf to start from
+            return TryItemIterator::failed(BPlusTreeError::corrupted_tree(
+                "Tree",
+                "no leaf reachable for the start of the range",
+            ));
+        }
+        TryItemIterator::new(RangeIterator::new_with_skip_owned(
+            self, start_info, skip_first, end_info,
+        ))
+    }
+
+    /// Returns an iterator over the entries with keys below `range.end`.
+    ///
+    /// The scan starts at the first leaf without descending the tree, so this
+    /// is the cheapest way to read a prefix of the key space.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..20 {
+    ///     tree.insert(i, i * 10);
+    /// }
+    /// assert_eq!(tree.head(..3).collect::<Vec<_>>(), vec![(&0, &0), (&1, &10), (&2, &20)]);
+    /// assert_eq!(tree.head_count(..15), 15);
+    /// ```
+    pub fn head(&self, range: RangeTo<K>) -> ItemIterator<'_, K, V> {
+        ItemIterator::new(self).with_end_key(range.end, false)
+    }
+
+    /// Returns an iterator over the entries with keys at or above
+    /// `range.start`.
+    ///
+    /// One descent finds the start; the scan then runs to the last leaf
+    /// without comparing keys against an end bound.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..20 {
+    ///     tree.insert(i, i * 10);
+    /// }
+    /// assert_eq!(tree.tail(18..).collect::<Vec<_>>(), vec![(&18, &180), (&19, &190)]);
+    /// assert_eq!(tree.tail_count(5..), 15);
+    /// ```
+    pub fn tail(&self, range: RangeFrom<K>) -> ItemIterator<'_, K, V> {
+        let (leaf_id, index) = self
+            .find_leaf_for_key(&range.start)
+            .unwrap_or((NULL_NODE, 0));
+        ItemIterator::new_from_position_with_bounds(self, leaf_id, index, Bound::Unbounded)
+    }
+
+    /// Counts the entries with keys below `range.end`.
+    pub fn head_count(&self, range: RangeTo<K>) -> usize {
+        self.count_range(range)
+    }
+
+    /// Counts the entries with keys at or above `range.start`.
+    pub fn tail_count(&self, range: RangeFrom<K>) -> usize {
+        self.count_range(range)
+    }
+
+    /// Counts the entries whose keys fall in `range`.
+    ///
+    /// Only the first and last leaf of the range are searched; every leaf in
+    /// between contributes its length without visiting its entries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..100 {
+    ///     tree.insert(i, i);
+    /// }
+    /// assert_eq!(tree.count_range(10..20), 10);
+    /// assert_eq!(tree.count_range(95..), 5);
+    /// ```
+    pub fn count_range<R>(&self, range: R) -> usize
+    where
+        R: RangeBounds<K>,
+    {
+        let mut count = 0;
+        self.for_each_leaf_slice_in_range(&range, |keys, _| count += keys.len());
+        count
+    }
+
+    /// Returns an iterator over the entries at positions `ranks` in key
+    /// order, counting from zero, for paging through the map by position.
+    ///
+    /// Finding the start is linear in the rank, not logarithmic: branches do
+    /// not record how many entries lie below them, so there is no rank
+    /// descent. Instead the leaf chain is followed from the first leaf,
+    /// skipping each leaf by its length without visiting its entries, which
+    /// costs one step per leaf before the start. Pages deep into a large map
+    /// get slower the deeper they are; to page through the whole map, resume
+    /// with [`range`](Self::range) from the last key of the previous page
+    /// instead. The end is found the same way from the start, and the
+    /// iterator then stops before the key there.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..100 {
+    ///     tree.insert(i * 2, i);
+    /// }
+    /// let page: Vec<_> = tree.range_by_rank(10..13).map(|(k, _)| *k).collect();
+    /// assert_eq!(page, vec![20, 22, 24]);
+    /// assert_eq!(tree.range_by_rank(98..).count(), 2);
+    /// assert_eq!(tree.range_by_rank(150..).next(), None);
+    /// ```
+    pub fn range_by_rank<R>(&self, ranks: R) -> RangeIterator<'_, K, V>
+    where
+        R: RangeBounds<usize>,
+    {
+        let start = match ranks.start_bound() {
+            Bound::Included(&rank) => Some(rank),
+            Bound::Excluded(&rank) => rank.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        // `None` runs to the last entry
+        let end = match ranks.end_bound() {
+            Bound::Included(&rank) => rank.checked_add(1),
+            Bound::Excluded(&rank) => Some(rank),
+            Bound::Unbounded => None,
+        };
+
+        let start_info = start
+            .filter(|&start| end.is_none_or(|end| start < end))
+            .and_then(|start| {
+                let first = self.get_first_leaf_id()?;
+                self.position_after_entries((first, 0), start)
+            });
+        let end_info = start_info
+            .zip(start)
+            .zip(end)
+            .and_then(|((position, start), end)| {
+                let (leaf_id, index) = self.position_after_entries(position, end - start)?;
+                let key = self.get_leaf(leaf_id)?.get_key(index)?;
+                Some((key.clone(), false))
+            });
+        RangeIterator::new_with_skip_owned(self, start_info, false, end_info)
+    }
+
+    /// Returns owned copies of the entries whose keys fall in `range`.
+    ///
+    /// The result is allocated once at its final size and filled leaf by leaf,
+    /// which avoids the reallocations and per-item bound checks of collecting
+    /// from [`range`](BPlusTreeMap::range).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..10 {
+    ///     tree.insert(i, i.to_string());
+    /// }
+    /// let owned = tree.collect_range_owned(3..=5);
+    /// assert_eq!(owned, vec![(3, "3".to_string()), (4, "4".to_string()), (5, "5".to_string())]);
+    /// ```
+    pub fn collect_range_owned<R>(&self, range: R) -> Vec<(K, V)>
+    where
+        R: RangeBounds<K>,
+    {
+        // Find the leaf slices first so the result can be sized exactly
+        let mut slices = Vec::new();
+        self.for_each_leaf_slice_in_range(&range, |keys, values| slices.push((keys, values)));
+
+        let total = slices.iter().map(|(keys, _)| keys.len()).sum();
+        let mut items = Vec::with_capacity(total);
+        for (keys, values) in slices {
+            items.extend(keys.iter().cloned().zip(values.iter().cloned()));
+        }
+        items
+    }
+
+    /// Returns true if any key falls in `range`.
+    ///
+    /// Descends once and looks at the first key at or after the start bound,
+    /// so it costs about the same as a single lookup and builds no iterator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut reservations = BPlusTreeMap::new(16).unwrap();
+    /// reservations.insert(900, "standup");
+    /// reservations.insert(1400, "review");
+    ///
+    /// assert!(reservations.contains_range(1300..1500));
+    /// assert!(!reservations.contains_range(1000..1400));
+    /// ```
+    pub fn contains_range<R>(&self, range: R) -> bool
+    where
+        R: RangeBounds<K>,
+    {
+        let (mut leaf_id, mut index) = match self.range_start_position(range.start_bound()) {
+            Some(position) => position,
+            None => return false,
+        };
+
+        // The first candidate is usually in this leaf; it is only in a later
+        // one when the start falls past this leaf's last key
+        while let Some(leaf) = self.get_leaf(leaf_id) {
+            if let Some(key) = leaf.keys.get(index) {
+                return match range.end_bound() {
+                    Bound::Included(end) => key <= end,
+                    Bound::Excluded(end) => key < end,
+                    Bound::Unbounded => true,
+                };
+            }
+            leaf_id = leaf.next;
+            index = 0;
+        }
+        false
+    }
+
+    /// Returns the smallest key strictly greater than `probe`.
+    ///
+    /// `probe` need not be in the tree. This is one descent, plus a step along
+    /// the leaf chain when `probe` sorts after every key in its leaf.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..10 {
+    ///     tree.insert(i * 10, ());
+    /// }
+    /// assert_eq!(tree.next_key_after(&35), Some(&40));
+    /// assert_eq!(tree.next_key_after(&40), Some(&50));
+    /// assert_eq!(tree.next_key_after(&90), None);
+    /// ```
+    pub fn next_key_after(&self, probe: &K) -> Option<&K> {
+        let (mut leaf_id, mut index) = self.range_start_position(Bound::Excluded(probe))?;
+        while let Some(leaf) = self.get_leaf(leaf_id) {
+            if let Some(key) = leaf.keys.get(index) {
+                return Some(key);
+            }
+            leaf_id = leaf.next;
+            index = 0;
+        }
+        None
+    }
+
+    /// Returns the largest key strictly less than `probe`.
+    ///
+    /// `probe` need not be in the tree. Leaves are only linked forwards, so
+    /// when the answer is not in `probe`'s leaf this descends the nearest left
+    /// subtree along its right edge instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut tree = BPlusTreeMap::new(4).unwrap();
+    /// for i in 0..10 {
+    ///     tree.insert(i * 10, ());
+    /// }
+    /// assert_eq!(tree.prev_key_before(&35), Some(&30));
+    /// assert_eq!(tree.prev_key_before(&30), Some(&20));
+    /// assert_eq!(tree.prev_key_before(&0), None);
+    /// ```
+    pub fn prev_key_before(&self, probe: &K) -> Option<&K> {
+        self.last_entry_before(Bound::Excluded(probe))
+            .map(|(key, _)| key)
+    }
+
     /// Returns the first key-value pair in the tree.
     pub fn first(&self) -> Option<(&K, &V)> {
         self.items().next()
@@ -61,7 +371,7 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
 
     /// Returns the last key-value pair in the tree.
     pub fn last(&self) -> Option<(&K, &V)> {
-        self.items().last()
+        self.items().next_back()
     }
 
     // ============================================================================
@@ -76,7 +386,11 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
         // Optimize start bound resolution - eliminate redundant Option handling
         let (start_info, skip_first) = match range.start_bound() {
             Bound::Included(key) => (self.find_leaf_for_key(key), false),
-            Bound::Excluded(key) => (self.find_leaf_for_key(key), true),
+            Bound::Excluded(key) => (
+                self.find_leaf_for_key(key)
+                    .map(|position| self.position_after_key(position, key)),
+                false,
+            ),
             Bound::Unbounded => (self.get_first_leaf_id().map(|id| (id, 0)), false),
         };
 
@@ -90,6 +404,121 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
         (start_info, skip_first, end_info)
     }
 
+    /// Step an insertion position for `key` past `key` itself, if present, so
+    /// that it is the start of a range with `key` excluded.
+    pub(crate) fn position_after_key(
+        &self,
+        (leaf_id, index): (NodeId, usize),
+        key: &K,
+    ) -> (NodeId, usize) {
+        let matched = self
+            .get_leaf(leaf_id)
+            .and_then(|leaf| leaf.get_key(index))
+            .is_some_and(|found| found == key);
+        (leaf_id, index + usize::from(matched))
+    }
+
+    /// Position of the entry `count` entries after `(leaf_id, index)`,
+    /// following the leaf chain. `None` if the chain ends first.
+    pub(crate) fn position_after_entries(
+        &self,
+        (mut leaf_id, index): (NodeId, usize),
+        count: usize,
+    ) -> Option<(NodeId, usize)> {
+        let mut remaining = index.checked_add(count)?;
+        loop {
+            let leaf = self.get_leaf(leaf_id)?;
+            if remaining < leaf.keys.len() {
+                return Some((leaf_id, remaining));
+            }
+            remaining -= leaf.keys.len();
+            leaf_id = leaf.next;
+        }
+    }
+
+    /// Last entry before an end bound: at or below an `Included` key, below an
+    /// `Excluded` one, or the last entry in the tree when `Unbounded`.
+    pub(crate) fn last_entry_before(&self, end: Bound<&K>) -> Option<(&K, &V)> {
+        self.last_entry_in_subtree(&self.root, end)
+    }
+
+    /// Last entry before `end` within a subtree, skipping leaves emptied by
+    /// deferred rebalancing.
+    fn last_entry_in_subtree(&self, node: &NodeRef<K, V>, end: Bound<&K>) -> Option<(&K, &V)> {
+        match node {
+            NodeRef::Leaf(id, _) => {
+                let leaf = self.get_leaf(*id)?;
+                let count = match end {
+                    Bound::Included(probe) => leaf.keys.partition_point(|key| key <= probe),
+                    Bound::Excluded(probe) => leaf.keys.partition_point(|key| key < probe),
+                    Bound::Unbounded => leaf.keys.len(),
+                };
+                let index = count.checked_sub(1)?;
+                Some((&leaf.keys[index], &leaf.values[index]))
+            }
+            NodeRef::Branch(id, _) => {
+                let branch = self.get_branch(*id)?;
+                let child_index = match end {
+                    Bound::Included(probe) | Bound::Excluded(probe) => {
+                        branch.find_child_index(probe)
+                    }
+                    Bound::Unbounded => branch.child_count() - 1,
+                };
+                self.last_entry_in_subtree(&branch.child(child_index)?, end)
+                    .or_else(|| {
+                        // Everything left of the probe's child is smaller
+                        branch
+                            .children()
+                            .take(child_index)
+                            .rev()
+                            .find_map(|child| self.last_entry_in_subtree(&child, Bound::Unbounded))
+                    })
+            }
+        }
+    }
+
+    /// Leaf and index of the first entry at or after a start bound. The index
+    /// may equal the leaf's length, in which case the entry is in a later leaf.
+    pub(crate) fn range_start_position(&self, start: Bound<&K>) -> Option<(NodeId, usize)> {
+        match start {
+            Bound::Included(key) => self.find_leaf_for_key(key),
+            Bound::Excluded(key) => self
+                .find_leaf_for_key_with_match(key)
+                .map(|(leaf_id, index, matched)| (leaf_id, index + usize::from(matched))),
+            Bound::Unbounded => self.get_first_leaf_id().map(|leaf_id| (leaf_id, 0)),
+        }
+    }
+
+    /// Call `f` with the keys and values of each leaf that overlap `range`,
+    /// in key order.
+    pub(crate) fn for_each_leaf_slice_in_range<'a, R, F>(&'a self, range: &R, mut f: F)
+    where
+        R: RangeBounds<K>,
+        F: FnMut(&'a [K], &'a [V]),
+    {
+        let (mut leaf_id, mut start) = match self.range_start_position(range.start_bound()) {
+            Some(position) => position,
+            None => return,
+        };
+
+        while let Some(leaf) = self.get_leaf(leaf_id) {
+            let keys = &leaf.keys[start.min(leaf.keys.len())..];
+            let end = match range.end_bound() {
+                Bound::Included(key) => keys.partition_point(|k| k <= key),
+                Bound::Excluded(key) => keys.partition_point(|k| k < key),
+                Bound::Unbounded => keys.len(),
+            };
+            if end > 0 {
+                f(&keys[..end], &leaf.values[start..start + end]);
+            }
+            if end < keys.len() {
+                return;
+            }
+            leaf_id = leaf.next;
+            start = 0;
+        }
+    }
+
     // ============================================================================
     // RANGE OPTIMIZATION HELPERS
     // ============================================================================
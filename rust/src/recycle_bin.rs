@@ -0,0 +1,235 @@
+//! Soft deletion with undo.
+//!
+//! [`RecycleBinMap`] keeps removed entries in a second [`BPlusTreeMap`], the
+//! recycle bin, stamped with the time they were removed. Until the bin is
+//! purged a removal can be undone with [`restore`](RecycleBinMap::restore),
+//! which gives applications trash-can semantics without maintaining a shadow
+//! structure by hand.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::error::InitResult;
+use crate::iteration::RangeIterator;
+use crate::types::BPlusTreeMap;
+use std::fmt;
+use std::ops::RangeBounds;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A removed value and when it was removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deleted<V> {
+    /// The value held when the key was removed.
+    pub value: V,
+    /// Clock reading at removal.
+    pub deleted_at: u64,
+}
+
+/// A map whose removals can be undone until they are purged.
+///
+/// Timestamps come from a clock function, by default the milliseconds since
+/// the Unix epoch. Inserting a key that is in the bin discards the binned
+/// entry, so [`restore`](Self::restore) never overwrites a live value.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::RecycleBinMap;
+///
+/// let mut clock = 0;
+/// let mut notes = RecycleBinMap::with_clock(16, move || {
+///     clock += 1;
+///     clock
+/// })
+/// .unwrap();
+/// notes.insert(1, "draft");
+/// notes.insert(2, "final");
+///
+/// notes.remove(&1);
+/// notes.remove(&2);
+/// assert!(notes.is_empty());
+///
+/// assert_eq!(notes.restore(&2), Some(&"final"));
+/// assert_eq!(notes.purge_older_than(10), 1);
+/// assert_eq!(notes.restore(&1), None);
+/// ```
+pub struct RecycleBinMap<K, V> {
+    live: BPlusTreeMap<K, V>,
+    bin: BPlusTreeMap<K, Deleted<V>>,
+    clock: Box<dyn FnMut() -> u64>,
+}
+
+impl<K: TreeKey, V: TreeValue> RecycleBinMap<K, V> {
+    /// Create an empty map whose removals are stamped with wall-clock
+    /// milliseconds.
+    pub fn new(capacity: usize) -> InitResult<Self> {
+        Self::with_clock(capacity, || {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis() as u64)
+        })
+    }
+
+    /// Create an empty map whose removals are stamped by `clock`.
+    pub fn with_clock(capacity: usize, clock: impl FnMut() -> u64 + 'static) -> InitResult<Self> {
+        Ok(Self {
+            live: BPlusTreeMap::new(capacity)?,
+            bin: BPlusTreeMap::new(capacity)?,
+            clock: Box::new(clock),
+        })
+    }
+
+    /// The live entries.
+    pub fn tree(&self) -> &BPlusTreeMap<K, V> {
+        &self.live
+    }
+
+    /// The removed entries awaiting restore or purge.
+    pub fn recycle_bin(&self) -> &BPlusTreeMap<K, Deleted<V>> {
+        &self.bin
+    }
+
+    /// Number of live entries.
+    pub fn len(&self) -> usize {
+        self.live.len()
+    }
+
+    /// Returns true if there are no live entries.
+    pub fn is_empty(&self) -> bool {
+        self.live.is_empty()
+    }
+
+    /// Get the live value stored under `key`.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.live.get(key)
+    }
+
+    /// Iterate over the live entries in `range`.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> RangeIterator<'_, K, V> {
+        self.live.range(range)
+    }
+
+    /// Insert `value` under `key`, returning the previous live value if any.
+    /// A binned entry for `key` is discarded.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.bin.remove(&key);
+        self.live.insert(key, value)
+    }
+
+    /// Move `key` to the recycle bin, returning a reference to the binned
+    /// value if the key was live.
+    pub fn remove(&mut self, key: &K) -> Option<&V> {
+        let value = self.live.remove(key)?;
+        let deleted_at = (self.clock)();
+        self.bin.insert(key.clone(), Deleted { value, deleted_at });
+        self.bin.get(key).map(|deleted| &deleted.value)
+    }
+
+    /// Move `key` back from the recycle bin, returning a reference to the
+    /// restored value, or `None` if the key is not in the bin.
+    pub fn restore(&mut self, key: &K) -> Option<&V> {
+        let deleted = self.bin.remove(key)?;
+        self.live.insert(key.clone(), deleted.value);
+        self.live.get(key)
+    }
+
+    /// Permanently drop binned entries removed before `timestamp`, returning
+    /// how many were dropped.
+    pub fn purge_older_than(&mut self, timestamp: u64) -> usize {
+        let expired: Vec<K> = self
+            .bin
+            .items()
+            .filter(|(_, deleted)| deleted.deleted_at < timestamp)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &expired {
+            self.bin.remove(key);
+        }
+        expired.len()
+    }
+
+    /// Permanently drop every binned entry.
+    pub fn empty_recycle_bin(&mut self) {
+        self.bin.clear();
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for RecycleBinMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecycleBinMap")
+            .field("live", &self.live)
+            .field("bin", &self.bin)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn map_with_manual_clock() -> (RecycleBinMap<i32, i32>, Rc<Cell<u64>>) {
+        let now = Rc::new(Cell::new(0));
+        let clock = Rc::clone(&now);
+        let map = RecycleBinMap::with_clock(4, move || clock.get()).unwrap();
+        (map, now)
+    }
+
+    #[test]
+    fn test_remove_and_restore_round_trip() {
+        let (mut map, now) = map_with_manual_clock();
+        for i in 0..50 {
+            map.insert(i, i * 10);
+        }
+        for i in (0..50).step_by(2) {
+            now.set(i as u64);
+            assert_eq!(map.remove(&i), Some(&(i * 10)));
+        }
+        assert_eq!(map.remove(&0), None);
+        assert_eq!(map.len(), 25);
+        assert_eq!(map.recycle_bin().len(), 25);
+        assert_eq!(
+            map.recycle_bin().get(&10),
+            Some(&Deleted {
+                value: 100,
+                deleted_at: 10
+            })
+        );
+
+        for i in (0..50).step_by(2) {
+            assert_eq!(map.restore(&i), Some(&(i * 10)));
+        }
+        assert_eq!(map.restore(&0), None);
+        assert!(map
+            .range(..)
+            .map(|(k, v)| (*k, *v))
+            .eq((0..50).map(|i| (i, i * 10))));
+        assert!(map.recycle_bin().is_empty());
+        assert!(map.tree().check_invariants() && map.recycle_bin().check_invariants());
+    }
+
+    #[test]
+    fn test_purge_and_reinsert_drop_binned_entries() {
+        let (mut map, now) = map_with_manual_clock();
+        for i in 0..20 {
+            map.insert(i, i);
+        }
+        for i in 0..20 {
+            now.set(100 + i as u64);
+            map.remove(&i);
+        }
+
+        assert_eq!(map.purge_older_than(110), 10);
+        assert_eq!(map.purge_older_than(110), 0);
+        assert_eq!(map.restore(&9), None);
+
+        // A new value supersedes the binned one
+        map.insert(15, -1);
+        assert_eq!(map.restore(&15), None);
+        assert_eq!(map.get(&15), Some(&-1));
+
+        assert_eq!(map.restore(&12), Some(&12));
+        map.empty_recycle_bin();
+        assert_eq!(map.restore(&13), None);
+        assert_eq!(map.len(), 2);
+    }
+}
@@ -0,0 +1,99 @@
+//! Rebuilding a tree under new keys.
+//!
+//! Schema migrations often change the key type but not its order: seconds
+//! become milliseconds, a `u32` id widens to a `u64`. Such a transform
+//! keeps the entries sorted, so the new tree can be loaded in a single
+//! batch with no real sorting work. A transform that does not preserve
+//! order still works; the batch sorts the entries on the way in.
+
+use crate::batch_operations::{BatchOp, WriteBatch};
+use crate::bounds::{TreeKey, TreeValue};
+use crate::types::BPlusTreeMap;
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Consume the tree and rebuild it with every key passed through `f`.
+    ///
+    /// The new tree has the same capacity, rebalance strategy and deletion
+    /// mode. A byte budget is not carried over, as its size function is
+    /// written for the old key type.
+    ///
+    /// The entries are loaded with one [`apply_batch`](Self::apply_batch),
+    /// whose stable sort takes linear time on input that is already in
+    /// order. So an increasing `f` costs no sorting, while any other `f` is
+    /// still handled correctly. If `f` maps several keys to the same new
+    /// key, the value of the largest old key wins, as if the entries had
+    /// been inserted in their old order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut seconds = BPlusTreeMap::new(16).unwrap();
+    /// seconds.insert(1_700_000_000u64, "login");
+    /// seconds.insert(1_700_000_005u64, "logout");
+    ///
+    /// let millis = seconds.map_keys(|secs| secs * 1_000);
+    /// assert_eq!(millis.get(&1_700_000_005_000), Some(&"logout"));
+    ///
+    /// // Not monotonic: still correct, just sorted on the way
+    /// let mut words = BPlusTreeMap::new(16).unwrap();
+    /// words.insert(1, "one");
+    /// words.insert(2, "two");
+    /// let by_name = words.map_keys(|n| if n == 1 { "z" } else { "a" });
+    /// assert_eq!(by_name.keys().collect::<Vec<_>>(), vec![&"a", &"z"]);
+    /// ```
+    pub fn map_keys<K2, F>(mut self, mut f: F) -> BPlusTreeMap<K2, V>
+    where
+        K2: TreeKey,
+        F: FnMut(K) -> K2,
+    {
+        let mut target = BPlusTreeMap::with_valid_capacity(self.capacity);
+        target.set_rebalance_strategy(self.rebalance_strategy);
+        target.set_deletion_mode(self.deletion_mode);
+        target.set_overflow_mode(self.overflow_mode);
+
+        let batch: WriteBatch<K2, V> = self
+            .remove_min_k(self.len())
+            .into_iter()
+            .map(|(key, value)| BatchOp::Insert(f(key), value))
+            .collect();
+        target.apply_batch(batch);
+        target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_map_keys_monotonic() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..1_000u32 {
+            tree.insert(i, i);
+        }
+        let tree = tree.map_keys(|k| u64::from(k) * 1_000);
+        assert_eq!(tree.len(), 1_000);
+        assert!(tree.items().all(|(k, v)| *k == u64::from(*v) * 1_000));
+        assert!(tree.check_invariants());
+    }
+
+    #[test]
+    fn test_map_keys_non_monotonic_matches_reference() {
+        // Reversing and folding onto 100 keys exercises both the sort
+        // fallback and collisions
+        let transform = |k: u32| (1_000 - k) % 100;
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        let mut model = BTreeMap::new();
+        for i in 0..1_000 {
+            tree.insert(i, i);
+            model.insert(transform(i), i);
+        }
+
+        let tree = tree.map_keys(transform);
+        assert!(tree.items().map(|(k, v)| (*k, *v)).eq(model));
+        assert!(tree.check_invariants());
+    }
+}
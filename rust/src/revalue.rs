@@ -0,0 +1,221 @@
+//! Transforming every value without searching for its key.
+//!
+//! Updating each entry through `get_mut` descends from the root once per
+//! key. The methods here visit the entries in key order without repeated
+//! descents: [`map_values_in_place`] walks the leaf chain and rewrites each
+//! leaf's value array where it lies, [`update_many`] does the same for a
+//! sorted list of keys, and [`map_values`] drains the tree and loads the
+//! results into a new one in a single batch.
+//!
+//! [`map_values_in_place`]: BPlusTreeMap::map_values_in_place
+//! [`update_many`]: BPlusTreeMap::update_many
+//! [`map_values`]: BPlusTreeMap::map_values
+
+use crate::batch_operations::{BatchOp, WriteBatch};
+use crate::bounds::{TreeKey, TreeValue};
+use crate::types::{BPlusTreeMap, NULL_NODE};
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Call `f` on every entry in ascending key order, letting it modify the
+    /// value.
+    ///
+    /// The tree's shape does not change, so this is a single pass over the
+    /// leaves. As with [`merge_value`](Self::merge_value), a value that grows
+    /// is not checked against a [`ByteBudget`](crate::ByteBudget).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut prices = BPlusTreeMap::new(4).unwrap();
+    /// for (item, price) in [("apple", 100), ("bread", 250), ("milk", 90)] {
+    ///     prices.insert(item, price);
+    /// }
+    ///
+    /// // Ten percent off everything but bread
+    /// prices.map_values_in_place(|item, price| {
+    ///     if *item != "bread" {
+    ///         *price -= *price / 10;
+    ///     }
+    /// });
+    /// assert_eq!(prices.values().copied().collect::<Vec<_>>(), vec![90, 250, 81]);
+    /// ```
+    pub fn map_values_in_place<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V),
+    {
+        let mut leaf_id = self.get_first_leaf_id();
+        while let Some(leaf) = leaf_id.and_then(|id| self.get_leaf_mut(id)) {
+            let (keys, values) = (&leaf.keys, &mut leaf.values);
+            for (key, value) in keys.iter().zip(values.iter_mut()) {
+                f(key, value);
+            }
+            leaf_id = (leaf.next != NULL_NODE).then_some(leaf.next);
+        }
+    }
+
+    /// Call `f` on the entry for each key in `keys` that is in the tree,
+    /// letting it modify the value, and return how many calls were made.
+    ///
+    /// The write-side counterpart of [`get_many`](Self::get_many). `keys`
+    /// must be in ascending order: the tree is descended once, for the first
+    /// key, and the rest are found by moving forward along the leaf chain, so
+    /// a key smaller than the one before it is not found. A key given twice
+    /// is visited twice. As with
+    /// [`map_values_in_place`](Self::map_values_in_place), a value that grows
+    /// is not checked against a [`ByteBudget`](crate::ByteBudget).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut balances = BPlusTreeMap::new(4).unwrap();
+    /// for account in 0..100 {
+    ///     balances.insert(account, 0);
+    /// }
+    ///
+    /// let credited = balances.update_many(&[3, 17, 42, 250], |_, balance| *balance += 10);
+    /// assert_eq!(credited, 3);
+    /// assert_eq!(balances.get(&42), Some(&10));
+    /// assert_eq!(balances.get(&43), Some(&0));
+    /// ```
+    pub fn update_many<F>(&mut self, keys: &[K], mut f: F) -> usize
+    where
+        F: FnMut(&K, &mut V),
+    {
+        let Some((mut leaf_id, mut start)) =
+            keys.first().and_then(|first| self.find_leaf_for_key(first))
+        else {
+            return 0;
+        };
+        let mut updated = 0;
+        for key in keys {
+            // Move right until the leaf that holds the key or its successor
+            loop {
+                let Some(leaf) = self.get_leaf_mut(leaf_id) else {
+                    return updated;
+                };
+                let rest = &leaf.keys[start.min(leaf.keys.len())..];
+                match rest.binary_search(key) {
+                    Ok(offset) => {
+                        start += offset;
+                        f(&leaf.keys[start], &mut leaf.values[start]);
+                        updated += 1;
+                        break;
+                    }
+                    Err(offset) if offset < rest.len() => {
+                        start += offset;
+                        break;
+                    }
+                    Err(_) if leaf.next == NULL_NODE => return updated,
+                    Err(_) => {
+                        leaf_id = leaf.next;
+                        start = 0;
+                    }
+                }
+            }
+        }
+        updated
+    }
+
+    /// Consume the tree and rebuild it with every value passed through `f`.
+    ///
+    /// The keys are unchanged and already in order, so the new tree is
+    /// loaded with one [`apply_batch`](Self::apply_batch) and no sorting. It
+    /// has the same capacity, rebalance strategy and deletion mode; a byte
+    /// budget is not carried over, as its size function is written for the
+    /// old value type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut lines = BPlusTreeMap::new(4).unwrap();
+    /// lines.insert(1, "fn main() {");
+    /// lines.insert(2, "}");
+    ///
+    /// let lengths = lines.map_values(|_, line| line.len());
+    /// assert_eq!(lengths.get(&1), Some(&11));
+    /// ```
+    pub fn map_values<V2, F>(mut self, mut f: F) -> BPlusTreeMap<K, V2>
+    where
+        V2: TreeValue,
+        F: FnMut(&K, V) -> V2,
+    {
+        let mut target = BPlusTreeMap::with_valid_capacity(self.capacity);
+        target.set_rebalance_strategy(self.rebalance_strategy);
+        target.set_deletion_mode(self.deletion_mode);
+        target.set_overflow_mode(self.overflow_mode);
+
+        let batch: WriteBatch<K, V2> = self
+            .remove_min_k(self.len())
+            .into_iter()
+            .map(|(key, value)| {
+                let value = f(&key, value);
+                BatchOp::Insert(key, value)
+            })
+            .collect();
+        target.apply_batch(batch);
+        target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+
+    #[test]
+    fn test_map_values_in_place_and_rebuilt_agree() {
+        for count in [0u32, 1, 3, 1_000] {
+            let mut tree = BPlusTreeMap::new(4).unwrap();
+            for i in 0..count {
+                tree.insert(i, i);
+            }
+
+            let mut visited = Vec::new();
+            tree.map_values_in_place(|key, value| {
+                visited.push(*key);
+                *value = *key * 2 + 1;
+            });
+            assert!(visited.iter().copied().eq(0..count));
+            assert!(tree.items().all(|(k, v)| *v == k * 2 + 1));
+
+            let rebuilt = tree.map_values(|key, value| u64::from(value - key));
+            assert_eq!(rebuilt.len(), count as usize);
+            assert!(rebuilt.items().all(|(k, v)| *v == u64::from(k + 1)));
+            assert!(rebuilt.check_invariants());
+        }
+    }
+
+    #[test]
+    fn test_update_many_matches_get_mut() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        let mut expected = BPlusTreeMap::new(4).unwrap();
+        for i in (0..2_000u32).step_by(2) {
+            tree.insert(i, 0u32);
+            expected.insert(i, 0u32);
+        }
+
+        let mut rng = SeededRng::new(41);
+        let mut keys: Vec<u32> = (0..700).map(|_| rng.below(2_100) as u32).collect();
+        keys.sort();
+
+        let updated = tree.update_many(&keys, |key, value| *value += key + 1);
+        let mut count = 0;
+        for key in &keys {
+            if let Some(value) = expected.get_mut(key) {
+                *value += key + 1;
+                count += 1;
+            }
+        }
+        assert_eq!(updated, count);
+        assert!(tree.items().eq(expected.items()));
+
+        assert_eq!(tree.update_many(&[], |_, _| unreachable!()), 0);
+        assert_eq!(tree.update_many(&[5_000, 6_000], |_, _| unreachable!()), 0);
+    }
+}
@@ -0,0 +1,404 @@
+//! Range scans that can stop early and pick up where they left off.
+//!
+//! A request handler walking a large range may need to give up when the
+//! caller has what it needs, or when its time slot runs out.
+//! [`BPlusTreeMap::scan_until`] calls a closure on each entry, stops as soon
+//! as the closure returns [`ControlFlow::Break`] or the deadline passes, and
+//! reports how far it got so that a later call can resume the scan.
+//!
+//! A maintenance job that changes the tree as it walks it cannot hold a
+//! borrowing iterator. [`BPlusTreeMap::scan_weak`] returns a [`WeakScan`]
+//! that keeps only the last key it returned and borrows the tree for one
+//! step at a time, seeking again from that key whenever it needs a new
+//! leaf.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::types::{BPlusTreeMap, NULL_NODE};
+use std::ops::{Bound, ControlFlow, RangeBounds};
+use std::time::Instant;
+
+/// How a scan started by [`BPlusTreeMap::scan_until`] ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanOutcome<K, B> {
+    /// Every entry in the range was visited.
+    Finished {
+        /// Entries passed to the closure.
+        visited: usize,
+    },
+    /// The closure returned `Break(value)` on the entry with key `last`.
+    Stopped {
+        /// Entries passed to the closure, including `last`.
+        visited: usize,
+        /// Key of the entry the closure stopped on.
+        last: K,
+        /// The value the closure broke with.
+        value: B,
+    },
+    /// The deadline passed after the entry with key `last`.
+    TimedOut {
+        /// Entries passed to the closure, including `last`.
+        visited: usize,
+        /// Key of the last entry visited.
+        last: K,
+    },
+}
+
+impl<K: Clone, B> ScanOutcome<K, B> {
+    /// Number of entries passed to the closure.
+    pub fn visited(&self) -> usize {
+        match self {
+            ScanOutcome::Finished { visited }
+            | ScanOutcome::Stopped { visited, .. }
+            | ScanOutcome::TimedOut { visited, .. } => *visited,
+        }
+    }
+
+    /// Returns true if the scan reached the end of its range.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, ScanOutcome::Finished { .. })
+    }
+
+    /// Start bound for a scan of the rest of the range, just after the last
+    /// entry visited, or `None` if the scan finished.
+    pub fn resume_from(&self) -> Option<Bound<K>> {
+        match self {
+            ScanOutcome::Finished { .. } => None,
+            ScanOutcome::Stopped { last, .. } | ScanOutcome::TimedOut { last, .. } => {
+                Some(Bound::Excluded(last.clone()))
+            }
+        }
+    }
+}
+
+/// A scan over a range that the tree may change under between steps.
+/// Returned by [`BPlusTreeMap::scan_weak`].
+///
+/// Each leaf is copied out whole when the scan reaches it, and the next one
+/// is found by seeking past the last key returned. Keys only move forward,
+/// so no key is returned twice, and every key that stays in the tree for
+/// the whole scan is returned exactly once. Keys inserted or removed while
+/// the scan runs may or may not be seen, and an entry comes back with the
+/// value it had when its leaf was copied.
+#[derive(Debug, Clone)]
+pub struct WeakScan<K, V> {
+    /// Where the next leaf read starts.
+    resume: Bound<K>,
+    end: Bound<K>,
+    /// Entries of the last leaf read that have not been returned yet.
+    buffer: std::vec::IntoIter<(K, V)>,
+    done: bool,
+}
+
+impl<K: TreeKey, V: TreeValue> WeakScan<K, V> {
+    /// The next entry of the scan, or `None` once the range is used up.
+    /// Reads the next leaf from `tree` when the last one has been returned.
+    pub fn advance(&mut self, tree: &BPlusTreeMap<K, V>) -> Option<(K, V)> {
+        if let Some(entry) = self.buffer.next() {
+            return Some(entry);
+        }
+        if self.done {
+            return None;
+        }
+        let entries = self.read_leaf(tree);
+        match entries.last() {
+            Some((last, _)) => self.resume = Bound::Excluded(last.clone()),
+            None => self.done = true,
+        }
+        self.buffer = entries.into_iter();
+        self.buffer.next()
+    }
+
+    /// Copy the in-range entries of the first non-empty leaf at or after the
+    /// resume point.
+    fn read_leaf(&mut self, tree: &BPlusTreeMap<K, V>) -> Vec<(K, V)> {
+        let Some((mut leaf_id, mut start)) = tree.range_start_position(self.resume.as_ref()) else {
+            return Vec::new();
+        };
+        while let Some(leaf) = tree.get_leaf(leaf_id) {
+            let start_at = start.min(leaf.keys.len());
+            let keys = &leaf.keys[start_at..];
+            if !keys.is_empty() {
+                let end = match &self.end {
+                    Bound::Included(end) => keys.partition_point(|k| k <= end),
+                    Bound::Excluded(end) => keys.partition_point(|k| k < end),
+                    Bound::Unbounded => keys.len(),
+                };
+                self.done = end < keys.len();
+                let values = &leaf.values[start_at..start_at + end];
+                return keys[..end]
+                    .iter()
+                    .cloned()
+                    .zip(values.iter().cloned())
+                    .collect();
+            }
+            if leaf.next == NULL_NODE {
+                break;
+            }
+            leaf_id = leaf.next;
+            start = 0;
+        }
+        Vec::new()
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Start a scan of `range` that does not borrow the tree between steps,
+    /// so the tree can be changed while the scan is under way.
+    ///
+    /// See [`WeakScan`] for what the scan sees of those changes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::BPlusTreeMap;
+    ///
+    /// let mut sessions = BPlusTreeMap::new(4).unwrap();
+    /// for id in 0..100 {
+    ///     sessions.insert(id, id % 7);
+    /// }
+    ///
+    /// // Expire idle sessions, opening replacements, while walking the tree
+    /// let mut scan = sessions.scan_weak(..);
+    /// while let Some((id, idle)) = scan.advance(&sessions) {
+    ///     if idle > 4 {
+    ///         sessions.remove(&id);
+    ///         sessions.insert(id + 1_000, 0);
+    ///     }
+    /// }
+    /// assert!(sessions.values().all(|&idle| idle <= 4));
+    /// ```
+    pub fn scan_weak<R: RangeBounds<K>>(&self, range: R) -> WeakScan<K, V> {
+        WeakScan {
+            resume: range.start_bound().cloned(),
+            end: range.end_bound().cloned(),
+            buffer: Vec::new().into_iter(),
+            done: false,
+        }
+    }
+
+    /// Call `f` on each entry in `range`, in key order, until it returns
+    /// [`ControlFlow::Break`], the range ends, or `deadline` passes.
+    ///
+    /// The deadline is checked between leaves, so a scan overruns it by at
+    /// most one leaf's worth of calls. It is only checked once at least one
+    /// entry has been visited, so every call makes progress even when the
+    /// deadline has already passed. To carry on after a stop or a timeout,
+    /// scan again from [`ScanOutcome::resume_from`] to the original end.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::{BPlusTreeMap, ScanOutcome};
+    /// use std::ops::{Bound, ControlFlow};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let mut tree = BPlusTreeMap::new(16).unwrap();
+    /// for i in 0..10_000 {
+    ///     tree.insert(i, i % 97);
+    /// }
+    ///
+    /// // Find the first value above 90, giving up after a millisecond
+    /// let deadline = Instant::now() + Duration::from_millis(1);
+    /// let outcome = tree.scan_until(.., Some(deadline), |key, &value| {
+    ///     if value > 90 {
+    ///         ControlFlow::Break(*key)
+    ///     } else {
+    ///         ControlFlow::Continue(())
+    ///     }
+    /// });
+    /// if let ScanOutcome::Stopped { value, .. } = outcome {
+    ///     assert_eq!(value, 91);
+    /// }
+    ///
+    /// // Sum the whole tree in slices that each stop at an expired deadline
+    /// let (mut start, mut total) = (Bound::Unbounded, 0);
+    /// loop {
+    ///     let outcome = tree.scan_until((start, Bound::Unbounded), Some(Instant::now()), |_, v| {
+    ///         total += v;
+    ///         ControlFlow::<()>::Continue(())
+    ///     });
+    ///     match outcome.resume_from() {
+    ///         Some(resume) => start = resume,
+    ///         None => break,
+    ///     }
+    /// }
+    /// assert_eq!(total, tree.values().sum());
+    /// ```
+    pub fn scan_until<R, F, B>(
+        &self,
+        range: R,
+        deadline: Option<Instant>,
+        mut f: F,
+    ) -> ScanOutcome<K, B>
+    where
+        R: RangeBounds<K>,
+        F: FnMut(&K, &V) -> ControlFlow<B>,
+    {
+        let mut visited = 0;
+        let mut last: Option<&K> = None;
+        let Some((mut leaf_id, mut start)) = self.range_start_position(range.start_bound()) else {
+            return ScanOutcome::Finished { visited };
+        };
+        let in_range = |key: &K| match range.end_bound() {
+            Bound::Included(end) => key <= end,
+            Bound::Excluded(end) => key < end,
+            Bound::Unbounded => true,
+        };
+
+        while let Some(leaf) = self.get_leaf(leaf_id) {
+            let start_at = start.min(leaf.keys.len());
+            let keys = &leaf.keys[start_at..];
+            let values = &leaf.values[start_at..];
+            if let Some(first) = keys.first() {
+                if !in_range(first) {
+                    break;
+                }
+                if let (Some(last), Some(deadline)) = (last, deadline) {
+                    if Instant::now() >= deadline {
+                        return ScanOutcome::TimedOut {
+                            visited,
+                            last: last.clone(),
+                        };
+                    }
+                }
+            }
+            for (key, value) in keys.iter().zip(values) {
+                if !in_range(key) {
+                    return ScanOutcome::Finished { visited };
+                }
+                visited += 1;
+                last = Some(key);
+                if let ControlFlow::Break(value) = f(key, value) {
+                    return ScanOutcome::Stopped {
+                        visited,
+                        last: key.clone(),
+                        value,
+                    };
+                }
+            }
+            leaf_id = leaf.next;
+            start = 0;
+        }
+        ScanOutcome::Finished { visited }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+
+    #[test]
+    fn test_break_stops_on_the_entry_and_resumes_after_it() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..100u32 {
+            tree.insert(i, i * 2);
+        }
+        let outcome = tree.scan_until(10..50, None, |key, _| {
+            if *key == 20 {
+                ControlFlow::Break("found")
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        assert_eq!(
+            outcome,
+            ScanOutcome::Stopped {
+                visited: 11,
+                last: 20,
+                value: "found"
+            }
+        );
+
+        let resume = outcome.resume_from().unwrap();
+        let mut rest = Vec::new();
+        let outcome = tree.scan_until((resume, Bound::Excluded(50)), None, |key, _| {
+            rest.push(*key);
+            ControlFlow::<()>::Continue(())
+        });
+        assert_eq!(outcome, ScanOutcome::Finished { visited: 29 });
+        assert_eq!(rest, (21..50).collect::<Vec<_>>());
+        assert_eq!(outcome.resume_from(), None);
+    }
+
+    #[test]
+    fn test_expired_deadline_yields_once_per_leaf_and_covers_range() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..500u32 {
+            tree.insert(i, i * 2);
+        }
+        let expected: Vec<_> = tree.range(37..=411).map(|(k, v)| (*k, *v)).collect();
+
+        let mut seen = Vec::new();
+        let mut start = Bound::Included(37);
+        let mut calls = 0;
+        loop {
+            calls += 1;
+            let outcome = tree.scan_until(
+                (start, Bound::Included(411)),
+                Some(Instant::now()),
+                |k, v| {
+                    seen.push((*k, *v));
+                    ControlFlow::<()>::Continue(())
+                },
+            );
+            // Each call gets through at least one entry and at most one leaf
+            assert!(outcome.visited() >= 1 && outcome.visited() <= 4);
+            match outcome.resume_from() {
+                Some(resume) => start = resume,
+                None => break,
+            }
+        }
+        assert_eq!(seen, expected);
+        assert!(calls > expected.len() / 4);
+    }
+
+    #[test]
+    fn test_empty_and_out_of_range_scans_finish() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..50u32 {
+            tree.insert(i, i * 2);
+        }
+        let mut calls = 0;
+        let mut count = |_: &u32, _: &u32| {
+            calls += 1;
+            ControlFlow::<()>::Continue(())
+        };
+        assert!(tree.scan_until(60.., None, &mut count).is_finished());
+        assert!(tree.scan_until(20..20, None, &mut count).is_finished());
+        assert_eq!(calls, 0);
+    }
+
+    #[test]
+    fn test_weak_scan_visits_surviving_keys_once_while_the_tree_changes() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..2_000u32 {
+            tree.insert(i, i * 2);
+        }
+        let mut rng = SeededRng::new(77);
+        let mut seen = Vec::new();
+        let mut scan = tree.scan_weak(100..1_900);
+        while let Some((key, _)) = scan.advance(&tree) {
+            seen.push(key);
+            for _ in 0..3 {
+                // Churn only odd keys; even keys stay for the whole scan
+                let other = rng.below(2_500) as u32 | 1;
+                if rng.below(2) == 0 {
+                    tree.remove(&other);
+                } else {
+                    tree.insert(other, 0);
+                }
+            }
+        }
+
+        assert!(seen.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(seen.iter().all(|key| (100..1_900).contains(key)));
+        let evens: Vec<u32> = seen.iter().copied().filter(|key| key % 2 == 0).collect();
+        assert_eq!(evens, (100..1_900).step_by(2).collect::<Vec<_>>());
+        tree.check_invariants_detailed().unwrap();
+
+        let mut empty = tree.scan_weak(5_000..);
+        assert_eq!(empty.advance(&tree), None);
+        assert_eq!(empty.advance(&tree), None);
+    }
+}
@@ -0,0 +1,276 @@
+//! Continuous differential checking against `std::collections::BTreeMap`.
+//!
+//! [`model_test`](crate::model_test) replays a generated operation stream;
+//! [`ShadowMap`] does the same checking on whatever the application itself
+//! does. It mirrors every mutation into a `BTreeMap` oracle, compares each
+//! result as it is returned, and every N operations compares the full
+//! contents and the tree's invariants, panicking at the first divergence.
+//! That makes it suitable for integration and staging environments where
+//! real workloads can find bugs that generated ones miss.
+//!
+//! The oracle doubles memory use and a full comparison is O(n), so this is
+//! a debugging aid behind the `shadow` feature.
+
+use crate::batch_operations::{BatchOp, WriteBatch};
+use crate::bounds::{TreeKey, TreeValue};
+use crate::error::InitResult;
+use crate::iteration::RangeIterator;
+use crate::types::BPlusTreeMap;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::ops::{Bound, RangeBounds};
+
+/// A tree that checks itself against a `BTreeMap` after its mutations.
+///
+/// # Panics
+///
+/// Every mutating method panics if the tree's result differs from the
+/// oracle's, or, on a full check, if the contents differ or an invariant is
+/// broken. The message names the operation count and the first difference.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::ShadowMap;
+///
+/// // Compare everything after every 100th mutation
+/// let mut map = ShadowMap::with_check_interval(4, 100).unwrap();
+/// for i in 0..1_000 {
+///     map.insert(i, i * 2);
+/// }
+/// assert_eq!(map.remove_range(100..200), 100);
+/// assert_eq!(map.remove(&5), Some(10));
+/// map.verify().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ShadowMap<K, V> {
+    tree: BPlusTreeMap<K, V>,
+    oracle: BTreeMap<K, V>,
+    check_every: usize,
+    ops: u64,
+}
+
+impl<K, V> ShadowMap<K, V>
+where
+    K: TreeKey + Debug,
+    V: TreeValue + PartialEq + Debug,
+{
+    /// Create an empty map with node capacity `capacity` that runs a full
+    /// check after every mutation.
+    pub fn new(capacity: usize) -> InitResult<Self> {
+        Self::with_check_interval(capacity, 1)
+    }
+
+    /// Create an empty map that runs a full check after every `every`
+    /// mutations. Results are still compared on every call. An interval of
+    /// 0 disables the periodic check, leaving [`verify`](Self::verify).
+    pub fn with_check_interval(capacity: usize, every: usize) -> InitResult<Self> {
+        Ok(Self {
+            tree: BPlusTreeMap::new(capacity)?,
+            oracle: BTreeMap::new(),
+            check_every: every,
+            ops: 0,
+        })
+    }
+
+    /// The underlying tree, for reads.
+    pub fn tree(&self) -> &BPlusTreeMap<K, V> {
+        &self.tree
+    }
+
+    /// Stop checking and return the underlying tree.
+    pub fn into_inner(self) -> BPlusTreeMap<K, V> {
+        self.tree
+    }
+
+    /// Number of mutations applied so far.
+    pub fn op_count(&self) -> u64 {
+        self.ops
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Returns true if the map holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Get the value stored under `key`, checked against the oracle.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let found = self.tree.get(key);
+        self.expect_same("get", found, self.oracle.get(key));
+        found
+    }
+
+    /// Iterate over the entries in `range`.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> RangeIterator<'_, K, V> {
+        self.tree.range(range)
+    }
+
+    /// Insert `value` under `key` and return the previous value if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let expected = self.oracle.insert(key.clone(), value.clone());
+        let previous = self.tree.insert(key, value);
+        self.expect_same("insert", &previous, &expected);
+        self.after_mutation("insert");
+        previous
+    }
+
+    /// Remove `key` and return its value if it was present.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let expected = self.oracle.remove(key);
+        let removed = self.tree.remove(key);
+        self.expect_same("remove", &removed, &expected);
+        self.after_mutation("remove");
+        removed
+    }
+
+    /// Remove the entries in `range` and return how many there were.
+    pub fn remove_range<R: RangeBounds<K>>(&mut self, range: R) -> usize {
+        let bounds: (Bound<K>, Bound<K>) =
+            (range.start_bound().cloned(), range.end_bound().cloned());
+        let doomed: Vec<K> = self
+            .oracle
+            .range(bounds.clone())
+            .map(|(k, _)| k.clone())
+            .collect();
+        for key in &doomed {
+            self.oracle.remove(key);
+        }
+        let removed = self.tree.remove_range(bounds);
+        self.expect_same("remove_range", removed, doomed.len());
+        self.after_mutation("remove_range");
+        removed
+    }
+
+    /// Apply a write batch, returning one result per operation as
+    /// [`BPlusTreeMap::apply_batch`] does.
+    pub fn apply_batch(&mut self, batch: WriteBatch<K, V>) -> Vec<Option<V>> {
+        let expected: Vec<Option<V>> = batch
+            .ops()
+            .iter()
+            .map(|op| match op {
+                BatchOp::Insert(key, value) => self.oracle.insert(key.clone(), value.clone()),
+                BatchOp::Remove(key) => self.oracle.remove(key),
+            })
+            .collect();
+        let results = self.tree.apply_batch(batch);
+        self.expect_same("apply_batch", &results, &expected);
+        self.after_mutation("apply_batch");
+        results
+    }
+
+    /// Remove every entry.
+    pub fn clear(&mut self) {
+        self.oracle.clear();
+        self.tree.clear();
+        self.after_mutation("clear");
+    }
+
+    /// Compare the full contents with the oracle and check the tree's
+    /// invariants, describing the first problem found.
+    pub fn verify(&self) -> Result<(), String> {
+        self.tree.check_invariants_detailed()?;
+        if self.tree.len() != self.oracle.len() {
+            return Err(format!(
+                "tree has {} entries, oracle has {}",
+                self.tree.len(),
+                self.oracle.len()
+            ));
+        }
+        let mut oracle = self.oracle.iter();
+        for (position, entry) in self.tree.items().enumerate() {
+            let expected = oracle.next();
+            if Some(entry) != expected {
+                return Err(format!(
+                    "entry {} is {:?}, oracle has {:?}",
+                    position, entry, expected
+                ));
+            }
+        }
+        match oracle.next() {
+            Some(extra) => Err(format!(
+                "tree iteration ended before oracle entry {:?}",
+                extra
+            )),
+            None => Ok(()),
+        }
+    }
+
+    // Failing loudly on divergence is what a shadow map is for
+    #[allow(clippy::panic)]
+    fn after_mutation(&mut self, op: &str) {
+        self.ops += 1;
+        if self.check_every == 0 || !self.ops.is_multiple_of(self.check_every as u64) {
+            return;
+        }
+        if let Err(problem) = self.verify() {
+            panic!(
+                "shadow check failed after mutation {} ({}): {}",
+                self.ops, op, problem
+            );
+        }
+    }
+
+    fn expect_same<T: PartialEq + Debug>(&self, op: &str, actual: T, expected: T) {
+        assert!(
+            actual == expected,
+            "shadow check failed at mutation {} ({}): tree returned {:?}, oracle {:?}",
+            self.ops + 1,
+            op,
+            actual,
+            expected
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+
+    #[test]
+    fn test_shadow_map_follows_random_workload() {
+        let mut map = ShadowMap::with_check_interval(4, 7).unwrap();
+        let mut rng = SeededRng::new(11);
+        for i in 0..3_000u32 {
+            let key = rng.below(500) as u32;
+            match i % 10 {
+                0..=4 => {
+                    map.insert(key, i);
+                }
+                5..=7 => {
+                    map.remove(&key);
+                }
+                8 => {
+                    map.get(&key);
+                }
+                _ if i % 500 == 9 => {
+                    map.remove_range(key..key + 50);
+                }
+                _ => {
+                    let mut batch = WriteBatch::new();
+                    batch.insert(key, i).remove(key + 1).insert(key + 2, i);
+                    map.apply_batch(batch);
+                }
+            }
+        }
+        map.verify().unwrap();
+        assert!(map.op_count() > 2_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "shadow check failed")]
+    fn test_divergence_panics() {
+        let mut map = ShadowMap::new(4).unwrap();
+        for i in 0..20 {
+            map.insert(i, i);
+        }
+        // Change the tree behind the oracle's back
+        map.tree.insert(5, 500);
+        map.insert(21, 21);
+    }
+}
@@ -0,0 +1,296 @@
+//! Soak tests for arena exhaustion and fragmentation.
+//!
+//! Enabled with the `testing` feature. Each [`AttackPattern`] is a targeted
+//! insert/delete sequence that stresses one part of the tree: splits, merges,
+//! free-list growth or long borrow chains. Every cycle of a pattern inserts a
+//! batch of keys and removes them again, so a tree that reuses its freed nodes
+//! should stop growing once the first few cycles have settled its layout.
+//! [`run`] repeats a pattern, checks the invariants after every cycle and fails
+//! if the arenas grow beyond a [`SoakBudget`].
+//!
+//! ```
+//! use bplustree::soak::{self, AttackPattern, SoakBudget};
+//!
+//! let report = soak::run(4, AttackPattern::Fragmentation, 20, SoakBudget::default()).unwrap();
+//! assert_eq!(report.cycles, 20);
+//! assert!(report.slot_growth() <= SoakBudget::default().max_slot_growth);
+//! ```
+
+use crate::types::BPlusTreeMap;
+use std::fmt;
+
+/// Number of long-lived keys kept in the tree underneath every attack.
+const RESIDENT_KEYS: i32 = 64;
+
+/// Cycles allowed to grow the arenas before the budget applies. The first
+/// cycles can leave the resident keys spread over a few more nodes than they
+/// started in, which raises the peak of later cycles once.
+const WARMUP_CYCLES: usize = 2;
+
+/// A targeted insert/delete sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttackPattern {
+    /// Build a large subtree and delete all of it in scattered order,
+    /// flooding the free lists.
+    ArenaExhaustion,
+    /// Insert widely spaced keys, delete every other one, refill the gaps and
+    /// then clear, leaving holes scattered through the arenas.
+    Fragmentation,
+    /// Grow in ascending order and shrink in descending order, splitting and
+    /// then merging along the right edge.
+    Sawtooth,
+    /// Repeatedly remove the smallest key of the attack range so that each
+    /// removal borrows from or merges with a right sibling.
+    BorrowChain,
+    /// Interleave inserts and removals around one hot spot, splitting and
+    /// merging the same few nodes over and over.
+    Churn,
+}
+
+impl AttackPattern {
+    /// Every pattern, for sweeping all of them.
+    pub const ALL: [AttackPattern; 5] = [
+        AttackPattern::ArenaExhaustion,
+        AttackPattern::Fragmentation,
+        AttackPattern::Sawtooth,
+        AttackPattern::BorrowChain,
+        AttackPattern::Churn,
+    ];
+
+    /// Run one cycle. Every key inserted is removed again before returning.
+    fn apply(self, tree: &mut BPlusTreeMap<i32, i32>, cycle: usize) {
+        // Attack keys sit above the resident keys
+        let base = RESIDENT_KEYS * 10;
+        let tag = cycle as i32;
+        match self {
+            AttackPattern::ArenaExhaustion => {
+                for i in 0..1_000 {
+                    tree.insert(base + i, tag);
+                }
+                // 7 is coprime to 1000, so this visits every key once
+                for i in 0..1_000 {
+                    tree.remove(&(base + (i * 7) % 1_000));
+                }
+            }
+            AttackPattern::Fragmentation => {
+                for i in 0..500 {
+                    tree.insert(base + i * 10, tag);
+                }
+                for i in (0..500).step_by(2) {
+                    tree.remove(&(base + i * 10));
+                }
+                for i in 0..250 {
+                    tree.insert(base + i * 10 + 5, tag);
+                }
+                for i in 0..500 {
+                    tree.remove(&(base + i * 10));
+                    tree.remove(&(base + i * 10 + 5));
+                }
+            }
+            AttackPattern::Sawtooth => {
+                for i in 0..600 {
+                    tree.insert(base + i, tag);
+                }
+                for i in (0..600).rev() {
+                    tree.remove(&(base + i));
+                }
+            }
+            AttackPattern::BorrowChain => {
+                for i in 0..600 {
+                    tree.insert(base + i, tag);
+                }
+                for i in 0..600 {
+                    tree.remove(&(base + i));
+                }
+            }
+            AttackPattern::Churn => {
+                for i in 0..400 {
+                    tree.insert(base + i, tag);
+                    if i % 3 == 2 {
+                        tree.remove(&(base + i - 1));
+                    }
+                }
+                for i in 0..400 {
+                    tree.remove(&(base + i));
+                }
+            }
+        }
+    }
+}
+
+/// Limits a soak run must stay within.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SoakBudget {
+    /// How many arena slots (leaf and branch) may be created after the warmup
+    /// cycles. Freed slots should be reused, so steady state needs none.
+    pub max_slot_growth: usize,
+    /// Largest share of arena slots allowed to sit on the free lists at the
+    /// end of the run.
+    pub max_free_fraction: f64,
+}
+
+impl Default for SoakBudget {
+    fn default() -> Self {
+        Self {
+            max_slot_growth: 0,
+            max_free_fraction: 1.0,
+        }
+    }
+}
+
+/// Measurements from a soak run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoakReport {
+    /// Number of completed cycles.
+    pub cycles: usize,
+    /// Arena slots in use or free after the warmup cycles.
+    pub baseline_slots: usize,
+    /// Arena slots in use or free after the last completed cycle.
+    pub final_slots: usize,
+    /// Most leaves on the free list after any cycle.
+    pub peak_free_leaves: usize,
+    /// Most branches on the free list after any cycle.
+    pub peak_free_branches: usize,
+    /// Share of arena slots on the free lists after the last cycle.
+    pub free_fraction: f64,
+}
+
+impl SoakReport {
+    /// Slots created after the warmup cycles.
+    pub fn slot_growth(&self) -> usize {
+        self.final_slots.saturating_sub(self.baseline_slots)
+    }
+}
+
+/// Why a soak run failed, with the measurements up to that point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoakFailure {
+    /// Cycle during which the failure was detected.
+    pub cycle: usize,
+    pub reason: String,
+    pub report: SoakReport,
+}
+
+impl fmt::Display for SoakFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "soak failed at cycle {}: {}", self.cycle, self.reason)
+    }
+}
+
+impl std::error::Error for SoakFailure {}
+
+/// Run `cycles` cycles of `pattern` against a tree of the given capacity
+/// holding a fixed set of resident keys, checking the invariants, the resident
+/// keys and the budget after every cycle.
+pub fn run(
+    capacity: usize,
+    pattern: AttackPattern,
+    cycles: usize,
+    budget: SoakBudget,
+) -> Result<SoakReport, SoakFailure> {
+    let mut report = SoakReport {
+        cycles: 0,
+        baseline_slots: 0,
+        final_slots: 0,
+        peak_free_leaves: 0,
+        peak_free_branches: 0,
+        free_fraction: 0.0,
+    };
+    let fail = |cycle: usize, reason: String, report: &SoakReport| SoakFailure {
+        cycle,
+        reason,
+        report: report.clone(),
+    };
+
+    let mut tree = BPlusTreeMap::new(capacity)
+        .map_err(|e| fail(0, format!("cannot create tree: {}", e), &report))?;
+    for key in 0..RESIDENT_KEYS {
+        tree.insert(key * 10, key);
+    }
+
+    for cycle in 0..cycles {
+        pattern.apply(&mut tree, cycle);
+
+        if let Err(e) = tree.check_invariants_detailed() {
+            return Err(fail(cycle, format!("invariant violated: {}", e), &report));
+        }
+        if tree.len() != RESIDENT_KEYS as usize
+            || !(0..RESIDENT_KEYS).all(|key| tree.get(&(key * 10)) == Some(&key))
+        {
+            return Err(fail(cycle, "resident keys changed".to_string(), &report));
+        }
+
+        let slots = arena_slots(&tree);
+        if cycle < WARMUP_CYCLES {
+            report.baseline_slots = slots;
+        }
+        report.cycles = cycle + 1;
+        report.final_slots = slots;
+        report.peak_free_leaves = report.peak_free_leaves.max(tree.free_leaf_count());
+        report.peak_free_branches = report.peak_free_branches.max(tree.free_branch_count());
+        report.free_fraction = if slots == 0 {
+            0.0
+        } else {
+            (tree.free_leaf_count() + tree.free_branch_count()) as f64 / slots as f64
+        };
+
+        if report.slot_growth() > budget.max_slot_growth {
+            return Err(fail(
+                cycle,
+                format!(
+                    "arenas grew by {} slots, budget is {}",
+                    report.slot_growth(),
+                    budget.max_slot_growth
+                ),
+                &report,
+            ));
+        }
+    }
+
+    if report.free_fraction > budget.max_free_fraction {
+        return Err(fail(
+            cycles.saturating_sub(1),
+            format!(
+                "{:.2} of arena slots are free, budget is {:.2}",
+                report.free_fraction, budget.max_free_fraction
+            ),
+            &report,
+        ));
+    }
+    Ok(report)
+}
+
+/// Leaf and branch slots ever created and not compacted away.
+fn arena_slots(tree: &BPlusTreeMap<i32, i32>) -> usize {
+    let leaves = tree.leaf_arena_stats();
+    let branches = tree.branch_arena_stats();
+    leaves.allocated_count + leaves.free_count + branches.allocated_count + branches.free_count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_pattern_reuses_freed_nodes() {
+        for capacity in [4, 5, 8, 16] {
+            for pattern in AttackPattern::ALL {
+                if let Err(failure) = run(capacity, pattern, 30, SoakBudget::default()) {
+                    panic!("{:?} at capacity {}: {}", pattern, capacity, failure);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_free_fraction_budget_is_enforced() {
+        let budget = SoakBudget {
+            max_slot_growth: 0,
+            max_free_fraction: 0.0,
+        };
+        let failure = run(4, AttackPattern::ArenaExhaustion, 3, budget).unwrap_err();
+        assert!(failure.reason.contains("free"));
+        assert_eq!(failure.report.cycles, 3);
+        assert!(failure.report.peak_free_leaves > 0);
+    }
+}
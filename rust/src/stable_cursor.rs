@@ -0,0 +1,302 @@
+//! Cursors that stay valid while the tree changes.
+//!
+//! Iterators borrow the tree, so a long-lived reader cannot hold one while a
+//! writer inserts or removes entries. A [`StableCursor`] instead remembers the
+//! key it is positioned on and borrows the tree only for the duration of each
+//! call. Splits, merges and removals between calls cannot invalidate it: the
+//! next move simply continues from the anchor key, wherever that key's entry,
+//! or the place it used to be, now lives.
+
+use crate::types::{BPlusTreeMap, NodeId, NULL_NODE};
+use std::ops::Bound;
+
+/// Where a cursor is anchored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Anchor<K> {
+    /// Before the first entry.
+    Start,
+    /// On an entry with this key, which may since have been removed.
+    At(K),
+    /// After the last entry.
+    End,
+}
+
+/// A bidirectional cursor anchored to a key rather than a position.
+///
+/// The cursor caches the leaf it last read from and reuses it when the leaf
+/// still holds the anchor key at the same index, so stepping through an
+/// unchanged part of the tree does not descend from the root. After
+/// mutations the cache fails validation and the cursor re-seeks by key.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::{BPlusTreeMap, StableCursor};
+///
+/// let mut log = BPlusTreeMap::new(4).unwrap();
+/// for seq in 0..5 {
+///     log.insert(seq, format!("event {}", seq));
+/// }
+///
+/// let mut reader = StableCursor::new();
+/// while let Some((seq, _)) = reader.advance(&log) {
+///     if *seq == 2 {
+///         break;
+///     }
+/// }
+///
+/// // Writers may change the tree while the reader is parked
+/// log.remove(&2);
+/// for seq in 5..50 {
+///     log.insert(seq, format!("event {}", seq));
+/// }
+///
+/// assert_eq!(reader.advance(&log).map(|(seq, _)| *seq), Some(3));
+/// assert_eq!(reader.retreat(&log).map(|(seq, _)| *seq), Some(1));
+/// ```
+#[derive(Debug, Clone)]
+pub struct StableCursor<K> {
+    anchor: Anchor<K>,
+    /// Leaf and index where the anchor key was last seen.
+    hint: Option<(NodeId, usize)>,
+}
+
+impl<K> Default for StableCursor<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> StableCursor<K> {
+    /// Create a cursor positioned before the first entry.
+    pub fn new() -> Self {
+        Self {
+            anchor: Anchor::Start,
+            hint: None,
+        }
+    }
+
+    /// Create a cursor positioned after the last entry, for reading
+    /// backwards with [`retreat`](Self::retreat).
+    pub fn at_end() -> Self {
+        Self {
+            anchor: Anchor::End,
+            hint: None,
+        }
+    }
+
+    /// Create a cursor anchored at `key`, whether or not the key is present.
+    /// The next [`advance`](Self::advance) returns the first entry after it.
+    pub fn at(key: K) -> Self {
+        Self {
+            anchor: Anchor::At(key),
+            hint: None,
+        }
+    }
+
+    /// The key the cursor is anchored at, or `None` before the first or
+    /// after the last entry.
+    pub fn key(&self) -> Option<&K> {
+        match &self.anchor {
+            Anchor::At(key) => Some(key),
+            Anchor::Start | Anchor::End => None,
+        }
+    }
+}
+
+impl<K: Ord + Clone> StableCursor<K> {
+    /// The entry at the anchor key, if it is still in the tree.
+    pub fn current<'a, V: Clone>(&self, tree: &'a BPlusTreeMap<K, V>) -> Option<(&'a K, &'a V)> {
+        let key = self.key()?;
+        if let Some(entry) = self.hinted_entry(tree, key) {
+            return Some(entry);
+        }
+        let (leaf_id, index) = tree.find_leaf_for_key(key)?;
+        let leaf = tree.get_leaf(leaf_id)?;
+        match leaf.keys.get(index) {
+            Some(found) if found == key => Some((found, &leaf.values[index])),
+            _ => None,
+        }
+    }
+
+    /// Move to the next entry after the anchor and return it. At the end the
+    /// cursor moves past the last entry and returns `None`.
+    pub fn advance<'a, V: Clone>(
+        &mut self,
+        tree: &'a BPlusTreeMap<K, V>,
+    ) -> Option<(&'a K, &'a V)> {
+        let start = match &self.anchor {
+            Anchor::Start => tree.range_start_position(Bound::Unbounded),
+            Anchor::At(key) => match self.hint {
+                Some((leaf_id, index)) if self.hinted_entry(tree, key).is_some() => {
+                    Some((leaf_id, index + 1))
+                }
+                _ => tree.range_start_position(Bound::Excluded(key)),
+            },
+            Anchor::End => return None,
+        };
+
+        match start.and_then(|position| first_entry_from(tree, position)) {
+            Some((leaf_id, index)) => {
+                let leaf = tree.get_leaf(leaf_id)?;
+                let (key, value) = (&leaf.keys[index], &leaf.values[index]);
+                self.anchor = Anchor::At(key.clone());
+                self.hint = Some((leaf_id, index));
+                Some((key, value))
+            }
+            None => {
+                self.anchor = Anchor::End;
+                self.hint = None;
+                None
+            }
+        }
+    }
+
+    /// Move to the entry before the anchor and return it. At the start the
+    /// cursor moves before the first entry and returns `None`.
+    pub fn retreat<'a, V: Clone>(
+        &mut self,
+        tree: &'a BPlusTreeMap<K, V>,
+    ) -> Option<(&'a K, &'a V)> {
+        // Leaves only link forwards, so stepping back within the cached leaf
+        // is the only move that avoids a descent
+        let previous = match &self.anchor {
+            Anchor::Start => return None,
+            Anchor::At(key) => match self.hint {
+                Some((leaf_id, index)) if index > 0 && self.hinted_entry(tree, key).is_some() => {
+                    let leaf = tree.get_leaf(leaf_id)?;
+                    self.hint = Some((leaf_id, index - 1));
+                    Some((&leaf.keys[index - 1], &leaf.values[index - 1]))
+                }
+                _ => {
+                    self.hint = None;
+                    tree.last_entry_before(Bound::Excluded(key))
+                }
+            },
+            Anchor::End => {
+                self.hint = None;
+                tree.last_entry_before(Bound::Unbounded)
+            }
+        };
+
+        match previous {
+            Some((key, value)) => {
+                self.anchor = Anchor::At(key.clone());
+                Some((key, value))
+            }
+            None => {
+                self.anchor = Anchor::Start;
+                self.hint = None;
+                None
+            }
+        }
+    }
+
+    /// The entry at the cached position, if it still holds `key`.
+    fn hinted_entry<'a, V: Clone>(
+        &self,
+        tree: &'a BPlusTreeMap<K, V>,
+        key: &K,
+    ) -> Option<(&'a K, &'a V)> {
+        let (leaf_id, index) = self.hint?;
+        let leaf = tree.get_leaf(leaf_id)?;
+        match leaf.keys.get(index) {
+            Some(found) if found == key => Some((found, &leaf.values[index])),
+            _ => None,
+        }
+    }
+}
+
+/// Position of the first entry at or after `(leaf_id, index)`, following the
+/// leaf chain past exhausted and empty leaves.
+fn first_entry_from<K: Ord + Clone, V: Clone>(
+    tree: &BPlusTreeMap<K, V>,
+    (mut leaf_id, mut index): (NodeId, usize),
+) -> Option<(NodeId, usize)> {
+    loop {
+        let leaf = tree.get_leaf(leaf_id)?;
+        if index < leaf.keys.len() {
+            return Some((leaf_id, index));
+        }
+        if leaf.next == NULL_NODE {
+            return None;
+        }
+        leaf_id = leaf.next;
+        index = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use crate::DeletionMode;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_cursor_walks_both_ways() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..30 {
+            tree.insert(i, i * 10);
+        }
+
+        let mut cursor = StableCursor::new();
+        let forward: Vec<i32> =
+            std::iter::from_fn(|| cursor.advance(&tree).map(|(k, _)| *k)).collect();
+        assert_eq!(forward, (0..30).collect::<Vec<_>>());
+        assert_eq!(cursor.key(), None);
+        assert_eq!(cursor.advance(&tree), None);
+
+        let backward: Vec<i32> =
+            std::iter::from_fn(|| cursor.retreat(&tree).map(|(k, _)| *k)).collect();
+        assert_eq!(backward, (0..30).rev().collect::<Vec<_>>());
+        assert_eq!(cursor.retreat(&tree), None);
+        assert_eq!(cursor.advance(&tree), Some((&0, &0)));
+
+        let mut cursor = StableCursor::at(14);
+        assert_eq!(cursor.current(&tree), Some((&14, &140)));
+        assert_eq!(cursor.advance(&tree), Some((&15, &150)));
+        assert_eq!(StableCursor::at_end().retreat(&tree), Some((&29, &290)));
+    }
+
+    #[test]
+    fn test_cursor_survives_interleaved_mutation() {
+        for mode in [DeletionMode::Eager, DeletionMode::DeferredRebalance] {
+            let mut tree = BPlusTreeMap::new(4).unwrap();
+            tree.set_deletion_mode(mode);
+            let mut reference = BTreeMap::new();
+            let mut cursor = StableCursor::new();
+            let mut rng = SeededRng::new(21);
+            let mut next = |bound: u64| rng.below(bound) as i32;
+
+            for i in 0..4_000 {
+                let key = next(300);
+                if next(3) == 0 {
+                    tree.remove(&key);
+                    reference.remove(&key);
+                } else {
+                    tree.insert(key, i);
+                    reference.insert(key, i);
+                }
+
+                // The cursor's next move must match a fresh lookup from its anchor
+                let expected = if next(2) == 0 {
+                    let expected = match cursor.key() {
+                        Some(anchor) => reference.range(anchor + 1..).next(),
+                        None if cursor.anchor == Anchor::Start => reference.iter().next(),
+                        None => None,
+                    };
+                    (cursor.advance(&tree), expected)
+                } else {
+                    let expected = match cursor.key() {
+                        Some(anchor) => reference.range(..*anchor).next_back(),
+                        None if cursor.anchor == Anchor::End => reference.iter().next_back(),
+                        None => None,
+                    };
+                    (cursor.retreat(&tree), expected)
+                };
+                assert_eq!(expected.0, expected.1, "{:?} step {}", mode, i);
+            }
+        }
+    }
+}
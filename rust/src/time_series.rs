@@ -0,0 +1,259 @@
+//! A tree keyed by `u64` timestamps.
+//!
+//! [`TimeSeriesTree`] wraps a [`BPlusTreeMap<u64, V>`] for the most common
+//! workload: samples arrive mostly in time order, queries ask for a time
+//! window or the newest samples, and old samples are dropped from the front.
+//! The wrapper remembers the rightmost leaf, so appending a sample newer than
+//! all others pushes it onto that leaf without searching from the root until
+//! the leaf fills and has to split.
+
+use crate::bounds::TreeValue;
+use crate::error::InitResult;
+use crate::iteration::{ItemIterator, RangeIterator};
+use crate::types::{BPlusTreeMap, NodeId, NodeRef};
+use std::iter::{FusedIterator, Rev, Take};
+use std::num::NonZeroU64;
+use std::ops::{Bound, RangeBounds};
+
+/// A map from `u64` timestamps to samples.
+///
+/// Timestamps are plain integers in whatever unit the caller chooses.
+/// Reads that the wrapper does not offer go through [`tree`](Self::tree);
+/// writes go through the wrapper so that its record of the rightmost leaf
+/// stays current.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::TimeSeriesTree;
+/// use std::num::NonZeroU64;
+///
+/// let mut series = TimeSeriesTree::new(16).unwrap();
+/// for second in 0..600 {
+///     series.append(second, second as f64 * 0.5);
+/// }
+///
+/// assert_eq!(series.between(10, 13).count(), 3);
+/// let newest: Vec<u64> = series.latest(2).map(|(ts, _)| *ts).collect();
+/// assert_eq!(newest, vec![599, 598]);
+///
+/// // The first sample in each minute
+/// let minute = NonZeroU64::new(60).unwrap();
+/// let per_minute: Vec<(u64, f64)> = series.downsample(.., minute).map(|(ts, v)| (ts, *v)).collect();
+/// assert_eq!(per_minute.len(), 10);
+/// assert_eq!(per_minute[1], (60, 30.0));
+///
+/// assert_eq!(series.truncate_before(300), 300);
+/// assert_eq!(series.first(), Some((&300, &150.0)));
+/// ```
+pub struct TimeSeriesTree<V> {
+    tree: BPlusTreeMap<u64, V>,
+    /// The rightmost leaf, or `None` if it has to be found again.
+    tail: Option<NodeId>,
+}
+
+impl<V: TreeValue> TimeSeriesTree<V> {
+    /// Create an empty series backed by a tree with node capacity `capacity`.
+    pub fn new(capacity: usize) -> InitResult<Self> {
+        Ok(Self::from_tree(BPlusTreeMap::new(capacity)?))
+    }
+
+    /// Wrap an existing tree.
+    pub fn from_tree(tree: BPlusTreeMap<u64, V>) -> Self {
+        Self { tree, tail: None }
+    }
+
+    /// The underlying tree, for reads.
+    pub fn tree(&self) -> &BPlusTreeMap<u64, V> {
+        &self.tree
+    }
+
+    /// Return the underlying tree.
+    pub fn into_inner(self) -> BPlusTreeMap<u64, V> {
+        self.tree
+    }
+
+    /// Number of samples.
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Returns true if the series holds no samples.
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Get the sample at `ts`.
+    pub fn get(&self, ts: u64) -> Option<&V> {
+        self.tree.get(&ts)
+    }
+
+    /// The oldest sample.
+    pub fn first(&self) -> Option<(&u64, &V)> {
+        self.tree.first()
+    }
+
+    /// The newest sample.
+    pub fn last(&self) -> Option<(&u64, &V)> {
+        self.tree.last()
+    }
+
+    /// Record `value` at `ts`, returning the sample it replaces if `ts` was
+    /// already present.
+    ///
+    /// A `ts` newer than every sample in the series goes straight onto the
+    /// rightmost leaf while that leaf has room. Anything else, including a
+    /// late sample, is an ordinary insert.
+    pub fn append(&mut self, ts: u64, value: V) -> Option<V> {
+        if self.tree.byte_budget.is_none() && self.tree.leaf_boundary_hook.is_none() {
+            if let Some(leaf) = self.tail.and_then(|id| self.tree.get_leaf_mut(id)) {
+                let newest = leaf.keys.last().is_none_or(|last| *last < ts);
+                if newest && !leaf.is_full() {
+                    leaf.keys.push(ts);
+                    leaf.values.push(value);
+                    return None;
+                }
+            }
+        }
+        let previous = self.tree.insert(ts, value);
+        self.tail = self.rightmost_leaf();
+        previous
+    }
+
+    /// Iterate over the samples from `start` up to but not including `end`,
+    /// oldest first.
+    pub fn between(&self, start: u64, end: u64) -> RangeIterator<'_, u64, V> {
+        self.tree.range(start..end)
+    }
+
+    /// Iterate over the newest `n` samples, newest first.
+    pub fn latest(&self, n: usize) -> Take<Rev<ItemIterator<'_, u64, V>>> {
+        self.tree.items().rev().take(n)
+    }
+
+    /// Remove every sample older than `ts` and return how many were removed.
+    pub fn truncate_before(&mut self, ts: u64) -> usize {
+        let removed = self.tree.remove_range(..ts);
+        self.tail = None;
+        removed
+    }
+
+    /// Iterate over the samples in `range` bucketed by time, yielding the
+    /// start of each bucket that holds a sample together with the first
+    /// sample in it.
+    ///
+    /// Buckets are aligned to multiples of `width`, and empty buckets are
+    /// skipped. Each bucket costs one search from the root, so sparse
+    /// output over a long series reads only the samples it yields.
+    pub fn downsample<R: RangeBounds<u64>>(
+        &self,
+        range: R,
+        width: NonZeroU64,
+    ) -> Downsample<'_, V> {
+        Downsample {
+            tree: &self.tree,
+            next: Some(range.start_bound().cloned()),
+            end: range.end_bound().cloned(),
+            width: width.get(),
+        }
+    }
+
+    /// Find the rightmost leaf by following the last child of each branch.
+    fn rightmost_leaf(&self) -> Option<NodeId> {
+        let mut current = self.tree.root;
+        loop {
+            match current {
+                NodeRef::Leaf(id, _) => return Some(id),
+                NodeRef::Branch(id, _) => {
+                    let branch = self.tree.get_branch(id)?;
+                    current = branch.child(branch.child_count().checked_sub(1)?)?;
+                }
+            }
+        }
+    }
+}
+
+/// Iterator over the first sample in each time bucket, from
+/// [`TimeSeriesTree::downsample`].
+pub struct Downsample<'a, V> {
+    tree: &'a BPlusTreeMap<u64, V>,
+    /// Where the search for the next bucket starts, or `None` once done.
+    next: Option<Bound<u64>>,
+    end: Bound<u64>,
+    width: u64,
+}
+
+impl<'a, V: TreeValue> Iterator for Downsample<'a, V> {
+    type Item = (u64, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.next.take()?;
+        let (ts, value) = self.tree.range((start, self.end)).next()?;
+        let bucket = ts - ts % self.width;
+        self.next = bucket.checked_add(self.width).map(Bound::Included);
+        Some((bucket, value))
+    }
+}
+
+impl<V: TreeValue> FusedIterator for Downsample<'_, V> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_append_matches_btreemap() {
+        let mut rng = SeededRng::new(5);
+        let mut series = TimeSeriesTree::new(4).unwrap();
+        let mut expected = BTreeMap::new();
+        let mut ts = 0u64;
+        for i in 0..3_000u64 {
+            // Mostly in order, with some late and repeated timestamps
+            let at = match rng.below(10) {
+                0 => ts.saturating_sub(rng.below(50)),
+                1 => ts,
+                _ => {
+                    ts += 1 + rng.below(3);
+                    ts
+                }
+            };
+            assert_eq!(series.append(at, i), expected.insert(at, i));
+            if i % 500 == 0 {
+                series.truncate_before(ts / 4);
+                expected = expected.split_off(&(ts / 4));
+            }
+        }
+        assert!(series.tree().check_invariants());
+        assert!(series.tree().items().eq(expected.iter()));
+        assert!(series.latest(7).eq(expected.iter().rev().take(7)));
+        assert!(series
+            .between(ts / 2, ts / 2 + 40)
+            .eq(expected.range(ts / 2..ts / 2 + 40)));
+    }
+
+    #[test]
+    fn test_downsample_yields_first_sample_per_bucket() {
+        let mut series = TimeSeriesTree::new(4).unwrap();
+        for ts in [3, 4, 9, 10, 35, 36, 59, u64::MAX] {
+            series.append(ts, ts);
+        }
+        let width = NonZeroU64::new(10).unwrap();
+        let buckets: Vec<(u64, u64)> = series.downsample(.., width).map(|(b, v)| (b, *v)).collect();
+        let last_bucket = u64::MAX - u64::MAX % 10;
+        assert_eq!(
+            buckets,
+            vec![
+                (0, 3),
+                (10, 10),
+                (30, 35),
+                (50, 59),
+                (last_bucket, u64::MAX)
+            ]
+        );
+
+        let window: Vec<u64> = series.downsample(4..50, width).map(|(b, _)| b).collect();
+        assert_eq!(window, vec![0, 10, 30]);
+    }
+}
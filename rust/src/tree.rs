@@ -0,0 +1,162 @@
+//! The tree handle and its configuration.
+
+use crate::byte_budget::ByteBudget;
+use crate::compact_arena::CompactArena;
+use crate::leaf_boundary::LeafBoundaryHook;
+use crate::node::{BranchNode, LeafNode, NodeRef};
+
+/// B+ Tree implementation with Rust dict-like API.
+///
+/// A B+ tree is a self-balancing tree data structure that maintains sorted data
+/// and allows searches, sequential access, insertions, and deletions in O(log n).
+/// Unlike B trees, all values are stored in leaf nodes, making range queries
+/// and sequential access very efficient.
+///
+/// # Type Parameters
+///
+/// * `K` - Key type that must implement `Ord + Clone + Debug`
+/// * `V` - Value type that must implement `Clone + Debug`
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::BPlusTreeMap;
+///
+/// let mut tree = BPlusTreeMap::new(16).unwrap();
+/// tree.insert(1, "one");
+/// tree.insert(2, "two");
+/// tree.insert(3, "three");
+///
+/// assert_eq!(tree.get(&2), Some(&"two"));
+/// assert_eq!(tree.len(), 3);
+///
+/// // Range queries
+/// let range: Vec<_> = tree.range(1..3).collect();
+/// assert_eq!(range, [(&1, &"one"), (&2, &"two")]);
+/// ```
+///
+/// # Performance Characteristics
+///
+/// - **Insertion**: O(log n)
+/// - **Lookup**: O(log n)
+/// - **Deletion**: O(log n)
+/// - **Range queries**: O(log n + k) where k is the number of items in range
+/// - **Iteration**: O(n)
+///
+/// # Ordering
+///
+/// Every iterator yields entries in strictly ascending key order, as defined
+/// by `K`'s `Ord`. Ties cannot occur: a key that compares `Equal` to a stored
+/// one is the same key, so inserting it replaces the value and keeps the
+/// stored key, as `BTreeMap` does. No operation reorders entries. Splits,
+/// merges, batches and bulk removals move runs of entries between nodes
+/// without changing their relative order.
+///
+/// The order is only as sound as `Ord` itself, and a key whose ordering
+/// changes while it is stored breaks it. With the `order-checks` feature,
+/// debug builds compare a sample of keys each time an iterator crosses a leaf
+/// boundary and panic on a contradiction.
+///
+/// # Capacity Guidelines
+///
+/// - Minimum capacity: 4 (enforced)
+/// - Recommended capacity: 16-128 depending on use case; use
+///   [`tune_capacity`](Self::tune_capacity) to measure which suits a workload
+/// - Higher capacity = fewer tree levels but larger nodes
+/// - Lower capacity = more tree levels but smaller nodes
+#[derive(Debug)]
+pub struct BPlusTreeMap<K, V> {
+    /// Maximum number of keys per node.
+    pub(crate) capacity: usize,
+    /// The root node of the tree.
+    pub(crate) root: NodeRef<K, V>,
+    /// The root leaf while the tree has never split, addressed as
+    /// `INLINE_ROOT`. Small maps then need no arena storage at all.
+    pub(crate) inline_root: Option<LeafNode<K, V>>,
+
+    // Compact arena-based allocation for better performance
+    /// Compact arena storage for leaf nodes (eliminates Option wrapper overhead).
+    pub(crate) leaf_arena: CompactArena<LeafNode<K, V>>,
+    /// Compact arena storage for branch nodes (eliminates Option wrapper overhead).
+    pub(crate) branch_arena: CompactArena<BranchNode<K, V>>,
+
+    /// How underfull nodes pick a sibling to borrow from or merge with.
+    pub(crate) rebalance_strategy: RebalanceStrategy,
+    /// Whether `remove` rebalances immediately or defers it to `vacuum`.
+    pub(crate) deletion_mode: DeletionMode,
+    /// Whether a full leaf splits or is allowed to grow.
+    pub(crate) overflow_mode: OverflowMode,
+    /// Optional limit on the bytes a leaf's entries may weigh.
+    pub(crate) byte_budget: Option<ByteBudget<K, V>>,
+    /// Called when leaves split or merge.
+    pub(crate) leaf_boundary_hook: Option<LeafBoundaryHook<K>>,
+    /// Bumped whenever a leaf may change, so that item handles can tell
+    /// whether the slot they point at still holds their entry.
+    pub(crate) generation: u64,
+}
+
+/// Sibling selection strategy used when rebalancing an underfull node.
+///
+/// `PreferLeft` is the classic behavior: borrow from the left sibling when it
+/// can donate, and merge into the left sibling when nobody can. Under workloads
+/// that alternate between ends of a node this drains left siblings first and
+/// leads to more cascading merges. `PreferFuller` looks at both siblings and
+/// borrows from the one with more keys, merging with the one with fewer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RebalanceStrategy {
+    /// Always try the left sibling first, then the right one.
+    #[default]
+    PreferLeft,
+    /// Borrow from the fuller sibling and merge with the emptier one.
+    PreferFuller,
+}
+
+/// Controls when removals restore the minimum-occupancy invariant.
+///
+/// In `DeferredRebalance` mode `remove` takes the entry out of its leaf
+/// straight away, as it does in `Eager` mode, but skips the borrowing and
+/// merging that follows. There are no tombstones: lookups and iteration never
+/// see a removed entry. What is deferred is the structural repair, so leaves
+/// are left underfull or even empty and the tree no longer meets the
+/// minimum-occupancy invariant.
+///
+/// Calling [`BPlusTreeMap::vacuum`] is required to restore it. Nothing
+/// vacuums on its own except switching back to `Eager` mode, and until then
+/// the space held by sparse and empty nodes is not given back. This keeps
+/// deletes cheap in churn-heavy workloads at the cost of that memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeletionMode {
+    /// Rebalance after every removal.
+    #[default]
+    Eager,
+    /// Skip rebalancing on removal; `vacuum` must be called to compact.
+    DeferredRebalance,
+}
+
+/// Controls what an insert does when its leaf is full.
+///
+/// In `GrowLeaves` mode a leaf is allowed to hold up to `factor` times the
+/// node capacity before an insert splits it. There are no overflow pages: the
+/// leaf's own key and value vectors simply grow. A burst of writes
+/// concentrated on one hot key range then grows a few leaves instead of
+/// splitting them over and over and pushing new separators into the branches
+/// above, so the branch fanout stays stable.
+///
+/// The price is paid inside the grown leaf. An insert shifts every entry
+/// after its slot, so it costs up to `factor` times what it would in a leaf
+/// of normal size, and lookups search a longer leaf. `factor` is clamped to
+/// `1..=`[`MAX_LEAF_GROWTH`](crate::MAX_LEAF_GROWTH) so that a leaf can never
+/// grow without bound. [`BPlusTreeMap::vacuum`] splits grown leaves back to
+/// normal size once the burst is over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowMode {
+    /// Split a leaf as soon as it is full.
+    #[default]
+    Split,
+    /// Let a leaf grow to `factor` times the node capacity before it splits.
+    GrowLeaves {
+        /// How many times the node capacity a leaf may hold, from 1 up to
+        /// [`MAX_LEAF_GROWTH`](crate::MAX_LEAF_GROWTH).
+        factor: usize,
+    },
+}
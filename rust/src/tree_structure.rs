@@ -3,39 +3,115 @@
 //! This module contains all tree-level operations that manage the overall structure,
 //! including size queries, clearing, node counting, and tree statistics.
 
-use crate::types::{BPlusTreeMap, LeafNode, NodeId, NodeRef};
-use std::marker::PhantomData;
+use crate::bounds::{TreeKey, TreeValue};
+use crate::node::min_keys_for;
+use crate::types::{BPlusTreeMap, LeafNode, NodeId, NodeRef, MAX_HEIGHT, NULL_NODE};
 
 // ============================================================================
 // TREE STRUCTURE OPERATIONS
 // ============================================================================
 
-impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
     /// Returns the number of elements in the tree.
+    ///
+    /// Walks the tree from the root rather than following the leaf chain, so
+    /// that validation can compare the two.
     pub fn len(&self) -> usize {
-        self.len_recursive(&self.root)
+        // Depth-first, with each level's next child index on the path
+        let mut path = [(NULL_NODE, 0usize); MAX_HEIGHT];
+        let mut depth = 0;
+        let mut count = 0;
+        let mut next = Some(self.root);
+        while let Some(node) = next.take() {
+            match node {
+                NodeRef::Leaf(id, _) => count += self.get_leaf(id).map_or(0, |leaf| leaf.len()),
+                NodeRef::Branch(id, _) => {
+                    path[depth] = (id, 0);
+                    depth += 1;
+                }
+            }
+            // Climb out of finished branches to the next unvisited child
+            while depth > 0 && next.is_none() {
+                let (branch_id, child_index) = &mut path[depth - 1];
+                next = self
+                    .get_branch(*branch_id)
+                    .and_then(|branch| branch.child(*child_index));
+                match next {
+                    Some(_) => *child_index += 1,
+                    None => depth -= 1,
+                }
+            }
+        }
+        count
     }
 
-    /// Recursively count elements with proper arena access.
-    fn len_recursive(&self, node: &NodeRef<K, V>) -> usize {
-        match node {
-            NodeRef::Leaf(id, _) => self.get_leaf(*id).map(|leaf| leaf.len()).unwrap_or(0),
+    /// Returns true if the tree is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the maximum number of keys per node.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Fewest keys a leaf other than the root holds once rebalanced.
+    pub fn min_keys_leaf(&self) -> usize {
+        min_keys_for(self.capacity)
+    }
+
+    /// Fewest keys a branch other than the root holds once rebalanced.
+    pub fn min_keys_branch(&self) -> usize {
+        min_keys_for(self.capacity)
+    }
+
+    /// Returns true if the node holds fewer keys than the minimum for its
+    /// kind, and false for a node that is not in the tree.
+    ///
+    /// This reports occupancy only. The root may always be underfull, and
+    /// deferred rebalancing or a byte budget leave underfull leaves behind on
+    /// purpose, so an underfull node is not a broken invariant by itself.
+    #[inline]
+    pub fn is_node_underfull(&self, node_ref: &NodeRef<K, V>) -> bool {
+        match node_ref {
+            NodeRef::Leaf(id, _) => self
+                .get_leaf(*id)
+                .map(|leaf| leaf.is_underfull())
+                .unwrap_or(false),
             NodeRef::Branch(id, _) => self
                 .get_branch(*id)
-                .map(|branch| {
-                    branch
-                        .children
-                        .iter()
-                        .map(|child| self.len_recursive(child))
-                        .sum()
-                })
-                .unwrap_or(0),
+                .map(|branch| branch.is_underfull())
+                .unwrap_or(false),
         }
     }
 
-    /// Returns true if the tree is empty.
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
+    /// Count the underfull leaf and branch nodes below the root, as
+    /// `(leaves, branches)`.
+    ///
+    /// Always `(0, 0)` for a tree with eager deletion and no byte budget.
+    pub fn underfull_node_count(&self) -> (usize, usize) {
+        let mut counts = (0, 0);
+        if let NodeRef::Branch(id, _) = self.root {
+            self.count_underfull_children(id, &mut counts);
+        }
+        counts
+    }
+
+    /// Add the underfull nodes below `branch_id` to `counts`.
+    fn count_underfull_children(&self, branch_id: NodeId, counts: &mut (usize, usize)) {
+        let Some(branch) = self.get_branch(branch_id) else {
+            return;
+        };
+        for child in branch.children() {
+            let underfull = usize::from(self.is_node_underfull(&child));
+            match child {
+                NodeRef::Leaf(_, _) => counts.0 += underfull,
+                NodeRef::Branch(id, _) => {
+                    counts.1 += underfull;
+                    self.count_underfull_children(id, counts);
+                }
+            }
+        }
     }
 
     /// Returns true if the root is a leaf node.
@@ -56,9 +132,8 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
                 .get_branch(*id)
                 .map(|branch| {
                     branch
-                        .children
-                        .iter()
-                        .map(|child| self.leaf_count_recursive(child))
+                        .children()
+                        .map(|child| self.leaf_count_recursive(&child))
                         .sum()
                 })
                 .unwrap_or(0),
@@ -71,10 +146,8 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
         self.leaf_arena.clear();
         self.branch_arena.clear();
 
-        // Create a new root leaf
-        let root_leaf = LeafNode::new(self.capacity);
-        let root_id = self.leaf_arena.allocate(root_leaf);
-        self.root = NodeRef::Leaf(root_id, PhantomData);
+        // Start over with an inline root leaf
+        self.reset_inline_root();
     }
 
     /// Count the number of leaf and branch nodes actually in the tree structure.
@@ -87,6 +160,21 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
         }
     }
 
+    /// Arena nodes that are allocated but cannot be reached from the root, as
+    /// `(leaves, branches)`.
+    ///
+    /// Every operation frees the nodes it takes out of the tree, including
+    /// a branch abandoned when the root collapses, so this is always `(0, 0)`.
+    /// Anything else is a leaked arena slot.
+    pub fn unreachable_node_count(&self) -> (usize, usize) {
+        let (leaves, branches) = self.count_nodes_in_tree();
+        let leaves = leaves - usize::from(self.inline_root.is_some());
+        (
+            self.leaf_arena.allocated_count().saturating_sub(leaves),
+            self.branch_arena.allocated_count().saturating_sub(branches),
+        )
+    }
+
     /// Recursively count nodes in the tree.
     fn count_nodes_recursive(&self, node: &NodeRef<K, V>) -> (usize, usize) {
         match node {
@@ -97,8 +185,8 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
                     let mut total_branches = 1; // Count this branch
 
                     // Recursively count in all children
-                    for child in &branch.children {
-                        let (child_leaves, child_branches) = self.count_nodes_recursive(child);
+                    for child in branch.children() {
+                        let (child_leaves, child_branches) = self.count_nodes_recursive(&child);
                         total_leaves += child_leaves;
                         total_branches += child_branches;
                     }
@@ -118,21 +206,13 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
 
     /// Get the ID of the first (leftmost) leaf in the tree
     pub fn get_first_leaf_id(&self) -> Option<NodeId> {
-        let mut current = &self.root;
+        let mut current = self.root;
 
         loop {
             match current {
-                NodeRef::Leaf(leaf_id, _) => return Some(*leaf_id),
+                NodeRef::Leaf(leaf_id, _) => return Some(leaf_id),
                 NodeRef::Branch(branch_id, _) => {
-                    if let Some(branch) = self.get_branch(*branch_id) {
-                        if !branch.children.is_empty() {
-                            current = &branch.children[0];
-                        } else {
-                            return None;
-                        }
-                    } else {
-                        return None;
-                    }
+                    current = self.get_branch(branch_id)?.child(0)?;
                 }
             }
         }
@@ -142,67 +222,91 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
     /// Returns the leaf `NodeId` and the insertion index within that leaf.
     #[inline]
     pub(crate) fn find_leaf_for_key(&self, key: &K) -> Option<(NodeId, usize)> {
-        let mut current = &self.root;
+        let mut current = self.root;
 
         loop {
             match current {
                 NodeRef::Leaf(leaf_id, _) => {
-                    if let Some(leaf) = self.get_leaf(*leaf_id) {
+                    if let Some(leaf) = self.get_leaf(leaf_id) {
                         // Find the position where this key would be inserted
                         let index = match leaf.binary_search_keys(key) {
                             Ok(idx) => idx,  // Key found at exact position
                             Err(idx) => idx, // Key would be inserted at this position
                         };
-                        return Some((*leaf_id, index));
+                        return Some((leaf_id, index));
                     } else {
                         return None;
                     }
                 }
                 NodeRef::Branch(branch_id, _) => {
-                    if let Some(branch) = self.get_branch(*branch_id) {
-                        let child_index = branch.find_child_index(key);
-                        if let Some(child) = branch.children.get(child_index) {
-                            current = child;
-                        } else {
-                            return None;
-                        }
-                    } else {
-                        return None;
-                    }
+                    current = self.get_branch(branch_id)?.get_child(key)?;
                 }
             }
         }
     }
 
+    /// Find the leaf that holds `key` if it is present. Only a root leaf can
+    /// be inline, so leaves below a branch come straight from the arena.
+    #[inline(always)]
+    pub(crate) fn leaf_for_key(&self, key: &K) -> Option<&LeafNode<K, V>> {
+        let mut branch_id = match self.root {
+            NodeRef::Leaf(leaf_id, _) => return self.get_leaf(leaf_id),
+            NodeRef::Branch(branch_id, _) => branch_id,
+        };
+        loop {
+            let branch = self.get_branch(branch_id)?;
+            let child_id = *branch.child_ids.get(branch.find_child_index(key))?;
+            if branch.children_are_leaves {
+                return self.leaf_arena.get(child_id);
+            }
+            branch_id = child_id;
+        }
+    }
+
     /// Find the target leaf and provide both the index and whether the key matched exactly.
     /// Returns `(leaf_id, index, matched)` where `matched` is true if the key exists at `index`.
     #[inline(always)]
     pub(crate) fn find_leaf_for_key_with_match(&self, key: &K) -> Option<(NodeId, usize, bool)> {
-        let mut current = &self.root;
+        let mut current = self.root;
 
         loop {
             match current {
                 NodeRef::Leaf(leaf_id, _) => {
-                    if let Some(leaf) = self.get_leaf(*leaf_id) {
+                    if let Some(leaf) = self.get_leaf(leaf_id) {
                         match leaf.binary_search_keys(key) {
-                            Ok(idx) => return Some((*leaf_id, idx, true)),
-                            Err(idx) => return Some((*leaf_id, idx, false)),
+                            Ok(idx) => return Some((leaf_id, idx, true)),
+                            Err(idx) => return Some((leaf_id, idx, false)),
                         }
                     } else {
                         return None;
                     }
                 }
                 NodeRef::Branch(branch_id, _) => {
-                    if let Some(branch) = self.get_branch(*branch_id) {
-                        let child_index = branch.find_child_index(key);
-                        if let Some(child) = branch.children.get(child_index) {
-                            current = child;
-                        } else {
-                            return None;
-                        }
-                    } else {
-                        return None;
-                    }
+                    current = self.get_branch(branch_id)?.get_child(key)?;
+                }
+            }
+        }
+    }
+
+    /// Descend to the leaf where `key` belongs, recording each branch passed
+    /// and the child index taken in `path`. Returns the leaf and the number of
+    /// path entries written, or `None` if a branch is missing or empty.
+    pub(crate) fn path_to_leaf(
+        &self,
+        key: &K,
+        path: &mut [(NodeId, usize); MAX_HEIGHT],
+    ) -> Option<(NodeId, usize)> {
+        let mut current = self.root;
+        let mut depth = 0;
+        loop {
+            match current {
+                NodeRef::Leaf(leaf_id, _) => return Some((leaf_id, depth)),
+                NodeRef::Branch(branch_id, _) => {
+                    let branch = self.get_branch(branch_id)?;
+                    let child_index = branch.find_child_index(key);
+                    current = branch.child(child_index)?;
+                    path[depth] = (branch_id, child_index);
+                    depth += 1;
                 }
             }
         }
@@ -219,7 +323,7 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
     pub fn find_child(&self, branch_id: NodeId, key: &K) -> Option<(usize, NodeRef<K, V>)> {
         self.get_branch(branch_id).and_then(|branch| {
             let idx = branch.find_child_index(key);
-            branch.children.get(idx).cloned().map(|child| (idx, child))
+            branch.child(idx).map(|child| (idx, child))
         })
     }
 
@@ -227,7 +331,7 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
     pub fn find_child_mut(&mut self, branch_id: NodeId, key: &K) -> Option<(usize, NodeRef<K, V>)> {
         self.get_branch_mut(branch_id).and_then(|branch| {
             let idx = branch.find_child_index(key);
-            branch.children.get(idx).cloned().map(|child| (idx, child))
+            branch.child(idx).map(|child| (idx, child))
         })
     }
 
@@ -0,0 +1,169 @@
+//! Read-only range views over a BPlusTreeMap.
+//!
+//! A [`TreeView`] borrows the tree together with a pair of bounds, so a
+//! function can be handed "a slice of the tree" without copying entries into a
+//! `Vec` and without seeing anything outside the range.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::iteration::RangeIterator;
+use crate::types::BPlusTreeMap;
+use std::ops::{Bound, RangeBounds};
+
+/// A borrowed, read-only window onto the entries of a tree within a key range.
+///
+/// Created by [`BPlusTreeMap::view`]. Lookups outside the range behave as if
+/// the key were absent.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::{BPlusTreeMap, TreeView};
+///
+/// fn total(view: &TreeView<'_, i32, i32>) -> i32 {
+///     view.iter().map(|(_, v)| *v).sum()
+/// }
+///
+/// let mut tree = BPlusTreeMap::new(16).unwrap();
+/// for i in 0..10 {
+///     tree.insert(i, i * 10);
+/// }
+///
+/// let view = tree.view(3..6);
+/// assert_eq!(view.len(), 3);
+/// assert_eq!(view.get(&4), Some(&40));
+/// assert_eq!(view.get(&8), None);
+/// assert_eq!(total(&view), 120);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TreeView<'a, K, V> {
+    tree: &'a BPlusTreeMap<K, V>,
+    start: Bound<K>,
+    end: Bound<K>,
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Returns a read-only view of the entries whose keys fall in `range`.
+    ///
+    /// Creating a view only clones the range bounds; no entries are copied.
+    pub fn view<R>(&self, range: R) -> TreeView<'_, K, V>
+    where
+        R: RangeBounds<K>,
+    {
+        TreeView {
+            tree: self,
+            start: range.start_bound().cloned(),
+            end: range.end_bound().cloned(),
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone> TreeView<'a, K, V> {
+    /// Returns true if `key` lies within the view's range.
+    pub fn in_range(&self, key: &K) -> bool {
+        (self.start.as_ref(), self.end.as_ref()).contains(key)
+    }
+
+    /// Get the value for `key` if it is in the range and present in the tree.
+    pub fn get(&self, key: &K) -> Option<&'a V> {
+        if self.in_range(key) {
+            self.tree.get(key)
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if the view contains `key`.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Number of entries in the view.
+    ///
+    /// This walks the range, so it is linear in the size of the view.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns true if the view holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// Iterate over the entries in the view in key order.
+    pub fn iter(&self) -> RangeIterator<'a, K, V> {
+        self.tree.range((self.start.clone(), self.end.clone()))
+    }
+
+    /// Iterate over the keys in the view in key order.
+    pub fn keys(&self) -> impl Iterator<Item = &'a K> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Iterate over the values in the view in key order.
+    pub fn values(&self) -> impl Iterator<Item = &'a V> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /// Returns the first entry in the view.
+    pub fn first(&self) -> Option<(&'a K, &'a V)> {
+        self.iter().next()
+    }
+
+    /// Returns the last entry in the view.
+    pub fn last(&self) -> Option<(&'a K, &'a V)> {
+        self.iter().next_back()
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone> IntoIterator for &TreeView<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = RangeIterator<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view_restricts_lookups_and_iteration() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..100 {
+            tree.insert(i * 2, i);
+        }
+        let view = tree.view(10..=20);
+
+        assert_eq!(view.len(), 6);
+        assert_eq!(view.get(&12), Some(&6));
+        assert_eq!(view.get(&22), None);
+        assert!(!view.contains_key(&8));
+        assert_eq!(view.first(), Some((&10, &5)));
+        assert_eq!(view.last(), Some((&20, &10)));
+        assert_eq!(
+            view.keys().copied().collect::<Vec<_>>(),
+            vec![10, 12, 14, 16, 18, 20]
+        );
+    }
+
+    #[test]
+    fn test_view_bounds() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..100 {
+            tree.insert(i * 2, i);
+        }
+
+        assert_eq!(tree.view(..).len(), 100);
+        assert_eq!(tree.view(190..).len(), 5);
+        assert_eq!(tree.view(..5).len(), 3);
+        assert!(tree.view(11..12).is_empty());
+        assert_eq!(tree.view(11..12).first(), None);
+
+        let excluded = tree.view((Bound::Excluded(10), Bound::Excluded(14)));
+        assert_eq!((&excluded).into_iter().count(), 1);
+        assert!(!excluded.in_range(&10));
+        assert_eq!(excluded.get(&10), None);
+    }
+}
@@ -0,0 +1,214 @@
+//! Choosing a node capacity by measuring it.
+//!
+//! The best capacity depends on key and value sizes, on the mix of reads
+//! and writes, and on the machine, so general advice can only give a range.
+//! [`BPlusTreeMap::tune_capacity`] runs a sample of the caller's own
+//! workload against a fresh tree for each candidate configuration, several
+//! rounds each, and reports which one was fastest.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::builder::BPlusTreeBuilder;
+use crate::error::BuildError;
+use crate::types::BPlusTreeMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Timings of one candidate configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuningRun {
+    /// The configuration measured.
+    pub config: BPlusTreeBuilder,
+    /// Node capacity of the trees it built.
+    pub capacity: usize,
+    /// Time taken by each round, in the order they ran.
+    pub rounds: Vec<Duration>,
+}
+
+impl TuningRun {
+    /// Median round time, the figure candidates are ranked by.
+    pub fn median(&self) -> Duration {
+        let mut sorted = self.rounds.clone();
+        sorted.sort();
+        sorted.get(sorted.len() / 2).copied().unwrap_or_default()
+    }
+
+    /// Fastest round time.
+    pub fn fastest(&self) -> Duration {
+        self.rounds.iter().copied().min().unwrap_or_default()
+    }
+}
+
+/// What [`BPlusTreeMap::tune_capacity`] measured, one run per candidate in
+/// the order given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuningReport {
+    runs: Vec<TuningRun>,
+}
+
+impl TuningReport {
+    /// Every candidate's timings.
+    pub fn runs(&self) -> &[TuningRun] {
+        &self.runs
+    }
+
+    /// The candidate with the lowest median time, or `None` if there were
+    /// no candidates.
+    pub fn best(&self) -> Option<&TuningRun> {
+        self.best_index().map(|index| &self.runs[index])
+    }
+
+    fn best_index(&self) -> Option<usize> {
+        (0..self.runs.len()).min_by_key(|&index| self.runs[index].median())
+    }
+}
+
+impl fmt::Display for TuningReport {
+    /// A table of the candidates with the fastest marked.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let best = self.best_index();
+        writeln!(f, "{:>8}  {:>12}  {:>12}", "capacity", "median", "fastest")?;
+        for (index, run) in self.runs.iter().enumerate() {
+            let marker = if Some(index) == best { "  *" } else { "" };
+            writeln!(
+                f,
+                "{:>8}  {:>12?}  {:>12?}{}",
+                run.capacity,
+                run.median(),
+                run.fastest(),
+                marker
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Time `workload` on a fresh tree built from each of `candidates` and
+    /// report the results.
+    ///
+    /// Each candidate runs `rounds` times, at least once. Rounds go round
+    /// robin over the candidates, so drift in machine load during the sweep
+    /// spreads evenly instead of penalising the last ones. Only the workload
+    /// is timed, not building the tree. Run it in a release build, with a
+    /// sample large enough that one round takes a few milliseconds.
+    ///
+    /// Fails if a candidate cannot be built, including one configured for a
+    /// backend other than [`Backend::Standard`], which the workload cannot
+    /// run against.
+    ///
+    /// [`Backend::Standard`]: crate::Backend::Standard
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bplustree::{BPlusTreeBuilder, BPlusTreeMap};
+    ///
+    /// let candidates = [8, 16, 32, 64, 128].map(|c| BPlusTreeBuilder::new().capacity(c));
+    /// let report = BPlusTreeMap::tune_capacity(candidates, 3, |tree: &mut BPlusTreeMap<u64, u64>| {
+    ///     for i in 0..2_000 {
+    ///         tree.insert(i * 7919 % 2_000, i);
+    ///     }
+    ///     for i in 0..2_000 {
+    ///         assert!(tree.get(&i).is_some());
+    ///     }
+    /// })
+    /// .unwrap();
+    ///
+    /// assert_eq!(report.runs().len(), 5);
+    /// let best = report.best().unwrap();
+    /// let tree: BPlusTreeMap<u64, u64> = best.config.build().unwrap();
+    /// assert_eq!(tree.capacity(), best.capacity);
+    /// println!("{}", report);
+    /// ```
+    pub fn tune_capacity<F>(
+        candidates: impl IntoIterator<Item = BPlusTreeBuilder>,
+        rounds: usize,
+        mut workload: F,
+    ) -> Result<TuningReport, BuildError>
+    where
+        F: FnMut(&mut BPlusTreeMap<K, V>),
+    {
+        let mut runs = Vec::new();
+        for config in candidates {
+            // Build once up front so a bad candidate fails before any timing
+            let capacity = config.build::<K, V>()?.capacity();
+            runs.push(TuningRun {
+                config,
+                capacity,
+                rounds: Vec::new(),
+            });
+        }
+        for _ in 0..rounds.max(1) {
+            for run in &mut runs {
+                let mut tree = run.config.build()?;
+                let start = Instant::now();
+                workload(&mut tree);
+                run.rounds.push(start.elapsed());
+            }
+        }
+        Ok(TuningReport { runs })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OverflowMode;
+
+    #[test]
+    fn test_every_candidate_runs_every_round_on_a_fresh_tree() {
+        let candidates = [
+            BPlusTreeBuilder::new().capacity(4),
+            BPlusTreeBuilder::new().capacity(32),
+            BPlusTreeBuilder::write_heavy().capacity(16),
+        ];
+        let mut seen = Vec::new();
+        let report =
+            BPlusTreeMap::tune_capacity(candidates, 4, |tree: &mut BPlusTreeMap<u32, u32>| {
+                assert!(tree.is_empty());
+                seen.push((tree.capacity(), tree.overflow_mode()));
+                for i in 0..500 {
+                    tree.insert(i, i);
+                }
+            })
+            .unwrap();
+
+        assert_eq!(seen.len(), 12);
+        assert_eq!(seen[2], (16, OverflowMode::GrowLeaves { factor: 3 }));
+        let capacities: Vec<_> = report.runs().iter().map(|run| run.capacity).collect();
+        assert_eq!(capacities, [4, 32, 16]);
+        assert!(report.runs().iter().all(|run| run.rounds.len() == 4));
+
+        let best = report.best().unwrap();
+        assert!(report
+            .runs()
+            .iter()
+            .all(|run| run.median() >= best.median()));
+        assert!(best.fastest() <= best.median());
+        assert_eq!(report.to_string().matches('*').count(), 1);
+    }
+
+    #[test]
+    fn test_unbuildable_candidate_is_reported() {
+        let candidates = [
+            BPlusTreeBuilder::new().capacity(16),
+            BPlusTreeBuilder::new().capacity(1),
+        ];
+        let mut calls = 0;
+        let result = BPlusTreeMap::<u32, u32>::tune_capacity(candidates, 1, |_| calls += 1);
+        assert!(matches!(result, Err(BuildError::InvalidCapacity(_))));
+        assert_eq!(calls, 0);
+
+        #[cfg(feature = "compressed")]
+        {
+            let compressed = [BPlusTreeBuilder::new().backend(crate::Backend::Compressed)];
+            assert!(matches!(
+                BPlusTreeMap::<u32, u32>::tune_capacity(compressed, 1, |_| {}),
+                Err(BuildError::BackendMismatch { .. })
+            ));
+        }
+
+        let empty = BPlusTreeMap::<u32, u32>::tune_capacity([], 3, |_| {}).unwrap();
+        assert!(empty.best().is_none());
+    }
+}
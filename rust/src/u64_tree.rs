@@ -0,0 +1,490 @@
+//! A B+ tree specialized for `u64` keys.
+//!
+//! [`BPlusTreeMap`](crate::BPlusTreeMap) is generic over `K: Ord`, so its
+//! searches are binary searches with a data-dependent branch per step and its
+//! nodes hold keys in separately allocated `Vec`s. [`U64Tree`] fixes the key
+//! type and node size instead:
+//!
+//! - every node stores its keys inline in a 64-byte-aligned array of
+//!   `NODE_KEYS` (64) slots, so node keys start on a cache line and a search
+//!   touches whole lines only;
+//! - unused key slots hold `u64::MAX`, so searches run a fixed number of
+//!   steps over the whole array and each step is a conditional move instead
+//!   of a data-dependent branch;
+//! - leaves are never freed, so the leftmost leaf is always the first one
+//!   allocated and leaf ids need no validity checks.
+//!
+//! Removal takes the entry out of its leaf without borrowing or merging, as
+//! in [`DeletionMode::DeferredRebalance`](crate::DeletionMode::DeferredRebalance). Call
+//! [`U64Tree::compact`] after heavy deletion to rebuild densely.
+
+use std::ops::{Bound, RangeBounds};
+
+/// Key slots per node.
+const NODE_KEYS: usize = 64;
+
+/// Bound on the number of branch levels: with at least `NODE_KEYS / 2 + 1`
+/// children per branch, 2^32 leaves fit in far fewer levels.
+const MAX_HEIGHT: usize = 12;
+
+/// Marks the end of a leaf chain.
+const NO_LEAF: u32 = u32::MAX;
+
+/// Inline key storage, aligned so that node keys start on a cache line.
+/// Slots past the node's length hold `u64::MAX`, keeping the array sorted.
+#[derive(Debug, Clone, Copy)]
+#[repr(C, align(64))]
+struct Keys([u64; NODE_KEYS]);
+
+impl Keys {
+    const EMPTY: Keys = Keys([u64::MAX; NODE_KEYS]);
+
+    /// Number of keys that are less than `probe`.
+    ///
+    /// A binary search over the full fixed-size array: the loop has a constant
+    /// trip count and each step is a conditional move rather than a branch.
+    /// Unused slots hold `u64::MAX`, so they never count.
+    #[inline(always)]
+    fn count_below(&self, probe: u64) -> usize {
+        let mut base = 0;
+        let mut size = NODE_KEYS;
+        while size > 1 {
+            let half = size / 2;
+            base = if self.0[base + half - 1] < probe {
+                base + half
+            } else {
+                base
+            };
+            size -= half;
+        }
+        base + usize::from(self.0[base] < probe)
+    }
+
+    /// Number of the first `len` keys that are at most `probe`.
+    #[inline(always)]
+    fn count_at_or_below(&self, len: usize, probe: u64) -> usize {
+        let mut base = 0;
+        let mut size = NODE_KEYS;
+        while size > 1 {
+            let half = size / 2;
+            base = if self.0[base + half - 1] <= probe {
+                base + half
+            } else {
+                base
+            };
+            size -= half;
+        }
+        // Unused slots compare equal to a probe of `u64::MAX`
+        (base + usize::from(self.0[base] <= probe)).min(len)
+    }
+
+    /// Insert `key` at `index`, shifting later keys right.
+    #[inline]
+    fn insert(&mut self, len: usize, index: usize, key: u64) {
+        self.0.copy_within(index..len, index + 1);
+        self.0[index] = key;
+    }
+
+    /// Remove the key at `index`, shifting later keys left.
+    #[inline]
+    fn remove(&mut self, len: usize, index: usize) {
+        self.0.copy_within(index + 1..len, index);
+        self.0[len - 1] = u64::MAX;
+    }
+
+    /// Move the keys from `at` onwards into a new array.
+    fn split_off(&mut self, len: usize, at: usize) -> Keys {
+        let mut right = Keys::EMPTY;
+        right.0[..len - at].copy_from_slice(&self.0[at..len]);
+        self.0[at..len].fill(u64::MAX);
+        right
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Leaf<V> {
+    keys: Keys,
+    len: usize,
+    next: u32,
+    values: Vec<V>,
+}
+
+impl<V> Leaf<V> {
+    fn new() -> Self {
+        Self {
+            keys: Keys::EMPTY,
+            len: 0,
+            next: NO_LEAF,
+            values: Vec::with_capacity(NODE_KEYS),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Branch {
+    keys: Keys,
+    len: usize,
+    children: [u32; NODE_KEYS + 1],
+}
+
+/// An ordered map from `u64` keys, tuned for lookup and insert speed.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::U64Tree;
+///
+/// let mut tree = U64Tree::new();
+/// for key in (0..1_000u64).rev() {
+///     tree.insert(key, key * 2);
+/// }
+/// assert_eq!(tree.get(500), Some(&1_000));
+/// assert_eq!(tree.remove(500), Some(1_000));
+/// assert_eq!(tree.range(498..503).map(|(k, _)| k).collect::<Vec<_>>(), vec![498, 499, 501, 502]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct U64Tree<V> {
+    leaves: Vec<Leaf<V>>,
+    branches: Vec<Branch>,
+    root: u32,
+    /// Number of branch levels above the leaves.
+    height: usize,
+    len: usize,
+}
+
+impl<V> Default for U64Tree<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> U64Tree<V> {
+    /// Create an empty tree.
+    pub fn new() -> Self {
+        Self {
+            leaves: vec![Leaf::new()],
+            branches: Vec::new(),
+            root: 0,
+            height: 0,
+            len: 0,
+        }
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the tree holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Remove every entry and release all nodes but one.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Get the value stored under `key`.
+    #[inline]
+    pub fn get(&self, key: u64) -> Option<&V> {
+        let leaf = &self.leaves[self.find_leaf(key) as usize];
+        let index = leaf.keys.count_below(key);
+        (index < leaf.len && leaf.keys.0[index] == key).then(|| &leaf.values[index])
+    }
+
+    /// Get a mutable reference to the value stored under `key`.
+    #[inline]
+    pub fn get_mut(&mut self, key: u64) -> Option<&mut V> {
+        let leaf_id = self.find_leaf(key);
+        let leaf = &mut self.leaves[leaf_id as usize];
+        let index = leaf.keys.count_below(key);
+        (index < leaf.len && leaf.keys.0[index] == key).then(|| &mut leaf.values[index])
+    }
+
+    /// Returns true if `key` is present.
+    #[inline]
+    pub fn contains_key(&self, key: u64) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Insert `value` under `key`, returning the previous value if any.
+    pub fn insert(&mut self, key: u64, value: V) -> Option<V> {
+        // Record the path so splits can be pushed upwards. Branches are at
+        // least half full, so the tree can never be this deep.
+        let mut path = [(0u32, 0usize); MAX_HEIGHT];
+        let mut node = self.root;
+        for step in &mut path[..self.height] {
+            let branch = &self.branches[node as usize];
+            let child_index = branch.keys.count_at_or_below(branch.len, key);
+            *step = (node, child_index);
+            node = branch.children[child_index];
+        }
+
+        let new_id = self.leaves.len() as u32;
+        let leaf = &mut self.leaves[node as usize];
+        let index = leaf.keys.count_below(key);
+        if index < leaf.len && leaf.keys.0[index] == key {
+            return Some(std::mem::replace(&mut leaf.values[index], value));
+        }
+        self.len += 1;
+
+        if leaf.len < NODE_KEYS {
+            leaf.keys.insert(leaf.len, index, key);
+            leaf.values.insert(index, value);
+            leaf.len += 1;
+            return None;
+        }
+
+        // Split the leaf in half, then insert into the correct side
+        let mid = NODE_KEYS / 2;
+        let mut right = Leaf {
+            keys: leaf.keys.split_off(leaf.len, mid),
+            len: NODE_KEYS - mid,
+            next: leaf.next,
+            values: Vec::with_capacity(NODE_KEYS),
+        };
+        right.values.extend(leaf.values.drain(mid..));
+        leaf.len = mid;
+        leaf.next = new_id;
+        let target = if index <= mid { leaf } else { &mut right };
+        let target_index = if index <= mid { index } else { index - mid };
+        target.keys.insert(target.len, target_index, key);
+        target.values.insert(target_index, value);
+        target.len += 1;
+        let separator = right.keys.0[0];
+        self.leaves.push(right);
+
+        self.insert_into_parents(&path[..self.height], separator, new_id);
+        None
+    }
+
+    /// Remove `key`, returning its value if it was present.
+    ///
+    /// Leaves are not rebalanced; see [`compact`](U64Tree::compact).
+    pub fn remove(&mut self, key: u64) -> Option<V> {
+        let leaf_id = self.find_leaf(key);
+        let leaf = &mut self.leaves[leaf_id as usize];
+        let index = leaf.keys.count_below(key);
+        if index >= leaf.len || leaf.keys.0[index] != key {
+            return None;
+        }
+        leaf.keys.remove(leaf.len, index);
+        leaf.len -= 1;
+        self.len -= 1;
+        Some(leaf.values.remove(index))
+    }
+
+    /// Rebuild the tree with full leaves, dropping space left by removals.
+    pub fn compact(&mut self) {
+        let mut rebuilt = Self::new();
+        for leaf in std::mem::take(&mut self.leaves) {
+            for (index, value) in leaf.values.into_iter().enumerate() {
+                rebuilt.insert(leaf.keys.0[index], value);
+            }
+        }
+        *self = rebuilt;
+    }
+
+    /// Iterate over all entries in key order.
+    pub fn iter(&self) -> U64TreeIter<'_, V> {
+        U64TreeIter {
+            tree: self,
+            leaf: 0,
+            index: 0,
+            end: Bound::Unbounded,
+        }
+    }
+
+    /// Iterate over the entries whose keys fall in `range`, in key order.
+    pub fn range<R: RangeBounds<u64>>(&self, range: R) -> U64TreeIter<'_, V> {
+        let start = match range.start_bound() {
+            Bound::Included(&key) => Some(key),
+            Bound::Excluded(&key) => key.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let (leaf, index) = match start {
+            Some(start) => {
+                let leaf = self.find_leaf(start);
+                let node = &self.leaves[leaf as usize];
+                (leaf, node.keys.count_below(start))
+            }
+            None => (NO_LEAF, 0),
+        };
+        U64TreeIter {
+            tree: self,
+            leaf,
+            index,
+            end: range.end_bound().cloned(),
+        }
+    }
+
+    /// Descend to the leaf that would hold `key`.
+    #[inline(always)]
+    fn find_leaf(&self, key: u64) -> u32 {
+        let mut node = self.root;
+        for _ in 0..self.height {
+            let branch = &self.branches[node as usize];
+            node = branch.children[branch.keys.count_at_or_below(branch.len, key)];
+        }
+        node
+    }
+
+    /// Insert a new right child and its separator into each ancestor on
+    /// `path`, splitting full branches and growing a new root if needed.
+    fn insert_into_parents(&mut self, path: &[(u32, usize)], mut separator: u64, mut child: u32) {
+        for &(branch_id, child_index) in path.iter().rev() {
+            let branch = &mut self.branches[branch_id as usize];
+            if branch.len < NODE_KEYS {
+                branch.keys.insert(branch.len, child_index, separator);
+                branch
+                    .children
+                    .copy_within(child_index + 1..branch.len + 1, child_index + 2);
+                branch.children[child_index + 1] = child;
+                branch.len += 1;
+                return;
+            }
+
+            // Lay out all keys and children, then split around the middle key
+            let mut keys = [0u64; NODE_KEYS + 1];
+            let mut children = [0u32; NODE_KEYS + 2];
+            keys[..child_index].copy_from_slice(&branch.keys.0[..child_index]);
+            keys[child_index] = separator;
+            keys[child_index + 1..].copy_from_slice(&branch.keys.0[child_index..]);
+            children[..child_index + 1].copy_from_slice(&branch.children[..child_index + 1]);
+            children[child_index + 1] = child;
+            children[child_index + 2..].copy_from_slice(&branch.children[child_index + 1..]);
+
+            let mid = NODE_KEYS / 2;
+            branch.keys = Keys::EMPTY;
+            branch.keys.0[..mid].copy_from_slice(&keys[..mid]);
+            branch.children[..mid + 1].copy_from_slice(&children[..mid + 1]);
+            branch.len = mid;
+
+            let mut right = Branch {
+                keys: Keys::EMPTY,
+                len: NODE_KEYS - mid,
+                children: [0; NODE_KEYS + 1],
+            };
+            right.keys.0[..NODE_KEYS - mid].copy_from_slice(&keys[mid + 1..]);
+            right.children[..NODE_KEYS - mid + 1].copy_from_slice(&children[mid + 1..]);
+
+            separator = keys[mid];
+            child = self.branches.len() as u32;
+            self.branches.push(right);
+        }
+
+        // The root split: grow the tree by one level
+        let mut root = Branch {
+            keys: Keys::EMPTY,
+            len: 1,
+            children: [0; NODE_KEYS + 1],
+        };
+        root.keys.0[0] = separator;
+        root.children[0] = self.root;
+        root.children[1] = child;
+        self.root = self.branches.len() as u32;
+        self.branches.push(root);
+        self.height += 1;
+    }
+}
+
+/// Iterator over the entries of a [`U64Tree`] in key order.
+pub struct U64TreeIter<'a, V> {
+    tree: &'a U64Tree<V>,
+    leaf: u32,
+    index: usize,
+    end: Bound<u64>,
+}
+
+impl<'a, V> Iterator for U64TreeIter<'a, V> {
+    type Item = (u64, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let leaf = self.tree.leaves.get(self.leaf as usize)?;
+            if self.index < leaf.len {
+                let key = leaf.keys.0[self.index];
+                let in_range = match self.end {
+                    Bound::Included(end) => key <= end,
+                    Bound::Excluded(end) => key < end,
+                    Bound::Unbounded => true,
+                };
+                if !in_range {
+                    self.leaf = NO_LEAF;
+                    return None;
+                }
+                self.index += 1;
+                return Some((key, &leaf.values[self.index - 1]));
+            }
+            self.leaf = leaf.next;
+            self.index = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_key_layout_is_cache_aligned() {
+        assert_eq!(std::mem::align_of::<Keys>(), 64);
+        assert_eq!(std::mem::size_of::<Keys>(), NODE_KEYS * 8);
+    }
+
+    #[test]
+    fn test_count_ignores_unused_slots() {
+        let mut keys = Keys::EMPTY;
+        keys.0[..3].copy_from_slice(&[10, 20, 30]);
+        assert_eq!(keys.count_below(25), 2);
+        assert_eq!(keys.count_at_or_below(3, 20), 2);
+        assert_eq!(keys.count_at_or_below(3, u64::MAX), 3);
+    }
+
+    #[test]
+    fn test_random_operations_match_btreemap() {
+        let mut tree = U64Tree::new();
+        let mut reference = BTreeMap::new();
+        let mut rng = SeededRng::new(11);
+
+        for i in 0..50_000u64 {
+            let key = rng.below(20_000);
+            if rng.below(4) == 0 {
+                assert_eq!(tree.remove(key), reference.remove(&key));
+            } else {
+                assert_eq!(tree.insert(key, i), reference.insert(key, i));
+            }
+        }
+        assert_eq!(tree.len(), reference.len());
+        assert!(tree.iter().eq(reference.iter().map(|(k, v)| (*k, v))));
+        assert!(tree
+            .range(5_000..=7_500)
+            .eq(reference.range(5_000..=7_500).map(|(k, v)| (*k, v))));
+
+        tree.compact();
+        assert_eq!(tree.len(), reference.len());
+        assert!(tree.iter().eq(reference.iter().map(|(k, v)| (*k, v))));
+        for key in 0..20_000 {
+            assert_eq!(tree.get(key), reference.get(&key));
+        }
+    }
+
+    #[test]
+    fn test_extreme_keys() {
+        let mut tree = U64Tree::new();
+        for key in (0..100).chain(u64::MAX - 100..=u64::MAX) {
+            tree.insert(key, key);
+        }
+        assert_eq!(tree.get(u64::MAX), Some(&u64::MAX));
+        assert_eq!(tree.range(u64::MAX..).count(), 1);
+        assert_eq!(
+            tree.range((Bound::Excluded(u64::MAX), Bound::Unbounded))
+                .count(),
+            0
+        );
+        assert_eq!(tree.iter().count(), 201);
+    }
+}
@@ -2,15 +2,36 @@
 //!
 //! This module contains all validation methods, invariant checking, debugging utilities,
 //! and test helpers for the B+ tree implementation.
+//!
+//! Everything except [`integrity_check`](BPlusTreeMap::integrity_check) is
+//! compiled only with the `validation` feature (on by default) or in tests.
 
-use crate::error::{BPlusTreeError, TreeResult};
-use crate::types::{BPlusTreeMap, NodeId, NodeRef};
+use crate::bounds::{TreeKey, TreeValue};
+use crate::types::BPlusTreeMap;
+#[cfg(any(test, feature = "validation"))]
+use crate::{
+    error::{BPlusTreeError, TreeResult},
+    types::{DeletionMode, NodeId, NodeRef},
+};
 
 // ============================================================================
 // VALIDATION METHODS
 // ============================================================================
 
-impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
+    /// Invariant check used by the validating operations. Without the
+    /// `validation` feature the check is compiled out and always passes.
+    #[inline]
+    pub(crate) fn integrity_check(&self) -> Result<(), String> {
+        #[cfg(any(test, feature = "validation"))]
+        return self.check_invariants_detailed();
+        #[cfg(not(any(test, feature = "validation")))]
+        Ok(())
+    }
+}
+
+#[cfg(any(test, feature = "validation"))]
+impl<K: TreeKey, V: TreeValue> BPlusTreeMap<K, V> {
     /// Check if the tree maintains B+ tree invariants.
     /// Returns true if all invariants are satisfied.
     pub fn check_invariants(&self) -> bool {
@@ -35,8 +56,9 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
 
     /// Check that arena allocation matches tree structure
     fn check_arena_tree_consistency(&self) -> TreeResult<()> {
-        // Count nodes in the tree structure
+        // Count nodes in the tree structure, less the inline root
         let (tree_leaf_count, tree_branch_count) = self.count_nodes_in_tree();
+        let tree_leaf_count = tree_leaf_count - usize::from(self.inline_root.is_some());
 
         // Get arena counts
         let leaf_stats = self.leaf_arena_stats();
@@ -138,8 +160,8 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
             NodeRef::Leaf(id, _) => ids.push(*id),
             NodeRef::Branch(id, _) => {
                 if let Some(branch) = self.get_branch(*id) {
-                    for child in &branch.children {
-                        self.collect_leaf_ids(child, ids);
+                    for child in branch.children() {
+                        self.collect_leaf_ids(&child, ids);
                     }
                 }
             }
@@ -173,13 +195,19 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
                         }
                     }
 
-                    // Check capacity constraints
-                    if leaf.keys_len() > self.capacity {
+                    // Check capacity constraints, allowing for grown leaves
+                    if leaf.keys_len() > self.leaf_key_limit() {
                         return false; // Node exceeds capacity
                     }
 
-                    // Check minimum occupancy
-                    if !leaf.keys_is_empty() && leaf.is_underfull() {
+                    // Check minimum occupancy. Deferred rebalancing leaves underfull
+                    // leaves behind on purpose until the next vacuum, and a
+                    // byte budget sizes leaves by weight rather than count.
+                    if self.deletion_mode == DeletionMode::Eager
+                        && self.byte_budget.is_none()
+                        && !leaf.keys_is_empty()
+                        && leaf.is_underfull()
+                    {
                         // For root nodes, allow fewer keys only if it's the only node
                         if _is_root {
                             // Root leaf can have any number of keys >= 1
@@ -217,7 +245,7 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
             NodeRef::Branch(id, _) => {
                 if let Some(branch) = self.get_branch(*id) {
                     // Check branch invariants
-                    if branch.keys.len() + 1 != branch.children.len() {
+                    if branch.keys.len() + 1 != branch.child_count() {
                         return false; // Branch must have one more child than keys
                     }
 
@@ -245,12 +273,12 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
                     }
 
                     // Check that branch has at least one child
-                    if branch.children.is_empty() {
+                    if branch.child_count() == 0 {
                         return false; // Branch must have at least one child
                     }
 
                     // Check children recursively
-                    for (i, child) in branch.children.iter().enumerate() {
+                    for (i, child) in branch.children().enumerate() {
                         let child_min = if i == 0 {
                             min_key
                         } else {
@@ -262,7 +290,7 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
                             Some(&branch.keys[i])
                         };
 
-                        if !self.check_node_invariants(child, child_min, child_max, false) {
+                        if !self.check_node_invariants(&child, child_min, child_max, false) {
                             return false;
                         }
                     }
@@ -312,8 +340,8 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
             }
             NodeRef::Branch(id, _) => {
                 if let Some(branch) = self.get_branch(*id) {
-                    for child in &branch.children {
-                        self.collect_leaf_sizes(child, sizes);
+                    for child in branch.children() {
+                        self.collect_leaf_sizes(&child, sizes);
                     }
                 }
             }
@@ -345,10 +373,10 @@ impl<K: Ord + Clone, V: Clone> BPlusTreeMap<K, V> {
                         id,
                         branch.capacity,
                         branch.keys.len(),
-                        branch.children.len()
+                        branch.child_count()
                     );
-                    for child in &branch.children {
-                        self.print_node(child, depth + 1);
+                    for child in branch.children() {
+                        self.print_node(&child, depth + 1);
                     }
                 } else {
                     println!("{}Branch[id={}]: <missing>", indent, id);
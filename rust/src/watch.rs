@@ -0,0 +1,326 @@
+//! Change notifications for key ranges.
+//!
+//! [`WatchedMap`] wraps a [`BPlusTreeMap`] and calls back registered watchers
+//! whenever an insert, update or removal touches a key inside the range they
+//! asked about. It suits caches and materialised views that must follow a
+//! slice of the tree without rescanning it. Trees that nobody watches pay
+//! nothing: the notification layer lives entirely in the wrapper.
+
+use crate::bounds::{TreeKey, TreeValue};
+use crate::error::InitResult;
+use crate::iteration::RangeIterator;
+use crate::types::BPlusTreeMap;
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+/// A change to one entry, passed to watchers of a range containing its key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchEvent<'a, K, V> {
+    /// A key that was absent was inserted.
+    Inserted { key: &'a K, value: &'a V },
+    /// The value under a present key was replaced.
+    Updated { key: &'a K, old: &'a V, new: &'a V },
+    /// A key was removed.
+    Removed { key: &'a K, value: &'a V },
+}
+
+impl<K, V> WatchEvent<'_, K, V> {
+    /// The key the event is about.
+    pub fn key(&self) -> &K {
+        match self {
+            WatchEvent::Inserted { key, .. }
+            | WatchEvent::Updated { key, .. }
+            | WatchEvent::Removed { key, .. } => key,
+        }
+    }
+}
+
+/// Identifies a registered watcher, for [`WatchedMap::unwatch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WatchId(u64);
+
+type Callback<K, V> = Box<dyn FnMut(&WatchEvent<'_, K, V>)>;
+
+struct Watcher<K, V> {
+    id: WatchId,
+    start: Bound<K>,
+    end: Bound<K>,
+    callback: Callback<K, V>,
+}
+
+impl<K: Ord, V> Watcher<K, V> {
+    fn covers(&self, key: &K) -> bool {
+        (self.start.as_ref(), self.end.as_ref()).contains(key)
+    }
+}
+
+/// A tree that notifies watchers of changes within key ranges.
+///
+/// Callbacks run synchronously, inside the mutating call and after the tree
+/// has been updated, in the order the watchers were registered. Reads go
+/// through [`tree`](Self::tree); all writes must go through the wrapper so
+/// that none escape notification.
+///
+/// # Examples
+///
+/// ```
+/// use bplustree::{WatchEvent, WatchedMap};
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+///
+/// let mut prices = WatchedMap::new(16).unwrap();
+/// let changed = Rc::new(RefCell::new(Vec::new()));
+/// let sink = Rc::clone(&changed);
+/// prices.watch("b".to_string().."c".to_string(), move |event| {
+///     sink.borrow_mut().push(event.key().clone());
+/// });
+///
+/// prices.insert("apple".to_string(), 3);
+/// prices.insert("banana".to_string(), 5);
+/// prices.insert("banana".to_string(), 4);
+/// prices.remove(&"banana".to_string());
+///
+/// assert_eq!(changed.borrow().len(), 3);
+/// assert!(changed.borrow().iter().all(|key| key == "banana"));
+/// ```
+pub struct WatchedMap<K, V> {
+    tree: BPlusTreeMap<K, V>,
+    watchers: Vec<Watcher<K, V>>,
+    next_id: u64,
+}
+
+impl<K: TreeKey, V: TreeValue> WatchedMap<K, V> {
+    /// Create an empty map backed by a tree with node capacity `capacity`.
+    pub fn new(capacity: usize) -> InitResult<Self> {
+        Ok(Self::from_tree(BPlusTreeMap::new(capacity)?))
+    }
+
+    /// Watch an existing tree. Its current entries raise no events.
+    pub fn from_tree(tree: BPlusTreeMap<K, V>) -> Self {
+        Self {
+            tree,
+            watchers: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// The underlying tree, for reads.
+    pub fn tree(&self) -> &BPlusTreeMap<K, V> {
+        &self.tree
+    }
+
+    /// Stop watching and return the underlying tree.
+    pub fn into_inner(self) -> BPlusTreeMap<K, V> {
+        self.tree
+    }
+
+    /// Call `callback` for every change to a key within `range`.
+    pub fn watch<R, F>(&mut self, range: R, callback: F) -> WatchId
+    where
+        R: RangeBounds<K>,
+        F: FnMut(&WatchEvent<'_, K, V>) + 'static,
+    {
+        let id = WatchId(self.next_id);
+        self.next_id += 1;
+        self.watchers.push(Watcher {
+            id,
+            start: range.start_bound().cloned(),
+            end: range.end_bound().cloned(),
+            callback: Box::new(callback),
+        });
+        id
+    }
+
+    /// Remove a watcher. Returns false if it was already removed.
+    pub fn unwatch(&mut self, id: WatchId) -> bool {
+        let before = self.watchers.len();
+        self.watchers.retain(|watcher| watcher.id != id);
+        self.watchers.len() < before
+    }
+
+    /// Number of registered watchers.
+    pub fn watcher_count(&self) -> usize {
+        self.watchers.len()
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Returns true if the map holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Get the value stored under `key`.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.tree.get(key)
+    }
+
+    /// Iterate over the entries in `range`.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> RangeIterator<'_, K, V> {
+        self.tree.range(range)
+    }
+
+    /// Insert `value` under `key`, notifying watchers of the key, and return
+    /// the previous value if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if !self.is_watched(&key) {
+            return self.tree.insert(key, value);
+        }
+
+        let previous = self.tree.insert(key.clone(), value);
+        let Some(current) = self.tree.get(&key) else {
+            return previous;
+        };
+        let event = match &previous {
+            Some(old) => WatchEvent::Updated {
+                key: &key,
+                old,
+                new: current,
+            },
+            None => WatchEvent::Inserted {
+                key: &key,
+                value: current,
+            },
+        };
+        Self::notify(&mut self.watchers, &event);
+        previous
+    }
+
+    /// Remove `key`, notifying watchers of the key if it was present, and
+    /// return its value.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let removed = self.tree.remove(key)?;
+        Self::notify(
+            &mut self.watchers,
+            &WatchEvent::Removed {
+                key,
+                value: &removed,
+            },
+        );
+        Some(removed)
+    }
+
+    /// Remove every entry, notifying watchers once for each entry in their
+    /// range.
+    pub fn clear(&mut self) {
+        for (key, value) in self.tree.items() {
+            Self::notify(&mut self.watchers, &WatchEvent::Removed { key, value });
+        }
+        self.tree.clear();
+    }
+
+    fn is_watched(&self, key: &K) -> bool {
+        self.watchers.iter().any(|watcher| watcher.covers(key))
+    }
+
+    fn notify(watchers: &mut [Watcher<K, V>], event: &WatchEvent<'_, K, V>) {
+        for watcher in watchers.iter_mut() {
+            if watcher.covers(event.key()) {
+                (watcher.callback)(event);
+            }
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for WatchedMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WatchedMap")
+            .field("tree", &self.tree)
+            .field("watchers", &self.watchers.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_test::SeededRng;
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_watchers_see_changes_in_their_range_only() {
+        let mut map = WatchedMap::new(4).unwrap();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let sink = Rc::clone(&log);
+        let low = map.watch(..10, move |event| {
+            let entry = match *event {
+                WatchEvent::Inserted { key, value } => ("insert", *key, *value),
+                WatchEvent::Updated { key, new, .. } => ("update", *key, *new),
+                WatchEvent::Removed { key, value } => ("remove", *key, *value),
+            };
+            sink.borrow_mut().push(entry);
+        });
+        let sink = Rc::clone(&log);
+        map.watch(5..=20, move |event| {
+            sink.borrow_mut().push(("high", *event.key(), 0));
+        });
+
+        map.insert(3, 30);
+        map.insert(7, 70);
+        map.insert(3, 31);
+        map.insert(50, 500);
+        assert_eq!(map.remove(&3), Some(31));
+        assert_eq!(map.remove(&3), None);
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                ("insert", 3, 30),
+                ("insert", 7, 70),
+                ("high", 7, 0),
+                ("update", 3, 31),
+                ("remove", 3, 31)
+            ]
+        );
+
+        log.borrow_mut().clear();
+        assert!(map.unwatch(low));
+        assert!(!map.unwatch(low));
+        map.insert(8, 80);
+        map.clear();
+        assert_eq!(
+            *log.borrow(),
+            vec![("high", 8, 0), ("high", 7, 0), ("high", 8, 0)]
+        );
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_watched_cache_tracks_the_tree() {
+        let mut map = WatchedMap::new(4).unwrap();
+        let cache = Rc::new(RefCell::new(BTreeMap::new()));
+        let view = Rc::clone(&cache);
+        map.watch(100..200, move |event| {
+            let mut view = view.borrow_mut();
+            match *event {
+                WatchEvent::Inserted { key, value }
+                | WatchEvent::Updated {
+                    key, new: value, ..
+                } => {
+                    view.insert(*key, *value);
+                }
+                WatchEvent::Removed { key, .. } => {
+                    view.remove(key);
+                }
+            }
+        });
+
+        let mut rng = SeededRng::new(13);
+        for i in 0..3_000 {
+            let key = rng.below(300) as i32;
+            if rng.below(4) == 0 {
+                map.remove(&key);
+            } else {
+                map.insert(key, i);
+            }
+        }
+
+        assert!(map.range(100..200).eq(cache.borrow().iter()));
+        assert!(map.tree().check_invariants());
+    }
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "validation")]
+
 use bplustree::{assert_tree_valid, verify_attack_result};
 
 mod test_utils;
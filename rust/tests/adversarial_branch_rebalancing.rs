@@ -1,3 +1,5 @@
+#![cfg(feature = "validation")]
+
 mod test_utils;
 use test_utils::*;
 
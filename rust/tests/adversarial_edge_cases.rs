@@ -1,3 +1,5 @@
+#![cfg(feature = "validation")]
+
 mod test_utils;
 use test_utils::*;
 
@@ -257,10 +259,7 @@ fn test_extreme_key_values_attack() {
     }
 
     // Test range queries with extreme bounds
-    let range1: Vec<_> = tree
-        .items_range(Some(&i32::MIN), Some(&0))
-        .map(|(k, _)| *k)
-        .collect();
+    let range1: Vec<_> = tree.range(i32::MIN..0).map(|(k, _)| *k).collect();
 
     if range1.len() != 4 {
         // MIN, MIN+1, -1000000, -1
@@ -0,0 +1,174 @@
+//! Every structural operation frees what it takes out of the tree: after
+//! each one the allocated arena slots are exactly the reachable nodes.
+#![cfg(feature = "validation")]
+
+use bplustree::{BPlusTreeMap, ByteBudget, DeletionMode, EvictFrom, WriteBatch};
+
+fn assert_no_leaks(tree: &BPlusTreeMap<u32, u32>, after: &str) {
+    assert_eq!(
+        tree.unreachable_node_count(),
+        (0, 0),
+        "arena nodes leaked after {}",
+        after
+    );
+    tree.check_invariants_detailed()
+        .unwrap_or_else(|e| panic!("invariants broken after {}: {}", after, e));
+}
+
+fn filled(n: u32) -> BPlusTreeMap<u32, u32> {
+    let mut tree = BPlusTreeMap::new(4).unwrap();
+    for i in 0..n {
+        tree.insert(i, i);
+    }
+    tree
+}
+
+#[test]
+fn test_root_collapse_frees_old_root() {
+    let mut tree = filled(200);
+    assert!(tree.allocated_branch_count() > 1);
+    for i in 0..200 {
+        tree.remove(&i);
+        assert_no_leaks(&tree, &format!("remove({})", i));
+    }
+    assert!(tree.is_leaf_root());
+    assert_eq!(tree.allocated_branch_count(), 0);
+}
+
+#[test]
+fn test_point_operations_leave_no_unreachable_nodes() {
+    let mut tree = BPlusTreeMap::new(4).unwrap();
+    let mut state = 9u64;
+    for i in 0..3_000u32 {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let key = ((state >> 33) % 400) as u32;
+        match i % 5 {
+            0..=2 => {
+                tree.insert(key, i);
+            }
+            3 => {
+                tree.remove(&key);
+            }
+            _ => {
+                let _ = tree.replace_key(&key, key + 1);
+            }
+        }
+        assert_no_leaks(&tree, &format!("operation {}", i));
+    }
+    for key in 0..401 {
+        if key % 2 == 0 {
+            tree.locate(key).remove();
+        } else {
+            tree.locate(key).insert(key);
+        }
+    }
+    assert_no_leaks(&tree, "locate");
+}
+
+#[test]
+fn test_bulk_operations_leave_no_unreachable_nodes() {
+    let mut tree = filled(1_000);
+    assert_eq!(tree.remove_range(100..400), 300);
+    assert_no_leaks(&tree, "remove_range");
+
+    tree.drain_range(600..700).for_each(drop);
+    assert_no_leaks(&tree, "drain_range");
+
+    let mut batch = WriteBatch::new();
+    for i in 0..300 {
+        batch.insert(i * 3, i).remove(i * 3 + 1);
+    }
+    tree.apply_batch(batch);
+    assert_no_leaks(&tree, "apply_batch");
+
+    tree.batch_insert((2_000..2_200).map(|i| (i, i)).collect())
+        .unwrap();
+    assert_no_leaks(&tree, "batch_insert");
+
+    tree.remove_min_k(50);
+    tree.remove_max_k(50);
+    assert_no_leaks(&tree, "remove_min_k/remove_max_k");
+
+    tree.evict_to_len(300, EvictFrom::Head);
+    assert_no_leaks(&tree, "evict_to_len");
+
+    let mut dest = BPlusTreeMap::new(4).unwrap();
+    tree.move_range(..2_100, &mut dest).unwrap();
+    assert_no_leaks(&tree, "move_range (source)");
+    assert_no_leaks(&dest, "move_range (destination)");
+
+    tree.clear();
+    assert_no_leaks(&tree, "clear");
+}
+
+#[test]
+fn test_deferred_removal_and_maintenance_leave_no_unreachable_nodes() {
+    let mut tree = filled(1_000);
+    tree.set_deletion_mode(DeletionMode::DeferredRebalance);
+    for i in (0..1_000).filter(|i| i % 4 != 0) {
+        tree.remove(&i);
+    }
+    assert_eq!(tree.unreachable_node_count(), (0, 0));
+
+    tree.vacuum();
+    assert_no_leaks(&tree, "vacuum");
+
+    tree.set_deletion_mode(DeletionMode::Eager);
+    tree.coalesce_leaves();
+    assert_no_leaks(&tree, "coalesce_leaves");
+
+    tree.set_byte_budget(Some(ByteBudget::new(64, |_, _| 8)));
+    for i in 0..500 {
+        tree.insert(i * 7, i);
+    }
+    for i in 0..250 {
+        tree.remove(&(i * 14));
+    }
+    assert_no_leaks(&tree, "byte-budgeted insert and remove");
+}
+
+#[test]
+fn test_leaf_and_branch_walks_cover_the_allocated_nodes() {
+    let mut tree = filled(500);
+    for i in (0..500).step_by(3) {
+        tree.remove(&i);
+    }
+    assert_no_leaks(&tree, "removes before walking");
+
+    let leaves: Vec<_> = tree.leaves().collect();
+    assert_eq!(leaves.len(), tree.allocated_leaf_count());
+    let keys: Vec<u32> = leaves
+        .iter()
+        .flat_map(|(_, leaf)| leaf.keys().iter().copied())
+        .collect();
+    assert_eq!(keys, tree.keys().copied().collect::<Vec<_>>());
+    for (id, leaf) in &leaves {
+        assert!(std::ptr::eq(tree.get_leaf(*id).unwrap(), *leaf));
+    }
+
+    // Breadth first: each level's children are the next level, in order
+    let branches: Vec<_> = tree.branches().collect();
+    assert_eq!(branches.len(), tree.allocated_branch_count());
+    let (root_id, _) = branches[0];
+    let mut level = vec![root_id];
+    let mut walked = Vec::new();
+    while !level.is_empty() {
+        walked.extend(level.iter().copied());
+        level = level
+            .iter()
+            .flat_map(|&id| tree.get_branch(id).unwrap().children())
+            .filter(|child| !child.is_leaf())
+            .map(|child| child.id())
+            .collect();
+    }
+    assert_eq!(
+        walked,
+        branches.iter().map(|(id, _)| *id).collect::<Vec<_>>()
+    );
+
+    let small = filled(3);
+    assert_eq!(small.leaves().count(), 1);
+    assert_eq!(small.branches().count(), 0);
+}
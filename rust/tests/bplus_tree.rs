@@ -1,5 +1,6 @@
-use bplustree::{BPlusTreeError, BPlusTreeMap, NodeRef};
-use std::marker::PhantomData;
+#![cfg(feature = "validation")]
+
+use bplustree::{BPlusTreeError, BPlusTreeMap, NodeRef, QueryError};
 
 mod test_utils;
 use test_utils::*;
@@ -10,13 +11,15 @@ use test_utils::*;
 
 #[test]
 fn test_node_ref_id_and_is_leaf() {
-    let leaf: NodeRef<i32, i32> = NodeRef::Leaf(7, PhantomData);
+    let leaf: NodeRef<i32, i32> = NodeRef::leaf(7);
     assert_eq!(leaf.id(), 7);
     assert!(leaf.is_leaf());
+    assert!(!leaf.is_branch());
 
-    let branch: NodeRef<i32, i32> = NodeRef::Branch(13, PhantomData);
+    let branch: NodeRef<i32, i32> = NodeRef::branch(13);
     assert_eq!(branch.id(), 13);
     assert!(!branch.is_leaf());
+    assert!(branch.is_branch());
 }
 
 // ============================================================================
@@ -396,7 +399,7 @@ fn test_range_iterator() {
         tree.insert(i, format!("value_{}", i));
     }
 
-    let range_items: Vec<_> = tree.items_range(Some(&3), Some(&8)).collect();
+    let range_items: Vec<_> = tree.range(3..8).collect();
     assert_eq!(range_items, vec![
         (&3, &"value_3".to_string()),
         (&4, &"value_4".to_string()),
@@ -517,7 +520,7 @@ fn test_leaf_split_updates_parent_branch() {
     assert_eq!(tree.len(), 9, "Tree should have 9 items");
 
     // Verify that the range query works correctly across the split
-    let range: Vec<_> = tree.items_range(Some(&1), Some(&10)).collect();
+    let range: Vec<_> = tree.range(1..10).collect();
     assert_eq!(range.len(), 9, "Range query should return all 9 items");
 
     // Verify items are in sorted order
@@ -611,7 +614,7 @@ fn test_root_promotion_leaf_to_branch() {
     assert_eq!(new_value, None, "Should be able to insert new key");
 
     // Verify range queries work across the promoted structure
-    let range: Vec<_> = tree.items_range(Some(&1), Some(&7)).collect();
+    let range: Vec<_> = tree.range(1..7).collect();
     assert_eq!(range.len(), 6, "Range query should return all 6 items");
 
     // Verify items are in sorted order
@@ -681,7 +684,7 @@ fn test_branch_node_split_creates_new_level() {
     assert_eq!(tree.len(), 25, "Tree should have 25 items");
 
     // Verify range queries work correctly across the complex structure
-    let range: Vec<_> = tree.items_range(Some(&1), Some(&26)).collect();
+    let range: Vec<_> = tree.range(1..26).collect();
     assert_eq!(range.len(), 25, "Range query should return all 25 items");
 
     // Verify items are in sorted order
@@ -827,9 +830,9 @@ fn test_comprehensive_insert_scenarios() {
         assert_eq!(tree3.len(), 50, "Random tree should have 50 items");
 
         // Test range queries on all trees
-        let range1: Vec<_> = tree.items_range(Some(&10), Some(&20)).collect();
-        let range2: Vec<_> = tree2.items_range(Some(&10), Some(&20)).collect();
-        let range3: Vec<_> = tree3.items_range(Some(&10), Some(&20)).collect();
+        let range1: Vec<_> = tree.range(10..20).collect();
+        let range2: Vec<_> = tree2.range(10..20).collect();
+        let range3: Vec<_> = tree3.range(10..20).collect();
 
         assert_eq!(
             range1.len(),
@@ -864,10 +867,10 @@ fn test_leaf_allocation() {
     let id2 = tree.allocate_leaf(leaf2);
     let id3 = tree.allocate_leaf(leaf3);
 
-    // IDs should be sequential starting from 1 (since 0 is the initial arena leaf)
-    assert_eq!(id1, 1, "First allocation should get ID 1");
-    assert_eq!(id2, 2, "Second allocation should get ID 2");
-    assert_eq!(id3, 3, "Third allocation should get ID 3");
+    // IDs should be sequential starting from 0 (the root leaf starts inline)
+    assert_eq!(id1, 0, "First allocation should get ID 0");
+    assert_eq!(id2, 1, "Second allocation should get ID 1");
+    assert_eq!(id3, 2, "Third allocation should get ID 2");
 
     // Test retrieval
     assert!(
@@ -1085,7 +1088,7 @@ fn test_key_error_on_missing_key() {
 
     // Test that get_item returns error for missing keys
     let result = tree.get_item(&2);
-    assert_eq!(result, Err(BPlusTreeError::KeyNotFound));
+    assert_eq!(result, Err(BPlusTreeError::Query(QueryError::KeyNotFound)));
 
     // Existing key should work
     let result = tree.get_item(&1);
@@ -1100,7 +1103,7 @@ fn test_remove_nonexistent_key_raises_error() {
 
     // Try to remove non-existent key
     let result = tree.remove_item(&3);
-    assert_eq!(result, Err(BPlusTreeError::KeyNotFound));
+    assert_eq!(result, Err(BPlusTreeError::Query(QueryError::KeyNotFound)));
 
     // Tree should be unchanged
     assert_eq!(tree.len(), 2);
@@ -1203,7 +1206,7 @@ fn test_iterate_from_key() {
         tree.insert(i, format!("value{}", i));
     }
 
-    let items: Vec<_> = tree.items_range(Some(&5), None).collect();
+    let items: Vec<_> = tree.range(5..).collect();
     assert_eq!(items.len(), 5); // keys 5, 6, 7, 8, 9
     for (i, (key, value)) in items.iter().enumerate() {
         let expected_key = i + 5;
@@ -1220,7 +1223,7 @@ fn test_iterate_until_key() {
         tree.insert(i, format!("value{}", i));
     }
 
-    let items: Vec<_> = tree.items_range(None, Some(&5)).collect();
+    let items: Vec<_> = tree.range(..5).collect();
     assert_eq!(items.len(), 5); // keys 0, 1, 2, 3, 4
     for (i, (key, value)) in items.iter().enumerate() {
         let expected_key = i;
@@ -1237,7 +1240,7 @@ fn test_iterate_range() {
         tree.insert(i, format!("value{}", i));
     }
 
-    let items: Vec<_> = tree.items_range(Some(&5), Some(&15)).collect();
+    let items: Vec<_> = tree.range(5..15).collect();
     assert_eq!(items.len(), 10); // keys 5, 6, 7, 8, 9, 10, 11, 12, 13, 14
     for (i, (key, value)) in items.iter().enumerate() {
         let expected_key = i + 5;
@@ -1255,7 +1258,7 @@ fn test_iterate_from_nonexistent_key() {
     }
 
     // Start from 4 (doesn't exist, should start from 5)
-    let items: Vec<_> = tree.items_range(Some(&4), None).collect();
+    let items: Vec<_> = tree.range(4..).collect();
     assert_eq!(items.len(), 3); // keys 5, 7, 9
     assert_eq!(*items[0].0, 5);
     assert_eq!(*items[1].0, 7);
@@ -1270,7 +1273,8 @@ fn test_iterate_empty_range() {
     }
 
     // Start after end (invalid range)
-    let items: Vec<_> = tree.items_range(Some(&7), Some(&3)).collect();
+    #[allow(clippy::reversed_empty_ranges)]
+    let items: Vec<_> = tree.range(7..3).collect();
     assert_eq!(items, vec![]);
 }
 
@@ -2056,19 +2060,19 @@ fn test_range_query_edge_cases() {
     }
 
     // Range that covers the entire tree
-    let all_items: Vec<_> = tree.items_range(None, None).collect();
+    let all_items: Vec<_> = tree.range(..).collect();
     assert_eq!(all_items.len(), 20);
 
     // Range that starts before the first key
-    let from_neg: Vec<_> = tree.items_range(Some(&-5), Some(&5)).collect();
+    let from_neg: Vec<_> = tree.range(-5..5).collect();
     assert_eq!(from_neg.len(), 5); // 0, 1, 2, 3, 4
 
     // Range that ends after the last key
-    let to_far: Vec<_> = tree.items_range(Some(&15), Some(&100)).collect();
+    let to_far: Vec<_> = tree.range(15..100).collect();
     assert_eq!(to_far.len(), 5); // 15, 16, 17, 18, 19
 
     // Range with no items
-    let no_items: Vec<_> = tree.items_range(Some(&25), Some(&30)).collect();
+    let no_items: Vec<_> = tree.range(25..30).collect();
     assert_eq!(no_items.len(), 0);
 }
 
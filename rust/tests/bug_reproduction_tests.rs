@@ -1,3 +1,5 @@
+#![cfg(feature = "validation")]
+
 /// Test cases to reproduce specific bugs found in the B+ tree implementation
 /// Each test demonstrates a concrete failure case for the identified issues
 // BPlusTreeMap import removed - using test_utils instead
@@ -256,13 +258,12 @@ fn test_root_collapse_edge_cases() {
 }
 
 #[test]
-#[should_panic(expected = "Arena ID collision")]
 fn test_arena_id_collision() {
     // This test is harder to trigger directly, but we can check for the.
     let tree = create_tree_4();
 
-    // The root should be at ID 0, and the first arena allocation should also try to use 0
-    // This creates potential confusion
+    // The root used to be at ID 0, where the first arena allocation also went.
+    // It now starts inline in the map, outside the arena
 
     // Test the ID collision by checking arena behavior
     let initial_leaf_stats = tree.leaf_arena_stats();
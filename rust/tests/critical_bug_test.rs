@@ -1,3 +1,5 @@
+#![cfg(feature = "validation")]
+
 /// Test to verify linked list integrity during merge operations
 /// These tests ensure proper linked list maintenance during deletions
 use bplustree::BPlusTreeMap;
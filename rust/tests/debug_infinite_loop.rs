@@ -1,3 +1,5 @@
+#![cfg(feature = "validation")]
+
 /// Debug test to find the infinite loop
 use bplustree::BPlusTreeMap;
 
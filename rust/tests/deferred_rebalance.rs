@@ -0,0 +1,181 @@
+#![cfg(feature = "validation")]
+
+use bplustree::{BPlusTreeMap, DeletionMode, NodeRef};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::BTreeMap;
+
+fn deferred_tree(capacity: usize, n: i32) -> BPlusTreeMap<i32, i32> {
+    let mut tree = BPlusTreeMap::new(capacity).unwrap();
+    for i in 0..n {
+        tree.insert(i, i * 10);
+    }
+    tree.set_deletion_mode(DeletionMode::DeferredRebalance);
+    tree
+}
+
+fn assert_matches(tree: &BPlusTreeMap<i32, i32>, map: &BTreeMap<i32, i32>, context: &str) {
+    if let Err(e) = tree.check_invariants_detailed() {
+        panic!("{}: invariants violated: {}", context, e);
+    }
+    let got: Vec<_> = tree.items().map(|(k, v)| (*k, *v)).collect();
+    let expected: Vec<_> = map.iter().map(|(k, v)| (*k, *v)).collect();
+    assert_eq!(got, expected, "{}", context);
+    assert_eq!(tree.len(), map.len(), "{}", context);
+}
+
+#[test]
+fn default_mode_is_eager() {
+    let tree: BPlusTreeMap<i32, i32> = BPlusTreeMap::new(4).unwrap();
+    assert_eq!(tree.deletion_mode(), DeletionMode::Eager);
+}
+
+#[test]
+fn deferred_remove_keeps_structure_until_vacuum() {
+    let mut tree = deferred_tree(4, 100);
+    let leaves_before = tree.leaf_count();
+
+    for i in 0..80 {
+        assert_eq!(tree.remove(&i), Some(i * 10));
+    }
+    assert_eq!(tree.remove(&0), None);
+
+    // No leaf was merged or freed, but the data is already gone.
+    assert_eq!(tree.leaf_count(), leaves_before);
+    assert_eq!(tree.free_leaf_count(), 0);
+    assert_eq!(tree.len(), 20);
+    assert_eq!(tree.get(&5), None);
+    assert_eq!(tree.get(&90), Some(&900));
+    assert_eq!(tree.first(), Some((&80, &800)));
+    let keys: Vec<_> = tree.keys().copied().collect();
+    assert_eq!(keys, (80..100).collect::<Vec<_>>());
+    let ranged: Vec<_> = tree.range(10..85).map(|(k, _)| *k).collect();
+    assert_eq!(ranged, vec![80, 81, 82, 83, 84]);
+    assert!(tree.check_invariants());
+
+    tree.vacuum();
+    assert!(tree.leaf_count() < leaves_before);
+    assert!(tree.free_leaf_count() > 0);
+    assert!(tree.leaf_sizes().iter().all(|&size| size >= 2));
+    assert_eq!(tree.len(), 20);
+    assert!(tree.validate().is_ok());
+}
+
+#[test]
+fn underfull_nodes_are_reported_until_vacuum() {
+    let mut tree = deferred_tree(6, 200);
+    assert_eq!(tree.min_keys_leaf(), 3);
+    assert_eq!(tree.min_keys_branch(), 3);
+    assert_eq!(tree.underfull_node_count(), (0, 0));
+
+    for i in (0..200).filter(|i| i % 3 != 0) {
+        tree.remove(&i);
+    }
+    let (underfull_leaves, _) = tree.underfull_node_count();
+    assert!(underfull_leaves > 0);
+
+    let first = tree.get_first_leaf_id().unwrap();
+    let first_len = tree.get_leaf(first).unwrap().keys_len();
+    let first_ref = NodeRef::leaf(first);
+    assert_eq!(
+        tree.is_node_underfull(&first_ref),
+        first_len < tree.min_keys_leaf()
+    );
+
+    tree.vacuum();
+    assert_eq!(tree.underfull_node_count(), (0, 0));
+    assert!(tree
+        .leaf_sizes()
+        .iter()
+        .all(|&size| size >= tree.min_keys_leaf()));
+}
+
+#[test]
+fn vacuum_drains_fully_emptied_tree() {
+    let mut tree = deferred_tree(5, 200);
+    for i in 0..200 {
+        tree.remove(&i);
+    }
+    assert!(tree.is_empty());
+    assert_eq!(tree.first(), None);
+    assert_eq!(tree.items().count(), 0);
+
+    tree.vacuum();
+    assert!(tree.is_empty());
+    assert!(tree.is_leaf_root());
+    assert!(tree.check_invariants());
+
+    tree.insert(7, 70);
+    assert_eq!(tree.get(&7), Some(&70));
+}
+
+#[test]
+fn vacuum_is_noop_on_well_formed_tree() {
+    let mut tree = deferred_tree(4, 50);
+    let sizes = tree.leaf_sizes();
+    tree.vacuum();
+    assert_eq!(tree.leaf_sizes(), sizes);
+}
+
+#[test]
+fn switching_to_eager_vacuums() {
+    let mut tree = deferred_tree(4, 64);
+    for i in (0..64).filter(|i| i % 4 != 0) {
+        tree.remove(&i);
+    }
+    tree.set_deletion_mode(DeletionMode::Eager);
+    assert_eq!(tree.deletion_mode(), DeletionMode::Eager);
+    assert!(tree.validate().is_ok());
+
+    // Eager removals continue from a valid tree.
+    for i in (0..64).step_by(8) {
+        assert_eq!(tree.remove(&i), Some(i * 10));
+        assert!(tree.check_invariants());
+    }
+    assert_eq!(tree.len(), 8);
+}
+
+#[test]
+fn random_deferred_workload_matches_btreemap() {
+    for &capacity in &[4, 5, 8, 16] {
+        let mut rng = StdRng::seed_from_u64(capacity as u64);
+        let mut tree = BPlusTreeMap::new(capacity).unwrap();
+        tree.set_deletion_mode(DeletionMode::DeferredRebalance);
+        let mut map = BTreeMap::new();
+
+        for round in 0..20 {
+            for _ in 0..200 {
+                let key = rng.gen_range(0..500);
+                if rng.gen_range(0..3) == 0 {
+                    assert_eq!(tree.insert(key, round), map.insert(key, round));
+                } else {
+                    assert_eq!(tree.remove(&key), map.remove(&key));
+                }
+            }
+            let context = format!("capacity {} round {}", capacity, round);
+            assert_matches(&tree, &map, &context);
+            if round % 5 == 4 {
+                tree.vacuum();
+                assert_matches(&tree, &map, &format!("{} after vacuum", context));
+                assert!(tree.validate().is_ok(), "{}", context);
+            }
+        }
+    }
+}
+
+#[test]
+fn leaf_slices_skip_emptied_leaves() {
+    let mut tree = deferred_tree(4, 200);
+    for i in 40..160 {
+        tree.remove(&i);
+    }
+    let slices = tree.leaf_slices();
+    assert!(slices
+        .iter()
+        .all(|(keys, values)| !keys.is_empty() && keys.len() == values.len()));
+    let keys: Vec<i32> = slices
+        .iter()
+        .flat_map(|(keys, _)| keys.iter().copied())
+        .collect();
+    let expected: Vec<i32> = (0..40).chain(160..200).collect();
+    assert_eq!(keys, expected);
+}
@@ -1,9 +1,11 @@
 //! Enhanced error handling tests
 //! These tests verify the improved error handling patterns, Result type aliases,
 //! and convenience methods for robust B+ tree operations
+#![cfg(feature = "validation")]
 
 use bplustree::{
     BPlusTreeError, BPlusTreeMap, BTreeResult, BTreeResultExt, InitResult, KeyResult, ModifyResult,
+    QueryError,
 };
 
 mod test_utils;
@@ -120,7 +122,8 @@ fn test_result_extension_trait() {
         .contains("Key not found"));
 
     // Test or_default_with_log for types that implement Default
-    let result: Result<Vec<String>, BPlusTreeError> = Err(BPlusTreeError::KeyNotFound);
+    let result: Result<Vec<String>, BPlusTreeError> =
+        Err(BPlusTreeError::Query(QueryError::KeyNotFound));
     let default_value = result.or_default_with_log();
     assert_eq!(default_value, Vec::<String>::new());
 
@@ -305,7 +308,7 @@ fn test_integration_with_existing_api() {
     // Use old get with new error handling
     let value = tree
         .get(&1)
-        .ok_or(BPlusTreeError::KeyNotFound)
+        .ok_or(BPlusTreeError::Query(QueryError::KeyNotFound))
         .with_context("Mixed API usage");
     assert!(value.is_ok());
 
@@ -332,7 +335,8 @@ fn test_error_recovery_patterns() {
     assert_eq!(value, &fallback);
 
     // Test error logging with or_default_with_log
-    let result: Result<Vec<String>, BPlusTreeError> = Err(BPlusTreeError::KeyNotFound);
+    let result: Result<Vec<String>, BPlusTreeError> =
+        Err(BPlusTreeError::Query(QueryError::KeyNotFound));
     let default_vec = result.or_default_with_log();
     assert!(default_vec.is_empty());
 
@@ -1,7 +1,8 @@
 //! Error handling consistency tests
 //! These tests verify that the B+ tree implementation uses consistent error handling patterns
+#![cfg(feature = "validation")]
 
-use bplustree::{BPlusTreeError, BPlusTreeMap};
+use bplustree::{BPlusTreeError, BPlusTreeMap, CapacityError, CorruptionError, QueryError};
 
 mod test_utils;
 use test_utils::*;
@@ -19,8 +20,8 @@ fn test_public_api_error_consistency() {
     );
 
     match invalid_tree {
-        Err(BPlusTreeError::InvalidCapacity(_)) => {
-            println!("✅ Constructor returns proper InvalidCapacity error");
+        Err(BPlusTreeError::Capacity(CapacityError::TooSmall { minimum: 4, .. })) => {
+            println!("✅ Constructor returns proper CapacityTooSmall error");
         }
         Err(other) => panic!("Wrong error type: {:?}", other),
         Ok(_) => panic!("Should have failed with invalid capacity"),
@@ -37,7 +38,7 @@ fn test_public_api_error_consistency() {
     );
 
     match missing_key_result {
-        Err(BPlusTreeError::KeyNotFound) => {
+        Err(BPlusTreeError::Query(QueryError::KeyNotFound)) => {
             println!("✅ get_item returns proper KeyNotFound error");
         }
         Err(other) => panic!("Wrong error type: {:?}", other),
@@ -52,7 +53,7 @@ fn test_public_api_error_consistency() {
     );
 
     match remove_missing_result {
-        Err(BPlusTreeError::KeyNotFound) => {
+        Err(BPlusTreeError::Query(QueryError::KeyNotFound)) => {
             println!("✅ remove_item returns proper KeyNotFound error");
         }
         Err(other) => panic!("Wrong error type: {:?}", other),
@@ -68,14 +69,14 @@ fn test_error_message_formatting() {
     println!("=== ERROR MESSAGE FORMATTING TEST ===");
 
     let errors = vec![
-        BPlusTreeError::KeyNotFound,
-        BPlusTreeError::InvalidCapacity("capacity too small".to_string()),
-        BPlusTreeError::DataIntegrityError("corruption detected".to_string()),
-        BPlusTreeError::ArenaError("allocation failed".to_string()),
-        BPlusTreeError::NodeError("node not found".to_string()),
-        BPlusTreeError::CorruptedTree("tree structure invalid".to_string()),
-        BPlusTreeError::InvalidState("invalid operation".to_string()),
-        BPlusTreeError::AllocationError("out of memory".to_string()),
+        BPlusTreeError::Query(QueryError::KeyNotFound),
+        CapacityError::Invalid("capacity too small".to_string()).into(),
+        CorruptionError::DataIntegrity("corruption detected".to_string()).into(),
+        CorruptionError::Arena("allocation failed".to_string()).into(),
+        CorruptionError::Node("node not found".to_string()).into(),
+        CorruptionError::Tree("tree structure invalid".to_string()).into(),
+        QueryError::InvalidState("invalid operation".to_string()).into(),
+        CapacityError::Allocation("out of memory".to_string()).into(),
     ];
 
     for error in errors {
@@ -103,6 +104,38 @@ fn test_error_message_formatting() {
     println!("✅ Error message formatting verified");
 }
 
+/// Test that each category reports itself through the helpers
+#[test]
+fn test_error_categories() {
+    let errors: Vec<(BPlusTreeError, bool)> = vec![
+        (BPlusTreeError::Query(QueryError::KeyNotFound), false),
+        (QueryError::InvalidState("busy".to_string()).into(), false),
+        (
+            CapacityError::Invalid("too small".to_string()).into(),
+            false,
+        ),
+        (
+            CapacityError::Allocation("out of memory".to_string()).into(),
+            false,
+        ),
+        (
+            CorruptionError::Arena("slot freed".to_string()).into(),
+            true,
+        ),
+        (CorruptionError::Tree("leaf chain".to_string()).into(), true),
+    ];
+
+    for (error, corruption) in errors {
+        assert_eq!(error.is_corruption(), corruption, "{}", error);
+    }
+
+    assert!(BPlusTreeError::corrupted_tree("leaf", "dangling").is_corruption());
+    assert!(!BPlusTreeError::Query(QueryError::KeyNotFound).is_corruption());
+    assert!(BPlusTreeMap::<i32, i32>::new(1)
+        .unwrap_err()
+        .is_capacity_error());
+}
+
 /// Test that operations handle edge cases gracefully
 #[test]
 fn test_edge_case_error_handling() {
@@ -0,0 +1,61 @@
+//! `try_items` and `try_range` must report a broken leaf chain where `items`
+//! and `range` stop quietly.
+#![cfg(feature = "validation")]
+
+use bplustree::{BPlusTreeError, CorruptionError};
+
+mod test_utils;
+use test_utils::*;
+
+#[test]
+fn test_intact_tree_yields_only_ok() {
+    let tree = create_tree_4_int_with_data(100);
+    let items: Vec<_> = tree.try_items().collect::<Result<_, _>>().unwrap();
+    assert_eq!(items, tree.items().collect::<Vec<_>>());
+
+    let range: Vec<_> = tree.try_range(10..=20).collect::<Result<_, _>>().unwrap();
+    assert_eq!(range, tree.range(10..=20).collect::<Vec<_>>());
+
+    let empty = create_tree_4_int();
+    assert_eq!(empty.try_items().count(), 0);
+    assert_eq!(empty.try_range(3..).count(), 0);
+}
+
+#[test]
+fn test_missing_leaf_in_chain_is_reported() {
+    let mut tree = create_tree_4_int_with_data(100);
+    let first = tree.get_first_leaf_id().unwrap();
+    let victim = (0..)
+        .find(|&id| id != first && tree.get_leaf(id).is_some())
+        .unwrap();
+    tree.deallocate_leaf(victim);
+
+    // The plain iterator ends early without saying why
+    assert!(tree.items().count() < 100);
+
+    let results: Vec<_> = tree.try_items().collect();
+    let (last, entries) = results.split_last().unwrap();
+    assert!(entries.iter().all(Result::is_ok));
+    match last {
+        Err(BPlusTreeError::Corruption(CorruptionError::Tree(message))) => {
+            assert!(message.contains(&format!("leaf {}", victim)), "{}", message)
+        }
+        other => panic!("expected a corruption error, got {:?}", other),
+    }
+    assert!(tree.try_range(0..).any(|entry| entry.is_err()));
+}
+
+#[test]
+fn test_missing_start_leaf_is_reported() {
+    let mut tree = create_tree_4_int_with_data(100);
+    let first = tree.get_first_leaf_id().unwrap();
+    tree.deallocate_leaf(first);
+
+    assert_eq!(tree.range(..5).count(), 0);
+    let mut range = tree.try_range(..5);
+    assert!(matches!(
+        range.next(),
+        Some(Err(BPlusTreeError::Corruption(CorruptionError::Tree(_))))
+    ));
+    assert!(range.next().is_none());
+}
@@ -6,6 +6,7 @@
 //! - All fuzz tests: `cargo test --test fuzz_tests -- --ignored`
 //! - Specific test: `cargo test fuzz_test_bplustree -- --ignored --nocapture`
 //! - With custom timing: `FUZZ_TIME=30s cargo test fuzz_test_timed -- --ignored --nocapture`
+#![cfg(feature = "validation")]
 
 use bplustree::BPlusTreeMap;
 use std::collections::{BTreeMap, HashSet};
@@ -0,0 +1,127 @@
+#![cfg(feature = "validation")]
+
+use bplustree::{BPlusTreeMap, OverflowMode, MAX_LEAF_GROWTH};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::BTreeMap;
+
+fn spread_tree(capacity: usize) -> BPlusTreeMap<u64, u64> {
+    let mut tree = BPlusTreeMap::new(capacity).unwrap();
+    for i in 0..2_000 {
+        tree.insert(i * 1_000, i);
+    }
+    tree
+}
+
+fn assert_matches(tree: &BPlusTreeMap<u64, u64>, map: &BTreeMap<u64, u64>, context: &str) {
+    if let Err(e) = tree.check_invariants_detailed() {
+        panic!("{}: invariants violated: {}", context, e);
+    }
+    assert!(tree
+        .items()
+        .map(|(k, v)| (*k, *v))
+        .eq(map.iter().map(|(k, v)| (*k, *v))));
+    assert_eq!(tree.len(), map.len(), "{}", context);
+}
+
+#[test]
+fn default_mode_is_split() {
+    let tree: BPlusTreeMap<u64, u64> = BPlusTreeMap::new(8).unwrap();
+    assert_eq!(tree.overflow_mode(), OverflowMode::Split);
+}
+
+#[test]
+fn hot_range_burst_keeps_branches_stable() {
+    let mut split = spread_tree(8);
+    let mut grown = spread_tree(8);
+    grown.set_overflow_mode(OverflowMode::GrowLeaves { factor: 8 });
+    let (_, branches_before) = grown.count_nodes_in_tree();
+
+    // Every key lands between two neighbouring existing keys
+    for i in 1..50 {
+        split.insert(1_000_000 + i, i);
+        grown.insert(1_000_000 + i, i);
+    }
+    assert!(grown.check_invariants());
+    assert_eq!(grown.count_nodes_in_tree().1, branches_before);
+    assert!(grown.leaf_count() < split.leaf_count());
+    assert!(grown.items().eq(split.items()));
+}
+
+#[test]
+fn grown_leaves_still_split_at_the_growth_limit() {
+    let mut tree = BPlusTreeMap::new(4).unwrap();
+    tree.set_overflow_mode(OverflowMode::GrowLeaves { factor: 3 });
+    for i in 0..1_000u64 {
+        tree.insert(i, i);
+    }
+    tree.check_invariants_detailed().unwrap();
+    assert!(tree.leaf_sizes().into_iter().all(|len| len <= 12));
+    assert!(tree.leaf_count() > 1_000 / 12);
+}
+
+#[test]
+fn growth_factor_is_clamped() {
+    let mut tree = BPlusTreeMap::new(4).unwrap();
+    tree.set_overflow_mode(OverflowMode::GrowLeaves { factor: usize::MAX });
+    for i in 0..1_000u64 {
+        tree.insert(i, i);
+    }
+    tree.check_invariants_detailed().unwrap();
+    let limit = 4 * MAX_LEAF_GROWTH;
+    assert!(tree.leaf_sizes().into_iter().all(|len| len <= limit));
+    assert!(tree.leaf_count() > 1_000 / limit);
+
+    // A factor of zero behaves like one, which is the same as splitting
+    let mut tree = BPlusTreeMap::new(4).unwrap();
+    tree.set_overflow_mode(OverflowMode::GrowLeaves { factor: 0 });
+    for i in 0..100u64 {
+        tree.insert(i, i);
+    }
+    assert!(tree.leaf_sizes().into_iter().all(|len| len <= 4));
+}
+
+#[test]
+fn vacuum_splits_grown_leaves() {
+    let mut tree = spread_tree(8);
+    tree.set_overflow_mode(OverflowMode::GrowLeaves { factor: 5 });
+    for i in 1..40 {
+        tree.insert(500_000 + i, i);
+    }
+    assert!(tree.leaf_sizes().into_iter().any(|len| len > 8));
+
+    tree.set_overflow_mode(OverflowMode::Split);
+    tree.vacuum();
+    tree.check_invariants_detailed().unwrap();
+    assert!(tree.leaf_sizes().into_iter().all(|len| len <= 8));
+    assert_eq!(tree.len(), 2_039);
+}
+
+#[test]
+fn grow_leaves_mode_matches_model() {
+    let mut tree = BPlusTreeMap::new(4).unwrap();
+    tree.set_overflow_mode(OverflowMode::GrowLeaves { factor: 4 });
+    let mut map = BTreeMap::new();
+    let mut rng = StdRng::seed_from_u64(11);
+    for step in 0..5_000u64 {
+        // Most operations hit one narrow range
+        let key = if rng.gen_range(0..4) == 0 {
+            rng.gen_range(0..10_000)
+        } else {
+            5_000 + rng.gen_range(0..50)
+        };
+        if rng.gen_range(0..3) == 0 {
+            assert_eq!(tree.remove(&key), map.remove(&key), "remove {}", key);
+        } else {
+            assert_eq!(
+                tree.insert(key, step),
+                map.insert(key, step),
+                "insert {}",
+                key
+            );
+        }
+    }
+    assert_matches(&tree, &map, "after mixed operations");
+
+    tree.vacuum();
+    assert_matches(&tree, &map, "after vacuum");
+}
@@ -0,0 +1,150 @@
+//! Keys whose `Ord` breaks its contract. Debug builds must report the first
+//! contradiction; release builds must keep going without panicking.
+
+use bplustree::BPlusTreeMap;
+use std::cell::Cell;
+use std::cmp::Ordering;
+
+thread_local! {
+    static STATE: Cell<u64> = const { Cell::new(7) };
+}
+
+fn next_random() -> u64 {
+    STATE.with(|state| {
+        let next = state
+            .get()
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        state.set(next);
+        next >> 33
+    })
+}
+
+/// Compares by value most of the time and at random otherwise.
+#[derive(Clone, Debug)]
+struct Unreliable(u32);
+
+impl Ord for Unreliable {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match next_random() % 8 {
+            0 => Ordering::Less,
+            1 => Ordering::Greater,
+            _ => self.0.cmp(&other.0),
+        }
+    }
+}
+
+impl PartialOrd for Unreliable {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Unreliable {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Unreliable {}
+
+fn exercise(tree: &mut BPlusTreeMap<Unreliable, u32>) {
+    for i in 0..20_000 {
+        let key = Unreliable((next_random() % 500) as u32);
+        match i % 5 {
+            0 | 1 => {
+                tree.insert(key, i);
+            }
+            2 => {
+                tree.remove(&key);
+            }
+            3 => {
+                tree.get(&key);
+            }
+            _ => {
+                let end = Unreliable(key.0 + 50);
+                tree.range(key..end).count();
+            }
+        }
+    }
+    tree.items().count();
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "inconsistent `Ord`")]
+fn test_debug_builds_report_inconsistent_ord() {
+    let mut tree = BPlusTreeMap::new(4).unwrap();
+    exercise(&mut tree);
+}
+
+#[test]
+#[cfg(not(debug_assertions))]
+fn test_release_builds_survive_inconsistent_ord() {
+    for capacity in [4, 16, 64] {
+        let mut tree = BPlusTreeMap::new(capacity).unwrap();
+        exercise(&mut tree);
+        assert!(tree.len() <= 20_000);
+        tree.clear();
+        assert!(tree.is_empty());
+    }
+}
+
+/// No two keys are ever equal, not even a key and itself, and every key is
+/// less than every other.
+#[cfg(not(debug_assertions))]
+#[derive(Clone, Debug)]
+struct NeverEqual(u32);
+
+#[cfg(not(debug_assertions))]
+impl Ord for NeverEqual {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self == other {
+            Ordering::Equal
+        } else {
+            Ordering::Less
+        }
+    }
+}
+
+#[cfg(not(debug_assertions))]
+impl PartialOrd for NeverEqual {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(not(debug_assertions))]
+impl PartialEq for NeverEqual {
+    fn eq(&self, _: &Self) -> bool {
+        false
+    }
+}
+
+#[cfg(not(debug_assertions))]
+impl Eq for NeverEqual {}
+
+#[test]
+#[cfg(not(debug_assertions))]
+fn test_or_insert_with_returns_inserted_slot_despite_broken_eq() {
+    // A full capacity-4 root leaf, split by a key at the front, middle or end
+    for new_key in [5, 25, 50] {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for key in [10, 20, 30, 40] {
+            tree.insert(NeverEqual(key), vec![key]);
+        }
+        tree.locate(NeverEqual(new_key))
+            .or_insert_with(|| vec![42])
+            .push(new_key);
+        let stored: Vec<(u32, Vec<u32>)> = tree.items().map(|(k, v)| (k.0, v.clone())).collect();
+        assert_eq!(stored.len(), 5);
+        for (key, value) in stored {
+            let expected = if key == new_key {
+                vec![42, new_key]
+            } else {
+                vec![key]
+            };
+            assert_eq!(value, expected, "new key {}", new_key);
+        }
+    }
+}
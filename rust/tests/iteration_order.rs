@@ -0,0 +1,216 @@
+//! The ordering contract: iteration is strictly ascending, equal keys never
+//! coexist, and updating an equal key keeps the key that was stored.
+#![cfg(feature = "validation")]
+
+use bplustree::{BPlusTreeMap, ByteBudget, EvictFrom, WriteBatch};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// A key ordered by `rank` alone, with a `tag` that shows which of several
+/// equal keys the tree kept.
+#[derive(Debug, Clone, Copy)]
+struct Tagged {
+    rank: u32,
+    tag: u32,
+}
+
+impl PartialEq for Tagged {
+    fn eq(&self, other: &Self) -> bool {
+        self.rank == other.rank
+    }
+}
+
+impl Eq for Tagged {}
+
+impl PartialOrd for Tagged {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Tagged {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank.cmp(&other.rank)
+    }
+}
+
+fn key(rank: u32, tag: u32) -> Tagged {
+    Tagged { rank, tag }
+}
+
+fn assert_strictly_ascending<'a>(keys: impl Iterator<Item = &'a u32>) {
+    let keys: Vec<_> = keys.collect();
+    assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+fn assert_all_views_ascending(tree: &BPlusTreeMap<u32, u32>) {
+    assert_strictly_ascending(tree.keys());
+    assert_strictly_ascending(tree.items().map(|(k, _)| k));
+    assert_strictly_ascending(tree.range(100..900).map(|(k, _)| k));
+    assert_strictly_ascending(tree.group_by_leaf().flat_map(|(keys, _)| keys));
+}
+
+#[test]
+fn test_iteration_is_strictly_ascending_through_every_mutation() {
+    for capacity in [4, 5, 16, 64] {
+        let mut tree = BPlusTreeMap::new(capacity).unwrap();
+        let mut other = BPlusTreeMap::new(capacity).unwrap();
+        let mut model = BTreeMap::new();
+        let mut state = capacity as u64;
+        let mut next = || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((state >> 33) % 1_000) as u32
+        };
+
+        for round in 0..20 {
+            for _ in 0..200 {
+                let k = next();
+                tree.insert(k, round);
+                model.insert(k, round);
+            }
+            for _ in 0..50 {
+                let k = next();
+                assert_eq!(tree.remove(&k), model.remove(&k));
+            }
+            let mut batch = WriteBatch::new();
+            for _ in 0..50 {
+                let k = next();
+                batch.insert(k, round).remove(next());
+            }
+            tree.apply_batch(batch.clone());
+            for op in batch.ops() {
+                match op {
+                    bplustree::BatchOp::Insert(k, v) => model.insert(*k, *v),
+                    bplustree::BatchOp::Remove(k) => model.remove(k),
+                };
+            }
+            assert_all_views_ascending(&tree);
+
+            for (k, _) in tree.remove_min_k(5) {
+                model.remove(&k);
+            }
+            for (k, _) in tree.evict_to_len(model.len().saturating_sub(5), EvictFrom::Tail) {
+                model.remove(&k);
+            }
+            let start = next();
+            if tree.move_range(start..start + 20, &mut other).is_ok() {
+                other.move_range(start..start + 20, &mut tree).unwrap();
+            }
+            tree.coalesce_leaves();
+            assert_all_views_ascending(&tree);
+            assert!(tree
+                .items()
+                .map(|(k, v)| (*k, *v))
+                .eq(model.iter().map(|(k, v)| (*k, *v))));
+        }
+    }
+}
+
+#[test]
+fn test_equal_key_updates_keep_the_stored_key() {
+    let mut tree = BPlusTreeMap::new(4).unwrap();
+    let mut model = BTreeMap::new();
+    for rank in 0..100 {
+        tree.insert(key(rank, 0), rank);
+        model.insert(key(rank, 0), rank);
+    }
+
+    // Point inserts, batches and merges all replace the value only
+    for rank in (0..100).step_by(3) {
+        assert_eq!(
+            tree.insert(key(rank, 1), 1_000),
+            model.insert(key(rank, 1), 1_000)
+        );
+    }
+    let mut batch = WriteBatch::new();
+    for rank in (1..100).step_by(3) {
+        batch.insert(key(rank, 2), 2_000);
+        model.insert(key(rank, 2), 2_000);
+    }
+    tree.apply_batch(batch);
+    for rank in (2..100).step_by(3) {
+        tree.merge_value(key(rank, 3), 3_000, |stored, new| *stored = new);
+        model.insert(key(rank, 3), 3_000);
+    }
+
+    assert_eq!(tree.len(), 100);
+    assert!(tree.keys().all(|k| k.tag == 0));
+    assert!(tree
+        .items()
+        .map(|(k, v)| (k.rank, k.tag, *v))
+        .eq(model.iter().map(|(k, v)| (k.rank, k.tag, *v))));
+}
+
+#[test]
+fn test_byte_budget_regrowth_keeps_the_stored_key() {
+    let mut tree = BPlusTreeMap::new(16).unwrap();
+    tree.set_byte_budget(Some(ByteBudget::new(64, |_: &Tagged, value: &String| {
+        value.len()
+    })));
+    for rank in 0..40 {
+        tree.insert(key(rank, 0), "x".repeat(8));
+    }
+    // Heavier values force their leaves to split
+    for rank in 0..40 {
+        tree.insert(key(rank, 1), "x".repeat(30));
+    }
+    assert!(tree.keys().all(|k| k.tag == 0));
+    assert!(tree.keys().map(|k| k.rank).eq(0..40));
+    assert!(tree.check_invariants());
+}
+
+#[cfg(all(feature = "order-checks", debug_assertions))]
+mod order_checks {
+    use bplustree::BPlusTreeMap;
+    use std::cell::Cell;
+    use std::cmp::Ordering;
+    use std::rc::Rc;
+
+    /// A key the test can change while the tree holds it.
+    #[derive(Clone)]
+    struct Shared(Rc<Cell<u32>>);
+
+    impl PartialEq for Shared {
+        fn eq(&self, other: &Self) -> bool {
+            self.0.get() == other.0.get()
+        }
+    }
+
+    impl Eq for Shared {}
+
+    impl PartialOrd for Shared {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Shared {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.0.get().cmp(&other.0.get())
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "keys out of order")]
+    fn test_mutated_key_is_caught_while_iterating() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        let cells: Vec<_> = (0..100).map(|i| Rc::new(Cell::new(i))).collect();
+        for cell in &cells {
+            tree.insert(Shared(Rc::clone(cell)), ());
+        }
+        cells[50].set(0);
+        tree.keys().for_each(drop);
+    }
+
+    #[test]
+    fn test_intact_tree_passes_checks() {
+        let mut tree = BPlusTreeMap::new(4).unwrap();
+        for i in 0..1_000 {
+            tree.insert(i, i);
+        }
+        assert_eq!(tree.items().count(), 1_000);
+        assert_eq!(tree.range(10..500).count(), 490);
+    }
+}
@@ -0,0 +1,94 @@
+#![cfg(feature = "validation")]
+
+use bplustree::{BPlusTreeMap, DeletionMode};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::BTreeMap;
+
+mod test_utils;
+use test_utils::*;
+
+fn churned_tree(
+    capacity: usize,
+    mode: DeletionMode,
+) -> (BPlusTreeMap<i32, i32>, BTreeMap<i32, i32>) {
+    let mut tree = create_tree_capacity_int(capacity);
+    let mut map = BTreeMap::new();
+    tree.set_deletion_mode(mode);
+    let mut rng = StdRng::seed_from_u64(17);
+    for i in 0..6_000 {
+        let key = rng.gen_range(0..2_000);
+        if rng.gen_range(0..5) < 2 {
+            tree.remove(&key);
+            map.remove(&key);
+        } else {
+            tree.insert(key, i);
+            map.insert(key, i);
+        }
+    }
+    (tree, map)
+}
+
+#[test]
+fn test_coalesce_after_churn_preserves_contents() {
+    for &capacity in &[4, 5, 16] {
+        for mode in [DeletionMode::Eager, DeletionMode::DeferredRebalance] {
+            let (mut tree, map) = churned_tree(capacity, mode);
+            let before = tree.leaf_count();
+            let released = tree.coalesce_leaves();
+
+            let context = format!("capacity {} {:?}", capacity, mode);
+            assert_full_validation_int(&tree, &context);
+            assert_eq!(tree.leaf_count(), before - released, "{}", context);
+            assert!(
+                tree.items().map(|(k, v)| (*k, *v)).eq(map.clone()),
+                "{}",
+                context
+            );
+
+            // No two sibling leaves that fit together are left behind
+            assert_eq!(tree.coalesce_leaves(), 0, "{}", context);
+        }
+    }
+}
+
+#[test]
+fn test_coalesce_packs_leaves_left_sparse_by_deferred_rebalancing() {
+    let mut tree = create_tree_capacity_int(8);
+    insert_sequential_range_int(&mut tree, 4_000);
+    tree.set_deletion_mode(DeletionMode::DeferredRebalance);
+    for i in (0..4_000).filter(|i| i % 8 != 0) {
+        tree.remove(&i);
+    }
+    let before = tree.leaf_count();
+
+    tree.coalesce_leaves();
+    assert_full_validation_int(&tree, "after coalescing");
+    assert_eq!(tree.len(), 500);
+    assert!(tree.leaf_count() * 4 < before);
+}
+
+#[test]
+fn test_coalesce_in_range_only_touches_that_range() {
+    let mut tree = create_tree_capacity_int(8);
+    insert_sequential_range_int(&mut tree, 4_000);
+    tree.set_deletion_mode(DeletionMode::DeferredRebalance);
+    for i in (0..4_000).filter(|i| i % 8 != 0) {
+        tree.remove(&i);
+    }
+    let sizes_before = tree.leaf_sizes();
+
+    assert!(tree.coalesce_leaves_in(1_000..2_000) > 0);
+    assert_full_validation_int(&tree, "after range coalescing");
+    assert!(tree.keys().copied().eq((0..4_000).step_by(8)));
+
+    // Leaves well outside the range keep their original sizes
+    let sizes_after = tree.leaf_sizes();
+    assert_eq!(sizes_after[..50], sizes_before[..50]);
+    assert_eq!(
+        sizes_after[sizes_after.len() - 50..],
+        sizes_before[sizes_before.len() - 50..]
+    );
+
+    let mut empty = create_tree_capacity_int(4);
+    assert_eq!(empty.coalesce_leaves_in(..), 0);
+}
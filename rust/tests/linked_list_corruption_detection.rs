@@ -1,5 +1,6 @@
 //! Linked list integrity verification tests
 //! These tests verify proper linked list maintenance during merge operations
+#![cfg(feature = "validation")]
 
 mod test_utils;
 use test_utils::*;
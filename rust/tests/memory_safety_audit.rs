@@ -1,5 +1,6 @@
 //! Memory safety audit tests
 //! These tests verify that all type conversions are properly bounds-checked
+#![cfg(feature = "validation")]
 
 use bplustree::BPlusTreeMap;
 
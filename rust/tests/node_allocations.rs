@@ -0,0 +1,89 @@
+//! Counts heap allocations made while filling a tree, to check that node
+//! vectors are sized once when the node is created and never reallocated.
+//! With the `smallvec` feature small nodes allocate nothing, so the counts
+//! below do not apply.
+#![cfg(all(feature = "validation", not(feature = "smallvec")))]
+
+use bplustree::BPlusTreeMap;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static REALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = REALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Allocations and reallocations made on this thread while running `f`.
+fn count_allocations(f: impl FnOnce()) -> (usize, usize) {
+    let before = (ALLOCATIONS.get(), REALLOCATIONS.get());
+    f();
+    (ALLOCATIONS.get() - before.0, REALLOCATIONS.get() - before.1)
+}
+
+fn fill(keys: impl Iterator<Item = u64>, count: usize) {
+    let capacity = 16;
+    let mut tree = BPlusTreeMap::new(capacity).unwrap();
+    tree.reserve_keys_hint(count);
+
+    let (allocations, reallocations) = count_allocations(|| {
+        for key in keys.take(count) {
+            tree.insert(key, key);
+        }
+    });
+
+    // Every node after the first costs exactly its two vectors
+    let (leaves, branches) = tree.count_nodes_in_tree();
+    assert_eq!(reallocations, 0);
+    assert_eq!(allocations, 2 * (leaves - 1 + branches));
+    assert!(tree.check_invariants());
+}
+
+#[test]
+fn test_filling_a_node_does_not_allocate() {
+    let mut tree = BPlusTreeMap::new(32).unwrap();
+    let (allocations, reallocations) = count_allocations(|| {
+        for i in 0..32 {
+            tree.insert(i, i);
+        }
+    });
+    assert_eq!((allocations, reallocations), (0, 0));
+}
+
+#[test]
+fn test_sequential_inserts_allocate_only_new_nodes() {
+    fill(0.., 50_000);
+    fill((0..50_000).rev(), 50_000);
+}
+
+#[test]
+fn test_random_inserts_allocate_only_new_nodes() {
+    let mut state: u64 = 3;
+    let keys = std::iter::repeat_with(move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        state >> 16
+    });
+    fill(keys, 50_000);
+}
@@ -0,0 +1,109 @@
+//! Golden files for the portable format shared with the Python package.
+//!
+//! The files in `testdata/portable` at the repository root are also checked
+//! by `python/tests/test_portable.py`; both implementations must read them
+//! and write them back byte for byte.
+
+use bplustree::{BPlusTreeMap, Portable, PortableValue};
+use std::fmt::Debug;
+use std::path::PathBuf;
+
+fn golden(name: &str) -> Vec<u8> {
+    let path: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "..",
+        "testdata",
+        "portable",
+        name,
+    ]
+    .iter()
+    .collect();
+    std::fs::read(&path).unwrap_or_else(|e| panic!("reading {}: {}", path.display(), e))
+}
+
+/// Check that `name` reads back as `entries` and that a tree of `capacity`
+/// holding them writes exactly the file's bytes.
+fn check_golden<K, V>(name: &str, capacity: usize, entries: Vec<(K, V)>)
+where
+    K: Ord + Clone + Debug + Portable,
+    V: Clone + PartialEq + Debug + Portable,
+{
+    let bytes = golden(name);
+    let tree = BPlusTreeMap::<K, V>::read_portable(&bytes[..]).unwrap();
+    assert_eq!(tree.capacity(), capacity, "{}", name);
+    let read: Vec<_> = tree.items().map(|(k, v)| (k.clone(), v.clone())).collect();
+    assert_eq!(read, entries, "{}", name);
+
+    let mut built = BPlusTreeMap::new(capacity).unwrap();
+    for (key, value) in entries {
+        built.insert(key, value);
+    }
+    let mut written = Vec::new();
+    built.write_portable(&mut written).unwrap();
+    assert_eq!(written, bytes, "{}", name);
+}
+
+#[test]
+fn test_empty_golden_file() {
+    check_golden::<i64, i64>("empty.bpt", 16, Vec::new());
+}
+
+#[test]
+fn test_int_str_golden_file() {
+    let mut keys = vec![i64::MIN, -1, 0, 1, i64::MAX];
+    keys.extend(10..40);
+    keys.sort();
+    let entries = keys
+        .into_iter()
+        .map(|k| {
+            let value = match k {
+                0 => String::new(),
+                1 => "naïve ✓".to_string(),
+                _ => format!("value {}", k),
+            };
+            (k, value)
+        })
+        .collect();
+    check_golden("int_str.bpt", 4, entries);
+}
+
+#[test]
+fn test_str_bytes_golden_file() {
+    let entries = ["", "a", "apple", "b", "ζ"]
+        .iter()
+        .map(|k| {
+            let mut value = k.as_bytes().to_vec();
+            value.extend_from_slice(&[0x00, 0xFF]);
+            (k.to_string(), value)
+        })
+        .collect();
+    check_golden("str_bytes.bpt", 8, entries);
+}
+
+#[test]
+fn test_bytes_mixed_golden_file() {
+    let entries = vec![
+        (vec![], PortableValue::Int(7)),
+        (vec![0x00], PortableValue::Str("seven".to_string())),
+        (vec![0x00, 0x01], PortableValue::Bytes(vec![0x07])),
+        (vec![0xFF], PortableValue::Int(-7)),
+    ];
+    check_golden("bytes_mixed.bpt", 5, entries);
+}
+
+#[test]
+fn test_golden_files_read_as_portable_values() {
+    // Any file reads with dynamically typed keys and values
+    for name in [
+        "empty.bpt",
+        "int_str.bpt",
+        "str_bytes.bpt",
+        "bytes_mixed.bpt",
+    ] {
+        let bytes = golden(name);
+        let tree = BPlusTreeMap::<PortableValue, PortableValue>::read_portable(&bytes[..]).unwrap();
+        let mut written = Vec::new();
+        tree.write_portable(&mut written).unwrap();
+        assert_eq!(written, bytes, "{}", name);
+    }
+}
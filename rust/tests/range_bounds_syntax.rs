@@ -197,3 +197,19 @@ fn test_range_syntax_excluded_start() {
     let result: Vec<_> = tree.range(range).map(|(k, _)| *k).collect();
     assert_eq!(result, vec![4, 5, 6]); // 3 is excluded
 }
+
+#[test]
+#[allow(deprecated)]
+fn test_items_range_shim_matches_range() {
+    let mut tree = BPlusTreeMap::new(4).unwrap();
+    for i in 0..50 {
+        tree.insert(i * 2, i);
+    }
+
+    assert!(tree
+        .items_range(Some(&10), Some(&30))
+        .eq(tree.range(10..30)));
+    assert!(tree.items_range(Some(&11), None).eq(tree.range(11..)));
+    assert!(tree.items_range(None, Some(&7)).eq(tree.range(..7)));
+    assert!(tree.items_range(None, None).eq(tree.range(..)));
+}
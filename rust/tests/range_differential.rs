@@ -99,3 +99,389 @@ fn test_range_differential_gaps_and_nonexistent_bounds() {
         // Intentionally avoid inverted ranges: std::BTreeMap panics for start > end
     }
 }
+
+#[test]
+fn test_count_and_collect_range_owned_match_btreemap() {
+    use std::ops::Bound::{self, Excluded, Included, Unbounded};
+
+    let data: Vec<i32> = (0..300).map(|i| i * 3).collect();
+    for &cap in &[4_usize, 5, 16] {
+        let (tree, map) = populate_maps(cap, &data);
+        let points = [-5, 0, 1, 3, 299, 450, 451, 897, 898, 1000];
+        let bounds = |p: i32| [Included(p), Excluded(p), Unbounded];
+
+        for &lo in &points {
+            for &hi in &points {
+                if lo > hi {
+                    continue;
+                }
+                for start in bounds(lo) {
+                    for end in bounds(hi) {
+                        if lo == hi && start == Excluded(lo) && end == Excluded(hi) {
+                            continue; // BTreeMap panics on this range
+                        }
+                        let range: (Bound<i32>, Bound<i32>) = (start, end);
+                        let expected: Vec<_> = map.range(range).map(|(k, v)| (*k, *v)).collect();
+                        assert_eq!(
+                            tree.collect_range_owned(range),
+                            expected,
+                            "cap={} range={:?}",
+                            cap,
+                            range
+                        );
+                        assert_eq!(tree.count_range(range), expected.len());
+                    }
+                }
+            }
+        }
+
+        // Inverted ranges are empty rather than panicking
+        let (lo, hi) = (500, 100);
+        assert_eq!(tree.count_range(lo..hi), 0);
+        assert!(tree.collect_range_owned(lo..hi).is_empty());
+    }
+}
+
+#[test]
+fn test_contains_range_matches_btreemap() {
+    use std::ops::Bound::{self, Excluded, Included, Unbounded};
+
+    let data: Vec<i32> = (0..200).map(|i| i * 5).collect();
+    for &cap in &[4_usize, 5, 16] {
+        let (tree, map) = populate_maps(cap, &data);
+        for lo in -3..1003 {
+            for (start, end) in [
+                (Included(lo), Excluded(lo + 3)),
+                (Excluded(lo), Included(lo + 4)),
+                (Included(lo), Unbounded),
+                (Unbounded, Excluded(lo)),
+            ] {
+                let range: (Bound<i32>, Bound<i32>) = (start, end);
+                assert_eq!(
+                    tree.contains_range(range),
+                    map.range(range).next().is_some(),
+                    "cap={} range={:?}",
+                    cap,
+                    range
+                );
+            }
+        }
+    }
+
+    let empty: bplustree::BPlusTreeMap<i32, i32> = bplustree::BPlusTreeMap::new(4).unwrap();
+    assert!(!empty.contains_range(..));
+}
+
+#[test]
+fn test_neighbor_queries_match_btreemap() {
+    let data: Vec<i32> = (0..150).map(|i| i * 4).collect();
+    for &cap in &[4_usize, 5, 16] {
+        let (mut tree, map) = populate_maps(cap, &data);
+        for probe in -3..603 {
+            assert_eq!(
+                tree.next_key_after(&probe),
+                map.range(probe + 1..).next().map(|(k, _)| k),
+                "cap={} next after {}",
+                cap,
+                probe
+            );
+            assert_eq!(
+                tree.prev_key_before(&probe),
+                map.range(..probe).next_back().map(|(k, _)| k),
+                "cap={} prev before {}",
+                cap,
+                probe
+            );
+        }
+
+        // Leaves emptied by deferred rebalancing are skipped in both directions
+        tree.set_deletion_mode(bplustree::DeletionMode::DeferredRebalance);
+        for k in 100..500 {
+            tree.remove(&k);
+        }
+        assert_eq!(tree.next_key_after(&96), Some(&500));
+        assert_eq!(tree.prev_key_before(&500), Some(&96));
+    }
+}
+
+#[test]
+fn test_excluded_start_between_keys_keeps_next_key() {
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+
+    // An excluded start that is absent must not drop the key that follows it
+    for &cap in &[4_usize, 5, 8] {
+        let data: Vec<i32> = (0..60).map(|i| i * 3 + 10).collect();
+        let (tree, map) = populate_maps(cap, &data);
+        for start in 0..200 {
+            for bounds in [
+                (Excluded(start), Unbounded),
+                (Excluded(start), Included(start + 7)),
+            ] {
+                let got: Vec<_> = tree.range(bounds).collect();
+                let expected: Vec<_> = map.range(bounds).collect();
+                assert_eq!(got, expected, "cap={} bounds={:?}", cap, bounds);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_head_and_tail_match_btreemap() {
+    for &cap in &[4_usize, 5, 8] {
+        let data: Vec<i32> = (0..120).map(|i| i * 2).collect();
+        let (mut tree, map) = populate_maps(cap, &data);
+        for k in -2..245 {
+            assert!(tree.head(..k).eq(map.range(..k)), "cap={} head {}", cap, k);
+            assert!(tree.tail(k..).eq(map.range(k..)), "cap={} tail {}", cap, k);
+            assert_eq!(tree.head_count(..k), map.range(..k).count());
+            assert_eq!(tree.tail_count(k..), map.range(k..).count());
+        }
+
+        // Leaves emptied by deferred rebalancing do not end either scan early
+        tree.set_deletion_mode(bplustree::DeletionMode::DeferredRebalance);
+        for k in 40..160 {
+            tree.remove(&k);
+        }
+        let expected: Vec<i32> = data
+            .iter()
+            .copied()
+            .filter(|k| !(40..160).contains(k))
+            .collect();
+        assert!(tree
+            .head(..1_000)
+            .map(|(k, _)| *k)
+            .eq(expected.iter().copied()));
+        assert!(tree.tail(0..).map(|(k, _)| *k).eq(expected.iter().copied()));
+        assert!(tree
+            .tail(41..)
+            .map(|(k, _)| *k)
+            .eq(expected.iter().copied().filter(|k| *k >= 41)));
+    }
+}
+
+/// Pull from both ends of `got` and `exp` in the same pseudo-random order
+/// until both are exhausted.
+fn assert_same_double_ended<T, A, B>(mut got: A, mut exp: B, state: &mut u64, label: &str)
+where
+    T: PartialEq + std::fmt::Debug,
+    A: DoubleEndedIterator<Item = T>,
+    B: DoubleEndedIterator<Item = T>,
+{
+    loop {
+        *state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let (g, e) = if (*state >> 40) & 1 == 0 {
+            (got.next(), exp.next())
+        } else {
+            (got.next_back(), exp.next_back())
+        };
+        assert_eq!(g, e, "{}", label);
+        if e.is_none() {
+            assert_eq!(got.next(), None, "{}", label);
+            assert_eq!(got.next_back(), None, "{}", label);
+            return;
+        }
+    }
+}
+
+#[test]
+fn test_double_ended_iteration_matches_btreemap() {
+    use bplustree::DeletionMode;
+    use std::ops::Bound;
+
+    let mut state = 41u64;
+    for &cap in &[4_usize, 5, 8] {
+        for deferred in [false, true] {
+            // Every third key, with a stretch removed to leave empty leaves
+            // when rebalancing is deferred
+            let data: Vec<i32> = (0..300).map(|i| i * 3).collect();
+            let (mut tree, mut map) = populate_maps(cap, &data);
+            if deferred {
+                tree.set_deletion_mode(DeletionMode::DeferredRebalance);
+            }
+            for k in (150..450).step_by(3) {
+                tree.remove(&k);
+                map.remove(&k);
+            }
+
+            let label = format!("cap={} deferred={}", cap, deferred);
+            assert_same_double_ended(tree.items(), map.iter(), &mut state, &label);
+            assert_same_double_ended(tree.keys(), map.keys(), &mut state, &label);
+            assert_same_double_ended(tree.values(), map.values(), &mut state, &label);
+            assert!(tree.items().rev().take(10).eq(map.iter().rev().take(10)));
+            assert_eq!(tree.last(), map.last_key_value());
+
+            for _ in 0..200 {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let lo = ((state >> 33) % 950) as i32 - 25;
+                let hi = lo + ((state >> 20) % 120) as i32;
+                let bound = |k: i32, which: u64| match which % 3 {
+                    0 => Bound::Included(k),
+                    1 => Bound::Excluded(k),
+                    _ => Bound::Unbounded,
+                };
+                let mut range = (bound(lo, state >> 50), bound(hi, state >> 55));
+                if lo == hi {
+                    // BTreeMap rejects a range excluding the same key twice
+                    range.1 = Bound::Included(hi);
+                }
+                let label = format!("{} range={:?}", label, range);
+                assert_same_double_ended(tree.range(range), map.range(range), &mut state, &label);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_reversed_range_is_empty_from_both_ends() {
+    let (tree, _) = populate_maps(4, &(0..50).collect::<Vec<_>>());
+    let reversed = (std::ops::Bound::Included(30), std::ops::Bound::Excluded(10));
+    assert_eq!(tree.range(reversed).next_back(), None);
+    assert_eq!(tree.range(reversed).rev().count(), 0);
+    assert_eq!(tree.range(20..20).next_back(), None);
+}
+
+#[test]
+fn test_range_by_rank_matches_skip_and_take() {
+    use bplustree::DeletionMode;
+    use std::ops::Bound;
+
+    let mut state = 7u64;
+    for &cap in &[4_usize, 5, 8] {
+        for deferred in [false, true] {
+            let data: Vec<i32> = (0..200).map(|i| i * 2).collect();
+            let (mut tree, mut map) = populate_maps(cap, &data);
+            if deferred {
+                tree.set_deletion_mode(DeletionMode::DeferredRebalance);
+            }
+            for k in (100..260).step_by(2) {
+                tree.remove(&k);
+                map.remove(&k);
+            }
+
+            for _ in 0..200 {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let lo = ((state >> 33) % 140) as usize;
+                let hi = lo + ((state >> 20) % 40) as usize;
+                let start = match (state >> 50) % 3 {
+                    0 => Bound::Included(lo),
+                    1 => Bound::Excluded(lo),
+                    _ => Bound::Unbounded,
+                };
+                let end = match (state >> 55) % 3 {
+                    0 => Bound::Included(hi),
+                    1 => Bound::Excluded(hi),
+                    _ => Bound::Unbounded,
+                };
+                let skip = match start {
+                    Bound::Included(r) => r,
+                    Bound::Excluded(r) => r + 1,
+                    Bound::Unbounded => 0,
+                };
+                let take = match end {
+                    Bound::Included(r) => (r + 1).saturating_sub(skip),
+                    Bound::Excluded(r) => r.saturating_sub(skip),
+                    Bound::Unbounded => usize::MAX,
+                };
+
+                let label = format!("cap={} deferred={} ranks={:?}", cap, deferred, (start, end));
+                assert_same_double_ended(
+                    tree.range_by_rank((start, end)),
+                    map.iter().skip(skip).take(take),
+                    &mut state,
+                    &label,
+                );
+            }
+        }
+    }
+
+    let (tree, _) = populate_maps(4, &(0..10).collect::<Vec<_>>());
+    assert_eq!(tree.range_by_rank(..=usize::MAX).count(), 10);
+    assert_eq!(
+        tree.range_by_rank((Bound::Excluded(usize::MAX), Bound::Unbounded))
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn test_iter_mut_and_values_mut_match_btreemap() {
+    use bplustree::DeletionMode;
+
+    // Capacity 4 with 3 keys keeps the root leaf inline
+    for &(cap, n) in &[(4_usize, 3_i32), (4, 300), (5, 300), (8, 300)] {
+        for deferred in [false, true] {
+            let data: Vec<i32> = (0..n).collect();
+            let (mut tree, mut map) = populate_maps(cap, &data);
+            if deferred {
+                tree.set_deletion_mode(DeletionMode::DeferredRebalance);
+            }
+            for k in (50..200).filter(|k| k % 4 != 0) {
+                tree.remove(&k);
+                map.remove(&k);
+            }
+
+            for ((tk, tv), (mk, mv)) in tree.iter_mut().zip(map.iter_mut()) {
+                assert_eq!(tk, mk);
+                *tv += tk;
+                *mv += mk;
+            }
+            tree.values_mut().for_each(|v| *v *= 3);
+            map.values_mut().for_each(|v| *v *= 3);
+
+            let label = format!("cap={} n={} deferred={}", cap, n, deferred);
+            assert_eq!(tree.iter_mut().count(), map.len(), "{}", label);
+            assert!(tree.items().eq(map.iter()), "{}", label);
+        }
+    }
+}
+
+#[test]
+fn test_into_iter_matches_btreemap() {
+    use bplustree::DeletionMode;
+
+    // Capacity 4 with 3 keys keeps the root leaf inline
+    for &(cap, n) in &[(4_usize, 3_i32), (4, 300), (5, 300), (8, 300)] {
+        for deferred in [false, true] {
+            let build = || {
+                let data: Vec<i32> = (0..n).collect();
+                let (mut tree, mut map) = populate_maps(cap, &data);
+                if deferred {
+                    tree.set_deletion_mode(DeletionMode::DeferredRebalance);
+                }
+                for k in (50..200).filter(|k| k % 4 != 0) {
+                    tree.remove(&k);
+                    map.remove(&k);
+                }
+                (tree, map)
+            };
+            let label = format!("cap={} n={} deferred={}", cap, n, deferred);
+
+            let (tree, map) = build();
+            let mut owned = tree.into_iter();
+            assert_eq!(owned.len(), map.len(), "{}", label);
+            assert!(owned.by_ref().take(5).eq(map.clone().into_iter().take(5)));
+            assert_eq!(owned.len(), map.len().saturating_sub(5), "{}", label);
+            // Dropping a partly consumed iterator drops the rest
+            drop(owned);
+
+            let (tree, map) = build();
+            let got: Vec<_> = tree.into_iter().collect();
+            let exp: Vec<_> = map.into_iter().collect();
+            assert_eq!(got, exp, "{}", label);
+        }
+    }
+
+    // Values are moved out, not cloned
+    let mut tree = BPlusTreeMap::new(4).unwrap();
+    for i in 0..50 {
+        tree.insert(i, Box::new(i));
+    }
+    let boxes: Vec<Box<i32>> = tree.into_iter().map(|(_, v)| v).collect();
+    assert_eq!(boxes.len(), 50);
+}
@@ -1,4 +1,6 @@
-use bplustree::BPlusTreeMap;
+#![cfg(feature = "validation")]
+
+use bplustree::{BPlusTreeError, BPlusTreeMap, QueryError, RebalanceStrategy};
 
 mod test_utils;
 use test_utils::*;
@@ -503,3 +505,252 @@ fn test_remove_nonexistent_key() {
     tree.validate()
         .expect("Tree should maintain invariants after failed remove");
 }
+
+/// Leaves [10, 11, 20] [30, 40] [50, 51, 60, 61] with capacity 4.
+/// Removing 30 underflows the middle leaf while both neighbours can donate.
+fn create_uneven_siblings(strategy: RebalanceStrategy) -> BPlusTreeMap<i32, i32> {
+    let mut tree = create_tree_capacity_int(4);
+    tree.set_rebalance_strategy(strategy);
+    for key in [10, 20, 30, 40, 50, 60, 11, 51, 61] {
+        tree.insert(key, key * 10);
+    }
+    assert_eq!(tree.leaf_sizes(), vec![3, 2, 4]);
+    tree
+}
+
+#[test]
+fn test_default_rebalance_strategy_prefers_left() {
+    let mut tree = create_uneven_siblings(RebalanceStrategy::default());
+    assert_eq!(tree.rebalance_strategy(), RebalanceStrategy::PreferLeft);
+
+    tree.remove(&30);
+    assert_eq!(tree.leaf_sizes(), vec![2, 2, 4]);
+    assert_invariants_int(&tree, "borrow from left");
+}
+
+#[test]
+fn test_prefer_fuller_borrows_from_fuller_sibling() {
+    let mut tree = create_uneven_siblings(RebalanceStrategy::PreferFuller);
+
+    tree.remove(&30);
+    assert_eq!(tree.leaf_sizes(), vec![3, 2, 3]);
+    assert_eq!(tree.get(&40), Some(&400));
+    assert_invariants_int(&tree, "borrow from right");
+}
+
+#[test]
+fn test_prefer_fuller_avoids_cascading_merge() {
+    let mut left_first = create_uneven_siblings(RebalanceStrategy::PreferLeft);
+    let mut fuller = create_uneven_siblings(RebalanceStrategy::PreferFuller);
+
+    // Drain the middle leaf and then its left neighbour. Borrowing from the
+    // left leaves it at minimum, so the second removal has to merge.
+    for tree in [&mut left_first, &mut fuller] {
+        tree.remove(&30);
+        tree.remove(&10);
+        assert_invariants_int(tree, "after alternating removals");
+    }
+
+    // Every merge frees one leaf slot; nothing is reallocated during removal.
+    assert_eq!(left_first.free_leaf_count(), 1);
+    assert_eq!(fuller.free_leaf_count(), 0);
+    assert_eq!(fuller.leaf_sizes(), vec![2, 2, 3]);
+}
+
+#[test]
+fn test_prefer_fuller_random_workload_matches_reference() {
+    use std::collections::BTreeMap;
+
+    let mut tree = create_tree_capacity_int(5);
+    tree.set_rebalance_strategy(RebalanceStrategy::PreferFuller);
+    let mut reference = BTreeMap::new();
+
+    // Simple LCG keeps the workload deterministic
+    let mut state: u64 = 0x5eed;
+    let mut next = || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 33) as i32 % 2000
+    };
+
+    for _ in 0..3000 {
+        let key = next();
+        tree.insert(key, key);
+        reference.insert(key, key);
+    }
+    for _ in 0..3000 {
+        let key = next();
+        assert_eq!(tree.remove(&key), reference.remove(&key));
+    }
+
+    assert_full_validation_int(&tree, "prefer fuller random workload");
+    assert!(tree
+        .items()
+        .map(|(k, v)| (*k, *v))
+        .eq(reference.into_iter()));
+}
+
+#[test]
+fn test_remove_min_k_matches_repeated_first_removal() {
+    for capacity in [4, 5, 8, 16] {
+        for n in [0, 1, 3, 17, 100, 499, 500, 600] {
+            let mut tree = create_tree_capacity_int(capacity);
+            for i in 0..500 {
+                tree.insert(i, i * 2);
+            }
+
+            let removed = tree.remove_min_k(n);
+            let expected: Vec<_> = (0..n.min(500) as i32).map(|i| (i, i * 2)).collect();
+            assert_eq!(removed, expected, "capacity {} n {}", capacity, n);
+            assert_eq!(tree.len(), 500 - n.min(500));
+            assert_full_validation_int(&tree, "remove_min_k");
+            assert!(tree.keys().copied().eq(n.min(500) as i32..500));
+        }
+    }
+}
+
+#[test]
+fn test_remove_max_k_returns_largest_first() {
+    for capacity in [4, 5, 8, 16] {
+        for n in [0, 1, 3, 17, 100, 499, 500, 600] {
+            let mut tree = create_tree_capacity_int(capacity);
+            for i in 0..500 {
+                tree.insert(i, i * 2);
+            }
+
+            let removed = tree.remove_max_k(n);
+            let kept = 500 - n.min(500) as i32;
+            let expected: Vec<_> = (kept..500).rev().map(|i| (i, i * 2)).collect();
+            assert_eq!(removed, expected, "capacity {} n {}", capacity, n);
+            assert_full_validation_int(&tree, "remove_max_k");
+            assert!(tree.keys().copied().eq(0..kept));
+        }
+    }
+}
+
+#[test]
+fn test_bulk_pops_interleaved_with_inserts() {
+    use std::collections::BTreeMap;
+
+    let mut tree = create_tree_capacity_int(5);
+    let mut reference = BTreeMap::new();
+    let mut state: u64 = 0xb01c;
+    let mut next = || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 33) as i32 % 5000
+    };
+
+    for round in 0..200 {
+        for _ in 0..40 {
+            let key = next();
+            tree.insert(key, round);
+            reference.insert(key, round);
+        }
+        let n = (next() % 30) as usize;
+        let expected: Vec<_> = if round % 2 == 0 {
+            std::iter::from_fn(|| reference.pop_first())
+                .take(n)
+                .collect()
+        } else {
+            std::iter::from_fn(|| reference.pop_last())
+                .take(n)
+                .collect()
+        };
+        let removed = if round % 2 == 0 {
+            tree.remove_min_k(n)
+        } else {
+            tree.remove_max_k(n)
+        };
+        assert_eq!(removed, expected, "round {}", round);
+        assert_invariants_int(&tree, "interleaved bulk pops");
+    }
+    assert!(tree
+        .items()
+        .map(|(k, v)| (*k, *v))
+        .eq(reference.into_iter()));
+}
+
+#[test]
+fn test_replace_key_within_leaf_keeps_structure() {
+    let mut tree = create_tree_capacity_int(4);
+    for i in 0..40 {
+        tree.insert(i * 10, i);
+    }
+    let sizes = tree.leaf_sizes();
+
+    // Every key except the last in its leaf can move halfway towards its
+    // successor without leaving its slot
+    let mut position = 0;
+    for &size in &sizes {
+        for old in (position..position + size - 1).map(|i| i as i32 * 10) {
+            assert_eq!(tree.replace_key(&old, old + 5), Ok(None));
+        }
+        position += size;
+    }
+    assert_eq!(tree.leaf_sizes(), sizes);
+    assert_eq!(tree.get(&5), Some(&0));
+    assert_eq!(tree.get(&0), None);
+    assert_full_validation_int(&tree, "in-leaf replace_key");
+}
+
+#[test]
+fn test_replace_key_across_leaves_and_overwrite() {
+    let mut tree = create_tree_capacity_int(4);
+    for i in 0..40 {
+        tree.insert(i, i * 100);
+    }
+
+    assert_eq!(tree.replace_key(&0, 1000), Ok(None));
+    assert_eq!(tree.replace_key(&39, -5), Ok(None));
+    assert_eq!(tree.replace_key(&10, 20), Ok(Some(2000)));
+    assert_eq!(tree.replace_key(&7, 7), Ok(None));
+    assert_eq!(
+        tree.replace_key(&10, 11),
+        Err(BPlusTreeError::Query(QueryError::KeyNotFound))
+    );
+
+    assert_eq!(tree.get(&1000), Some(&0));
+    assert_eq!(tree.get(&-5), Some(&3900));
+    assert_eq!(tree.get(&20), Some(&1000));
+    assert_eq!(tree.get(&7), Some(&700));
+    assert_eq!(tree.len(), 39);
+    assert_full_validation_int(&tree, "cross-leaf replace_key");
+}
+
+#[test]
+fn test_replace_key_random_matches_reference() {
+    use std::collections::BTreeMap;
+
+    let mut tree = create_tree_capacity_int(5);
+    let mut reference = BTreeMap::new();
+    let mut state: u64 = 0x4e9;
+    let mut next = || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 33) as i32 % 1000
+    };
+
+    for i in 0..600 {
+        let key = next();
+        tree.insert(key, i);
+        reference.insert(key, i);
+    }
+    for _ in 0..2000 {
+        let (old, new) = (next(), next());
+        let expected = match reference.remove(&old) {
+            Some(value) => Ok(reference.insert(new, value)),
+            None => Err(BPlusTreeError::Query(QueryError::KeyNotFound)),
+        };
+        assert_eq!(tree.replace_key(&old, new), expected);
+    }
+
+    assert_full_validation_int(&tree, "random replace_key");
+    assert!(tree
+        .items()
+        .map(|(k, v)| (*k, *v))
+        .eq(reference.into_iter()));
+}
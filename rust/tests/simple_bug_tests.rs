@@ -1,3 +1,5 @@
+#![cfg(feature = "validation")]
+
 /// Simplified tests to demonstrate specific bugs in the B+ tree implementation
 mod test_utils;
 use test_utils::*;
@@ -1,3 +1,5 @@
+#![cfg(feature = "validation")]
+
 /// Tests that specifically demonstrate the identified bugs with clear evidence
 use bplustree::BPlusTreeMap;
 
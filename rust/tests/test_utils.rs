@@ -1,4 +1,5 @@
 #![allow(dead_code)] // Allow unused utility functions for future tests
+#![cfg(feature = "validation")]
 
 /// Comprehensive test utilities to eliminate massive test duplication
 /// This module provides reusable patterns for adversarial testing and common operations
@@ -0,0 +1,129 @@
+#![cfg(feature = "validation")]
+
+use bplustree::{BPlusTreeMap, BatchOp, WriteBatch};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::BTreeMap;
+
+fn apply_to_reference(
+    map: &mut BTreeMap<i32, i32>,
+    batch: &WriteBatch<i32, i32>,
+) -> Vec<Option<i32>> {
+    batch
+        .ops()
+        .iter()
+        .map(|op| match op {
+            BatchOp::Insert(k, v) => map.insert(*k, *v),
+            BatchOp::Remove(k) => map.remove(k),
+        })
+        .collect()
+}
+
+fn assert_matches(tree: &BPlusTreeMap<i32, i32>, map: &BTreeMap<i32, i32>, context: &str) {
+    if let Err(e) = tree.check_invariants_detailed() {
+        panic!("{}: invariants violated: {}", context, e);
+    }
+    let got: Vec<_> = tree.items().map(|(k, v)| (*k, *v)).collect();
+    let expected: Vec<_> = map.iter().map(|(k, v)| (*k, *v)).collect();
+    assert_eq!(got, expected, "{}", context);
+}
+
+#[test]
+fn test_large_batch_into_small_tree() {
+    for capacity in [4, 5, 7, 16] {
+        let mut tree = BPlusTreeMap::new(capacity).unwrap();
+        let mut map = BTreeMap::new();
+        tree.insert(500, 0);
+        map.insert(500, 0);
+
+        // Every key lands in the single root leaf, which must split many ways
+        let mut batch = WriteBatch::new();
+        for i in 0..1000 {
+            batch.insert(i, i * 2);
+        }
+        let expected = apply_to_reference(&mut map, &batch);
+        assert_eq!(tree.apply_batch(batch), expected);
+        assert_matches(&tree, &map, &format!("capacity {}", capacity));
+    }
+}
+
+#[test]
+fn test_batch_removing_whole_subtrees() {
+    for capacity in [4, 5, 8] {
+        let mut tree = BPlusTreeMap::new(capacity).unwrap();
+        let mut map = BTreeMap::new();
+        for i in 0..2000 {
+            tree.insert(i, i);
+            map.insert(i, i);
+        }
+
+        // Empties every leaf under several adjacent branches at once
+        let mut batch = WriteBatch::new();
+        for i in 200..1700 {
+            batch.remove(i);
+        }
+        let expected = apply_to_reference(&mut map, &batch);
+        assert_eq!(tree.apply_batch(batch), expected);
+        assert_matches(&tree, &map, &format!("capacity {}", capacity));
+    }
+}
+
+#[test]
+fn test_random_mixed_batches_match_reference() {
+    let mut rng = StdRng::seed_from_u64(0xba7c4);
+    for capacity in [4, 5, 6, 9, 32] {
+        let mut tree = BPlusTreeMap::new(capacity).unwrap();
+        let mut map = BTreeMap::new();
+
+        for round in 0..40 {
+            let mut batch = WriteBatch::new();
+            let size = 1 + rng.gen_range(0..300);
+            // Alternate between insert-heavy and remove-heavy rounds
+            let insert_percent = if round % 3 == 2 { 20 } else { 70 };
+            for _ in 0..size {
+                let key = rng.gen_range(0..3000);
+                if rng.gen_range(0..100) < insert_percent {
+                    batch.insert(key, rng.gen_range(0..1000));
+                } else {
+                    batch.remove(key);
+                }
+            }
+
+            let expected = apply_to_reference(&mut map, &batch);
+            assert_eq!(tree.apply_batch(batch), expected);
+            assert_matches(
+                &tree,
+                &map,
+                &format!("capacity {} round {}", capacity, round),
+            );
+        }
+    }
+}
+
+#[test]
+fn test_batch_results_follow_operation_order() {
+    let mut tree = BPlusTreeMap::new(4).unwrap();
+    let mut batch = WriteBatch::new();
+    batch
+        .insert(1, 10)
+        .insert(1, 11)
+        .remove(1)
+        .remove(1)
+        .insert(1, 12);
+
+    assert_eq!(
+        tree.apply_batch(batch),
+        vec![None, Some(10), Some(11), None, None]
+    );
+    assert_eq!(tree.get(&1), Some(&12));
+}
+
+#[test]
+fn test_empty_batch_is_noop() {
+    let mut tree = BPlusTreeMap::new(4).unwrap();
+    for i in 0..50 {
+        tree.insert(i, i);
+    }
+    assert!(tree.apply_batch(WriteBatch::new()).is_empty());
+    assert_eq!(tree.len(), 50);
+    assert!(tree.check_invariants_detailed().is_ok());
+}